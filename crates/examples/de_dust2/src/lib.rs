@@ -9,6 +9,10 @@ pub fn run() -> anyhow::Result<()>
         let mut engine = oxide::engine::EngineBuilder::new()
                 .with_debug_ui()
                 .with_toggle(KeyCode::Tab)?
+                .with_ui_focus_key(KeyCode::F1)
+                .with_config_file("oxide.toml")?
+                .with_clip_planes(0.1, 10_000.0)
+                .with_reversed_z()
                 .build()?;
 
         engine.add_model("dust_2", "de_dust_2_with_real_light.glb");
@@ -0,0 +1,125 @@
+//! Relay for the `net` feature of the Pong example.
+//!
+//! Assigns every client that joins a paddle and forwards each message to the
+//! other clients. Listens on `127.0.0.1:9001` unless an address is passed.
+
+use pong::net::PongMessage;
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tungstenite::{Message, WebSocket};
+
+type Clients = Arc<Mutex<Vec<(usize, Sender<Vec<u8>>)>>>;
+
+fn main() -> anyhow::Result<()>
+{
+        env_logger::init();
+
+        let addr = std::env::args()
+                .nth(1)
+                .unwrap_or_else(|| "127.0.0.1:9001".to_string());
+
+        let listener = TcpListener::bind(&addr)?;
+
+        log::info!("Pong relay listening on ws://{}", addr);
+
+        let clients: Clients = Arc::new(Mutex::new(Vec::new()));
+
+        for (id, stream) in listener.incoming().enumerate()
+        {
+                let stream = match stream
+                {
+                        Ok(s) => s,
+                        Err(e) =>
+                        {
+                                log::warn!("{e}");
+                                continue;
+                        }
+                };
+
+                let clients = clients.clone();
+
+                std::thread::spawn(move || {
+                        let (tx, rx) = mpsc::channel();
+
+                        let player = {
+                                let mut clients = clients.lock().unwrap();
+                                clients.push((id, tx));
+                                (clients.len() - 1) as u8 % 2
+                        };
+
+                        log::info!("Client {} joined as paddle {}", id, player + 1);
+
+                        if let Err(e) = serve(id, player, stream, &rx, &clients)
+                        {
+                                log::warn!("Client {}: {e:#}", id);
+                        }
+
+                        clients.lock().unwrap().retain(|(c, _)| *c != id);
+
+                        log::info!("Client {} left", id);
+                });
+        }
+
+        Ok(())
+}
+
+fn serve(
+        id: usize,
+        player: u8,
+        stream: TcpStream,
+        forwarded: &Receiver<Vec<u8>>,
+        clients: &Clients,
+) -> anyhow::Result<()>
+{
+        let mut socket: WebSocket<TcpStream> = tungstenite::accept(stream)?;
+
+        socket.get_mut()
+                .set_read_timeout(Some(Duration::from_millis(5)))?;
+
+        socket.send(Message::Binary(
+                oxide::net::encode(&PongMessage::Welcome {
+                        player,
+                })?
+                .into(),
+        ))?;
+
+        loop
+        {
+                for data in forwarded.try_iter()
+                {
+                        socket.write(Message::Binary(data.into()))?;
+                }
+
+                socket.flush()?;
+
+                match socket.read()
+                {
+                        Ok(Message::Binary(data)) =>
+                        {
+                                for (other, tx) in clients.lock().unwrap().iter()
+                                {
+                                        if *other != id
+                                        {
+                                                let _ = tx.send(data.to_vec());
+                                        }
+                                }
+                        }
+                        Ok(Message::Close(_)) | Err(tungstenite::Error::ConnectionClosed) =>
+                        {
+                                return Ok(());
+                        }
+                        Ok(_) =>
+                        {}
+                        Err(tungstenite::Error::Io(e))
+                                if matches!(
+                                        e.kind(),
+                                        std::io::ErrorKind::WouldBlock
+                                                | std::io::ErrorKind::TimedOut
+                                ) =>
+                        {}
+                        Err(e) => return Err(e.into()),
+                }
+        }
+}
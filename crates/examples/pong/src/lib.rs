@@ -1,22 +1,50 @@
 use cgmath::{Deg, Euler, Point3, Quaternion, Rad, Rotation3, Vector3};
-use oxide::camera::{Camera, Projection};
-use oxide_macro::oxide_main;
+use oxide::audio::{Bus, Emitter};
+use oxide::camera::{Camera, CameraBindings, Projection};
+use oxide::egui;
+use oxide::engine::Engine;
+use oxide::events::EventReader;
+use oxide::game_state::GameState;
+use oxide::math::collision::Aabb;
+use oxide::math::interpolation::Interpolated;
+use oxide::replay::{Replay, ReplayEvent};
+use oxide_macro::{Inspect, oxide_main};
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
+use std::rc::Rc;
 use winit::event::ElementState;
 use winit::keyboard::KeyCode;
 
+#[cfg(feature = "net")]
+pub mod net;
+
+#[derive(Inspect)]
 pub struct Player
 {
         pub model_name: &'static str,
         pub position: Point3<f32>,
 }
 
+impl Player
+{
+        /// The area the ball bounces off, as a point.
+        pub fn bounds(&self) -> Aabb
+        {
+                let half = Vector3::new(0.5, 0.5, 1.0);
+
+                Aabb::new(self.position - half, self.position + half)
+        }
+}
+
+#[derive(Inspect)]
 pub struct Ball
 {
         pub position: Point3<f32>,
         pub velocity: Vector3<f32>,
 }
 
+/// Shown in the debug UI's "Inspector" window.
+#[derive(Inspect)]
 pub struct PongGame
 {
         pub paddle_1: Player,
@@ -26,6 +54,16 @@ pub struct PongGame
         pub height: f32,
         pub last_tick: u8,
         pub is_init: bool,
+        /// Set by [`PongGame::update`] when the ball hit a wall or paddle.
+        pub bounced: bool,
+        /// Set by [`PongGame::update`] when the ball left the field.
+        pub scored: bool,
+        /// Set by a timer to put the ball back in play after a point.
+        #[inspect(skip)]
+        pub serve: Rc<Cell<bool>>,
+        /// Where the ball is drawn, between its last two ticks.
+        #[inspect(skip)]
+        pub ball_motion: Interpolated<Point3<f32>>,
 }
 
 impl PongGame
@@ -49,6 +87,10 @@ impl PongGame
                         height: 6.0,
                         last_tick: 0,
                         is_init: false,
+                        bounced: false,
+                        scored: false,
+                        serve: Rc::new(Cell::new(false)),
+                        ball_motion: Interpolated::new(Point3::new(0.0, 0.0, 0.0)),
                 }
         }
 
@@ -87,14 +129,14 @@ impl PongGame
                 let quat_1: Quaternion<f32> = Quaternion::from(euler_1);
                 let quat_2: Quaternion<f32> = Quaternion::from(euler_2);
 
-                models.get_mut("bg").unwrap().position.y = -90.0;
+                models.get_mut("bg").unwrap().transform.position.y = -90.0;
 
                 models.get_mut("bg").unwrap().set_rotation_speed(0, 2.0);
                 models.get_mut("bg").unwrap().set_rotation_speed(1, 2.0);
                 models.get_mut("bg").unwrap().set_rotation_speed(2, 2.0);
                 models.get_mut("bg").unwrap().is_spinning = true;
 
-                models.get_mut("paddle_1").unwrap().rotation = quat_2;
+                models.get_mut("paddle_1").unwrap().transform.rotation = quat_2;
 
                 models.get_mut("paddle_1")
                         .unwrap()
@@ -112,9 +154,10 @@ impl PongGame
 
                 models.get_mut("ball").unwrap().is_spinning = true;
 
-                models.get_mut("paddle_2").unwrap().rotation = quat_1;
+                models.get_mut("paddle_2").unwrap().transform.rotation = quat_1;
 
-                models.get_mut("ball").unwrap().scale = cgmath::Vector3::new(0.2, 0.2, 0.2);
+                models.get_mut("ball").unwrap().transform.scale =
+                        cgmath::Vector3::new(0.2, 0.2, 0.2);
 
                 self.is_init = true;
         }
@@ -124,16 +167,24 @@ impl PongGame
                 delta: f32,
         )
         {
+                if self.serve.take()
+                {
+                        self.ball.velocity = Vector3::new(4.0, 0.0, 2.0);
+                }
+
                 self.ball.position += self.ball.velocity * delta;
 
+                self.bounced = false;
+                self.scored = false;
+
                 if self.ball.position.z >= self.height || self.ball.position.z <= -self.height
                 {
                         self.ball.velocity.z = -self.ball.velocity.z;
+                        self.bounced = true;
                 }
 
                 // Bounce off paddle 1
-                if (self.ball.position.x <= self.paddle_1.position.x + 0.5
-                        && (self.ball.position.z - self.paddle_1.position.z).abs() <= 1.0)
+                if self.paddle_1.bounds().contains_point(self.ball.position)
                 {
                         self.ball.velocity.x = self.ball.velocity.x.abs();
 
@@ -146,11 +197,12 @@ impl PongGame
 
                         // Speed up slightly
                         self.ball.velocity *= 1.05;
+
+                        self.bounced = true;
                 }
 
                 // Bounce off paddle 2
-                if (self.ball.position.x >= self.paddle_2.position.x - 0.5
-                        && (self.ball.position.z - self.paddle_2.position.z).abs() <= 1.0)
+                if self.paddle_2.bounds().contains_point(self.ball.position)
                 {
                         self.ball.velocity.x = -self.ball.velocity.x.abs();
 
@@ -160,12 +212,22 @@ impl PongGame
                         self.ball.velocity.z = normalized_offset * 5.0;
 
                         self.ball.velocity *= 1.05;
+
+                        self.bounced = true;
                 }
 
                 if self.ball.position.x < -self.width || self.ball.position.x > self.width
                 {
                         self.ball.position = Point3::new(0.0, 0.0, 0.0);
-                        self.ball.velocity = Vector3::new(4.0, 0.0, 2.0);
+                        self.ball.velocity = Vector3::new(0.0, 0.0, 0.0);
+                        self.scored = true;
+
+                        // Back in the middle at once, not flying there.
+                        self.ball_motion.set(self.ball.position);
+                }
+                else
+                {
+                        self.ball_motion.push(self.ball.position);
                 }
         }
 
@@ -198,6 +260,87 @@ impl PongGame
         }
 }
 
+/// Overlay pushed on top of the game with [P], the game below doesn't tick.
+fn paused() -> GameState
+{
+        let mut resume_was_down = true;
+
+        GameState::new("paused")
+                .with_behavior(move |eng| {
+                        let resume_down = eng.pressed_keys.contains(&KeyCode::KeyP);
+                        if resume_down && !resume_was_down
+                        {
+                                eng.pop_state();
+                        }
+                        resume_was_down = resume_down;
+                })
+                .with_ui(|ctx, transitions| {
+                        egui::Window::new("Paused")
+                                .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+                                .collapsible(false)
+                                .resizable(false)
+                                .show(ctx, |ui| {
+                                        ui.label("Press [P] to resume");
+
+                                        if ui.button("Resume").clicked()
+                                        {
+                                                transitions.pop();
+                                        }
+                                });
+                })
+}
+
+/// [F5] starts and stops recording, [F6] plays the last recording back.
+/// Natively the recording is also saved to `pong_replay.toml`.
+fn replay_controls() -> impl FnMut(&mut Engine)
+{
+        let mut last: Option<Replay> = None;
+        let mut record_was_down = false;
+        let mut play_was_down = false;
+
+        move |eng| {
+                let record_down = eng.pressed_keys.contains(&KeyCode::F5);
+                let play_down = eng.pressed_keys.contains(&KeyCode::F6);
+
+                // While playing, the keys come from the recording.
+                let record = record_down && !record_was_down && !eng.replay.is_playing();
+                let play = play_down && !play_was_down && !eng.replay.is_playing();
+
+                record_was_down = record_down;
+                play_was_down = play_down;
+
+                if record && eng.replay.is_recording()
+                {
+                        last = eng.stop_recording();
+
+                        #[cfg(not(target_arch = "wasm32"))]
+                        if let Some(Err(e)) = last.as_ref().map(|r| r.save("pong_replay.toml"))
+                        {
+                                log::warn!("{e:#}");
+                        }
+                }
+                else if record
+                {
+                        eng.start_recording();
+                }
+
+                if play
+                {
+                        #[cfg(not(target_arch = "wasm32"))]
+                        if last.is_none()
+                        {
+                                last = Replay::load("pong_replay.toml").ok();
+                        }
+
+                        match last.clone()
+                        {
+                                Some(replay) => eng.play_replay(replay),
+                                None => log::warn!("Nothing recorded yet, press [F5]"),
+                        }
+                }
+        }
+}
+
 #[oxide_main]
 pub fn run() -> anyhow::Result<()>
 {
@@ -207,6 +350,8 @@ pub fn run() -> anyhow::Result<()>
                 .with_debug_ui()
                 .with_tps(144u16)
                 .with_toggle(KeyCode::Tab)?
+                // The camera is fixed, the keys belong to the paddles.
+                .with_camera_bindings(CameraBindings::none())
                 .build()?;
 
         engine.add_model("bg", "forest_2_by_creepercoastal.glb");
@@ -214,9 +359,41 @@ pub fn run() -> anyhow::Result<()>
         engine.add_model("paddle_2", "blue_paddle.glb");
         engine.add_model("ball", "dodecahedron.glb");
 
-        let mut game = PongGame::new();
+        #[cfg(feature = "scripting")]
+        engine.attach_script("ball", "ball.rhai");
+
+        let game = Rc::new(RefCell::new(PongGame::new()));
+        engine.inspect("Pong", game.clone());
+
+        let mut pause_was_down = false;
+        let mut replays = EventReader::<ReplayEvent>::new();
+
+        #[cfg(feature = "net")]
+        let mut sync = net::PaddleSync::default();
+
+        let play = GameState::new("play").with_behavior(move |eng| {
+                let mut game = game.borrow_mut();
+
+                let pause_down = eng.pressed_keys.contains(&KeyCode::KeyP);
+                if pause_down && !pause_was_down
+                {
+                        eng.push_state(paused());
+                }
+                pause_was_down = pause_down;
+
+                // A replay starts from a fresh game.
+                if replays
+                        .read(&eng.events)
+                        .any(|e| *e == ReplayEvent::Started)
+                {
+                        *game = PongGame::new();
+                }
+
+                #[cfg(feature = "net")]
+                sync.update(eng, &mut game);
+
+                let ball = eng.interpolated(&game.ball_motion);
 
-        engine.register_behavior(move |eng| {
                 let state = match eng.state.as_mut()
                 {
                         None => return,
@@ -228,6 +405,10 @@ pub fn run() -> anyhow::Result<()>
                         game.init(&mut state.camera, &mut state.models);
                 }
 
+                // Drawn every frame between ticks, the ball moves smoothly
+                // at any frame rate.
+                state.models.get_mut("ball").unwrap().transform.position = ball;
+
                 if eng.current_tick == game.last_tick
                 {
                         return;
@@ -235,23 +416,54 @@ pub fn run() -> anyhow::Result<()>
 
                 game.update(1.0 / eng.tps as f32);
 
-                state.models.get_mut("paddle_1").unwrap().position = game.paddle_1.position;
-                state.models.get_mut("paddle_2").unwrap().position = game.paddle_2.position;
-                state.models.get_mut("ball").unwrap().position = game.ball.position;
+                state.models.get_mut("paddle_1").unwrap().transform.position =
+                        game.paddle_1.position;
+                state.models.get_mut("paddle_2").unwrap().transform.position =
+                        game.paddle_2.position;
+
+                // The camera looks straight down from far above, so the whole
+                // field is within the reference distance and the pan is
+                // widened to reach the sides of the screen.
+                let emitter = Emitter::new("ball")
+                        .with_distances(50.0, 100.0)
+                        .with_pan_scale(6.0);
+
+                if game.bounced
+                        && let Err(e) =
+                                eng.audio
+                                        .play_spatial(Bus::Sfx, "bounce.wav", false, emitter)
+                {
+                        log::warn!("{e:#}");
+                }
 
-                if eng.pressed_keys.contains(&KeyCode::KeyR)
+                #[cfg(feature = "scripting")]
+                if game.bounced
+                {
+                        eng.scripts.send(
+                                "bounce",
+                                cgmath::InnerSpace::magnitude(game.ball.velocity) as f64,
+                        );
+                }
+
+                // Over the network each client only moves its own paddle.
+                #[cfg(feature = "net")]
+                let controls = |player| sync.controls(player);
+                #[cfg(not(feature = "net"))]
+                let controls = |_| true;
+
+                if controls(1) && eng.pressed_keys.contains(&KeyCode::KeyR)
                 {
                         game.move_paddle(1, true);
                 }
-                if eng.pressed_keys.contains(&KeyCode::KeyF)
+                if controls(1) && eng.pressed_keys.contains(&KeyCode::KeyF)
                 {
                         game.move_paddle(1, false);
                 }
-                if eng.pressed_keys.contains(&KeyCode::ArrowUp)
+                if controls(0) && eng.pressed_keys.contains(&KeyCode::ArrowUp)
                 {
                         game.move_paddle(0, true);
                 }
-                if eng.pressed_keys.contains(&KeyCode::ArrowDown)
+                if controls(0) && eng.pressed_keys.contains(&KeyCode::ArrowDown)
                 {
                         game.move_paddle(0, false);
                 }
@@ -260,11 +472,22 @@ pub fn run() -> anyhow::Result<()>
                         game.init(&mut state.camera, &mut state.models);
                 }
 
+                if game.scored
+                {
+                        let serve = game.serve.clone();
+
+                        eng.after(1.0, move |_| serve.set(true));
+                }
+
                 game.last_tick = eng.current_tick;
 
                 log::info!("Tick: {}", eng.current_tick);
         });
 
+        engine.push_state(play);
+
+        engine.register_behavior(replay_controls());
+
         let runner = oxide::engine::EngineRunner::new(engine)?;
         runner.run()?;
 
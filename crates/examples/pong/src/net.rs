@@ -0,0 +1,151 @@
+//! Paddle sync between two clients, enabled with the `net` feature.
+//!
+//! Start the relay with `cargo run -p pong --features net --bin pong_relay`,
+//! then two games. The relay assigns each client a paddle and forwards the
+//! paddle positions, the ball is simulated by both clients.
+
+use crate::PongGame;
+use oxide::engine::Engine;
+use oxide::events::EventReader;
+use oxide::net::{ConnectionId, NetEvent};
+use serde::{Deserialize, Serialize};
+
+pub const SERVER: &str = "ws://127.0.0.1:9001";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum PongMessage
+{
+        /// Sent by the relay to a client that joined.
+        Welcome
+        {
+                player: u8
+        },
+        Paddle
+        {
+                player: u8, z: f32
+        },
+}
+
+#[derive(Debug, Default)]
+pub struct PaddleSync
+{
+        connection: Option<ConnectionId>,
+
+        /// Paddle controlled by this client, both are until the relay
+        /// answered.
+        pub player: Option<u8>,
+
+        events: EventReader<NetEvent>,
+
+        last_tick: u8,
+}
+
+impl PaddleSync
+{
+        /// Whether the keys of `player` move a paddle on this client.
+        pub fn controls(
+                &self,
+                player: u8,
+        ) -> bool
+        {
+                self.player.is_none_or(|p| p == player)
+        }
+
+        pub fn update(
+                &mut self,
+                eng: &mut Engine,
+                game: &mut PongGame,
+        )
+        {
+                let connection = match self.connection
+                {
+                        Some(c) => c,
+                        None => match eng.connect(SERVER)
+                        {
+                                Ok(c) =>
+                                {
+                                        self.connection = Some(c);
+                                        c
+                                }
+                                Err(e) =>
+                                {
+                                        log::warn!("{e:#}");
+                                        return;
+                                }
+                        },
+                };
+
+                for event in self.events.read(&eng.events)
+                {
+                        match event
+                        {
+                                NetEvent::Connected(_) => log::info!("Waiting for a paddle"),
+                                NetEvent::Disconnected {
+                                        ..
+                                } =>
+                                {
+                                        // Play locally from now on.
+                                        self.player = None;
+                                }
+                                NetEvent::Error {
+                                        ..
+                                } =>
+                                {}
+                        }
+                }
+
+                for message in eng.net.receive::<PongMessage>(connection)
+                {
+                        match message
+                        {
+                                PongMessage::Welcome {
+                                        player,
+                                } =>
+                                {
+                                        log::info!("Playing paddle {}", player + 1);
+
+                                        self.player = Some(player);
+                                }
+                                PongMessage::Paddle {
+                                        player,
+                                        z,
+                                } if !self.controls(player) => match player
+                                {
+                                        0 => game.paddle_1.position.z = z,
+                                        _ => game.paddle_2.position.z = z,
+                                },
+                                PongMessage::Paddle {
+                                        ..
+                                } =>
+                                {}
+                        }
+                }
+
+                if eng.current_tick == self.last_tick
+                {
+                        return;
+                }
+
+                self.last_tick = eng.current_tick;
+
+                if let Some(player) = self.player
+                {
+                        let z = match player
+                        {
+                                0 => game.paddle_1.position.z,
+                                _ => game.paddle_2.position.z,
+                        };
+
+                        if let Err(e) = eng.net.send(
+                                connection,
+                                &PongMessage::Paddle {
+                                        player,
+                                        z,
+                                },
+                        )
+                        {
+                                log::warn!("{e:#}");
+                        }
+                }
+        }
+}
@@ -1,28 +1,14 @@
+use oxide::math::interpolation::Interpolated;
+use oxide::transform::Transform;
 use oxide_macro::oxide_main;
 use winit::event::ElementState;
 use winit::keyboard::KeyCode;
 
 pub struct Segment
 {
-        pub prev_pos: cgmath::Vector3<f32>,
         pub pos: cgmath::Vector3<f32>,
 }
 
-impl Segment
-{
-        pub fn store_prev(&mut self)
-        {
-                self.prev_pos = self.pos;
-        }
-
-        pub fn interpolate(
-                &self,
-                alpha: f32,
-        ) -> cgmath::Vector3<f32>
-        {
-                self.prev_pos * (1.0 - alpha) + self.pos * alpha
-        }
-}
 pub struct SnakeGame
 {
         pub grid: Grid,
@@ -30,6 +16,9 @@ pub struct SnakeGame
         pub last_tick: u8,
         pub started: bool,
         pub game_over: bool,
+        /// The head between its last two grid cells, drawn moving from one
+        /// to the next between ticks.
+        pub head: Interpolated<Transform>,
 }
 
 impl SnakeGame
@@ -45,6 +34,7 @@ impl SnakeGame
                         started: false,
                         last_tick: 0,
                         game_over: false,
+                        head: Interpolated::new(Transform::IDENTITY),
                 }
         }
 
@@ -61,9 +51,10 @@ impl SnakeGame
                 self.snake.grid_pos = (x, z);
 
                 self.snake.update_segment_pos();
-                self.snake.segment.store_prev();
 
-                model.position = cgmath::Point3::new(x as f32, 0.0, z as f32);
+                model.transform.position = cgmath::Point3::new(x as f32, 0.0, z as f32);
+
+                self.head.set(model.transform);
         }
 
         pub fn is_colliding(&self) -> bool
@@ -170,7 +161,6 @@ impl Snake
                         grid_pos: (0, 0),
                         step_speed,
                         segment: Segment {
-                                prev_pos: cgmath::Vector3::new(0.0, 0.0, 0.0),
                                 pos: cgmath::Vector3::new(0.0, 0.0, 0.0),
                         },
                 }
@@ -253,6 +243,9 @@ pub fn run() -> anyhow::Result<()>
         engine.add_model("log_3", "log_photogrammetrised.glb");
         engine.add_model("log_4", "log_photogrammetrised.glb");
 
+        engine.camera_follow("snake_head", (0.0, 30.0, 25.0), 3.0);
+        engine.camera_look_at("snake_head");
+
         let snake = Snake::new("snake_head", 4f32);
 
         let mut game = SnakeGame::new(Grid::new(20, 20), snake);
@@ -272,42 +265,44 @@ pub fn run() -> anyhow::Result<()>
                         game.started = true;
                 }
 
-                let v = game.snake.segment.interpolate(eng.lerp_alpha);
-                snake_head.position = cgmath::Point3::new(v.x, v.y, v.z);
+                eng.interpolate_model("snake_head", &game.head);
 
-                if eng.current_tick != game.last_tick
+                if eng.current_tick == game.last_tick
                 {
-                        game.snake.segment.store_prev();
-                        game.update_grid_pos();
-                        game.snake.update_segment_pos();
+                        return;
+                }
 
-                        game.last_tick = eng.current_tick;
+                game.update_grid_pos();
+                game.snake.update_segment_pos();
 
-                        log::info!(
-                                "Tick {}, Prev: {:?}, Pos: {:?}",
-                                eng.current_tick,
-                                game.snake.segment.prev_pos,
-                                game.snake.segment.pos
-                        );
+                game.last_tick = eng.current_tick;
 
-                        if game.is_colliding()
-                        {
-                                log::info!("Game Over");
+                log::info!("Tick {}, Pos: {:?}", eng.current_tick, game.snake.segment.pos);
 
-                                snake_head.position = cgmath::Point3::new(0.0, 0.0, 0.0);
+                if game.is_colliding()
+                {
+                        log::info!("Game Over");
 
-                                game.game_over = true;
+                        game.game_over = true;
 
-                                game.snake
-                                        .change_direction(&(KeyCode::Enter, ElementState::Pressed));
+                        game.snake
+                                .change_direction(&(KeyCode::Enter, ElementState::Pressed));
 
-                                game.snake.segment.store_prev();
-                                game.update_grid_pos();
-                                game.snake.update_segment_pos();
+                        game.update_grid_pos();
+                        game.snake.update_segment_pos();
 
-                                return;
-                        }
+                        let v = game.snake.segment.pos;
+
+                        game.head
+                                .set(game.head.current().with_position((v.x, v.y, v.z)));
+
+                        return;
                 }
+
+                let v = game.snake.segment.pos;
+
+                game.head
+                        .push(game.head.current().with_position((v.x, v.y, v.z)));
         });
 
         let runner = oxide::engine::EngineRunner::new(engine)?;
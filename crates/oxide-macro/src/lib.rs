@@ -2,41 +2,222 @@ use proc_macro::TokenStream;
 use quote::quote;
 use syn::parse_macro_input;
 use syn::ItemFn;
+use syn::LitBool;
+use syn::LitStr;
 
+/// Options of `#[oxide_main(...)]`, all optional.
+struct Options
+{
+        /// `log = "debug"`, the most verbose level logged.
+        log: proc_macro2::TokenStream,
+        /// `canvas = "game-canvas"`, id of the canvas element on the web.
+        canvas: Option<LitStr>,
+        /// `panic_hook = false` keeps panics out of the browser console and
+        /// writes no crash reports on native.
+        panic_hook: bool,
+        /// `crash_reports = "reports"`, directory of the crash reports on
+        /// native.
+        crash_reports: Option<LitStr>,
+}
+
+impl Default for Options
+{
+        fn default() -> Self
+        {
+                Self {
+                        log: quote!(Info),
+                        canvas: None,
+                        panic_hook: true,
+                        crash_reports: None,
+                }
+        }
+}
+
+impl Options
+{
+        fn parse(
+                &mut self,
+                meta: syn::meta::ParseNestedMeta,
+        ) -> syn::Result<()>
+        {
+                if meta.path.is_ident("log")
+                {
+                        let level: LitStr = meta.value()?.parse()?;
+
+                        self.log = match level.value().to_lowercase().as_str()
+                        {
+                                "trace" => quote!(Trace),
+                                "debug" => quote!(Debug),
+                                "info" => quote!(Info),
+                                "warn" => quote!(Warn),
+                                "error" => quote!(Error),
+                                _ => return Err(syn::Error::new(
+                                        level.span(),
+                                        "expected one of \"trace\", \"debug\", \"info\", \"warn\" or \"error\"",
+                                )),
+                        };
+                }
+                else if meta.path.is_ident("canvas")
+                {
+                        self.canvas = Some(meta.value()?.parse()?);
+                }
+                else if meta.path.is_ident("panic_hook")
+                {
+                        let enabled: LitBool = meta.value()?.parse()?;
+
+                        self.panic_hook = enabled.value;
+                }
+                else if meta.path.is_ident("crash_reports")
+                {
+                        self.crash_reports = Some(meta.value()?.parse()?);
+                }
+                else
+                {
+                        return Err(meta.error(
+                                "expected `log`, `canvas`, `panic_hook` or `crash_reports`",
+                        ));
+                }
+
+                Ok(())
+        }
+}
+
+/// Wires a `fn() -> anyhow::Result<()>` up as the entry point on native and
+/// the web.
+///
+/// ```ignore
+/// #[oxide_main(log = "debug", panic_hook = false)]
+/// pub fn run() -> anyhow::Result<()>
+/// ```
+///
+/// The function may take an `EngineBuilder` instead, created with the
+/// options that configure the engine applied. It's still called without
+/// arguments:
+///
+/// ```ignore
+/// #[oxide_main(canvas = "game-canvas")]
+/// pub fn run(builder: EngineBuilder) -> anyhow::Result<()>
+/// ```
+///
+/// - `log`: most verbose level logged, `"info"` by default, see
+///   `oxide::log`. `RUST_LOG` still overrides it on native.
+/// - `canvas`: id of the canvas element the engine draws to on the web,
+///   `"canvas"` by default, see `EngineBuilder::with_canvas_id`. Needs the
+///   function to take the builder.
+/// - `panic_hook`: show panics in the browser console and write crash
+///   reports on native, see `oxide::crash`. `true` by default.
+/// - `crash_reports`: directory of the crash reports, `"crash-reports"` by
+///   default.
 #[proc_macro_attribute]
 pub fn oxide_main(
-        _attr: TokenStream,
+        attr: TokenStream,
         item: TokenStream,
 ) -> TokenStream
 {
+        let mut options = Options::default();
+        let parser = syn::meta::parser(|meta| options.parse(meta));
+        parse_macro_input!(attr with parser);
+
         let input_fn = parse_macro_input!(item as ItemFn);
         let fn_name = &input_fn.sig.ident;
         let fn_block = &input_fn.block;
         let fn_sig = &input_fn.sig;
         let fn_vis = &input_fn.vis;
 
+        let level = &options.log;
+
+        // The function either builds its engine itself or takes the
+        // `EngineBuilder` with the options applied.
+        let takes_builder = match input_fn.sig.inputs.len()
+        {
+                0 => false,
+                1 => true,
+                _ =>
+                {
+                        return TokenStream::from(
+                                syn::Error::new_spanned(
+                                        &input_fn.sig.inputs,
+                                        "expected no arguments or the `EngineBuilder`",
+                                )
+                                .to_compile_error(),
+                        );
+                }
+        };
+
+        if let Some(canvas) = &options.canvas
+                && !takes_builder
+        {
+                return TokenStream::from(syn::Error::new(
+                        canvas.span(),
+                        "`canvas` is set on the `EngineBuilder`, take it as the argument of the function",
+                )
+                .to_compile_error());
+        }
+
+        let canvas = options.canvas.as_ref().map(|canvas| {
+                quote! {
+                    .with_canvas_id(#canvas)
+                }
+        });
+
+        let mut outer_sig = fn_sig.clone();
+        outer_sig.inputs.clear();
+
+        let body = if takes_builder
+        {
+                let mut inner_sig = fn_sig.clone();
+                inner_sig.ident = syn::Ident::new("__oxide_main", fn_name.span());
+
+                quote! {
+                    #inner_sig #fn_block
+
+                    __oxide_main(oxide::engine::EngineBuilder::new() #canvas)
+                }
+        }
+        else
+        {
+                quote!(#fn_block)
+        };
+
+        let panic_hook = options.panic_hook.then(|| {
+                quote! {
+                    console_error_panic_hook::set_once();
+                }
+        });
+
+        let crash_reports = options.panic_hook.then(|| {
+                let dir = options
+                        .crash_reports
+                        .as_ref()
+                        .map(LitStr::value)
+                        .unwrap_or_else(|| "crash-reports".to_string());
+
+                quote! {
+                    #[cfg(not(target_arch = "wasm32"))]
+                    oxide::crash::install(#dir);
+                }
+        });
+
         let expanded = quote! {
             // Original function
-            #fn_vis #fn_sig {
+            #fn_vis #outer_sig {
                 // Non-WASM initialization
                 #[cfg(not(target_arch = "wasm32"))]
-                {
-                    let _ = env_logger::builder().is_test(true).try_init();
-                }
+                oxide::log::init(oxide::log::LogConfig::with_level(oxide::log::LevelFilter::#level));
+
+                #crash_reports
 
                 // Original function body
-                #fn_block
+                #body
             }
 
             // WASM entrypoint
             #[cfg(target_arch = "wasm32")]
             #[wasm_bindgen::prelude::wasm_bindgen(start)]
             pub fn run_wasm() -> Result<(), wasm_bindgen::JsValue> {
-                use wasm_bindgen::UnwrapThrowExt;
-
-                console_error_panic_hook::set_once();
+                #panic_hook
 
-                console_log::init_with_level(log::Level::Info).unwrap_throw();
+                oxide::log::init(oxide::log::LogConfig::with_level(oxide::log::LevelFilter::#level));
 
                 #fn_name().map_err(|e| {
                     wasm_bindgen::JsValue::from_str(
@@ -48,3 +229,183 @@ pub fn oxide_main(
 
         TokenStream::from(expanded)
 }
+
+/// Options of a field, `#[inspect(skip)]` or `#[inspect(color)]`.
+#[derive(Default)]
+struct InspectField
+{
+        skip: bool,
+        color: bool,
+}
+
+impl InspectField
+{
+        fn parse(attrs: &[syn::Attribute]) -> syn::Result<Self>
+        {
+                let mut field = Self::default();
+
+                for attr in attrs.iter().filter(|a| a.path().is_ident("inspect"))
+                {
+                        attr.parse_nested_meta(|meta| {
+                                if meta.path.is_ident("skip")
+                                {
+                                        field.skip = true;
+                                }
+                                else if meta.path.is_ident("color")
+                                {
+                                        field.color = true;
+                                }
+                                else
+                                {
+                                        return Err(meta.error("expected `skip` or `color`"));
+                                }
+
+                                Ok(())
+                        })?;
+                }
+
+                Ok(field)
+        }
+}
+
+/// Implements `oxide::ui::inspect::Inspect`, an editing UI for the debug
+/// window.
+///
+/// Structs show their fields in a collapsible section, field-less enums a
+/// combo box of their variants. Every field needs to implement `Inspect`,
+/// or be marked `#[inspect(skip)]`. `#[inspect(color)]` shows `[f32; 3]` and
+/// `[f32; 4]` fields as color pickers.
+///
+/// Needs the `ui` feature of `oxide`, which is enabled by default.
+#[proc_macro_derive(Inspect, attributes(inspect))]
+pub fn derive_inspect(item: TokenStream) -> TokenStream
+{
+        let input = parse_macro_input!(item as syn::DeriveInput);
+
+        match inspect_body(&input)
+        {
+                Ok(body) =>
+                {
+                        let name = &input.ident;
+                        let (impl_generics, ty_generics, where_clause) =
+                                input.generics.split_for_impl();
+
+                        TokenStream::from(quote! {
+                            impl #impl_generics ::oxide::ui::inspect::Inspect for #name #ty_generics #where_clause {
+                                fn inspect(
+                                    &mut self,
+                                    ui: &mut ::oxide::egui::Ui,
+                                    label: &str,
+                                ) -> bool {
+                                    #body
+                                }
+                            }
+                        })
+                }
+                Err(e) => TokenStream::from(e.to_compile_error()),
+        }
+}
+
+fn inspect_body(input: &syn::DeriveInput) -> syn::Result<proc_macro2::TokenStream>
+{
+        match &input.data
+        {
+                syn::Data::Struct(data) =>
+                {
+                        let mut fields = Vec::new();
+
+                        for (i, field) in data.fields.iter().enumerate()
+                        {
+                                let options = InspectField::parse(&field.attrs)?;
+
+                                if options.skip
+                                {
+                                        continue;
+                                }
+
+                                let (member, label) = match &field.ident
+                                {
+                                        Some(ident) => (quote!(#ident), ident.to_string()),
+                                        None =>
+                                        {
+                                                let index = syn::Index::from(i);
+                                                (quote!(#index), i.to_string())
+                                        }
+                                };
+
+                                fields.push(if options.color
+                                {
+                                        quote! {
+                                            changed |= ::oxide::ui::inspect::InspectColor::inspect_color(&mut self.#member, ui, #label);
+                                        }
+                                }
+                                else
+                                {
+                                        quote! {
+                                            changed |= ::oxide::ui::inspect::Inspect::inspect(&mut self.#member, ui, #label);
+                                        }
+                                });
+                        }
+
+                        Ok(quote! {
+                            let mut changed = false;
+
+                            ::oxide::egui::CollapsingHeader::new(label)
+                                .id_salt(label)
+                                .show(ui, |ui| {
+                                    #(#fields)*
+                                });
+
+                            changed
+                        })
+                }
+                syn::Data::Enum(data) =>
+                {
+                        let mut variants = Vec::new();
+
+                        for variant in &data.variants
+                        {
+                                if !matches!(variant.fields, syn::Fields::Unit)
+                                {
+                                        return Err(syn::Error::new_spanned(
+                                                variant,
+                                                "Inspect only supports enums without fields",
+                                        ));
+                                }
+
+                                variants.push(&variant.ident);
+                        }
+
+                        let names: Vec<String> = variants.iter().map(|v| v.to_string()).collect();
+
+                        Ok(quote! {
+                            let mut changed = false;
+
+                            let selected = match self {
+                                #(Self::#variants => #names,)*
+                            };
+
+                            ui.horizontal(|ui| {
+                                ui.label(label);
+
+                                ::oxide::egui::ComboBox::from_id_salt(label)
+                                    .selected_text(selected)
+                                    .show_ui(ui, |ui| {
+                                        #(
+                                            if ui.selectable_label(matches!(self, Self::#variants), #names).clicked() {
+                                                *self = Self::#variants;
+                                                changed = true;
+                                            }
+                                        )*
+                                    });
+                            });
+
+                            changed
+                        })
+                }
+                syn::Data::Union(_) => Err(syn::Error::new_spanned(
+                        &input.ident,
+                        "Inspect can't be derived for unions",
+                )),
+        }
+}
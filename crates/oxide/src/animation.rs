@@ -0,0 +1,1088 @@
+//! Keyframed model animation driven by a state machine.
+//!
+//! An [`AnimationClip`] moves, turns and scales a model over time. Clips are
+//! the states of an [`Animator`], which switches between them through
+//! [`Transition`]s when their conditions on the animator's parameters hold,
+//! cross-fading over the transition's blend time. Behaviors only set the
+//! parameters:
+//!
+//! ```ignore
+//! let bob = AnimationClip::new("run")
+//!         .with_key(0.0, Transform::IDENTITY)
+//!         .with_key(0.25, Transform::from_position((0.0, 0.3, 0.0)))
+//!         .with_key(0.5, Transform::IDENTITY)
+//!         .looping(true);
+//!
+//! engine.animate(
+//!         "player",
+//!         Animator::new(AnimationClip::new("idle"))
+//!                 .with_state(bob)
+//!                 .with_transition(
+//!                         Transition::new("idle", "run", 0.2).when(Condition::is_true("running")),
+//!                 )
+//!                 .with_transition(
+//!                         Transition::new("run", "idle", 0.2).when(Condition::is_false("running")),
+//!                 ),
+//! );
+//!
+//! engine.register_behavior(|engine| {
+//!         let running = engine.pressed_keys.contains(&KeyCode::KeyW);
+//!
+//!         if let Some(anim) = engine.animator_mut("player")
+//!         {
+//!                 anim.set_bool("running", running);
+//!         }
+//! });
+//! ```
+//!
+//! Animations write [`Model::pose`](crate::model::Model::pose), applied on
+//! top of the model's transform, so game code keeps moving the model while
+//! it's animated.
+//!
+//! Clips that walk or turn the model can move it for real instead: with
+//! [`Animator::with_root_motion`] their movement along the ground and
+//! turning about Y go to [`Model::transform`](crate::model::Model::transform)
+//! as they play, so a walk cycle keyed a step forward carries the model
+//! along and a looping one keeps it walking.
+//!
+//! Crowds stay affordable with an [`AnimationLod`], which updates models
+//! far from the camera or out of view every few frames only:
+//!
+//! ```ignore
+//! engine.animation.lod = AnimationLod {
+//!         levels: vec![LodLevel::new(20.0, 2), LodLevel::new(60.0, 6)],
+//!         offscreen_interval: 0,
+//! };
+//! ```
+
+use crate::engine::Engine;
+use crate::geometry::skin::Skin;
+use crate::math::interpolation::Interpolate;
+use crate::transform::Transform;
+use cgmath::{EuclideanSpace, InnerSpace, MetricSpace, Point3, Quaternion, Rotation};
+use instant::Instant;
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Keyframe
+{
+        /// Seconds from the start of the clip.
+        pub time: f32,
+        pub transform: Transform,
+}
+
+/// Keyframes of a pose over time, blended linearly between them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationClip
+{
+        pub name: String,
+        /// Sorted by time.
+        keyframes: Vec<Keyframe>,
+        /// Starts over after the last keyframe instead of holding it.
+        pub looping: bool,
+        /// Playback rate, 2.0 plays twice as fast.
+        pub speed: f32,
+}
+
+impl AnimationClip
+{
+        /// A clip without keyframes, holding the rest pose.
+        pub fn new(name: impl Into<String>) -> Self
+        {
+                Self {
+                        name: name.into(),
+                        keyframes: Vec::new(),
+                        looping: false,
+                        speed: 1.0,
+                }
+        }
+
+        /// Adds a keyframe, replacing one at the same time.
+        pub fn with_key(
+                mut self,
+                time: f32,
+                transform: Transform,
+        ) -> Self
+        {
+                self.keyframes.retain(|k| k.time != time);
+
+                let index = self.keyframes.partition_point(|k| k.time < time);
+                self.keyframes.insert(
+                        index,
+                        Keyframe {
+                                time,
+                                transform,
+                        },
+                );
+
+                self
+        }
+
+        pub fn looping(
+                mut self,
+                looping: bool,
+        ) -> Self
+        {
+                self.looping = looping;
+                self
+        }
+
+        pub fn with_speed(
+                mut self,
+                speed: f32,
+        ) -> Self
+        {
+                self.speed = speed;
+                self
+        }
+
+        pub fn keyframes(&self) -> &[Keyframe]
+        {
+                &self.keyframes
+        }
+
+        /// Time of the last keyframe.
+        pub fn duration(&self) -> f32
+        {
+                self.keyframes.last().map_or(0.0, |k| k.time)
+        }
+
+        /// Whether a clip that doesn't loop has played to its end at `time`.
+        pub fn is_finished(
+                &self,
+                time: f32,
+        ) -> bool
+        {
+                !self.looping && time >= self.duration()
+        }
+
+        /// The pose at `time` seconds into the clip.
+        pub fn sample(
+                &self,
+                time: f32,
+        ) -> Transform
+        {
+                let (Some(first), Some(last)) = (self.keyframes.first(), self.keyframes.last())
+                else
+                {
+                        return Transform::IDENTITY;
+                };
+
+                let duration = self.duration();
+                let time = if self.looping && duration > 0.0
+                {
+                        time.rem_euclid(duration)
+                }
+                else
+                {
+                        time
+                };
+
+                if time <= first.time
+                {
+                        return first.transform;
+                }
+
+                if time >= last.time
+                {
+                        return last.transform;
+                }
+
+                let next = self.keyframes.partition_point(|k| k.time <= time);
+                let (a, b) = (&self.keyframes[next - 1], &self.keyframes[next]);
+
+                a.transform
+                        .interpolate(&b.transform, (time - a.time) / (b.time - a.time))
+        }
+}
+
+/// A value transitions are conditioned on.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Parameter
+{
+        Bool(bool),
+        Float(f32),
+        /// Set until a transition conditioned on it is taken.
+        Trigger(bool),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition
+{
+        Bool
+        {
+                name: String, value: bool
+        },
+        Greater
+        {
+                name: String, value: f32
+        },
+        Less
+        {
+                name: String, value: f32
+        },
+        /// A trigger was set, see [`Animator::set_trigger`].
+        Trigger(String),
+        /// The current clip doesn't loop and has played to its end.
+        Finished,
+}
+
+impl Condition
+{
+        pub fn is_true(name: impl Into<String>) -> Self
+        {
+                Self::Bool {
+                        name: name.into(),
+                        value: true,
+                }
+        }
+
+        pub fn is_false(name: impl Into<String>) -> Self
+        {
+                Self::Bool {
+                        name: name.into(),
+                        value: false,
+                }
+        }
+
+        pub fn greater(
+                name: impl Into<String>,
+                value: f32,
+        ) -> Self
+        {
+                Self::Greater {
+                        name: name.into(),
+                        value,
+                }
+        }
+
+        pub fn less(
+                name: impl Into<String>,
+                value: f32,
+        ) -> Self
+        {
+                Self::Less {
+                        name: name.into(),
+                        value,
+                }
+        }
+
+        pub fn trigger(name: impl Into<String>) -> Self
+        {
+                Self::Trigger(name.into())
+        }
+}
+
+/// A switch from one state to another, taken once all its conditions hold.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transition
+{
+        /// `None` from any state but `to`.
+        pub from: Option<String>,
+        pub to: String,
+        /// Seconds both clips are cross-faded over.
+        pub blend: f32,
+        pub conditions: Vec<Condition>,
+}
+
+impl Transition
+{
+        pub fn new(
+                from: impl Into<String>,
+                to: impl Into<String>,
+                blend: f32,
+        ) -> Self
+        {
+                Self {
+                        from: Some(from.into()),
+                        to: to.into(),
+                        blend,
+                        conditions: Vec::new(),
+                }
+        }
+
+        /// A transition from every other state, e.g. to a "hit" state.
+        pub fn from_any(
+                to: impl Into<String>,
+                blend: f32,
+        ) -> Self
+        {
+                Self {
+                        from: None,
+                        to: to.into(),
+                        blend,
+                        conditions: Vec::new(),
+                }
+        }
+
+        pub fn when(
+                mut self,
+                condition: Condition,
+        ) -> Self
+        {
+                self.conditions.push(condition);
+                self
+        }
+}
+
+/// Clips of the joints of a [`Skin`] playing together, each keying the
+/// local transform of the joint of its name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkeletalClip
+{
+        pub name: String,
+        pub joints: BTreeMap<String, AnimationClip>,
+        pub looping: bool,
+}
+
+impl SkeletalClip
+{
+        pub fn new(name: impl Into<String>) -> Self
+        {
+                Self {
+                        name: name.into(),
+                        joints: BTreeMap::new(),
+                        looping: false,
+                }
+        }
+
+        /// Keys joint `joint` with `clip`, replacing its clip.
+        pub fn with_joint(
+                mut self,
+                joint: impl Into<String>,
+                clip: AnimationClip,
+        ) -> Self
+        {
+                self.joints.insert(joint.into(), clip);
+                self
+        }
+
+        pub fn looping(
+                mut self,
+                looping: bool,
+        ) -> Self
+        {
+                self.looping = looping;
+                self
+        }
+
+        /// Duration of the longest joint clip.
+        pub fn duration(&self) -> f32
+        {
+                self.joints
+                        .values()
+                        .map(AnimationClip::duration)
+                        .fold(0.0, f32::max)
+        }
+
+        /// Poses the joints of `skin` at `time` seconds in, the joints without
+        /// a clip keep their pose.
+        pub fn pose(
+                &self,
+                skin: &mut Skin,
+                time: f32,
+        )
+        {
+                let duration = self.duration();
+                let time = if self.looping && duration > 0.0
+                {
+                        time.rem_euclid(duration)
+                }
+                else
+                {
+                        time
+                };
+
+                for joint in &mut skin.joints
+                {
+                        if let Some(clip) = self.joints.get(&joint.name)
+                        {
+                                joint.transform = clip.sample(time);
+                        }
+                }
+        }
+}
+
+/// Which part of a clip's motion moves the model itself, see
+/// [`Animator::with_root_motion`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RootMotion
+{
+        /// Movement along X and Z, moving up and down stays in the pose.
+        pub translation: bool,
+        /// Turning about Y, tilting stays in the pose.
+        pub rotation: bool,
+}
+
+impl RootMotion
+{
+        /// All motion stays in the pose.
+        pub const NONE: Self = Self {
+                translation: false,
+                rotation: false,
+        };
+
+        pub const TRANSLATION: Self = Self {
+                translation: true,
+                rotation: false,
+        };
+
+        pub const ALL: Self = Self {
+                translation: true,
+                rotation: true,
+        };
+
+        pub fn is_none(&self) -> bool
+        {
+                *self == Self::NONE
+        }
+
+        /// The part of `pose` moving the model.
+        fn root(
+                &self,
+                pose: &Transform,
+        ) -> Transform
+        {
+                let mut root = Transform::IDENTITY;
+
+                if self.translation
+                {
+                        root.position.x = pose.position.x;
+                        root.position.z = pose.position.z;
+                }
+
+                if self.rotation
+                {
+                        let q = pose.rotation;
+                        let twist = Quaternion::new(q.s, 0.0, q.v.y, 0.0);
+
+                        if twist.magnitude2() > f32::EPSILON
+                        {
+                                root.rotation = twist.normalize();
+                        }
+                }
+
+                root
+        }
+
+        /// `pose` with its root taken out, relative to the model.
+        fn strip(
+                &self,
+                pose: Transform,
+        ) -> Transform
+        {
+                between(self.root(&pose), pose)
+        }
+
+        /// How far the root of `clip` moves from `from` to `to` seconds in,
+        /// relative to where it was at `from`.
+        fn delta(
+                &self,
+                clip: &AnimationClip,
+                from: f32,
+                to: f32,
+        ) -> Transform
+        {
+                let (Some(first), Some(last)) = (clip.keyframes.first(), clip.keyframes.last())
+                else
+                {
+                        return Transform::IDENTITY;
+                };
+
+                let root = |time: f32| self.root(&clip.sample(time));
+                let duration = clip.duration();
+
+                // Wrapping around continues from the end of the loop instead
+                // of jumping back to its start.
+                if clip.looping
+                        && duration > 0.0
+                        && (to / duration).floor() > (from / duration).floor()
+                {
+                        return between(root(from), self.root(&last.transform))
+                                * between(self.root(&first.transform), root(to));
+                }
+
+                between(root(from), root(to))
+        }
+}
+
+/// `to` relative to `from`, which has no scale.
+fn between(
+        from: Transform,
+        to: Transform,
+) -> Transform
+{
+        let inverse = from.rotation.invert();
+
+        Transform {
+                position: Point3::from_vec(inverse.rotate_vector(to.position - from.position)),
+                rotation: inverse * to.rotation,
+                scale: to.scale,
+        }
+}
+
+/// The clip faded out during a transition.
+#[derive(Debug, Clone, PartialEq)]
+struct Blend
+{
+        from: String,
+        from_time: f32,
+        elapsed: f32,
+        duration: f32,
+}
+
+/// A state machine of clips attached to a model, see the
+/// [module docs](self).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Animator
+{
+        states: BTreeMap<String, AnimationClip>,
+        transitions: Vec<Transition>,
+        parameters: HashMap<String, Parameter>,
+        current: String,
+        /// Seconds into the current clip.
+        time: f32,
+        blend: Option<Blend>,
+        root_motion: RootMotion,
+        /// Root movement of the last update.
+        root_delta: Transform,
+}
+
+impl Animator
+{
+        /// Starts in `initial`.
+        pub fn new(initial: AnimationClip) -> Self
+        {
+                let current = initial.name.clone();
+
+                Self {
+                        states: BTreeMap::from([(current.clone(), initial)]),
+                        transitions: Vec::new(),
+                        parameters: HashMap::new(),
+                        current,
+                        time: 0.0,
+                        blend: None,
+                        root_motion: RootMotion::NONE,
+                        root_delta: Transform::IDENTITY,
+                }
+        }
+
+        /// Adds a state named after the clip, replacing one of that name.
+        pub fn with_state(
+                mut self,
+                clip: AnimationClip,
+        ) -> Self
+        {
+                self.states.insert(clip.name.clone(), clip);
+                self
+        }
+
+        /// Transitions are checked in the order they were added, the first
+        /// one that can be taken is.
+        pub fn with_transition(
+                mut self,
+                transition: Transition,
+        ) -> Self
+        {
+                self.transitions.push(transition);
+                self
+        }
+
+        /// Moves the model by the `root_motion` of its clips instead of
+        /// posing it, see the [module docs](self).
+        pub fn with_root_motion(
+                mut self,
+                root_motion: RootMotion,
+        ) -> Self
+        {
+                self.root_motion = root_motion;
+                self
+        }
+
+        pub fn root_motion(&self) -> RootMotion
+        {
+                self.root_motion
+        }
+
+        /// How far the last [`Animator::update`] moved the root, relative to
+        /// the model.
+        pub fn root_delta(&self) -> Transform
+        {
+                self.root_delta
+        }
+
+        pub fn current_state(&self) -> &str
+        {
+                &self.current
+        }
+
+        /// Seconds into the current state.
+        pub fn state_time(&self) -> f32
+        {
+                self.time
+        }
+
+        pub fn is_blending(&self) -> bool
+        {
+                self.blend.is_some()
+        }
+
+        pub fn state(
+                &self,
+                name: &str,
+        ) -> Option<&AnimationClip>
+        {
+                self.states.get(name)
+        }
+
+        pub fn set_bool(
+                &mut self,
+                name: impl Into<String>,
+                value: bool,
+        )
+        {
+                self.parameters.insert(name.into(), Parameter::Bool(value));
+        }
+
+        pub fn set_float(
+                &mut self,
+                name: impl Into<String>,
+                value: f32,
+        )
+        {
+                self.parameters.insert(name.into(), Parameter::Float(value));
+        }
+
+        /// Sets a trigger, reset when a transition conditioned on it is
+        /// taken.
+        pub fn set_trigger(
+                &mut self,
+                name: impl Into<String>,
+        )
+        {
+                self.parameters
+                        .insert(name.into(), Parameter::Trigger(true));
+        }
+
+        pub fn reset_trigger(
+                &mut self,
+                name: &str,
+        )
+        {
+                if let Some(Parameter::Trigger(set)) = self.parameters.get_mut(name)
+                {
+                        *set = false;
+                }
+        }
+
+        pub fn parameter(
+                &self,
+                name: &str,
+        ) -> Option<Parameter>
+        {
+                self.parameters.get(name).copied()
+        }
+
+        /// Switches to `state` right away, fading over `blend` seconds.
+        /// Returns false for an unknown state.
+        pub fn play(
+                &mut self,
+                state: &str,
+                blend: f32,
+        ) -> bool
+        {
+                if !self.states.contains_key(state)
+                {
+                        log::warn!("Animator has no state {:?}", state);
+                        return false;
+                }
+
+                let from = std::mem::replace(&mut self.current, state.to_string());
+                let from_time = std::mem::replace(&mut self.time, 0.0);
+
+                self.blend = (blend > 0.0).then_some(Blend {
+                        from,
+                        from_time,
+                        elapsed: 0.0,
+                        duration: blend,
+                });
+
+                true
+        }
+
+        fn holds(
+                &self,
+                condition: &Condition,
+        ) -> bool
+        {
+                match condition
+                {
+                        Condition::Bool {
+                                name,
+                                value,
+                        } => self.parameter(name) == Some(Parameter::Bool(*value)),
+                        Condition::Greater {
+                                name,
+                                value,
+                        } =>
+                        {
+                                matches!(self.parameter(name), Some(Parameter::Float(f)) if f > *value)
+                        }
+                        Condition::Less {
+                                name,
+                                value,
+                        } =>
+                        {
+                                matches!(self.parameter(name), Some(Parameter::Float(f)) if f < *value)
+                        }
+                        Condition::Trigger(name) =>
+                        {
+                                self.parameter(name) == Some(Parameter::Trigger(true))
+                        }
+                        Condition::Finished => self
+                                .states
+                                .get(&self.current)
+                                .is_some_and(|clip| clip.is_finished(self.time)),
+                }
+        }
+
+        /// Takes the first transition whose conditions hold.
+        fn transition(&mut self)
+        {
+                let Some(transition) = self
+                        .transitions
+                        .iter()
+                        .find(|t| {
+                                let from = match &t.from
+                                {
+                                        Some(from) => *from == self.current,
+                                        None => t.to != self.current,
+                                };
+
+                                from && t.conditions.iter().all(|c| self.holds(c))
+                        })
+                        .cloned()
+                else
+                {
+                        return;
+                };
+
+                for condition in &transition.conditions
+                {
+                        if let Condition::Trigger(name) = condition
+                        {
+                                self.reset_trigger(name);
+                        }
+                }
+
+                self.play(&transition.to, transition.blend);
+        }
+
+        /// Moves the animation `dt` seconds on and returns the pose.
+        pub fn update(
+                &mut self,
+                dt: f32,
+        ) -> Transform
+        {
+                self.transition();
+
+                let speed = |name: &str| self.states.get(name).map_or(1.0, |c| c.speed);
+                let delta = |name: &str, from: f32, to: f32| {
+                        self.states.get(name).map_or(Transform::IDENTITY, |clip| {
+                                self.root_motion.delta(clip, from, to)
+                        })
+                };
+
+                let time = self.time + dt * speed(&self.current);
+                self.root_delta = delta(&self.current, self.time, time);
+                self.time = time;
+
+                if let Some(blend) = &self.blend
+                {
+                        let from_time = blend.from_time + dt * speed(&blend.from);
+                        let elapsed = blend.elapsed + dt;
+
+                        self.root_delta = delta(&blend.from, blend.from_time, from_time)
+                                .interpolate(&self.root_delta, elapsed / blend.duration);
+
+                        self.blend = (elapsed < blend.duration).then(|| Blend {
+                                from_time,
+                                elapsed,
+                                ..blend.clone()
+                        });
+                }
+
+                self.pose()
+        }
+
+        /// The pose at the current time, cross-faded while blending.
+        pub fn pose(&self) -> Transform
+        {
+                let sample = |name: &str, time: f32| {
+                        self.states
+                                .get(name)
+                                .map_or(Transform::IDENTITY, |clip| clip.sample(time))
+                };
+
+                let pose = sample(&self.current, self.time);
+                let pose = match &self.blend
+                {
+                        Some(blend) => sample(&blend.from, blend.from_time)
+                                .interpolate(&pose, blend.elapsed / blend.duration),
+                        None => pose,
+                };
+
+                if self.root_motion.is_none()
+                {
+                        pose
+                }
+                else
+                {
+                        self.root_motion.strip(pose)
+                }
+        }
+}
+
+/// How often animations far from the camera update.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LodLevel
+{
+        /// From the camera to the model, from which on `interval` applies.
+        pub distance: f32,
+        /// Frames per update, 1 updates every frame.
+        pub interval: u32,
+}
+
+impl LodLevel
+{
+        pub fn new(
+                distance: f32,
+                interval: u32,
+        ) -> Self
+        {
+                Self {
+                        distance,
+                        interval,
+                }
+        }
+}
+
+/// When animations update, see [`AnimationSystem::lod`]. A skipped update
+/// isn't lost, the next one catches up on the time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationLod
+{
+        /// The level of the largest distance a model is past applies, closer
+        /// models update every frame.
+        pub levels: Vec<LodLevel>,
+        /// Frames per update of models outside the camera's view, 0 pauses
+        /// them until they're back in view.
+        pub offscreen_interval: u32,
+}
+
+impl Default for AnimationLod
+{
+        /// Every animation updates every frame.
+        fn default() -> Self
+        {
+                Self {
+                        levels: Vec::new(),
+                        offscreen_interval: 1,
+                }
+        }
+}
+
+impl AnimationLod
+{
+        /// Frames per update of a model `distance` from the camera, `None`
+        /// for a paused one.
+        pub fn interval(
+                &self,
+                distance: f32,
+                visible: bool,
+        ) -> Option<u32>
+        {
+                if !visible
+                {
+                        return (self.offscreen_interval > 0).then_some(self.offscreen_interval);
+                }
+
+                let interval = self
+                        .levels
+                        .iter()
+                        .filter(|level| distance >= level.distance)
+                        .max_by(|a, b| a.distance.total_cmp(&b.distance))
+                        .map_or(1, |level| level.interval);
+
+                Some(interval.max(1))
+        }
+}
+
+/// The animators by model handle.
+#[derive(Debug)]
+pub struct AnimationSystem
+{
+        animators: BTreeMap<String, Animator>,
+
+        /// Update rate of animations by distance and visibility.
+        pub lod: AnimationLod,
+
+        /// Time every animator hasn't been updated by yet.
+        pending: HashMap<String, f32>,
+
+        frame: u64,
+
+        last_update: Option<Instant>,
+}
+
+impl Default for AnimationSystem
+{
+        fn default() -> Self
+        {
+                Self::new()
+        }
+}
+
+impl AnimationSystem
+{
+        pub fn new() -> Self
+        {
+                Self {
+                        animators: BTreeMap::new(),
+                        lod: AnimationLod::default(),
+                        pending: HashMap::new(),
+                        frame: 0,
+                        last_update: None,
+                }
+        }
+
+        pub fn len(&self) -> usize
+        {
+                self.animators.len()
+        }
+
+        pub fn is_empty(&self) -> bool
+        {
+                self.animators.is_empty()
+        }
+
+        /// Whether root motion changes the transform of `model`.
+        pub fn moves(
+                &self,
+                model: &str,
+        ) -> bool
+        {
+                self.animators
+                        .get(model)
+                        .is_some_and(|a| !a.root_motion.is_none())
+        }
+
+        /// Advances every animator due this frame by the time since its
+        /// last update and poses its model, see [`AnimationSystem::lod`].
+        ///
+        /// Called by the engine once per frame.
+        pub(crate) fn update(engine: &mut Engine)
+        {
+                let now = Instant::now();
+
+                let dt = engine
+                        .animation
+                        .last_update
+                        .replace(now)
+                        .map(|last| (now - last).as_secs_f32())
+                        .unwrap_or_default();
+
+                let Some(state) = engine.state.as_mut()
+                else
+                {
+                        return;
+                };
+
+                let system = &mut engine.animation;
+                let camera = state.camera.core.position;
+                let frustum = state.camera.frustum();
+
+                system.frame += 1;
+
+                for (index, (handle, animator)) in system.animators.iter_mut().enumerate()
+                {
+                        let pending = system.pending.entry(handle.clone()).or_default();
+                        *pending += dt;
+
+                        let interval = match state.models.get(handle)
+                        {
+                                Some(model) => system.lod.interval(
+                                        model.transform.position.distance(camera),
+                                        model.world_bounds()
+                                                .is_none_or(|b| frustum.intersects_aabb(&b)),
+                                ),
+                                None => Some(1),
+                        };
+
+                        // Staggered so a crowd doesn't update all in the same
+                        // frame.
+                        let due = interval.is_some_and(|i| {
+                                (system.frame + index as u64).is_multiple_of(i as u64)
+                        });
+
+                        if !due
+                        {
+                                continue;
+                        }
+
+                        let pose = animator.update(std::mem::take(pending));
+
+                        if let Some(model) = state.models.get_mut(handle)
+                        {
+                                model.pose = pose;
+
+                                if !animator.root_motion.is_none()
+                                {
+                                        model.transform = model.transform * animator.root_delta;
+                                }
+                        }
+                }
+        }
+}
+
+impl Engine
+{
+        /// Animates `model` with `animator`, replacing its current one.
+        pub fn animate(
+                &mut self,
+                model: impl Into<String>,
+                animator: Animator,
+        ) -> &mut Animator
+        {
+                let model = model.into();
+
+                self.animation.animators.insert(model.clone(), animator);
+                self.animation.pending.remove(&model);
+
+                self.animation.animators.get_mut(&model).unwrap()
+        }
+
+        /// Stops animating `model` and puts it back in its rest pose.
+        pub fn stop_animating(
+                &mut self,
+                model: &str,
+        ) -> Option<Animator>
+        {
+                if let Some(m) = self.state.as_mut().and_then(|s| s.models.get_mut(model))
+                {
+                        m.pose = Transform::IDENTITY;
+                }
+
+                self.animation.pending.remove(model);
+                self.animation.animators.remove(model)
+        }
+
+        /// The animator of `model`, to set its parameters.
+        pub fn animator_mut(
+                &mut self,
+                model: &str,
+        ) -> Option<&mut Animator>
+        {
+                self.animation.animators.get_mut(model)
+        }
+}
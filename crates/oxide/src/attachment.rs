@@ -0,0 +1,201 @@
+//! Models carried along by sockets of other models.
+//!
+//! A socket is a named point of a model, one of its
+//! [`Model::sockets`](crate::model::Model::sockets) or a mesh node loaded
+//! with it. An attached model is placed at its parent's socket every frame,
+//! after animations pose the parent, so a sword stays in the hand swinging
+//! it:
+//!
+//! ```ignore
+//! engine.state.as_mut().unwrap().models.get_mut("hero").unwrap().sockets.insert(
+//!         "hand_r".to_string(),
+//!         Transform::from_position((0.4, 1.1, 0.2)),
+//! );
+//!
+//! if let Some(sword) = engine.attach("sword", "hero", "hand_r")
+//! {
+//!         sword.offset = Transform::IDENTITY.with_euler_angles([90.0, 0.0, 0.0]);
+//! }
+//! ```
+//!
+//! Anything following a model follows its attachments too, like
+//! [sound emitters](crate::audio::spatial::Emitter) and models attached to
+//! them in turn.
+
+use crate::engine::Engine;
+use crate::transform::Transform;
+use std::collections::BTreeMap;
+
+/// Where a model is attached.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attachment
+{
+        /// Handle of the model carrying this one.
+        pub parent: String,
+        /// Socket of `parent`, its origin when it has no such socket.
+        pub socket: String,
+        /// Placement relative to the socket.
+        pub offset: Transform,
+}
+
+/// The attachments by model handle.
+#[derive(Debug, Default)]
+pub struct AttachmentSystem
+{
+        attachments: BTreeMap<String, Attachment>,
+}
+
+impl AttachmentSystem
+{
+        pub fn new() -> Self
+        {
+                Self::default()
+        }
+
+        pub fn len(&self) -> usize
+        {
+                self.attachments.len()
+        }
+
+        pub fn is_empty(&self) -> bool
+        {
+                self.attachments.is_empty()
+        }
+
+        pub fn get(
+                &self,
+                model: &str,
+        ) -> Option<&Attachment>
+        {
+                self.attachments.get(model)
+        }
+
+        /// Whether `model` is carried by `ancestor`, directly or through
+        /// other attachments.
+        pub fn is_attached_to(
+                &self,
+                model: &str,
+                ancestor: &str,
+        ) -> bool
+        {
+                let mut current = model;
+
+                while let Some(attachment) = self.attachments.get(current)
+                {
+                        if attachment.parent == ancestor
+                        {
+                                return true;
+                        }
+
+                        current = &attachment.parent;
+                }
+
+                false
+        }
+
+        /// How many attachments carry `model`.
+        fn depth(
+                &self,
+                model: &str,
+        ) -> usize
+        {
+                let mut depth = 0;
+                let mut current = model;
+
+                while let Some(attachment) = self.attachments.get(current)
+                {
+                        depth += 1;
+                        current = &attachment.parent;
+                }
+
+                depth
+        }
+
+        /// Places every attached model at its socket, parents before their
+        /// children so a chain follows within the frame.
+        ///
+        /// Called by the engine once per frame, after the animations.
+        pub(crate) fn update(engine: &mut Engine)
+        {
+                let system = &engine.attachment;
+
+                let Some(state) = engine.state.as_mut()
+                else
+                {
+                        return;
+                };
+
+                let mut order: Vec<_> = system.attachments.iter().collect();
+                order.sort_by_key(|(model, _)| system.depth(model));
+
+                for (model, attachment) in order
+                {
+                        let Some(parent) = state.models.get(&attachment.parent)
+                        else
+                        {
+                                continue;
+                        };
+
+                        let socket = parent
+                                .world_socket(&attachment.socket)
+                                .unwrap_or(parent.transform * parent.pose);
+
+                        if let Some(model) = state.models.get_mut(model)
+                        {
+                                model.transform = socket * attachment.offset;
+                        }
+                }
+        }
+}
+
+impl Engine
+{
+        /// Carries `model` at `socket` of `parent` every frame until
+        /// [`Engine::detach`], replacing where it was attached. `None` when
+        /// `parent` is `model` or carried by it.
+        pub fn attach(
+                &mut self,
+                model: impl Into<String>,
+                parent: impl Into<String>,
+                socket: impl Into<String>,
+        ) -> Option<&mut Attachment>
+        {
+                let model = model.into();
+                let parent = parent.into();
+
+                if parent == model || self.attachment.is_attached_to(&parent, &model)
+                {
+                        log::warn!("Can't attach {:?} to {:?}, it carries it", model, parent);
+                        return None;
+                }
+
+                self.attachment.attachments.insert(
+                        model.clone(),
+                        Attachment {
+                                parent,
+                                socket: socket.into(),
+                                offset: Transform::IDENTITY,
+                        },
+                );
+
+                self.attachment.attachments.get_mut(&model)
+        }
+
+        /// Stops carrying `model`, leaving it where it is.
+        pub fn detach(
+                &mut self,
+                model: &str,
+        ) -> Option<Attachment>
+        {
+                self.attachment.attachments.remove(model)
+        }
+
+        /// Where `model` is attached, to change its socket or offset.
+        pub fn attachment_mut(
+                &mut self,
+                model: &str,
+        ) -> Option<&mut Attachment>
+        {
+                self.attachment.attachments.get_mut(model)
+        }
+}
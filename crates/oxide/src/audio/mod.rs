@@ -0,0 +1,567 @@
+//! Audio playback for native and WebAssembly targets.
+//!
+//! [`AudioSystem`] is owned by the [`Engine`](crate::engine::Engine) and is
+//! reached from behaviors through `engine.audio()`. Every sound is played on
+//! a [`Bus`], and each bus has its own volume that is multiplied with the
+//! master volume.
+//!
+//! Backends:
+//! - `wasm32`: WebAudio, each bus is a `GainNode` and sounds are streamed
+//!   through `<audio>` elements.
+//! - Native: `rodio`, enabled with the `native-audio` feature. Without the
+//!   feature a silent backend is used that only logs what would be played.
+//!
+//! Files are resolved through [`crate::resources::resource_path`], the same
+//! way models are.
+//!
+//! Sounds started with [`AudioSystem::play_at`] follow a model and are
+//! attenuated and panned relative to the camera, see [`spatial`].
+
+use crate::camera::Camera;
+use crate::model::Model;
+use anyhow::Result;
+use cgmath::Point3;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[cfg(all(not(target_arch = "wasm32"), feature = "native-audio"))]
+pub mod native;
+pub mod spatial;
+#[cfg(target_arch = "wasm32")]
+pub mod web;
+
+pub use spatial::{Emitter, Listener};
+
+/// Mixing bus a sound is played on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Bus
+{
+        Music,
+        Sfx,
+}
+
+impl Bus
+{
+        pub const ALL: [Bus; 2] = [Bus::Music, Bus::Sfx];
+}
+
+/// Handle to a playing sound, returned by [`AudioSystem::play`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SoundHandle(pub u64);
+
+/// Everything a backend needs to start a sound.
+#[derive(Debug, Clone)]
+pub struct PlayRequest
+{
+        /// Resolved path (native) or URL (wasm) of the audio file.
+        pub path: String,
+        pub bus: Bus,
+        pub looping: bool,
+        /// Effective volume, bus volume multiplied by the master volume.
+        pub volume: f32,
+}
+
+/// Platform specific audio output.
+pub trait AudioBackend
+{
+        fn name(&self) -> &str;
+
+        fn play(
+                &mut self,
+                handle: SoundHandle,
+                request: PlayRequest,
+        ) -> Result<()>;
+
+        fn stop(
+                &mut self,
+                handle: SoundHandle,
+        );
+
+        /// Applies the effective volume to every sound playing on `bus`.
+        fn set_bus_volume(
+                &mut self,
+                bus: Bus,
+                volume: f32,
+        );
+
+        /// Sets the distance attenuation and stereo pan (`-1.0..=1.0`) of a
+        /// positional sound. The gain is applied on top of the bus volume.
+        fn set_spatial(
+                &mut self,
+                handle: SoundHandle,
+                gain: f32,
+                pan: f32,
+        );
+
+        fn is_playing(
+                &self,
+                handle: SoundHandle,
+        ) -> bool;
+
+        /// Browsers keep audio suspended until the first user gesture, this
+        /// is called on input to unlock it. No-op on native.
+        fn resume(&mut self) {}
+}
+
+/// Backend used when no audio output is available.
+///
+/// Logs every request so the game logic can still be followed.
+#[derive(Debug, Default)]
+pub struct NullBackend {}
+
+impl AudioBackend for NullBackend
+{
+        fn name(&self) -> &str
+        {
+                "null"
+        }
+
+        fn play(
+                &mut self,
+                handle: SoundHandle,
+                request: PlayRequest,
+        ) -> Result<()>
+        {
+                log::debug!("Audio (null): {:?} {:?}", handle, request);
+
+                Ok(())
+        }
+
+        fn stop(
+                &mut self,
+                #[allow(unused_variables)] handle: SoundHandle,
+        )
+        {
+        }
+
+        fn set_bus_volume(
+                &mut self,
+                #[allow(unused_variables)] bus: Bus,
+                #[allow(unused_variables)] volume: f32,
+        )
+        {
+        }
+
+        fn set_spatial(
+                &mut self,
+                #[allow(unused_variables)] handle: SoundHandle,
+                #[allow(unused_variables)] gain: f32,
+                #[allow(unused_variables)] pan: f32,
+        )
+        {
+        }
+
+        fn is_playing(
+                &self,
+                #[allow(unused_variables)] handle: SoundHandle,
+        ) -> bool
+        {
+                false
+        }
+}
+
+/// Engine-level audio mixer.
+pub struct AudioSystem
+{
+        backend: Box<dyn AudioBackend>,
+
+        master_volume: f32,
+
+        bus_volumes: HashMap<Bus, f32>,
+
+        /// Sounds started through this system and not yet known to be
+        /// finished.
+        playing: HashMap<SoundHandle, Bus>,
+
+        music: Option<SoundHandle>,
+
+        /// Positional sounds and the model each one follows.
+        emitters: HashMap<SoundHandle, Emitter>,
+
+        listener: Listener,
+
+        /// Model positions as of the last [`AudioSystem::update_spatial`],
+        /// used to place sounds started in between.
+        model_positions: HashMap<String, Point3<f32>>,
+
+        next_handle: u64,
+}
+
+impl std::fmt::Debug for AudioSystem
+{
+        fn fmt(
+                &self,
+                f: &mut std::fmt::Formatter<'_>,
+        ) -> std::fmt::Result
+        {
+                f.debug_struct("AudioSystem")
+                        .field("backend", &self.backend.name())
+                        .field("master_volume", &self.master_volume)
+                        .field("bus_volumes", &self.bus_volumes)
+                        .field("playing", &self.playing.len())
+                        .field("music", &self.music)
+                        .field("emitters", &self.emitters.len())
+                        .field("listener", &self.listener)
+                        .finish()
+        }
+}
+
+impl Default for AudioSystem
+{
+        fn default() -> Self
+        {
+                Self::new()
+        }
+}
+
+impl AudioSystem
+{
+        /// Creates the audio system with the platform's default backend.
+        pub fn new() -> Self
+        {
+                Self::with_backend(Self::default_backend())
+        }
+
+        pub fn with_backend(backend: Box<dyn AudioBackend>) -> Self
+        {
+                log::info!("Audio backend: {}", backend.name());
+
+                Self {
+                        backend,
+                        master_volume: 1.0,
+                        bus_volumes: Bus::ALL.iter().map(|b| (*b, 1.0)).collect(),
+                        playing: HashMap::new(),
+                        music: None,
+                        emitters: HashMap::new(),
+                        listener: Listener::default(),
+                        model_positions: HashMap::new(),
+                        next_handle: 0,
+                }
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        fn default_backend() -> Box<dyn AudioBackend>
+        {
+                match web::WebAudioBackend::new()
+                {
+                        Ok(b) => Box::new(b),
+                        Err(e) =>
+                        {
+                                log::warn!("WebAudio unavailable, audio disabled: {e:#}");
+                                Box::new(NullBackend::default())
+                        }
+                }
+        }
+
+        #[cfg(all(not(target_arch = "wasm32"), feature = "native-audio"))]
+        fn default_backend() -> Box<dyn AudioBackend>
+        {
+                match native::RodioBackend::new()
+                {
+                        Ok(b) => Box::new(b),
+                        Err(e) =>
+                        {
+                                log::warn!("No audio output device, audio disabled: {e:#}");
+                                Box::new(NullBackend::default())
+                        }
+                }
+        }
+
+        #[cfg(all(not(target_arch = "wasm32"), not(feature = "native-audio")))]
+        fn default_backend() -> Box<dyn AudioBackend>
+        {
+                Box::new(NullBackend::default())
+        }
+
+        pub fn backend_name(&self) -> &str
+        {
+                self.backend.name()
+        }
+
+        /// Plays a one-shot sound effect on the [`Bus::Sfx`] bus.
+        ///
+        /// `file_name` is relative to the `resources/` directory.
+        pub fn play(
+                &mut self,
+                file_name: &str,
+        ) -> Result<SoundHandle>
+        {
+                self.play_on(Bus::Sfx, file_name, false)
+        }
+
+        /// Plays a sound on the given bus, optionally looping it forever.
+        pub fn play_on(
+                &mut self,
+                bus: Bus,
+                file_name: &str,
+                looping: bool,
+        ) -> Result<SoundHandle>
+        {
+                let handle = SoundHandle(self.next_handle);
+                self.next_handle += 1;
+
+                let request = PlayRequest {
+                        path: Self::resolve(file_name)?,
+                        bus,
+                        looping,
+                        volume: self.effective_volume(bus),
+                };
+
+                self.backend.play(handle, request)?;
+
+                self.playing.insert(handle, bus);
+
+                Ok(handle)
+        }
+
+        /// Plays a one-shot sound effect that comes from the model with the
+        /// given handle.
+        pub fn play_at(
+                &mut self,
+                model: &str,
+                file_name: &str,
+        ) -> Result<SoundHandle>
+        {
+                self.play_spatial(Bus::Sfx, file_name, false, Emitter::new(model))
+        }
+
+        /// Plays a sound that follows `emitter.model` until it finishes or
+        /// is stopped.
+        pub fn play_spatial(
+                &mut self,
+                bus: Bus,
+                file_name: &str,
+                looping: bool,
+                emitter: Emitter,
+        ) -> Result<SoundHandle>
+        {
+                let handle = self.play_on(bus, file_name, looping)?;
+
+                if let Some(position) = self.model_positions.get(&emitter.model)
+                {
+                        let (gain, pan) = self.listener.spatialize(&emitter, *position);
+
+                        self.backend.set_spatial(handle, gain, pan);
+                }
+
+                self.emitters.insert(handle, emitter);
+
+                Ok(handle)
+        }
+
+        /// Streams a looping track on the [`Bus::Music`] bus, replacing the
+        /// current one.
+        pub fn play_music(
+                &mut self,
+                file_name: &str,
+        ) -> Result<SoundHandle>
+        {
+                self.stop_music();
+
+                let handle = self.play_on(Bus::Music, file_name, true)?;
+
+                self.music = Some(handle);
+
+                Ok(handle)
+        }
+
+        pub fn stop_music(&mut self)
+        {
+                if let Some(handle) = self.music.take()
+                {
+                        self.stop(handle);
+                }
+        }
+
+        pub fn stop(
+                &mut self,
+                handle: SoundHandle,
+        )
+        {
+                self.backend.stop(handle);
+                self.playing.remove(&handle);
+                self.emitters.remove(&handle);
+        }
+
+        pub fn stop_all(&mut self)
+        {
+                let handles: Vec<SoundHandle> = self.playing.keys().copied().collect();
+
+                for handle in handles
+                {
+                        self.stop(handle);
+                }
+
+                self.music = None;
+        }
+
+        pub fn is_playing(
+                &self,
+                handle: SoundHandle,
+        ) -> bool
+        {
+                self.playing.contains_key(&handle) && self.backend.is_playing(handle)
+        }
+
+        pub fn volume(
+                &self,
+                bus: Bus,
+        ) -> f32
+        {
+                self.bus_volumes.get(&bus).copied().unwrap_or(1.0)
+        }
+
+        /// Sets the volume of a bus, clamped to `0.0..=1.0`.
+        pub fn set_volume(
+                &mut self,
+                bus: Bus,
+                volume: f32,
+        )
+        {
+                self.bus_volumes.insert(bus, volume.clamp(0.0, 1.0));
+
+                self.backend.set_bus_volume(bus, self.effective_volume(bus));
+        }
+
+        pub fn master_volume(&self) -> f32
+        {
+                self.master_volume
+        }
+
+        pub fn set_master_volume(
+                &mut self,
+                volume: f32,
+        )
+        {
+                self.master_volume = volume.clamp(0.0, 1.0);
+
+                for bus in Bus::ALL
+                {
+                        self.backend.set_bus_volume(bus, self.effective_volume(bus));
+                }
+        }
+
+        fn effective_volume(
+                &self,
+                bus: Bus,
+        ) -> f32
+        {
+                self.master_volume * self.volume(bus)
+        }
+
+        /// Unlocks audio output on the web after a user gesture.
+        pub fn resume(&mut self)
+        {
+                self.backend.resume();
+        }
+
+        /// Forgets sounds that have finished playing.
+        ///
+        /// Called by the engine once per frame.
+        pub fn update(&mut self)
+        {
+                let finished: Vec<SoundHandle> = self
+                        .playing
+                        .keys()
+                        .copied()
+                        .filter(|h| !self.backend.is_playing(*h))
+                        .collect();
+
+                for handle in finished
+                {
+                        self.stop(handle);
+                }
+
+                if let Some(music) = self.music
+                        && !self.playing.contains_key(&music)
+                {
+                        self.music = None;
+                }
+        }
+
+        pub fn listener(&self) -> &Listener
+        {
+                &self.listener
+        }
+
+        /// Moves the listener to `camera` and re-applies the gain and pan of
+        /// every positional sound.
+        ///
+        /// Called by the engine once per frame. Sounds whose model has been
+        /// removed keep their last parameters.
+        pub fn update_spatial(
+                &mut self,
+                camera: &Camera,
+                models: &HashMap<String, Model>,
+        )
+        {
+                self.listener = Listener::from_camera(camera);
+
+                self.model_positions.clear();
+                self.model_positions.extend(models
+                        .iter()
+                        .map(|(name, model)| (name.clone(), model.transform.position)));
+
+                for (handle, emitter) in &self.emitters
+                {
+                        if let Some(position) = self.model_positions.get(&emitter.model)
+                        {
+                                let (gain, pan) = self.listener.spatialize(emitter, *position);
+
+                                self.backend.set_spatial(*handle, gain, pan);
+                        }
+                }
+        }
+
+        #[cfg(feature = "ui")]
+        pub fn ui(
+                &mut self,
+                ui: &mut egui::Ui,
+        )
+        {
+                egui::CollapsingHeader::new("Audio")
+                        .default_open(false)
+                        .show(ui, |ui| {
+                                ui.label(format!("Backend: {}", self.backend_name()));
+                                ui.label(format!("Playing: {}", self.playing.len()));
+                                ui.label(format!("Positional: {}", self.emitters.len()));
+
+                                let mut master = self.master_volume;
+                                if ui.add(egui::Slider::new(&mut master, 0.0..=1.0).text("Master"))
+                                        .changed()
+                                {
+                                        self.set_master_volume(master);
+                                }
+
+                                for bus in Bus::ALL
+                                {
+                                        let mut volume = self.volume(bus);
+                                        if ui.add(egui::Slider::new(&mut volume, 0.0..=1.0)
+                                                .text(format!("{:?}", bus)))
+                                                .changed()
+                                        {
+                                                self.set_volume(bus, volume);
+                                        }
+                                }
+                        });
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        fn resolve(file_name: &str) -> Result<String>
+        {
+                let path = crate::resources::resource_path(file_name, None);
+
+                if !path.exists()
+                {
+                        anyhow::bail!("Audio file not found: {}", path.display());
+                }
+
+                Ok(path.to_string_lossy().to_string())
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        fn resolve(file_name: &str) -> Result<String>
+        {
+                Ok(crate::resources::resource_path(file_name, None))
+        }
+}
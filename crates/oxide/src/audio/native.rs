@@ -0,0 +1,265 @@
+//! Native audio backend built on `rodio`.
+//!
+//! Only compiled with the `native-audio` feature, since `rodio` needs the
+//! platform audio libraries (ALSA on Linux) at build time.
+//!
+//! Every sound is converted to stereo and passed through [`Panned`], so the
+//! pan of positional sounds can be changed while they play.
+
+use crate::audio::{AudioBackend, Bus, PlayRequest, SoundHandle};
+use anyhow::{Context, Result};
+use rodio::Source;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
+/// Shared pan value, an `f32` stored as bits.
+type PanControl = Arc<AtomicU32>;
+
+/// Stereo source with an adjustable equal-power pan.
+struct Panned<S>
+where
+        S: Source,
+{
+        input: rodio::conversions::ChannelCountConverter<S>,
+
+        sample_rate: rodio::SampleRate,
+
+        total_duration: Option<Duration>,
+
+        pan: PanControl,
+
+        /// Whether the next sample is for the left channel.
+        left: bool,
+}
+
+impl<S> Panned<S>
+where
+        S: Source,
+{
+        fn new(
+                input: S,
+                pan: PanControl,
+        ) -> Self
+        {
+                let channels = input.channels();
+                let sample_rate = input.sample_rate();
+                let total_duration = input.total_duration();
+
+                Self {
+                        input: rodio::conversions::ChannelCountConverter::new(input, channels, 2),
+                        sample_rate,
+                        total_duration,
+                        pan,
+                        left: true,
+                }
+        }
+}
+
+impl<S> Iterator for Panned<S>
+where
+        S: Source,
+{
+        type Item = rodio::Sample;
+
+        fn next(&mut self) -> Option<Self::Item>
+        {
+                let sample = self.input.next()?;
+
+                let pan = f32::from_bits(self.pan.load(Ordering::Relaxed));
+                let angle = (pan + 1.0) * std::f32::consts::FRAC_PI_4;
+
+                let gain = if self.left { angle.cos() } else { angle.sin() };
+
+                self.left = !self.left;
+
+                // Equal power pan is -3 dB in the center, compensate so
+                // unpanned sounds keep their original loudness.
+                Some(sample * gain * std::f32::consts::SQRT_2)
+        }
+}
+
+impl<S> Source for Panned<S>
+where
+        S: Source,
+{
+        fn current_span_len(&self) -> Option<usize>
+        {
+                None
+        }
+
+        fn channels(&self) -> rodio::ChannelCount
+        {
+                2
+        }
+
+        fn sample_rate(&self) -> rodio::SampleRate
+        {
+                self.sample_rate
+        }
+
+        fn total_duration(&self) -> Option<Duration>
+        {
+                self.total_duration
+        }
+}
+
+struct NativeSound
+{
+        bus: Bus,
+
+        sink: rodio::Sink,
+
+        /// Distance attenuation, multiplied with the bus volume.
+        gain: f32,
+
+        pan: PanControl,
+}
+
+pub struct RodioBackend
+{
+        /// Must be kept alive for as long as sounds should be audible.
+        stream: rodio::OutputStream,
+
+        sounds: HashMap<SoundHandle, NativeSound>,
+
+        bus_volumes: HashMap<Bus, f32>,
+}
+
+impl RodioBackend
+{
+        pub fn new() -> Result<Self>
+        {
+                let mut stream = rodio::OutputStreamBuilder::open_default_stream()
+                        .context("Failed to open default audio output")?;
+
+                // rodio prints to stderr when the stream is dropped otherwise.
+                stream.log_on_drop(false);
+
+                Ok(Self {
+                        stream,
+                        sounds: HashMap::new(),
+                        bus_volumes: Bus::ALL.iter().map(|b| (*b, 1.0)).collect(),
+                })
+        }
+}
+
+impl AudioBackend for RodioBackend
+{
+        fn name(&self) -> &str
+        {
+                "rodio"
+        }
+
+        fn play(
+                &mut self,
+                handle: SoundHandle,
+                request: PlayRequest,
+        ) -> Result<()>
+        {
+                let file = File::open(&request.path)
+                        .with_context(|| format!("Failed to open {}", request.path))?;
+
+                let reader = BufReader::new(file);
+
+                let sink = rodio::Sink::connect_new(self.stream.mixer());
+
+                sink.set_volume(request.volume);
+
+                self.bus_volumes.insert(request.bus, request.volume);
+
+                let pan: PanControl = Arc::new(AtomicU32::new(0.0_f32.to_bits()));
+
+                // Decoding happens on the audio thread while playing, so
+                // long music tracks are streamed from disk.
+                if request.looping
+                {
+                        sink.append(Panned::new(
+                                rodio::Decoder::new_looped(reader).with_context(|| {
+                                        format!("Failed to decode {}", request.path)
+                                })?,
+                                pan.clone(),
+                        ));
+                }
+                else
+                {
+                        sink.append(Panned::new(
+                                rodio::Decoder::new(reader).with_context(|| {
+                                        format!("Failed to decode {}", request.path)
+                                })?,
+                                pan.clone(),
+                        ));
+                }
+
+                self.sounds.insert(
+                        handle,
+                        NativeSound {
+                                bus: request.bus,
+                                sink,
+                                gain: 1.0,
+                                pan,
+                        },
+                );
+
+                Ok(())
+        }
+
+        fn stop(
+                &mut self,
+                handle: SoundHandle,
+        )
+        {
+                if let Some(sound) = self.sounds.remove(&handle)
+                {
+                        sound.sink.stop();
+                }
+        }
+
+        fn set_bus_volume(
+                &mut self,
+                bus: Bus,
+                volume: f32,
+        )
+        {
+                self.bus_volumes.insert(bus, volume);
+
+                for sound in self.sounds.values()
+                {
+                        if sound.bus == bus
+                        {
+                                sound.sink.set_volume(volume * sound.gain);
+                        }
+                }
+        }
+
+        fn set_spatial(
+                &mut self,
+                handle: SoundHandle,
+                gain: f32,
+                pan: f32,
+        )
+        {
+                if let Some(sound) = self.sounds.get_mut(&handle)
+                {
+                        let bus_volume = self.bus_volumes.get(&sound.bus).copied().unwrap_or(1.0);
+
+                        sound.gain = gain;
+                        sound.sink.set_volume(bus_volume * gain);
+                        sound.pan
+                                .store(pan.clamp(-1.0, 1.0).to_bits(), Ordering::Relaxed);
+                }
+        }
+
+        fn is_playing(
+                &self,
+                handle: SoundHandle,
+        ) -> bool
+        {
+                self.sounds
+                        .get(&handle)
+                        .is_some_and(|sound| !sound.sink.empty())
+        }
+}
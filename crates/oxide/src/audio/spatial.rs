@@ -0,0 +1,147 @@
+//! Positional audio.
+//!
+//! Sounds can be attached to a model handle with an [`Emitter`]. Every frame
+//! the engine moves the [`Listener`] to the active camera and recomputes the
+//! gain and stereo pan of each emitter from the model's position.
+//!
+//! The model is intentionally simple and identical on every backend:
+//! - Attenuation follows the WebAudio "inverse" distance model.
+//! - Panning is the projection of the direction to the emitter onto the
+//!   listener's right vector.
+
+use crate::camera::Camera;
+use cgmath::{InnerSpace, MetricSpace, Point3, Vector3};
+
+/// Attaches a sound to a model.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Emitter
+{
+        /// Handle of the model the sound follows, as given to
+        /// [`Engine::add_model`](crate::engine::Engine::add_model).
+        pub model: String,
+
+        /// Distance up to which the sound plays at full volume.
+        pub ref_distance: f32,
+
+        /// Distance after which the sound doesn't get any quieter.
+        pub max_distance: f32,
+
+        /// How fast the volume drops past `ref_distance`.
+        pub rolloff: f32,
+
+        /// Multiplies the pan, useful when the camera is far away from a
+        /// mostly flat scene and sounds would otherwise all come from the
+        /// center.
+        pub pan_scale: f32,
+}
+
+impl Emitter
+{
+        pub fn new(model: impl Into<String>) -> Self
+        {
+                Self {
+                        model: model.into(),
+                        ref_distance: 1.0,
+                        max_distance: 100.0,
+                        rolloff: 1.0,
+                        pan_scale: 1.0,
+                }
+        }
+
+        pub fn with_distances(
+                mut self,
+                ref_distance: f32,
+                max_distance: f32,
+        ) -> Self
+        {
+                self.ref_distance = ref_distance.max(f32::EPSILON);
+                self.max_distance = max_distance.max(self.ref_distance);
+                self
+        }
+
+        pub fn with_rolloff(
+                mut self,
+                rolloff: f32,
+        ) -> Self
+        {
+                self.rolloff = rolloff.max(0.0);
+                self
+        }
+
+        pub fn with_pan_scale(
+                mut self,
+                pan_scale: f32,
+        ) -> Self
+        {
+                self.pan_scale = pan_scale.max(0.0);
+                self
+        }
+
+        /// Gain in `0.0..=1.0` for a listener `distance` units away.
+        pub fn attenuation(
+                &self,
+                distance: f32,
+        ) -> f32
+        {
+                let d = distance.clamp(self.ref_distance, self.max_distance);
+
+                self.ref_distance / (self.ref_distance + self.rolloff * (d - self.ref_distance))
+        }
+}
+
+/// The "ears" of the scene, follows the active camera.
+#[derive(Debug, Clone, Copy)]
+pub struct Listener
+{
+        pub position: Point3<f32>,
+        pub right: Vector3<f32>,
+}
+
+impl Default for Listener
+{
+        fn default() -> Self
+        {
+                Self {
+                        position: Point3::new(0.0, 0.0, 0.0),
+                        right: Vector3::unit_x(),
+                }
+        }
+}
+
+impl Listener
+{
+        pub fn from_camera(camera: &Camera) -> Self
+        {
+                Self {
+                        position: camera.core.position,
+                        right: camera.core.right(),
+                }
+        }
+
+        /// Returns `(gain, pan)` for `emitter` placed at `position`.
+        ///
+        /// `pan` is in `-1.0..=1.0`, negative values are on the left.
+        pub fn spatialize(
+                &self,
+                emitter: &Emitter,
+                position: Point3<f32>,
+        ) -> (f32, f32)
+        {
+                let distance = self.position.distance(position);
+
+                let gain = emitter.attenuation(distance);
+
+                let pan = if distance > f32::EPSILON
+                {
+                        (((position - self.position) / distance).dot(self.right)
+                                * emitter.pan_scale)
+                                .clamp(-1.0, 1.0)
+                }
+                else
+                {
+                        0.0
+                };
+
+                (gain, pan)
+        }
+}
@@ -0,0 +1,175 @@
+//! WebAudio backend for `wasm32`.
+//!
+//! Each [`Bus`] owns a `GainNode` connected to the context destination.
+//! Sounds are streamed through `<audio>` elements wrapped in a
+//! `MediaElementAudioSourceNode`, so long music tracks don't have to be fully
+//! downloaded and decoded before they start.
+//!
+//! A sound is routed `source -> StereoPannerNode -> GainNode -> bus`, the last
+//! two are driven by [`AudioBackend::set_spatial`] for positional sounds.
+
+use crate::audio::{AudioBackend, Bus, PlayRequest, SoundHandle};
+use anyhow::Result;
+use std::collections::HashMap;
+use web_sys::{
+        AudioContext, GainNode, HtmlAudioElement, MediaElementAudioSourceNode, StereoPannerNode,
+};
+
+fn js_err(e: wasm_bindgen::JsValue) -> anyhow::Error
+{
+        anyhow::anyhow!("{:?}", e)
+}
+
+struct WebSound
+{
+        element: HtmlAudioElement,
+
+        /// Kept so the node graph isn't collected while playing.
+        #[allow(dead_code)]
+        source: MediaElementAudioSourceNode,
+
+        panner: StereoPannerNode,
+
+        gain: GainNode,
+}
+
+pub struct WebAudioBackend
+{
+        context: AudioContext,
+
+        buses: HashMap<Bus, GainNode>,
+
+        sounds: HashMap<SoundHandle, WebSound>,
+}
+
+impl WebAudioBackend
+{
+        pub fn new() -> Result<Self>
+        {
+                let context = AudioContext::new().map_err(js_err)?;
+
+                let mut buses = HashMap::new();
+
+                for bus in Bus::ALL
+                {
+                        let gain = context.create_gain().map_err(js_err)?;
+
+                        gain.connect_with_audio_node(&context.destination())
+                                .map_err(js_err)?;
+
+                        buses.insert(bus, gain);
+                }
+
+                Ok(Self {
+                        context,
+                        buses,
+                        sounds: HashMap::new(),
+                })
+        }
+}
+
+impl AudioBackend for WebAudioBackend
+{
+        fn name(&self) -> &str
+        {
+                "webaudio"
+        }
+
+        fn play(
+                &mut self,
+                handle: SoundHandle,
+                request: PlayRequest,
+        ) -> Result<()>
+        {
+                let bus = self
+                        .buses
+                        .get(&request.bus)
+                        .ok_or_else(|| anyhow::anyhow!("Missing audio bus {:?}", request.bus))?;
+
+                bus.gain().set_value(request.volume);
+
+                let element = HtmlAudioElement::new_with_src(&request.path).map_err(js_err)?;
+
+                element.set_loop(request.looping);
+
+                let source = self
+                        .context
+                        .create_media_element_source(&element)
+                        .map_err(js_err)?;
+
+                let panner = self.context.create_stereo_panner().map_err(js_err)?;
+
+                let gain = self.context.create_gain().map_err(js_err)?;
+
+                source.connect_with_audio_node(&panner).map_err(js_err)?;
+                panner.connect_with_audio_node(&gain).map_err(js_err)?;
+                gain.connect_with_audio_node(bus).map_err(js_err)?;
+
+                // The returned promise rejects if the context is still locked,
+                // `resume` is called on the next user gesture.
+                let _ = element.play().map_err(js_err)?;
+
+                self.sounds.insert(
+                        handle,
+                        WebSound {
+                                element,
+                                source,
+                                panner,
+                                gain,
+                        },
+                );
+
+                Ok(())
+        }
+
+        fn stop(
+                &mut self,
+                handle: SoundHandle,
+        )
+        {
+                if let Some(sound) = self.sounds.remove(&handle)
+                {
+                        let _ = sound.element.pause();
+                        let _ = sound.gain.disconnect();
+                }
+        }
+
+        fn set_bus_volume(
+                &mut self,
+                bus: Bus,
+                volume: f32,
+        )
+        {
+                if let Some(gain) = self.buses.get(&bus)
+                {
+                        gain.gain().set_value(volume);
+                }
+        }
+
+        fn set_spatial(
+                &mut self,
+                handle: SoundHandle,
+                gain: f32,
+                pan: f32,
+        )
+        {
+                if let Some(sound) = self.sounds.get(&handle)
+                {
+                        sound.gain.gain().set_value(gain);
+                        sound.panner.pan().set_value(pan.clamp(-1.0, 1.0));
+                }
+        }
+
+        fn is_playing(
+                &self,
+                handle: SoundHandle,
+        ) -> bool
+        {
+                self.sounds.get(&handle).is_some_and(|s| !s.element.ended())
+        }
+
+        fn resume(&mut self)
+        {
+                let _ = self.context.resume();
+        }
+}
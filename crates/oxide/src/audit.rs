@@ -0,0 +1,473 @@
+//! Determinism audits of the fixed-step simulation.
+//!
+//! While an audit runs, every engine tick hashes what game logic can see:
+//! the tick since the audit started, the state of [`Engine::rng`] and the
+//! models, plus whatever the game adds with
+//! [`DeterminismAudit::add_source`]. Two runs that should be the same can
+//! then be compared tick by tick and the first tick they differ in points
+//! at the bug:
+//!
+//! ```ignore
+//! let score = Rc::new(Cell::new(0u32));
+//!
+//! engine.audit.add_source({
+//!         let score = score.clone();
+//!         move |_, hasher| score.get().hash(hasher)
+//! });
+//!
+//! engine.start_audit();
+//! // ... later
+//! let first = engine.stop_audit().unwrap();
+//!
+//! // Run again, compared as it goes.
+//! engine.start_audit_against(first);
+//!
+//! let mut reader = EventReader::<AuditEvent>::new();
+//!
+//! engine.register_behavior(move |eng| {
+//!         for AuditEvent::Diverged(divergence) in reader.read(&eng.events)
+//!         {
+//!                 log::error!("Diverged at tick {}", divergence.tick);
+//!         }
+//! });
+//! ```
+//!
+//! A replay recorded while an audit runs keeps the hashes, see
+//! [`Replay::hashes`], and playing it back compares against them, so a
+//! replay that doesn't play out like the recording tells where.
+//!
+//! Only state advanced on ticks can be compared. That covers the models'
+//! visibility and the transforms set by game logic and the
+//! [`Scheduler`](crate::scheduler::Scheduler), and the positions of
+//! [`SteeringSystem`](crate::steering::SteeringSystem) agents on the tick.
+//! Spinning models and models moved by
+//! [`TweenSystem`](crate::tween::TweenSystem) or root motion of the
+//! [`AnimationSystem`](crate::animation::AnimationSystem) advance by frame
+//! time, so their transforms are left out.
+//!
+//! Hashing every model each tick costs, audits are off unless started.
+
+use crate::engine::Engine;
+use crate::model::Model;
+use crate::replay::Replay;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::hash::{Hash, Hasher};
+
+/// Bumped when the file format or what is hashed changes.
+const VERSION: u32 = 1;
+
+/// Adds the game's own state to the hash of a tick.
+pub type AuditSource = Box<dyn Fn(&Engine, &mut AuditHasher)>;
+
+/// FNV-1a, the same on every platform and toolchain, so hashes of runs on
+/// different machines or builds can be compared.
+#[derive(Debug, Clone)]
+pub struct AuditHasher(u64);
+
+impl Default for AuditHasher
+{
+        fn default() -> Self
+        {
+                Self(0xcbf2_9ce4_8422_2325)
+        }
+}
+
+impl Hasher for AuditHasher
+{
+        fn finish(&self) -> u64
+        {
+                self.0
+        }
+
+        fn write(
+                &mut self,
+                bytes: &[u8],
+        )
+        {
+                for byte in bytes
+                {
+                        self.0 ^= *byte as u64;
+                        self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+                }
+        }
+}
+
+impl AuditHasher
+{
+        /// Hashes the bits of `value`, `-0.0` and `0.0` differ.
+        pub fn write_f32(
+                &mut self,
+                value: f32,
+        )
+        {
+                self.write_u32(value.to_bits());
+        }
+}
+
+/// The hashes of an audited run, one per tick.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditLog
+{
+        pub version: u32,
+
+        /// Seed of [`Engine::rng`] when the audit started.
+        pub seed: u64,
+
+        /// Ticks per second of the run.
+        pub tps: u16,
+
+        pub hashes: Vec<u64>,
+}
+
+/// The first tick two runs differ in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Divergence
+{
+        /// Tick since the audit started.
+        pub tick: u64,
+
+        /// Hash of the run compared against.
+        pub expected: u64,
+
+        pub actual: u64,
+}
+
+impl AuditLog
+{
+        /// The first tick `other` differs from this run in. Runs of
+        /// different lengths are compared over the shorter one.
+        pub fn first_divergence(
+                &self,
+                other: &AuditLog,
+        ) -> Option<Divergence>
+        {
+                first_divergence(&self.hashes, &other.hashes)
+        }
+
+        pub fn to_toml(&self) -> Result<String>
+        {
+                Ok(toml::to_string(self)?)
+        }
+
+        pub fn from_toml(source: &str) -> Result<Self>
+        {
+                let log: Self = toml::from_str(source)?;
+
+                if log.version != VERSION
+                {
+                        anyhow::bail!(
+                                "Audit log version {} isn't supported, expected {}",
+                                log.version,
+                                VERSION
+                        );
+                }
+
+                Ok(log)
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        pub fn save(
+                &self,
+                path: impl AsRef<std::path::Path>,
+        ) -> Result<()>
+        {
+                std::fs::write(path, self.to_toml()?)?;
+
+                Ok(())
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self>
+        {
+                Self::from_toml(&std::fs::read_to_string(path)?)
+        }
+}
+
+fn first_divergence(
+        expected: &[u64],
+        actual: &[u64],
+) -> Option<Divergence>
+{
+        expected.iter()
+                .zip(actual)
+                .position(|(a, b)| a != b)
+                .map(|tick| Divergence {
+                        tick: tick as u64,
+                        expected: expected[tick],
+                        actual: actual[tick],
+                })
+}
+
+/// Published on the event bus the first time an audit compared against
+/// another run differs from it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditEvent
+{
+        Diverged(Divergence),
+}
+
+/// The running audit, owned by the [`Engine`].
+#[derive(Default)]
+pub struct DeterminismAudit
+{
+        running: bool,
+
+        seed: u64,
+
+        hashes: Vec<u64>,
+
+        /// Hashes of the run compared against.
+        expected: Option<Vec<u64>>,
+
+        divergence: Option<Divergence>,
+
+        sources: Vec<AuditSource>,
+}
+
+impl std::fmt::Debug for DeterminismAudit
+{
+        fn fmt(
+                &self,
+                f: &mut std::fmt::Formatter<'_>,
+        ) -> std::fmt::Result
+        {
+                f.debug_struct("DeterminismAudit")
+                        .field("running", &self.running)
+                        .field("ticks", &self.hashes.len())
+                        .field("comparing", &self.expected.is_some())
+                        .field("divergence", &self.divergence)
+                        .field("sources", &self.sources.len())
+                        .finish()
+        }
+}
+
+impl DeterminismAudit
+{
+        pub fn new() -> Self
+        {
+                Self::default()
+        }
+
+        pub fn is_running(&self) -> bool
+        {
+                self.running
+        }
+
+        /// Ticks hashed since the audit started.
+        pub fn ticks(&self) -> u64
+        {
+                self.hashes.len() as u64
+        }
+
+        /// The first tick this run differed from the one compared against.
+        pub fn divergence(&self) -> Option<Divergence>
+        {
+                self.divergence
+        }
+
+        /// Hashes more state every tick, in the order the sources were
+        /// added. Both runs need the same sources.
+        pub fn add_source(
+                &mut self,
+                source: impl Fn(&Engine, &mut AuditHasher) + 'static,
+        )
+        {
+                self.sources.push(Box::new(source));
+        }
+
+        /// Hashes the tick and compares it.
+        ///
+        /// Called by the engine every time the tick advances, after the
+        /// replay fed its input.
+        pub(crate) fn update(engine: &mut Engine)
+        {
+                if !engine.audit.running
+                {
+                        return;
+                }
+
+                let tick = engine.audit.hashes.len();
+                let hash = engine.tick_hash(tick as u64);
+
+                engine.audit.hashes.push(hash);
+
+                if engine.audit.divergence.is_some()
+                {
+                        return;
+                }
+
+                let Some(&expected) = engine.audit.expected.as_ref().and_then(|e| e.get(tick))
+                else
+                {
+                        return;
+                };
+
+                if expected != hash
+                {
+                        let divergence = Divergence {
+                                tick: tick as u64,
+                                expected,
+                                actual: hash,
+                        };
+
+                        log::error!(
+                                "Run diverged at tick {}: {:016x} instead of {:016x}",
+                                tick,
+                                hash,
+                                expected
+                        );
+
+                        engine.audit.divergence = Some(divergence);
+                        engine.events.publish(AuditEvent::Diverged(divergence));
+                }
+        }
+}
+
+impl Engine
+{
+        /// Starts hashing every tick, from tick 0.
+        pub fn start_audit(&mut self)
+        {
+                log::info!("Determinism audit started");
+
+                self.audit.running = true;
+                self.audit.seed = self.rng.seed();
+                self.audit.hashes.clear();
+                self.audit.expected = None;
+                self.audit.divergence = None;
+        }
+
+        /// Starts hashing every tick and compares the hashes to `log`,
+        /// publishing [`AuditEvent::Diverged`] at the first difference.
+        /// Reseeds [`Engine::rng`] with the seed of `log`.
+        pub fn start_audit_against(
+                &mut self,
+                log: AuditLog,
+        )
+        {
+                self.rng.reseed(log.seed);
+                self.start_audit();
+                self.audit.expected = Some(log.hashes);
+        }
+
+        /// Stops the audit and returns its hashes.
+        pub fn stop_audit(&mut self) -> Option<AuditLog>
+        {
+                if !self.audit.running
+                {
+                        return None;
+                }
+
+                self.audit.running = false;
+                self.audit.expected = None;
+
+                log::info!("Determinism audit stopped after {} ticks", self.audit.hashes.len());
+
+                Some(AuditLog {
+                        version: VERSION,
+                        seed: self.audit.seed,
+                        tps: self.tps,
+                        hashes: std::mem::take(&mut self.audit.hashes),
+                })
+        }
+
+        /// Restarts the audit with the recording, see [`Replay::hashes`].
+        pub(crate) fn audit_recording(&mut self)
+        {
+                if self.audit.running
+                {
+                        self.start_audit();
+                }
+        }
+
+        /// The hashes of the audit for a finished recording.
+        pub(crate) fn audit_hashes(&self) -> Option<Vec<u64>>
+        {
+                self.audit.running.then(|| self.audit.hashes.clone())
+        }
+
+        /// Compares the playback of `replay` to its recording when it has
+        /// hashes.
+        pub(crate) fn audit_replay(
+                &mut self,
+                replay: &Replay,
+        )
+        {
+                if let Some(hashes) = &replay.hashes
+                {
+                        self.start_audit();
+                        self.audit.expected = Some(hashes.clone());
+                }
+        }
+
+        /// Whether the transform of `model` only changes on ticks. Spinning,
+        /// tweens and root motion move models by frame time, which differs
+        /// between runs.
+        fn is_tick_driven(
+                &self,
+                handle: &str,
+                model: &Model,
+        ) -> bool
+        {
+                !model.is_spinning && !self.tweens.moves(handle) && !self.animation.moves(handle)
+        }
+
+        /// Hash of what game logic sees at `tick`.
+        fn tick_hash(
+                &self,
+                tick: u64,
+        ) -> u64
+        {
+                let mut hasher = AuditHasher::default();
+
+                tick.hash(&mut hasher);
+                self.rng.fingerprint().hash(&mut hasher);
+
+                if let Some(state) = &self.state
+                {
+                        let mut models: Vec<_> = state.models.iter().collect();
+                        models.sort_by(|a, b| a.0.cmp(b.0));
+
+                        for (handle, model) in models
+                        {
+                                handle.hash(&mut hasher);
+                                model.visible.hash(&mut hasher);
+
+                                if !self.is_tick_driven(handle, model)
+                                {
+                                        continue;
+                                }
+
+                                let t = &model.transform;
+
+                                // Agents are drawn between ticks, their
+                                // position on the tick is what's stepped.
+                                let position = self.steering.position(handle).unwrap_or(t.position);
+
+                                for value in [
+                                        position.x,
+                                        position.y,
+                                        position.z,
+                                        t.rotation.s,
+                                        t.rotation.v.x,
+                                        t.rotation.v.y,
+                                        t.rotation.v.z,
+                                        t.scale.x,
+                                        t.scale.y,
+                                        t.scale.z,
+                                ]
+                                {
+                                        hasher.write_f32(value);
+                                }
+                        }
+                }
+
+                for source in &self.audit.sources
+                {
+                        source(self, &mut hasher);
+                }
+
+                hasher.finish()
+        }
+}
@@ -0,0 +1,305 @@
+//! Repeatable performance runs whose results can be compared between engine
+//! versions.
+//!
+//! [`EngineRunner::run_benchmark`] runs the app like [`EngineRunner::run`],
+//! but flies the camera along a fixed path for a given time, records every
+//! frame and exits:
+//!
+//! ```ignore
+//! let mut engine = EngineBuilder::new().build()?;
+//! engine.add_model("city", "city.glb");
+//! engine.play_camera_path(CameraPath::load("paths/city.toml")?);
+//!
+//! EngineRunner::new(engine)?.run_benchmark("city", 30.0)?;
+//! ```
+//!
+//! The path is the one set with
+//! [`Engine::play_camera_path`](crate::engine::Engine::play_camera_path),
+//! looped, or a circle around the origin without one, see
+//! [`CameraPath::orbit`]. Recording starts once the queued models were
+//! uploaded, see [`crate::upload`].
+//!
+//! Results are written to `benchmarks/<scene>-<time>.json`, the summary and
+//! every frame, and `benchmarks/<scene>-<time>.csv`, one row per frame.
+//! On the web the summary is logged instead.
+
+use crate::camera_path::CameraPath;
+use crate::engine::{Engine, EngineRunner};
+use anyhow::Result;
+use cgmath::Point3;
+use serde::{Deserialize, Serialize};
+
+/// Directory the results are written to, native only.
+pub const OUTPUT_DIR: &str = "benchmarks";
+
+/// One recorded frame.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BenchmarkFrame
+{
+        /// Seconds since recording started.
+        pub time: f32,
+
+        pub frame_ms: f32,
+
+        pub draw_calls: usize,
+
+        pub visible_models: usize,
+
+        /// Estimated GPU memory of the models, see
+        /// [`Engine::gpu_memory`](crate::engine::Engine::gpu_memory).
+        pub gpu_memory: u64,
+}
+
+/// Results of a benchmark run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchmarkReport
+{
+        pub scene: String,
+
+        /// Version of the engine crate.
+        pub engine_version: String,
+
+        /// Name and backend of the GPU.
+        pub adapter: String,
+
+        pub seconds: f32,
+
+        pub average_fps: f32,
+        pub average_ms: f32,
+        pub p50_ms: f32,
+        pub p95_ms: f32,
+        pub p99_ms: f32,
+        pub worst_ms: f32,
+
+        pub average_draw_calls: f32,
+        pub peak_gpu_memory: u64,
+
+        pub frames: Vec<BenchmarkFrame>,
+}
+
+impl BenchmarkReport
+{
+        fn new(
+                scene: String,
+                adapter: String,
+                frames: Vec<BenchmarkFrame>,
+        ) -> Self
+        {
+                let count = frames.len().max(1) as f32;
+
+                let mut times: Vec<f32> = frames.iter().map(|f| f.frame_ms).collect();
+                times.sort_by(f32::total_cmp);
+
+                let percentile = |p: f32| {
+                        times.get(((times.len() as f32 * p) as usize)
+                                .min(times.len().saturating_sub(1)))
+                                .copied()
+                                .unwrap_or(0.0)
+                };
+
+                let total_ms: f32 = times.iter().sum();
+
+                Self {
+                        scene,
+                        engine_version: env!("CARGO_PKG_VERSION").to_string(),
+                        adapter,
+                        seconds: total_ms / 1000.0,
+                        average_fps: count * 1000.0 / total_ms.max(f32::EPSILON),
+                        average_ms: total_ms / count,
+                        p50_ms: percentile(0.5),
+                        p95_ms: percentile(0.95),
+                        p99_ms: percentile(0.99),
+                        worst_ms: times.last().copied().unwrap_or(0.0),
+                        average_draw_calls: frames.iter().map(|f| f.draw_calls as f32).sum::<f32>()
+                                / count,
+                        peak_gpu_memory: frames.iter().map(|f| f.gpu_memory).max().unwrap_or(0),
+                        frames,
+                }
+        }
+
+        pub fn to_json(&self) -> Result<String>
+        {
+                Ok(serde_json::to_string_pretty(self)?)
+        }
+
+        /// The frames, one row each, with a header.
+        pub fn to_csv(&self) -> String
+        {
+                let mut csv = String::from("time,frame_ms,draw_calls,visible_models,gpu_memory\n");
+
+                for f in &self.frames
+                {
+                        csv.push_str(&format!(
+                                "{:.4},{:.4},{},{},{}\n",
+                                f.time, f.frame_ms, f.draw_calls, f.visible_models, f.gpu_memory
+                        ));
+                }
+
+                csv
+        }
+
+        /// Writes `<scene>-<time>.json` and `.csv` into `dir`.
+        #[cfg(not(target_arch = "wasm32"))]
+        pub fn save(
+                &self,
+                dir: impl AsRef<std::path::Path>,
+        ) -> Result<std::path::PathBuf>
+        {
+                let dir = dir.as_ref();
+
+                std::fs::create_dir_all(dir)?;
+
+                let seconds = std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .map(|d| d.as_secs())
+                        .unwrap_or(0);
+
+                let path = dir.join(format!("{}-{}", self.scene, seconds));
+
+                std::fs::write(path.with_extension("json"), self.to_json()?)?;
+                std::fs::write(path.with_extension("csv"), self.to_csv())?;
+
+                Ok(path.with_extension("json"))
+        }
+}
+
+/// A running benchmark, owned by the [`Engine`].
+#[derive(Debug)]
+pub struct Benchmark
+{
+        scene: String,
+
+        seconds: f32,
+
+        /// Seconds recorded so far, `None` while waiting for the uploads.
+        elapsed: Option<f32>,
+
+        frames: Vec<BenchmarkFrame>,
+}
+
+impl Benchmark
+{
+        /// Records the last frame and ends the run after its time.
+        ///
+        /// Called by the engine once per frame, after the memory was
+        /// measured.
+        pub(crate) fn update(engine: &mut Engine)
+        {
+                let Some(benchmark) = engine.benchmark.as_mut()
+                else
+                {
+                        return;
+                };
+
+                let Some(elapsed) = benchmark.elapsed
+                else
+                {
+                        if engine.state.is_some() && engine.uploads.pending() == 0
+                        {
+                                log::info!(
+                                        "Benchmark {} started, {} seconds",
+                                        benchmark.scene,
+                                        benchmark.seconds
+                                );
+
+                                benchmark.elapsed = Some(0.0);
+                                engine.camera_path.play();
+                        }
+
+                        return;
+                };
+
+                let metrics = &engine.metrics;
+                let frame = metrics.frame_time().as_secs_f32();
+
+                benchmark.frames.push(BenchmarkFrame {
+                        time: elapsed,
+                        frame_ms: frame * 1000.0,
+                        draw_calls: metrics.draw_calls(),
+                        visible_models: metrics.visible_models(),
+                        gpu_memory: engine.memory.usage().total,
+                });
+
+                benchmark.elapsed = Some(elapsed + frame);
+
+                if elapsed + frame < benchmark.seconds
+                {
+                        return;
+                }
+
+                let Some(benchmark) = engine.benchmark.take()
+                else
+                {
+                        return;
+                };
+
+                let adapter = engine.state.as_ref().map_or_else(String::new, |state| {
+                        let info = state.adapter.get_info();
+                        format!("{} ({:?})", info.name, info.backend)
+                });
+
+                let report = BenchmarkReport::new(benchmark.scene, adapter, benchmark.frames);
+
+                log::info!(
+                        "Benchmark {} finished: {:.1} fps average, {:.2} ms p99, {:.2} ms worst",
+                        report.scene,
+                        report.average_fps,
+                        report.p99_ms,
+                        report.worst_ms
+                );
+
+                #[cfg(not(target_arch = "wasm32"))]
+                match report.save(OUTPUT_DIR)
+                {
+                        Ok(path) => log::info!("Benchmark results written to {}", path.display()),
+                        Err(e) => log::error!("Failed to write the benchmark results: {}", e),
+                }
+
+                engine.stop_camera_path();
+                engine.request_exit();
+        }
+}
+
+impl Engine
+{
+        /// Flies the camera path for `seconds` once the models are uploaded,
+        /// records the frames and exits, see [`crate::benchmark`].
+        pub fn start_benchmark(
+                &mut self,
+                scene: impl Into<String>,
+                seconds: f32,
+        )
+        {
+                if self.camera_path.path.keyframes.is_empty()
+                {
+                        self.camera_path.path =
+                                CameraPath::orbit(Point3::new(0.0, 0.0, 0.0), 10.0, 3.0, 20.0);
+                }
+
+                self.camera_path.path.looping = true;
+                self.camera_path.stop(&mut self.events);
+
+                self.benchmark = Some(Benchmark {
+                        scene: scene.into(),
+                        seconds,
+                        elapsed: None,
+                        frames: Vec::new(),
+                });
+        }
+}
+
+impl EngineRunner
+{
+        /// Runs the engines like [`EngineRunner::run`] while the first one
+        /// benchmarks `scene` for `seconds`, see [`crate::benchmark`].
+        pub fn run_benchmark(
+                mut self,
+                scene: impl Into<String>,
+                seconds: f32,
+        ) -> Result<()>
+        {
+                self.engines[0].start_benchmark(scene, seconds);
+
+                self.run()
+        }
+}
@@ -1,9 +1,17 @@
+use crate::math::collision::{Aabb, Frustum, Ray};
+use crate::renderer::graph::EnvironmentUniform;
 use cgmath::*;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "input")]
+use std::collections::HashMap;
 use std::f32::consts::FRAC_PI_2;
 use std::time::Duration;
 use wgpu::util::DeviceExt;
+#[cfg(feature = "input")]
 use winit::dpi::PhysicalPosition;
+#[cfg(feature = "input")]
 use winit::event::*;
+#[cfg(feature = "input")]
 use winit::keyboard::KeyCode;
 
 #[derive(Debug)]
@@ -18,15 +26,35 @@ pub struct Camera
         pub show_dpad: bool,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct CameraConfig
 {
         pub sensitivity: f32,
         pub speed: f32,
-        pub aspect_ratio_correction: bool,
-        pub initial_aspect: Option<f32>,
-        pub aspect: f32,
+        /// How quickly the camera reaches `speed` while a key is held,
+        /// higher is snappier. `0.0` or less starts moving right away.
+        pub acceleration: f32,
+        /// How quickly the camera stops after the keys are released.
+        /// `0.0` or less stops right away.
+        pub damping: f32,
+        /// Speed multiplier while [`CameraAction::Boost`] is held.
+        pub boost: f32,
+        /// Keys moving the free-fly camera.
+        #[cfg(feature = "input")]
+        pub bindings: CameraBindings,
+        /// Width over height the scene is drawn at, whatever the window's.
+        /// The rest of the window is left black, see
+        /// [`crate::renderer::viewport`]. `None` stretches the scene over
+        /// the window.
+        pub locked_aspect: Option<f32>,
         pub fovy: Deg<f32>,
+        /// Distance of the near clip plane.
+        pub znear: f32,
+        /// Distance of the far clip plane, anything further is clipped.
+        pub zfar: f32,
+        /// Stores depth from 1 at the near plane to 0 at the far plane, see
+        /// [`Projection::reversed_z`]. Only read when the engine starts.
+        pub reversed_z: bool,
 }
 
 impl Default for CameraConfig
@@ -36,14 +64,102 @@ impl Default for CameraConfig
                 Self {
                         sensitivity: 2.0,
                         speed: 30.0,
-                        aspect_ratio_correction: true,
-                        initial_aspect: Some(1.0),
-                        aspect: 1.0,
+                        acceleration: 10.0,
+                        damping: 8.0,
+                        boost: 3.0,
+                        #[cfg(feature = "input")]
+                        bindings: CameraBindings::default(),
+                        locked_aspect: None,
                         fovy: Deg(60.0),
+                        znear: 0.1,
+                        zfar: 1000.0,
+                        reversed_z: false,
                 }
         }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CameraAction
+{
+        Forward,
+        Backward,
+        Left,
+        Right,
+        Up,
+        Down,
+        /// Multiplies the speed by [`CameraConfig::boost`].
+        Boost,
+}
+
+/// Action map of the free-fly camera.
+///
+/// Games that use the same keys for their own input unbind them, or use
+/// [`CameraBindings::none`] when the camera shouldn't move at all.
+#[cfg(feature = "input")]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CameraBindings
+{
+        keys: HashMap<KeyCode, CameraAction>,
+}
+
+#[cfg(feature = "input")]
+impl Default for CameraBindings
+{
+        /// WASD, Space and Left Shift to move, Left Ctrl to boost.
+        fn default() -> Self
+        {
+                Self::none()
+                        .bind(KeyCode::KeyW, CameraAction::Forward)
+                        .bind(KeyCode::KeyS, CameraAction::Backward)
+                        .bind(KeyCode::KeyA, CameraAction::Left)
+                        .bind(KeyCode::KeyD, CameraAction::Right)
+                        .bind(KeyCode::Space, CameraAction::Up)
+                        .bind(KeyCode::ShiftLeft, CameraAction::Down)
+                        .bind(KeyCode::ControlLeft, CameraAction::Boost)
+        }
+}
+
+#[cfg(feature = "input")]
+impl CameraBindings
+{
+        /// No keys bound, the camera only turns with the mouse.
+        pub fn none() -> Self
+        {
+                Self {
+                        keys: HashMap::new(),
+                }
+        }
+
+        /// Binds `key` to `action`, replacing what it was bound to before.
+        /// An action can have several keys.
+        pub fn bind(
+                mut self,
+                key: KeyCode,
+                action: CameraAction,
+        ) -> Self
+        {
+                self.keys.insert(key, action);
+                self
+        }
+
+        pub fn unbind(
+                mut self,
+                key: KeyCode,
+        ) -> Self
+        {
+                self.keys.remove(&key);
+                self
+        }
+
+        pub fn action(
+                &self,
+                key: KeyCode,
+        ) -> Option<CameraAction>
+        {
+                self.keys.get(&key).copied()
+        }
+}
+
 impl Default for Camera
 {
         fn default() -> Self
@@ -74,13 +190,12 @@ impl Default for Camera
 
 impl Camera
 {
+        #[cfg(feature = "ui")]
         pub fn ui(
                 &mut self,
                 ui: &mut egui::Ui,
         )
         {
-                let mut aspect = self.config.aspect_ratio_correction;
-
                 egui::CollapsingHeader::new("Camera Settings")
                         .default_open(true)
                         .show(ui, |ui| {
@@ -105,6 +220,30 @@ impl Camera
                                                         .step_by(0.1));
                                                         ui.end_row();
 
+                                                        ui.label("Acceleration");
+                                                        ui.add(egui::Slider::new(
+                                                                &mut self.config.acceleration,
+                                                                0.0..=50.0,
+                                                        )
+                                                        .step_by(0.1));
+                                                        ui.end_row();
+
+                                                        ui.label("Damping");
+                                                        ui.add(egui::Slider::new(
+                                                                &mut self.config.damping,
+                                                                0.0..=50.0,
+                                                        )
+                                                        .step_by(0.1));
+                                                        ui.end_row();
+
+                                                        ui.label("Boost");
+                                                        ui.add(egui::Slider::new(
+                                                                &mut self.config.boost,
+                                                                1.0..=10.0,
+                                                        )
+                                                        .step_by(0.1));
+                                                        ui.end_row();
+
                                                         ui.label("FOV Y");
                                                         ui.add(egui::Slider::new(
                                                                 &mut self.config.fovy.0,
@@ -120,8 +259,25 @@ impl Camera
                                                         ui.checkbox(&mut self.locked_in, "");
                                                         ui.end_row();
 
-                                                        ui.label("Aspect Ratio Correction");
-                                                        ui.checkbox(&mut aspect, "");
+                                                        ui.label("Locked Aspect");
+                                                        ui.horizontal(|ui| {
+                                                                let mut locked =
+                                                                        self.config.locked_aspect.is_some();
+
+                                                                if ui.checkbox(&mut locked, "").changed()
+                                                                {
+                                                                        self.config.locked_aspect =
+                                                                                locked.then_some(16.0 / 9.0);
+                                                                }
+
+                                                                if let Some(aspect) =
+                                                                        self.config.locked_aspect.as_mut()
+                                                                {
+                                                                        ui.add(egui::DragValue::new(aspect)
+                                                                                .range(0.25..=4.0)
+                                                                                .speed(0.01));
+                                                                }
+                                                        });
                                                         ui.end_row();
 
                                                         ui.label("ZNear");
@@ -138,9 +294,16 @@ impl Camera
                                                         ui.add(egui::Slider::new(
                                                                 &mut self.projection.zfar,
                                                                 (self.projection.znear + 0.01)
-                                                                        ..=1000.0,
+                                                                        ..=100_000.0,
                                                         )
-                                                        .step_by(0.01));
+                                                        .logarithmic(true));
+                                                        ui.end_row();
+
+                                                        ui.label("Reversed Z");
+                                                        ui.label(self
+                                                                .projection
+                                                                .reversed_z
+                                                                .to_string());
                                                 });
                                 });
 
@@ -226,33 +389,21 @@ impl Camera
                                 });
                         });
 
-                if aspect != self.config.aspect_ratio_correction
-                {
-                        log::info!("Aspect ratio correction changed");
-
-                        if !aspect
-                        {
-                                self.config.aspect = self.projection.aspect;
-                                self.projection.aspect = self.config.initial_aspect.unwrap();
-                        }
-                        else
-                        {
-                                self.projection.aspect = self.config.aspect;
-                        }
-
-                        self.config.aspect_ratio_correction = aspect;
-                }
-
                 self.projection.fovy = Deg(self.config.fovy.0).into();
         }
 
         pub fn new() -> Self
         {
-                let core = CameraCore::new((0.0, 5.0, 10.0), Deg(-90.0), Deg(-20.0));
+                Self::with_config(CameraConfig::default())
+        }
 
-                let projection = Projection::new(Deg(60.0), 0.1, 1000.0);
+        /// Camera with the field of view and clip planes of `config`.
+        pub fn with_config(config: CameraConfig) -> Self
+        {
+                let core = CameraCore::new((0.0, 5.0, 10.0), Deg(-90.0), Deg(-20.0));
 
-                let config = CameraConfig::default();
+                let mut projection = Projection::new(config.fovy, config.znear, config.zfar);
+                projection.reversed_z = config.reversed_z;
 
                 let controller = CameraController::new();
 
@@ -271,6 +422,26 @@ impl Camera
                 }
         }
 
+        /// Feeds a key to the controller through
+        /// [`CameraConfig::bindings`], returns whether it was bound.
+        #[cfg(feature = "input")]
+        pub fn handle_key(
+                &mut self,
+                key: KeyCode,
+                pressed: bool,
+        ) -> bool
+        {
+                match self.config.bindings.action(key)
+                {
+                        Some(action) =>
+                        {
+                                self.controller.handle_action(action, pressed);
+                                true
+                        }
+                        None => false,
+                }
+        }
+
         pub fn update(
                 &mut self,
                 dt: &Duration,
@@ -281,6 +452,63 @@ impl Camera
                 self.uniform.update_view_proj(&self.core, &self.projection);
         }
 
+        /// The ray from the camera through `x` and `y` in normalized device
+        /// coordinates, `(0.0, 0.0)` is the middle of the screen, for
+        /// picking with [`Engine::raycast`](crate::engine::Engine::raycast).
+        pub fn ray(
+                &self,
+                x: f32,
+                y: f32,
+        ) -> Ray
+        {
+                let view_proj = self.projection.calc_matrix() * self.core.calc_matrix();
+
+                Ray::from_ndc(x, y, &view_proj, self.projection.reversed_z)
+                        .unwrap_or(Ray::new(self.core.position, self.core.forward()))
+        }
+
+        /// Where `point` is on screen in normalized device coordinates, the
+        /// inverse of [`Camera::ray`]. `None` behind the camera.
+        pub fn project(
+                &self,
+                point: Point3<f32>,
+        ) -> Option<(f32, f32)>
+        {
+                let view_proj = self.projection.calc_matrix() * self.core.calc_matrix();
+                let clip = view_proj * point.to_homogeneous();
+
+                (clip.w > f32::EPSILON).then(|| (clip.x / clip.w, clip.y / clip.w))
+        }
+
+        /// What the camera sees, to skip things off screen.
+        pub fn frustum(&self) -> Frustum
+        {
+                Frustum::from_matrix(&(self.projection.calc_matrix() * self.core.calc_matrix()))
+        }
+
+        /// Moves the camera back along its view direction until `bounds`
+        /// fill the view, e.g. [`Model::world_bounds`](crate::model::Model::world_bounds).
+        /// Keeps where it's looking.
+        pub fn frame(
+                &mut self,
+                bounds: &Aabb,
+        )
+        {
+                let center = bounds.center();
+                let radius = (bounds.size().magnitude() * 0.5).max(0.001);
+
+                // The narrower of the two fields of view, so the bounds fit
+                // either way.
+                let half_fovy = self.projection.fovy.0 * 0.5;
+                let half_fovx = (half_fovy.tan() * self.projection.aspect).atan();
+                let half_fov = half_fovy.min(half_fovx);
+
+                let distance = (radius / half_fov.sin()).max(self.projection.znear + radius);
+
+                self.core.position = center - self.core.forward() * distance;
+                self.uniform.update_view_proj(&self.core, &self.projection);
+        }
+
         pub fn get_buffer(
                 &self,
                 device: &wgpu::Device,
@@ -298,20 +526,7 @@ impl Camera
                 device: &wgpu::Device,
         ) -> wgpu::BindGroupLayout
         {
-                device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                        entries: &[wgpu::BindGroupLayoutEntry {
-                                binding: 0,
-                                visibility: wgpu::ShaderStages::VERTEX
-                                        | wgpu::ShaderStages::FRAGMENT,
-                                ty: wgpu::BindingType::Buffer {
-                                        ty: wgpu::BufferBindingType::Uniform,
-                                        has_dynamic_offset: false,
-                                        min_binding_size: None,
-                                },
-                                count: None,
-                        }],
-                        label: Some("camera_bind_group_layout"),
-                })
+                create_camera_bind_group_layout(device)
         }
 
         pub fn get_bind_group(
@@ -333,6 +548,23 @@ impl Camera
         }
 }
 
+pub fn create_camera_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout
+{
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Uniform,
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                        },
+                        count: None,
+                }],
+                label: Some("camera_bind_group_layout"),
+        })
+}
+
 // We need this for Rust to store our data correctly for the shaders
 #[repr(C)]
 // This is so we can store this in a buffer
@@ -343,6 +575,13 @@ pub struct CameraUniform
         // to convert the Matrix4 into a 4x4 f32 array
         pub view_position: [f32; 4],
         pub view_proj: [[f32; 4]; 4],
+        /// Lighting, fog and exposure, set by the engine every frame.
+        pub environment: EnvironmentUniform,
+        /// Last frame's `view_proj`, without the jitter, see
+        /// [`TemporalPass`](crate::renderer::temporal::TemporalPass).
+        pub previous_view_proj: [[f32; 4]; 4],
+        /// Offset of `view_proj` in NDC in `xy`, for TAA.
+        pub jitter: [f32; 4],
 }
 
 impl CameraUniform
@@ -353,6 +592,9 @@ impl CameraUniform
                 Self {
                         view_proj: Matrix4::identity().into(),
                         view_position: [0.0; 4],
+                        environment: EnvironmentUniform::default(),
+                        previous_view_proj: Matrix4::identity().into(),
+                        jitter: [0.0; 4],
                 }
         }
 
@@ -375,6 +617,15 @@ pub const OPENGL_TO_WGPU_MATRIX: Matrix4<f32> = Matrix4::from_cols(
     Vector4::new(0.0, 0.0, 0.5, 1.0),
 );
 
+/// Maps depth `z` to `1 - z`, see [`Projection::reversed_z`].
+#[rustfmt::skip]
+const REVERSE_Z_MATRIX: Matrix4<f32> = Matrix4::from_cols(
+    Vector4::new(1.0, 0.0, 0.0, 0.0),
+    Vector4::new(0.0, 1.0, 0.0, 0.0),
+    Vector4::new(0.0, 0.0, -1.0, 0.0),
+    Vector4::new(0.0, 0.0, 1.0, 1.0),
+);
+
 const SAFE_FRAC_PI_2: f32 = FRAC_PI_2 - 0.0001;
 
 #[derive(Debug)]
@@ -400,17 +651,24 @@ impl CameraCore
                 }
         }
 
-        pub fn calc_matrix(&self) -> Matrix4<f32>
+        /// Unit vector the camera is looking along.
+        pub fn forward(&self) -> Vector3<f32>
         {
                 let (sin_pitch, cos_pitch) = self.pitch.0.sin_cos();
                 let (sin_yaw, cos_yaw) = self.yaw.0.sin_cos();
 
-                Matrix4::look_to_rh(
-                        self.position,
-                        Vector3::new(cos_pitch * cos_yaw, sin_pitch, cos_pitch * sin_yaw)
-                                .normalize(),
-                        Vector3::unit_y(),
-                )
+                Vector3::new(cos_pitch * cos_yaw, sin_pitch, cos_pitch * sin_yaw).normalize()
+        }
+
+        /// Unit vector pointing to the right side of the screen.
+        pub fn right(&self) -> Vector3<f32>
+        {
+                self.forward().cross(Vector3::unit_y()).normalize()
+        }
+
+        pub fn calc_matrix(&self) -> Matrix4<f32>
+        {
+                Matrix4::look_to_rh(self.position, self.forward(), Vector3::unit_y())
         }
 }
 
@@ -421,6 +679,13 @@ pub struct Projection
         pub fovy: Rad<f32>,
         pub znear: f32,
         pub zfar: f32,
+        /// Maps the near plane to depth 1 and the far plane to 0. Float
+        /// depth is most precise near 0 while perspective depth is least
+        /// precise far away, reversing it pairs the two so distant geometry
+        /// stops z-fighting on large maps. The pipelines must compare with
+        /// `Greater` and clear depth to 0, see
+        /// [`PipelineManager::reversed_z`](crate::renderer::pipeline::PipelineManager::reversed_z).
+        pub reversed_z: bool,
 }
 
 impl Projection
@@ -436,6 +701,7 @@ impl Projection
                         fovy: fovy.into(),
                         znear,
                         zfar,
+                        reversed_z: false,
                 }
         }
 
@@ -450,11 +716,78 @@ impl Projection
 
         pub fn calc_matrix(&self) -> Matrix4<f32>
         {
-                OPENGL_TO_WGPU_MATRIX * perspective(self.fovy, self.aspect, self.znear, self.zfar)
+                let matrix = OPENGL_TO_WGPU_MATRIX
+                        * perspective(self.fovy, self.aspect, self.znear, self.zfar);
+
+                if self.reversed_z
+                {
+                        REVERSE_Z_MATRIX * matrix
+                }
+                else
+                {
+                        matrix
+                }
+        }
+}
+
+/// A projection without perspective, things keep their size at any
+/// distance, e.g. for top-down views like the
+/// [minimap](crate::ui::minimap).
+#[derive(Debug, Clone, Copy)]
+pub struct Orthographic
+{
+        /// Half the height of the view in world units.
+        pub half_height: f32,
+        pub aspect: f32,
+        pub znear: f32,
+        pub zfar: f32,
+        /// See [`Projection::reversed_z`].
+        pub reversed_z: bool,
+}
+
+impl Orthographic
+{
+        pub fn new(
+                half_height: f32,
+                znear: f32,
+                zfar: f32,
+        ) -> Self
+        {
+                Self {
+                        half_height,
+                        aspect: 1.0,
+                        znear,
+                        zfar,
+                        reversed_z: false,
+                }
+        }
+
+        pub fn calc_matrix(&self) -> Matrix4<f32>
+        {
+                let half_width = self.half_height * self.aspect;
+
+                let matrix = OPENGL_TO_WGPU_MATRIX
+                        * ortho(
+                                -half_width,
+                                half_width,
+                                -self.half_height,
+                                self.half_height,
+                                self.znear,
+                                self.zfar,
+                        );
+
+                if self.reversed_z
+                {
+                        REVERSE_Z_MATRIX * matrix
+                }
+                else
+                {
+                        matrix
+                }
         }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct CameraController
 {
         pub amount_left: f32,
@@ -466,6 +799,17 @@ pub struct CameraController
         pub rotate_horizontal: f32,
         pub rotate_vertical: f32,
         pub scroll: f32,
+        pub boost: bool,
+        /// Current velocity, eased towards the one the amounts ask for.
+        pub velocity: Vector3<f32>,
+}
+
+impl Default for CameraController
+{
+        fn default() -> Self
+        {
+                Self::new()
+        }
 }
 
 impl CameraController
@@ -482,49 +826,41 @@ impl CameraController
                         rotate_horizontal: 0.0,
                         rotate_vertical: 0.0,
                         scroll: 0.0,
+                        boost: false,
+                        velocity: Vector3::zero(),
                 }
         }
 
-        pub fn handle_key(
+        /// Lets go of every key, the camera keeps coasting with its
+        /// velocity.
+        pub fn release_all(&mut self)
+        {
+                self.amount_left = 0.0;
+                self.amount_right = 0.0;
+                self.amount_forward = 0.0;
+                self.amount_backward = 0.0;
+                self.amount_up = 0.0;
+                self.amount_down = 0.0;
+                self.boost = false;
+        }
+
+        pub fn handle_action(
                 &mut self,
-                key: KeyCode,
+                action: CameraAction,
                 pressed: bool,
-        ) -> bool
+        )
         {
                 let amount = if pressed { 1.0 } else { 0.0 };
-                match key
+
+                match action
                 {
-                        KeyCode::KeyW =>
-                        {
-                                self.amount_forward = amount;
-                                true
-                        }
-                        KeyCode::KeyS =>
-                        {
-                                self.amount_backward = amount;
-                                true
-                        }
-                        KeyCode::KeyA =>
-                        {
-                                self.amount_left = amount;
-                                true
-                        }
-                        KeyCode::KeyD =>
-                        {
-                                self.amount_right = amount;
-                                true
-                        }
-                        KeyCode::Space =>
-                        {
-                                self.amount_up = amount;
-                                true
-                        }
-                        KeyCode::ShiftLeft =>
-                        {
-                                self.amount_down = amount;
-                                true
-                        }
-                        _ => false,
+                        CameraAction::Forward => self.amount_forward = amount,
+                        CameraAction::Backward => self.amount_backward = amount,
+                        CameraAction::Left => self.amount_left = amount,
+                        CameraAction::Right => self.amount_right = amount,
+                        CameraAction::Up => self.amount_up = amount,
+                        CameraAction::Down => self.amount_down = amount,
+                        CameraAction::Boost => self.boost = pressed,
                 }
         }
 
@@ -538,6 +874,7 @@ impl CameraController
                 self.rotate_vertical = mouse_dy as f32;
         }
 
+        #[cfg(feature = "input")]
         pub fn handle_scroll(
                 &mut self,
                 delta: &MouseScrollDelta,
@@ -562,14 +899,48 @@ impl CameraController
         {
                 let dt = dt.as_secs_f32();
 
-                // Move forward/backward and left/right
+                // Move forward/backward, left/right and up/down. Since we
+                // don't use roll, up is always the world's y axis.
                 let (yaw_sin, yaw_cos) = camera.yaw.0.sin_cos();
                 let forward = Vector3::new(yaw_cos, 0.0, yaw_sin).normalize();
                 let right = Vector3::new(-yaw_sin, 0.0, yaw_cos).normalize();
-                camera.position +=
-                        forward * (self.amount_forward - self.amount_backward) * config.speed * dt;
-                camera.position +=
-                        right * (self.amount_right - self.amount_left) * config.speed * dt;
+
+                let speed = if self.boost
+                {
+                        config.speed * config.boost
+                }
+                else
+                {
+                        config.speed
+                };
+
+                let target = (forward * (self.amount_forward - self.amount_backward)
+                        + right * (self.amount_right - self.amount_left)
+                        + Vector3::unit_y() * (self.amount_up - self.amount_down))
+                        * speed;
+
+                // Accelerate towards the target while moving, damp when
+                // the keys are released. Frame rate independent.
+                let rate = if target.magnitude2() > 0.0
+                {
+                        config.acceleration
+                }
+                else
+                {
+                        config.damping
+                };
+
+                let t = if rate > 0.0
+                {
+                        1.0 - (-rate * dt).exp()
+                }
+                else
+                {
+                        1.0
+                };
+
+                self.velocity += (target - self.velocity) * t;
+                camera.position += self.velocity * dt;
 
                 // Move in/out (aka. "zoom")
                 // Note: this isn't an actual zoom. The camera's position
@@ -582,10 +953,6 @@ impl CameraController
                         scrollward * self.scroll * config.speed * config.sensitivity * dt;
                 self.scroll = 0.0;
 
-                // Move up/down. Since we don't use roll, we can just
-                // modify the y coordinate directly.
-                camera.position.y += (self.amount_up - self.amount_down) * config.speed * dt;
-
                 // Rotate
                 camera.yaw += Rad(self.rotate_horizontal) * config.sensitivity * dt;
                 camera.pitch += Rad(-self.rotate_vertical) * config.sensitivity * dt;
@@ -0,0 +1,192 @@
+//! Camera constraints, e.g. a chase camera.
+//!
+//! Constraints are applied in order every frame, after the behaviors and
+//! the camera controller ran, so they win over free-fly input:
+//!
+//! ```ignore
+//! engine.camera_follow("snake_head", (0.0, 30.0, 25.0), 3.0);
+//! engine.camera_look_at("snake_head");
+//! ```
+//!
+//! Constraints on models that aren't loaded yet are skipped until they are.
+
+use crate::camera::Camera;
+use crate::engine::Engine;
+use crate::model::Model;
+use cgmath::{Deg, InnerSpace, Rad, Vector3};
+use std::collections::HashMap;
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CameraConstraint
+{
+        /// Keeps the camera at the model's position plus `offset`.
+        ///
+        /// `stiffness` is how quickly the camera catches up, higher is
+        /// snappier. `0.0` or less moves it there right away.
+        FollowTarget
+        {
+                handle: String,
+                offset: Vector3<f32>,
+                stiffness: f32,
+        },
+        /// Turns the camera towards the model.
+        LookAt
+        {
+                handle: String
+        },
+}
+
+/// Constraints of the engine's camera, owned by the [`Engine`].
+#[derive(Debug, Default)]
+pub struct CameraConstraints
+{
+        constraints: Vec<CameraConstraint>,
+}
+
+impl CameraConstraints
+{
+        pub fn new() -> Self
+        {
+                Self::default()
+        }
+
+        pub fn push(
+                &mut self,
+                constraint: CameraConstraint,
+        )
+        {
+                self.constraints.push(constraint);
+        }
+
+        pub fn clear(&mut self)
+        {
+                self.constraints.clear();
+        }
+
+        pub fn is_empty(&self) -> bool
+        {
+                self.constraints.is_empty()
+        }
+
+        pub fn iter(&self) -> impl Iterator<Item = &CameraConstraint>
+        {
+                self.constraints.iter()
+        }
+
+        /// Moves and turns `camera`, then updates its uniform.
+        ///
+        /// Called by the engine once per frame.
+        pub(crate) fn apply(
+                &self,
+                camera: &mut Camera,
+                models: &HashMap<String, Model>,
+                dt: &Duration,
+        )
+        {
+                if self.constraints.is_empty()
+                {
+                        return;
+                }
+
+                for constraint in &self.constraints
+                {
+                        match constraint
+                        {
+                                CameraConstraint::FollowTarget {
+                                        handle,
+                                        offset,
+                                        stiffness,
+                                } =>
+                                {
+                                        let Some(model) = models.get(handle)
+                                        else
+                                        {
+                                                continue;
+                                        };
+
+                                        let target = model.transform.position + offset;
+
+                                        // Frame rate independent exponential smoothing.
+                                        let t = if *stiffness > 0.0
+                                        {
+                                                1.0 - (-stiffness * dt.as_secs_f32()).exp()
+                                        }
+                                        else
+                                        {
+                                                1.0
+                                        };
+
+                                        camera.core.position += (target - camera.core.position) * t;
+                                }
+                                CameraConstraint::LookAt {
+                                        handle,
+                                } =>
+                                {
+                                        let Some(model) = models.get(handle)
+                                        else
+                                        {
+                                                continue;
+                                        };
+
+                                        let direction =
+                                                model.transform.position - camera.core.position;
+
+                                        if direction.magnitude2() <= f32::EPSILON
+                                        {
+                                                continue;
+                                        }
+
+                                        let direction = direction.normalize();
+                                        let max_pitch = Rad::from(Deg(89.0)).0;
+
+                                        camera.core.yaw = Rad(direction.z.atan2(direction.x));
+                                        camera.core.pitch = Rad(direction
+                                                .y
+                                                .asin()
+                                                .clamp(-max_pitch, max_pitch));
+                                }
+                        }
+                }
+
+                camera.uniform
+                        .update_view_proj(&camera.core, &camera.projection);
+        }
+}
+
+impl Engine
+{
+        /// Makes the camera follow the model `handle`, see
+        /// [`CameraConstraint::FollowTarget`].
+        pub fn camera_follow(
+                &mut self,
+                handle: impl Into<String>,
+                offset: impl Into<Vector3<f32>>,
+                stiffness: f32,
+        )
+        {
+                self.camera_constraints
+                        .push(CameraConstraint::FollowTarget {
+                                handle: handle.into(),
+                                offset: offset.into(),
+                                stiffness,
+                        });
+        }
+
+        /// Keeps the camera pointed at the model `handle`.
+        pub fn camera_look_at(
+                &mut self,
+                handle: impl Into<String>,
+        )
+        {
+                self.camera_constraints.push(CameraConstraint::LookAt {
+                        handle: handle.into(),
+                });
+        }
+
+        /// Gives the camera back to the camera controller.
+        pub fn clear_camera_constraints(&mut self)
+        {
+                self.camera_constraints.clear();
+        }
+}
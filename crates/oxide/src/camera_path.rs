@@ -0,0 +1,763 @@
+//! Camera fly-throughs along Catmull-Rom splines.
+//!
+//! A [`CameraPath`] is a list of keyframes with time stamps. Playing it moves
+//! the camera through every keyframe at its time, which is meant for
+//! cinematics and for benchmarks, since the camera takes the same route
+//! every run:
+//!
+//! ```ignore
+//! let path = CameraPath::load("paths/flyby.toml")?;
+//! engine.play_camera_path(path);
+//! ```
+//!
+//! [`CameraPathEvent::Finished`] carries the frame times of the run. Paths
+//! are edited in the "Camera Path" window of the debug UI, which draws the
+//! spline and the keyframes as gizmos, and stored as TOML, see
+//! [`CameraPath::to_toml`].
+
+use crate::camera::{Camera, CameraCore};
+use crate::engine::Engine;
+use crate::events::EventBus;
+use crate::renderer::debug_draw::DebugLines;
+use anyhow::Result;
+use cgmath::{Point3, Rad, Vector3};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::{PI, TAU};
+use std::time::Duration;
+
+/// Bumped when the file format changes.
+const VERSION: u32 = 1;
+
+/// Line segments per spline segment drawn by the gizmos.
+const GIZMO_STEPS: usize = 16;
+
+const PATH_COLOR: [f32; 4] = [0.3, 0.9, 0.9, 1.0];
+const KEYFRAME_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const SELECTED_COLOR: [f32; 4] = [1.0, 0.6, 0.1, 1.0];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CameraKeyframe
+{
+        /// Seconds since the start of the path.
+        pub time: f32,
+
+        pub position: [f32; 3],
+
+        /// Radians, like [`CameraCore::yaw`].
+        pub yaw: f32,
+
+        /// Radians, like [`CameraCore::pitch`].
+        pub pitch: f32,
+}
+
+impl CameraKeyframe
+{
+        /// Keyframe at the current pose of `camera`.
+        pub fn from_camera(
+                time: f32,
+                camera: &CameraCore,
+        ) -> Self
+        {
+                Self {
+                        time,
+                        position: camera.position.into(),
+                        yaw: camera.yaw.0,
+                        pitch: camera.pitch.0,
+                }
+        }
+
+        /// Moves `camera` to the keyframe.
+        pub fn apply(
+                &self,
+                camera: &mut CameraCore,
+        )
+        {
+                camera.position = self.position.into();
+                camera.yaw = Rad(self.yaw);
+                camera.pitch = Rad(self.pitch);
+        }
+
+        fn forward(&self) -> Vector3<f32>
+        {
+                Vector3::new(
+                        self.yaw.cos() * self.pitch.cos(),
+                        self.pitch.sin(),
+                        self.yaw.sin() * self.pitch.cos(),
+                )
+        }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CameraPath
+{
+        pub version: u32,
+
+        /// Starts over at the first keyframe instead of finishing.
+        #[serde(default)]
+        pub looping: bool,
+
+        /// Sorted by time.
+        pub keyframes: Vec<CameraKeyframe>,
+}
+
+impl Default for CameraPath
+{
+        fn default() -> Self
+        {
+                Self {
+                        version: VERSION,
+                        looping: false,
+                        keyframes: Vec::new(),
+                }
+        }
+}
+
+impl CameraPath
+{
+        pub fn new() -> Self
+        {
+                Self::default()
+        }
+
+        /// Inserts `keyframe` in time order and returns its index.
+        pub fn add_keyframe(
+                &mut self,
+                keyframe: CameraKeyframe,
+        ) -> usize
+        {
+                let index = self.keyframes.partition_point(|k| k.time <= keyframe.time);
+
+                self.keyframes.insert(index, keyframe);
+
+                index
+        }
+
+        /// A looping circle of `radius` around `center`, `height` above it,
+        /// facing `center` and taking `seconds` per round.
+        pub fn orbit(
+                center: Point3<f32>,
+                radius: f32,
+                height: f32,
+                seconds: f32,
+        ) -> Self
+        {
+                const STEPS: usize = 12;
+
+                let keyframes = (0..=STEPS)
+                        .map(|i| {
+                                let angle = i as f32 / STEPS as f32 * TAU;
+                                let position = center
+                                        + Vector3::new(
+                                                radius * angle.cos(),
+                                                height,
+                                                radius * angle.sin(),
+                                        );
+
+                                CameraKeyframe {
+                                        time: i as f32 / STEPS as f32 * seconds,
+                                        position: position.into(),
+                                        yaw: wrap(angle + PI),
+                                        pitch: (-height).atan2(radius),
+                                }
+                        })
+                        .collect();
+
+                Self {
+                        version: VERSION,
+                        looping: true,
+                        keyframes,
+                }
+        }
+
+        /// Sorts the keyframes again after their times were edited.
+        pub fn sort(&mut self)
+        {
+                self.keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+        }
+
+        /// Time of the last keyframe.
+        pub fn duration(&self) -> Duration
+        {
+                Duration::from_secs_f32(self.keyframes.last().map_or(0.0, |k| k.time.max(0.0)))
+        }
+
+        /// Pose at `time` seconds, clamped to the first and last keyframe.
+        /// `None` without keyframes.
+        pub fn sample(
+                &self,
+                time: f32,
+        ) -> Option<CameraKeyframe>
+        {
+                let keys = &self.keyframes;
+
+                let first = keys.first()?;
+                let last = keys.last()?;
+
+                if keys.len() == 1 || time <= first.time
+                {
+                        return Some(CameraKeyframe {
+                                time,
+                                ..*first
+                        });
+                }
+
+                if time >= last.time
+                {
+                        return Some(CameraKeyframe {
+                                time,
+                                ..*last
+                        });
+                }
+
+                // Segment from keys[i] to keys[i + 1].
+                let i = keys.partition_point(|k| k.time <= time) - 1;
+
+                let k1 = keys[i];
+                let k2 = keys[i + 1];
+                let k0 = keys[i.saturating_sub(1)];
+                let k3 = keys[(i + 2).min(keys.len() - 1)];
+
+                let span = k2.time - k1.time;
+                let t = if span > f32::EPSILON
+                {
+                        (time - k1.time) / span
+                }
+                else
+                {
+                        1.0
+                };
+
+                let position = [0, 1, 2].map(|c| {
+                        catmull_rom(
+                                k0.position[c],
+                                k1.position[c],
+                                k2.position[c],
+                                k3.position[c],
+                                t,
+                        )
+                });
+
+                // Yaw is unwrapped so the camera turns the short way round.
+                let y1 = k1.yaw;
+                let y0 = y1 + wrap(k0.yaw - y1);
+                let y2 = y1 + wrap(k2.yaw - y1);
+                let y3 = y2 + wrap(k3.yaw - y2);
+
+                Some(CameraKeyframe {
+                        time,
+                        position,
+                        yaw: catmull_rom(y0, y1, y2, y3, t),
+                        pitch: catmull_rom(k0.pitch, k1.pitch, k2.pitch, k3.pitch, t),
+                })
+        }
+
+        /// Adds the spline and the keyframes to `lines`, the keyframe at
+        /// `selected` highlighted.
+        pub fn gizmos(
+                &self,
+                lines: &mut DebugLines,
+                selected: Option<usize>,
+        )
+        {
+                for pair in self.keyframes.windows(2)
+                {
+                        let mut previous = Point3::from(pair[0].position);
+
+                        for step in 1..=GIZMO_STEPS
+                        {
+                                let time = pair[0].time
+                                        + (pair[1].time - pair[0].time) * step as f32
+                                                / GIZMO_STEPS as f32;
+
+                                let Some(sample) = self.sample(time)
+                                else
+                                {
+                                        break;
+                                };
+
+                                let point = Point3::from(sample.position);
+                                lines.line(previous, point, PATH_COLOR);
+                                previous = point;
+                        }
+                }
+
+                for (i, keyframe) in self.keyframes.iter().enumerate()
+                {
+                        let color = if selected == Some(i)
+                        {
+                                SELECTED_COLOR
+                        }
+                        else
+                        {
+                                KEYFRAME_COLOR
+                        };
+                        let p = Point3::from(keyframe.position);
+                        let size = 0.25;
+
+                        for axis in [Vector3::unit_x(), Vector3::unit_y(), Vector3::unit_z()]
+                        {
+                                lines.line(p - axis * size, p + axis * size, color);
+                        }
+
+                        lines.line(p, p + keyframe.forward(), color);
+                }
+        }
+
+        pub fn to_toml(&self) -> Result<String>
+        {
+                Ok(toml::to_string(self)?)
+        }
+
+        pub fn from_toml(source: &str) -> Result<Self>
+        {
+                let mut path: Self = toml::from_str(source)?;
+
+                if path.version != VERSION
+                {
+                        anyhow::bail!(
+                                "Camera path version {} isn't supported, expected {}",
+                                path.version,
+                                VERSION
+                        );
+                }
+
+                path.sort();
+
+                Ok(path)
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        pub fn save(
+                &self,
+                path: impl AsRef<std::path::Path>,
+        ) -> Result<()>
+        {
+                std::fs::write(path, self.to_toml()?)?;
+
+                Ok(())
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self>
+        {
+                Self::from_toml(&std::fs::read_to_string(path)?)
+        }
+}
+
+fn catmull_rom(
+        p0: f32,
+        p1: f32,
+        p2: f32,
+        p3: f32,
+        t: f32,
+) -> f32
+{
+        let t2 = t * t;
+        let t3 = t2 * t;
+
+        0.5 * (2.0 * p1
+                + (p2 - p0) * t
+                + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2
+                + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t3)
+}
+
+/// Angle in `-PI..PI`.
+fn wrap(angle: f32) -> f32
+{
+        (angle + PI).rem_euclid(TAU) - PI
+}
+
+/// Frame times of a finished playback.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CameraPathStats
+{
+        pub frames: u32,
+        pub duration: Duration,
+        pub worst_frame: Duration,
+}
+
+impl CameraPathStats
+{
+        pub fn average_frame(&self) -> Duration
+        {
+                self.duration / self.frames.max(1)
+        }
+
+        pub fn average_fps(&self) -> f32
+        {
+                self.frames as f32 / self.duration.as_secs_f32().max(f32::EPSILON)
+        }
+}
+
+/// Published on the event bus when playback starts and ends.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CameraPathEvent
+{
+        Started,
+        /// Also published when playback is stopped early.
+        Finished(CameraPathStats),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Playback
+{
+        elapsed: f32,
+        stats: CameraPathStats,
+        started: bool,
+}
+
+/// The path being played or edited, owned by the [`Engine`].
+#[derive(Debug)]
+pub struct CameraPathSystem
+{
+        pub path: CameraPath,
+
+        /// Draws the path while the debug UI is open.
+        pub show_gizmos: bool,
+
+        playback: Option<Playback>,
+
+        /// Keyframe selected in the editor.
+        #[cfg(feature = "ui")]
+        selected: Option<usize>,
+
+        /// File the editor saves to and loads from.
+        #[cfg(all(feature = "ui", not(target_arch = "wasm32")))]
+        file: String,
+}
+
+impl Default for CameraPathSystem
+{
+        fn default() -> Self
+        {
+                Self {
+                        path: CameraPath::new(),
+                        show_gizmos: true,
+                        playback: None,
+                        #[cfg(feature = "ui")]
+                        selected: None,
+                        #[cfg(all(feature = "ui", not(target_arch = "wasm32")))]
+                        file: "camera_path.toml".to_string(),
+                }
+        }
+}
+
+impl CameraPathSystem
+{
+        pub fn new() -> Self
+        {
+                Self::default()
+        }
+
+        pub fn is_playing(&self) -> bool
+        {
+                self.playback.is_some()
+        }
+
+        /// Seconds since playback started.
+        pub fn elapsed(&self) -> Option<f32>
+        {
+                self.playback.map(|p| p.elapsed)
+        }
+
+        /// Plays [`CameraPathSystem::path`] from the start.
+        pub fn play(&mut self)
+        {
+                self.playback = Some(Playback {
+                        elapsed: 0.0,
+                        stats: CameraPathStats::default(),
+                        started: false,
+                });
+        }
+
+        pub(crate) fn stop(
+                &mut self,
+                events: &mut EventBus,
+        )
+        {
+                if let Some(playback) = self.playback.take()
+                {
+                        log::info!(
+                                "Camera path finished: {} frames, {:.2} fps average, worst frame {:?}",
+                                playback.stats.frames,
+                                playback.stats.average_fps(),
+                                playback.stats.worst_frame
+                        );
+
+                        events.publish(CameraPathEvent::Finished(playback.stats));
+                }
+        }
+
+        /// Moves `camera` along the path, then updates its uniform.
+        ///
+        /// Called by the engine once per frame, after the camera
+        /// constraints.
+        pub(crate) fn apply(
+                &mut self,
+                camera: &mut Camera,
+                dt: &Duration,
+                events: &mut EventBus,
+        )
+        {
+                let Some(playback) = &mut self.playback
+                else
+                {
+                        return;
+                };
+
+                if !playback.started
+                {
+                        // The first frame's time was spent before playback.
+                        playback.started = true;
+                        events.publish(CameraPathEvent::Started);
+                }
+                else
+                {
+                        playback.elapsed += dt.as_secs_f32();
+                        playback.stats.frames += 1;
+                        playback.stats.duration += *dt;
+                        playback.stats.worst_frame = playback.stats.worst_frame.max(*dt);
+                }
+
+                let duration = self.path.duration().as_secs_f32();
+                let mut time = playback.elapsed;
+
+                if self.path.looping && duration > 0.0
+                {
+                        time %= duration;
+                }
+
+                if let Some(sample) = self.path.sample(time)
+                {
+                        sample.apply(&mut camera.core);
+
+                        camera.uniform
+                                .update_view_proj(&camera.core, &camera.projection);
+                }
+
+                if !self.path.looping && playback.elapsed >= duration
+                {
+                        self.stop(events);
+                }
+        }
+
+        /// Adds the path to `lines` when [`CameraPathSystem::show_gizmos`]
+        /// is set.
+        #[cfg(feature = "ui")]
+        pub(crate) fn gizmos(
+                &self,
+                lines: &mut DebugLines,
+        )
+        {
+                if self.show_gizmos
+                {
+                        self.path.gizmos(lines, self.selected);
+                }
+        }
+
+        /// The "Camera Path" editor window.
+        #[cfg(feature = "ui")]
+        pub(crate) fn ui(
+                &mut self,
+                ctx: &egui::Context,
+                camera: &mut Camera,
+                events: &mut EventBus,
+        )
+        {
+                egui::Window::new("Camera Path")
+                        .default_open(false)
+                        .show(ctx, |ui| {
+                                self.playback_ui(ui, events);
+
+                                ui.separator();
+
+                                self.keyframes_ui(ui, camera);
+
+                                #[cfg(not(target_arch = "wasm32"))]
+                                {
+                                        ui.separator();
+                                        self.file_ui(ui);
+                                }
+                        });
+        }
+
+        #[cfg(feature = "ui")]
+        fn playback_ui(
+                &mut self,
+                ui: &mut egui::Ui,
+                events: &mut EventBus,
+        )
+        {
+                ui.horizontal(|ui| {
+                        if self.is_playing()
+                        {
+                                if ui.button("Stop").clicked()
+                                {
+                                        self.stop(events);
+                                }
+                        }
+                        else if ui
+                                .add_enabled(
+                                        self.path.keyframes.len() > 1,
+                                        egui::Button::new("Play"),
+                                )
+                                .clicked()
+                        {
+                                self.play();
+                        }
+
+                        ui.checkbox(&mut self.path.looping, "Loop");
+                        ui.checkbox(&mut self.show_gizmos, "Gizmos");
+                });
+
+                let duration = self.path.duration().as_secs_f32();
+
+                match self.playback
+                {
+                        Some(playback) => ui.label(format!(
+                                "{:.2} / {:.2} s, {} frames",
+                                playback.elapsed, duration, playback.stats.frames
+                        )),
+                        None => ui.label(format!("{:.2} s", duration)),
+                };
+        }
+
+        #[cfg(feature = "ui")]
+        fn keyframes_ui(
+                &mut self,
+                ui: &mut egui::Ui,
+                camera: &mut Camera,
+        )
+        {
+                let mut remove = None;
+                let mut resort = false;
+
+                egui::Grid::new("camera_path_keyframes")
+                        .striped(true)
+                        .show(ui, |ui| {
+                                for (i, keyframe) in self.path.keyframes.iter_mut().enumerate()
+                                {
+                                        if ui.selectable_label(
+                                                self.selected == Some(i),
+                                                format!("#{}", i),
+                                        )
+                                        .clicked()
+                                        {
+                                                self.selected = Some(i);
+                                        }
+
+                                        resort |= ui
+                                                .add(egui::DragValue::new(&mut keyframe.time)
+                                                        .speed(0.05)
+                                                        .range(0.0..=f32::MAX)
+                                                        .suffix(" s"))
+                                                .changed();
+
+                                        if ui.button("View").clicked()
+                                        {
+                                                keyframe.apply(&mut camera.core);
+                                                camera.uniform.update_view_proj(
+                                                        &camera.core,
+                                                        &camera.projection,
+                                                );
+                                                self.selected = Some(i);
+                                        }
+
+                                        if ui.button("Set to camera").clicked()
+                                        {
+                                                *keyframe = CameraKeyframe::from_camera(
+                                                        keyframe.time,
+                                                        &camera.core,
+                                                );
+                                        }
+
+                                        if ui.button("Delete").clicked()
+                                        {
+                                                remove = Some(i);
+                                        }
+
+                                        ui.end_row();
+                                }
+                        });
+
+                if let Some(i) = remove
+                {
+                        self.path.keyframes.remove(i);
+                        self.selected = None;
+                }
+
+                if resort
+                {
+                        self.path.sort();
+                }
+
+                if ui.button("Add keyframe at camera").clicked()
+                {
+                        let time = self.path.keyframes.last().map_or(0.0, |k| k.time + 1.0);
+
+                        self.selected = Some(self
+                                .path
+                                .add_keyframe(CameraKeyframe::from_camera(time, &camera.core)));
+                }
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        #[cfg(feature = "ui")]
+        fn file_ui(
+                &mut self,
+                ui: &mut egui::Ui,
+        )
+        {
+                ui.horizontal(|ui| {
+                        ui.text_edit_singleline(&mut self.file);
+
+                        if ui.button("Save").clicked()
+                                && let Err(e) = self.path.save(&self.file)
+                        {
+                                log::error!("Failed to save camera path {}: {}", self.file, e);
+                        }
+
+                        if ui.button("Load").clicked()
+                        {
+                                match CameraPath::load(&self.file)
+                                {
+                                        Ok(path) =>
+                                        {
+                                                self.path = path;
+                                                self.selected = None;
+                                        }
+                                        Err(e) => log::error!(
+                                                "Failed to load camera path {}: {}",
+                                                self.file,
+                                                e
+                                        ),
+                                }
+                        }
+                });
+        }
+}
+
+impl Engine
+{
+        /// Flies the camera along `path`, replacing the edited path.
+        pub fn play_camera_path(
+                &mut self,
+                path: CameraPath,
+        )
+        {
+                self.camera_path.path = path;
+                self.camera_path.play();
+        }
+
+        /// Stops playback early, publishing [`CameraPathEvent::Finished`].
+        pub fn stop_camera_path(&mut self)
+        {
+                self.camera_path.stop(&mut self.events);
+        }
+
+        /// The path shown in the editor.
+        pub fn camera_path_mut(&mut self) -> &mut CameraPath
+        {
+                &mut self.camera_path.path
+        }
+}
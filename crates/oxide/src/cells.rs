@@ -0,0 +1,462 @@
+//! Worlds too large to keep loaded, divided into cells loaded and unloaded
+//! as the camera moves.
+//!
+//! A cell is a box in the world and the models in it. Cells the camera
+//! comes within [`CellStreaming::load_distance`] of are loaded in the
+//! background, nearest first and at most
+//! [`CellStreaming::max_loading`] at once, see [`crate::loading`], their
+//! models uploaded within the frame's budget, see [`crate::upload`]. Cells
+//! the camera leaves farther than [`CellStreaming::unload_distance`] behind
+//! are unloaded again, with [`Engine::unload_model`]. The unload distance
+//! is larger, so a camera moving along the border doesn't load and unload
+//! the same cell over and over:
+//!
+//! ```ignore
+//! let mut engine = EngineBuilder::new()
+//!         .with_cell_streaming(150.0, 200.0)
+//!         .build()?;
+//!
+//! engine.add_cell(
+//!         "harbor",
+//!         Aabb::new(Point3::new(0.0, -10.0, 0.0), Point3::new(100.0, 50.0, 100.0)),
+//! );
+//! engine.add_cell_model(
+//!         "harbor",
+//!         CellModel::new("harbor_crane", "crane.glb")
+//!                 .with_transform(Transform::from_position([20.0, 0.0, 35.0])),
+//! );
+//! ```
+//!
+//! [`CellEvent`]s are published as cells finish loading and are unloaded.
+//! Streamed models aren't added with [`Engine::spawn_model`], so save games
+//! don't spawn them again, their cells do.
+
+use crate::engine::Engine;
+use crate::geometry::bounds::Aabb;
+use crate::loading::{Inbox, Loaded, receive, spawn_loader};
+use crate::resources::import::ImportOptions;
+use crate::transform::Transform;
+use cgmath::{MetricSpace, Point3};
+use std::collections::{BTreeMap, HashMap};
+
+/// Published on the event bus, with the name of the cell.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CellEvent
+{
+        /// Every model of the cell is loaded or failed to load. Models
+        /// waiting for their upload appear shortly after.
+        Loaded(String),
+        Unloaded(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CellState
+{
+        Unloaded,
+        Loading,
+        Loaded,
+}
+
+/// A model of a cell, loaded from `file` under `handle` and placed at
+/// `transform`.
+#[derive(Debug, Clone)]
+pub struct CellModel
+{
+        pub handle: String,
+        pub file: String,
+        pub options: ImportOptions,
+        pub transform: Transform,
+}
+
+impl CellModel
+{
+        pub fn new(
+                handle: impl Into<String>,
+                file: impl Into<String>,
+        ) -> Self
+        {
+                Self {
+                        handle: handle.into(),
+                        file: file.into(),
+                        options: ImportOptions::default(),
+                        transform: Transform::IDENTITY,
+                }
+        }
+
+        pub fn with_options(
+                mut self,
+                options: ImportOptions,
+        ) -> Self
+        {
+                self.options = options;
+                self
+        }
+
+        pub fn with_transform(
+                mut self,
+                transform: Transform,
+        ) -> Self
+        {
+                self.transform = transform;
+                self
+        }
+}
+
+/// A part of the world loaded as a whole.
+#[derive(Debug)]
+pub struct Cell
+{
+        /// The part of the world the cell covers, distances are measured to
+        /// it.
+        pub bounds: Aabb,
+
+        /// Models added while the cell is loaded appear the next time it
+        /// loads.
+        pub models: Vec<CellModel>,
+
+        state: CellState,
+
+        /// Where the loader hands the models over while loading.
+        inbox: Option<Inbox>,
+
+        /// Models the loader hasn't handed over yet.
+        remaining: usize,
+}
+
+impl Cell
+{
+        pub fn new(bounds: Aabb) -> Self
+        {
+                Self {
+                        bounds,
+                        models: Vec::new(),
+                        state: CellState::Unloaded,
+                        inbox: None,
+                        remaining: 0,
+                }
+        }
+
+        pub fn state(&self) -> CellState
+        {
+                self.state
+        }
+
+        /// From `point` to the nearest point of the cell, 0 inside it.
+        pub fn distance(
+                &self,
+                point: Point3<f32>,
+        ) -> f32
+        {
+                self.bounds.closest_point(point).distance(point)
+        }
+}
+
+/// The cells and when they're loaded, owned by the [`Engine`].
+#[derive(Debug)]
+pub struct CellStreaming
+{
+        /// Cells nearer to the camera are loaded.
+        pub load_distance: f32,
+
+        /// Loaded cells farther from the camera are unloaded. Larger than
+        /// the load distance.
+        pub unload_distance: f32,
+
+        /// Cells loading at the same time at most.
+        pub max_loading: usize,
+
+        cells: BTreeMap<String, Cell>,
+
+        /// Transforms of streamed models waiting for their upload, by
+        /// handle.
+        placements: HashMap<String, Transform>,
+}
+
+impl Default for CellStreaming
+{
+        fn default() -> Self
+        {
+                Self {
+                        load_distance: 100.0,
+                        unload_distance: 120.0,
+                        max_loading: 1,
+                        cells: BTreeMap::new(),
+                        placements: HashMap::new(),
+                }
+        }
+}
+
+impl CellStreaming
+{
+        pub fn new() -> Self
+        {
+                Self::default()
+        }
+
+        pub fn cell(
+                &self,
+                name: &str,
+        ) -> Option<&Cell>
+        {
+                self.cells.get(name)
+        }
+
+        /// The cells by name.
+        pub fn cells(&self) -> impl Iterator<Item = (&str, &Cell)>
+        {
+                self.cells.iter().map(|(name, cell)| (name.as_str(), cell))
+        }
+
+        /// Names of the cells in `state`.
+        pub fn in_state(
+                &self,
+                state: CellState,
+        ) -> Vec<&str>
+        {
+                self.cells()
+                        .filter(|(_, cell)| cell.state == state)
+                        .map(|(name, _)| name)
+                        .collect()
+        }
+
+        /// Loads and unloads cells around the camera and places the models
+        /// that arrived.
+        ///
+        /// Called by the engine once per frame, after the loading screen.
+        pub(crate) fn update(engine: &mut Engine)
+        {
+                if engine.loading.is_active()
+                {
+                        return;
+                }
+
+                let Some(camera) = engine
+                        .state
+                        .as_ref()
+                        .map(|state| state.camera.core.position)
+                else
+                {
+                        return;
+                };
+
+                let mut events = Vec::new();
+                let mut uploads = Vec::new();
+
+                let streaming = &mut engine.cells;
+
+                for (name, cell) in &mut streaming.cells
+                {
+                        let Some(inbox) = &cell.inbox
+                        else
+                        {
+                                continue;
+                        };
+
+                        for loaded in receive(inbox)
+                        {
+                                match loaded
+                                {
+                                        Loaded::Model(handle, data) =>
+                                        {
+                                                if let Some(model) = cell
+                                                        .models
+                                                        .iter()
+                                                        .find(|m| m.handle == handle)
+                                                {
+                                                        streaming.placements.insert(
+                                                                handle.clone(),
+                                                                model.transform,
+                                                        );
+                                                }
+
+                                                uploads.push((handle, data));
+                                        }
+                                        Loaded::Failed(_) =>
+                                        {}
+                                        Loaded::Splash(_) => continue,
+                                }
+
+                                cell.remaining = cell.remaining.saturating_sub(1);
+                        }
+
+                        if cell.remaining == 0
+                        {
+                                cell.state = CellState::Loaded;
+                                cell.inbox = None;
+                                events.push(CellEvent::Loaded(name.clone()));
+                        }
+                }
+
+                for (handle, data) in uploads
+                {
+                        engine.queue_upload(handle, data);
+                }
+
+                if let Some(state) = engine.state.as_mut()
+                {
+                        engine.cells.placements.retain(|handle, transform| {
+                                match state.models.get_mut(handle)
+                                {
+                                        Some(model) =>
+                                        {
+                                                model.transform = *transform;
+                                                false
+                                        }
+                                        None => true,
+                                }
+                        });
+                }
+
+                let streaming = &engine.cells;
+
+                let unload: Vec<String> = streaming
+                        .cells
+                        .iter()
+                        .filter(|(_, cell)| cell.state != CellState::Unloaded)
+                        .filter(|(_, cell)| cell.distance(camera) > streaming.unload_distance)
+                        .map(|(name, _)| name.clone())
+                        .collect();
+
+                for name in unload
+                {
+                        engine.unload_cell(&name);
+                        events.push(CellEvent::Unloaded(name));
+                }
+
+                let streaming = &mut engine.cells;
+
+                let loading = streaming
+                        .cells
+                        .values()
+                        .filter(|cell| cell.state == CellState::Loading)
+                        .count();
+
+                let mut load: Vec<(f32, &String)> = streaming
+                        .cells
+                        .iter()
+                        .filter(|(_, cell)| cell.state == CellState::Unloaded)
+                        .map(|(name, cell)| (cell.distance(camera), name))
+                        .filter(|(distance, _)| *distance <= streaming.load_distance)
+                        .collect();
+                load.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+                let load: Vec<String> = load
+                        .into_iter()
+                        .take(streaming.max_loading.saturating_sub(loading))
+                        .map(|(_, name)| name.clone())
+                        .collect();
+
+                for name in load
+                {
+                        let Some(cell) = streaming.cells.get_mut(&name)
+                        else
+                        {
+                                continue;
+                        };
+
+                        let models = cell
+                                .models
+                                .iter()
+                                .map(|m| (m.handle.clone(), m.file.clone(), m.options.clone()))
+                                .collect();
+
+                        log::info!("Loading cell {}", name);
+
+                        cell.state = CellState::Loading;
+                        cell.remaining = cell.models.len();
+                        cell.inbox = Some(spawn_loader(models, None));
+                }
+
+                for event in events
+                {
+                        engine.events.publish(event);
+                }
+        }
+}
+
+impl Engine
+{
+        /// Adds a cell covering `bounds`, replacing and unloading any cell
+        /// named `name`, see [`crate::cells`].
+        pub fn add_cell(
+                &mut self,
+                name: impl Into<String>,
+                bounds: Aabb,
+        )
+        {
+                let name = name.into();
+
+                self.remove_cell(&name);
+                self.cells.cells.insert(name, Cell::new(bounds));
+        }
+
+        /// Adds `model` to the cell `cell`, returns false when there's no
+        /// such cell.
+        pub fn add_cell_model(
+                &mut self,
+                cell: &str,
+                model: CellModel,
+        ) -> bool
+        {
+                match self.cells.cells.get_mut(cell)
+                {
+                        Some(cell) =>
+                        {
+                                cell.models.push(model);
+                                true
+                        }
+                        None => false,
+                }
+        }
+
+        /// Unloads and removes the cell `name`, returns false when there's
+        /// no such cell.
+        pub fn remove_cell(
+                &mut self,
+                name: &str,
+        ) -> bool
+        {
+                self.unload_cell(name);
+                self.cells.cells.remove(name).is_some()
+        }
+
+        pub fn cell_state(
+                &self,
+                name: &str,
+        ) -> Option<CellState>
+        {
+                self.cells.cell(name).map(Cell::state)
+        }
+
+        /// Removes the models of the cell `name` from the scene, also the
+        /// ones still loading. It loads again once the camera comes near.
+        pub fn unload_cell(
+                &mut self,
+                name: &str,
+        )
+        {
+                let Some(cell) = self.cells.cells.get_mut(name)
+                else
+                {
+                        return;
+                };
+
+                if cell.state != CellState::Unloaded
+                {
+                        log::info!("Unloading cell {}", name);
+                }
+
+                cell.state = CellState::Unloaded;
+                cell.inbox = None;
+                cell.remaining = 0;
+
+                let handles: Vec<String> = cell.models.iter().map(|m| m.handle.clone()).collect();
+
+                for handle in handles
+                {
+                        self.cells.placements.remove(&handle);
+                        self.uploads.cancel(&handle);
+                        self.unload_model(&handle);
+                }
+        }
+}
@@ -1,4 +1,14 @@
+use crate::camera::CameraConfig;
+use crate::dpi::DpiPolicy;
 use crate::engine::FillMode;
+use crate::log::LogConfig;
+use crate::renderer::graph::EnvironmentSettings;
+use crate::renderer::grid::GridSettings;
+use crate::renderer::surface::ColorSpace;
+use crate::renderer::temporal::TemporalSettings;
+use crate::snapping::SnapSettings;
+use crate::ui::UiSettings;
+use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -6,8 +16,75 @@ pub struct Config
 {
         /// Polygon fill mode, depends on the platforms wgpu features.
         pub fill_mode: FillMode,
+        /// Draws the debug UI, ignored without the `ui` feature.
         pub enable_debug: bool,
         pub debug_toggle_key: Option<u32>,
+        /// Focuses the debug UI for keyboard navigation, or gives the
+        /// keyboard back to the game.
+        pub ui_focus_key: Option<u32>,
+        /// Frames the models selected in the debug UI, see
+        /// [`Camera::frame`](crate::camera::Camera::frame). F by default.
+        pub frame_key: Option<u32>,
+        /// Outlines the model under the cursor while the debug UI is shown,
+        /// see [`crate::ui::hover`].
+        pub highlight_hovered: bool,
+        /// Reference grid and world axes.
+        pub grid: GridSettings,
+        /// Snapping of models edited in the debug UI, see
+        /// [`crate::snapping`].
+        pub snapping: SnapSettings,
+        /// TAA and motion blur, they need to be enabled before the engine
+        /// starts to create the HDR target.
+        pub temporal: TemporalSettings,
+        /// Background of the scene, saved with it.
+        pub environment: EnvironmentSettings,
+        /// Camera the engine starts with.
+        #[serde(skip)]
+        pub camera: CameraConfig,
+        /// Surface color space, applied when the window is created.
+        pub color_space: ColorSpace,
+        /// Id of the canvas element drawn to on the web.
+        #[serde(skip)]
+        pub canvas_id: String,
+        /// Debug UI scale and layout, kept in the config file.
+        pub ui: UiSettings,
+        /// Pixels per point of the UI and the web canvas, see
+        /// [`crate::dpi`].
+        pub dpi: DpiPolicy,
+        /// Starts with the depth pre-pass on, see
+        /// [`DepthPrePass`](crate::renderer::graph::DepthPrePass).
+        pub depth_prepass: bool,
+        /// Directory compiled pipelines are kept in between runs, native
+        /// only.
+        pub pipeline_cache: Option<std::path::PathBuf>,
+        /// Directory decoded model files are kept in between runs, see
+        /// [`crate::resources::cache`]. Native only.
+        pub import_cache: Option<std::path::PathBuf>,
+        /// Largest side of the textures of models the engine loads, larger
+        /// ones are scaled down, see
+        /// [`TextureLimits::max_size`](crate::texture::TextureLimits::max_size).
+        pub max_texture_size: Option<u32>,
+        /// Logger settings applied when the engine is built, see
+        /// [`crate::log`]. `None` keeps the logger as it is.
+        pub log: Option<LogConfig>,
+        #[serde(skip)]
+        file: Option<ConfigFile>,
+}
+
+/// Contents of the config file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Persisted
+{
+        #[serde(default)]
+        ui: UiSettings,
+}
+
+#[derive(Debug)]
+struct ConfigFile
+{
+        path: String,
+        /// What the file holds, so unchanged settings aren't written again.
+        saved: UiSettings,
 }
 
 impl Config
@@ -18,6 +95,137 @@ impl Config
                         fill_mode: FillMode::Fill,
                         enable_debug: false,
                         debug_toggle_key: None,
+                        ui_focus_key: None,
+                        frame_key: Some(winit::keyboard::KeyCode::KeyF as u32),
+                        highlight_hovered: true,
+                        grid: GridSettings::default(),
+                        snapping: SnapSettings::default(),
+                        temporal: TemporalSettings::default(),
+                        environment: EnvironmentSettings::default(),
+                        camera: CameraConfig::default(),
+                        color_space: ColorSpace::default(),
+                        canvas_id: "canvas".to_string(),
+                        ui: UiSettings::default(),
+                        dpi: DpiPolicy::default(),
+                        depth_prepass: false,
+                        pipeline_cache: None,
+                        import_cache: None,
+                        max_texture_size: None,
+                        log: None,
+                        file: None,
+                }
+        }
+
+        /// Reads the settings kept between runs from `path` and writes them
+        /// back there when they change. A missing file keeps the defaults.
+        ///
+        /// The file is TOML on native and a `localStorage` entry on the web.
+        pub fn load_file(
+                &mut self,
+                path: impl Into<String>,
+        ) -> Result<()>
+        {
+                let path = path.into();
+
+                let persisted: Persisted = match Self::read(&path)?
+                {
+                        Some(source) => toml::from_str(&source)?,
+                        None => Persisted::default(),
+                };
+
+                self.ui = persisted.ui;
+                self.file = Some(ConfigFile {
+                        path,
+                        saved: persisted.ui,
+                });
+
+                Ok(())
+        }
+
+        /// Writes the config file if the settings changed since it was read.
+        ///
+        /// Called by the engine after the debug UI, while nothing is being
+        /// dragged.
+        pub(crate) fn save_file_if_changed(&mut self)
+        {
+                let Some(file) = &mut self.file
+                else
+                {
+                        return;
+                };
+
+                if file.saved == self.ui
+                {
+                        return;
                 }
+
+                let persisted = Persisted {
+                        ui: self.ui,
+                };
+
+                let result = toml::to_string(&persisted)
+                        .map_err(anyhow::Error::from)
+                        .and_then(|source| Self::write(&file.path, &source));
+
+                match result
+                {
+                        Ok(()) => file.saved = self.ui,
+                        Err(e) =>
+                        {
+                                log::error!("Failed to write config file {}: {}", file.path, e);
+
+                                // Don't retry every frame.
+                                self.file = None;
+                        }
+                }
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        fn read(path: &str) -> Result<Option<String>>
+        {
+                match std::fs::read_to_string(path)
+                {
+                        Ok(source) => Ok(Some(source)),
+                        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+                        Err(e) => Err(e.into()),
+                }
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        fn write(
+                path: &str,
+                source: &str,
+        ) -> Result<()>
+        {
+                Ok(std::fs::write(path, source)?)
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        fn storage() -> Result<web_sys::Storage>
+        {
+                use anyhow::Context;
+
+                web_sys::window()
+                        .and_then(|w| w.local_storage().ok().flatten())
+                        .context("localStorage is unavailable")
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        fn read(path: &str) -> Result<Option<String>>
+        {
+                Self::storage()?
+                        .get_item(&format!("oxide.config.{}", path))
+                        .map_err(|e| anyhow::anyhow!("Failed to read {}: {:?}", path, e))
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        fn write(
+                path: &str,
+                source: &str,
+        ) -> Result<()>
+        {
+                Self::storage()?
+                        .set_item(&format!("oxide.config.{}", path), source)
+                        .map_err(|e| anyhow::anyhow!("Failed to write {}: {:?}", path, e))
         }
 }
@@ -0,0 +1,217 @@
+//! Crash reports written when the app panics, native only.
+//!
+//! [`install`] sets a panic hook that, next to the usual message on
+//! stderr, writes a directory for every panic into the reports directory:
+//!
+//! - `report.txt`: the panic message and location, the thread, the backtrace
+//!   and the GPU adapter,
+//! - `config.toml`: the engine's [`Config`](crate::config::Config) when it was
+//!   built,
+//! - `log.txt`: the last [`LOG_LINES`] lines logged, see [`crate::log`],
+//! - `screenshot.png`: the last frame handed to [`set_screenshot`], if any.
+//!
+//! `#[oxide_main]` installs it into `crash-reports` next to the working
+//! directory, mirroring the browser console hook on the web:
+//!
+//! ```ignore
+//! oxide::crash::install("crash-reports");
+//!
+//! // Keep a frame around for the report now and then.
+//! engine.on_render(move |frame| {
+//!         if frame_count % 600 == 0
+//!         {
+//!                 let readback = Readback::texture(frame.device, frame.encoder, &target);
+//!
+//!                 frame.read_back(readback, move |result| {
+//!                         if let Ok(pixels) = result
+//!                         {
+//!                                 oxide::crash::set_screenshot(width, height, pixels);
+//!                         }
+//!                 });
+//!         }
+//! });
+//! ```
+
+use std::backtrace::Backtrace;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, Once};
+
+/// Log lines kept in a report.
+pub const LOG_LINES: usize = 200;
+
+/// A frame for the next report, tightly packed RGBA8 rows.
+#[derive(Debug, Clone)]
+struct Screenshot
+{
+        width: u32,
+        height: u32,
+        pixels: Vec<u8>,
+}
+
+/// What the reports hold next to the panic.
+#[derive(Debug)]
+struct Context
+{
+        dir: Option<PathBuf>,
+        adapter: Option<String>,
+        config: Option<String>,
+        screenshot: Option<Screenshot>,
+}
+
+static CONTEXT: Mutex<Context> = Mutex::new(Context {
+        dir: None,
+        adapter: None,
+        config: None,
+        screenshot: None,
+});
+
+static INSTALL: Once = Once::new();
+
+/// The context, also after a panic while it was held.
+fn context() -> MutexGuard<'static, Context>
+{
+        CONTEXT.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Writes a crash report into `dir` on every panic, keeping the panic hook
+/// that was set before. Installed once, later calls only change the
+/// directory.
+pub fn install(dir: impl Into<PathBuf>)
+{
+        context().dir = Some(dir.into());
+
+        INSTALL.call_once(|| {
+                let previous = std::panic::take_hook();
+
+                std::panic::set_hook(Box::new(move |info| {
+                        previous(info);
+
+                        match write_report(info)
+                        {
+                                Ok(Some(path)) =>
+                                {
+                                        eprintln!("Crash report written to {}", path.display())
+                                }
+                                Ok(None) =>
+                                {}
+                                Err(e) => eprintln!("Failed to write the crash report: {}", e),
+                        }
+                }));
+        });
+}
+
+/// Keeps `pixels`, `width` by `height` tightly packed RGBA8 rows, for the
+/// next report. Replaces the frame kept before.
+pub fn set_screenshot(
+        width: u32,
+        height: u32,
+        pixels: Vec<u8>,
+)
+{
+        if pixels.len() as u64 != width as u64 * height as u64 * 4
+        {
+                log::warn!("Ignoring a {}x{} screenshot of {} bytes", width, height, pixels.len());
+                return;
+        }
+
+        context().screenshot = Some(Screenshot {
+                width,
+                height,
+                pixels,
+        });
+}
+
+/// Records the adapter the engine renders with.
+pub(crate) fn set_adapter(info: &wgpu::AdapterInfo)
+{
+        context().adapter = Some(format!("{:#?}", info));
+}
+
+/// Records the engine's config.
+pub(crate) fn set_config(config: &crate::config::Config)
+{
+        context().config =
+                Some(toml::to_string_pretty(config).unwrap_or_else(|_| format!("{:#?}", config)));
+}
+
+/// Writes the report of the panic `info`, `None` without a directory.
+fn write_report(info: &std::panic::PanicHookInfo) -> std::io::Result<Option<PathBuf>>
+{
+        // Gathered before the context is locked, the logger locks its own
+        // state.
+        let backtrace = Backtrace::force_capture();
+        let lines = crate::log::recent_lines();
+
+        let context = context();
+
+        let Some(dir) = &context.dir
+        else
+        {
+                return Ok(None);
+        };
+
+        let path = report_dir(dir)?;
+
+        let mut report = std::fs::File::create(path.join("report.txt"))?;
+
+        writeln!(report, "{}", info)?;
+        writeln!(report, "Thread: {}", std::thread::current().name().unwrap_or("<unnamed>"))?;
+        writeln!(report, "Version: {} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))?;
+        writeln!(report, "Platform: {} {}", std::env::consts::OS, std::env::consts::ARCH)?;
+        writeln!(report)?;
+        writeln!(report, "Adapter: {}", context.adapter.as_deref().unwrap_or("<none>"))?;
+        writeln!(report)?;
+        writeln!(report, "Backtrace:\n{}", backtrace)?;
+
+        if let Some(config) = &context.config
+        {
+                std::fs::write(path.join("config.toml"), config)?;
+        }
+
+        let mut log = std::fs::File::create(path.join("log.txt"))?;
+
+        for line in &lines[lines.len().saturating_sub(LOG_LINES)..]
+        {
+                writeln!(log, "{}", line)?;
+        }
+
+        if let Some(screenshot) = &context.screenshot
+        {
+                image::save_buffer(
+                        path.join("screenshot.png"),
+                        &screenshot.pixels,
+                        screenshot.width,
+                        screenshot.height,
+                        image::ColorType::Rgba8,
+                )
+                .map_err(std::io::Error::other)?;
+        }
+
+        Ok(Some(path))
+}
+
+/// A new directory in `dir` named after the time, numbered when several
+/// panics happen in the same second.
+fn report_dir(dir: &Path) -> std::io::Result<PathBuf>
+{
+        let seconds = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+
+        std::fs::create_dir_all(dir)?;
+
+        let mut path = dir.join(format!("crash-{}", seconds));
+        let mut n = 1;
+
+        while path.exists()
+        {
+                n += 1;
+                path = dir.join(format!("crash-{}-{}", seconds, n));
+        }
+
+        std::fs::create_dir(&path)?;
+
+        Ok(path)
+}
@@ -0,0 +1,71 @@
+//! How points, the unit of the UI, map to pixels.
+//!
+//! A display's scale factor is its physical pixels per point, e.g. 2 on
+//! most high-DPI screens. Native windows report it and are sized in
+//! physical pixels, the web reports it as the device pixel ratio and sizes
+//! the page in CSS pixels, which are points. One [`DpiPolicy`] picks the
+//! pixels per point for both the 3D surface and egui, so the UI is laid
+//! out and its text rasterized at the pixels it's drawn at:
+//!
+//! - The web canvas is the page size in points times the policy's factor.
+//! - egui draws at the factor times the debug UI scale.
+//!
+//! Native windows always present at their physical size, there the policy
+//! only changes the size of the UI.
+
+use serde::{Deserialize, Serialize};
+use winit::window::Window;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum DpiPolicy
+{
+        /// One pixel per physical pixel of the display, the sharpest.
+        #[default]
+        Physical,
+        /// One pixel per point, what the web drew at before. Cheaper on
+        /// high-DPI displays, and blurrier.
+        Logical,
+        /// A fixed number of pixels per point, whatever the display's.
+        Custom(f32),
+}
+
+impl DpiPolicy
+{
+        /// Pixels per point on a display with `scale_factor`.
+        pub fn factor(
+                &self,
+                scale_factor: f64,
+        ) -> f32
+        {
+                match *self
+                {
+                        DpiPolicy::Physical => scale_factor as f32,
+                        DpiPolicy::Logical => 1.0,
+                        DpiPolicy::Custom(factor) if factor > 0.0 => factor,
+                        DpiPolicy::Custom(_) => 1.0,
+                }
+        }
+
+        /// Pixels per point of the display `window` is on.
+        pub fn window_factor(
+                &self,
+                window: &Window,
+        ) -> f32
+        {
+                self.factor(scale_factor(window))
+        }
+}
+
+/// Physical pixels per point of the display `window` is on.
+pub fn scale_factor(#[allow(unused_variables)] window: &Window) -> f64
+{
+        #[cfg(target_arch = "wasm32")]
+        {
+                web_sys::window().map_or(1.0, |w| w.device_pixel_ratio())
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+                window.scale_factor()
+        }
+}
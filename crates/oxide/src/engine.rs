@@ -8,17 +8,19 @@
 //!
 //! # Key Concepts
 //!
-//! ## Singleton Engine
-//! Only one engine instance is allowed per process. Attempting to create
-//! multiple instances will result in a panic. This simplifies GPU resource
-//! management and event loop handling.
+//! ## Several Engines
+//! Every [`Engine`] owns its window and GPU state, so a process can build as
+//! many as it needs, e.g. for multi-window tools or tests. winit allows one
+//! event loop per process, [`EngineRunner::with_engines`] drives several
+//! engines on it. Engines create their own `wgpu` instance unless one is
+//! shared with [`EngineBuilder::with_instance`].
 //!
 //! ## EngineHandler
 //! Users define their engine behavior by implementing [`EngineHandler`]
 //! and providing a static `setup` function that returns an [`EngineRunner`].
 //! This setup function is registered internally and later executed by [`run`].
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::sync::Arc;
 use std::time::Duration;
 #[cfg(target_arch = "wasm32")]
@@ -27,22 +29,96 @@ use winit::platform::web::EventLoopExtWebSys;
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
-use crate::camera::Camera;
+use crate::animation::AnimationSystem;
+use crate::attachment::AttachmentSystem;
+use crate::audio::AudioSystem;
+use crate::audit::DeterminismAudit;
+use crate::benchmark::Benchmark;
+use crate::camera::{Camera, CameraBindings, CameraConfig};
+use crate::camera_constraints::CameraConstraints;
+use crate::camera_path::CameraPathSystem;
+use crate::cells::CellStreaming;
 use crate::config::Config;
-use crate::material::create_material_bind_group_layout;
-use crate::model::Model;
+use crate::dpi::DpiPolicy;
+use crate::environment::Environment;
+use crate::events::EventBus;
+use crate::frame::{FrameContext, RenderHooks};
+use crate::game_state::StateMachine;
+use crate::input::InputCapture;
+use crate::loading::{Loading, LoadingScreen};
+use crate::material::{SamplerDesc, create_material_bind_group_layout};
+use crate::material_registry::MaterialRegistry;
+use crate::math::rng::Rng;
+use crate::measure::Measurement;
+#[cfg(feature = "ui")]
+use crate::memory::AssetUsage;
+use crate::memory::MemoryBudget;
+use crate::metrics::Metrics;
+use crate::model::{DrawModel, Model};
+#[cfg(feature = "net")]
+use crate::net::NetSystem;
+use crate::reflection::{ReflectionProbe, ReflectionProbes};
+#[cfg(feature = "gpu-culling")]
+use crate::renderer::culling::{FrustumCullingPass, OcclusionCullingPass};
+use crate::renderer::custom;
+use crate::renderer::debug_draw::DebugDrawPass;
+use crate::renderer::debug_view::DebugViewPass;
 use crate::renderer::graph::BackgroundPass;
+use crate::renderer::graph::DEPTH;
+use crate::renderer::graph::DepthPrePass;
 use crate::renderer::graph::GeometryPass;
 use crate::renderer::graph::RenderGraph;
-use crate::renderer::pipeline::PipelineManager;
-use crate::renderer::surface::SurfaceManager;
-use crate::resources::create_transform_bind_group_layout;
+use crate::renderer::graph::SURFACE;
+use crate::renderer::grid::GridPass;
+use crate::renderer::grid::GridSettings;
+use crate::renderer::grid::create_grid_bind_group_layout;
+use crate::renderer::highlight::HighlightPass;
+use crate::renderer::light_shafts::LightShaftPass;
+use crate::renderer::light_shafts::create_light_shafts_bind_group_layout;
+use crate::renderer::pipeline::{PipelineKind, PipelineManager};
+use crate::renderer::polyline::{PolylinePass, Polylines, create_polyline_bind_group_layout};
+use crate::renderer::preprocess::ShaderDefines;
+use crate::renderer::probe::create_probe_bind_group_layout;
+use crate::renderer::surface::{ColorSpace, SurfaceManager};
+use crate::renderer::temporal::{MotionBlur, TemporalPass, TemporalSettings};
+use crate::renderer::crowd::{CrowdPass, Crowds, create_crowd_bind_group_layout};
+use crate::renderer::tilemap::{TileMapPass, TileMaps};
+use crate::renderer::viewport::Viewport;
+use crate::replay::ReplaySystem;
+use crate::resources::{
+        create_skinned_transform_bind_group_layout, create_transform_bind_group_layout,
+};
+use crate::resources::import::ImportOptions;
+#[cfg(target_arch = "wasm32")]
+use crate::resources::ModelData;
+use crate::save::SaveSystem;
+use crate::selection::Selection;
+use crate::scheduler::Scheduler;
+#[cfg(feature = "scripting")]
+use crate::scripting::ScriptSystem;
+use crate::shutdown::Shutdown;
+use crate::steering::SteeringSystem;
+use crate::streaming::TextureStreaming;
 use crate::texture::Texture;
-use crate::ui::UiSystem;
+use crate::tween::TweenSystem;
+#[cfg(feature = "ui")]
+use crate::ui::assets::AssetPanel;
+#[cfg(feature = "ui")]
+use crate::ui::console::Console;
+#[cfg(feature = "ui")]
+use crate::ui::selection::SelectionPanel;
+#[cfg(feature = "ui")]
+use crate::ui::inspect::Inspectors;
+#[cfg(feature = "ui")]
+use crate::ui::minimap::Minimap;
+#[cfg(feature = "ui")]
+use crate::ui::{UiNav, UiSettings, UiSystem};
+use crate::undo::UndoStack;
+use crate::upload::UploadQueue;
+use crate::window::WindowHooks;
 use anyhow::{Context, Result};
 use derivative::Derivative;
 use instant::Instant;
-use serde::{Deserialize, Serialize};
 use winit::event::{DeviceEvent, DeviceId, ElementState};
 use winit::event_loop::ControlFlow;
 use winit::window::Window;
@@ -54,14 +130,25 @@ use winit::{
         window::WindowId,
 };
 
+/// Lives with the pipelines so the renderer builds without the engine.
+pub use crate::renderer::pipeline::FillMode;
+
 /// Runner for the [`Engine`].
 pub struct EngineRunner
 {
-        pub engine: Option<Engine>,
+        /// One window each, the event loop exits when all of them did.
+        pub engines: Vec<Engine>,
 
         pub event_loop: EventLoop<EngineState>,
 }
 
+/// Drives several engines on one event loop, routing window events by
+/// window and removing engines that exited.
+struct EngineGroup
+{
+        engines: Vec<Engine>,
+}
+
 impl EngineRunner
 {
         /// Constructor for [`EnginerRunner`].
@@ -72,18 +159,40 @@ impl EngineRunner
         /// # Returns
         ///
         /// `anyhow::Result<EngineRunner>`.
-        pub fn new(#[allow(unused_mut)] mut engine: Engine) -> Result<Self>
+        pub fn new(engine: Engine) -> Result<Self>
         {
+                Self::with_engines(vec![engine])
+        }
+
+        /// Runs several engines, each in its own window, on the one event
+        /// loop the process may have.
+        ///
+        /// Closing a window tears its engine down, see [`crate::shutdown`].
+        /// [`EngineRunner::run`] returns once every engine exited.
+        pub fn with_engines(#[allow(unused_mut)] mut engines: Vec<Engine>) -> Result<Self>
+        {
+                if engines.is_empty()
+                {
+                        anyhow::bail!("No engines to run.");
+                }
+
                 let event_loop: EventLoop<EngineState> = EventLoop::with_user_event().build()?;
                 event_loop.set_control_flow(ControlFlow::Poll);
 
-                #[cfg(target_arch = "wasm32")]
+                let shared = engines.len() > 1;
+
+                for engine in &mut engines
                 {
-                        engine.proxy = Some(event_loop.create_proxy());
+                        engine.shutdown.shares_event_loop = shared;
+
+                        #[cfg(target_arch = "wasm32")]
+                        {
+                                engine.proxy = Some(event_loop.create_proxy());
+                        }
                 }
 
                 Ok(Self {
-                        engine: Some(engine),
+                        engines,
                         event_loop,
                 })
         }
@@ -97,38 +206,141 @@ impl EngineRunner
         /// # Returns
         ///
         /// `anyhow::Result<()>`. because `run_app()` returns a Result.
-        pub fn run(self) -> Result<()>
+        pub fn run(mut self) -> Result<()>
         {
-                #[allow(unused_mut)]
-                let mut engine = match self.engine
+                if self.engines.len() == 1
                 {
-                        Some(e) => e,
-                        None => anyhow::bail!("Engine doesn't exist."),
+                        #[allow(unused_mut)]
+                        let mut engine = self.engines.remove(0);
+
+                        #[cfg(target_arch = "wasm32")]
+                        {
+                                let engine = Box::leak(Box::new(engine));
+                                self.event_loop.spawn_app(engine);
+                        }
+
+                        #[cfg(not(target_arch = "wasm32"))]
+                        self.event_loop.run_app(&mut engine)?;
+
+                        return Ok(());
+                }
+
+                #[allow(unused_mut)]
+                let mut group = EngineGroup {
+                        engines: self.engines,
                 };
 
                 #[cfg(target_arch = "wasm32")]
                 {
-                        let engine = Box::leak(Box::new(engine));
-                        self.event_loop.spawn_app(engine);
+                        let group = Box::leak(Box::new(group));
+                        self.event_loop.spawn_app(group);
                 }
 
                 #[cfg(not(target_arch = "wasm32"))]
-                self.event_loop.run_app(&mut engine)?;
+                self.event_loop.run_app(&mut group)?;
 
                 Ok(())
         }
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Serialize, Deserialize)]
-pub enum FillMode
+impl EngineGroup
 {
-        Fill = 0,
-        Wireframe = 1,
-        Vertex = 2,
+        fn engine_mut(
+                &mut self,
+                id: WindowId,
+        ) -> Option<&mut Engine>
+        {
+                self.engines
+                        .iter_mut()
+                        .find(|e| e.window.as_ref().is_some_and(|w| w.id() == id))
+        }
+
+        /// Drops the engines that exited, which closes their windows, and
+        /// ends the loop after the last one.
+        fn remove_exited(
+                &mut self,
+                event_loop: &ActiveEventLoop,
+        )
+        {
+                self.engines.retain(|e| !e.shutdown.exited());
+
+                if self.engines.is_empty()
+                {
+                        event_loop.exit();
+                }
+        }
+}
+
+impl ApplicationHandler<EngineState> for EngineGroup
+{
+        fn resumed(
+                &mut self,
+                event_loop: &ActiveEventLoop,
+        )
+        {
+                for engine in &mut self.engines
+                {
+                        engine.resumed(event_loop);
+                }
+        }
+
+        fn user_event(
+                &mut self,
+                event_loop: &ActiveEventLoop,
+                state: EngineState,
+        )
+        {
+                if let Some(engine) = self.engine_mut(state.window_id)
+                {
+                        engine.user_event(event_loop, state);
+                }
+        }
+
+        fn window_event(
+                &mut self,
+                event_loop: &ActiveEventLoop,
+                id: WindowId,
+                event: WindowEvent,
+        )
+        {
+                if let Some(engine) = self.engine_mut(id)
+                {
+                        engine.window_event(event_loop, id, event);
+                }
+
+                self.remove_exited(event_loop);
+        }
+
+        fn device_event(
+                &mut self,
+                event_loop: &ActiveEventLoop,
+                device_id: DeviceId,
+                event: DeviceEvent,
+        )
+        {
+                for engine in &mut self.engines
+                {
+                        engine.device_event(event_loop, device_id, event.clone());
+                }
+        }
+
+        fn exiting(
+                &mut self,
+                event_loop: &ActiveEventLoop,
+        )
+        {
+                for engine in &mut self.engines
+                {
+                        engine.exiting(event_loop);
+                }
+        }
 }
 
 pub type Behavior = Box<dyn FnMut(&mut Engine)>;
 
+#[cfg(target_arch = "wasm32")]
+type SpawnedModels = std::rc::Rc<std::cell::RefCell<Vec<(String, ModelData)>>>;
+
 /// Main entrypoint of Oxide.
 ///
 /// To construct [`Engine`], use [`EngineBuilder`].
@@ -169,6 +381,156 @@ pub struct Engine
 
         pub model_map: HashMap<String, String>,
 
+        /// How the models are changed when loaded, by handle, see
+        /// [`Engine::add_model_with`].
+        pub import_options: HashMap<String, ImportOptions>,
+
+        /// Events published by subsystems and behaviors, see
+        /// [`crate::events`].
+        pub events: EventBus,
+
+        /// Sound effects and music, see [`Engine::audio`].
+        pub audio: AudioSystem,
+
+        /// Running animations, see [`Engine::tween_position`].
+        pub tweens: TweenSystem,
+
+        /// Timers and coroutines, see [`Engine::after`].
+        pub scheduler: Scheduler,
+
+        /// Models moved by steering behaviors, see [`Engine::steer`].
+        pub steering: SteeringSystem,
+
+        /// Animated models, see [`Engine::animate`].
+        pub animation: AnimationSystem,
+
+        /// Models carried by other models, see [`Engine::attach`].
+        pub attachment: AttachmentSystem,
+
+        /// Stack of game states, see [`Engine::push_state`].
+        pub states: StateMachine,
+
+        /// GPU memory of the models and the budget, see
+        /// [`Engine::gpu_memory`].
+        pub memory: MemoryBudget,
+
+        /// Base color textures loaded at the mip levels the camera needs,
+        /// see [`EngineBuilder::with_texture_streaming`].
+        pub texture_streaming: TextureStreaming,
+
+        /// Models loaded at runtime waiting for their upload, see
+        /// [`EngineBuilder::with_upload_budget`].
+        pub uploads: UploadQueue,
+
+        /// Loading screen shown until the startup models are loaded, see
+        /// [`EngineBuilder::with_loading_screen`].
+        pub loading: Loading,
+
+        /// Parts of the world loaded around the camera, see
+        /// [`Engine::add_cell`].
+        pub cells: CellStreaming,
+
+        /// Cubemaps of the scene shiny materials reflect, see
+        /// [`Engine::add_reflection_probe`].
+        pub reflection_probes: ReflectionProbes,
+
+        /// Day/night cycle, see [`Engine::environment`].
+        pub environment: Environment,
+
+        /// WGSL of the custom shaders by name, see [`Engine::add_shader`].
+        pub shaders: HashMap<String, String>,
+
+        /// Materials shared between models, see [`Engine::materials`].
+        pub materials: MaterialRegistry,
+
+        /// Frame and tick statistics, see [`Engine::metrics`].
+        pub metrics: Metrics,
+
+        /// Whether the UI captures keyboard or mouse input, see
+        /// [`Engine::ui_wants_keyboard`].
+        pub input: InputCapture,
+
+        /// Chase camera and similar, see [`Engine::camera_follow`].
+        pub camera_constraints: CameraConstraints,
+
+        /// Spline fly-throughs, see [`Engine::play_camera_path`].
+        pub camera_path: CameraPathSystem,
+
+        /// Values edited live in the debug UI, see [`Engine::inspect`].
+        #[cfg(feature = "ui")]
+        pub inspectors: Inspectors,
+
+        /// Loaded models and their memory in the debug UI, see
+        /// [`crate::ui::assets`].
+        #[cfg(feature = "ui")]
+        pub asset_panel: AssetPanel,
+
+        /// Edits of every selected model in the debug UI, see
+        /// [`crate::ui::selection`].
+        #[cfg(feature = "ui")]
+        pub selection_panel: SelectionPanel,
+
+        /// Recent log lines and log levels in the debug UI, see
+        /// [`crate::log`].
+        #[cfg(feature = "ui")]
+        pub console: Console,
+
+        /// Top-down map over the scene, see [`Engine::minimap`].
+        #[cfg(feature = "ui")]
+        pub minimap: Minimap,
+
+        /// Custom draws recorded every frame, see [`Engine::on_render`].
+        pub render_hooks: RenderHooks,
+
+        /// Lines with a width, see [`Engine::add_polyline`].
+        pub polylines: Polylines,
+
+        /// Grids of tiles, see [`Engine::add_tilemap`].
+        pub tilemaps: TileMaps,
+
+        /// Instanced skinned models, see [`Engine::add_crowd`].
+        pub crowds: Crowds,
+
+        /// Window lifecycle callbacks, see [`Engine::on_window_event`].
+        pub window_hooks: WindowHooks,
+
+        /// Exit request and cleanup, see [`Engine::request_exit`].
+        pub shutdown: Shutdown,
+
+        /// Shared `wgpu` instance, see [`EngineBuilder::with_instance`].
+        pub instance: Option<wgpu::Instance>,
+
+        /// Rhai scripts, see [`Engine::add_script`].
+        #[cfg(feature = "scripting")]
+        pub scripts: ScriptSystem,
+
+        /// Input recording and playback, see [`Engine::start_recording`].
+        pub replay: ReplaySystem,
+
+        /// Per-tick hashes of the simulation, see [`Engine::start_audit`].
+        pub audit: DeterminismAudit,
+
+        /// The running benchmark, see [`crate::benchmark`].
+        pub(crate) benchmark: Option<Benchmark>,
+
+        /// Saveable state and save slots, see [`Engine::save_game`].
+        pub saves: SaveSystem,
+
+        /// Random numbers for game logic. Reseeded when a replay is recorded
+        /// or played, so logic using it stays deterministic, see
+        /// [`crate::math::rng`].
+        pub rng: Rng,
+
+        /// WebSocket connections, see [`Engine::connect`].
+        #[cfg(feature = "net")]
+        pub net: NetSystem,
+
+        /// Models loaded by [`Engine::spawn_model`] that finished
+        /// downloading, queued for their upload on the next frame.
+        #[cfg(target_arch = "wasm32")]
+        #[derivative(Debug = "ignore")]
+        pub(crate) spawned: SpawnedModels,
+
         /// The OS/Browser window for rendering and input handling.
         pub window: Option<Arc<Window>>,
 
@@ -187,45 +549,249 @@ impl Engine
                 self.behavior_list.push(Box::new(f));
         }
 
+        /// Access to the [`AudioSystem`], e.g.
+        /// `engine.audio().play("bounce.ogg")`.
+        pub fn audio(&mut self) -> &mut AudioSystem
+        {
+                &mut self.audio
+        }
+
         pub fn render(
                 &mut self,
                 dt: &Duration,
         ) -> Result<()>
         {
                 let state = self.state.as_mut().context("EngineState missing")?;
+                #[cfg(feature = "ui")]
                 let window = self.window.as_ref().context("Window missing")?;
 
+                state.sync_depth_prepass(&self.shaders);
+                state.sync_viewport();
+                state.build_geometry_permutations();
+
+                for model in state.models.values_mut()
+                {
+                        model.update_morphs(&state.queue);
+                        model.update_nodes(&state.queue);
+
+                        if let Some(billboard) = model.billboard
+                        {
+                                model.transform.rotation = billboard
+                                        .rotation(model.transform.position, state.camera.core.position);
+                        }
+                }
+
                 #[rustfmt::skip]
                 let Some((output, frame, mut encoder)) =
                         state.surface_manager.acquire_frame(&state.device)?
                 else { return Ok(()); };
 
-                state.render_graph.execute(
+                state.render_graph.set_environment(&self.config.environment);
+
+                if let Some(pass) = state.render_graph.pass_mut::<PolylinePass>()
+                {
+                        pass.sync(&self.polylines);
+                }
+
+                if let Some(pass) = state.render_graph.pass_mut::<TileMapPass>()
+                {
+                        pass.sync(&self.tilemaps);
+                }
+
+                if let Some(pass) = state.render_graph.pass_mut::<CrowdPass>()
+                {
+                        pass.sync(&self.crowds, dt.as_secs_f32());
+                }
+
+                // Drawn on their cells and as crowd members only.
+                for handle in self.tilemaps.palette_models().chain(self.crowds.models())
+                {
+                        if let Some(model) = state.models.get_mut(handle)
+                        {
+                                model.visible = false;
+                        }
+                }
+
+                state.camera.uniform.environment = self.config.environment.uniform();
+                // From the camera again, so last frame's jitter isn't kept.
+                state.camera
+                        .uniform
+                        .update_view_proj(&state.camera.core, &state.camera.projection);
+
+                if let Some(temporal) = state.render_graph.pass_mut::<TemporalPass>()
+                {
+                        let configuration = &state.surface_manager.configuration;
+                        let viewport = state.pipeline_manager.viewport.unwrap_or(Viewport::full(
+                                configuration.width,
+                                configuration.height,
+                        ));
+
+                        temporal.prepare(
+                                &mut state.camera.uniform,
+                                viewport.width,
+                                viewport.height,
+                        );
+                }
+
+                #[cfg(feature = "gpu-culling")]
+                state.prepare_culling();
+
+                let depth_texture = state
+                        .surface_manager
+                        .depth_texture()
+                        .context("Depth texture missing")?;
+
+                let camera = state.camera.get_bind_group(&state.device);
+
+                let pass_buffers = state.render_graph.execute(
                         &frame,
                         &mut encoder,
                         &state.pipeline_manager,
-                        &state.camera.get_bind_group(&state.device),
-                        &state.depth_texture,
+                        &camera,
+                        depth_texture,
                         Some(&state.models),
                         &state.device,
+                        &state.queue,
                 );
 
-                if self.config.enable_debug
+                for model in state.models.values_mut()
                 {
-                        state.show_debug_window(
-                                window.clone(),
-                                &mut self.config.fill_mode,
-                                &frame,
-                                &mut encoder,
-                                &dt,
-                        );
+                        model.previous_transform = Some(model.calculate_transform());
+                }
+
+                let mut readbacks = Vec::new();
+
+                self.render_hooks.run(&mut FrameContext {
+                        device: &state.device,
+                        queue: &state.queue,
+                        encoder: &mut encoder,
+                        view: &frame,
+                        depth: &depth_texture.view,
+                        camera: &camera,
+                        format: state.surface_manager.render_format(),
+                        width: state.surface_manager.configuration.width,
+                        height: state.surface_manager.configuration.height,
+                        readbacks: &mut readbacks,
+                });
+
+                // With the HDR target the UI is drawn onto the surface, after
+                // the scene was copied there.
+                let hdr = state.surface_manager.hdr();
+
+                #[allow(unused_variables)]
+                let ui_view = if hdr
+                {
+                        state.surface_manager.finish_frame(&output, &mut encoder);
+
+                        output.texture
+                                .create_view(&wgpu::TextureViewDescriptor::default())
+                }
+                else
+                {
+                        frame.clone()
+                };
+
+                #[cfg(feature = "ui")]
+                let show_debug = self.config.enable_debug;
+
+                #[cfg(feature = "ui")]
+                if show_debug || self.states.has_ui() || self.minimap.enabled
+                {
+                        state.begin_ui(window, self.config.dpi);
+
+                        if show_debug
+                        {
+                                state.update_thumbnails(&mut encoder);
+                                state.show_debug_window(&mut self.config, &mut self.audio, &dt);
+                                state.show_measurement();
+                                state.show_hovered(&self.config);
+
+                                self.camera_path.ui(
+                                        state.gui.renderer.context(),
+                                        &mut state.camera,
+                                        &mut self.events,
+                                );
+
+                                self.inspectors.ui(state.gui.renderer.context());
+
+                                self.asset_panel.ui(
+                                        state.gui.renderer.context(),
+                                        &AssetUsage::collect(
+                                                &state.models,
+                                                &self.model_map,
+                                                &self.materials,
+                                        ),
+                                );
+
+                                self.selection_panel.ui(
+                                        state.gui.renderer.context(),
+                                        &mut state.selection,
+                                        &mut state.models,
+                                        &mut state.history,
+                                        &state.queue,
+                                        &self.attachment,
+                                );
+
+                                self.console.ui(state.gui.renderer.context());
+
+                                self.environment.ui(state.gui.renderer.context());
+
+                                if let Some(pass) = state
+                                        .render_graph
+                                        .pass_mut::<DebugDrawPass>()
+                                        .filter(|pass| pass.enabled)
+                                {
+                                        self.camera_path.gizmos(&mut pass.lines);
+                                }
+
+                                if !state.gui.renderer.context().is_using_pointer()
+                                {
+                                        self.config.save_file_if_changed();
+                                }
+                        }
+
+                        // Under the menus of the game states.
+                        if self.minimap.enabled
+                        {
+                                state.show_minimap(&mut self.minimap, &mut encoder);
+                        }
+
+                        self.states.ui(state.gui.renderer.context());
+
+                        state.end_ui(window, &ui_view, &mut encoder);
                 }
 
-                state.queue.submit(std::iter::once(encoder.finish()));
+                if !hdr
+                {
+                        state.surface_manager.finish_frame(&output, &mut encoder);
+                }
+
+                state.queue.submit(pass_buffers
+                        .into_iter()
+                        .chain(std::iter::once(encoder.finish())));
                 output.present();
 
+                state.render_graph.after_submit();
+
+                for (readback, callback) in readbacks
+                {
+                        readback.map(callback);
+                }
+
+                crate::renderer::readback::poll(&state.device);
+
                 state.update(&dt);
 
+                self.metrics.frame(&state.models);
+
+                self.camera_constraints
+                        .apply(&mut state.camera, &state.models, dt);
+
+                self.camera_path
+                        .apply(&mut state.camera, dt, &mut self.events);
+
+                self.audio.update_spatial(&state.camera, &state.models);
+
                 Ok(())
         }
 
@@ -238,17 +804,379 @@ impl Engine
                 self.model_map.insert(handle.into(), file_name.into());
         }
 
+        /// [`Engine::add_model`] with the model scaled, turned or otherwise
+        /// fixed up as it's loaded, see [`ImportOptions`].
+        pub fn add_model_with(
+                &mut self,
+                handle: impl Into<String>,
+                file_name: impl Into<String>,
+                options: ImportOptions,
+        )
+        {
+                let handle = handle.into();
+
+                self.import_options.insert(handle.clone(), options);
+                self.add_model(handle, file_name);
+        }
+
+        /// Adds an image file as a plane `size` units along its longer side,
+        /// the other side following the image's aspect ratio. It faces +Z,
+        /// or the camera with [`ImportOptions::billboard`]:
+        ///
+        /// ```ignore
+        /// engine.add_image_plane("poster", "art.png", 2.0);
+        ///
+        /// engine.add_model_with(
+        ///         "tree",
+        ///         "tree.png",
+        ///         ImportOptions {
+        ///                 scale: 4.0,
+        ///                 billboard: Some(Billboard::Cylindrical),
+        ///                 ..ImportOptions::default()
+        ///         },
+        /// );
+        /// ```
+        ///
+        /// PNG, JPEG and TGA images load as planes with any of the model
+        /// functions, [`Engine::spawn_model`] included.
+        pub fn add_image_plane(
+                &mut self,
+                handle: impl Into<String>,
+                file_name: impl Into<String>,
+                size: f32,
+        )
+        {
+                self.add_model_with(
+                        handle,
+                        file_name,
+                        ImportOptions {
+                                scale: size,
+                                ..ImportOptions::default()
+                        },
+                );
+        }
+
+        /// [`Engine::spawn_model`] with [`ImportOptions`].
+        pub fn spawn_model_with(
+                &mut self,
+                handle: impl Into<String>,
+                file_name: impl Into<String>,
+                options: ImportOptions,
+        )
+        {
+                let handle = handle.into();
+
+                self.import_options.insert(handle.clone(), options);
+                self.spawn_model(handle, file_name);
+        }
+
+        /// The import options of the model `handle`, the defaults unless it
+        /// was added with some.
+        pub fn import_options(
+                &self,
+                handle: &str,
+        ) -> ImportOptions
+        {
+                let mut options = self.import_options.get(handle).cloned().unwrap_or_default();
+
+                if options.cache.is_none()
+                {
+                        options.cache = self.config.import_cache.clone();
+                }
+
+                options
+        }
+
+        /// Loads a model while the engine is running and adds it under
+        /// `handle`, replacing any model with the same handle.
+        ///
+        /// Native loads block until the model is ready, on the web it is
+        /// downloaded in the background and appears a few frames later.
+        /// With an upload budget the model appears once it's uploaded, see
+        /// [`crate::upload`].
+        /// Before the engine state exists this is the same as
+        /// [`Engine::add_model`].
+        pub fn spawn_model(
+                &mut self,
+                handle: impl Into<String>,
+                file_name: impl Into<String>,
+        )
+        {
+                let (handle, file_name) = (handle.into(), file_name.into());
+
+                // Remembered so save games can spawn the model again.
+                self.model_map.insert(handle.clone(), file_name.clone());
+
+                let options = self.import_options(&handle);
+
+                if self.state.is_none()
+                {
+                        return self.add_model(handle, file_name);
+                }
+
+                #[cfg(not(target_arch = "wasm32"))]
+                {
+                        match pollster::block_on(crate::resources::load_model_data(
+                                &file_name,
+                                Some("de_dust2"),
+                                &options,
+                        ))
+                        {
+                                Ok(data) => self.queue_upload(handle, data),
+                                Err(e) => log::error!("Failed to spawn {}: {:#}", file_name, e),
+                        }
+                }
+
+                #[cfg(target_arch = "wasm32")]
+                {
+                        let spawned = self.spawned.clone();
+
+                        wasm_bindgen_futures::spawn_local(async move {
+                                let data = crate::resources::load_model_data(
+                                        &file_name,
+                                        Some("de_dust2"),
+                                        &options,
+                                )
+                                .await;
+
+                                match data
+                                {
+                                        Ok(data) => spawned.borrow_mut().push((handle, data)),
+                                        Err(e) => log::error!(
+                                                "Failed to spawn {}: {:#}",
+                                                file_name,
+                                                e
+                                        ),
+                                }
+                        });
+                }
+        }
+
+        /// Changes how material `slot` of the model `handle` samples its
+        /// base color texture, see [`Model::set_sampler`].
+        pub fn set_sampler(
+                &mut self,
+                handle: &str,
+                slot: usize,
+                sampler: SamplerDesc,
+        )
+        {
+                let Some(state) = self.state.as_mut()
+                else
+                {
+                        return;
+                };
+
+                match state.models.get_mut(handle)
+                {
+                        Some(model) =>
+                        {
+                                model.set_sampler(&state.device, &state.queue, slot, sampler)
+                        }
+                        None => log::warn!("set_sampler: no model {}", handle),
+                }
+        }
+
+        /// Turns the [`DepthPrePass`] on or off, the debug UI shows the GPU
+        /// time of the passes to compare.
+        pub fn set_depth_prepass(
+                &mut self,
+                enabled: bool,
+        )
+        {
+                if let Some(pass) = self
+                        .state
+                        .as_mut()
+                        .and_then(|state| state.render_graph.pass_mut::<DepthPrePass>())
+                {
+                        pass.enabled = enabled;
+                }
+
+                self.config.depth_prepass = enabled;
+        }
+
+        /// Registers the custom shader `name`, the fragment stage of models
+        /// drawn with it, see [`crate::renderer::custom`]. Replaces a shader
+        /// of the same name.
+        pub fn add_shader(
+                &mut self,
+                name: &str,
+                wgsl: &str,
+        )
+        {
+                self.shaders.insert(name.to_string(), wgsl.to_string());
+
+                if let Some(state) = self.state.as_mut()
+                {
+                        state.build_custom_pipeline(name, wgsl);
+                }
+        }
+
+        /// Draws the model `handle` with the custom shader `name`, or the
+        /// engine's shader with `None`.
+        pub fn set_model_shader(
+                &mut self,
+                handle: &str,
+                name: Option<&str>,
+        )
+        {
+                let Some(state) = self.state.as_mut()
+                else
+                {
+                        return;
+                };
+
+                match state.models.get_mut(handle)
+                {
+                        Some(model) => model.shader = name.map(str::to_string),
+                        None => log::warn!("set_model_shader: no model {}", handle),
+                }
+        }
+
+        /// Moves the camera to frame the model `handle`, with the world
+        /// bounds it's culled with, see [`Camera::frame`]. Returns false
+        /// when there's no such model or it has no meshes.
+        pub fn frame_model(
+                &mut self,
+                handle: &str,
+        ) -> bool
+        {
+                let Some(state) = self.state.as_mut()
+                else
+                {
+                        return false;
+                };
+
+                match state.models.get(handle).and_then(|m| m.world_bounds())
+                {
+                        Some(bounds) =>
+                        {
+                                state.camera.frame(&bounds);
+                                true
+                        }
+                        None => false,
+                }
+        }
+
+        /// Copies the model `handle` to `new_handle`, sharing its GPU
+        /// geometry and textures, see [`Model::duplicate`]. Replaces a
+        /// model with that handle. Returns false when there's no model
+        /// `handle`.
+        pub fn duplicate_model(
+                &mut self,
+                handle: &str,
+                new_handle: impl Into<String>,
+        ) -> bool
+        {
+                let new_handle = new_handle.into();
+
+                let Some(state) = self.state.as_mut()
+                else
+                {
+                        return false;
+                };
+
+                let Some(copy) = state
+                        .models
+                        .get(handle)
+                        .map(|model| model.duplicate(&state.device, &state.queue))
+                else
+                {
+                        log::warn!("duplicate_model: no model {}", handle);
+                        return false;
+                };
+
+                state.models.insert(new_handle.clone(), copy);
+
+                // Saved and spawned again like the original.
+                if let Some(file) = self.model_map.get(handle).cloned()
+                {
+                        self.model_map.insert(new_handle.clone(), file);
+                }
+
+                if let Some(options) = self.import_options.get(handle).cloned()
+                {
+                        self.import_options.insert(new_handle, options);
+                }
+
+                true
+        }
+
+        /// Duplicates the selected model in place under a new handle, e.g.
+        /// `crate_2` for `crate`, and selects the copy. Bound to Ctrl+D in
+        /// the debug UI. Returns the new handle.
+        pub fn duplicate_selected(&mut self) -> Option<String>
+        {
+                let state = self.state.as_ref()?;
+                let handle = state.selection.primary()?.to_string();
+
+                let new_handle = (2..)
+                        .map(|n| format!("{}_{}", handle, n))
+                        .find(|h| !state.models.contains_key(h))?;
+
+                if !self.duplicate_model(&handle, new_handle.clone())
+                {
+                        return None;
+                }
+
+                self.select_model(Some(&new_handle));
+
+                Some(new_handle)
+        }
+
+        /// Uploads `params` for the custom shader of the model `handle`,
+        /// read at `@group(3) @binding(1)`. The struct has to match the
+        /// WGSL one, including its padding.
+        pub fn set_material_params<T: bytemuck::Pod>(
+                &mut self,
+                handle: &str,
+                params: T,
+        )
+        {
+                let Some(state) = self.state.as_mut()
+                else
+                {
+                        return;
+                };
+
+                match state.models.get_mut(handle)
+                {
+                        Some(model) => model.set_params(
+                                &state.device,
+                                &state.queue,
+                                bytemuck::bytes_of(&params),
+                        ),
+                        None => log::warn!("set_material_params: no model {}", handle),
+                }
+        }
+
         fn resize(&mut self)
         {
                 #[cfg(target_arch = "wasm32")]
                 {
-                        let (width, height) = Self::get_body_size().unwrap();
-                        self._resize(width, height);
+                        if let Some((width, height)) = Self::get_body_size()
+                        {
+                                // The body is in points, the canvas in pixels.
+                                let factor = self
+                                        .window
+                                        .as_ref()
+                                        .map_or(1.0, |w| self.config.dpi.window_factor(w));
+
+                                self._resize(
+                                        (width as f32 * factor).round() as u32,
+                                        (height as f32 * factor).round() as u32,
+                                );
+                        }
                 }
 
                 #[cfg(not(target_arch = "wasm32"))]
                 {
-                        let size = self.window.as_ref().unwrap().inner_size();
+                        let Some(size) = self.window.as_ref().map(|w| w.inner_size())
+                        else
+                        {
+                                return;
+                        };
+
                         self._resize(size.width, size.height);
                 }
         }
@@ -280,46 +1208,15 @@ impl Engine
                 height: u32,
         )
         {
-                if width == 0 || height == 0
-                {
-                        return;
-                }
-
-                let state = self.state.as_mut().unwrap();
-
-                state.camera.projection.resize(width, height);
-
-                // Clamping to max dim to prevent panic!
-                let max_dim = state.device.limits().max_texture_dimension_2d;
-                let final_width = width.min(max_dim);
-                let final_height = height.min(max_dim);
-
-                state.surface_manager.configuration.width = final_width;
-                state.surface_manager.configuration.height = final_height;
-
-                state.surface_manager
-                        .surface
-                        .configure(&state.device, &state.surface_manager.configuration);
-
-                state.depth_texture = Texture::create_depth_texture(
-                        &state.device,
-                        &state.surface_manager.configuration,
-                        "depth_texture",
-                );
-
-                if state.camera.config.aspect_ratio_correction
-                {
-                        let aspect = final_width as f32 / final_height as f32;
-
-                        state.camera.projection.aspect = aspect;
-                }
+                // Resizes can arrive before the state exists on the web.
+                let Some(state) = self.state.as_mut()
                 else
                 {
-                        state.camera.projection.aspect =
-                                state.camera.config.initial_aspect.unwrap();
-                }
+                        return;
+                };
 
-                state.surface_manager.is_surface_configured = true;
+                state.surface_manager.resize(&state.device, width, height);
+                state.sync_viewport();
         }
 }
 
@@ -343,6 +1240,10 @@ pub struct EngineState
 
         pub instance: wgpu::Instance,
 
+        /// Window the state renders to, routes the state back to its engine
+        /// when it was created asynchronously.
+        pub window_id: WindowId,
+
         /// The rendering surface tied to the window.
         pub surface_manager: SurfaceManager,
 
@@ -357,12 +1258,24 @@ pub struct EngineState
 
         pub camera: Camera,
 
-        pub depth_texture: Texture,
+        pub render_graph: RenderGraph,
+
+        pub pipeline_manager: PipelineManager,
+
+        /// Models selected in the debug UI, see [`crate::selection`].
+        pub selection: Selection,
+
+        /// Model under the cursor in the debug UI, see
+        /// [`crate::ui::hover`].
+        pub hovered: Option<String>,
 
-        pub render_graph: RenderGraph,
+        /// Points picked in the debug UI, see [`crate::measure`].
+        pub measurement: Measurement,
 
-        pub pipeline_manager: PipelineManager,
+        /// Edits made in the debug UI, see [`crate::undo`].
+        pub history: UndoStack,
 
+        #[cfg(feature = "ui")]
         pub gui: UiSystem,
 }
 
@@ -371,6 +1284,9 @@ impl EngineState
         /// Creates a new EngineState by initializing the surface, adapter,
         /// device, and queue.
         ///
+        /// It starts without models, they're loaded behind the loading
+        /// screen afterwards, see [`crate::loading`].
+        ///
         /// # Parameters
         /// - `instance`: WGPU instance to create surfaces and request adapters.
         /// - `window`: The window to render to. Must outlive the EngineState.
@@ -380,10 +1296,14 @@ impl EngineState
         /// creation fails.
         pub async fn new(
                 window: Arc<Window>,
-                model_map: HashMap<String, String>,
+                camera_config: CameraConfig,
+                color_space: ColorSpace,
+                hdr: bool,
+                instance: Option<wgpu::Instance>,
         ) -> Result<EngineState>
         {
-                let instance = EngineBuilder::instance();
+                let instance = instance.unwrap_or_else(EngineBuilder::instance);
+                let window_id = window.id();
 
                 Self::log_all_adapters(&instance);
 
@@ -393,58 +1313,44 @@ impl EngineState
 
                 Self::log_adapter_info(&adapter);
 
+                #[cfg(not(target_arch = "wasm32"))]
+                crate::crash::set_adapter(&adapter.get_info());
+
                 let (device, queue) = EngineBuilder::device_queue(&adapter).await?;
 
-                let surface_manager =
-                        SurfaceManager::new(&instance, window.clone(), &size, &adapter)?;
+                let surface_manager = SurfaceManager::new(
+                        &instance,
+                        window.clone(),
+                        &size,
+                        &adapter,
+                        color_space,
+                        hdr,
+                )?;
 
                 let pipeline_manager = PipelineManager::new();
 
                 let render_graph = RenderGraph::new();
 
-                let gui = UiSystem::new(
-                        &device,
-                        &surface_manager.configuration.format,
-                        None,
-                        1,
-                        &window,
-                );
-
-                let camera = Camera::new();
-
-                let depth_texture = Texture::create_depth_texture(
-                        &device,
-                        &surface_manager.configuration,
-                        "depth_texture",
-                );
-
-                let mut models = HashMap::new();
-
-                for (handle, file_name) in model_map.iter()
-                {
-                        let model = crate::resources::load_model(
-                                file_name,
-                                Some("de_dust2"),
-                                &device,
-                                &queue,
-                                &create_material_bind_group_layout(&device),
-                                &create_transform_bind_group_layout(&device),
-                        )
-                        .await?;
+                #[cfg(feature = "ui")]
+                let gui = UiSystem::new(&device, &surface_manager.ui_format(), None, 1, &window);
 
-                        models.insert(handle.to_string(), model);
-                }
+                let camera = Camera::with_config(camera_config);
 
                 Ok(EngineState {
                         instance,
+                        window_id,
                         camera,
-                        models,
+                        models: HashMap::new(),
                         render_graph,
                         pipeline_manager,
+                        selection: Selection::new(),
+                        hovered: None,
+                        measurement: Measurement::new(),
+                        history: UndoStack::new(),
                         adapter,
-                        depth_texture,
                         device,
                         queue,
+                        #[cfg(feature = "ui")]
                         gui,
                         surface_manager,
                 })
@@ -465,6 +1371,8 @@ impl EngineState
 
         pub fn build_pipelines(&mut self)
         {
+                self.pipeline_manager.reversed_z = self.camera.projection.reversed_z;
+
                 let transform_bind_group_layout = create_transform_bind_group_layout(&self.device);
 
                 let material_bind_group_layout = create_material_bind_group_layout(&self.device);
@@ -474,7 +1382,7 @@ impl EngineState
 
                 self.pipeline_manager.build_geometry_pipeline(
                         &self.device,
-                        &self.surface_manager.configuration,
+                        &self.surface_manager.render_configuration(),
                         &[
                                 &self.camera.get_bind_group_layout(&self.device),
                                 &transform_bind_group_layout,
@@ -483,74 +1391,512 @@ impl EngineState
                         ],
                         &FillMode::Fill,
                 );
+
+                self.pipeline_manager.build_depth_prepass_pipeline(
+                        &self.device,
+                        &[
+                                &self.camera.get_bind_group_layout(&self.device),
+                                &transform_bind_group_layout,
+                                &material_bind_group_layout,
+                                &model_transform_bind_group_layout,
+                        ],
+                );
+
+                self.pipeline_manager.build_highlight_pipeline(
+                        &self.device,
+                        &self.surface_manager.render_configuration(),
+                        &[
+                                &self.camera.get_bind_group_layout(&self.device),
+                                &transform_bind_group_layout,
+                                &material_bind_group_layout,
+                                &model_transform_bind_group_layout,
+                        ],
+                );
+
+                self.pipeline_manager.build_grid_pipeline(
+                        &self.device,
+                        &self.surface_manager.render_configuration(),
+                        &[
+                                &self.camera.get_bind_group_layout(&self.device),
+                                &create_grid_bind_group_layout(&self.device),
+                        ],
+                );
+
+                self.pipeline_manager.build_debug_lines_pipeline(
+                        &self.device,
+                        &self.surface_manager.render_configuration(),
+                        &[&self.camera.get_bind_group_layout(&self.device)],
+                );
+
+                self.pipeline_manager.build_tiles_pipeline(
+                        &self.device,
+                        &self.surface_manager.render_configuration(),
+                        &[
+                                &self.camera.get_bind_group_layout(&self.device),
+                                &transform_bind_group_layout,
+                                &material_bind_group_layout,
+                                &model_transform_bind_group_layout,
+                        ],
+                );
+
+                self.pipeline_manager.build_crowd_pipeline(
+                        &self.device,
+                        &self.surface_manager.render_configuration(),
+                        &[
+                                &self.camera.get_bind_group_layout(&self.device),
+                                &transform_bind_group_layout,
+                                &material_bind_group_layout,
+                                &create_crowd_bind_group_layout(&self.device),
+                        ],
+                );
+
+                self.pipeline_manager.build_polyline_pipeline(
+                        &self.device,
+                        &self.surface_manager.render_configuration(),
+                        &[
+                                &self.camera.get_bind_group_layout(&self.device),
+                                &create_polyline_bind_group_layout(&self.device),
+                        ],
+                );
+
+                self.pipeline_manager.build_light_shafts_pipeline(
+                        &self.device,
+                        &self.surface_manager.render_configuration(),
+                        &[
+                                &self.camera.get_bind_group_layout(&self.device),
+                                &create_light_shafts_bind_group_layout(&self.device),
+                        ],
+                );
+
+                self.pipeline_manager.build_fill_pipeline(
+                        &self.device,
+                        &self.surface_manager.render_configuration(),
+                );
+
+                #[cfg(feature = "gpu-culling")]
+                self.build_culling();
         }
 
-        pub fn build_passes(&mut self)
+        /// Builds the model pipelines again when the [`DepthPrePass`] was
+        /// turned on or off, and tells the [`GeometryPass`] whether to keep
+        /// its depth.
+        pub fn sync_depth_prepass(
+                &mut self,
+                shaders: &HashMap<String, String>,
+        )
         {
-                let bg_pass = BackgroundPass {
-                        name: "bg_pass".to_string(),
-                        enabled: true,
-                        clear_color: wgpu::Color {
-                                r: 1.0,
-                                g: 0.0,
-                                b: 0.0,
-                                a: 1.0,
-                        },
-                };
+                let enabled = self
+                        .render_graph
+                        .pass_mut::<DepthPrePass>()
+                        .is_some_and(|pass| pass.enabled);
 
-                let bg_pass_2 = BackgroundPass {
-                        name: "bg_pass_2".to_string(),
-                        enabled: true,
-                        clear_color: wgpu::Color {
-                                r: 0.0,
-                                g: 1.0,
-                                b: 0.0,
-                                a: 1.0,
-                        },
-                };
+                if enabled != self.pipeline_manager.depth_prepass
+                {
+                        self.pipeline_manager.depth_prepass = enabled;
 
-                let bg_pass_3 = BackgroundPass {
-                        name: "bg_pass_3".to_string(),
-                        enabled: true,
-                        clear_color: wgpu::Color {
-                                r: 0.05,
-                                g: 0.05,
-                                b: 0.05,
-                                a: 1.0,
-                        },
+                        let fill_mode = self.pipeline_manager.fill_mode;
+
+                        self.pipeline_manager.build_geometry_pipeline(
+                                &self.device,
+                                &self.surface_manager.render_configuration(),
+                                &[
+                                        &self.camera.get_bind_group_layout(&self.device),
+                                        &create_transform_bind_group_layout(&self.device),
+                                        &create_material_bind_group_layout(&self.device),
+                                        &create_transform_bind_group_layout(&self.device),
+                                ],
+                                &fill_mode,
+                        );
+
+                        for (name, wgsl) in shaders
+                        {
+                                self.build_custom_pipeline(name, wgsl);
+                        }
+                }
+
+                let load_depth = self.pipeline_manager.depth_prepass_active();
+
+                if let Some(pass) = self.render_graph.pass_mut::<GeometryPass>()
+                {
+                        pass.load_depth = load_depth;
+                }
+        }
+
+        /// Fits the scene into the surface at
+        /// [`CameraConfig::locked_aspect`](crate::camera::CameraConfig::locked_aspect)
+        /// and matches the projection to it.
+        pub fn sync_viewport(&mut self)
+        {
+                let configuration = &self.surface_manager.configuration;
+
+                // Minimized, keep the projection of the last real size.
+                if !self.surface_manager.is_surface_configured
+                {
+                        return;
+                }
+
+                let viewport = self.camera.config.locked_aspect.map(|aspect| {
+                        Viewport::fit(configuration.width, configuration.height, aspect)
+                });
+
+                self.pipeline_manager.viewport = viewport;
+
+                match viewport
+                {
+                        Some(viewport) => self.camera.projection.aspect = viewport.aspect(),
+                        None => self
+                                .camera
+                                .projection
+                                .resize(configuration.width, configuration.height),
+                }
+        }
+
+        /// Builds the geometry pipelines of the material feature sets,
+        /// skinning and reflection probes the models use that aren't built
+        /// yet.
+        pub fn build_geometry_permutations(&mut self)
+        {
+                #[allow(unused_mut)]
+                let mut used: Vec<ShaderDefines> = self
+                        .models
+                        .values()
+                        .flat_map(|model| model.meshes.iter().map(|m| model.defines(m)))
+                        .collect();
+
+                #[cfg(feature = "gpu-culling")]
+                used.extend(self.culled_permutations());
+
+                let missing: BTreeSet<ShaderDefines> = used
+                        .into_iter()
+                        .filter(|defines| {
+                                !defines.is_empty()
+                                        && !self.pipeline_manager
+                                                .geometry_permutations
+                                                .contains_key(defines)
+                        })
+                        .collect();
+
+                if missing.is_empty()
+                {
+                        return;
+                }
+
+                let transform_bind_group_layout = create_transform_bind_group_layout(&self.device);
+
+                let skinned_bind_group_layout = create_skinned_transform_bind_group_layout(&self.device);
+
+                let material_bind_group_layout = create_material_bind_group_layout(&self.device);
+
+                let probe_bind_group_layout = create_probe_bind_group_layout(&self.device, false);
+
+                let skinned_probe_bind_group_layout =
+                        create_probe_bind_group_layout(&self.device, true);
+
+                for defines in missing
+                {
+                        #[cfg(feature = "gpu-culling")]
+                        if defines.contains("CULLED")
+                        {
+                                self.build_culled_permutation(
+                                        &defines,
+                                        &material_bind_group_layout,
+                                );
+                                continue;
+                        }
+
+                        let model_bind_group_layout = match (
+                                defines.contains("SKINNED"),
+                                defines.contains("REFLECTION_PROBE"),
+                        )
+                        {
+                                (true, true) => &skinned_probe_bind_group_layout,
+                                (false, true) => &probe_bind_group_layout,
+                                (true, false) => &skinned_bind_group_layout,
+                                (false, false) => &transform_bind_group_layout,
+                        };
+
+                        self.pipeline_manager.build_geometry_permutation(
+                                &self.device,
+                                &self.surface_manager.render_configuration(),
+                                &[
+                                        &self.camera.get_bind_group_layout(&self.device),
+                                        &transform_bind_group_layout,
+                                        &material_bind_group_layout,
+                                        model_bind_group_layout,
+                                ],
+                                &defines,
+                        );
+                }
+        }
+
+        /// Draws `models` into `view` seen by `camera`, with the engine's
+        /// model pipelines and lighting. `view` must have the render format
+        /// and the size of `depth_texture`.
+        pub(crate) fn draw_offscreen(
+                &self,
+                models: &[&Model],
+                camera: &wgpu::BindGroup,
+                view: &wgpu::TextureView,
+                depth_texture: &Texture,
+                clear: wgpu::Color,
+                encoder: &mut wgpu::CommandEncoder,
+        )
+        {
+                let pipeline_manager = &self.pipeline_manager;
+                let prepass = pipeline_manager.depth_prepass_active();
+
+                // The model pipelines only shade the pre-pass depth then.
+                if prepass
+                {
+                        let mut render_pass =
+                                encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                                        label: Some("offscreen_depth"),
+                                        color_attachments: &[],
+                                        depth_stencil_attachment: Some(
+                                                wgpu::RenderPassDepthStencilAttachment {
+                                                        view: &depth_texture.view,
+                                                        depth_ops: Some(wgpu::Operations {
+                                                                load: wgpu::LoadOp::Clear(
+                                                                        pipeline_manager
+                                                                                .depth_clear(),
+                                                                ),
+                                                                store: wgpu::StoreOp::Store,
+                                                        }),
+                                                        stencil_ops: None,
+                                                },
+                                        ),
+                                        occlusion_query_set: None,
+                                        timestamp_writes: None,
+                                });
+
+                        render_pass.set_pipeline(pipeline_manager.get(PipelineKind::DepthPrepass));
+
+                        for model in models
+                        {
+                                self.draw_model(&mut render_pass, model, camera, false);
+                        }
+                }
+
+                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        label: Some("offscreen"),
+                        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                                view,
+                                resolve_target: None,
+                                ops: wgpu::Operations {
+                                        load: wgpu::LoadOp::Clear(clear),
+                                        store: wgpu::StoreOp::Store,
+                                },
+                        })],
+                        depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                                view: &depth_texture.view,
+                                depth_ops: Some(wgpu::Operations {
+                                        load: if prepass
+                                        {
+                                                wgpu::LoadOp::Load
+                                        }
+                                        else
+                                        {
+                                                wgpu::LoadOp::Clear(pipeline_manager.depth_clear())
+                                        },
+                                        store: wgpu::StoreOp::Store,
+                                }),
+                                stencil_ops: None,
+                        }),
+                        occlusion_query_set: None,
+                        timestamp_writes: None,
+                });
+
+                for model in models
+                {
+                        self.draw_model(&mut render_pass, model, camera, true);
+                }
+        }
+
+        /// Draws every mesh of `model`, with the geometry pipeline of its
+        /// material when `shade`.
+        fn draw_model<'a>(
+                &self,
+                render_pass: &mut wgpu::RenderPass<'a>,
+                model: &'a Model,
+                camera: &wgpu::BindGroup,
+                shade: bool,
+        )
+        {
+                render_pass.set_bind_group(0, camera, &[]);
+                render_pass.set_bind_group(
+                        3,
+                        &model.create_model_transform_bind_group(&self.device),
+                        &[],
+                );
+
+                for mesh in model.meshes.iter()
+                {
+                        let material = &model.materials[mesh.material];
+
+                        if shade
+                        {
+                                render_pass.set_pipeline(
+                                        self.pipeline_manager
+                                                .geometry_pipeline(&material.defines()),
+                                );
+                        }
+
+                        render_pass.set_bind_group(1, &mesh.transform_bind_group, &[]);
+                        render_pass.set_bind_group(2, &material.material_bind_group, &[]);
+                        render_pass.draw_mesh(mesh);
+                }
+        }
+
+        /// Builds the pipeline of a custom shader, logging instead when it
+        /// doesn't compile.
+        pub fn build_custom_pipeline(
+                &mut self,
+                name: &str,
+                wgsl: &str,
+        )
+        {
+                let source = match custom::source(wgsl, &self.pipeline_manager.includes)
+                {
+                        Ok(source) => source,
+                        Err(e) =>
+                        {
+                                log::error!("Custom shader {}: {}", name, e);
+
+                                self.pipeline_manager.custom_pipelines.remove(name);
+
+                                return;
+                        }
                 };
 
+                self.pipeline_manager.build_custom_pipeline(
+                        &self.device,
+                        &self.surface_manager.render_configuration(),
+                        &[
+                                &self.camera.get_bind_group_layout(&self.device),
+                                &create_transform_bind_group_layout(&self.device),
+                                &create_material_bind_group_layout(&self.device),
+                                &custom::create_custom_model_bind_group_layout(&self.device),
+                        ],
+                        name,
+                        &source,
+                );
+        }
+
+        pub fn build_passes(
+                &mut self,
+                grid: &GridSettings,
+                temporal: &TemporalSettings,
+                depth_prepass: bool,
+        )
+        {
                 let geometry_pass = GeometryPass {
                         name: "geometry_pass".to_string(),
                         enabled: true,
+                        load_depth: false,
                 };
 
-                self.render_graph.add_pass(Box::new(bg_pass));
-                self.render_graph.add_pass(Box::new(bg_pass_2));
-                self.render_graph.add_pass(Box::new(bg_pass_3));
+                self.render_graph
+                        .add_pass(Box::new(BackgroundPass::new("background_pass")));
+                #[cfg(feature = "gpu-culling")]
+                self.render_graph
+                        .add_pass(Box::new(FrustumCullingPass::new()));
+                self.render_graph
+                        .add_pass(Box::new(DepthPrePass::new(depth_prepass)));
+                #[cfg(feature = "gpu-culling")]
+                self.render_graph
+                        .add_pass(Box::new(OcclusionCullingPass::new()));
                 self.render_graph.add_pass(Box::new(geometry_pass));
+                self.render_graph.add_pass(Box::new(TileMapPass::new()));
+                self.render_graph
+                        .add_pass(Box::new(CrowdPass::new(&self.device)));
+                self.render_graph.add_pass(Box::new(LightShaftPass::new()));
+                self.render_graph.add_pass(Box::new(GridPass::new(grid)));
+                self.render_graph
+                        .add_pass(Box::new(PolylinePass::new(&self.device)));
+                self.render_graph.add_pass(Box::new(DebugDrawPass::new()));
+                self.render_graph.add_pass(Box::new(HighlightPass::new()));
+                self.render_graph.add_pass(Box::new(TemporalPass::new(
+                        temporal,
+                        self.surface_manager.render_format(),
+                        self.surface_manager.hdr(),
+                )));
+                self.render_graph.add_pass(Box::new(DebugViewPass::new(
+                        self.surface_manager.render_format(),
+                )));
+
+                self.render_graph
+                        .enable_gpu_timing(&self.device, &self.queue);
+
+                self.render_graph
+                        .set_format(SURFACE, self.surface_manager.render_format());
+                self.render_graph.set_format(DEPTH, Texture::DEPTH_FORMAT);
         }
 
-        pub fn show_debug_window(
+        /// Restores the debug UI scale and layout of the last run.
+        #[cfg(feature = "ui")]
+        pub fn apply_ui_settings(
                 &mut self,
-                window: Arc<Window>,
-                fill_mode: &mut FillMode,
+                settings: &UiSettings,
+        )
+        {
+                self.gui.ui_scale = settings.scale;
+                self.gui.renderer.show_right_panel = settings.show_right_panel;
+        }
+
+        /// Starts the `egui` frame shared by the debug window and the UI of
+        /// game states.
+        #[cfg(feature = "ui")]
+        pub fn begin_ui(
+                &mut self,
+                window: &Window,
+                dpi: DpiPolicy,
+        )
+        {
+                let pixels_per_point = dpi.window_factor(window) * self.gui.ui_scale;
+
+                self.gui.renderer.begin_frame(window, pixels_per_point);
+        }
+
+        /// Finishes the `egui` frame and draws it on top of `frame`.
+        #[cfg(feature = "ui")]
+        pub fn end_ui(
+                &mut self,
+                window: &Window,
                 frame: &wgpu::TextureView,
                 encoder: &mut wgpu::CommandEncoder,
-                dt: &Duration,
         )
         {
-                let pixels_per_point = self.gui.ui_scale;
+                // What the frame was laid out at, see [`crate::dpi`].
+                let pixels_per_point = self.gui.renderer.context().pixels_per_point();
 
                 let screen_descriptor = egui_wgpu::ScreenDescriptor {
                         size_in_pixels: [
                                 self.surface_manager.configuration.width,
                                 self.surface_manager.configuration.height,
                         ],
-                        pixels_per_point, /* inversely counteracts the
-                                           * Browser DPI */
+                        pixels_per_point,
                 };
 
+                self.gui.renderer.end_frame_and_draw(
+                        &self.device,
+                        &self.queue,
+                        encoder,
+                        window,
+                        frame,
+                        screen_descriptor,
+                );
+        }
+
+        /// Draws the debug window, must be called between
+        /// [`EngineState::begin_ui`] and [`EngineState::end_ui`].
+        #[cfg(feature = "ui")]
+        pub fn show_debug_window(
+                &mut self,
+                config: &mut Config,
+                audio: &mut AudioSystem,
+                dt: &Duration,
+        )
+        {
                 {
                         let supported = self.adapter.features();
 
@@ -560,24 +1906,26 @@ impl EngineState
 
                         let enabled_features = supported & desired;
 
-                        self.gui.renderer
-                                .begin_frame(window.clone().as_ref(), &mut self.gui.ui_scale);
-
-                        let mut temp_fill_mode = fill_mode.clone();
+                        let fill_mode = config.fill_mode;
 
                         self.gui.renderer.render(
                                 &mut self.render_graph,
                                 &mut self.gui.ui_scale,
-                                &mut temp_fill_mode,
+                                config,
                                 enabled_features,
                                 &mut self.camera,
                                 &dt,
                                 &mut self.models,
+                                &mut self.selection,
+                                &mut self.measurement,
+                                &mut self.history,
+                                &self.queue,
+                                audio,
                         );
 
-                        if temp_fill_mode != *fill_mode
+                        if config.fill_mode != fill_mode
                         {
-                                log::info!("Fill Mode: {:?}", temp_fill_mode);
+                                log::info!("Fill Mode: {:?}", config.fill_mode);
 
                                 // Create transform bind group layout
                                 let transform_bind_group_layout =
@@ -592,27 +1940,16 @@ impl EngineState
                                 // Request Pipeline Rebuild
                                 self.pipeline_manager.build_geometry_pipeline(
                                         &self.device,
-                                        &self.surface_manager.configuration,
+                                        &self.surface_manager.render_configuration(),
                                         &[
                                                 &self.camera.get_bind_group_layout(&self.device),
                                                 &transform_bind_group_layout,
                                                 &material_bind_group_layout,
                                                 &model_transform_bind_group_layout,
                                         ],
-                                        &temp_fill_mode,
+                                        &config.fill_mode,
                                 );
                         }
-
-                        *fill_mode = temp_fill_mode;
-
-                        self.gui.renderer.end_frame_and_draw(
-                                &self.device,
-                                &self.queue,
-                                encoder,
-                                window.clone().as_ref(),
-                                &frame,
-                                screen_descriptor,
-                        );
                 }
         }
 
@@ -679,11 +2016,11 @@ impl ApplicationHandler<EngineState> for Engine
                         use wasm_bindgen::JsCast;
                         use winit::platform::web::WindowAttributesExtWebSys;
 
-                        const CANVAS_ID: &str = "canvas";
-
                         let window = wgpu::web_sys::window().unwrap_throw();
                         let document = window.document().unwrap_throw();
-                        let canvas = document.get_element_by_id(CANVAS_ID).unwrap_throw();
+                        let canvas = document
+                                .get_element_by_id(&self.config.canvas_id)
+                                .unwrap_throw();
                         let html_canvas_element = canvas.unchecked_into();
 
                         window_attributes =
@@ -694,15 +2031,24 @@ impl ApplicationHandler<EngineState> for Engine
 
                 self.window = Some(window.clone());
 
-                let model_map = self.model_map.clone();
+                let camera_config = self.config.camera.clone();
+                let color_space = self.config.color_space;
+                let hdr = self.config.temporal.enabled();
+                let instance = self.instance.clone();
 
                 #[cfg(not(target_arch = "wasm32"))]
                 {
-                        self.state = Some(pollster::block_on(EngineState::new(window, model_map))
-                                .unwrap_or_else(|e| {
-                                        log::error!("Failed to initialize EngineState: {:?}", e);
-                                        panic!("Failed to initialize EngineState");
-                                }));
+                        self.state = Some(pollster::block_on(EngineState::new(
+                                window,
+                                camera_config,
+                                color_space,
+                                hdr,
+                                instance,
+                        ))
+                        .unwrap_or_else(|e| {
+                                log::error!("Failed to initialize EngineState: {:?}", e);
+                                panic!("Failed to initialize EngineState");
+                        }));
                 }
 
                 #[cfg(target_arch = "wasm32")]
@@ -712,8 +2058,14 @@ impl ApplicationHandler<EngineState> for Engine
                         if let Some(proxy) = self.proxy.take()
                         {
                                 wasm_bindgen_futures::spawn_local(async move {
-                                        let state_result =
-                                                EngineState::new(window, model_map).await;
+                                        let state_result = EngineState::new(
+                                                window,
+                                                camera_config,
+                                                color_space,
+                                                hdr,
+                                                instance,
+                                        )
+                                        .await;
                                         match state_result
                                         {
                                                 Ok(state) =>
@@ -743,9 +2095,32 @@ impl ApplicationHandler<EngineState> for Engine
                 {
                         let state = self.state.as_mut().unwrap();
 
+                        if let Some(dir) = &self.config.pipeline_cache
+                        {
+                                state.pipeline_manager.load_cache(
+                                        &state.device,
+                                        &state.adapter.get_info(),
+                                        dir,
+                                );
+                        }
+
                         state.build_pipelines();
 
-                        state.build_passes();
+                        for (name, wgsl) in &self.shaders
+                        {
+                                state.build_custom_pipeline(name, wgsl);
+                        }
+
+                        state.build_passes(
+                                &self.config.grid,
+                                &self.config.temporal,
+                                self.config.depth_prepass,
+                        );
+
+                        #[cfg(feature = "ui")]
+                        state.apply_ui_settings(&self.config.ui);
+
+                        Loading::start(self);
                 }
         }
 
@@ -772,7 +2147,21 @@ impl ApplicationHandler<EngineState> for Engine
 
                         state.build_pipelines();
 
-                        state.build_passes();
+                        for (name, wgsl) in &self.shaders
+                        {
+                                state.build_custom_pipeline(name, wgsl);
+                        }
+
+                        state.build_passes(
+                                &self.config.grid,
+                                &self.config.temporal,
+                                self.config.depth_prepass,
+                        );
+
+                        #[cfg(feature = "ui")]
+                        state.apply_ui_settings(&self.config.ui);
+
+                        Loading::start(self);
                 }
 
                 self.resize();
@@ -785,22 +2174,49 @@ impl ApplicationHandler<EngineState> for Engine
                 event: WindowEvent,
         )
         {
-                let elapsed = Instant::now() - self.start_time;
-
-                while elapsed - self.last_tick_time >= self.tps_interval
+                // Nothing runs behind the loading screen, see
+                // [`crate::loading`].
+                if !self.loading.is_active()
                 {
-                        self.current_tick += 1;
-                        self.last_tick_time += self.tps_interval;
-                }
+                        let elapsed = Instant::now() - self.start_time;
+                        let mut ticks = 0;
+
+                        while elapsed - self.last_tick_time >= self.tps_interval
+                        {
+                                self.current_tick = self.current_tick.wrapping_add(1);
+                                self.last_tick_time += self.tps_interval;
+                                ticks += 1;
+
+                                ReplaySystem::update(self);
+                                Scheduler::update(self);
+                                SteeringSystem::update(self);
+                                DeterminismAudit::update(self);
+                        }
 
-                let mut behaviors = std::mem::take(&mut self.behavior_list);
+                        self.metrics
+                                .ticks(ticks, self.tps, elapsed.saturating_sub(self.last_tick_time));
 
-                for behaviour in &mut behaviors
-                {
-                        behaviour(self); // now allowed, no borrow conflict
+                        let mut behaviors = std::mem::take(&mut self.behavior_list);
+
+                        for behaviour in &mut behaviors
+                        {
+                                behaviour(self); // now allowed, no borrow conflict
+                        }
+
+                        self.behavior_list = behaviors;
+
+                        StateMachine::update(self);
+
+                        #[cfg(feature = "scripting")]
+                        ScriptSystem::update(self);
                 }
 
-                self.behavior_list = behaviors;
+                if self.shutdown.exit_requested()
+                {
+                        Shutdown::exit(self, event_loop);
+
+                        return;
+                }
 
                 let state = match &mut self.state
                 {
@@ -808,19 +2224,93 @@ impl ApplicationHandler<EngineState> for Engine
                         None => return,
                 };
 
+                #[cfg(feature = "ui")]
                 state.gui
                         .renderer
                         .handle_input(&self.window.as_ref().unwrap(), &event);
 
+                #[cfg(feature = "ui")]
+                let ui_drawn = self.config.enable_debug || self.states.has_ui();
+
+                #[cfg(feature = "ui")]
+                if self.input
+                        .update(ui_drawn.then(|| state.gui.renderer.context()))
+                {
+                        // Keys held while a text field takes focus would never
+                        // see their release otherwise.
+                        if !self.replay.is_playing()
+                        {
+                                self.pressed_keys.clear();
+                        }
+
+                        state.camera.controller.release_all();
+                }
+
                 match event
                 {
                         WindowEvent::CloseRequested =>
                         {
-                                event_loop.exit();
+                                let allowed = WindowHooks::dispatch(
+                                        self,
+                                        crate::window::WindowEvent::CloseRequested,
+                                );
+
+                                if allowed
+                                {
+                                        Shutdown::exit(self, event_loop);
+                                }
+                        }
+                        WindowEvent::Resized(size) =>
+                        {
+                                self.resize();
+
+                                WindowHooks::dispatch(
+                                        self,
+                                        crate::window::WindowEvent::Resized {
+                                                width: size.width,
+                                                height: size.height,
+                                        },
+                                );
+                        }
+                        WindowEvent::Focused(focused) =>
+                        {
+                                WindowHooks::dispatch(
+                                        self,
+                                        crate::window::WindowEvent::Focused(focused),
+                                );
                         }
-                        WindowEvent::Resized(_size) =>
+                        WindowEvent::ScaleFactorChanged {
+                                scale_factor, ..
+                        } =>
                         {
+                                // The canvas follows the device pixel ratio
+                                // on the web, see [`crate::dpi`].
+                                #[cfg(target_arch = "wasm32")]
                                 self.resize();
+
+                                WindowHooks::dispatch(
+                                        self,
+                                        crate::window::WindowEvent::ScaleFactorChanged {
+                                                scale_factor,
+                                        },
+                                );
+                        }
+                        WindowEvent::RedrawRequested if self.loading.is_active() =>
+                        {
+                                self.events.update();
+
+                                UploadQueue::update(self);
+                                Loading::update(self);
+
+                                if let Err(e) = Loading::render(self)
+                                {
+                                        log::error!("Unable to render the loading screen {}", e);
+                                }
+
+                                if let Some(window) = self.window.as_ref()
+                                {
+                                        window.request_redraw();
+                                }
                         }
                         WindowEvent::RedrawRequested =>
                         {
@@ -834,6 +2324,31 @@ impl ApplicationHandler<EngineState> for Engine
 
                                 self.lerp_alpha = alpha;
 
+                                self.events.update();
+
+                                self.audio.update();
+
+                                SaveSystem::update(self);
+                                #[cfg(feature = "ui")]
+                                AssetPanel::update(self);
+                                #[cfg(feature = "ui")]
+                                SelectionPanel::update(self);
+                                MemoryBudget::update(self);
+                                Benchmark::update(self);
+                                CellStreaming::update(self);
+                                UploadQueue::update(self);
+                                TextureStreaming::update(self);
+                                ReflectionProbes::update(self);
+
+                                #[cfg(feature = "net")]
+                                NetSystem::update(self);
+
+                                TweenSystem::update(self);
+                                SteeringSystem::interpolate(self);
+                                AnimationSystem::update(self);
+                                AttachmentSystem::update(self);
+                                Environment::update(self);
+
                                 match self.render(&last_render_time)
                                 {
                                         Ok(_) =>
@@ -866,8 +2381,29 @@ impl ApplicationHandler<EngineState> for Engine
                                 ..
                         } =>
                         {
+                                // Browsers only allow audio after a user gesture.
+                                self.audio.resume();
+
+                                // Presses typed into the UI don't reach the game.
+                                let captured = key_state.is_pressed() && self.input.keyboard();
+
+                                #[cfg(feature = "ui")]
+                                if self.config.enable_debug
+                                        && self.config.ui_focus_key == Some(code as u32)
+                                        && key_state.is_pressed()
+                                {
+                                        state.gui.renderer.navigate(
+                                                if captured { UiNav::Back } else { UiNav::Down },
+                                        );
+
+                                        return;
+                                }
+
+                                // The replay owns the game input while it plays.
                                 match key_state
                                 {
+                                        _ if self.replay.is_playing() || captured =>
+                                        {}
                                         ElementState::Pressed =>
                                         {
                                                 self.pressed_keys.insert(code);
@@ -878,15 +2414,58 @@ impl ApplicationHandler<EngineState> for Engine
                                         }
                                 }
 
-                                state.camera
-                                        .controller
-                                        .handle_key(code, key_state.is_pressed());
+                                if captured
+                                {
+                                        return;
+                                }
+
+                                state.camera.handle_key(code, key_state.is_pressed());
 
                                 if code == KeyCode::Escape && key_state.is_pressed()
                                 {
                                         state.camera.locked_in = !state.camera.locked_in;
                                 }
 
+                                #[cfg(feature = "ui")]
+                                if self.config.enable_debug
+                                        && self.config.frame_key == Some(code as u32)
+                                        && key_state.is_pressed()
+                                        && let Some(bounds) = state
+                                                .selection
+                                                .handles()
+                                                .iter()
+                                                .filter_map(|handle| state.models.get(handle))
+                                                .filter_map(|model| model.world_bounds())
+                                                .reduce(|a, b| a.union(b))
+                                {
+                                        state.camera.frame(&bounds);
+                                }
+
+                                #[cfg(feature = "ui")]
+                                if self.config.enable_debug
+                                        && key_state.is_pressed()
+                                        && (self.pressed_keys.contains(&KeyCode::ControlLeft)
+                                                || self.pressed_keys.contains(&KeyCode::ControlRight))
+                                {
+                                        match code
+                                        {
+                                                KeyCode::KeyZ =>
+                                                {
+                                                        state.history.undo(&mut state.models, &state.queue);
+                                                }
+                                                KeyCode::KeyY =>
+                                                {
+                                                        state.history.redo(&mut state.models, &state.queue);
+                                                }
+                                                KeyCode::KeyD =>
+                                                {
+                                                        self.duplicate_selected();
+                                                }
+                                                _ =>
+                                                {}
+                                        }
+                                }
+
                                 match self.config.debug_toggle_key
                                 {
                                         None =>
@@ -928,7 +2507,7 @@ impl ApplicationHandler<EngineState> for Engine
                                 delta: (dx, dy),
                         } =>
                         {
-                                if state.camera.locked_in
+                                if state.camera.locked_in && !self.input.pointer()
                                 {
                                         state.camera.controller.handle_mouse(dx, dy);
                                 }
@@ -937,6 +2516,16 @@ impl ApplicationHandler<EngineState> for Engine
                         {}
                 }
         }
+
+        /// Tears the engine down before the event loop returns, see
+        /// [`crate::shutdown`].
+        fn exiting(
+                &mut self,
+                _event_loop: &ActiveEventLoop,
+        )
+        {
+                Shutdown::run(self);
+        }
 }
 
 /// A builder for the engine, responsible for preparing configuration
@@ -966,7 +2555,9 @@ impl ApplicationHandler<EngineState> for Engine
 ///
 /// # Example
 ///
-/// ```rust
+/// Needs a `resources/` folder and a display, so it is only compiled.
+///
+/// ```rust,no_run
 /// use oxide::engine::{EngineBuilder, EngineRunner, Engine};
 ///
 /// let engine: Engine = EngineBuilder::new()
@@ -979,7 +2570,7 @@ impl ApplicationHandler<EngineState> for Engine
 /// let runner: EngineRunner = EngineRunner::new(engine).unwrap();
 ///
 /// // EngineState is still None.
-/// assert!(runner.engine.unwrap().state.is_none());
+/// assert!(runner.engines.iter().all(|e| e.state.is_none()));
 ///
 /// // Once run is called, EngineState is constructed.
 /// // runner.run().unwrap();
@@ -1024,6 +2615,56 @@ impl EngineBuilder
                                 start_time: Instant::now(),
                                 config,
                                 model_map,
+                                import_options: HashMap::new(),
+                                events: EventBus::new(),
+                                audio: AudioSystem::new(),
+                                tweens: TweenSystem::new(),
+                                scheduler: Scheduler::new(),
+                                steering: SteeringSystem::new(),
+                                animation: AnimationSystem::new(),
+                                attachment: AttachmentSystem::new(),
+                                states: StateMachine::new(),
+                                memory: MemoryBudget::new(),
+                                texture_streaming: TextureStreaming::new(),
+                                uploads: UploadQueue::new(),
+                                loading: Loading::new(),
+                                cells: CellStreaming::new(),
+                                reflection_probes: ReflectionProbes::new(),
+                                environment: Environment::new(),
+                                shaders: HashMap::new(),
+                                materials: MaterialRegistry::new(),
+                                metrics: Metrics::new(),
+                                input: InputCapture::new(),
+                                camera_constraints: CameraConstraints::new(),
+                                camera_path: CameraPathSystem::new(),
+                                #[cfg(feature = "ui")]
+                                inspectors: Inspectors::new(),
+                                #[cfg(feature = "ui")]
+                                asset_panel: AssetPanel::new(),
+                                #[cfg(feature = "ui")]
+                                selection_panel: SelectionPanel::new(),
+                                #[cfg(feature = "ui")]
+                                console: Console::new(),
+                                #[cfg(feature = "ui")]
+                                minimap: Minimap::new(),
+                                render_hooks: RenderHooks::new(),
+                                polylines: Polylines::new(),
+                                tilemaps: TileMaps::new(),
+                                crowds: Crowds::new(),
+                                window_hooks: WindowHooks::new(),
+                                shutdown: Shutdown::new(),
+                                instance: None,
+                                #[cfg(feature = "scripting")]
+                                scripts: ScriptSystem::new(),
+                                replay: ReplaySystem::new(),
+                                audit: DeterminismAudit::new(),
+                                benchmark: None,
+                                saves: SaveSystem::new(),
+                                rng: Rng::from_os_rng(),
+                                #[cfg(feature = "net")]
+                                net: NetSystem::new(),
+                                #[cfg(target_arch = "wasm32")]
+                                spawned: Default::default(),
                                 state: None,
                                 window: None,
                         },
@@ -1042,6 +2683,17 @@ impl EngineBuilder
                 self
         }
 
+        /// Seeds [`Engine::rng`] with `seed` instead of a random one, so runs
+        /// play out the same.
+        pub fn with_seed(
+                mut self,
+                seed: u64,
+        ) -> Self
+        {
+                self.engine.rng.reseed(seed);
+                self
+        }
+
         pub fn keybind<F>(
                 self,
                 key_code: KeyCode,
@@ -1054,13 +2706,347 @@ impl EngineBuilder
                 self
         }
 
-        /// Render a Debug GUI using `egui`.
+        /// Render a Debug GUI using `egui`. Does nothing without the `ui`
+        /// feature.
         pub fn with_debug_ui(mut self) -> Self
         {
                 self.engine.config.enable_debug = true;
                 self
         }
 
+        /// Draw a reference grid on the XZ plane and the world axes. It can
+        /// also be toggled from the debug UI.
+        pub fn with_grid(mut self) -> Self
+        {
+                self.engine.config.grid.enabled = true;
+                self
+        }
+
+        /// Color the background is cleared to. Can be changed later in the
+        /// debug UI or through [`Engine::config`].
+        pub fn with_clear_color(
+                mut self,
+                r: f32,
+                g: f32,
+                b: f32,
+        ) -> Self
+        {
+                self.engine.config.environment.clear_color = [r, g, b, 1.0];
+                self
+        }
+
+        /// Distance between grid lines and how far from the camera the grid
+        /// fades out. Default is 1 and 50.
+        pub fn with_grid_spacing(
+                mut self,
+                spacing: f32,
+                fade_distance: f32,
+        ) -> Self
+        {
+                self.engine.config.grid.spacing = spacing;
+                self.engine.config.grid.fade_distance = fade_distance;
+                self
+        }
+
+        /// Distance of the near and far clip planes. Default is 0.1 and 1000.
+        pub fn with_clip_planes(
+                mut self,
+                znear: f32,
+                zfar: f32,
+        ) -> Self
+        {
+                self.engine.config.camera.znear = znear;
+                self.engine.config.camera.zfar = zfar;
+                self
+        }
+
+        /// Use reversed-Z depth, which keeps distant geometry from
+        /// z-fighting when the far plane is far away. See
+        /// [`Projection::reversed_z`](crate::camera::Projection::reversed_z).
+        pub fn with_reversed_z(mut self) -> Self
+        {
+                self.engine.config.camera.reversed_z = true;
+                self
+        }
+
+        /// Draws the scene at `aspect`, width over height, with black bars
+        /// where the window is wider or narrower, instead of stretching it.
+        /// See [`CameraConfig::locked_aspect`](crate::camera::CameraConfig::locked_aspect).
+        pub fn with_locked_aspect(
+                mut self,
+                aspect: f32,
+        ) -> Self
+        {
+                self.engine.config.camera.locked_aspect = Some(aspect);
+                self
+        }
+
+        /// Creates the GPU state from `instance` instead of a new one, so
+        /// several engines share it, e.g. `EngineBuilder::instance()` cloned
+        /// for each.
+        pub fn with_instance(
+                mut self,
+                instance: wgpu::Instance,
+        ) -> Self
+        {
+                self.engine.instance = Some(instance);
+                self
+        }
+
+        /// Scales textures larger than `size` down on load, e.g. 8K scans on
+        /// the web, see [`Config::max_texture_size`].
+        pub fn with_max_texture_size(
+                mut self,
+                size: u32,
+        ) -> Self
+        {
+                self.engine.config.max_texture_size = Some(size);
+                self
+        }
+
+        /// Streams the base color textures of models loaded afterwards,
+        /// starting with mips of at most `initial_size` and keeping them
+        /// under `budget` bytes, see [`crate::streaming`].
+        pub fn with_texture_streaming(
+                mut self,
+                initial_size: u32,
+                budget: Option<u64>,
+        ) -> Self
+        {
+                self.engine.texture_streaming.initial_size = Some(initial_size);
+                self.engine.texture_streaming.budget = budget;
+                self
+        }
+
+        /// Logger settings, module levels and the log file, applied when
+        /// the engine is built, see [`crate::log`].
+        pub fn with_log(
+                mut self,
+                log: crate::log::LogConfig,
+        ) -> Self
+        {
+                self.engine.config.log = Some(log);
+                self
+        }
+
+        /// Uploads at most `bytes` of the models loaded at runtime per
+        /// frame, spreading large loads over several frames, see
+        /// [`crate::upload`].
+        pub fn with_upload_budget(
+                mut self,
+                bytes: u64,
+        ) -> Self
+        {
+                self.engine.uploads.bytes_per_frame = Some(bytes);
+                self
+        }
+
+        /// Loads the cells nearer to the camera than `load_distance` and
+        /// unloads the ones farther than `unload_distance`, see
+        /// [`crate::cells`].
+        pub fn with_cell_streaming(
+                mut self,
+                load_distance: f32,
+                unload_distance: f32,
+        ) -> Self
+        {
+                self.engine.cells.load_distance = load_distance;
+                self.engine.cells.unload_distance = unload_distance.max(load_distance);
+                self
+        }
+
+        /// Adds a reflection probe, captured once the startup models are
+        /// loaded, see [`crate::reflection`].
+        pub fn with_reflection_probe(
+                mut self,
+                name: impl Into<String>,
+                probe: ReflectionProbe,
+        ) -> Self
+        {
+                self.engine.add_reflection_probe(name, probe);
+                self
+        }
+
+        /// Shows `screen` while the models added before the engine started
+        /// are loaded, see [`crate::loading`].
+        pub fn with_loading_screen(
+                mut self,
+                screen: LoadingScreen,
+        ) -> Self
+        {
+                self.engine.loading.screen = screen;
+                self
+        }
+
+        /// Id of the canvas element to draw to on the web, `"canvas"` by
+        /// default. `#[oxide_main(canvas = "...")]` sets it on the builder
+        /// it passes in. Engines running side by side need one each.
+        pub fn with_canvas_id(
+                mut self,
+                id: impl Into<String>,
+        ) -> Self
+        {
+                self.engine.config.canvas_id = id.into();
+                self
+        }
+
+        /// Pixels per point of the UI and the web canvas, the display's by
+        /// default. See [`DpiPolicy`].
+        pub fn with_dpi_policy(
+                mut self,
+                dpi: DpiPolicy,
+        ) -> Self
+        {
+                self.engine.config.dpi = dpi;
+                self
+        }
+
+        /// How colors are encoded for the display, sRGB by default. See
+        /// [`ColorSpace`].
+        pub fn with_color_space(
+                mut self,
+                color_space: ColorSpace,
+        ) -> Self
+        {
+                self.engine.config.color_space = color_space;
+                self
+        }
+
+        /// Keys moving the free-fly camera, see [`CameraBindings`].
+        pub fn with_camera_bindings(
+                mut self,
+                bindings: CameraBindings,
+        ) -> Self
+        {
+                self.engine.config.camera.bindings = bindings;
+                self
+        }
+
+        /// Keeps the debug UI scale and layout in `path` between runs, see
+        /// [`Config::load_file`].
+        pub fn with_config_file(
+                mut self,
+                path: impl Into<String>,
+        ) -> Result<Self>
+        {
+                self.engine.config.load_file(path)?;
+
+                Ok(self)
+        }
+
+        /// Key that moves the keyboard focus into the debug UI, where the
+        /// arrow keys, Tab, Enter and Space work like on a web page. Pressing
+        /// it again, or Escape, gives the keyboard back to the game.
+        pub fn with_ui_focus_key(
+                mut self,
+                key_code: KeyCode,
+        ) -> Self
+        {
+                self.engine.config.ui_focus_key = Some(key_code as u32);
+                self
+        }
+
+        /// Key that frames the models selected in the debug UI, F by
+        /// default, see [`Engine::frame_model`]. `None` turns it off.
+        pub fn with_frame_key(
+                mut self,
+                key_code: Option<KeyCode>,
+        ) -> Self
+        {
+                self.engine.config.frame_key = key_code.map(|k| k as u32);
+                self
+        }
+
+        /// Temporal antialiasing, renders into the HDR target, see
+        /// [`crate::renderer::temporal`].
+        pub fn with_taa(mut self) -> Self
+        {
+                self.engine.config.temporal.taa = true;
+                self
+        }
+
+        /// Blurs `motion` over the distance moved in a frame, renders into
+        /// the HDR target.
+        pub fn with_motion_blur(
+                mut self,
+                motion: MotionBlur,
+        ) -> Self
+        {
+                self.engine.config.temporal.motion_blur = motion;
+                self
+        }
+
+        /// Draws the depth of the models before shading them, see
+        /// [`DepthPrePass`].
+        pub fn with_depth_prepass(mut self) -> Self
+        {
+                self.engine.config.depth_prepass = true;
+                self
+        }
+
+        /// Keeps the compiled pipelines in `dir` between runs, one file per
+        /// adapter and driver, so later starts compile less. Native only,
+        /// on backends that support it, see
+        /// [`PipelineManager::load_cache`].
+        pub fn with_pipeline_cache(
+                mut self,
+                dir: impl Into<std::path::PathBuf>,
+        ) -> Self
+        {
+                self.engine.config.pipeline_cache = Some(dir.into());
+                self
+        }
+
+        /// Keeps decoded model files in `dir` between runs, so later starts
+        /// don't decode their textures again, see
+        /// [`crate::resources::cache`]. Native only.
+        pub fn with_import_cache(
+                mut self,
+                dir: impl Into<std::path::PathBuf>,
+        ) -> Self
+        {
+                self.engine.config.import_cache = Some(dir.into());
+                self
+        }
+
+        /// Light shafts around objects in front of the sun, see
+        /// [`crate::renderer::light_shafts`].
+        pub fn with_light_shafts(mut self) -> Self
+        {
+                self.engine.config.environment.sun_shafts = true;
+                self
+        }
+
+        /// Lens flare while the sun is on screen.
+        pub fn with_lens_flare(mut self) -> Self
+        {
+                self.engine.config.environment.sun_flare = true;
+                self
+        }
+
+        /// Runs the day/night cycle with days taking `day_length`, see
+        /// [`crate::environment`].
+        pub fn with_day_night_cycle(
+                mut self,
+                day_length: Duration,
+        ) -> Self
+        {
+                self.engine.environment.day_length = day_length;
+                self.engine.environment.enabled = true;
+                self
+        }
+
+        /// Unload hidden models when the models use more than `bytes` of
+        /// GPU memory, see [`crate::memory`].
+        pub fn with_memory_budget(
+                mut self,
+                bytes: u64,
+        ) -> Self
+        {
+                self.engine.memory.budget = Some(bytes);
+                self
+        }
+
         pub fn with_toggle(
                 mut self,
                 key_code: KeyCode,
@@ -1092,10 +3078,20 @@ impl EngineBuilder
         {
                 self.engine.tps_interval = Duration::from_secs_f32(1.0 / self.engine.tps as f32);
 
+                if let Some(log) = &self.engine.config.log
+                {
+                        crate::log::init(log.clone());
+                }
+
+                #[cfg(not(target_arch = "wasm32"))]
+                crate::crash::set_config(&self.engine.config);
+
                 Ok(self.engine)
         }
 
-        fn instance() -> wgpu::Instance
+        /// The `wgpu` instance an engine creates when none is shared, see
+        /// [`EngineBuilder::with_instance`].
+        pub fn instance() -> wgpu::Instance
         {
                 wgpu::Instance::new(&wgpu::InstanceDescriptor {
                         #[cfg(not(target_arch = "wasm32"))]
@@ -1160,7 +3156,17 @@ impl EngineBuilder
 
                 let desired = wgpu::Features::POLYGON_MODE_LINE
                         | wgpu::Features::POLYGON_MODE_POINT
-                        | wgpu::Features::TIMESTAMP_QUERY;
+                        | wgpu::Features::TIMESTAMP_QUERY
+                        | wgpu::Features::TIMESTAMP_QUERY_INSIDE_ENCODERS
+                        | wgpu::Features::PIPELINE_CACHE;
+
+                // Draws the culled meshes together, see
+                // `crate::renderer::culling`.
+                #[cfg(feature = "gpu-culling")]
+                let desired = desired
+                        | wgpu::Features::INDIRECT_FIRST_INSTANCE
+                        | wgpu::Features::MULTI_DRAW_INDIRECT
+                        | wgpu::Features::MULTI_DRAW_INDIRECT_COUNT;
 
                 let required_features = supported & desired;
 
@@ -1175,7 +3181,16 @@ impl EngineBuilder
                         // Reference <https://gpuweb.github.io/gpuweb/#gpusupportedlimits>
                         required_limits: if cfg!(target_arch = "wasm32")
                         {
-                                wgpu::Limits::downlevel_webgl2_defaults()
+                                // WebGPU has compute shaders and storage
+                                // buffers, e.g. for `gpu-culling`.
+                                if adapter.get_info().backend == wgpu::Backend::BrowserWebGpu
+                                {
+                                        wgpu::Limits::downlevel_defaults()
+                                }
+                                else
+                                {
+                                        wgpu::Limits::downlevel_webgl2_defaults()
+                                }
                         }
                         else
                         {
@@ -0,0 +1,310 @@
+//! Day/night cycle.
+//!
+//! The [`Environment`] moves the sun around the scene over a day and blends
+//! the sky, sun and ambient colors, fog and exposure between keyframes at
+//! times of day. It writes them to
+//! [`Config::environment`](crate::config::Config::environment) every frame
+//! while it runs:
+//!
+//! ```ignore
+//! let mut engine = EngineBuilder::new()
+//!         .with_day_night_cycle(Duration::from_secs(600))
+//!         .build()?;
+//!
+//! // Noon, from a behavior or anywhere else.
+//! engine.environment().set_time_of_day(0.5);
+//! ```
+//!
+//! Times of day go from 0 to 1, 0 is midnight, 0.25 sunrise, 0.5 noon and
+//! 0.75 sunset. The cycle is off until it's enabled, so a scene keeps the
+//! environment it was given.
+
+use crate::engine::Engine;
+use crate::renderer::graph::EnvironmentSettings;
+use instant::Instant;
+use std::time::Duration;
+
+/// Colors and fog of the environment at a time of day.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EnvironmentKey
+{
+        /// Time of day, 0 to 1.
+        pub time: f32,
+        /// Linear RGB of the sky, used as the clear color.
+        pub sky: [f32; 3],
+        pub sun_color: [f32; 3],
+        pub ambient: [f32; 3],
+        pub fog_color: [f32; 3],
+        pub fog_density: f32,
+        pub exposure: f32,
+}
+
+impl EnvironmentKey
+{
+        fn lerp(
+                &self,
+                other: &Self,
+                t: f32,
+        ) -> Self
+        {
+                let mix = |a: f32, b: f32| a + (b - a) * t;
+                let mix3 = |a: [f32; 3], b: [f32; 3]| {
+                        [mix(a[0], b[0]), mix(a[1], b[1]), mix(a[2], b[2])]
+                };
+
+                Self {
+                        time: mix(self.time, other.time),
+                        sky: mix3(self.sky, other.sky),
+                        sun_color: mix3(self.sun_color, other.sun_color),
+                        ambient: mix3(self.ambient, other.ambient),
+                        fog_color: mix3(self.fog_color, other.fog_color),
+                        fog_density: mix(self.fog_density, other.fog_density),
+                        exposure: mix(self.exposure, other.exposure),
+                }
+        }
+}
+
+/// Day/night cycle owned by the [`Engine`], see [`Engine::environment`].
+#[derive(Debug)]
+pub struct Environment
+{
+        /// Writes to the config's environment every frame when set.
+        pub enabled: bool,
+        /// Stops the time, the environment is still applied.
+        pub paused: bool,
+        /// Real time a full day takes.
+        pub day_length: Duration,
+        /// Tilts the sun's path towards +Z, in radians, 0 passes overhead.
+        pub sun_tilt: f32,
+        /// Sorted by time, blended in between and wrapping around midnight.
+        pub keys: Vec<EnvironmentKey>,
+        time_of_day: f32,
+        last_update: Option<Instant>,
+}
+
+impl Default for Environment
+{
+        fn default() -> Self
+        {
+                Self {
+                        enabled: false,
+                        paused: false,
+                        day_length: Duration::from_secs(600),
+                        sun_tilt: 0.4,
+                        keys: Self::default_keys(),
+                        time_of_day: 0.5,
+                        last_update: None,
+                }
+        }
+}
+
+impl Environment
+{
+        pub fn new() -> Self
+        {
+                Self::default()
+        }
+
+        /// Night, sunrise, noon and sunset.
+        pub fn default_keys() -> Vec<EnvironmentKey>
+        {
+                vec![
+                        EnvironmentKey {
+                                time: 0.0,
+                                sky: [0.005, 0.008, 0.02],
+                                sun_color: [0.02, 0.03, 0.06],
+                                ambient: [0.04, 0.05, 0.08],
+                                fog_color: [0.005, 0.008, 0.02],
+                                fog_density: 0.02,
+                                exposure: 1.4,
+                        },
+                        EnvironmentKey {
+                                time: 0.25,
+                                sky: [0.6, 0.3, 0.15],
+                                sun_color: [1.0, 0.5, 0.25],
+                                ambient: [0.25, 0.2, 0.2],
+                                fog_color: [0.6, 0.35, 0.2],
+                                fog_density: 0.015,
+                                exposure: 1.1,
+                        },
+                        EnvironmentKey {
+                                time: 0.5,
+                                sky: [0.35, 0.55, 0.9],
+                                sun_color: [1.0, 0.95, 0.85],
+                                ambient: [0.35, 0.4, 0.5],
+                                fog_color: [0.55, 0.65, 0.8],
+                                fog_density: 0.005,
+                                exposure: 1.0,
+                        },
+                        EnvironmentKey {
+                                time: 0.75,
+                                sky: [0.55, 0.25, 0.2],
+                                sun_color: [1.0, 0.4, 0.2],
+                                ambient: [0.2, 0.15, 0.2],
+                                fog_color: [0.5, 0.3, 0.25],
+                                fog_density: 0.015,
+                                exposure: 1.1,
+                        },
+                ]
+        }
+
+        pub fn time_of_day(&self) -> f32
+        {
+                self.time_of_day
+        }
+
+        /// Jumps to a time of day, 0 to 1, and enables the cycle.
+        pub fn set_time_of_day(
+                &mut self,
+                time: f32,
+        )
+        {
+                self.time_of_day = time.rem_euclid(1.0);
+                self.enabled = true;
+        }
+
+        /// Direction towards the sun at the current time, below the horizon
+        /// at night.
+        pub fn sun_direction(&self) -> [f32; 3]
+        {
+                let angle = (self.time_of_day - 0.25) * std::f32::consts::TAU;
+
+                let (sin, cos) = angle.sin_cos();
+
+                [cos, sin * self.sun_tilt.cos(), sin * self.sun_tilt.sin()]
+        }
+
+        /// The keys blended at the current time.
+        pub fn current(&self) -> Option<EnvironmentKey>
+        {
+                let t = self.time_of_day;
+
+                let next = self.keys.iter().position(|key| key.time > t);
+
+                let (from, to) = match next
+                {
+                        Some(0) | None => (self.keys.last()?, self.keys.first()?),
+                        Some(i) => (&self.keys[i - 1], &self.keys[i]),
+                };
+
+                // The span wraps around midnight when `to` is earlier.
+                let span = (to.time - from.time).rem_euclid(1.0);
+                let offset = (t - from.time).rem_euclid(1.0);
+
+                let blend = if span > 0.0 { offset / span } else { 0.0 };
+
+                Some(from.lerp(to, blend))
+        }
+
+        /// Writes the sun and the blended key to `settings`.
+        pub fn apply(
+                &self,
+                settings: &mut EnvironmentSettings,
+        )
+        {
+                // The moon, opposite the sun, lights the night.
+                let [x, y, z] = self.sun_direction();
+
+                settings.sun_direction = if y < 0.0 { [-x, -y, -z] } else { [x, y, z] };
+
+                let Some(key) = self.current()
+                else
+                {
+                        return;
+                };
+
+                let [r, g, b] = key.sky;
+
+                settings.clear_color = [r, g, b, 1.0];
+                settings.sun_color = key.sun_color;
+                settings.ambient = key.ambient;
+                settings.fog_color = key.fog_color;
+                settings.fog_density = key.fog_density;
+                settings.exposure = key.exposure;
+        }
+
+        /// Advances the time and applies the environment.
+        ///
+        /// Called by the engine once per frame.
+        pub(crate) fn update(engine: &mut Engine)
+        {
+                let now = Instant::now();
+
+                let environment = &mut engine.environment;
+
+                let dt = environment
+                        .last_update
+                        .replace(now)
+                        .map(|last| now - last)
+                        .unwrap_or_default();
+
+                if !environment.enabled
+                {
+                        return;
+                }
+
+                if !environment.paused && !environment.day_length.is_zero()
+                {
+                        let days = dt.as_secs_f32() / environment.day_length.as_secs_f32();
+
+                        environment.time_of_day = (environment.time_of_day + days).rem_euclid(1.0);
+                }
+
+                environment.apply(&mut engine.config.environment);
+        }
+
+        #[cfg(feature = "ui")]
+        pub(crate) fn ui(
+                &mut self,
+                ctx: &egui::Context,
+        )
+        {
+                egui::Window::new("Environment")
+                        .default_open(false)
+                        .show(ctx, |ui| {
+                                ui.checkbox(&mut self.enabled, "Day/night cycle");
+
+                                ui.add_enabled_ui(self.enabled, |ui| {
+                                        let mut time = self.time_of_day;
+
+                                        let hours = time * 24.0;
+
+                                        if ui.add(egui::Slider::new(&mut time, 0.0..=1.0).text(
+                                                format!(
+                                                        "{:02}:{:02}",
+                                                        hours as u32,
+                                                        (hours.fract() * 60.0) as u32
+                                                ),
+                                        ))
+                                        .changed()
+                                        {
+                                                self.set_time_of_day(time);
+                                        }
+
+                                        ui.checkbox(&mut self.paused, "Paused");
+
+                                        let mut seconds = self.day_length.as_secs_f32();
+
+                                        if ui.add(egui::Slider::new(&mut seconds, 10.0..=3600.0)
+                                                .logarithmic(true)
+                                                .text("Day length (s)"))
+                                                .changed()
+                                        {
+                                                self.day_length = Duration::from_secs_f32(seconds);
+                                        }
+
+                                        ui.add(egui::Slider::new(&mut self.sun_tilt, -1.5..=1.5)
+                                                .text("Sun tilt"));
+                                });
+                        });
+        }
+}
+
+impl Engine
+{
+        /// The day/night cycle, e.g. to set the time of day from a behavior.
+        pub fn environment(&mut self) -> &mut Environment
+        {
+                &mut self.environment
+        }
+}
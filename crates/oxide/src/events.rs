@@ -0,0 +1,226 @@
+//! Typed event bus for messages between engine subsystems and behaviors.
+//!
+//! Any `'static` type can be an event. Events are published with
+//! [`EventBus::publish`] and read with an [`EventReader`], which remembers
+//! what it has already seen. Behaviors run several times per frame, so a
+//! reader kept in the behavior's closure sees every event exactly once.
+//!
+//! Events are kept for two frames, a reader that isn't polled for longer
+//! misses them.
+//!
+//! ```ignore
+//! let mut reader = EventReader::<NetEvent>::new();
+//!
+//! engine.register_behavior(move |eng| {
+//!         for event in reader.read(&eng.events)
+//!         {
+//!                 log::info!("{:?}", event);
+//!         }
+//! });
+//! ```
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+/// Events of one type, double buffered by frame.
+struct Queue<E>
+{
+        /// Events published during the previous frame.
+        previous: Vec<E>,
+
+        /// Events published during the current frame.
+        current: Vec<E>,
+
+        /// Sequence number of `previous[0]`.
+        start: u64,
+}
+
+impl<E> Queue<E>
+{
+        fn new() -> Self
+        {
+                Self {
+                        previous: Vec::new(),
+                        current: Vec::new(),
+                        start: 0,
+                }
+        }
+
+        fn end(&self) -> u64
+        {
+                self.start + (self.previous.len() + self.current.len()) as u64
+        }
+}
+
+/// Lets [`EventBus::update`] swap the buffers without knowing the type.
+trait AnyQueue
+{
+        fn update(&mut self);
+
+        fn len(&self) -> usize;
+
+        fn as_any(&self) -> &dyn Any;
+
+        fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<E: 'static> AnyQueue for Queue<E>
+{
+        fn update(&mut self)
+        {
+                self.start += self.previous.len() as u64;
+                self.previous = std::mem::take(&mut self.current);
+        }
+
+        fn len(&self) -> usize
+        {
+                self.previous.len() + self.current.len()
+        }
+
+        fn as_any(&self) -> &dyn Any
+        {
+                self
+        }
+
+        fn as_any_mut(&mut self) -> &mut dyn Any
+        {
+                self
+        }
+}
+
+/// Event queues of every event type, owned by the
+/// [`Engine`](crate::engine::Engine).
+#[derive(Default)]
+pub struct EventBus
+{
+        queues: HashMap<TypeId, Box<dyn AnyQueue>>,
+}
+
+impl std::fmt::Debug for EventBus
+{
+        fn fmt(
+                &self,
+                f: &mut std::fmt::Formatter<'_>,
+        ) -> std::fmt::Result
+        {
+                f.debug_struct("EventBus")
+                        .field("types", &self.queues.len())
+                        .field("events", &self.queues.values().map(|q| q.len()).sum::<usize>())
+                        .finish()
+        }
+}
+
+impl EventBus
+{
+        pub fn new() -> Self
+        {
+                Self::default()
+        }
+
+        pub fn publish<E: 'static>(
+                &mut self,
+                event: E,
+        )
+        {
+                self.queues
+                        .entry(TypeId::of::<E>())
+                        .or_insert_with(|| Box::new(Queue::<E>::new()))
+                        .as_any_mut()
+                        .downcast_mut::<Queue<E>>()
+                        .expect("Event queue of the wrong type")
+                        .current
+                        .push(event);
+        }
+
+        /// Every buffered event of type `E`, oldest first. Prefer an
+        /// [`EventReader`], this returns the same events on every call.
+        pub fn iter<E: 'static>(&self) -> impl Iterator<Item = &E>
+        {
+                self.queue::<E>()
+                        .into_iter()
+                        .flat_map(|q| q.previous.iter().chain(q.current.iter()))
+        }
+
+        fn queue<E: 'static>(&self) -> Option<&Queue<E>>
+        {
+                self.queues
+                        .get(&TypeId::of::<E>())
+                        .and_then(|q| q.as_any().downcast_ref::<Queue<E>>())
+        }
+
+        /// Drops the events of the frame before last.
+        ///
+        /// Called by the engine at the start of every frame.
+        pub(crate) fn update(&mut self)
+        {
+                for queue in self.queues.values_mut()
+                {
+                        queue.update();
+                }
+        }
+}
+
+/// Cursor into the events of type `E` on an [`EventBus`].
+pub struct EventReader<E>
+{
+        /// Sequence number of the next unread event.
+        next: u64,
+
+        _marker: PhantomData<fn() -> E>,
+}
+
+impl<E> std::fmt::Debug for EventReader<E>
+{
+        fn fmt(
+                &self,
+                f: &mut std::fmt::Formatter<'_>,
+        ) -> std::fmt::Result
+        {
+                f.debug_struct("EventReader")
+                        .field("next", &self.next)
+                        .finish()
+        }
+}
+
+impl<E> Default for EventReader<E>
+{
+        fn default() -> Self
+        {
+                Self::new()
+        }
+}
+
+impl<E> EventReader<E>
+{
+        /// Creates a reader that also sees the events still buffered.
+        pub fn new() -> Self
+        {
+                Self {
+                        next: 0,
+                        _marker: PhantomData,
+                }
+        }
+}
+
+impl<E: 'static> EventReader<E>
+{
+        /// Events published since the last call, oldest first.
+        pub fn read<'a>(
+                &mut self,
+                bus: &'a EventBus,
+        ) -> impl Iterator<Item = &'a E>
+        {
+                let queue = bus.queue::<E>();
+
+                let (start, end) = queue.map(|q| (q.start, q.end())).unwrap_or((0, 0));
+
+                let skip = self.next.saturating_sub(start) as usize;
+
+                self.next = end;
+
+                queue.into_iter()
+                        .flat_map(|q| q.previous.iter().chain(q.current.iter()))
+                        .skip(skip)
+        }
+}
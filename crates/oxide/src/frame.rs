@@ -0,0 +1,168 @@
+//! Custom wgpu drawing without a
+//! [`RenderPass`](crate::renderer::graph::RenderPass).
+//!
+//! Hooks registered with [`Engine::on_render`] run every frame after the
+//! render graph and before the UI. They get the device, the queue and the
+//! frame's encoder and record into the same frame, so what they draw ends
+//! up over the scene and under the debug UI:
+//!
+//! ```ignore
+//! engine.on_render(move |frame| {
+//!         let mut pass = frame.begin_render_pass("overlay");
+//!
+//!         pass.set_pipeline(&pipeline);
+//!         pass.set_bind_group(0, frame.camera, &[]);
+//!         pass.draw(0..3, 0..1);
+//! });
+//! ```
+//!
+//! Pipelines are created once with [`Engine::device`] and
+//! [`FrameContext::format`], after the window exists, e.g. from a behavior
+//! on its first run.
+
+use crate::engine::Engine;
+use crate::renderer::readback::{Readback, ReadbackCallback, ReadbackResult};
+
+/// What a render hook gets for the current frame.
+pub struct FrameContext<'a>
+{
+        pub device: &'a wgpu::Device,
+        pub queue: &'a wgpu::Queue,
+
+        /// Submitted after the render graph's passes.
+        pub encoder: &'a mut wgpu::CommandEncoder,
+
+        /// Color target the scene was drawn to.
+        pub view: &'a wgpu::TextureView,
+
+        /// Depth of the scene, see
+        /// [`Texture::DEPTH_FORMAT`](crate::texture::Texture::DEPTH_FORMAT).
+        pub depth: &'a wgpu::TextureView,
+
+        /// Camera uniforms in the layout of
+        /// [`Camera::get_bind_group_layout`](crate::camera::Camera::get_bind_group_layout).
+        pub camera: &'a wgpu::BindGroup,
+
+        /// Format of [`FrameContext::view`], the one pipelines target.
+        pub format: wgpu::TextureFormat,
+
+        pub width: u32,
+        pub height: u32,
+
+        /// Mapped by the engine once the frame was submitted.
+        pub(crate) readbacks: &'a mut Vec<(Readback, ReadbackCallback)>,
+}
+
+impl FrameContext<'_>
+{
+        /// A pass over the scene that keeps its color and depth.
+        pub fn begin_render_pass(
+                &mut self,
+                label: &str,
+        ) -> wgpu::RenderPass<'_>
+        {
+                self.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        label: Some(label),
+                        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                                view: self.view,
+                                resolve_target: None,
+                                ops: wgpu::Operations {
+                                        load: wgpu::LoadOp::Load,
+                                        store: wgpu::StoreOp::Store,
+                                },
+                        })],
+                        depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                                view: self.depth,
+                                depth_ops: Some(wgpu::Operations {
+                                        load: wgpu::LoadOp::Load,
+                                        store: wgpu::StoreOp::Store,
+                                }),
+                                stencil_ops: None,
+                        }),
+                        timestamp_writes: None,
+                        occlusion_query_set: None,
+                })
+        }
+
+        /// Hands the bytes of `readback`, recorded into
+        /// [`FrameContext::encoder`], to `callback` once the frame finished
+        /// on the GPU, see [`crate::renderer::readback`].
+        pub fn read_back(
+                &mut self,
+                readback: Readback,
+                callback: impl FnOnce(ReadbackResult) + wgpu::WasmNotSend + 'static,
+        )
+        {
+                self.readbacks.push((readback, Box::new(callback)));
+        }
+}
+
+pub type RenderHook = Box<dyn FnMut(&mut FrameContext)>;
+
+/// Registered hooks, owned by the [`Engine`].
+#[derive(Default)]
+pub struct RenderHooks
+{
+        hooks: Vec<RenderHook>,
+}
+
+impl std::fmt::Debug for RenderHooks
+{
+        fn fmt(
+                &self,
+                f: &mut std::fmt::Formatter<'_>,
+        ) -> std::fmt::Result
+        {
+                f.debug_struct("RenderHooks")
+                        .field("hooks", &self.hooks.len())
+                        .finish()
+        }
+}
+
+impl RenderHooks
+{
+        pub fn new() -> Self
+        {
+                Self::default()
+        }
+
+        /// Runs the hooks in the order they were registered.
+        ///
+        /// Called by the engine after the render graph.
+        pub(crate) fn run(
+                &mut self,
+                frame: &mut FrameContext,
+        )
+        {
+                for hook in &mut self.hooks
+                {
+                        hook(frame);
+                }
+        }
+}
+
+impl Engine
+{
+        /// Calls `hook` every frame to record custom draws, see
+        /// [`crate::frame`].
+        pub fn on_render<F>(
+                &mut self,
+                hook: F,
+        ) where
+                F: 'static + FnMut(&mut FrameContext),
+        {
+                self.render_hooks.hooks.push(Box::new(hook));
+        }
+
+        /// The GPU device, `None` until the window is created.
+        pub fn device(&self) -> Option<&wgpu::Device>
+        {
+                self.state.as_ref().map(|state| &state.device)
+        }
+
+        /// The GPU queue, `None` until the window is created.
+        pub fn queue(&self) -> Option<&wgpu::Queue>
+        {
+                self.state.as_ref().map(|state| &state.queue)
+        }
+}
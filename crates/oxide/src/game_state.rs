@@ -0,0 +1,543 @@
+//! Game states for app flow, e.g. menu, loading, gameplay and pause.
+//!
+//! A [`GameState`] bundles behaviors, an optional `egui` UI and optionally a
+//! scene, the model handles that should only be visible while the state is.
+//! States live on a stack in the engine's [`StateMachine`]:
+//! - Only the top state's behaviors run, lower states are paused.
+//! - Only the top state's UI is drawn, unless a lower state opts in with
+//!   [`GameState::draw_when_paused`], e.g. a HUD under a pause menu.
+//! - Behaviors registered with [`Engine::register_behavior`] are not part of
+//!   any state and always run.
+//!
+//! Transitions are queued and applied after the behaviors of the frame have
+//! run, so a state can safely replace itself.
+
+use crate::engine::{Behavior, Engine};
+use crate::model::Model;
+use std::collections::{HashMap, HashSet};
+
+/// UI of a state, drawn every frame while the state is visible.
+///
+/// Gets the transition queue so menus can push, pop and replace states from
+/// buttons.
+#[cfg(feature = "ui")]
+pub type StateUi = Box<dyn FnMut(&egui::Context, &mut Transitions)>;
+
+/// A stack change requested through [`Transitions`].
+pub enum Transition
+{
+        Push(GameState),
+        Pop,
+        Replace(GameState),
+}
+
+impl std::fmt::Debug for Transition
+{
+        fn fmt(
+                &self,
+                f: &mut std::fmt::Formatter<'_>,
+        ) -> std::fmt::Result
+        {
+                match self
+                {
+                        Transition::Push(s) => write!(f, "Push({})", s.name),
+                        Transition::Pop => write!(f, "Pop"),
+                        Transition::Replace(s) => write!(f, "Replace({})", s.name),
+                }
+        }
+}
+
+/// Queue of pending transitions.
+#[derive(Debug, Default)]
+pub struct Transitions
+{
+        queue: Vec<Transition>,
+}
+
+impl Transitions
+{
+        /// Pauses the current state and makes `state` the top one.
+        pub fn push(
+                &mut self,
+                state: GameState,
+        )
+        {
+                self.queue.push(Transition::Push(state));
+        }
+
+        /// Leaves the current state and resumes the one below.
+        pub fn pop(&mut self)
+        {
+                self.queue.push(Transition::Pop);
+        }
+
+        /// Leaves the current state and enters `state` in its place.
+        pub fn replace(
+                &mut self,
+                state: GameState,
+        )
+        {
+                self.queue.push(Transition::Replace(state));
+        }
+
+        pub fn is_empty(&self) -> bool
+        {
+                self.queue.is_empty()
+        }
+}
+
+/// One entry of the [`StateMachine`] stack, built with the `with_*` methods.
+pub struct GameState
+{
+        name: String,
+
+        behaviors: Vec<Behavior>,
+
+        #[cfg(feature = "ui")]
+        ui: Option<StateUi>,
+
+        /// Model handles shown while this state is visible.
+        scene: Vec<String>,
+
+        on_enter: Option<Behavior>,
+
+        on_exit: Option<Behavior>,
+
+        on_pause: Option<Behavior>,
+
+        on_resume: Option<Behavior>,
+
+        draw_when_paused: bool,
+}
+
+impl std::fmt::Debug for GameState
+{
+        fn fmt(
+                &self,
+                f: &mut std::fmt::Formatter<'_>,
+        ) -> std::fmt::Result
+        {
+                let mut debug = f.debug_struct("GameState");
+
+                debug.field("name", &self.name)
+                        .field("behaviors", &self.behaviors.len());
+
+                #[cfg(feature = "ui")]
+                debug.field("ui", &self.ui.is_some());
+
+                debug.field("scene", &self.scene)
+                        .field("draw_when_paused", &self.draw_when_paused)
+                        .finish()
+        }
+}
+
+impl GameState
+{
+        pub fn new(name: impl Into<String>) -> Self
+        {
+                Self {
+                        name: name.into(),
+                        behaviors: Vec::new(),
+                        #[cfg(feature = "ui")]
+                        ui: None,
+                        scene: Vec::new(),
+                        on_enter: None,
+                        on_exit: None,
+                        on_pause: None,
+                        on_resume: None,
+                        draw_when_paused: false,
+                }
+        }
+
+        pub fn name(&self) -> &str
+        {
+                &self.name
+        }
+
+        /// Adds a behavior that runs every frame while this is the top state.
+        pub fn with_behavior<F>(
+                mut self,
+                f: F,
+        ) -> Self
+        where
+                F: 'static + FnMut(&mut Engine),
+        {
+                self.behaviors.push(Box::new(f));
+                self
+        }
+
+        #[cfg(feature = "ui")]
+        pub fn with_ui<F>(
+                mut self,
+                f: F,
+        ) -> Self
+        where
+                F: 'static + FnMut(&egui::Context, &mut Transitions),
+        {
+                self.ui = Some(Box::new(f));
+                self
+        }
+
+        /// Models, by the handle given to [`Engine::add_model`], that belong
+        /// to this state. They are hidden while another state with a scene
+        /// is on top.
+        pub fn with_scene<I, S>(
+                mut self,
+                models: I,
+        ) -> Self
+        where
+                I: IntoIterator<Item = S>,
+                S: Into<String>,
+        {
+                self.scene = models.into_iter().map(Into::into).collect();
+                self
+        }
+
+        pub fn on_enter<F>(
+                mut self,
+                f: F,
+        ) -> Self
+        where
+                F: 'static + FnMut(&mut Engine),
+        {
+                self.on_enter = Some(Box::new(f));
+                self
+        }
+
+        pub fn on_exit<F>(
+                mut self,
+                f: F,
+        ) -> Self
+        where
+                F: 'static + FnMut(&mut Engine),
+        {
+                self.on_exit = Some(Box::new(f));
+                self
+        }
+
+        /// Called when another state is pushed on top of this one.
+        pub fn on_pause<F>(
+                mut self,
+                f: F,
+        ) -> Self
+        where
+                F: 'static + FnMut(&mut Engine),
+        {
+                self.on_pause = Some(Box::new(f));
+                self
+        }
+
+        /// Called when the state above this one is popped.
+        pub fn on_resume<F>(
+                mut self,
+                f: F,
+        ) -> Self
+        where
+                F: 'static + FnMut(&mut Engine),
+        {
+                self.on_resume = Some(Box::new(f));
+                self
+        }
+
+        /// Keeps drawing this state's UI and scene while it's paused.
+        pub fn draw_when_paused(mut self) -> Self
+        {
+                self.draw_when_paused = true;
+                self
+        }
+
+        fn call(
+                hook: &mut Option<Behavior>,
+                engine: &mut Engine,
+        )
+        {
+                if let Some(f) = hook
+                {
+                        f(engine);
+                }
+        }
+}
+
+/// Stack of [`GameState`]s owned by the [`Engine`].
+#[derive(Debug, Default)]
+pub struct StateMachine
+{
+        stack: Vec<GameState>,
+
+        pending: Transitions,
+
+        /// Every model that appeared in a scene, so models of popped states
+        /// get hidden too.
+        scene_models: HashSet<String>,
+
+        scene_dirty: bool,
+}
+
+impl StateMachine
+{
+        pub fn new() -> Self
+        {
+                Self::default()
+        }
+
+        /// See [`Transitions::push`].
+        pub fn push(
+                &mut self,
+                state: GameState,
+        )
+        {
+                self.pending.push(state);
+        }
+
+        /// See [`Transitions::pop`].
+        pub fn pop(&mut self)
+        {
+                self.pending.pop();
+        }
+
+        /// See [`Transitions::replace`].
+        pub fn replace(
+                &mut self,
+                state: GameState,
+        )
+        {
+                self.pending.replace(state);
+        }
+
+        /// Name of the top state.
+        pub fn current(&self) -> Option<&str>
+        {
+                self.stack.last().map(|s| s.name())
+        }
+
+        /// Names from the bottom to the top of the stack.
+        pub fn names(&self) -> Vec<&str>
+        {
+                self.stack.iter().map(|s| s.name()).collect()
+        }
+
+        pub fn len(&self) -> usize
+        {
+                self.stack.len()
+        }
+
+        pub fn is_empty(&self) -> bool
+        {
+                self.stack.is_empty()
+        }
+
+        /// Whether any state is drawn with a UI.
+        #[cfg(feature = "ui")]
+        pub fn has_ui(&self) -> bool
+        {
+                self.visible().any(|s| s.ui.is_some())
+        }
+
+        /// Whether any state is drawn with a UI, never without the `ui`
+        /// feature.
+        #[cfg(not(feature = "ui"))]
+        pub fn has_ui(&self) -> bool
+        {
+                false
+        }
+
+        /// The top state and the paused states below it that are still drawn.
+        fn visible(&self) -> impl Iterator<Item = &GameState>
+        {
+                let top = self.stack.len().saturating_sub(1);
+
+                self.stack
+                        .iter()
+                        .enumerate()
+                        .filter(move |(i, s)| *i == top || s.draw_when_paused)
+                        .map(|(_, s)| s)
+        }
+
+        /// Draws the UI of every visible state, bottom to top.
+        #[cfg(feature = "ui")]
+        pub fn ui(
+                &mut self,
+                ctx: &egui::Context,
+        )
+        {
+                let top = self.stack.len().saturating_sub(1);
+
+                for (i, state) in self.stack.iter_mut().enumerate()
+                {
+                        if i != top && !state.draw_when_paused
+                        {
+                                continue;
+                        }
+
+                        if let Some(ui) = &mut state.ui
+                        {
+                                ui(ctx, &mut self.pending);
+                        }
+                }
+        }
+
+        /// Applies queued transitions and runs the top state's behaviors.
+        ///
+        /// Called by the engine after the global behaviors, every time they
+        /// run.
+        pub(crate) fn update(engine: &mut Engine)
+        {
+                Self::apply_transitions(engine);
+
+                // The stack can't change while the behaviors run, transitions
+                // are only queued.
+                let mut behaviors = match engine.states.stack.last_mut()
+                {
+                        Some(top) => std::mem::take(&mut top.behaviors),
+                        None => Vec::new(),
+                };
+
+                for behavior in &mut behaviors
+                {
+                        behavior(engine);
+                }
+
+                if let Some(top) = engine.states.stack.last_mut()
+                {
+                        top.behaviors = behaviors;
+                }
+
+                Self::apply_transitions(engine);
+
+                engine.states
+                        .update_scene(engine.state.as_mut().map(|s| &mut s.models));
+        }
+
+        fn apply_transitions(engine: &mut Engine)
+        {
+                // Hooks may queue transitions of their own, but a state that
+                // keeps replacing itself on enter would never settle.
+                for _ in 0..16
+                {
+                        let queue = std::mem::take(&mut engine.states.pending.queue);
+
+                        if queue.is_empty()
+                        {
+                                return;
+                        }
+
+                        for transition in queue
+                        {
+                                log::info!("Game state transition: {:?}", transition);
+
+                                match transition
+                                {
+                                        Transition::Push(state) =>
+                                        {
+                                                if let Some(mut top) = engine.states.stack.pop()
+                                                {
+                                                        GameState::call(&mut top.on_pause, engine);
+                                                        engine.states.stack.push(top);
+                                                }
+
+                                                Self::enter(engine, state);
+                                        }
+                                        Transition::Pop =>
+                                        {
+                                                Self::exit(engine);
+
+                                                if let Some(mut top) = engine.states.stack.pop()
+                                                {
+                                                        GameState::call(&mut top.on_resume, engine);
+                                                        engine.states.stack.push(top);
+                                                }
+                                        }
+                                        Transition::Replace(state) =>
+                                        {
+                                                Self::exit(engine);
+                                                Self::enter(engine, state);
+                                        }
+                                }
+                        }
+
+                        engine.states.scene_dirty = true;
+                }
+
+                log::warn!("Game state transitions didn't settle, dropping the rest");
+
+                engine.states.pending.queue.clear();
+        }
+
+        fn enter(
+                engine: &mut Engine,
+                mut state: GameState,
+        )
+        {
+                engine.states
+                        .scene_models
+                        .extend(state.scene.iter().cloned());
+
+                GameState::call(&mut state.on_enter, engine);
+
+                engine.states.stack.push(state);
+        }
+
+        fn exit(engine: &mut Engine)
+        {
+                if let Some(mut state) = engine.states.stack.pop()
+                {
+                        GameState::call(&mut state.on_exit, engine);
+                }
+        }
+
+        /// Shows the scenes of visible states and hides every other scene
+        /// model. Models that aren't in any scene are left alone.
+        fn update_scene(
+                &mut self,
+                models: Option<&mut HashMap<String, Model>>,
+        )
+        {
+                // Models are loaded asynchronously on the web, retry until
+                // they exist.
+                let models = match models
+                {
+                        Some(m) if self.scene_dirty => m,
+                        _ => return,
+                };
+
+                let visible: HashSet<&String> =
+                        self.visible().flat_map(|s| s.scene.iter()).collect();
+
+                for name in &self.scene_models
+                {
+                        if let Some(model) = models.get_mut(name)
+                        {
+                                model.visible = visible.contains(name);
+                        }
+                }
+
+                self.scene_dirty = false;
+        }
+}
+
+impl Engine
+{
+        /// See [`Transitions::push`].
+        pub fn push_state(
+                &mut self,
+                state: GameState,
+        )
+        {
+                self.states.push(state);
+        }
+
+        /// See [`Transitions::pop`].
+        pub fn pop_state(&mut self)
+        {
+                self.states.pop();
+        }
+
+        /// See [`Transitions::replace`].
+        pub fn replace_state(
+                &mut self,
+                state: GameState,
+        )
+        {
+                self.states.replace(state);
+        }
+}
@@ -0,0 +1,105 @@
+use cgmath::{Matrix4, Point3, Transform, Vector3};
+
+/// Axis aligned bounding box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb
+{
+        pub min: Point3<f32>,
+        pub max: Point3<f32>,
+}
+
+impl Aabb
+{
+        pub fn new(
+                min: Point3<f32>,
+                max: Point3<f32>,
+        ) -> Self
+        {
+                Self {
+                        min,
+                        max,
+                }
+        }
+
+        /// Smallest box containing every point, `None` without points.
+        pub fn from_points(points: impl IntoIterator<Item = Point3<f32>>) -> Option<Self>
+        {
+                points.into_iter().fold(None, |aabb: Option<Self>, p| {
+                        Some(match aabb
+                        {
+                                Some(aabb) => aabb.extend(p),
+                                None => Self::new(p, p),
+                        })
+                })
+        }
+
+        pub fn extend(
+                self,
+                p: Point3<f32>,
+        ) -> Self
+        {
+                Self {
+                        min: Point3::new(
+                                self.min.x.min(p.x),
+                                self.min.y.min(p.y),
+                                self.min.z.min(p.z),
+                        ),
+                        max: Point3::new(
+                                self.max.x.max(p.x),
+                                self.max.y.max(p.y),
+                                self.max.z.max(p.z),
+                        ),
+                }
+        }
+
+        pub fn union(
+                self,
+                other: Self,
+        ) -> Self
+        {
+                self.extend(other.min).extend(other.max)
+        }
+
+        pub fn center(&self) -> Point3<f32>
+        {
+                Point3::new(
+                        (self.min.x + self.max.x) * 0.5,
+                        (self.min.y + self.max.y) * 0.5,
+                        (self.min.z + self.max.z) * 0.5,
+                )
+        }
+
+        pub fn size(&self) -> Vector3<f32>
+        {
+                self.max - self.min
+        }
+
+        /// The 8 corners, the first four at `min.z`, both in the order
+        /// (min, min), (max, min), (max, max), (min, max) in x and y.
+        pub fn corners(&self) -> [Point3<f32>; 8]
+        {
+                let (a, b) = (self.min, self.max);
+
+                [
+                        Point3::new(a.x, a.y, a.z),
+                        Point3::new(b.x, a.y, a.z),
+                        Point3::new(b.x, b.y, a.z),
+                        Point3::new(a.x, b.y, a.z),
+                        Point3::new(a.x, a.y, b.z),
+                        Point3::new(b.x, a.y, b.z),
+                        Point3::new(b.x, b.y, b.z),
+                        Point3::new(a.x, b.y, b.z),
+                ]
+        }
+
+        /// Box around the transformed corners, which is larger than the
+        /// transformed box when there is rotation.
+        pub fn transform(
+                &self,
+                matrix: &Matrix4<f32>,
+        ) -> Self
+        {
+                Self::from_points(self.corners().map(|c| matrix.transform_point(c)))
+                        .unwrap_or(*self)
+        }
+}
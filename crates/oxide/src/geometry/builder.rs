@@ -0,0 +1,303 @@
+//! Meshes generated in code, like a snake's body or a voxel chunk.
+//!
+//! A [`MeshBuilder`] collects vertices and the triangles between them and
+//! builds [`MeshData`] for
+//! [`Model::from_data`](crate::model::Model::from_data). Equal vertices are
+//! stored once, so faces pushed one by one still share their corners:
+//!
+//! ```ignore
+//! let mut builder = MeshBuilder::new("chunk").with_material(0);
+//!
+//! for (x, y, z) in solid_faces
+//! {
+//!         builder.face_quad(
+//!                 [[x, y, z], [x + 1.0, y, z], [x + 1.0, y + 1.0, z], [x, y + 1.0, z]],
+//!                 UvRect::atlas_cell(16, 16, block.texture),
+//!         );
+//! }
+//!
+//! let model = Model::from_data(vec![builder.build()], materials, images, device, queue, materials_layout, transforms_layout);
+//! ```
+//!
+//! Vertices pushed with [`MeshBuilder::position`] have no normal until
+//! [`MeshBuilder::generate_normals`] smooths them from the triangles, or
+//! the built mesh's
+//! [`MeshData::recalculate_normals`](crate::geometry::mesh::MeshData::recalculate_normals)
+//! keeps the edges sharper than an angle.
+//!
+//! Meshes built without texture coordinates get them from
+//! [`MeshData::project_uvs`](crate::geometry::mesh::MeshData::project_uvs).
+
+use crate::geometry::mesh::MeshData;
+use crate::model::ModelVertex;
+use crate::resources::extras::Extras;
+use cgmath::{InnerSpace, Matrix4, SquareMatrix, Vector3};
+use std::collections::HashMap;
+
+/// Part of a texture a face shows, `min` at its top left.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UvRect
+{
+        pub min: [f32; 2],
+        pub max: [f32; 2],
+}
+
+impl UvRect
+{
+        /// The whole texture.
+        pub const FULL: UvRect = UvRect {
+                min: [0.0, 0.0],
+                max: [1.0, 1.0],
+        };
+
+        /// Cell `index` of a texture atlas of `columns` by `rows` cells,
+        /// counted row by row from the top left.
+        pub fn atlas_cell(
+                columns: u32,
+                rows: u32,
+                index: u32,
+        ) -> Self
+        {
+                let (columns, rows) = (columns.max(1), rows.max(1));
+                let (column, row) = (index % columns, index / columns % rows);
+                let size = [1.0 / columns as f32, 1.0 / rows as f32];
+
+                Self {
+                        min: [column as f32 * size[0], row as f32 * size[1]],
+                        max: [(column + 1) as f32 * size[0], (row + 1) as f32 * size[1]],
+                }
+        }
+}
+
+/// Builds the vertices and triangles of a [`MeshData`].
+#[derive(Debug, Clone)]
+pub struct MeshBuilder
+{
+        name: String,
+        vertices: Vec<ModelVertex>,
+        indices: Vec<u32>,
+        /// Index of every vertex by the bits of its attributes.
+        lookup: HashMap<[u32; 8], u32>,
+        material_id: Option<usize>,
+        transform: Matrix4<f32>,
+}
+
+impl MeshBuilder
+{
+        pub fn new(name: impl Into<String>) -> Self
+        {
+                Self {
+                        name: name.into(),
+                        vertices: Vec::new(),
+                        indices: Vec::new(),
+                        lookup: HashMap::new(),
+                        material_id: None,
+                        transform: Matrix4::identity(),
+                }
+        }
+
+        /// Material of the model the mesh is drawn with.
+        pub fn with_material(
+                mut self,
+                material_id: usize,
+        ) -> Self
+        {
+                self.material_id = Some(material_id);
+                self
+        }
+
+        /// Node transform of the mesh inside the model.
+        pub fn with_transform(
+                mut self,
+                transform: Matrix4<f32>,
+        ) -> Self
+        {
+                self.transform = transform;
+                self
+        }
+
+        pub fn vertex_count(&self) -> usize
+        {
+                self.vertices.len()
+        }
+
+        pub fn triangle_count(&self) -> usize
+        {
+                self.indices.len() / 3
+        }
+
+        pub fn is_empty(&self) -> bool
+        {
+                self.indices.is_empty()
+        }
+
+        /// Index of the vertex, added unless an equal one already was.
+        pub fn vertex(
+                &mut self,
+                position: [f32; 3],
+                normal: [f32; 3],
+                tex_coords: [f32; 2],
+        ) -> u32
+        {
+                let vertex = ModelVertex {
+                        position,
+                        tex_coords,
+                        normal,
+                };
+
+                *self.lookup.entry(key(&vertex)).or_insert_with(|| {
+                        self.vertices.push(vertex);
+                        self.vertices.len() as u32 - 1
+                })
+        }
+
+        /// A vertex without normal and texture coordinates, see
+        /// [`MeshBuilder::generate_normals`].
+        pub fn position(
+                &mut self,
+                position: [f32; 3],
+        ) -> u32
+        {
+                self.vertex(position, [0.0; 3], [0.0; 2])
+        }
+
+        /// A triangle of vertices counter-clockwise seen from the front.
+        pub fn triangle(
+                &mut self,
+                a: u32,
+                b: u32,
+                c: u32,
+        )
+        {
+                self.indices.extend([a, b, c]);
+        }
+
+        /// Two triangles of vertices counter-clockwise seen from the front.
+        pub fn quad(
+                &mut self,
+                a: u32,
+                b: u32,
+                c: u32,
+                d: u32,
+        )
+        {
+                self.indices.extend([a, b, c, c, d, a]);
+        }
+
+        /// A flat triangle, its normal facing the side its corners are
+        /// counter-clockwise on.
+        pub fn face_triangle(
+                &mut self,
+                corners: [[f32; 3]; 3],
+                tex_coords: [[f32; 2]; 3],
+        )
+        {
+                let normal = face_normal(corners[0], corners[1], corners[2]);
+                let [a, b, c] = [0, 1, 2].map(|i| self.vertex(corners[i], normal, tex_coords[i]));
+
+                self.triangle(a, b, c);
+        }
+
+        /// A flat quad, corners counter-clockwise from the bottom left seen
+        /// from the front, showing `uv` upright.
+        pub fn face_quad(
+                &mut self,
+                corners: [[f32; 3]; 4],
+                uv: UvRect,
+        )
+        {
+                let normal = face_normal(corners[0], corners[1], corners[2]);
+                let tex_coords = [
+                        [uv.min[0], uv.max[1]],
+                        [uv.max[0], uv.max[1]],
+                        [uv.max[0], uv.min[1]],
+                        [uv.min[0], uv.min[1]],
+                ];
+                let [a, b, c, d] =
+                        [0, 1, 2, 3].map(|i| self.vertex(corners[i], normal, tex_coords[i]));
+
+                self.quad(a, b, c, d);
+        }
+
+        /// Sets the normal of every vertex to the average of the triangles
+        /// around it, larger triangles weighing more. Vertices only share
+        /// normals when they share an index, a seam of split vertices stays
+        /// sharp.
+        pub fn generate_normals(&mut self)
+        {
+                let mut normals = vec![Vector3::new(0.0, 0.0, 0.0); self.vertices.len()];
+
+                for triangle in self.indices.chunks_exact(3)
+                {
+                        let [a, b, c] = [0, 1, 2].map(|i| {
+                                Vector3::from(self.vertices[triangle[i] as usize].position)
+                        });
+
+                        // Twice the area long.
+                        let normal = (b - a).cross(c - a);
+
+                        for &index in triangle
+                        {
+                                normals[index as usize] += normal;
+                        }
+                }
+
+                for (vertex, normal) in self.vertices.iter_mut().zip(normals)
+                {
+                        if normal.magnitude2() > 0.0
+                        {
+                                vertex.normal = normal.normalize().into();
+                        }
+                }
+
+                self.lookup = self
+                        .vertices
+                        .iter()
+                        .enumerate()
+                        .map(|(i, v)| (key(v), i as u32))
+                        .collect();
+        }
+
+        pub fn build(self) -> MeshData
+        {
+                MeshData {
+                        name: self.name,
+                        vertices: self.vertices,
+                        indices: self.indices,
+                        material_id: self.material_id,
+                        transform: self.transform,
+                        node: None,
+                        skin: None,
+                        morph_targets: Vec::new(),
+                        extras: Extras::new(),
+                }
+        }
+}
+
+/// The bits of the attributes of `vertex`.
+fn key(vertex: &ModelVertex) -> [u32; 8]
+{
+        let (p, n, t) = (vertex.position, vertex.normal, vertex.tex_coords);
+
+        // -0.0 and 0.0 are the same vertex.
+        [p[0], p[1], p[2], n[0], n[1], n[2], t[0], t[1]].map(|v| (v + 0.0).to_bits())
+}
+
+fn face_normal(
+        a: [f32; 3],
+        b: [f32; 3],
+        c: [f32; 3],
+) -> [f32; 3]
+{
+        let normal =
+                (Vector3::from(b) - Vector3::from(a)).cross(Vector3::from(c) - Vector3::from(a));
+
+        if normal.magnitude2() > 0.0
+        {
+                normal.normalize().into()
+        }
+        else
+        {
+                [0.0; 3]
+        }
+}
@@ -0,0 +1,476 @@
+//! Collision shapes generated from imported meshes.
+//!
+//! With
+//! [`ImportOptions::colliders`](crate::resources::import::ImportOptions::colliders)
+//! set, every mesh of a model gets a [`Collider`] when it's loaded: its
+//! bounding box, its convex hull or the triangles as they are. With
+//! [`ImportOptions::collision_nodes`](crate::resources::import::ImportOptions::collision_nodes)
+//! only the meshes of nodes named with the [`COLLISION_PREFIX`] do, and
+//! they aren't drawn, the common way to author simple collision geometry
+//! next to a detailed mesh in Blender:
+//!
+//! ```ignore
+//! engine.add_model_with(
+//!         "level",
+//!         "level.glb",
+//!         ImportOptions {
+//!                 colliders: Some(ColliderKind::ConvexHull),
+//!                 collision_nodes: true,
+//!                 ..ImportOptions::default()
+//!         },
+//! );
+//!
+//! // The colliders the player is in, e.g. trigger volumes.
+//! for (handle, collider) in engine.colliders_containing(player)
+//! {
+//!         // ...
+//! }
+//! ```
+//!
+//! The colliders are in model space with the import options and node
+//! transforms baked in, nodes moved later don't move them.
+//! [`Model::world_colliders`](crate::model::Model::world_colliders) puts
+//! them where the model is. Hulls are built point by point, which is meant
+//! for collision meshes of a few hundred vertices rather than detailed
+//! ones.
+
+#[cfg(feature = "runtime")]
+use crate::engine::Engine;
+use crate::geometry::bounds::Aabb;
+use crate::math::collision::{Plane, Ray};
+use cgmath::{EuclideanSpace, InnerSpace, Matrix4, Point3, Transform as _, Vector3};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Names of the nodes whose meshes are collision geometry, see
+/// [`crate::geometry::collider`].
+pub const COLLISION_PREFIX: &str = "COL_";
+
+/// Shape generated for a mesh.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ColliderKind
+{
+        /// The box around the vertices, the cheapest to test.
+        #[default]
+        Aabb,
+        /// The smallest convex shape around the vertices.
+        ConvexHull,
+        /// The triangles as they are, for static level geometry.
+        TriangleMesh,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColliderShape
+{
+        Aabb(Aabb),
+        /// Triangles counter-clockwise from the outside.
+        ConvexHull
+        {
+                vertices: Vec<Point3<f32>>,
+                indices: Vec<u32>,
+        },
+        TriangleMesh
+        {
+                vertices: Vec<Point3<f32>>,
+                indices: Vec<u32>,
+        },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Collider
+{
+        /// Name of the node or mesh it was generated from.
+        pub name: String,
+        pub shape: ColliderShape,
+}
+
+impl Collider
+{
+        /// A `kind` collider around the triangles `indices` of `vertices`.
+        /// A hull of flat or too few vertices is their box instead. `None`
+        /// without vertices.
+        pub fn from_triangles(
+                name: impl Into<String>,
+                kind: ColliderKind,
+                vertices: Vec<Point3<f32>>,
+                indices: Vec<u32>,
+        ) -> Option<Self>
+        {
+                let bounds = Aabb::from_points(vertices.iter().copied())?;
+
+                let shape = match kind
+                {
+                        ColliderKind::Aabb => ColliderShape::Aabb(bounds),
+                        ColliderKind::ConvexHull => match convex_hull(&vertices)
+                        {
+                                Some((vertices, indices)) => ColliderShape::ConvexHull {
+                                        vertices,
+                                        indices,
+                                },
+                                None => ColliderShape::Aabb(bounds),
+                        },
+                        ColliderKind::TriangleMesh => ColliderShape::TriangleMesh {
+                                vertices,
+                                indices,
+                        },
+                };
+
+                Some(Self {
+                        name: name.into(),
+                        shape,
+                })
+        }
+
+        /// The collider moved by `matrix`. A box stays a box, around the
+        /// transformed one when there's rotation.
+        pub fn transformed(
+                &self,
+                matrix: &Matrix4<f32>,
+        ) -> Self
+        {
+                let points = |vertices: &[Point3<f32>]| {
+                        vertices.iter()
+                                .map(|v| matrix.transform_point(*v))
+                                .collect::<Vec<_>>()
+                };
+
+                let shape = match &self.shape
+                {
+                        ColliderShape::Aabb(aabb) => ColliderShape::Aabb(aabb.transform(matrix)),
+                        ColliderShape::ConvexHull {
+                                vertices,
+                                indices,
+                        } => ColliderShape::ConvexHull {
+                                vertices: points(vertices),
+                                indices: indices.clone(),
+                        },
+                        ColliderShape::TriangleMesh {
+                                vertices,
+                                indices,
+                        } => ColliderShape::TriangleMesh {
+                                vertices: points(vertices),
+                                indices: indices.clone(),
+                        },
+                };
+
+                Self {
+                        name: self.name.clone(),
+                        shape,
+                }
+        }
+
+        pub fn bounds(&self) -> Aabb
+        {
+                match &self.shape
+                {
+                        ColliderShape::Aabb(aabb) => *aabb,
+                        ColliderShape::ConvexHull {
+                                vertices, ..
+                        }
+                        | ColliderShape::TriangleMesh {
+                                vertices, ..
+                        } => Aabb::from_points(vertices.iter().copied())
+                                .unwrap_or(Aabb::new(Point3::origin(), Point3::origin())),
+                }
+        }
+
+        /// The triangles of the shape, none for a box.
+        pub fn triangles(&self) -> impl Iterator<Item = [Point3<f32>; 3]> + '_
+        {
+                let (vertices, indices): (&[Point3<f32>], &[u32]) = match &self.shape
+                {
+                        ColliderShape::Aabb(_) => (&[], &[]),
+                        ColliderShape::ConvexHull {
+                                vertices,
+                                indices,
+                        }
+                        | ColliderShape::TriangleMesh {
+                                vertices,
+                                indices,
+                        } => (vertices, indices),
+                };
+
+                indices.chunks_exact(3).map(|t| {
+                        [
+                                vertices[t[0] as usize],
+                                vertices[t[1] as usize],
+                                vertices[t[2] as usize],
+                        ]
+                })
+        }
+
+        /// Distance along `ray` to the first hit.
+        pub fn intersect_ray(
+                &self,
+                ray: &Ray,
+        ) -> Option<f32>
+        {
+                let bounds = self.bounds();
+
+                if let ColliderShape::Aabb(aabb) = &self.shape
+                {
+                        return ray.intersect_aabb(aabb);
+                }
+
+                ray.intersect_aabb(&bounds)?;
+
+                self.triangles()
+                        .filter_map(|[a, b, c]| ray.intersect_triangle(a, b, c))
+                        .min_by(f32::total_cmp)
+        }
+
+        /// Whether `point` is inside. A triangle mesh is only tested right
+        /// when it's closed.
+        pub fn contains_point(
+                &self,
+                point: Point3<f32>,
+        ) -> bool
+        {
+                if !self.bounds().contains_point(point)
+                {
+                        return false;
+                }
+
+                match &self.shape
+                {
+                        ColliderShape::Aabb(_) => true,
+                        ColliderShape::ConvexHull {
+                                ..
+                        } => self.triangles().all(|[a, b, c]| {
+                                Plane::from_points(a, b, c).signed_distance(point) <= 1e-5
+                        }),
+                        ColliderShape::TriangleMesh {
+                                ..
+                        } =>
+                        {
+                                // Inside when a ray out crosses the surface an
+                                // odd number of times. Skewed, so it doesn't
+                                // run along edges of axis aligned meshes.
+                                let ray = Ray::new(point, Vector3::new(0.5731, 0.6123, 0.5447));
+
+                                self.triangles()
+                                        .filter(|[a, b, c]| {
+                                                ray.intersect_triangle(*a, *b, *c).is_some()
+                                        })
+                                        .count()
+                                        % 2
+                                        == 1
+                        }
+                }
+        }
+}
+
+/// Tolerance of the hull relative to the size of the points.
+const HULL_EPSILON: f32 = 1e-5;
+
+/// A triangle of a hull with its outward plane.
+struct Face
+{
+        vertices: [usize; 3],
+        normal: Vector3<f32>,
+        distance: f32,
+}
+
+impl Face
+{
+        fn new(
+                points: &[Point3<f32>],
+                vertices: [usize; 3],
+        ) -> Self
+        {
+                let [a, b, c] = vertices.map(|i| points[i]);
+                let normal = (b - a).cross(c - a).normalize();
+
+                Self {
+                        vertices,
+                        normal,
+                        distance: normal.dot(a.to_vec()),
+                }
+        }
+
+        fn height(
+                &self,
+                point: Point3<f32>,
+        ) -> f32
+        {
+                self.normal.dot(point.to_vec()) - self.distance
+        }
+}
+
+/// The convex hull of `points`, built by adding one point after the
+/// other to a tetrahedron. Its vertices and the triangles between them,
+/// counter-clockwise from the outside. `None` for fewer than four points
+/// or points in a plane.
+pub fn convex_hull(points: &[Point3<f32>]) -> Option<(Vec<Point3<f32>>, Vec<u32>)>
+{
+        let bounds = Aabb::from_points(points.iter().copied())?;
+        let epsilon = bounds.size().magnitude() * HULL_EPSILON;
+
+        // Four points far apart as the start.
+        let first = 0;
+        let second = farthest(points, |p| (p - points[first]).magnitude2())?;
+
+        let line = (points[second] - points[first]).normalize();
+        let third = farthest(points, |p| {
+                let offset = p - points[first];
+
+                (offset - line * offset.dot(line)).magnitude2()
+        })?;
+
+        let normal = (points[second] - points[first])
+                .cross(points[third] - points[first])
+                .normalize();
+        let fourth = farthest(points, |p| normal.dot(p - points[first]).abs())?;
+
+        if (points[second] - points[first]).magnitude() <= epsilon
+                || normal.dot(points[fourth] - points[first]).abs() <= epsilon
+                || !normal.x.is_finite()
+        {
+                return None;
+        }
+
+        let mut faces: Vec<Face> = [
+                [first, second, third],
+                [first, third, fourth],
+                [first, fourth, second],
+                [second, fourth, third],
+        ]
+        .into_iter()
+        .map(|vertices| Face::new(points, vertices))
+        .collect();
+
+        // Faced away from the inside.
+        let centroid =
+                Point3::centroid(&[points[first], points[second], points[third], points[fourth]]);
+
+        for face in &mut faces
+        {
+                if face.height(centroid) > 0.0
+                {
+                        face.vertices.swap(1, 2);
+                        *face = Face::new(points, face.vertices);
+                }
+        }
+
+        for (index, &point) in points.iter().enumerate()
+        {
+                let visible: Vec<usize> = (0..faces.len())
+                        .filter(|&i| faces[i].height(point) > epsilon)
+                        .collect();
+
+                if visible.is_empty()
+                {
+                        continue;
+                }
+
+                // Edges of the visible faces not shared by two of them.
+                let edges: HashSet<(usize, usize)> = visible
+                        .iter()
+                        .flat_map(|&i| {
+                                let [a, b, c] = faces[i].vertices;
+
+                                [(a, b), (b, c), (c, a)]
+                        })
+                        .collect();
+
+                let horizon: Vec<(usize, usize)> = edges
+                        .iter()
+                        .filter(|(a, b)| !edges.contains(&(*b, *a)))
+                        .copied()
+                        .collect();
+
+                let mut i = 0;
+                faces.retain(|_| {
+                        let keep = !visible.contains(&i);
+                        i += 1;
+                        keep
+                });
+
+                faces.extend(horizon
+                        .into_iter()
+                        .map(|(a, b)| Face::new(points, [a, b, index])));
+        }
+
+        // Only the points on the hull.
+        let mut remap: HashMap<usize, u32> = HashMap::new();
+        let mut vertices = Vec::new();
+        let mut indices = Vec::with_capacity(faces.len() * 3);
+
+        for face in &faces
+        {
+                for v in face.vertices
+                {
+                        let index = *remap.entry(v).or_insert_with(|| {
+                                vertices.push(points[v]);
+                                vertices.len() as u32 - 1
+                        });
+
+                        indices.push(index);
+                }
+        }
+
+        Some((vertices, indices))
+}
+
+/// Index of the point `distance` is largest for.
+fn farthest(
+        points: &[Point3<f32>],
+        distance: impl Fn(Point3<f32>) -> f32,
+) -> Option<usize>
+{
+        (0..points.len()).max_by(|&a, &b| distance(points[a]).total_cmp(&distance(points[b])))
+}
+
+#[cfg(feature = "runtime")]
+impl Engine
+{
+        /// The nearest collider of a visible model `ray` hits, with the
+        /// model's handle and the distance to it. Like
+        /// [`Engine::raycast`], for models with colliders, see
+        /// [`crate::geometry::collider`].
+        pub fn raycast_colliders(
+                &self,
+                ray: &Ray,
+        ) -> Option<(&str, f32)>
+        {
+                let state = self.state.as_ref()?;
+
+                state.models
+                        .iter()
+                        .filter(|(_, model)| model.visible && !model.colliders.is_empty())
+                        .filter_map(|(handle, model)| {
+                                model.world_colliders()
+                                        .iter()
+                                        .filter_map(|c| c.intersect_ray(ray))
+                                        .min_by(f32::total_cmp)
+                                        .map(|d| (handle.as_str(), d))
+                        })
+                        .min_by(|a, b| a.1.total_cmp(&b.1))
+        }
+
+        /// The colliders `point` is inside of with the handles of their
+        /// models, in the world, e.g. the trigger volumes a character
+        /// stands in.
+        pub fn colliders_containing(
+                &self,
+                point: Point3<f32>,
+        ) -> Vec<(&str, Collider)>
+        {
+                let Some(state) = self.state.as_ref()
+                else
+                {
+                        return Vec::new();
+                };
+
+                state.models
+                        .iter()
+                        .filter(|(_, model)| !model.colliders.is_empty())
+                        .flat_map(|(handle, model)| {
+                                model.world_colliders()
+                                        .into_iter()
+                                        .filter(|c| c.contains_point(point))
+                                        .map(move |c| (handle.as_str(), c))
+                        })
+                        .collect()
+        }
+}
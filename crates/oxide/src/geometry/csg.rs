@@ -0,0 +1,539 @@
+//! Boolean operations on closed meshes, for blocking out levels from
+//! primitives:
+//!
+//! ```ignore
+//! let room = cube.union(&corridor);
+//! let room = room.subtract(&doorway);
+//! let window = wall.intersect(&frame);
+//! ```
+//!
+//! The meshes are split along each other's triangles with BSP trees, like
+//! csg.js. Both must be closed, with every triangle facing out, or the
+//! result has holes. The result is in model space, the node transforms of
+//! both meshes applied, and is drawn with the material of `self`. Normals
+//! and texture coordinates of split triangles are interpolated.
+//!
+//! The trees are built recursively and the cost grows with the product of
+//! the triangle counts, meant for a few hundred triangles rather than
+//! imported props.
+
+use crate::geometry::mesh::MeshData;
+use crate::model::ModelVertex;
+use crate::resources::extras::Extras;
+use cgmath::{InnerSpace, Matrix, Matrix3, Point3, SquareMatrix, Transform, Vector2, Vector3};
+
+/// Distance under which a point is on a plane.
+const EPSILON: f64 = 1e-5;
+
+#[derive(Debug, Clone, Copy)]
+struct Vertex
+{
+        position: Vector3<f64>,
+        normal: Vector3<f64>,
+        tex_coords: Vector2<f64>,
+}
+
+impl Vertex
+{
+        fn lerp(
+                &self,
+                other: &Vertex,
+                t: f64,
+        ) -> Vertex
+        {
+                Vertex {
+                        position: self.position + (other.position - self.position) * t,
+                        normal: self.normal + (other.normal - self.normal) * t,
+                        tex_coords: self.tex_coords + (other.tex_coords - self.tex_coords) * t,
+                }
+        }
+
+        fn flip(&mut self)
+        {
+                self.normal = -self.normal;
+        }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Plane
+{
+        normal: Vector3<f64>,
+        w: f64,
+}
+
+impl Plane
+{
+        /// `None` for degenerate triangles.
+        fn from_points(
+                a: Vector3<f64>,
+                b: Vector3<f64>,
+                c: Vector3<f64>,
+        ) -> Option<Plane>
+        {
+                let normal = (b - a).cross(c - a);
+
+                (normal.magnitude2() > EPSILON * EPSILON).then(|| {
+                        let normal = normal.normalize();
+
+                        Plane {
+                                normal,
+                                w: normal.dot(a),
+                        }
+                })
+        }
+
+        fn flip(&mut self)
+        {
+                self.normal = -self.normal;
+                self.w = -self.w;
+        }
+
+        /// Sorts `polygon` into the lists by its side of the plane, splitting
+        /// it when it spans both.
+        fn split(
+                &self,
+                polygon: Polygon,
+                coplanar_front: &mut Vec<Polygon>,
+                coplanar_back: &mut Vec<Polygon>,
+                front: &mut Vec<Polygon>,
+                back: &mut Vec<Polygon>,
+        )
+        {
+                const COPLANAR: u8 = 0;
+                const FRONT: u8 = 1;
+                const BACK: u8 = 2;
+                const SPANNING: u8 = 3;
+
+                let sides: Vec<u8> = polygon
+                        .vertices
+                        .iter()
+                        .map(|v| {
+                                let distance = self.normal.dot(v.position) - self.w;
+
+                                if distance < -EPSILON
+                                {
+                                        BACK
+                                }
+                                else if distance > EPSILON
+                                {
+                                        FRONT
+                                }
+                                else
+                                {
+                                        COPLANAR
+                                }
+                        })
+                        .collect();
+
+                match sides.iter().fold(COPLANAR, |side, s| side | s)
+                {
+                        COPLANAR =>
+                        {
+                                if self.normal.dot(polygon.plane.normal) > 0.0
+                                {
+                                        coplanar_front.push(polygon);
+                                }
+                                else
+                                {
+                                        coplanar_back.push(polygon);
+                                }
+                        }
+                        FRONT => front.push(polygon),
+                        BACK => back.push(polygon),
+                        _ =>
+                        {
+                                let (mut f, mut b) = (Vec::new(), Vec::new());
+                                let count = polygon.vertices.len();
+
+                                for i in 0..count
+                                {
+                                        let j = (i + 1) % count;
+                                        let (si, sj) = (sides[i], sides[j]);
+                                        let (vi, vj) = (&polygon.vertices[i], &polygon.vertices[j]);
+
+                                        if si != BACK
+                                        {
+                                                f.push(*vi);
+                                        }
+
+                                        if si != FRONT
+                                        {
+                                                b.push(*vi);
+                                        }
+
+                                        if (si | sj) == SPANNING
+                                        {
+                                                let t = (self.w - self.normal.dot(vi.position))
+                                                        / self.normal
+                                                                .dot(vj.position - vi.position);
+                                                let v = vi.lerp(vj, t);
+
+                                                f.push(v);
+                                                b.push(v);
+                                        }
+                                }
+
+                                // The pieces stay on the plane of the polygon.
+                                for (pieces, list) in [(f, front), (b, back)]
+                                {
+                                        if pieces.len() >= 3
+                                        {
+                                                list.push(Polygon {
+                                                        vertices: pieces,
+                                                        plane: polygon.plane,
+                                                });
+                                        }
+                                }
+                        }
+                }
+        }
+}
+
+/// A convex polygon, counter-clockwise seen from the front.
+#[derive(Debug, Clone)]
+struct Polygon
+{
+        vertices: Vec<Vertex>,
+        plane: Plane,
+}
+
+impl Polygon
+{
+        /// `None` for fewer than three vertices or no area.
+        fn new(vertices: Vec<Vertex>) -> Option<Polygon>
+        {
+                if vertices.len() < 3
+                {
+                        return None;
+                }
+
+                let plane = Plane::from_points(
+                        vertices[0].position,
+                        vertices[1].position,
+                        vertices[2].position,
+                )?;
+
+                Some(Polygon {
+                        vertices,
+                        plane,
+                })
+        }
+
+        fn flip(&mut self)
+        {
+                self.vertices.reverse();
+                self.vertices.iter_mut().for_each(Vertex::flip);
+                self.plane.flip();
+        }
+}
+
+/// Node of a BSP tree, the polygons on its plane and the subtrees on
+/// either side.
+#[derive(Debug, Default)]
+struct Node
+{
+        plane: Option<Plane>,
+        front: Option<Box<Node>>,
+        back: Option<Box<Node>>,
+        polygons: Vec<Polygon>,
+}
+
+impl Node
+{
+        fn new(polygons: Vec<Polygon>) -> Node
+        {
+                let mut node = Node::default();
+                node.build(polygons);
+                node
+        }
+
+        /// Swaps inside and outside.
+        fn invert(&mut self)
+        {
+                self.polygons.iter_mut().for_each(Polygon::flip);
+
+                if let Some(plane) = &mut self.plane
+                {
+                        plane.flip();
+                }
+
+                if let Some(front) = &mut self.front
+                {
+                        front.invert();
+                }
+
+                if let Some(back) = &mut self.back
+                {
+                        back.invert();
+                }
+
+                std::mem::swap(&mut self.front, &mut self.back);
+        }
+
+        /// The parts of `polygons` outside the solid of this tree.
+        fn clip_polygons(
+                &self,
+                polygons: Vec<Polygon>,
+        ) -> Vec<Polygon>
+        {
+                let Some(plane) = self.plane
+                else
+                {
+                        return polygons;
+                };
+
+                let (mut front, mut back) = (Vec::new(), Vec::new());
+
+                for polygon in polygons
+                {
+                        let (mut coplanar_front, mut coplanar_back) = (Vec::new(), Vec::new());
+
+                        plane.split(
+                                polygon,
+                                &mut coplanar_front,
+                                &mut coplanar_back,
+                                &mut front,
+                                &mut back,
+                        );
+
+                        front.append(&mut coplanar_front);
+                        back.append(&mut coplanar_back);
+                }
+
+                let mut front = match &self.front
+                {
+                        Some(node) => node.clip_polygons(front),
+                        None => front,
+                };
+
+                let back = match &self.back
+                {
+                        Some(node) => node.clip_polygons(back),
+                        // Behind a leaf is inside.
+                        None => Vec::new(),
+                };
+
+                front.extend(back);
+                front
+        }
+
+        /// Removes the parts of this tree's polygons inside `other`.
+        fn clip_to(
+                &mut self,
+                other: &Node,
+        )
+        {
+                self.polygons = other.clip_polygons(std::mem::take(&mut self.polygons));
+
+                if let Some(front) = &mut self.front
+                {
+                        front.clip_to(other);
+                }
+
+                if let Some(back) = &mut self.back
+                {
+                        back.clip_to(other);
+                }
+        }
+
+        fn all_polygons(&self) -> Vec<Polygon>
+        {
+                let mut polygons = self.polygons.clone();
+
+                if let Some(front) = &self.front
+                {
+                        polygons.extend(front.all_polygons());
+                }
+
+                if let Some(back) = &self.back
+                {
+                        polygons.extend(back.all_polygons());
+                }
+
+                polygons
+        }
+
+        /// Adds `polygons` to the tree, splitting them along its planes.
+        fn build(
+                &mut self,
+                polygons: Vec<Polygon>,
+        )
+        {
+                let Some(first) = polygons.first()
+                else
+                {
+                        return;
+                };
+
+                let plane = *self.plane.get_or_insert(first.plane);
+                let (mut front, mut back) = (Vec::new(), Vec::new());
+                let (mut coplanar_front, mut coplanar_back) = (Vec::new(), Vec::new());
+
+                for polygon in polygons
+                {
+                        plane.split(
+                                polygon,
+                                &mut coplanar_front,
+                                &mut coplanar_back,
+                                &mut front,
+                                &mut back,
+                        );
+                }
+
+                self.polygons.append(&mut coplanar_front);
+                self.polygons.append(&mut coplanar_back);
+
+                if !front.is_empty()
+                {
+                        self.front.get_or_insert_default().build(front);
+                }
+
+                if !back.is_empty()
+                {
+                        self.back.get_or_insert_default().build(back);
+                }
+        }
+}
+
+impl MeshData
+{
+        /// Everything inside either mesh.
+        pub fn union(
+                &self,
+                other: &MeshData,
+        ) -> MeshData
+        {
+                let (mut a, mut b) = (Node::new(self.polygons()), Node::new(other.polygons()));
+
+                a.clip_to(&b);
+                b.clip_to(&a);
+                b.invert();
+                b.clip_to(&a);
+                b.invert();
+                a.build(b.all_polygons());
+
+                self.with_polygons(a.all_polygons())
+        }
+
+        /// This mesh with `other` cut out of it.
+        pub fn subtract(
+                &self,
+                other: &MeshData,
+        ) -> MeshData
+        {
+                let (mut a, mut b) = (Node::new(self.polygons()), Node::new(other.polygons()));
+
+                a.invert();
+                a.clip_to(&b);
+                b.clip_to(&a);
+                b.invert();
+                b.clip_to(&a);
+                b.invert();
+                a.build(b.all_polygons());
+                a.invert();
+
+                self.with_polygons(a.all_polygons())
+        }
+
+        /// Everything inside both meshes.
+        pub fn intersect(
+                &self,
+                other: &MeshData,
+        ) -> MeshData
+        {
+                let (mut a, mut b) = (Node::new(self.polygons()), Node::new(other.polygons()));
+
+                a.invert();
+                b.clip_to(&a);
+                b.invert();
+                a.clip_to(&b);
+                b.clip_to(&a);
+                a.build(b.all_polygons());
+                a.invert();
+
+                self.with_polygons(a.all_polygons())
+        }
+
+        /// The triangles in model space.
+        fn polygons(&self) -> Vec<Polygon>
+        {
+                let linear = Matrix3::from_cols(
+                        self.transform.x.truncate(),
+                        self.transform.y.truncate(),
+                        self.transform.z.truncate(),
+                );
+                let normal_matrix = linear.invert().map_or(linear, |m| m.transpose());
+
+                let vertex = |index: u32| {
+                        let v = &self.vertices[index as usize];
+                        let position = self.transform.transform_point(Point3::from(v.position));
+
+                        Vertex {
+                                position: position
+                                        .to_homogeneous()
+                                        .truncate()
+                                        .cast::<f64>()
+                                        .unwrap(),
+                                normal: (normal_matrix * Vector3::from(v.normal))
+                                        .cast::<f64>()
+                                        .unwrap(),
+                                tex_coords: Vector2::from(v.tex_coords).cast::<f64>().unwrap(),
+                        }
+                };
+
+                self.indices
+                        .chunks_exact(3)
+                        .filter_map(|t| Polygon::new(t.iter().map(|&i| vertex(i)).collect()))
+                        .collect()
+        }
+
+        /// A mesh of `polygons` fanned into triangles, named and drawn like
+        /// this one.
+        fn with_polygons(
+                &self,
+                polygons: Vec<Polygon>,
+        ) -> MeshData
+        {
+                let mut vertices = Vec::new();
+                let mut indices = Vec::new();
+
+                for polygon in polygons
+                {
+                        let first = vertices.len() as u32;
+
+                        vertices.extend(polygon.vertices.iter().map(|v| {
+                                let normal = if v.normal.magnitude2() > 0.0
+                                {
+                                        v.normal.normalize()
+                                }
+                                else
+                                {
+                                        polygon.plane.normal
+                                };
+
+                                ModelVertex {
+                                        position: v.position.cast::<f32>().unwrap().into(),
+                                        tex_coords: v.tex_coords.cast::<f32>().unwrap().into(),
+                                        normal: normal.cast::<f32>().unwrap().into(),
+                                }
+                        }));
+
+                        for i in 1..polygon.vertices.len() as u32 - 1
+                        {
+                                indices.extend([first, first + i, first + i + 1]);
+                        }
+                }
+
+                MeshData {
+                        name: self.name.clone(),
+                        vertices,
+                        indices,
+                        material_id: self.material_id,
+                        transform: cgmath::Matrix4::identity(),
+                        node: None,
+                        skin: None,
+                        morph_targets: Vec::new(),
+                        extras: Extras::new(),
+                }
+        }
+}
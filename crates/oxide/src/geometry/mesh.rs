@@ -1,5 +1,15 @@
-use crate::model::ModelVertex;
-use cgmath::Matrix4;
+use crate::geometry::bounds::Aabb;
+use crate::geometry::morph::{Morph, MorphTarget};
+use crate::geometry::skin::SkinData;
+use crate::model::{ModelVertex, Vertex};
+use crate::resources::extras::Extras;
+use cgmath::{
+        Deg, EuclideanSpace, InnerSpace, Matrix, Matrix3, Matrix4, Point3, Rad, SquareMatrix,
+        Transform, Vector3,
+};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use wgpu::util::DeviceExt;
 
 pub enum Primitive
 {
@@ -16,6 +26,31 @@ pub struct MeshData
         pub indices: Vec<u32>,
         pub material_id: Option<usize>,
         pub transform: Matrix4<f32>,
+        /// Index of the node placing the mesh, see
+        /// [`crate::geometry::node`].
+        pub node: Option<usize>,
+        /// Joints moving the vertices when the mesh is skinned, see
+        /// [`crate::geometry::skin`].
+        pub skin: Option<SkinData>,
+        /// Shapes the mesh blends towards, see [`crate::geometry::morph`].
+        pub morph_targets: Vec<MorphTarget>,
+        /// Application data of the glTF mesh, see
+        /// [`crate::resources::extras`].
+        pub extras: Extras,
+}
+
+/// How [`MeshData::project_uvs`] maps positions to texture coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UvProjection
+{
+        /// Onto the plane facing the direction, like a projector.
+        Planar(Vector3<f32>),
+        /// Every triangle onto the side of a cube it faces most, for
+        /// boxy shapes.
+        Box,
+        /// Longitude and latitude around the centre of the bounds, for
+        /// round shapes.
+        Spherical,
 }
 
 #[derive(Debug)]
@@ -28,4 +63,674 @@ pub struct Mesh
         pub material: usize,
         pub transform_buffer: wgpu::Buffer,
         pub transform_bind_group: wgpu::BindGroup,
+
+        /// Node transform of the mesh inside the model.
+        pub transform: Matrix4<f32>,
+        /// Index of the model node `transform` follows, see
+        /// [`Model::node_mut`](crate::model::Model::node_mut).
+        pub node: Option<usize>,
+
+        /// Vertices and indices kept on the CPU for debug drawing.
+        pub vertices: Vec<ModelVertex>,
+        pub indices: Vec<u32>,
+        /// Counts the changes to `vertices` and `indices`, for copies of
+        /// them like the shared buffers of [`crate::renderer::culling`].
+        pub revision: u64,
+
+        /// Bounds in model space, `transform` applied.
+        pub bounds: Aabb,
+
+        /// Created on first use, see [`Mesh::barycentric_buffer`].
+        pub barycentric_buffer: OnceLock<wgpu::Buffer>,
+
+        /// [`SkinVertex`](crate::geometry::skin::SkinVertex) of every vertex
+        /// when the mesh is skinned.
+        pub skin_buffer: Option<wgpu::Buffer>,
+
+        /// Targets of a morphed mesh, `vertices` hold the morphed shape.
+        pub morph: Option<Morph>,
+
+        /// Application data of the glTF mesh, see
+        /// [`crate::resources::extras`].
+        pub extras: Extras,
+}
+
+/// [`ModelVertex`] with the barycentric coordinate of its triangle corner,
+/// used by [`FillMode::WireframeOverlay`](crate::renderer::pipeline::FillMode).
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct BarycentricVertex
+{
+        pub position: [f32; 3],
+        pub tex_coords: [f32; 2],
+        pub normal: [f32; 3],
+        pub barycentric: [f32; 3],
+}
+
+impl Vertex for BarycentricVertex
+{
+        fn desc() -> wgpu::VertexBufferLayout<'static>
+        {
+                wgpu::VertexBufferLayout {
+                        array_stride: size_of::<BarycentricVertex>() as wgpu::BufferAddress,
+                        step_mode: wgpu::VertexStepMode::Vertex,
+                        attributes: &[
+                                wgpu::VertexAttribute {
+                                        offset: 0,
+                                        shader_location: 0,
+                                        format: wgpu::VertexFormat::Float32x3,
+                                },
+                                wgpu::VertexAttribute {
+                                        offset: size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                                        shader_location: 1,
+                                        format: wgpu::VertexFormat::Float32x2,
+                                },
+                                wgpu::VertexAttribute {
+                                        offset: size_of::<[f32; 5]>() as wgpu::BufferAddress,
+                                        shader_location: 2,
+                                        format: wgpu::VertexFormat::Float32x3,
+                                },
+                                wgpu::VertexAttribute {
+                                        offset: size_of::<[f32; 8]>() as wgpu::BufferAddress,
+                                        shader_location: 3,
+                                        format: wgpu::VertexFormat::Float32x3,
+                                },
+                        ],
+                }
+        }
+}
+
+impl Mesh
+{
+        /// Uploads `data` as a static mesh, its skin and morph targets are
+        /// left out.
+        pub fn from_data(
+                device: &wgpu::Device,
+                transform_layout: &wgpu::BindGroupLayout,
+                data: MeshData,
+        ) -> Self
+        {
+                let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                        label: Some(&format!("{} Vertex Buffer", data.name)),
+                        contents: bytemuck::cast_slice(&data.vertices),
+                        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                });
+
+                let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                        label: Some(&format!("{} Index Buffer", data.name)),
+                        contents: bytemuck::cast_slice(&data.indices),
+                        usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
+                });
+
+                let transform_data: [[f32; 4]; 4] = data.transform.into();
+
+                let transform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                        label: Some("Transform Buffer"),
+                        contents: bytemuck::cast_slice(&transform_data),
+                        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                });
+
+                let transform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                        layout: transform_layout,
+                        entries: &[wgpu::BindGroupEntry {
+                                binding: 0,
+                                resource: transform_buffer.as_entire_binding(),
+                        }],
+                        label: Some(&format!("{} Transform Bind Group", data.name)),
+                });
+
+                Self {
+                        bounds: data.bounds(),
+                        name: data.name,
+                        vertex_buffer,
+                        index_buffer,
+                        num_elements: data.indices.len() as u32,
+                        material: data.material_id.unwrap_or(0),
+                        transform_buffer,
+                        transform_bind_group,
+                        transform: data.transform,
+                        node: data.node,
+                        vertices: data.vertices,
+                        indices: data.indices,
+                        revision: 0,
+                        extras: data.extras,
+                        barycentric_buffer: OnceLock::new(),
+                        skin_buffer: None,
+                        morph: None,
+                }
+        }
+
+        /// A copy sharing the index, skin and, unless the mesh is morphed,
+        /// vertex buffer. The node transform gets its own buffer, morphed
+        /// meshes their own vertices since their weights are per model.
+        /// [`Mesh::update_vertices`] on either changes both otherwise.
+        pub fn duplicate(
+                &self,
+                device: &wgpu::Device,
+                transform_layout: &wgpu::BindGroupLayout,
+        ) -> Self
+        {
+                let vertex_buffer = match self.morph
+                {
+                        Some(_) => device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                                label: Some(&format!("{} Vertex Buffer", self.name)),
+                                contents: bytemuck::cast_slice(&self.vertices),
+                                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                        }),
+                        None => self.vertex_buffer.clone(),
+                };
+
+                let transform_data: [[f32; 4]; 4] = self.transform.into();
+
+                let transform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                        label: Some("Transform Buffer"),
+                        contents: bytemuck::cast_slice(&transform_data),
+                        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                });
+
+                let transform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                        layout: transform_layout,
+                        entries: &[wgpu::BindGroupEntry {
+                                binding: 0,
+                                resource: transform_buffer.as_entire_binding(),
+                        }],
+                        label: Some(&format!("{} Transform Bind Group", self.name)),
+                });
+
+                Self {
+                        name: self.name.clone(),
+                        vertex_buffer,
+                        index_buffer: self.index_buffer.clone(),
+                        num_elements: self.num_elements,
+                        material: self.material,
+                        transform_buffer,
+                        transform_bind_group,
+                        transform: self.transform,
+                        node: self.node,
+                        vertices: self.vertices.clone(),
+                        indices: self.indices.clone(),
+                        revision: self.revision,
+                        bounds: self.bounds,
+                        barycentric_buffer: OnceLock::new(),
+                        skin_buffer: self.skin_buffer.clone(),
+                        morph: self.morph.clone(),
+                        extras: self.extras.clone(),
+                }
+        }
+
+        /// Replaces the vertices, writing them into the vertex buffer or a
+        /// new one when they don't fit. For geometry changing at runtime,
+        /// like a waving flag.
+        ///
+        /// Morph targets are based on the new vertices from then on. The
+        /// vertex count of skinned and morphed meshes can't change, their
+        /// joints and targets are per vertex, such an update is refused.
+        pub fn update_vertices(
+                &mut self,
+                device: &wgpu::Device,
+                queue: &wgpu::Queue,
+                vertices: &[ModelVertex],
+        )
+        {
+                let fixed = self.skin_buffer.is_some() || self.morph.is_some();
+
+                if fixed && vertices.len() != self.vertices.len()
+                {
+                        log::warn!(
+                                "Can't change the vertex count of {:?}, it's skinned or morphed",
+                                self.name
+                        );
+                        return;
+                }
+
+                Self::write(
+                        device,
+                        queue,
+                        &mut self.vertex_buffer,
+                        bytemuck::cast_slice(vertices),
+                        wgpu::BufferUsages::VERTEX,
+                        &format!("{} Vertex Buffer", self.name),
+                );
+
+                self.vertices = vertices.to_vec();
+                self.revision += 1;
+
+                if let Some(morph) = &mut self.morph
+                {
+                        morph.base = self.vertices.clone();
+                }
+
+                self.barycentric_buffer = OnceLock::new();
+                self.update_bounds();
+        }
+
+        /// Replaces the indices, written like the vertices of
+        /// [`Mesh::update_vertices`]. Every index must be below the vertex
+        /// count.
+        pub fn update_indices(
+                &mut self,
+                device: &wgpu::Device,
+                queue: &wgpu::Queue,
+                indices: &[u32],
+        )
+        {
+                if indices.iter().any(|&i| i as usize >= self.vertices.len())
+                {
+                        log::warn!("Indices of {:?} point past its vertices", self.name);
+                        return;
+                }
+
+                Self::write(
+                        device,
+                        queue,
+                        &mut self.index_buffer,
+                        bytemuck::cast_slice(indices),
+                        wgpu::BufferUsages::INDEX,
+                        &format!("{} Index Buffer", self.name),
+                );
+
+                self.indices = indices.to_vec();
+                self.num_elements = indices.len() as u32;
+                self.revision += 1;
+                self.barycentric_buffer = OnceLock::new();
+        }
+
+        /// Writes `contents` into `buffer`, replaced by a larger one when
+        /// they don't fit.
+        fn write(
+                device: &wgpu::Device,
+                queue: &wgpu::Queue,
+                buffer: &mut wgpu::Buffer,
+                contents: &[u8],
+                usage: wgpu::BufferUsages,
+                label: &str,
+        )
+        {
+                if contents.len() as u64 > buffer.size()
+                {
+                        *buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                                label: Some(label),
+                                contents,
+                                usage: usage | wgpu::BufferUsages::COPY_DST,
+                        });
+                }
+                else if !contents.is_empty()
+                {
+                        queue.write_buffer(buffer, 0, contents);
+                }
+        }
+
+        /// Computes [`Mesh::bounds`] again after the vertices or the
+        /// transform changed.
+        pub fn update_bounds(&mut self)
+        {
+                if let Some(bounds) = Aabb::from_points(
+                        self.vertices
+                                .iter()
+                                .map(|v| self.transform.transform_point(Point3::from(v.position))),
+                )
+                {
+                        self.bounds = bounds;
+                }
+        }
+
+        /// Bytes of the mesh's buffers on the GPU.
+        pub fn gpu_memory(&self) -> u64
+        {
+                self.vertex_buffer.size()
+                        + self.index_buffer.size()
+                        + self.transform_buffer.size()
+                        + self.barycentric_buffer.get().map_or(0, |b| b.size())
+                        + self.skin_buffer.as_ref().map_or(0, |b| b.size())
+        }
+
+        /// Unindexed copy of the vertices with barycentric coordinates,
+        /// `num_elements` vertices long.
+        pub fn barycentric_buffer(
+                &self,
+                device: &wgpu::Device,
+        ) -> &wgpu::Buffer
+        {
+                self.barycentric_buffer.get_or_init(|| {
+                        const CORNERS: [[f32; 3]; 3] =
+                                [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+
+                        let vertices: Vec<BarycentricVertex> = self
+                                .indices
+                                .iter()
+                                .enumerate()
+                                .map(|(i, &index)| {
+                                        let v = self.vertices[index as usize];
+
+                                        BarycentricVertex {
+                                                position: v.position,
+                                                tex_coords: v.tex_coords,
+                                                normal: v.normal,
+                                                barycentric: CORNERS[i % 3],
+                                        }
+                                })
+                                .collect();
+
+                        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                                label: Some(&format!("{} Barycentric Vertex Buffer", self.name)),
+                                contents: bytemuck::cast_slice(&vertices),
+                                usage: wgpu::BufferUsages::VERTEX,
+                        })
+                })
+        }
+}
+
+impl MeshData
+{
+        /// Adds a triangle list moved by `transform`, baked into the
+        /// positions and normals.
+        pub fn append(
+                &mut self,
+                vertices: &[ModelVertex],
+                indices: &[u32],
+                transform: &Matrix4<f32>,
+        )
+        {
+                let offset = self.vertices.len() as u32;
+
+                // Normals go through the inverse transpose, so they stay
+                // perpendicular under non-uniform scale.
+                let linear = Matrix3::from_cols(
+                        transform.x.truncate(),
+                        transform.y.truncate(),
+                        transform.z.truncate(),
+                );
+                let normal_matrix = linear.invert().map_or(linear, |m| m.transpose());
+
+                self.vertices.extend(vertices.iter().map(|v| {
+                        let position = transform.transform_point(Point3::from(v.position));
+                        let normal = normal_matrix * Vector3::from(v.normal);
+
+                        ModelVertex {
+                                position: position.into(),
+                                normal: if normal.magnitude2() > 0.0
+                                {
+                                        normal.normalize().into()
+                                }
+                                else
+                                {
+                                        v.normal
+                                },
+                                tex_coords: v.tex_coords,
+                        }
+                }));
+
+                self.indices.extend(indices.iter().map(|i| i + offset));
+        }
+
+        /// Computes the normals from the triangles. Triangles meeting at a
+        /// corner are smoothed together when they're at most
+        /// `smoothing_angle` apart, 0° gives flat faces and 180° smooths
+        /// everything. Larger triangles weigh more.
+        ///
+        /// Vertices are split where a corner has several normals, the
+        /// skinning and morph targets of split vertices are copied.
+        pub fn recalculate_normals(
+                &mut self,
+                smoothing_angle: Deg<f32>,
+        )
+        {
+                let threshold = Rad::from(smoothing_angle).0.cos() - 1e-4;
+
+                let position = |index: u32| Vector3::from(self.vertices[index as usize].position);
+                let key = |index: u32| {
+                        self.vertices[index as usize]
+                                .position
+                                .map(|v| (v + 0.0).to_bits())
+                };
+
+                // Twice the area long.
+                let faces: Vec<Vector3<f32>> = self
+                        .indices
+                        .chunks_exact(3)
+                        .map(|t| {
+                                (position(t[1]) - position(t[0]))
+                                        .cross(position(t[2]) - position(t[0]))
+                        })
+                        .collect();
+
+                // Triangles by the corners they touch, split vertices at the
+                // same position included.
+                let mut corners: HashMap<[u32; 3], Vec<usize>> = HashMap::new();
+
+                for (face, triangle) in self.indices.chunks_exact(3).enumerate()
+                {
+                        for &index in triangle
+                        {
+                                corners.entry(key(index)).or_default().push(face);
+                        }
+                }
+
+                let mut vertices = Vec::new();
+                let mut sources = Vec::new();
+                let mut lookup: HashMap<(u32, [u32; 3]), u32> = HashMap::new();
+                let mut indices = Vec::with_capacity(self.indices.len());
+
+                for (face, triangle) in self.indices.chunks_exact(3).enumerate()
+                {
+                        let direction = faces[face].normalize();
+
+                        for &index in triangle
+                        {
+                                let normal: Vector3<f32> = corners[&key(index)]
+                                        .iter()
+                                        .map(|&other| faces[other])
+                                        .filter(|other| {
+                                                other.magnitude2() > 0.0
+                                                        && other.normalize().dot(direction)
+                                                                >= threshold
+                                        })
+                                        .sum();
+
+                                let mut vertex = self.vertices[index as usize];
+
+                                if normal.magnitude2() > 0.0
+                                {
+                                        vertex.normal = normal.normalize().into();
+                                }
+
+                                let new = *lookup
+                                        .entry((index, vertex.normal.map(|v| (v + 0.0).to_bits())))
+                                        .or_insert_with(|| {
+                                                vertices.push(vertex);
+                                                sources.push(index as usize);
+                                                vertices.len() as u32 - 1
+                                        });
+
+                                indices.push(new);
+                        }
+                }
+
+                self.split(vertices, &sources, indices);
+        }
+
+        /// Replaces the texture coordinates with a `projection` of the
+        /// positions, for procedural or [`csg`](crate::geometry::csg) meshes
+        /// without a hand made unwrap. One unit of the mesh spans
+        /// `scale` of the texture, the spherical projection wraps the
+        /// texture around once whatever the scale.
+        ///
+        /// Vertices are split where triangles need different coordinates,
+        /// like the box faces or the seam of the sphere.
+        pub fn project_uvs(
+                &mut self,
+                projection: UvProjection,
+                scale: f32,
+        )
+        {
+                let position = |index: u32| Vector3::from(self.vertices[index as usize].position);
+                let center =
+                        Aabb::from_points(self.vertices.iter().map(|v| Point3::from(v.position)))
+                                .map(|bounds| bounds.center().to_vec())
+                                .unwrap_or(Vector3::new(0.0, 0.0, 0.0));
+
+                let mut vertices = Vec::new();
+                let mut sources = Vec::new();
+                let mut lookup: HashMap<(u32, [u32; 2]), u32> = HashMap::new();
+                let mut indices = Vec::with_capacity(self.indices.len());
+
+                for triangle in self.indices.chunks_exact(3)
+                {
+                        let corners = [0, 1, 2].map(|i| position(triangle[i]));
+
+                        let mut uvs = match projection
+                        {
+                                UvProjection::Planar(normal) =>
+                                {
+                                        let (u, v) = plane_axes(normal);
+                                        corners.map(|p| [p.dot(u) * scale, -p.dot(v) * scale])
+                                }
+                                UvProjection::Box =>
+                                {
+                                        let face = (corners[1] - corners[0])
+                                                .cross(corners[2] - corners[0]);
+                                        let (u, v) = plane_axes(box_axis(face));
+                                        corners.map(|p| [p.dot(u) * scale, -p.dot(v) * scale])
+                                }
+                                UvProjection::Spherical => corners.map(|p| spherical(p - center)),
+                        };
+
+                        // Triangles crossing the seam take the long way round
+                        // otherwise.
+                        if let UvProjection::Spherical = projection
+                        {
+                                let max = uvs.iter().map(|uv| uv[0]).fold(f32::MIN, f32::max);
+
+                                for uv in &mut uvs
+                                {
+                                        if max - uv[0] > 0.5
+                                        {
+                                                uv[0] += 1.0;
+                                        }
+                                }
+                        }
+
+                        for (&index, uv) in triangle.iter().zip(uvs)
+                        {
+                                let mut vertex = self.vertices[index as usize];
+                                vertex.tex_coords = uv;
+
+                                let new = *lookup
+                                        .entry((index, uv.map(|v| (v + 0.0).to_bits())))
+                                        .or_insert_with(|| {
+                                                vertices.push(vertex);
+                                                sources.push(index as usize);
+                                                vertices.len() as u32 - 1
+                                        });
+
+                                indices.push(new);
+                        }
+                }
+
+                self.split(vertices, &sources, indices);
+        }
+
+        /// Takes the `vertices` and `indices` of a split, copying the
+        /// skinning and morph targets of the vertices at `sources`.
+        fn split(
+                &mut self,
+                vertices: Vec<ModelVertex>,
+                sources: &[usize],
+                indices: Vec<u32>,
+        )
+        {
+                if let Some(skin) = &mut self.skin
+                {
+                        skin.vertices = sources.iter().map(|&i| skin.vertices[i]).collect();
+                }
+
+                for target in &mut self.morph_targets
+                {
+                        target.positions = sources.iter().map(|&i| target.positions[i]).collect();
+
+                        if !target.normals.is_empty()
+                        {
+                                target.normals =
+                                        sources.iter().map(|&i| target.normals[i]).collect();
+                        }
+                }
+
+                self.vertices = vertices;
+                self.indices = indices;
+        }
+
+        /// Bounds in model space, `transform` applied.
+        pub fn bounds(&self) -> Aabb
+        {
+                Aabb::from_points(
+                        self.vertices
+                                .iter()
+                                .map(|v| self.transform.transform_point(Point3::from(v.position))),
+                )
+                .unwrap_or_else(|| {
+                        Aabb::new(Point3::new(0.0, 0.0, 0.0), Point3::new(0.0, 0.0, 0.0))
+                })
+        }
+}
+
+/// Right and up of a plane facing `normal`, up staying +Y unless the plane
+/// is horizontal.
+fn plane_axes(normal: Vector3<f32>) -> (Vector3<f32>, Vector3<f32>)
+{
+        let normal = if normal.magnitude2() > 0.0
+        {
+                normal.normalize()
+        }
+        else
+        {
+                Vector3::unit_z()
+        };
+
+        let up = if normal.y.abs() > 0.999
+        {
+                -Vector3::unit_z() * normal.y.signum()
+        }
+        else
+        {
+                Vector3::unit_y()
+        };
+
+        let right = up.cross(normal).normalize();
+
+        (right, normal.cross(right))
+}
+
+/// The cube side a triangle facing `face` is projected onto.
+fn box_axis(face: Vector3<f32>) -> Vector3<f32>
+{
+        let abs = face.map(f32::abs);
+
+        if abs.x >= abs.y && abs.x >= abs.z
+        {
+                Vector3::unit_x() * face.x.signum()
+        }
+        else if abs.y >= abs.z
+        {
+                Vector3::unit_y() * face.y.signum()
+        }
+        else
+        {
+                Vector3::unit_z() * face.z.signum()
+        }
+}
+
+/// Longitude and latitude of `direction`, both 0 to 1.
+fn spherical(direction: Vector3<f32>) -> [f32; 2]
+{
+        if direction.magnitude2() == 0.0
+        {
+                return [0.5, 0.5];
+        }
+
+        let direction = direction.normalize();
+
+        [
+                0.5 + direction.z.atan2(direction.x) / std::f32::consts::TAU,
+                0.5 - direction.y.clamp(-1.0, 1.0).asin() / std::f32::consts::PI,
+        ]
 }
@@ -1,2 +1,17 @@
+pub mod bounds;
+#[cfg(feature = "renderer")]
+pub mod builder;
+#[cfg(feature = "renderer")]
+pub mod collider;
+#[cfg(feature = "renderer")]
+pub mod csg;
+#[cfg(feature = "renderer")]
 pub mod mesh;
+#[cfg(feature = "renderer")]
+pub mod morph;
+#[cfg(feature = "renderer")]
+pub mod node;
+#[cfg(feature = "renderer")]
 pub mod primitives;
+#[cfg(feature = "renderer")]
+pub mod skin;
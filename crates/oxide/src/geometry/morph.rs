@@ -0,0 +1,92 @@
+//! Morph targets, also called blend shapes, for facial and other shape
+//! animation.
+//!
+//! A target moves every vertex of a mesh by an offset, and a mesh is drawn
+//! as its base shape plus each target scaled by its weight. Weights are set
+//! on the model by target name, across all its meshes:
+//!
+//! ```ignore
+//! let face = state.models.get_mut("face").unwrap();
+//!
+//! face.set_morph_weight("smile", 0.8);
+//!
+//! // Or eased in over time.
+//! engine.tween_morph_weight("face", "blink", 1.0, Duration::from_millis(80), Ease::OutQuad)
+//!         .then_morph_weight("face", "blink", 0.0, Duration::from_millis(80), Ease::InQuad);
+//! ```
+//!
+//! Morphed vertices are computed on the CPU and uploaded once a frame when
+//! a weight changed.
+
+use crate::model::ModelVertex;
+use cgmath::{InnerSpace, Vector3};
+
+/// Offsets of every vertex of a mesh.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MorphTarget
+{
+        pub name: String,
+        /// Weight the mesh is imported with.
+        pub weight: f32,
+        pub positions: Vec<[f32; 3]>,
+        /// Empty when the target doesn't change normals.
+        pub normals: Vec<[f32; 3]>,
+}
+
+/// The morph targets of a mesh and its unmorphed vertices.
+#[derive(Debug, Clone)]
+pub struct Morph
+{
+        pub targets: Vec<MorphTarget>,
+        pub base: Vec<ModelVertex>,
+}
+
+impl Morph
+{
+        /// The vertices with every target applied at `weight(name)`.
+        pub fn apply(
+                &self,
+                weight: impl Fn(&str) -> f32,
+        ) -> Vec<ModelVertex>
+        {
+                let mut vertices = self.base.clone();
+
+                for target in &self.targets
+                {
+                        let weight = weight(&target.name);
+
+                        if weight == 0.0
+                        {
+                                continue;
+                        }
+
+                        for (vertex, offset) in vertices.iter_mut().zip(&target.positions)
+                        {
+                                for (value, offset) in vertex.position.iter_mut().zip(offset)
+                                {
+                                        *value += offset * weight;
+                                }
+                        }
+
+                        for (vertex, offset) in vertices.iter_mut().zip(&target.normals)
+                        {
+                                for (value, offset) in vertex.normal.iter_mut().zip(offset)
+                                {
+                                        *value += offset * weight;
+                                }
+                        }
+                }
+
+                for vertex in &mut vertices
+                {
+                        let normal = Vector3::from(vertex.normal);
+
+                        if normal.magnitude2() > 0.0
+                        {
+                                vertex.normal = normal.normalize().into();
+                        }
+                }
+
+                vertices
+        }
+}
@@ -0,0 +1,80 @@
+//! The node hierarchy of an imported model.
+//!
+//! glTF places meshes with a tree of named nodes, a car's wheels below its
+//! body. The tree is kept on the model, every mesh following its node, so
+//! parts can be found and moved by name after import:
+//!
+//! ```ignore
+//! let car = state.models.get_mut("car").unwrap();
+//!
+//! if let Some(wheel) = car.node_mut("Wheel_FL")
+//! {
+//!         wheel.transform.rotation = Quaternion::from_angle_y(Deg(25.0));
+//! }
+//!
+//! // Where the wheel is, its children moved along.
+//! let hub = car.node_transform("Wheel_FL");
+//! ```
+//!
+//! Data authored on the nodes in the `extras` of the file comes along, see
+//! [`crate::resources::extras`].
+//!
+//! Meshes merged by
+//! [`ImportOptions::merge_by_material`](crate::resources::import::ImportOptions)
+//! and skinned meshes don't follow nodes, the former are baked and the
+//! latter posed by their joints.
+
+use crate::resources::extras::Extras;
+use crate::transform::Transform;
+use cgmath::Matrix4;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node
+{
+        pub name: String,
+        /// Index of the parent node, `None` for the roots.
+        pub parent: Option<usize>,
+        pub children: Vec<usize>,
+        /// Relative to the parent node, or to the model for the roots.
+        pub transform: Transform,
+        /// Application data of the glTF node, see
+        /// [`crate::resources::extras`].
+        pub extras: Extras,
+}
+
+/// Every node in model space, parents before their children in `nodes`
+/// or not.
+pub fn model_matrices(nodes: &[Node]) -> Vec<Matrix4<f32>>
+{
+        fn resolve(
+                nodes: &[Node],
+                index: usize,
+                resolved: &mut [Option<Matrix4<f32>>],
+                depth: usize,
+        ) -> Matrix4<f32>
+        {
+                if let Some(matrix) = resolved[index]
+                {
+                        return matrix;
+                }
+
+                let node = &nodes[index];
+                let local = node.transform.matrix();
+
+                // A parent loop in broken data ends at the root.
+                let matrix = match node.parent.filter(|_| depth < nodes.len())
+                {
+                        Some(parent) => resolve(nodes, parent, resolved, depth + 1) * local,
+                        None => local,
+                };
+
+                resolved[index] = Some(matrix);
+                matrix
+        }
+
+        let mut resolved = vec![None; nodes.len()];
+
+        (0..nodes.len())
+                .map(|i| resolve(nodes, i, &mut resolved, 0))
+                .collect()
+}
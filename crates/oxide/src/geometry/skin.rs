@@ -0,0 +1,320 @@
+//! Skinned meshes, bent by the joints of a skeleton in the vertex shader.
+//!
+//! Every vertex of a skinned mesh is moved by up to four joints, see
+//! [`SkinVertex`]. The joints of a [`Skin`] are posed through their
+//! [`Joint::transform`], and every frame the geometry pass uploads their
+//! palette, blended per vertex one of two ways, see [`SkinningMethod`].
+//!
+//! The palette is one uniform buffer of [`MAX_JOINTS`] matrices, 16 KiB,
+//! the most a uniform binding is guaranteed to hold, WebGL2 included.
+//! Joints past that are dropped on import, the vertices they moved follow
+//! the other joints.
+
+use crate::model::Vertex;
+use crate::transform::Transform;
+use cgmath::{Matrix4, Quaternion, SquareMatrix};
+
+/// Joints of one skin the palette holds.
+pub const MAX_JOINTS: usize = 256;
+
+/// How the joints moving a vertex are blended.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SkinningMethod
+{
+        /// Blends the joint matrices. Cheap, but twisting joints pinch the
+        /// mesh thinner, the "candy wrapper" look.
+        #[default]
+        Linear,
+        /// Blends the joints as dual quaternions, which keeps the volume
+        /// around twisting joints. Ignores the scale of joints.
+        DualQuaternion,
+}
+
+/// The joints moving a vertex and how much each does, the weights adding
+/// up to 1. Vertex buffer 1 of skinned meshes.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SkinVertex
+{
+        pub joints: [u32; 4],
+        pub weights: [f32; 4],
+}
+
+impl SkinVertex
+{
+        /// Scales the weights to add up to 1 and drops those of joints the
+        /// palette doesn't hold, a vertex without weights goes to joint 0.
+        pub fn normalized(
+                mut self,
+                joint_count: usize,
+        ) -> Self
+        {
+                for (joint, weight) in self.joints.iter_mut().zip(&mut self.weights)
+                {
+                        if *joint as usize >= joint_count.min(MAX_JOINTS)
+                        {
+                                *joint = 0;
+                                *weight = 0.0;
+                        }
+                }
+
+                let total: f32 = self.weights.iter().sum();
+
+                if total > 0.0
+                {
+                        self.weights = self.weights.map(|w| w / total);
+                }
+                else
+                {
+                        self.weights = [1.0, 0.0, 0.0, 0.0];
+                }
+
+                self
+        }
+}
+
+impl Vertex for SkinVertex
+{
+        fn desc() -> wgpu::VertexBufferLayout<'static>
+        {
+                wgpu::VertexBufferLayout {
+                        array_stride: size_of::<SkinVertex>() as wgpu::BufferAddress,
+                        step_mode: wgpu::VertexStepMode::Vertex,
+                        attributes: &[
+                                wgpu::VertexAttribute {
+                                        offset: 0,
+                                        shader_location: 3,
+                                        format: wgpu::VertexFormat::Uint32x4,
+                                },
+                                wgpu::VertexAttribute {
+                                        offset: size_of::<[u32; 4]>() as wgpu::BufferAddress,
+                                        shader_location: 4,
+                                        format: wgpu::VertexFormat::Float32x4,
+                                },
+                        ],
+                }
+        }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Joint
+{
+        pub name: String,
+        /// Index of the parent joint, `None` for the roots.
+        pub parent: Option<usize>,
+        /// Pose relative to the parent joint, or to the model for the
+        /// roots.
+        pub transform: Transform,
+        /// From model space to the joint in the bind pose.
+        pub inverse_bind: Matrix4<f32>,
+}
+
+/// Skinning data of an imported mesh.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkinData
+{
+        /// One per vertex of the mesh.
+        pub vertices: Vec<SkinVertex>,
+        pub joints: Vec<Joint>,
+}
+
+/// The skeleton posing the skinned meshes of a model.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Skin
+{
+        pub joints: Vec<Joint>,
+        pub method: SkinningMethod,
+}
+
+impl Skin
+{
+        /// At most [`MAX_JOINTS`] of `joints`, blended linearly.
+        pub fn new(mut joints: Vec<Joint>) -> Self
+        {
+                if joints.len() > MAX_JOINTS
+                {
+                        log::warn!(
+                                "Skin has {} joints, only the first {} are used",
+                                joints.len(),
+                                MAX_JOINTS
+                        );
+
+                        joints.truncate(MAX_JOINTS);
+
+                        for joint in &mut joints
+                        {
+                                joint.parent = joint.parent.filter(|&p| p < MAX_JOINTS);
+                        }
+                }
+
+                Self {
+                        joints,
+                        method: SkinningMethod::default(),
+                }
+        }
+
+        #[cfg(feature = "ui")]
+        pub fn ui(
+                &mut self,
+                ui: &mut egui::Ui,
+        )
+        {
+                ui.label(format!("{} joints", self.joints.len()));
+
+                egui::ComboBox::from_label("Skinning")
+                        .selected_text(format!("{:?}", self.method))
+                        .show_ui(ui, |ui| {
+                                ui.selectable_value(
+                                        &mut self.method,
+                                        SkinningMethod::Linear,
+                                        "Linear",
+                                );
+                                ui.selectable_value(
+                                        &mut self.method,
+                                        SkinningMethod::DualQuaternion,
+                                        "Dual quaternion",
+                                );
+                        });
+        }
+
+        pub fn with_method(
+                mut self,
+                method: SkinningMethod,
+        ) -> Self
+        {
+                self.method = method;
+                self
+        }
+
+        /// Index of the joint called `name`.
+        pub fn joint(
+                &self,
+                name: &str,
+        ) -> Option<usize>
+        {
+                self.joints.iter().position(|j| j.name == name)
+        }
+
+        pub fn joint_mut(
+                &mut self,
+                name: &str,
+        ) -> Option<&mut Joint>
+        {
+                self.joints.iter_mut().find(|j| j.name == name)
+        }
+
+        /// Every joint in model space, posed.
+        pub fn model_matrices(&self) -> Vec<Matrix4<f32>>
+        {
+                fn resolve(
+                        joints: &[Joint],
+                        index: usize,
+                        resolved: &mut [Option<Matrix4<f32>>],
+                        depth: usize,
+                ) -> Matrix4<f32>
+                {
+                        if let Some(matrix) = resolved[index]
+                        {
+                                return matrix;
+                        }
+
+                        let joint = &joints[index];
+                        let local = joint.transform.matrix();
+
+                        // A parent loop in broken data ends at the root.
+                        let matrix = match joint.parent.filter(|_| depth < joints.len())
+                        {
+                                Some(parent) =>
+                                {
+                                        resolve(joints, parent, resolved, depth + 1) * local
+                                }
+                                None => local,
+                        };
+
+                        resolved[index] = Some(matrix);
+                        matrix
+                }
+
+                let mut resolved = vec![None; self.joints.len()];
+
+                (0..self.joints.len())
+                        .map(|i| resolve(&self.joints, i, &mut resolved, 0))
+                        .collect()
+        }
+
+        /// The matrix of every joint moving vertices from the bind pose to
+        /// the current one.
+        pub fn palette(&self) -> Vec<Matrix4<f32>>
+        {
+                self.model_matrices()
+                        .iter()
+                        .zip(&self.joints)
+                        .map(|(matrix, joint)| matrix * joint.inverse_bind)
+                        .collect()
+        }
+
+        /// The palette as the vertex shader reads it for [`Skin::method`],
+        /// [`MAX_JOINTS`] entries of 64 bytes.
+        pub fn palette_bytes(&self) -> Vec<u8>
+        {
+                let palette = self.palette();
+                let mut data = vec![[[0.0f32; 4]; 4]; MAX_JOINTS];
+
+                for (entry, matrix) in data.iter_mut().zip(&palette)
+                {
+                        *entry = match self.method
+                        {
+                                SkinningMethod::Linear => (*matrix).into(),
+                                SkinningMethod::DualQuaternion =>
+                                {
+                                        let [real, dual] = dual_quaternion(matrix);
+                                        [real, dual, [0.0; 4], [0.0; 4]]
+                                }
+                        };
+                }
+
+                // Unused entries don't move anything, in case a vertex
+                // still points there.
+                for entry in data.iter_mut().skip(palette.len())
+                {
+                        *entry = match self.method
+                        {
+                                SkinningMethod::Linear => Matrix4::identity().into(),
+                                SkinningMethod::DualQuaternion =>
+                                {
+                                        [[0.0, 0.0, 0.0, 1.0], [0.0; 4], [0.0; 4], [0.0; 4]]
+                                }
+                        };
+                }
+
+                bytemuck::cast_slice(&data).to_vec()
+        }
+
+        /// The defines of the geometry shader permutation skinning with
+        /// [`Skin::method`].
+        pub fn defines(&self) -> crate::renderer::preprocess::ShaderDefines
+        {
+                let defines = crate::renderer::preprocess::ShaderDefines::new().with("SKINNED");
+
+                match self.method
+                {
+                        SkinningMethod::Linear => defines,
+                        SkinningMethod::DualQuaternion => defines.with("DUAL_QUATERNION_SKINNING"),
+                }
+        }
+}
+
+/// The rotation and translation of `matrix` as a unit dual quaternion, the
+/// real and dual part as `x, y, z, w`.
+fn dual_quaternion(matrix: &Matrix4<f32>) -> [[f32; 4]; 2]
+{
+        let transform = Transform::from_matrix(matrix);
+        let real = transform.rotation;
+        let t = transform.position;
+        let dual = Quaternion::new(0.0, t.x, t.y, t.z) * real * 0.5;
+
+        [
+                [real.v.x, real.v.y, real.v.z, real.s],
+                [dual.v.x, dual.v.y, dual.v.z, dual.s],
+        ]
+}
@@ -0,0 +1,104 @@
+//! Typed handles to assets owned by the engine.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+
+/// Refers to a `T` in the registry that handed it out, e.g. a
+/// [`Material`](crate::material::Material) in the
+/// [`MaterialRegistry`](crate::material_registry::MaterialRegistry).
+/// Serialized as its id, so saves can keep handles.
+#[derive(Serialize, Deserialize)]
+#[serde(transparent, bound = "")]
+pub struct Handle<T>
+{
+        id: u32,
+        #[serde(skip)]
+        marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Handle<T>
+{
+        pub fn new(id: u32) -> Self
+        {
+                Self {
+                        id,
+                        marker: PhantomData,
+                }
+        }
+
+        pub fn id(&self) -> u32
+        {
+                self.id
+        }
+}
+
+// Implemented by hand, deriving would require `T` to implement them too.
+
+impl<T> Clone for Handle<T>
+{
+        fn clone(&self) -> Self
+        {
+                *self
+        }
+}
+
+impl<T> Copy for Handle<T> {}
+
+impl<T> PartialEq for Handle<T>
+{
+        fn eq(
+                &self,
+                other: &Self,
+        ) -> bool
+        {
+                self.id == other.id
+        }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> PartialOrd for Handle<T>
+{
+        fn partial_cmp(
+                &self,
+                other: &Self,
+        ) -> Option<std::cmp::Ordering>
+        {
+                Some(self.cmp(other))
+        }
+}
+
+impl<T> Ord for Handle<T>
+{
+        fn cmp(
+                &self,
+                other: &Self,
+        ) -> std::cmp::Ordering
+        {
+                self.id.cmp(&other.id)
+        }
+}
+
+impl<T> Hash for Handle<T>
+{
+        fn hash<H: Hasher>(
+                &self,
+                state: &mut H,
+        )
+        {
+                self.id.hash(state);
+        }
+}
+
+impl<T> fmt::Debug for Handle<T>
+{
+        fn fmt(
+                &self,
+                f: &mut fmt::Formatter<'_>,
+        ) -> fmt::Result
+        {
+                write!(f, "Handle({})", self.id)
+        }
+}
@@ -0,0 +1,344 @@
+//! Game logic reloaded from a dynamic library while the engine runs.
+//!
+//! For native development only. The logic lives in its own crate built as a
+//! `cdylib` that depends on `oxide` with the `hot-reload` feature, keeps its
+//! state in one serializable type and exports it with [`hot_behavior!`]:
+//!
+//! ```ignore
+//! #[derive(Default, Serialize, Deserialize)]
+//! pub struct Logic
+//! {
+//!         spin: f32,
+//! }
+//!
+//! fn update(logic: &mut Logic, engine: &mut Engine)
+//! {
+//!         logic.spin += 1.0;
+//! }
+//!
+//! oxide::hot_behavior!(Logic, update);
+//! ```
+//!
+//! The game loads it with [`Engine::add_hot_behavior`]:
+//!
+//! ```ignore
+//! use std::env::consts::{DLL_PREFIX, DLL_SUFFIX};
+//!
+//! engine.add_hot_behavior(format!("target/debug/{DLL_PREFIX}logic{DLL_SUFFIX}"));
+//! ```
+//!
+//! After `cargo build -p logic` the engine notices the new library within a
+//! second, serializes the state with the old code, loads the new library and
+//! deserializes the state into it. Fields that can't be read back are reset
+//! to their defaults. The models stay loaded.
+//!
+//! Both sides must be built by the same compiler against the same `oxide`,
+//! since the engine is passed across as a Rust reference. The library must
+//! not hand the engine anything that points into its own code, e.g.
+//! closures for [`Engine::register_behavior`] or [`Engine::after`], as those
+//! would dangle after a reload.
+
+use crate::engine::Engine;
+use anyhow::Context;
+use libloading::Library;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+type LoadFn = fn(&str) -> *mut ();
+type UpdateFn = unsafe fn(*mut (), &mut Engine);
+type SaveFn = unsafe fn(*mut ()) -> String;
+type DropFn = unsafe fn(*mut ());
+
+/// Exports the functions [`Engine::add_hot_behavior`] looks for, see
+/// [`crate::hot_reload`].
+///
+/// `$state` is `Default + Serialize + DeserializeOwned`, `$update` a
+/// `fn(&mut $state, &mut Engine)`.
+#[macro_export]
+macro_rules! hot_behavior {
+        ($state:ty, $update:path) => {
+                #[doc(hidden)]
+                #[unsafe(no_mangle)]
+                pub fn oxide_hot_load(saved: &str) -> *mut ()
+                {
+                        Box::into_raw(Box::new($crate::hot_reload::restore::<$state>(saved))).cast()
+                }
+
+                /// # Safety
+                /// `state` comes from `oxide_hot_load` of this library.
+                #[doc(hidden)]
+                #[unsafe(no_mangle)]
+                pub unsafe fn oxide_hot_update(
+                        state: *mut (),
+                        engine: &mut $crate::engine::Engine,
+                )
+                {
+                        $update(unsafe { &mut *state.cast::<$state>() }, engine)
+                }
+
+                /// # Safety
+                /// `state` comes from `oxide_hot_load` of this library.
+                #[doc(hidden)]
+                #[unsafe(no_mangle)]
+                pub unsafe fn oxide_hot_save(state: *mut ()) -> String
+                {
+                        $crate::hot_reload::snapshot(unsafe { &*state.cast::<$state>() })
+                }
+
+                /// # Safety
+                /// `state` comes from `oxide_hot_load` of this library and
+                /// isn't used afterwards.
+                #[doc(hidden)]
+                #[unsafe(no_mangle)]
+                pub unsafe fn oxide_hot_drop(state: *mut ())
+                {
+                        drop(unsafe { Box::from_raw(state.cast::<$state>()) })
+                }
+        };
+}
+
+/// Reads the state saved before a reload, the default when there is none
+/// or it no longer fits the type.
+pub fn restore<T: Default + DeserializeOwned>(saved: &str) -> T
+{
+        if saved.is_empty()
+        {
+                return T::default();
+        }
+
+        serde_json::from_str(saved).unwrap_or_else(|e| {
+                log::warn!("Resetting hot reloaded state: {}", e);
+                T::default()
+        })
+}
+
+/// Saves the state before a reload.
+pub fn snapshot<T: Serialize>(state: &T) -> String
+{
+        serde_json::to_string(state).unwrap_or_else(|e| {
+                log::warn!("Failed to save hot reloaded state: {}", e);
+                String::new()
+        })
+}
+
+/// How often the library file is checked for changes.
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A loaded copy of the library and the state it created.
+struct Loaded
+{
+        library: Option<Library>,
+
+        /// Libraries are loaded from a copy, so the build can replace the
+        /// original and the OS doesn't hand back the cached old one.
+        copy: PathBuf,
+
+        state: *mut (),
+
+        update: UpdateFn,
+        save: SaveFn,
+        drop: DropFn,
+}
+
+impl Loaded
+{
+        fn open(
+                path: &PathBuf,
+                copy: PathBuf,
+                saved: &str,
+        ) -> anyhow::Result<Self>
+        {
+                let loaded = Self::load(path, copy.clone(), saved);
+
+                // Only a loaded copy is removed on drop, the library of a
+                // failed one is already unloaded here.
+                if loaded.is_err()
+                {
+                        let _ = std::fs::remove_file(&copy);
+                }
+
+                loaded
+        }
+
+        fn load(
+                path: &PathBuf,
+                copy: PathBuf,
+                saved: &str,
+        ) -> anyhow::Result<Self>
+        {
+                std::fs::copy(path, &copy)
+                        .with_context(|| format!("Failed to copy {}", path.display()))?;
+
+                // SAFETY: the library is built for this engine, see
+                // `crate::hot_reload`.
+                unsafe {
+                        let library = Library::new(&copy)?;
+
+                        let load = *library.get::<LoadFn>(b"oxide_hot_load")?;
+                        let update = *library.get::<UpdateFn>(b"oxide_hot_update")?;
+                        let save = *library.get::<SaveFn>(b"oxide_hot_save")?;
+                        let drop = *library.get::<DropFn>(b"oxide_hot_drop")?;
+
+                        Ok(Self {
+                                state: load(saved),
+                                library: Some(library),
+                                copy,
+                                update,
+                                save,
+                                drop,
+                        })
+                }
+        }
+
+        /// Serializes the state for the next library.
+        fn save(&self) -> String
+        {
+                // SAFETY: `state` was created by this library.
+                unsafe { (self.save)(self.state) }
+        }
+}
+
+impl Drop for Loaded
+{
+        fn drop(&mut self)
+        {
+                // SAFETY: `state` was created by this library, which is
+                // unloaded right after.
+                unsafe { (self.drop)(self.state) };
+
+                drop(self.library.take());
+
+                let _ = std::fs::remove_file(&self.copy);
+        }
+}
+
+/// A behavior running the update of a hot reloaded library, see
+/// [`crate::hot_reload`].
+pub struct HotBehavior
+{
+        path: PathBuf,
+
+        modified: Option<SystemTime>,
+
+        last_check: Option<instant::Instant>,
+
+        /// Copies made so far, numbering their file names.
+        generation: u32,
+
+        loaded: Option<Loaded>,
+}
+
+impl std::fmt::Debug for HotBehavior
+{
+        fn fmt(
+                &self,
+                f: &mut std::fmt::Formatter<'_>,
+        ) -> std::fmt::Result
+        {
+                f.debug_struct("HotBehavior")
+                        .field("path", &self.path)
+                        .field("generation", &self.generation)
+                        .field("loaded", &self.loaded.is_some())
+                        .finish()
+        }
+}
+
+impl HotBehavior
+{
+        pub fn new(path: impl Into<PathBuf>) -> Self
+        {
+                Self {
+                        path: path.into(),
+                        modified: None,
+                        last_check: None,
+                        generation: 0,
+                        loaded: None,
+                }
+        }
+
+        /// Reloads the library if it changed and runs its update.
+        pub fn update(
+                &mut self,
+                engine: &mut Engine,
+        )
+        {
+                self.check_reload();
+
+                if let Some(loaded) = &self.loaded
+                {
+                        // SAFETY: `state` was created by this library.
+                        unsafe { (loaded.update)(loaded.state, engine) };
+                }
+        }
+
+        fn check_reload(&mut self)
+        {
+                if self.last_check
+                        .is_some_and(|t| t.elapsed() < CHECK_INTERVAL)
+                {
+                        return;
+                }
+
+                self.last_check = Some(instant::Instant::now());
+
+                let modified = std::fs::metadata(&self.path)
+                        .and_then(|m| m.modified())
+                        .ok();
+
+                if modified.is_none() || modified == self.modified
+                {
+                        return;
+                }
+
+                self.generation += 1;
+
+                let file_name = self
+                        .path
+                        .file_name()
+                        .map(|n| n.to_string_lossy().to_string())
+                        .unwrap_or_default();
+
+                let copy = std::env::temp_dir().join(format!(
+                        "oxide-hot-{}-{}-{}",
+                        std::process::id(),
+                        self.generation,
+                        file_name
+                ));
+
+                let saved = self.loaded.as_ref().map(Loaded::save).unwrap_or_default();
+
+                // Not tried again until the file changes, e.g. when the build
+                // that was still writing it is done.
+                self.modified = modified;
+
+                // The old library keeps running if the new one can't be
+                // loaded.
+                match Loaded::open(&self.path, copy, &saved)
+                {
+                        Ok(loaded) =>
+                        {
+                                log::info!("Loaded {}", self.path.display());
+
+                                self.loaded = Some(loaded);
+                        }
+                        Err(e) => log::error!("Failed to load {}: {:#}", self.path.display(), e),
+                }
+        }
+}
+
+impl Engine
+{
+        /// Runs the update of a game logic library every frame, reloading
+        /// it when it's rebuilt. See [`crate::hot_reload`].
+        pub fn add_hot_behavior(
+                &mut self,
+                path: impl Into<PathBuf>,
+        )
+        {
+                let mut behavior = HotBehavior::new(path);
+
+                self.register_behavior(move |eng| behavior.update(eng));
+        }
+}
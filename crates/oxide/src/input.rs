@@ -0,0 +1,84 @@
+//! Input arbitration between the UI, the camera and the behaviors.
+//!
+//! `egui` sees every window event first. While it wants the keyboard, e.g.
+//! because a text field has focus, key presses don't reach
+//! [`Engine::pressed_keys`] or the camera controller. While it wants the
+//! pointer, mouse motion doesn't turn the camera. Releases always go
+//! through, so no key stays stuck.
+//!
+//! Without the `ui` feature nothing is captured.
+//!
+//! Behaviors that read input some other way check
+//! [`Engine::ui_wants_keyboard`] and [`Engine::ui_wants_pointer`]:
+//!
+//! ```ignore
+//! engine.register_behavior(|eng| {
+//!         if !eng.ui_wants_pointer()
+//!         {
+//!                 // pick a model under the cursor
+//!         }
+//! });
+//! ```
+
+use crate::engine::Engine;
+
+/// What the UI currently captures, owned by the [`Engine`].
+#[derive(Debug, Default, Clone, Copy)]
+pub struct InputCapture
+{
+        keyboard: bool,
+        pointer: bool,
+}
+
+impl InputCapture
+{
+        pub fn new() -> Self
+        {
+                Self::default()
+        }
+
+        /// The UI takes key presses.
+        pub fn keyboard(&self) -> bool
+        {
+                self.keyboard
+        }
+
+        /// The pointer is over the UI or dragging something in it.
+        pub fn pointer(&self) -> bool
+        {
+                self.pointer
+        }
+
+        /// Reads what the UI wants from `ctx`, `None` when no UI is drawn.
+        ///
+        /// Called by the engine for every window event, after `egui` saw
+        /// it. Returns true when the UI just took the keyboard.
+        #[cfg(feature = "ui")]
+        pub(crate) fn update(
+                &mut self,
+                ctx: Option<&egui::Context>,
+        ) -> bool
+        {
+                let was_keyboard = self.keyboard;
+
+                self.keyboard = ctx.is_some_and(|ctx| ctx.wants_keyboard_input());
+                self.pointer = ctx.is_some_and(|ctx| ctx.wants_pointer_input());
+
+                self.keyboard && !was_keyboard
+        }
+}
+
+impl Engine
+{
+        /// Key presses go to the UI, see [`crate::input`].
+        pub fn ui_wants_keyboard(&self) -> bool
+        {
+                self.input.keyboard()
+        }
+
+        /// Mouse input goes to the UI, see [`crate::input`].
+        pub fn ui_wants_pointer(&self) -> bool
+        {
+                self.input.pointer()
+        }
+}
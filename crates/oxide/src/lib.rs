@@ -50,6 +50,32 @@
 //!   now, do so by contributing to this crate or by maintaining a small fork;
 //!   the planned macro will reduce that need.
 //!
+//! # Using only the renderer
+//!
+//! The crate is split into features, each usable without the ones after it:
+//!
+//! - `renderer`: `renderer`, `model`, `geometry`, `camera`, `texture`,
+//!   `material` and the GPU side of `resources`, on wgpu.
+//! - `assets`: reading models, images and text from files or over HTTP,
+//!   the loaders in `resources`. Enables `renderer`.
+//! - `input`: winit glue, i.e. the camera's key bindings, creating the
+//!   surface from a window and `dpi`.
+//! - `runtime`: the window and event loop, game states, audio and the
+//!   other game systems. Enables the three above.
+//! - `ui`: the debug UI and the UI of game states, on egui.
+//!
+//! `runtime` and `ui` are enabled by default. A viewer embedded in an
+//! existing wgpu application, e.g. a CAD tool, only takes the renderer:
+//!
+//! ```toml
+//! oxide = { version = "0.1", default-features = false, features = ["renderer"] }
+//! ```
+//!
+//! Without `input` the application brings its own surface through
+//! `SurfaceManager::from_surface` and moves the camera with
+//! `CameraController::handle_action` instead of key codes. [`math`],
+//! [`transform`], [`lighting`] and the rest are always built.
+//!
 //! # Runtime lifecycle
 //!
 //! A typical frame performs the following steps:
@@ -142,15 +168,106 @@
 //! - `Ok(())` when the event loop exits cleanly.
 //! - An error if engine construction or the runner encounter a failure.
 
+#[cfg(feature = "runtime")]
+pub mod animation;
+#[cfg(feature = "runtime")]
+pub mod attachment;
+#[cfg(feature = "runtime")]
+pub mod audio;
+#[cfg(feature = "runtime")]
+pub mod audit;
+#[cfg(feature = "runtime")]
+pub mod benchmark;
+#[cfg(feature = "renderer")]
 pub mod camera;
+#[cfg(feature = "runtime")]
+pub mod camera_constraints;
+#[cfg(feature = "runtime")]
+pub mod camera_path;
+#[cfg(feature = "runtime")]
+pub mod cells;
+#[cfg(feature = "runtime")]
 pub mod config;
+#[cfg(all(feature = "runtime", not(target_arch = "wasm32")))]
+pub mod crash;
+#[cfg(feature = "input")]
+pub mod dpi;
+#[cfg(feature = "runtime")]
 pub mod engine;
+#[cfg(feature = "runtime")]
+pub mod environment;
+#[cfg(feature = "runtime")]
+pub mod events;
+#[cfg(feature = "runtime")]
+pub mod frame;
+#[cfg(feature = "runtime")]
+pub mod game_state;
 pub mod geometry;
+pub mod handle;
+#[cfg(all(feature = "hot-reload", not(target_arch = "wasm32")))]
+pub mod hot_reload;
+#[cfg(feature = "runtime")]
+pub mod input;
 pub mod lighting;
+#[cfg(feature = "runtime")]
+pub mod loading;
+pub mod log;
+#[cfg(feature = "renderer")]
 pub mod material;
+#[cfg(feature = "runtime")]
+pub mod material_registry;
+pub mod math;
+#[cfg(feature = "runtime")]
+pub mod measure;
+#[cfg(feature = "runtime")]
+pub mod memory;
+pub mod metadata;
+#[cfg(feature = "runtime")]
+pub mod metrics;
+#[cfg(feature = "renderer")]
 pub mod model;
+#[cfg(feature = "net")]
+pub mod net;
+#[cfg(feature = "runtime")]
+pub mod reflection;
+#[cfg(feature = "renderer")]
 pub mod renderer;
+#[cfg(feature = "runtime")]
+pub mod replay;
+#[cfg(feature = "renderer")]
 pub mod resources;
+#[cfg(feature = "runtime")]
+pub mod save;
+#[cfg(feature = "runtime")]
+pub mod scheduler;
+#[cfg(feature = "runtime")]
+pub mod selection;
+#[cfg(feature = "runtime")]
+pub mod shutdown;
+#[cfg(feature = "runtime")]
+pub mod snapping;
+#[cfg(feature = "scripting")]
+pub mod scripting;
+#[cfg(feature = "runtime")]
+pub mod steering;
+#[cfg(feature = "runtime")]
+pub mod streaming;
+#[cfg(feature = "renderer")]
 pub mod texture;
+pub mod transform;
+#[cfg(feature = "runtime")]
+pub mod tween;
+#[cfg(feature = "runtime")]
+pub mod upload;
+#[cfg(feature = "runtime")]
 pub mod ui;
+#[cfg(feature = "runtime")]
+pub mod undo;
 pub mod utils;
+#[cfg(feature = "runtime")]
+pub mod window;
+
+/// Re-exported so game state UIs can be written without depending on a
+/// matching `egui` version.
+#[cfg(feature = "ui")]
+pub use egui;
@@ -0,0 +1,468 @@
+//! Loading screen shown while the models added before the engine started
+//! are loaded, instead of a frozen window.
+//!
+//! Once the window and GPU are ready the models are read and decoded in the
+//! background, on a thread on native and as a task on the web, and uploaded
+//! as they arrive, see [`crate::upload`]. Meanwhile every frame draws the
+//! [`LoadingScreen`], a background, an optional splash image and a bar
+//! filling up as the models appear:
+//!
+//! ```ignore
+//! let mut engine = EngineBuilder::new()
+//!         .with_loading_screen(LoadingScreen {
+//!                 splash: Some("logo.png".to_string()),
+//!                 min_duration: Duration::from_secs(1),
+//!                 ..LoadingScreen::default()
+//!         })
+//!         .build()?;
+//! ```
+//!
+//! Behaviors, game states and scripts don't run and the simulation doesn't
+//! tick until every model is loaded or failed to load. Then
+//! [`LoadingEvent::Finished`] is published and the engine runs as usual.
+
+use crate::engine::Engine;
+use crate::renderer::splash::{SplashPass, SplashUniform};
+use crate::resources::ModelData;
+use crate::resources::import::ImportOptions;
+use anyhow::Result;
+use instant::Instant;
+use std::collections::HashSet;
+use std::time::Duration;
+
+/// Published on the event bus when the loading screen is done.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoadingEvent
+{
+        /// Every model is loaded, or failed to.
+        Finished,
+}
+
+/// Look of the loading screen, see
+/// [`EngineBuilder::with_loading_screen`](crate::engine::EngineBuilder::with_loading_screen).
+/// Colors are linear, like the clear color.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoadingScreen
+{
+        pub background: [f32; 4],
+
+        /// Loaded part of the progress bar.
+        pub bar: [f32; 4],
+
+        /// The rest of the progress bar.
+        pub track: [f32; 4],
+
+        /// Width of the progress bar, a fraction of the window's.
+        pub bar_width: f32,
+
+        /// Height of the progress bar in physical pixels.
+        pub bar_height: f32,
+
+        /// Image in `resources/` shown above the bar, e.g. a logo. It's fit
+        /// into half the window keeping its aspect ratio.
+        pub splash: Option<String>,
+
+        /// Shown at least this long, so quick loads don't flash it.
+        pub min_duration: Duration,
+}
+
+impl Default for LoadingScreen
+{
+        fn default() -> Self
+        {
+                Self {
+                        background: [0.01, 0.01, 0.012, 1.0],
+                        bar: [0.8, 0.8, 0.8, 1.0],
+                        track: [0.04, 0.04, 0.045, 1.0],
+                        bar_width: 0.4,
+                        bar_height: 6.0,
+                        splash: None,
+                        min_duration: Duration::ZERO,
+                }
+        }
+}
+
+impl LoadingScreen
+{
+        /// Where everything goes in a `width` by `height` target.
+        fn uniform(
+                &self,
+                width: f32,
+                height: f32,
+                image_size: Option<(u32, u32)>,
+                progress: f32,
+        ) -> SplashUniform
+        {
+                let bar_width = width * self.bar_width.clamp(0.0, 1.0);
+
+                let image_rect = image_size.map_or([0.0; 4], |(w, h)| {
+                        let scale = (width * 0.5 / w as f32).min(height * 0.5 / h as f32);
+                        let (w, h) = (w as f32 * scale, h as f32 * scale);
+
+                        [(width - w) * 0.5, height * 0.4 - h * 0.5, w, h]
+                });
+
+                SplashUniform {
+                        background: self.background,
+                        bar: self.bar,
+                        track: self.track,
+                        bar_rect: [
+                                (width - bar_width) * 0.5,
+                                height * 0.75 - self.bar_height * 0.5,
+                                bar_width,
+                                self.bar_height,
+                        ],
+                        image_rect,
+                        progress: progress.clamp(0.0, 1.0),
+                        _padding: [0.0; 3],
+                }
+        }
+}
+
+/// What the background loader hands over.
+#[derive(Debug)]
+pub(crate) enum Loaded
+{
+        Splash(image::RgbaImage),
+        Model(String, ModelData),
+        Failed(String),
+}
+
+/// Where the background loader hands over to, dropping it stops the loader
+/// after the model it's loading.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) type Inbox = std::sync::mpsc::Receiver<Loaded>;
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) type Inbox = std::rc::Rc<std::cell::RefCell<Vec<Loaded>>>;
+
+/// The loading screen and the models it waits for, owned by the
+/// [`Engine`].
+#[derive(Debug)]
+pub struct Loading
+{
+        pub screen: LoadingScreen,
+
+        active: bool,
+
+        started: Option<Instant>,
+
+        /// Handles of the models loaded behind the screen.
+        handles: Vec<String>,
+
+        failed: HashSet<String>,
+
+        progress: f32,
+
+        inbox: Option<Inbox>,
+
+        splash: Option<image::RgbaImage>,
+
+        /// Created with the first frame drawn.
+        pass: Option<SplashPass>,
+}
+
+impl Default for Loading
+{
+        fn default() -> Self
+        {
+                Self {
+                        screen: LoadingScreen::default(),
+                        active: true,
+                        started: None,
+                        handles: Vec::new(),
+                        failed: HashSet::new(),
+                        progress: 0.0,
+                        inbox: None,
+                        splash: None,
+                        pass: None,
+                }
+        }
+}
+
+impl Loading
+{
+        pub fn new() -> Self
+        {
+                Self::default()
+        }
+
+        /// Whether the loading screen is shown instead of the scene.
+        pub fn is_active(&self) -> bool
+        {
+                self.active
+        }
+
+        /// Part of the models loaded or failed to load, 0 to 1.
+        pub fn progress(&self) -> f32
+        {
+                self.progress
+        }
+
+        /// Starts loading the models of the engine in the background.
+        ///
+        /// Called by the engine once its state is created.
+        pub(crate) fn start(engine: &mut Engine)
+        {
+                let models: Vec<(String, String, ImportOptions)> = engine
+                        .model_map
+                        .iter()
+                        .map(|(handle, file)| {
+                                (handle.clone(), file.clone(), engine.import_options(handle))
+                        })
+                        .collect();
+
+                let loading = &mut engine.loading;
+
+                loading.handles = models.iter().map(|(handle, ..)| handle.clone()).collect();
+                loading.started = Some(Instant::now());
+                loading.inbox = Some(spawn_loader(models, loading.screen.splash.clone()));
+        }
+
+        /// Uploads the models loaded since the last frame and ends the
+        /// loading screen once all of them are there.
+        ///
+        /// Called by the engine once per frame while it's active.
+        pub(crate) fn update(engine: &mut Engine)
+        {
+                let loaded = engine
+                        .loading
+                        .inbox
+                        .as_ref()
+                        .map(receive)
+                        .unwrap_or_default();
+
+                for loaded in loaded
+                {
+                        match loaded
+                        {
+                                Loaded::Splash(image) => engine.loading.splash = Some(image),
+                                Loaded::Model(handle, data) => engine.queue_upload(handle, data),
+                                Loaded::Failed(handle) =>
+                                {
+                                        engine.loading.failed.insert(handle);
+                                }
+                        }
+                }
+
+                let (Some(state), Some(started)) = (engine.state.as_ref(), engine.loading.started)
+                else
+                {
+                        return;
+                };
+
+                let loading = &mut engine.loading;
+
+                let done = loading
+                        .handles
+                        .iter()
+                        .filter(|handle| {
+                                loading.failed.contains(*handle)
+                                        || state.models.contains_key(*handle)
+                        })
+                        .count();
+
+                loading.progress = if loading.handles.is_empty()
+                {
+                        1.0
+                }
+                else
+                {
+                        done as f32 / loading.handles.len() as f32
+                };
+
+                if done < loading.handles.len() || started.elapsed() < loading.screen.min_duration
+                {
+                        return;
+                }
+
+                log::info!(
+                        "Loaded {} models in {:.2?}",
+                        done - loading.failed.len(),
+                        started.elapsed()
+                );
+
+                loading.active = false;
+                loading.inbox = None;
+                loading.splash = None;
+                loading.pass = None;
+
+                // The simulation starts now, not with the engine.
+                engine.last_tick_time = Instant::now() - engine.start_time;
+                engine.events.publish(LoadingEvent::Finished);
+        }
+
+        /// Draws the loading screen into the next frame and presents it.
+        pub(crate) fn render(engine: &mut Engine) -> Result<()>
+        {
+                let loading = &mut engine.loading;
+
+                let Some(state) = engine.state.as_mut()
+                else
+                {
+                        return Ok(());
+                };
+
+                #[rustfmt::skip]
+                let Some((output, frame, mut encoder)) =
+                        state.surface_manager.acquire_frame(&state.device)?
+                else { return Ok(()); };
+
+                let format = state.surface_manager.render_format();
+
+                let pass = match &mut loading.pass
+                {
+                        Some(pass) if pass.format() == format => pass,
+                        pass => pass.insert(SplashPass::new(&state.device, &state.queue, format)),
+                };
+
+                if let Some(image) = &loading.splash
+                        && pass.image_size().is_none()
+                {
+                        pass.set_image(&state.device, &state.queue, image);
+                }
+
+                let configuration = &state.surface_manager.configuration;
+
+                pass.record(
+                        &state.queue,
+                        &mut encoder,
+                        &frame,
+                        &loading.screen.uniform(
+                                configuration.width as f32,
+                                configuration.height as f32,
+                                pass.image_size(),
+                                loading.progress,
+                        ),
+                );
+
+                state.surface_manager.finish_frame(&output, &mut encoder);
+
+                state.queue.submit(std::iter::once(encoder.finish()));
+                output.present();
+
+                Ok(())
+        }
+}
+
+/// What the loader handed over since the last call.
+pub(crate) fn receive(inbox: &Inbox) -> Vec<Loaded>
+{
+        #[cfg(not(target_arch = "wasm32"))]
+        return inbox.try_iter().collect();
+
+        #[cfg(target_arch = "wasm32")]
+        return inbox.borrow_mut().drain(..).collect();
+}
+
+/// Loads the splash image, then the models one after another, handing
+/// each to `send` until it returns false.
+async fn load(
+        models: Vec<(String, String, ImportOptions)>,
+        splash: Option<String>,
+        mut send: impl FnMut(Loaded) -> bool,
+)
+{
+        if let Some(file_name) = splash
+        {
+                match crate::resources::load_image(&file_name, Some("de_dust2")).await
+                {
+                        Ok(image) => _ = send(Loaded::Splash(image)),
+                        Err(e) => log::error!("Failed to load splash {}: {:#}", file_name, e),
+                }
+        }
+
+        for (handle, file_name, options) in models
+        {
+                let loaded = match crate::resources::load_model_data(
+                        &file_name,
+                        Some("de_dust2"),
+                        &options,
+                )
+                .await
+                {
+                        Ok(data) => Loaded::Model(handle, data),
+                        Err(e) =>
+                        {
+                                log::error!("Failed to load {}: {:#}", file_name, e);
+                                Loaded::Failed(handle)
+                        }
+                };
+
+                if !send(loaded)
+                {
+                        return;
+                }
+        }
+}
+
+/// Loads `models` and the `splash` image in the background, on a thread on
+/// native and as a task on the web.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn spawn_loader(
+        models: Vec<(String, String, ImportOptions)>,
+        splash: Option<String>,
+) -> Inbox
+{
+        let (sender, inbox) = std::sync::mpsc::channel();
+        let fallback = sender.clone();
+
+        let spawned = std::thread::Builder::new()
+                .name("oxide-loader".to_string())
+                .spawn({
+                        let (models, splash) = (models.clone(), splash.clone());
+
+                        move || {
+                                pollster::block_on(load(models, splash, |loaded| {
+                                        sender.send(loaded).is_ok()
+                                }))
+                        }
+                });
+
+        // Without a thread the models are loaded right here, behind a frozen
+        // window.
+        if let Err(e) = spawned
+        {
+                log::error!("Failed to start the loader thread: {}", e);
+
+                pollster::block_on(load(models, splash, |loaded| fallback.send(loaded).is_ok()));
+        }
+
+        inbox
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn spawn_loader(
+        models: Vec<(String, String, ImportOptions)>,
+        splash: Option<String>,
+) -> Inbox
+{
+        let inbox = Inbox::default();
+        let sink = inbox.clone();
+
+        wasm_bindgen_futures::spawn_local(async move {
+                load(models, splash, |loaded| {
+                        sink.borrow_mut().push(loaded);
+                        true
+                })
+                .await
+        });
+
+        inbox
+}
+
+impl Engine
+{
+        /// Whether the loading screen is still shown, see
+        /// [`crate::loading`].
+        pub fn is_loading(&self) -> bool
+        {
+                self.loading.is_active()
+        }
+
+        /// Part of the startup models loaded, 0 to 1.
+        pub fn loading_progress(&self) -> f32
+        {
+                self.loading.progress()
+        }
+}
@@ -0,0 +1,538 @@
+//! The logger behind the `log` macros: a global level, levels per module,
+//! a rolling log file on native and the last lines kept in memory.
+//!
+//! `#[oxide_main]` installs it with the level of its `log` option. The
+//! rest of the settings come from [`LogConfig`], given to
+//! [`EngineBuilder::with_log`](crate::engine::EngineBuilder::with_log) or
+//! to [`init`] directly:
+//!
+//! ```ignore
+//! oxide::log::init(
+//!         LogConfig::with_level(LevelFilter::Info)
+//!                 .module("wgpu_core", LevelFilter::Warn)
+//!                 .module("oxide::renderer", LevelFilter::Debug)
+//!                 .with_file("logs/game.log"),
+//! );
+//! ```
+//!
+//! A module's level applies to its submodules too, the most specific one
+//! wins. On native `RUST_LOG` overrides the config, e.g.
+//! `RUST_LOG=info,oxide::engine=trace`. Lines go to stderr on native and
+//! the browser console on the web.
+//!
+//! Levels change at runtime with [`set_level`] and [`set_module_level`],
+//! or from the debug UI's "Console" window.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
+
+pub use ::log::{Level, LevelFilter};
+
+/// Lines [`recent_lines`] keeps.
+const RECENT_LINES: usize = 500;
+
+/// Logger settings.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogConfig
+{
+        /// Level of the modules without their own.
+        pub level: LevelFilter,
+        /// Levels by module path, e.g. `"oxide::renderer"`.
+        pub modules: BTreeMap<String, LevelFilter>,
+        /// File the lines are also written to, native only.
+        pub file: Option<PathBuf>,
+        /// Bytes the file grows to before it's rolled over to `file.1`,
+        /// the older ones to `file.2` and on.
+        pub max_file_size: u64,
+        /// Rolled over files kept.
+        pub max_files: usize,
+}
+
+impl Default for LogConfig
+{
+        fn default() -> Self
+        {
+                Self {
+                        level: LevelFilter::Info,
+                        modules: BTreeMap::new(),
+                        file: None,
+                        max_file_size: 10 * 1024 * 1024,
+                        max_files: 3,
+                }
+        }
+}
+
+impl LogConfig
+{
+        pub fn with_level(level: LevelFilter) -> Self
+        {
+                Self {
+                        level,
+                        ..Self::default()
+                }
+        }
+
+        /// Logs `module` and its submodules at `level`.
+        pub fn module(
+                mut self,
+                module: impl Into<String>,
+                level: LevelFilter,
+        ) -> Self
+        {
+                self.modules.insert(module.into(), level);
+                self
+        }
+
+        /// Also writes the lines to `path`, see [`LogConfig::file`].
+        pub fn with_file(
+                mut self,
+                path: impl Into<PathBuf>,
+        ) -> Self
+        {
+                self.file = Some(path.into());
+                self
+        }
+
+        /// Level of `target`, from its most specific module.
+        pub fn level_of(
+                &self,
+                target: &str,
+        ) -> LevelFilter
+        {
+                self.modules
+                        .iter()
+                        .filter(|(module, _)| {
+                                target == module.as_str()
+                                        || target
+                                                .strip_prefix(module.as_str())
+                                                .is_some_and(|rest| rest.starts_with("::"))
+                        })
+                        .max_by_key(|(module, _)| module.len())
+                        .map_or(self.level, |(_, level)| *level)
+        }
+
+        /// The most verbose level of any module.
+        fn max_level(&self) -> LevelFilter
+        {
+                self.modules
+                        .values()
+                        .copied()
+                        .chain(std::iter::once(self.level))
+                        .max()
+                        .unwrap_or(self.level)
+        }
+
+        /// `RUST_LOG` style filters, `"info,wgpu_core=warn"`, applied over
+        /// the config. Unknown levels are skipped.
+        pub fn apply_filters(
+                &mut self,
+                filters: &str,
+        )
+        {
+                for filter in filters.split(',').map(str::trim).filter(|f| !f.is_empty())
+                {
+                        match filter.split_once('=')
+                        {
+                                Some((module, level)) =>
+                                {
+                                        if let Ok(level) = level.trim().parse()
+                                        {
+                                                self.modules
+                                                        .insert(module.trim().to_string(), level);
+                                        }
+                                }
+                                None =>
+                                {
+                                        if let Ok(level) = filter.parse()
+                                        {
+                                                self.level = level;
+                                        }
+                                        // A module alone logs everything.
+                                        else
+                                        {
+                                                self.modules.insert(
+                                                        filter.to_string(),
+                                                        LevelFilter::Trace,
+                                                );
+                                        }
+                                }
+                        }
+                }
+        }
+}
+
+/// A logged line, see [`recent_lines`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogLine
+{
+        /// Seconds since the logger was installed.
+        pub time: f32,
+        pub level: Level,
+        pub target: String,
+        pub message: String,
+}
+
+impl std::fmt::Display for LogLine
+{
+        fn fmt(
+                &self,
+                f: &mut std::fmt::Formatter<'_>,
+        ) -> std::fmt::Result
+        {
+                write!(
+                        f,
+                        "[{:>9.3}s {:<5} {}] {}",
+                        self.time, self.level, self.target, self.message
+                )
+        }
+}
+
+struct State
+{
+        config: LogConfig,
+        start: instant::Instant,
+        recent: VecDeque<LogLine>,
+        #[cfg(not(target_arch = "wasm32"))]
+        file: Option<RollingFile>,
+}
+
+struct Logger
+{
+        state: Mutex<Option<State>>,
+}
+
+static LOGGER: Logger = Logger {
+        state: Mutex::new(None),
+};
+
+impl Logger
+{
+        /// A panic while logging leaves the state usable, so later lines
+        /// aren't lost.
+        fn lock(&self) -> MutexGuard<'_, Option<State>>
+        {
+                self.state.lock().unwrap_or_else(|e| e.into_inner())
+        }
+}
+
+impl ::log::Log for Logger
+{
+        fn enabled(
+                &self,
+                metadata: &::log::Metadata,
+        ) -> bool
+        {
+                self.lock().as_ref().is_some_and(|state| {
+                        metadata.level() <= state.config.level_of(metadata.target())
+                })
+        }
+
+        fn log(
+                &self,
+                record: &::log::Record,
+        )
+        {
+                let mut state = self.lock();
+
+                let Some(state) = state.as_mut()
+                else
+                {
+                        return;
+                };
+
+                if record.level() > state.config.level_of(record.target())
+                {
+                        return;
+                }
+
+                let line = LogLine {
+                        time: state.start.elapsed().as_secs_f32(),
+                        level: record.level(),
+                        target: record.target().to_string(),
+                        message: record.args().to_string(),
+                };
+
+                #[cfg(not(target_arch = "wasm32"))]
+                {
+                        eprintln!("{}", line);
+
+                        if let Some(file) = &mut state.file
+                        {
+                                file.write_line(&line, &state.config);
+                        }
+                }
+
+                #[cfg(target_arch = "wasm32")]
+                {
+                        let text = wasm_bindgen::JsValue::from_str(&line.to_string());
+
+                        match line.level
+                        {
+                                Level::Error => web_sys::console::error_1(&text),
+                                Level::Warn => web_sys::console::warn_1(&text),
+                                Level::Info => web_sys::console::info_1(&text),
+                                Level::Debug | Level::Trace => web_sys::console::debug_1(&text),
+                        }
+                }
+
+                if state.recent.len() == RECENT_LINES
+                {
+                        state.recent.pop_front();
+                }
+
+                state.recent.push_back(line);
+        }
+
+        fn flush(&self)
+        {
+                #[cfg(not(target_arch = "wasm32"))]
+                if let Some(file) = self.lock().as_mut().and_then(|state| state.file.as_mut())
+                {
+                        file.flush();
+                }
+        }
+}
+
+/// Installs the logger with `config`, or changes the config of the
+/// installed one. `RUST_LOG` is applied over it on native.
+pub fn init(mut config: LogConfig)
+{
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Ok(filters) = std::env::var("RUST_LOG")
+        {
+                config.apply_filters(&filters);
+        }
+
+        let installed = {
+                let mut state = LOGGER.lock();
+                let installed = state.is_some();
+
+                match state.as_mut()
+                {
+                        Some(state) => state.set_config(config),
+                        None =>
+                        {
+                                let mut new = State {
+                                        config: LogConfig::default(),
+                                        start: instant::Instant::now(),
+                                        recent: VecDeque::new(),
+                                        #[cfg(not(target_arch = "wasm32"))]
+                                        file: None,
+                                };
+
+                                new.set_config(config);
+                                *state = Some(new);
+                        }
+                }
+
+                installed
+        };
+
+        if !installed && ::log::set_logger(&LOGGER).is_err()
+        {
+                // Another logger was installed first, ours stays unused.
+                *LOGGER.lock() = None;
+        }
+}
+
+impl State
+{
+        fn set_config(
+                &mut self,
+                config: LogConfig,
+        )
+        {
+                ::log::set_max_level(config.max_level());
+
+                #[cfg(not(target_arch = "wasm32"))]
+                if self.file.as_ref().map(|f| &f.path) != config.file.as_ref()
+                {
+                        self.file = config.file.as_ref().and_then(|path| {
+                                RollingFile::open(path.clone())
+                                        .map_err(|e| {
+                                                eprintln!(
+                                                        "Failed to open log file {}: {}",
+                                                        path.display(),
+                                                        e
+                                                )
+                                        })
+                                        .ok()
+                        });
+                }
+
+                self.config = config;
+        }
+}
+
+/// The config of the installed logger, the default one before [`init`].
+pub fn config() -> LogConfig
+{
+        LOGGER.lock()
+                .as_ref()
+                .map(|state| state.config.clone())
+                .unwrap_or_default()
+}
+
+/// Changes the config of the installed logger, see [`init`].
+fn edit(change: impl FnOnce(&mut LogConfig))
+{
+        if let Some(state) = LOGGER.lock().as_mut()
+        {
+                let mut config = state.config.clone();
+                change(&mut config);
+                state.set_config(config);
+        }
+}
+
+/// Sets the level of the modules without their own.
+pub fn set_level(level: LevelFilter)
+{
+        edit(|config| config.level = level);
+}
+
+/// Sets the level of `module` and its submodules, `None` removes it so
+/// they follow their parent again.
+pub fn set_module_level(
+        module: &str,
+        level: Option<LevelFilter>,
+)
+{
+        edit(|config| match level
+        {
+                Some(level) =>
+                {
+                        config.modules.insert(module.to_string(), level);
+                }
+                None =>
+                {
+                        config.modules.remove(module);
+                }
+        });
+}
+
+/// The last lines logged, oldest first.
+pub fn recent_lines() -> Vec<LogLine>
+{
+        LOGGER.lock()
+                .as_ref()
+                .map(|state| state.recent.iter().cloned().collect())
+                .unwrap_or_default()
+}
+
+/// The log file, rolled over when it gets too large.
+#[cfg(not(target_arch = "wasm32"))]
+struct RollingFile
+{
+        path: PathBuf,
+        file: std::io::BufWriter<std::fs::File>,
+        size: u64,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl RollingFile
+{
+        fn open(path: PathBuf) -> std::io::Result<Self>
+        {
+                if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty())
+                {
+                        std::fs::create_dir_all(dir)?;
+                }
+
+                let file = std::fs::OpenOptions::new()
+                        .create(true)
+                        .append(true)
+                        .open(&path)?;
+                let size = file.metadata()?.len();
+
+                Ok(Self {
+                        path,
+                        file: std::io::BufWriter::new(file),
+                        size,
+                })
+        }
+
+        /// `path` with `.index` appended.
+        fn rolled(
+                &self,
+                index: usize,
+        ) -> PathBuf
+        {
+                let mut path = self.path.clone().into_os_string();
+                path.push(format!(".{}", index));
+                path.into()
+        }
+
+        fn write_line(
+                &mut self,
+                line: &LogLine,
+                config: &LogConfig,
+        )
+        {
+                use std::io::Write;
+
+                let text = line.to_string();
+                let size = text.len() as u64 + 1;
+
+                if self.size > 0 && self.size + size > config.max_file_size
+                {
+                        self.roll(config.max_files);
+                }
+
+                if writeln!(self.file, "{}", text).is_ok()
+                {
+                        self.size += size;
+                }
+
+                // Warnings and errors reach the file even if the process
+                // dies right after.
+                if line.level <= Level::Warn
+                {
+                        self.flush();
+                }
+        }
+
+        fn flush(&mut self)
+        {
+                use std::io::Write;
+
+                let _ = self.file.flush();
+        }
+
+        /// Moves every file one index up, the current one to `.1`, and
+        /// starts a new one.
+        fn roll(
+                &mut self,
+                max_files: usize,
+        )
+        {
+                self.flush();
+
+                if max_files == 0
+                {
+                        let _ = std::fs::remove_file(&self.path);
+                }
+                else
+                {
+                        let _ = std::fs::remove_file(self.rolled(max_files));
+
+                        for index in (1..max_files).rev()
+                        {
+                                let _ = std::fs::rename(self.rolled(index), self.rolled(index + 1));
+                        }
+
+                        let _ = std::fs::rename(&self.path, self.rolled(1));
+                }
+
+                match Self::open(self.path.clone())
+                {
+                        Ok(file) => *self = file,
+                        Err(e) =>
+                        {
+                                eprintln!("Failed to roll log file {}: {}", self.path.display(), e)
+                        }
+                }
+        }
+}
@@ -1,3 +1,9 @@
+use crate::renderer::preprocess::ShaderDefines;
+use crate::renderer::surface::ColorSpace;
+use crate::resources::extras::Extras;
+use serde::{Deserialize, Serialize};
+use wgpu::util::DeviceExt;
+
 #[derive(Debug)]
 pub struct MaterialData
 {
@@ -8,9 +14,22 @@ pub struct MaterialData
         pub base_color_factor: [f32; 4],
         pub metallic_factor: f32,
         pub roughness_factor: f32,
+        /// Indices into the model's images.
         pub base_color_texture_index: Option<usize>,
         pub normal_texture_index: Option<usize>,
         pub metallic_roughness_texture_index: Option<usize>,
+        /// How the base color texture is sampled, from the glTF sampler.
+        pub sampler: SamplerDesc,
+        pub normal_sampler: SamplerDesc,
+        pub metallic_roughness_sampler: SamplerDesc,
+        /// How the texture data is stored, sRGB for colors and linear for
+        /// data like normals, which must not be decoded as colors.
+        pub base_color_space: ColorSpace,
+        pub normal_space: ColorSpace,
+        pub metallic_roughness_space: ColorSpace,
+        /// Application data of the glTF material, see
+        /// [`crate::resources::extras`].
+        pub extras: Extras,
 }
 
 impl Default for MaterialData
@@ -28,11 +47,178 @@ impl Default for MaterialData
                         base_color_texture_index: None,
                         normal_texture_index: None,
                         metallic_roughness_texture_index: None,
+                        sampler: SamplerDesc::default(),
+                        normal_sampler: SamplerDesc::default(),
+                        metallic_roughness_sampler: SamplerDesc::default(),
+                        base_color_space: ColorSpace::Srgb,
+                        normal_space: ColorSpace::Linear,
+                        metallic_roughness_space: ColorSpace::Linear,
+                        extras: Extras::new(),
                 }
         }
 }
 
-#[derive(Debug)]
+/// How a material samples its base color texture, see
+/// [`Model::set_sampler`](crate::model::Model::set_sampler).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SamplerDesc
+{
+        pub address_mode_u: wgpu::AddressMode,
+        pub address_mode_v: wgpu::AddressMode,
+        pub mag_filter: wgpu::FilterMode,
+        pub min_filter: wgpu::FilterMode,
+        pub mipmap_filter: wgpu::FilterMode,
+        pub lod_min_clamp: f32,
+        pub lod_max_clamp: f32,
+        /// Added to the mip level picked by the GPU, done in the shader
+        /// since samplers have no bias in WebGPU.
+        pub lod_bias: f32,
+        /// Maximum anisotropy, 1 turns it off. Only used when every filter
+        /// is linear.
+        pub anisotropy_clamp: u16,
+}
+
+impl Default for SamplerDesc
+{
+        fn default() -> Self
+        {
+                Self {
+                        address_mode_u: wgpu::AddressMode::Repeat,
+                        address_mode_v: wgpu::AddressMode::Repeat,
+                        mag_filter: wgpu::FilterMode::Linear,
+                        min_filter: wgpu::FilterMode::Linear,
+                        mipmap_filter: wgpu::FilterMode::Linear,
+                        lod_min_clamp: 0.0,
+                        lod_max_clamp: 32.0,
+                        lod_bias: 0.0,
+                        anisotropy_clamp: 1,
+                }
+        }
+}
+
+impl SamplerDesc
+{
+        /// Pixelated, e.g. for pixel art.
+        pub fn nearest() -> Self
+        {
+                Self {
+                        mag_filter: wgpu::FilterMode::Nearest,
+                        min_filter: wgpu::FilterMode::Nearest,
+                        mipmap_filter: wgpu::FilterMode::Nearest,
+                        ..Self::default()
+                }
+        }
+
+        /// The sampler of a glTF texture, linear filtering where it doesn't
+        /// say.
+        pub fn from_gltf(sampler: &gltf::texture::Sampler) -> Self
+        {
+                use gltf::texture::{MagFilter, MinFilter, WrappingMode};
+
+                let wrap = |mode| match mode
+                {
+                        WrappingMode::ClampToEdge => wgpu::AddressMode::ClampToEdge,
+                        WrappingMode::MirroredRepeat => wgpu::AddressMode::MirrorRepeat,
+                        WrappingMode::Repeat => wgpu::AddressMode::Repeat,
+                };
+
+                let mag_filter = match sampler.mag_filter()
+                {
+                        Some(MagFilter::Nearest) => wgpu::FilterMode::Nearest,
+                        _ => wgpu::FilterMode::Linear,
+                };
+
+                let (min_filter, mipmap_filter) = match sampler.min_filter()
+                {
+                        Some(MinFilter::Nearest | MinFilter::NearestMipmapNearest) =>
+                        {
+                                (wgpu::FilterMode::Nearest, wgpu::FilterMode::Nearest)
+                        }
+                        Some(MinFilter::NearestMipmapLinear) =>
+                        {
+                                (wgpu::FilterMode::Nearest, wgpu::FilterMode::Linear)
+                        }
+                        Some(MinFilter::LinearMipmapNearest) =>
+                        {
+                                (wgpu::FilterMode::Linear, wgpu::FilterMode::Nearest)
+                        }
+                        _ => (wgpu::FilterMode::Linear, wgpu::FilterMode::Linear),
+                };
+
+                Self {
+                        address_mode_u: wrap(sampler.wrap_s()),
+                        address_mode_v: wrap(sampler.wrap_t()),
+                        mag_filter,
+                        min_filter,
+                        mipmap_filter,
+                        ..Self::default()
+                }
+        }
+
+        pub fn create_sampler(
+                &self,
+                device: &wgpu::Device,
+        ) -> wgpu::Sampler
+        {
+                let linear = [self.mag_filter, self.min_filter, self.mipmap_filter]
+                        .iter()
+                        .all(|f| *f == wgpu::FilterMode::Linear);
+
+                device.create_sampler(&wgpu::SamplerDescriptor {
+                        label: Some("Material Sampler"),
+                        address_mode_u: self.address_mode_u,
+                        address_mode_v: self.address_mode_v,
+                        mag_filter: self.mag_filter,
+                        min_filter: self.min_filter,
+                        mipmap_filter: self.mipmap_filter,
+                        lod_min_clamp: self.lod_min_clamp,
+                        lod_max_clamp: self.lod_max_clamp,
+                        anisotropy_clamp: if linear
+                        {
+                                self.anisotropy_clamp.max(1)
+                        }
+                        else
+                        {
+                                1
+                        },
+                        ..Default::default()
+                })
+        }
+}
+
+/// The editable and serialized part of a material, see
+/// [`MaterialRegistry`](crate::material_registry::MaterialRegistry).
+/// Textures aren't part of it, they come from the model a material was
+/// shared from.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MaterialDesc
+{
+        pub name: String,
+        pub base_color_factor: [f32; 4],
+        pub metallic_factor: f32,
+        pub roughness_factor: f32,
+        /// See [`SamplerDesc::lod_bias`].
+        pub lod_bias: f32,
+}
+
+impl Default for MaterialDesc
+{
+        fn default() -> Self
+        {
+                Self {
+                        name: "material".to_string(),
+                        base_color_factor: [1.0, 1.0, 1.0, 1.0],
+                        metallic_factor: 1.0,
+                        roughness_factor: 1.0,
+                        lod_bias: 0.0,
+                }
+        }
+}
+
+/// Cloning shares the GPU resources, writes to the properties buffer show
+/// on every clone.
+#[derive(Debug, Clone)]
 pub struct Material
 {
         pub name: String,
@@ -42,7 +228,279 @@ pub struct Material
         pub base_color_factor: [f32; 4],
         pub metallic_factor: f32,
         pub roughness_factor: f32,
+        pub sampler: SamplerDesc,
+        pub properties_buffer: wgpu::Buffer,
         pub material_bind_group: wgpu::BindGroup,
+        /// Source of the base color texture when it's streamed.
+        pub stream: Option<crate::texture::StreamSource>,
+        /// Application data of the glTF material, see
+        /// [`crate::resources::extras`].
+        pub extras: Extras,
+}
+
+impl Material
+{
+        /// A material without textures, white where its shader samples them.
+        pub fn from_desc(
+                device: &wgpu::Device,
+                queue: &wgpu::Queue,
+                desc: &MaterialDesc,
+        ) -> Self
+        {
+                let sampler = SamplerDesc {
+                        lod_bias: desc.lod_bias,
+                        ..SamplerDesc::default()
+                };
+
+                let properties_buffer =
+                        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                                label: Some(&format!("{} Properties Buffer", desc.name)),
+                                contents: &[0; std::mem::size_of::<MaterialProperties>()],
+                                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                        });
+
+                let base_color_texture = crate::texture::Texture {
+                        sampler: sampler.create_sampler(device),
+                        ..crate::texture::Texture::create_dummy(device, queue)
+                };
+
+                let mut material = Self {
+                        name: desc.name.clone(),
+                        material_bind_group: device.create_bind_group(&wgpu::BindGroupDescriptor {
+                                layout: &create_material_bind_group_layout(device),
+                                entries: &[
+                                        wgpu::BindGroupEntry {
+                                                binding: 0,
+                                                resource: wgpu::BindingResource::TextureView(
+                                                        &base_color_texture.view,
+                                                ),
+                                        },
+                                        wgpu::BindGroupEntry {
+                                                binding: 1,
+                                                resource: wgpu::BindingResource::Sampler(
+                                                        &base_color_texture.sampler,
+                                                ),
+                                        },
+                                        wgpu::BindGroupEntry {
+                                                binding: 2,
+                                                resource: properties_buffer.as_entire_binding(),
+                                        },
+                                        wgpu::BindGroupEntry {
+                                                binding: 3,
+                                                resource: wgpu::BindingResource::TextureView(
+                                                        &base_color_texture.view,
+                                                ),
+                                        },
+                                        wgpu::BindGroupEntry {
+                                                binding: 4,
+                                                resource: wgpu::BindingResource::Sampler(
+                                                        &base_color_texture.sampler,
+                                                ),
+                                        },
+                                ],
+                                label: Some(&format!("{} Material Bind Group", desc.name)),
+                        }),
+                        base_color_texture,
+                        normal_texture: None,
+                        metallic_roughness_texture: None,
+                        base_color_factor: desc.base_color_factor,
+                        metallic_factor: desc.metallic_factor,
+                        roughness_factor: desc.roughness_factor,
+                        sampler,
+                        properties_buffer,
+                        stream: None,
+                        extras: Extras::new(),
+                };
+
+                material.apply_desc(queue, desc);
+
+                material
+        }
+
+        /// A copy sharing the textures, with its own factors so editing one
+        /// leaves the other as it is.
+        pub fn duplicate(
+                &self,
+                device: &wgpu::Device,
+        ) -> Self
+        {
+                let properties_buffer =
+                        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                                label: Some(&format!("{} Properties Buffer", self.name)),
+                                contents: bytemuck::cast_slice(&[self.properties()]),
+                                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                        });
+
+                let mut material = Self {
+                        name: self.name.clone(),
+                        base_color_texture: self.base_color_texture.clone(),
+                        normal_texture: self.normal_texture.clone(),
+                        metallic_roughness_texture: self.metallic_roughness_texture.clone(),
+                        base_color_factor: self.base_color_factor,
+                        metallic_factor: self.metallic_factor,
+                        roughness_factor: self.roughness_factor,
+                        sampler: self.sampler,
+                        material_bind_group: self.material_bind_group.clone(),
+                        properties_buffer,
+                        stream: self.stream.clone(),
+                        extras: self.extras.clone(),
+                };
+
+                material.material_bind_group = material
+                        .create_bind_group(device, &create_material_bind_group_layout(device));
+
+                material
+        }
+
+        pub fn desc(&self) -> MaterialDesc
+        {
+                MaterialDesc {
+                        name: self.name.clone(),
+                        base_color_factor: self.base_color_factor,
+                        metallic_factor: self.metallic_factor,
+                        roughness_factor: self.roughness_factor,
+                        lod_bias: self.sampler.lod_bias,
+                }
+        }
+
+        /// Takes the factors of `desc` and uploads them, the bind group
+        /// stays the same.
+        pub fn apply_desc(
+                &mut self,
+                queue: &wgpu::Queue,
+                desc: &MaterialDesc,
+        )
+        {
+                self.name = desc.name.clone();
+                self.base_color_factor = desc.base_color_factor;
+                self.metallic_factor = desc.metallic_factor;
+                self.roughness_factor = desc.roughness_factor;
+                self.sampler.lod_bias = desc.lod_bias;
+
+                queue.write_buffer(
+                        &self.properties_buffer,
+                        0,
+                        bytemuck::cast_slice(&[self.properties()]),
+                );
+        }
+
+        /// Edits the factors, uploaded right away. Returns true when one
+        /// was edited.
+        #[cfg(feature = "ui")]
+        pub fn ui(
+                &mut self,
+                ui: &mut egui::Ui,
+                queue: &wgpu::Queue,
+        ) -> bool
+        {
+                let mut desc = self.desc();
+                let mut changed = false;
+
+                ui.collapsing(&self.name, |ui| {
+                        ui.horizontal(|ui| {
+                                ui.label("Base color");
+                                changed |= ui
+                                        .color_edit_button_rgba_unmultiplied(
+                                                &mut desc.base_color_factor,
+                                        )
+                                        .changed();
+                        });
+
+                        changed |= ui
+                                .add(egui::Slider::new(&mut desc.metallic_factor, 0.0..=1.0)
+                                        .text("Metallic"))
+                                .changed();
+                        changed |= ui
+                                .add(egui::Slider::new(&mut desc.roughness_factor, 0.0..=1.0)
+                                        .text("Roughness"))
+                                .changed();
+                        changed |= ui
+                                .add(egui::Slider::new(&mut desc.lod_bias, -4.0..=4.0)
+                                        .text("LOD bias"))
+                                .changed();
+                });
+
+                if changed
+                {
+                        self.apply_desc(queue, &desc);
+                }
+
+                changed
+        }
+
+        pub fn properties(&self) -> MaterialProperties
+        {
+                MaterialProperties {
+                        base_color_factor: self.base_color_factor,
+                        metallic_factor: self.metallic_factor,
+                        roughness_factor: self.roughness_factor,
+                        lod_bias: self.sampler.lod_bias,
+                        _padding: 0.0,
+                }
+        }
+
+        /// Features the geometry pipeline is built for, see
+        /// [`crate::renderer::preprocess`].
+        pub fn defines(&self) -> ShaderDefines
+        {
+                let mut defines = ShaderDefines::new();
+
+                if self.normal_texture.is_some()
+                {
+                        defines.insert("HAS_NORMAL_MAP");
+                }
+
+                defines
+        }
+
+        /// Binds the textures and the properties buffer.
+        pub fn create_bind_group(
+                &self,
+                device: &wgpu::Device,
+                layout: &wgpu::BindGroupLayout,
+        ) -> wgpu::BindGroup
+        {
+                // Something is bound without a normal map, it isn't read.
+                let normal_texture = self
+                        .normal_texture
+                        .as_ref()
+                        .unwrap_or(&self.base_color_texture);
+
+                device.create_bind_group(&wgpu::BindGroupDescriptor {
+                        layout,
+                        entries: &[
+                                wgpu::BindGroupEntry {
+                                        binding: 0,
+                                        resource: wgpu::BindingResource::TextureView(
+                                                &self.base_color_texture.view,
+                                        ),
+                                },
+                                wgpu::BindGroupEntry {
+                                        binding: 1,
+                                        resource: wgpu::BindingResource::Sampler(
+                                                &self.base_color_texture.sampler,
+                                        ),
+                                },
+                                wgpu::BindGroupEntry {
+                                        binding: 2,
+                                        resource: self.properties_buffer.as_entire_binding(),
+                                },
+                                wgpu::BindGroupEntry {
+                                        binding: 3,
+                                        resource: wgpu::BindingResource::TextureView(
+                                                &normal_texture.view,
+                                        ),
+                                },
+                                wgpu::BindGroupEntry {
+                                        binding: 4,
+                                        resource: wgpu::BindingResource::Sampler(
+                                                &normal_texture.sampler,
+                                        ),
+                                },
+                        ],
+                        label: Some(&format!("{} Material Bind Group", self.name)),
+                })
+        }
 }
 
 #[repr(C)]
@@ -52,8 +510,9 @@ pub struct MaterialProperties
         pub base_color_factor: [f32; 4],
         pub metallic_factor: f32,
         pub roughness_factor: f32,
+        pub lod_bias: f32,
         // Padding to meet WGSL alignment requirements (16 bytes)
-        pub _padding: [f32; 2],
+        pub _padding: f32,
 }
 
 pub fn create_material_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout
@@ -91,6 +550,26 @@ pub fn create_material_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGro
                                 },
                                 count: None,
                         },
+                        // Normal map, read by the `HAS_NORMAL_MAP` permutation
+                        wgpu::BindGroupLayoutEntry {
+                                binding: 3,
+                                visibility: wgpu::ShaderStages::FRAGMENT,
+                                ty: wgpu::BindingType::Texture {
+                                        sample_type: wgpu::TextureSampleType::Float {
+                                                filterable: true,
+                                        },
+                                        view_dimension: wgpu::TextureViewDimension::D2,
+                                        multisampled: false,
+                                },
+                                count: None,
+                        },
+                        // Normal map sampler
+                        wgpu::BindGroupLayoutEntry {
+                                binding: 4,
+                                visibility: wgpu::ShaderStages::FRAGMENT,
+                                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                                count: None,
+                        },
                 ],
                 label: Some("material_bind_group_layout"),
         })
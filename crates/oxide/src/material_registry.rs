@@ -0,0 +1,296 @@
+//! Materials as assets shared between models.
+//!
+//! Every model loads its own materials. The [`MaterialRegistry`] holds
+//! materials on their own, created at runtime or shared from a model, that
+//! any material slot of any model can use. Editing one changes it on every
+//! model using it:
+//!
+//! ```ignore
+//! let gold = engine.create_material(MaterialDesc {
+//!         name: "gold".to_string(),
+//!         base_color_factor: [1.0, 0.8, 0.3, 1.0],
+//!         ..MaterialDesc::default()
+//! });
+//!
+//! engine.set_model_material("ball", 0, gold);
+//! engine.set_model_material("paddle", 0, gold);
+//!
+//! engine.edit_material(gold, |desc| desc.roughness_factor = 0.2);
+//! ```
+//!
+//! The registry serializes to JSON with [`MaterialRegistry::to_json`] and
+//! is restored with [`Engine::load_materials`], keeping the handles.
+
+use crate::engine::Engine;
+use crate::handle::Handle;
+use crate::material::{Material, MaterialDesc};
+use std::collections::{BTreeMap, HashMap};
+
+/// Materials by handle, owned by the [`Engine`], see
+/// [`Engine::materials`].
+#[derive(Debug, Default)]
+pub struct MaterialRegistry
+{
+        descs: BTreeMap<Handle<Material>, MaterialDesc>,
+        /// Created on first use, a material can be made before the GPU.
+        gpu: HashMap<Handle<Material>, Material>,
+        next_id: u32,
+}
+
+impl MaterialRegistry
+{
+        pub fn new() -> Self
+        {
+                Self::default()
+        }
+
+        pub fn insert(
+                &mut self,
+                desc: MaterialDesc,
+        ) -> Handle<Material>
+        {
+                let handle = Handle::new(self.next_id);
+
+                self.next_id += 1;
+                self.descs.insert(handle, desc);
+
+                handle
+        }
+
+        pub fn get(
+                &self,
+                handle: Handle<Material>,
+        ) -> Option<&MaterialDesc>
+        {
+                self.descs.get(&handle)
+        }
+
+        pub fn iter(&self) -> impl Iterator<Item = (Handle<Material>, &MaterialDesc)>
+        {
+                self.descs.iter().map(|(handle, desc)| (*handle, desc))
+        }
+
+        pub fn len(&self) -> usize
+        {
+                self.descs.len()
+        }
+
+        pub fn is_empty(&self) -> bool
+        {
+                self.descs.is_empty()
+        }
+
+        /// The material on the GPU, created the first time.
+        pub fn gpu(
+                &mut self,
+                handle: Handle<Material>,
+                device: &wgpu::Device,
+                queue: &wgpu::Queue,
+        ) -> Option<&Material>
+        {
+                let desc = self.descs.get(&handle)?;
+
+                Some(self
+                        .gpu
+                        .entry(handle)
+                        .or_insert_with(|| Material::from_desc(device, queue, desc)))
+        }
+
+        /// Every material by handle.
+        pub fn to_json(&self) -> anyhow::Result<String>
+        {
+                Ok(serde_json::to_string_pretty(&self.descs)?)
+        }
+
+        /// Replaces the materials with the ones in `json`, see
+        /// [`Engine::load_materials`] to update the models too.
+        pub fn from_json(
+                &mut self,
+                json: &str,
+        ) -> anyhow::Result<()>
+        {
+                let descs: BTreeMap<Handle<Material>, MaterialDesc> = serde_json::from_str(json)?;
+
+                self.gpu.retain(|handle, _| descs.contains_key(handle));
+                self.next_id = descs
+                        .keys()
+                        .map(|handle| handle.id() + 1)
+                        .max()
+                        .unwrap_or(0)
+                        .max(self.next_id);
+                self.descs = descs;
+
+                Ok(())
+        }
+}
+
+impl Engine
+{
+        /// The shared materials.
+        pub fn materials(&self) -> &MaterialRegistry
+        {
+                &self.materials
+        }
+
+        /// Adds a material without textures, see
+        /// [`Engine::set_model_material`] to use it.
+        pub fn create_material(
+                &mut self,
+                desc: MaterialDesc,
+        ) -> Handle<Material>
+        {
+                self.materials.insert(desc)
+        }
+
+        /// Makes material `slot` of the model `handle` a shared material,
+        /// keeping its textures, so other models can use it too. The
+        /// material is no longer streamed.
+        pub fn share_material(
+                &mut self,
+                handle: &str,
+                slot: usize,
+        ) -> Option<Handle<Material>>
+        {
+                let model = self.state.as_mut()?.models.get_mut(handle)?;
+
+                let material = model.materials.get_mut(slot)?;
+
+                if let Some(shared) = model.material_handles.get(&slot)
+                {
+                        return Some(*shared);
+                }
+
+                material.stream = None;
+
+                let shared = self.materials.insert(material.desc());
+
+                self.materials.gpu.insert(shared, material.clone());
+                model.material_handles.insert(slot, shared);
+
+                Some(shared)
+        }
+
+        /// Draws material `slot` of the model `handle` with the shared
+        /// `material`.
+        pub fn set_model_material(
+                &mut self,
+                handle: &str,
+                slot: usize,
+                material: Handle<Material>,
+        )
+        {
+                let Some(state) = self.state.as_mut()
+                else
+                {
+                        return;
+                };
+
+                let Some(model) = state.models.get_mut(handle)
+                else
+                {
+                        log::warn!("set_model_material: no model {}", handle);
+                        return;
+                };
+
+                let Some(target) = model.materials.get_mut(slot)
+                else
+                {
+                        log::warn!("set_model_material: no material in slot {}", slot);
+                        return;
+                };
+
+                let Some(shared) = self.materials.gpu(material, &state.device, &state.queue)
+                else
+                {
+                        log::warn!("set_model_material: no material {:?}", material);
+                        return;
+                };
+
+                *target = shared.clone();
+                model.material_handles.insert(slot, material);
+        }
+
+        /// Edits a shared material, every model using it changes.
+        pub fn edit_material(
+                &mut self,
+                material: Handle<Material>,
+                edit: impl FnOnce(&mut MaterialDesc),
+        )
+        {
+                let Some(desc) = self.materials.descs.get_mut(&material)
+                else
+                {
+                        log::warn!("edit_material: no material {:?}", material);
+                        return;
+                };
+
+                edit(desc);
+
+                self.sync_material(material);
+        }
+
+        /// Replaces the shared materials with the ones in `json`, from
+        /// [`MaterialRegistry::to_json`], and updates the models using them.
+        pub fn load_materials(
+                &mut self,
+                json: &str,
+        ) -> anyhow::Result<()>
+        {
+                self.materials.from_json(json)?;
+
+                let handles: Vec<_> = self.materials.descs.keys().copied().collect();
+
+                for handle in handles
+                {
+                        self.sync_material(handle);
+                }
+
+                Ok(())
+        }
+
+        /// Uploads the desc of `material` and copies it to the models.
+        fn sync_material(
+                &mut self,
+                material: Handle<Material>,
+        )
+        {
+                let Some(desc) = self.materials.descs.get(&material)
+                else
+                {
+                        return;
+                };
+
+                let Some(state) = self.state.as_mut()
+                else
+                {
+                        return;
+                };
+
+                if let Some(shared) = self.materials.gpu.get_mut(&material)
+                {
+                        shared.apply_desc(&state.queue, desc);
+                }
+
+                // The models share the buffer, only their copies of the
+                // factors are out of date.
+                for model in state.models.values_mut()
+                {
+                        for (slot, handle) in model.material_handles.iter()
+                        {
+                                if *handle != material
+                                {
+                                        continue;
+                                }
+
+                                if let Some(target) = model.materials.get_mut(*slot)
+                                {
+                                        target.name = desc.name.clone();
+                                        target.base_color_factor = desc.base_color_factor;
+                                        target.metallic_factor = desc.metallic_factor;
+                                        target.roughness_factor = desc.roughness_factor;
+                                        target.sampler.lod_bias = desc.lod_bias;
+                                }
+                        }
+                }
+        }
+}
@@ -0,0 +1,736 @@
+//! Bounding volumes and the intersection tests between them.
+//!
+//! The same types back frustum culling, picking and trigger volumes, and
+//! are there for game code checking whether things touch:
+//!
+//! ```ignore
+//! let state = engine.state.as_ref().unwrap();
+//! let ball = Sphere::new(ball_position, 0.25);
+//!
+//! if let Some(paddle) = state.models.get("paddle").and_then(|m| m.world_obb())
+//!         && ball.intersects_obb(&paddle)
+//! {
+//!         // Bounce.
+//! }
+//!
+//! // The model under the middle of the screen.
+//! let hit = engine.raycast(&state.camera.ray(0.0, 0.0));
+//! ```
+//!
+//! Tests return `true` for volumes that only touch, ray tests return the
+//! distance along the ray to the first hit, `0.0` when the ray starts
+//! inside.
+
+pub use crate::geometry::bounds::Aabb;
+use cgmath::{
+        EuclideanSpace, InnerSpace, Matrix, Matrix4, Point3, SquareMatrix, Transform, Vector3,
+        Vector4,
+};
+
+/// Below this, a direction is treated as parallel to a plane or axis.
+const EPSILON: f32 = 1e-6;
+
+impl Aabb
+{
+        /// Whether `point` is inside or on the box.
+        pub fn contains_point(
+                &self,
+                point: Point3<f32>,
+        ) -> bool
+        {
+                point.x >= self.min.x
+                        && point.x <= self.max.x
+                        && point.y >= self.min.y
+                        && point.y <= self.max.y
+                        && point.z >= self.min.z
+                        && point.z <= self.max.z
+        }
+
+        pub fn intersects(
+                &self,
+                other: &Aabb,
+        ) -> bool
+        {
+                self.min.x <= other.max.x
+                        && self.max.x >= other.min.x
+                        && self.min.y <= other.max.y
+                        && self.max.y >= other.min.y
+                        && self.min.z <= other.max.z
+                        && self.max.z >= other.min.z
+        }
+
+        /// The point in the box nearest to `point`, `point` itself when
+        /// it's inside.
+        pub fn closest_point(
+                &self,
+                point: Point3<f32>,
+        ) -> Point3<f32>
+        {
+                Point3::new(
+                        point.x.clamp(self.min.x, self.max.x),
+                        point.y.clamp(self.min.y, self.max.y),
+                        point.z.clamp(self.min.z, self.max.z),
+                )
+        }
+
+        pub fn intersects_sphere(
+                &self,
+                sphere: &Sphere,
+        ) -> bool
+        {
+                sphere.intersects_aabb(self)
+        }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sphere
+{
+        pub center: Point3<f32>,
+        pub radius: f32,
+}
+
+impl Sphere
+{
+        pub fn new(
+                center: Point3<f32>,
+                radius: f32,
+        ) -> Self
+        {
+                Self {
+                        center,
+                        radius,
+                }
+        }
+
+        /// Smallest sphere around `aabb`.
+        pub fn from_aabb(aabb: &Aabb) -> Self
+        {
+                Self::new(aabb.center(), aabb.size().magnitude() * 0.5)
+        }
+
+        pub fn contains_point(
+                &self,
+                point: Point3<f32>,
+        ) -> bool
+        {
+                (point - self.center).magnitude2() <= self.radius * self.radius
+        }
+
+        pub fn intersects(
+                &self,
+                other: &Sphere,
+        ) -> bool
+        {
+                let radius = self.radius + other.radius;
+
+                (other.center - self.center).magnitude2() <= radius * radius
+        }
+
+        pub fn intersects_aabb(
+                &self,
+                aabb: &Aabb,
+        ) -> bool
+        {
+                self.contains_point(aabb.closest_point(self.center))
+        }
+
+        pub fn intersects_obb(
+                &self,
+                obb: &Obb,
+        ) -> bool
+        {
+                self.contains_point(obb.closest_point(self.center))
+        }
+}
+
+/// Oriented bounding box, a box rotated into any direction.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Obb
+{
+        pub center: Point3<f32>,
+        /// Unit vectors along the box's local X, Y and Z.
+        pub axes: [Vector3<f32>; 3],
+        /// Half the size along each of `axes`.
+        pub half_extents: Vector3<f32>,
+}
+
+impl Obb
+{
+        pub fn new(
+                center: Point3<f32>,
+                axes: [Vector3<f32>; 3],
+                half_extents: Vector3<f32>,
+        ) -> Self
+        {
+                Self {
+                        center,
+                        axes,
+                        half_extents,
+                }
+        }
+
+        /// `aabb` moved, rotated and scaled by `matrix`, which must not
+        /// shear. Unlike [`Aabb::transform`] it stays as tight as the box.
+        pub fn from_aabb(
+                aabb: &Aabb,
+                matrix: &Matrix4<f32>,
+        ) -> Self
+        {
+                let half = aabb.size() * 0.5;
+                let axes = [
+                        matrix.transform_vector(Vector3::unit_x() * half.x),
+                        matrix.transform_vector(Vector3::unit_y() * half.y),
+                        matrix.transform_vector(Vector3::unit_z() * half.z),
+                ];
+
+                let length = axes.map(|a| a.magnitude());
+                let unit = |i: usize, fallback: Vector3<f32>| {
+                        if length[i] > EPSILON
+                        {
+                                axes[i] / length[i]
+                        }
+                        else
+                        {
+                                matrix.transform_vector(fallback).normalize()
+                        }
+                };
+
+                Self {
+                        center: matrix.transform_point(aabb.center()),
+                        axes: [
+                                unit(0, Vector3::unit_x()),
+                                unit(1, Vector3::unit_y()),
+                                unit(2, Vector3::unit_z()),
+                        ],
+                        half_extents: Vector3::new(length[0], length[1], length[2]),
+                }
+        }
+
+        /// `v` in the box's space, rotated so its axes are X, Y and Z.
+        fn local(
+                &self,
+                v: Vector3<f32>,
+        ) -> Vector3<f32>
+        {
+                Vector3::new(v.dot(self.axes[0]), v.dot(self.axes[1]), v.dot(self.axes[2]))
+        }
+
+        pub fn contains_point(
+                &self,
+                point: Point3<f32>,
+        ) -> bool
+        {
+                let local = self.local(point - self.center);
+
+                local.x.abs() <= self.half_extents.x
+                        && local.y.abs() <= self.half_extents.y
+                        && local.z.abs() <= self.half_extents.z
+        }
+
+        pub fn closest_point(
+                &self,
+                point: Point3<f32>,
+        ) -> Point3<f32>
+        {
+                let local = self.local(point - self.center);
+                let half = self.half_extents;
+
+                self.center
+                        + self.axes[0] * local.x.clamp(-half.x, half.x)
+                        + self.axes[1] * local.y.clamp(-half.y, half.y)
+                        + self.axes[2] * local.z.clamp(-half.z, half.z)
+        }
+
+        pub fn corners(&self) -> [Point3<f32>; 8]
+        {
+                let [x, y, z] = [
+                        self.axes[0] * self.half_extents.x,
+                        self.axes[1] * self.half_extents.y,
+                        self.axes[2] * self.half_extents.z,
+                ];
+                let c = self.center;
+
+                [
+                        c - x - y - z,
+                        c + x - y - z,
+                        c + x + y - z,
+                        c - x + y - z,
+                        c - x - y + z,
+                        c + x - y + z,
+                        c + x + y + z,
+                        c - x + y + z,
+                ]
+        }
+
+        /// Axis aligned box around the corners.
+        pub fn aabb(&self) -> Aabb
+        {
+                Aabb::from_points(self.corners()).unwrap_or(Aabb::new(self.center, self.center))
+        }
+
+        /// Separating axis test over the 15 axes that can keep two boxes
+        /// apart.
+        pub fn intersects(
+                &self,
+                other: &Obb,
+        ) -> bool
+        {
+                let (ea, eb) = (self.half_extents, other.half_extents);
+
+                // `other`'s axes and center in this box's space.
+                let r: [[f32; 3]; 3] = std::array::from_fn(|i| {
+                        std::array::from_fn(|j| self.axes[i].dot(other.axes[j]))
+                });
+                // Padded so parallel edges don't produce a zero axis.
+                let abs_r: [[f32; 3]; 3] =
+                        std::array::from_fn(|i| std::array::from_fn(|j| r[i][j].abs() + EPSILON));
+                let t = self.local(other.center - self.center);
+
+                for i in 0..3
+                {
+                        let rb = eb[0] * abs_r[i][0] + eb[1] * abs_r[i][1] + eb[2] * abs_r[i][2];
+
+                        if t[i].abs() > ea[i] + rb
+                        {
+                                return false;
+                        }
+                }
+
+                for j in 0..3
+                {
+                        let ra = ea[0] * abs_r[0][j] + ea[1] * abs_r[1][j] + ea[2] * abs_r[2][j];
+                        let distance = t[0] * r[0][j] + t[1] * r[1][j] + t[2] * r[2][j];
+
+                        if distance.abs() > ra + eb[j]
+                        {
+                                return false;
+                        }
+                }
+
+                for i in 0..3
+                {
+                        let (i1, i2) = ((i + 1) % 3, (i + 2) % 3);
+
+                        for j in 0..3
+                        {
+                                let (j1, j2) = ((j + 1) % 3, (j + 2) % 3);
+
+                                let ra = ea[i1] * abs_r[i2][j] + ea[i2] * abs_r[i1][j];
+                                let rb = eb[j1] * abs_r[i][j2] + eb[j2] * abs_r[i][j1];
+                                let distance = t[i2] * r[i1][j] - t[i1] * r[i2][j];
+
+                                if distance.abs() > ra + rb
+                                {
+                                        return false;
+                                }
+                        }
+                }
+
+                true
+        }
+
+        pub fn intersects_aabb(
+                &self,
+                aabb: &Aabb,
+        ) -> bool
+        {
+                self.intersects(&Obb::from(*aabb))
+        }
+
+        pub fn intersects_sphere(
+                &self,
+                sphere: &Sphere,
+        ) -> bool
+        {
+                sphere.intersects_obb(self)
+        }
+}
+
+impl From<Aabb> for Obb
+{
+        fn from(aabb: Aabb) -> Self
+        {
+                Self::new(
+                        aabb.center(),
+                        [Vector3::unit_x(), Vector3::unit_y(), Vector3::unit_z()],
+                        aabb.size() * 0.5,
+                )
+        }
+}
+
+/// The points `normal · p + distance = 0`, `normal` is a unit vector
+/// pointing to the positive side.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Plane
+{
+        pub normal: Vector3<f32>,
+        pub distance: f32,
+}
+
+impl Plane
+{
+        /// `normal` doesn't need to be a unit vector, the plane is
+        /// normalized.
+        pub fn new(
+                normal: Vector3<f32>,
+                distance: f32,
+        ) -> Self
+        {
+                let length = normal.magnitude();
+
+                Self {
+                        normal: normal / length,
+                        distance: distance / length,
+                }
+        }
+
+        pub fn from_point_normal(
+                point: Point3<f32>,
+                normal: Vector3<f32>,
+        ) -> Self
+        {
+                let normal = normal.normalize();
+
+                Self {
+                        normal,
+                        distance: -normal.dot(point.to_vec()),
+                }
+        }
+
+        /// The plane through a counter-clockwise triangle, facing the side
+        /// it's counter-clockwise from.
+        pub fn from_points(
+                a: Point3<f32>,
+                b: Point3<f32>,
+                c: Point3<f32>,
+        ) -> Self
+        {
+                Self::from_point_normal(a, (b - a).cross(c - a))
+        }
+
+        /// Positive on the side `normal` points to.
+        pub fn signed_distance(
+                &self,
+                point: Point3<f32>,
+        ) -> f32
+        {
+                self.normal.dot(point.to_vec()) + self.distance
+        }
+
+        /// Whether `aabb` is fully on the negative side.
+        pub fn is_behind_aabb(
+                &self,
+                aabb: &Aabb,
+        ) -> bool
+        {
+                // The corner furthest along the normal.
+                let corner = Point3::new(
+                        if self.normal.x >= 0.0
+                        {
+                                aabb.max.x
+                        }
+                        else
+                        {
+                                aabb.min.x
+                        },
+                        if self.normal.y >= 0.0
+                        {
+                                aabb.max.y
+                        }
+                        else
+                        {
+                                aabb.min.y
+                        },
+                        if self.normal.z >= 0.0
+                        {
+                                aabb.max.z
+                        }
+                        else
+                        {
+                                aabb.min.z
+                        },
+                );
+
+                self.signed_distance(corner) < 0.0
+        }
+}
+
+/// A half line, e.g. a bullet's path or the line under the cursor.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ray
+{
+        pub origin: Point3<f32>,
+        /// Unit vector, so distances along the ray are in world units.
+        pub direction: Vector3<f32>,
+}
+
+impl Ray
+{
+        /// `direction` is normalized.
+        pub fn new(
+                origin: Point3<f32>,
+                direction: Vector3<f32>,
+        ) -> Self
+        {
+                Self {
+                        origin,
+                        direction: direction.normalize(),
+                }
+        }
+
+        /// The ray through `x` and `y` in normalized device coordinates,
+        /// -1 to 1 from the bottom left, from the near plane of
+        /// `view_proj` away from the camera. `None` when the matrix can't
+        /// be inverted.
+        pub fn from_ndc(
+                x: f32,
+                y: f32,
+                view_proj: &Matrix4<f32>,
+                reversed_z: bool,
+        ) -> Option<Self>
+        {
+                let inverse = view_proj.invert()?;
+                let (near, far) = if reversed_z { (1.0, 0.0) } else { (0.0, 1.0) };
+
+                let unproject = |z: f32| {
+                        let p = inverse * Vector4::new(x, y, z, 1.0);
+
+                        Point3::from_homogeneous(p)
+                };
+
+                let (near, far) = (unproject(near), unproject(far));
+
+                Some(Self::new(near, far - near))
+        }
+
+        pub fn at(
+                &self,
+                distance: f32,
+        ) -> Point3<f32>
+        {
+                self.origin + self.direction * distance
+        }
+
+        pub fn intersect_plane(
+                &self,
+                plane: &Plane,
+        ) -> Option<f32>
+        {
+                let denominator = plane.normal.dot(self.direction);
+
+                if denominator.abs() < EPSILON
+                {
+                        return None;
+                }
+
+                let distance = -plane.signed_distance(self.origin) / denominator;
+
+                (distance >= 0.0).then_some(distance)
+        }
+
+        pub fn intersect_sphere(
+                &self,
+                sphere: &Sphere,
+        ) -> Option<f32>
+        {
+                let m = self.origin - sphere.center;
+                let b = m.dot(self.direction);
+                let c = m.magnitude2() - sphere.radius * sphere.radius;
+
+                // Outside and pointing away.
+                if c > 0.0 && b > 0.0
+                {
+                        return None;
+                }
+
+                let discriminant = b * b - c;
+
+                if discriminant < 0.0
+                {
+                        return None;
+                }
+
+                Some((-b - discriminant.sqrt()).max(0.0))
+        }
+
+        pub fn intersect_aabb(
+                &self,
+                aabb: &Aabb,
+        ) -> Option<f32>
+        {
+                slabs(self.origin.to_vec(), self.direction, aabb.min.to_vec(), aabb.max.to_vec())
+        }
+
+        pub fn intersect_obb(
+                &self,
+                obb: &Obb,
+        ) -> Option<f32>
+        {
+                slabs(
+                        obb.local(self.origin - obb.center),
+                        obb.local(self.direction),
+                        -obb.half_extents,
+                        obb.half_extents,
+                )
+        }
+
+        /// Hits either side of the triangle.
+        pub fn intersect_triangle(
+                &self,
+                a: Point3<f32>,
+                b: Point3<f32>,
+                c: Point3<f32>,
+        ) -> Option<f32>
+        {
+                let (ab, ac) = (b - a, c - a);
+                let p = self.direction.cross(ac);
+                let determinant = ab.dot(p);
+
+                if determinant.abs() < EPSILON
+                {
+                        return None;
+                }
+
+                let inverse = 1.0 / determinant;
+                let t = self.origin - a;
+                let u = t.dot(p) * inverse;
+
+                if !(0.0..=1.0).contains(&u)
+                {
+                        return None;
+                }
+
+                let q = t.cross(ab);
+                let v = self.direction.dot(q) * inverse;
+
+                if v < 0.0 || u + v > 1.0
+                {
+                        return None;
+                }
+
+                let distance = ac.dot(q) * inverse;
+
+                (distance >= 0.0).then_some(distance)
+        }
+}
+
+/// Distance to the box `min` to `max` along the ray, by clipping it
+/// against the three pairs of planes.
+fn slabs(
+        origin: Vector3<f32>,
+        direction: Vector3<f32>,
+        min: Vector3<f32>,
+        max: Vector3<f32>,
+) -> Option<f32>
+{
+        let mut near = 0.0_f32;
+        let mut far = f32::INFINITY;
+
+        for i in 0..3
+        {
+                if direction[i].abs() < EPSILON
+                {
+                        // Parallel, misses unless it runs between the planes.
+                        if origin[i] < min[i] || origin[i] > max[i]
+                        {
+                                return None;
+                        }
+
+                        continue;
+                }
+
+                let inverse = 1.0 / direction[i];
+                let (t0, t1) = ((min[i] - origin[i]) * inverse, (max[i] - origin[i]) * inverse);
+
+                near = near.max(t0.min(t1));
+                far = far.min(t0.max(t1));
+
+                if near > far
+                {
+                        return None;
+                }
+        }
+
+        Some(near)
+}
+
+/// The six planes around what a camera sees, normals pointing inwards.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frustum
+{
+        /// Left, right, bottom, top, near and far.
+        pub planes: [Plane; 6],
+}
+
+impl Frustum
+{
+        /// The frustum of a view projection matrix with depth 0 to 1 as
+        /// wgpu uses it, reversed or not.
+        pub fn from_matrix(view_proj: &Matrix4<f32>) -> Self
+        {
+                let row = |i: usize| view_proj.row(i);
+                let plane = |v: Vector4<f32>| Plane::new(v.truncate(), v.w);
+
+                Self {
+                        planes: [
+                                plane(row(3) + row(0)),
+                                plane(row(3) - row(0)),
+                                plane(row(3) + row(1)),
+                                plane(row(3) - row(1)),
+                                plane(row(2)),
+                                plane(row(3) - row(2)),
+                        ],
+                }
+        }
+
+        pub fn contains_point(
+                &self,
+                point: Point3<f32>,
+        ) -> bool
+        {
+                self.planes.iter().all(|p| p.signed_distance(point) >= 0.0)
+        }
+
+        /// Conservative, a sphere just outside a corner counts as visible.
+        pub fn intersects_sphere(
+                &self,
+                sphere: &Sphere,
+        ) -> bool
+        {
+                self.planes
+                        .iter()
+                        .all(|p| p.signed_distance(sphere.center) >= -sphere.radius)
+        }
+
+        /// Conservative like [`Frustum::intersects_sphere`].
+        pub fn intersects_aabb(
+                &self,
+                aabb: &Aabb,
+        ) -> bool
+        {
+                !self.planes.iter().any(|p| p.is_behind_aabb(aabb))
+        }
+}
+
+#[cfg(feature = "runtime")]
+impl crate::engine::Engine
+{
+        /// The nearest visible model `ray` hits and the distance to it,
+        /// tested against the models' oriented bounds.
+        pub fn raycast(
+                &self,
+                ray: &Ray,
+        ) -> Option<(&str, f32)>
+        {
+                let state = self.state.as_ref()?;
+
+                state.models
+                        .iter()
+                        .filter(|(_, model)| model.visible)
+                        .filter_map(|(handle, model)| {
+                                ray.intersect_obb(&model.world_obb()?)
+                                        .map(|d| (handle.as_str(), d))
+                        })
+                        .min_by(|a, b| a.1.total_cmp(&b.1))
+        }
+}
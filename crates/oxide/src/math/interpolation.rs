@@ -0,0 +1,303 @@
+//! Smoothing values over time and between fixed ticks.
+//!
+//! Game logic running on ticks moves things in steps, drawing them where
+//! they were at the last tick stutters whenever frames and ticks don't
+//! line up. An [`Interpolated`] value keeps the last two ticks and is drawn
+//! between them at [`Engine::lerp_alpha`](crate::engine::Engine::lerp_alpha):
+//!
+//! ```ignore
+//! let mut head = Interpolated::new(Transform::IDENTITY);
+//!
+//! engine.register_behavior(move |engine| {
+//!         if ticked
+//!         {
+//!                 head.push(head.current().with_position(next_cell));
+//!         }
+//!
+//!         engine.interpolate_model("snake_head", &head);
+//! });
+//! ```
+//!
+//! [`damp`] and [`smooth_damp`] follow a moving target frame by frame
+//! instead, e.g. a camera trailing the player, the same at any frame rate.
+
+use crate::transform::Transform;
+use cgmath::{
+        EuclideanSpace, InnerSpace, Matrix3, One, Point3, Quaternion, Vector2, Vector3, VectorSpace,
+};
+
+/// Values with a blend between two of them, `0.0` giving the first and
+/// `1.0` the second.
+pub trait Interpolate: Copy
+{
+        fn interpolate(
+                &self,
+                other: &Self,
+                t: f32,
+        ) -> Self;
+}
+
+impl Interpolate for f32
+{
+        fn interpolate(
+                &self,
+                other: &Self,
+                t: f32,
+        ) -> Self
+        {
+                self + (other - self) * t
+        }
+}
+
+impl Interpolate for Vector2<f32>
+{
+        fn interpolate(
+                &self,
+                other: &Self,
+                t: f32,
+        ) -> Self
+        {
+                self.lerp(*other, t)
+        }
+}
+
+impl Interpolate for Vector3<f32>
+{
+        fn interpolate(
+                &self,
+                other: &Self,
+                t: f32,
+        ) -> Self
+        {
+                self.lerp(*other, t)
+        }
+}
+
+impl Interpolate for Point3<f32>
+{
+        fn interpolate(
+                &self,
+                other: &Self,
+                t: f32,
+        ) -> Self
+        {
+                self + (other - self) * t
+        }
+}
+
+/// Spherical, along the shorter arc, so a turn keeps its speed and never
+/// goes the long way around.
+impl Interpolate for Quaternion<f32>
+{
+        fn interpolate(
+                &self,
+                other: &Self,
+                t: f32,
+        ) -> Self
+        {
+                self.slerp(*other, t)
+        }
+}
+
+impl Interpolate for Transform
+{
+        fn interpolate(
+                &self,
+                other: &Self,
+                t: f32,
+        ) -> Self
+        {
+                self.lerp(other, t)
+        }
+}
+
+/// A value changed on ticks, drawn between its last two values.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Interpolated<T>
+{
+        previous: T,
+        current: T,
+}
+
+impl<T: Interpolate> Interpolated<T>
+{
+        pub fn new(value: T) -> Self
+        {
+                Self {
+                        previous: value,
+                        current: value,
+                }
+        }
+
+        /// The value of a new tick, drawn moving towards it from the last
+        /// one.
+        pub fn push(
+                &mut self,
+                value: T,
+        )
+        {
+                self.previous = self.current;
+                self.current = value;
+        }
+
+        /// Jumps to `value` without drawing the way there, e.g. for a
+        /// respawn.
+        pub fn set(
+                &mut self,
+                value: T,
+        )
+        {
+                *self = Self::new(value);
+        }
+
+        pub fn previous(&self) -> T
+        {
+                self.previous
+        }
+
+        pub fn current(&self) -> T
+        {
+                self.current
+        }
+
+        /// Between the last two values, `alpha` is clamped to 0 to 1 so a
+        /// late tick holds the value instead of overshooting.
+        pub fn at(
+                &self,
+                alpha: f32,
+        ) -> T
+        {
+                self.previous
+                        .interpolate(&self.current, alpha.clamp(0.0, 1.0))
+        }
+}
+
+/// Moves `current` towards `target` by `1 - e^(-rate·dt)` of the way, so
+/// it follows the same at any frame rate. Higher `rate` follows more
+/// tightly.
+pub fn damp<T: Interpolate>(
+        current: T,
+        target: T,
+        rate: f32,
+        dt: f32,
+) -> T
+{
+        current.interpolate(&target, 1.0 - (-rate * dt).exp())
+}
+
+/// Moves `current` towards `target` like a critically damped spring,
+/// arriving in about `smooth_time` seconds without overshooting.
+/// `velocity` carries the motion between calls and starts at zero.
+pub fn smooth_damp<V: VectorSpace<Scalar = f32>>(
+        current: V,
+        target: V,
+        velocity: &mut V,
+        smooth_time: f32,
+        dt: f32,
+) -> V
+{
+        let omega = 2.0 / smooth_time.max(1e-4);
+        let x = omega * dt;
+        // Approximates e^-x, good within the steps a frame takes.
+        let decay = 1.0 / (1.0 + x + 0.48 * x * x + 0.235 * x * x * x);
+
+        let change = current - target;
+        let temp = (*velocity + change * omega) * dt;
+
+        *velocity = (*velocity - temp * omega) * decay;
+
+        target + (change + temp) * decay
+}
+
+/// [`smooth_damp`] for positions.
+pub fn smooth_damp_point(
+        current: Point3<f32>,
+        target: Point3<f32>,
+        velocity: &mut Vector3<f32>,
+        smooth_time: f32,
+        dt: f32,
+) -> Point3<f32>
+{
+        Point3::from_vec(smooth_damp(current.to_vec(), target.to_vec(), velocity, smooth_time, dt))
+}
+
+/// The rotation turning +Z, the way models face, along `direction` with
+/// +Y as close to `up` as it gets. No rotation for a zero `direction`.
+pub fn look_rotation(
+        direction: Vector3<f32>,
+        up: Vector3<f32>,
+) -> Quaternion<f32>
+{
+        if direction.magnitude2() < f32::EPSILON
+        {
+                return Quaternion::one();
+        }
+
+        let forward = direction.normalize();
+        let mut right = up.cross(forward);
+
+        // Looking straight along `up`, any right vector will do.
+        if right.magnitude2() < f32::EPSILON
+        {
+                let other = if forward.y.abs() < 0.99
+                {
+                        Vector3::unit_y()
+                }
+                else
+                {
+                        Vector3::unit_x()
+                };
+
+                right = other.cross(forward);
+        }
+
+        let right = right.normalize();
+        let up = forward.cross(right);
+
+        Quaternion::from(Matrix3::from_cols(right, up, forward)).normalize()
+}
+
+/// The rotation making something at `from` face `to`, see
+/// [`look_rotation`].
+pub fn look_at(
+        from: Point3<f32>,
+        to: Point3<f32>,
+        up: Vector3<f32>,
+) -> Quaternion<f32>
+{
+        look_rotation(to - from, up)
+}
+
+#[cfg(feature = "runtime")]
+impl crate::engine::Engine
+{
+        /// `value` at the current frame, between its last two ticks.
+        pub fn interpolated<T: Interpolate>(
+                &self,
+                value: &Interpolated<T>,
+        ) -> T
+        {
+                value.at(self.lerp_alpha)
+        }
+
+        /// Places model `handle` at `transform` for the current frame,
+        /// returns false when there's no such model.
+        pub fn interpolate_model(
+                &mut self,
+                handle: &str,
+                transform: &Interpolated<Transform>,
+        ) -> bool
+        {
+                let transform = self.interpolated(transform);
+
+                match self.state.as_mut().and_then(|s| s.models.get_mut(handle))
+                {
+                        Some(model) =>
+                        {
+                                model.transform = transform;
+                                true
+                        }
+                        None => false,
+                }
+        }
+}
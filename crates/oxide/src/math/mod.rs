@@ -0,0 +1,5 @@
+pub mod collision;
+pub mod interpolation;
+pub mod noise;
+#[cfg(feature = "runtime")]
+pub mod rng;
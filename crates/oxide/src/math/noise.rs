@@ -0,0 +1,397 @@
+//! Gradient noise for procedural content.
+//!
+//! A [`Noise`] is seeded like [`Rng`](crate::math::rng::Rng), the same seed
+//! gives the same terrain, clouds or wobble on every machine:
+//!
+//! ```ignore
+//! let noise = Noise::new(engine.rng.seed());
+//!
+//! for x in 0..64
+//! {
+//!         for z in 0..64
+//!         {
+//!                 let height = noise.fbm2(x as f32 * 0.05, z as f32 * 0.05, 4) * 8.0;
+//!                 // ...
+//!         }
+//! }
+//! ```
+//!
+//! Perlin noise is the classic lattice noise, simplex noise has fewer
+//! directional artifacts and is cheaper in 3D. Both are about -1 to 1, 0 at
+//! whole coordinates for Perlin, and repeat every 256 units.
+
+/// Skews the input of 2D simplex noise onto the triangle grid and back.
+const F2: f32 = 0.366_025_42;
+const G2: f32 = 0.211_324_87;
+const F3: f32 = 1.0 / 3.0;
+const G3: f32 = 1.0 / 6.0;
+
+/// Gradients of simplex noise, the 2D variant uses `x` and `y`.
+const GRADIENTS: [[f32; 3]; 12] = [
+        [1.0, 1.0, 0.0],
+        [-1.0, 1.0, 0.0],
+        [1.0, -1.0, 0.0],
+        [-1.0, -1.0, 0.0],
+        [1.0, 0.0, 1.0],
+        [-1.0, 0.0, 1.0],
+        [1.0, 0.0, -1.0],
+        [-1.0, 0.0, -1.0],
+        [0.0, 1.0, 1.0],
+        [0.0, -1.0, 1.0],
+        [0.0, 1.0, -1.0],
+        [0.0, -1.0, -1.0],
+];
+
+/// Seeded 2D and 3D Perlin and simplex noise.
+#[derive(Clone)]
+pub struct Noise
+{
+        seed: u64,
+        /// A shuffle of 0 to 255, twice so lookups don't wrap.
+        perm: [u8; 512],
+}
+
+impl std::fmt::Debug for Noise
+{
+        fn fmt(
+                &self,
+                f: &mut std::fmt::Formatter<'_>,
+        ) -> std::fmt::Result
+        {
+                f.debug_struct("Noise").field("seed", &self.seed).finish()
+        }
+}
+
+impl Default for Noise
+{
+        fn default() -> Self
+        {
+                Self::new(0)
+        }
+}
+
+impl Noise
+{
+        pub fn new(seed: u64) -> Self
+        {
+                let mut table: [u8; 256] = std::array::from_fn(|i| i as u8);
+                let mut state = seed;
+
+                for i in (1..table.len()).rev()
+                {
+                        let j = (splitmix64(&mut state) % (i as u64 + 1)) as usize;
+
+                        table.swap(i, j);
+                }
+
+                Self {
+                        seed,
+                        perm: std::array::from_fn(|i| table[i & 255]),
+                }
+        }
+
+        pub fn seed(&self) -> u64
+        {
+                self.seed
+        }
+
+        fn hash(
+                &self,
+                i: i32,
+        ) -> usize
+        {
+                self.perm[(i & 255) as usize] as usize
+        }
+
+        pub fn perlin2(
+                &self,
+                x: f32,
+                y: f32,
+        ) -> f32
+        {
+                let (xi, yi) = (x.floor() as i32, y.floor() as i32);
+                let (x, y) = (x - x.floor(), y - y.floor());
+                let (u, v) = (fade(x), fade(y));
+
+                let a = self.hash(xi) + (yi & 255) as usize;
+                let b = self.hash(xi + 1) + (yi & 255) as usize;
+
+                lerp(
+                        v,
+                        lerp(
+                                u,
+                                perlin_gradient2(self.perm[a], x, y),
+                                perlin_gradient2(self.perm[b], x - 1.0, y),
+                        ),
+                        lerp(
+                                u,
+                                perlin_gradient2(self.perm[a + 1], x, y - 1.0),
+                                perlin_gradient2(self.perm[b + 1], x - 1.0, y - 1.0),
+                        ),
+                )
+        }
+
+        pub fn perlin3(
+                &self,
+                x: f32,
+                y: f32,
+                z: f32,
+        ) -> f32
+        {
+                let (xi, yi, zi) = (x.floor() as i32, y.floor() as i32, z.floor() as i32);
+                let (x, y, z) = (x - x.floor(), y - y.floor(), z - z.floor());
+                let (u, v, w) = (fade(x), fade(y), fade(z));
+
+                let a = self.hash(xi) + (yi & 255) as usize;
+                let aa = self.perm[a] as usize + (zi & 255) as usize;
+                let ab = self.perm[a + 1] as usize + (zi & 255) as usize;
+                let b = self.hash(xi + 1) + (yi & 255) as usize;
+                let ba = self.perm[b] as usize + (zi & 255) as usize;
+                let bb = self.perm[b + 1] as usize + (zi & 255) as usize;
+
+                let g = |i: usize, dx: f32, dy: f32, dz: f32| {
+                        perlin_gradient3(self.perm[i], x - dx, y - dy, z - dz)
+                };
+
+                lerp(
+                        w,
+                        lerp(
+                                v,
+                                lerp(u, g(aa, 0.0, 0.0, 0.0), g(ba, 1.0, 0.0, 0.0)),
+                                lerp(u, g(ab, 0.0, 1.0, 0.0), g(bb, 1.0, 1.0, 0.0)),
+                        ),
+                        lerp(
+                                v,
+                                lerp(u, g(aa + 1, 0.0, 0.0, 1.0), g(ba + 1, 1.0, 0.0, 1.0)),
+                                lerp(u, g(ab + 1, 0.0, 1.0, 1.0), g(bb + 1, 1.0, 1.0, 1.0)),
+                        ),
+                )
+        }
+
+        pub fn simplex2(
+                &self,
+                x: f32,
+                y: f32,
+        ) -> f32
+        {
+                let s = (x + y) * F2;
+                let (i, j) = ((x + s).floor() as i32, (y + s).floor() as i32);
+                let t = (i + j) as f32 * G2;
+
+                let x0 = x - (i as f32 - t);
+                let y0 = y - (j as f32 - t);
+
+                // The lower or upper triangle of the cell.
+                let (i1, j1) = if x0 > y0 { (1, 0) } else { (0, 1) };
+
+                let corners = [
+                        (x0, y0, 0, 0),
+                        (x0 - i1 as f32 + G2, y0 - j1 as f32 + G2, i1, j1),
+                        (x0 - 1.0 + 2.0 * G2, y0 - 1.0 + 2.0 * G2, 1, 1),
+                ];
+
+                let mut n = 0.0;
+
+                for (x, y, di, dj) in corners
+                {
+                        let t = 0.5 - x * x - y * y;
+
+                        if t > 0.0
+                        {
+                                let g = GRADIENTS[self.perm
+                                        [self.hash(i + di) + ((j + dj) & 255) as usize]
+                                        as usize
+                                        % 12];
+
+                                n += t.powi(4) * (g[0] * x + g[1] * y);
+                        }
+                }
+
+                70.0 * n
+        }
+
+        pub fn simplex3(
+                &self,
+                x: f32,
+                y: f32,
+                z: f32,
+        ) -> f32
+        {
+                let s = (x + y + z) * F3;
+                let (i, j, k) =
+                        ((x + s).floor() as i32, (y + s).floor() as i32, (z + s).floor() as i32);
+                let t = (i + j + k) as f32 * G3;
+
+                let x0 = x - (i as f32 - t);
+                let y0 = y - (j as f32 - t);
+                let z0 = z - (k as f32 - t);
+
+                // Which of the six tetrahedra of the cell the point is in.
+                let (second, third) = if x0 >= y0
+                {
+                        if y0 >= z0
+                        {
+                                ((1, 0, 0), (1, 1, 0))
+                        }
+                        else if x0 >= z0
+                        {
+                                ((1, 0, 0), (1, 0, 1))
+                        }
+                        else
+                        {
+                                ((0, 0, 1), (1, 0, 1))
+                        }
+                }
+                else if y0 < z0
+                {
+                        ((0, 0, 1), (0, 1, 1))
+                }
+                else if x0 < z0
+                {
+                        ((0, 1, 0), (0, 1, 1))
+                }
+                else
+                {
+                        ((0, 1, 0), (1, 1, 0))
+                };
+
+                let corners = [(0, 0, 0), second, third, (1, 1, 1)];
+
+                let mut n = 0.0;
+
+                for (c, (di, dj, dk)) in corners.into_iter().enumerate()
+                {
+                        let offset = c as f32 * G3;
+                        let x = x0 - di as f32 + offset;
+                        let y = y0 - dj as f32 + offset;
+                        let z = z0 - dk as f32 + offset;
+
+                        let t = 0.6 - x * x - y * y - z * z;
+
+                        if t > 0.0
+                        {
+                                let hash = self.perm[self.perm
+                                        [self.hash(i + di) + ((j + dj) & 255) as usize]
+                                        as usize
+                                        + ((k + dk) & 255) as usize];
+                                let g = GRADIENTS[hash as usize % 12];
+
+                                n += t.powi(4) * (g[0] * x + g[1] * y + g[2] * z);
+                        }
+                }
+
+                32.0 * n
+        }
+
+        /// `octaves` layers of [`Noise::simplex2`], each at twice the
+        /// frequency and half the amplitude of the one before, for detail at
+        /// every scale. About -1 to 1.
+        pub fn fbm2(
+                &self,
+                x: f32,
+                y: f32,
+                octaves: u32,
+        ) -> f32
+        {
+                fbm(octaves, |frequency| self.simplex2(x * frequency, y * frequency))
+        }
+
+        /// [`Noise::fbm2`] in 3D.
+        pub fn fbm3(
+                &self,
+                x: f32,
+                y: f32,
+                z: f32,
+                octaves: u32,
+        ) -> f32
+        {
+                fbm(octaves, |frequency| self.simplex3(x * frequency, y * frequency, z * frequency))
+        }
+}
+
+fn fbm(
+        octaves: u32,
+        mut sample: impl FnMut(f32) -> f32,
+) -> f32
+{
+        let mut sum = 0.0;
+        let mut total = 0.0;
+        let mut amplitude = 1.0;
+        let mut frequency = 1.0;
+
+        for _ in 0..octaves.max(1)
+        {
+                sum += sample(frequency) * amplitude;
+                total += amplitude;
+                amplitude *= 0.5;
+                frequency *= 2.0;
+        }
+
+        sum / total
+}
+
+/// Eases the position in a cell so the noise is smooth across cells.
+fn fade(t: f32) -> f32
+{
+        t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(
+        t: f32,
+        a: f32,
+        b: f32,
+) -> f32
+{
+        a + t * (b - a)
+}
+
+fn perlin_gradient2(
+        hash: u8,
+        x: f32,
+        y: f32,
+) -> f32
+{
+        match hash & 7
+        {
+                0 => x + y,
+                1 => -x + y,
+                2 => x - y,
+                3 => -x - y,
+                4 => x,
+                5 => -x,
+                6 => y,
+                _ => -y,
+        }
+}
+
+fn perlin_gradient3(
+        hash: u8,
+        x: f32,
+        y: f32,
+        z: f32,
+) -> f32
+{
+        let h = hash & 15;
+        let u = if h < 8 { x } else { y };
+        let v = match h
+        {
+                0..4 => y,
+                12 | 14 => x,
+                _ => z,
+        };
+
+        (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+
+/// Next value of a SplitMix64 generator, enough to shuffle the table
+/// without depending on `rand`.
+fn splitmix64(state: &mut u64) -> u64
+{
+        *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+        let mut z = *state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+
+        z ^ (z >> 31)
+}
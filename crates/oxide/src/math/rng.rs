@@ -0,0 +1,185 @@
+//! The engine's seedable random numbers.
+//!
+//! Behaviors draw from [`Engine::rng`](crate::engine::Engine::rng) rather
+//! than their own generators, so one seed decides everything random in a
+//! run. Replays reseed it when they start, see [`crate::replay`], and
+//! [`EngineBuilder::with_seed`](crate::engine::EngineBuilder::with_seed)
+//! fixes it from the start:
+//!
+//! ```ignore
+//! engine.register_behavior(|engine| {
+//!         if engine.rng.chance(0.01)
+//!         {
+//!                 let x = engine.rng.range(-10.0..10.0);
+//!                 // Spawn something at x.
+//!         }
+//! });
+//! ```
+//!
+//! [`Rng`] implements [`rand::RngCore`], the rest of `rand` works on it too.
+
+use cgmath::{InnerSpace, Vector2, Vector3};
+use rand::distr::uniform::{SampleRange, SampleUniform};
+use rand::rngs::StdRng;
+use rand::seq::{IndexedRandom, SliceRandom};
+use rand::{Rng as _, RngCore, SeedableRng};
+
+/// A random number generator that knows its seed.
+#[derive(Clone)]
+pub struct Rng
+{
+        seed: u64,
+        inner: StdRng,
+}
+
+impl std::fmt::Debug for Rng
+{
+        fn fmt(
+                &self,
+                f: &mut std::fmt::Formatter<'_>,
+        ) -> std::fmt::Result
+        {
+                f.debug_struct("Rng").field("seed", &self.seed).finish()
+        }
+}
+
+impl Default for Rng
+{
+        fn default() -> Self
+        {
+                Self::from_os_rng()
+        }
+}
+
+impl Rng
+{
+        pub fn new(seed: u64) -> Self
+        {
+                Self {
+                        seed,
+                        inner: StdRng::seed_from_u64(seed),
+                }
+        }
+
+        /// Seeded from the OS, differently every run.
+        pub fn from_os_rng() -> Self
+        {
+                Self::new(rand::random())
+        }
+
+        /// The seed the generator was last started with.
+        pub fn seed(&self) -> u64
+        {
+                self.seed
+        }
+
+        /// Starts over from `seed`.
+        pub fn reseed(
+                &mut self,
+                seed: u64,
+        )
+        {
+                *self = Self::new(seed);
+        }
+
+        /// A new generator seeded from this one, for a separate stream of
+        /// numbers that's still decided by the engine's seed.
+        pub fn fork(&mut self) -> Self
+        {
+                Self::new(self.inner.next_u64())
+        }
+
+        /// The next number the generator would give, without taking it.
+        /// Two generators with the same fingerprint are very likely in the
+        /// same state.
+        pub fn fingerprint(&self) -> u64
+        {
+                self.inner.clone().next_u64()
+        }
+
+        /// A value in `range`, e.g. `0..10` or `-1.0..=1.0`.
+        pub fn range<T, R>(
+                &mut self,
+                range: R,
+        ) -> T
+        where
+                T: SampleUniform,
+                R: SampleRange<T>,
+        {
+                self.inner.random_range(range)
+        }
+
+        /// `true` with probability `p`, clamped to 0 to 1.
+        pub fn chance(
+                &mut self,
+                p: f64,
+        ) -> bool
+        {
+                self.inner.random_bool(p.clamp(0.0, 1.0))
+        }
+
+        /// A random element, `None` when `items` is empty.
+        pub fn pick<'a, T>(
+                &mut self,
+                items: &'a [T],
+        ) -> Option<&'a T>
+        {
+                items.choose(&mut self.inner)
+        }
+
+        pub fn shuffle<T>(
+                &mut self,
+                items: &mut [T],
+        )
+        {
+                items.shuffle(&mut self.inner);
+        }
+
+        /// A direction in the XY plane, uniformly distributed.
+        pub fn unit_vector2(&mut self) -> Vector2<f32>
+        {
+                let (sin, cos) = self.range(0.0..std::f32::consts::TAU).sin_cos();
+
+                Vector2::new(cos, sin)
+        }
+
+        /// A direction in 3D, uniformly distributed over the sphere.
+        pub fn unit_vector3(&mut self) -> Vector3<f32>
+        {
+                loop
+                {
+                        let v: Vector3<f32> = Vector3::new(
+                                self.range(-1.0..=1.0),
+                                self.range(-1.0..=1.0),
+                                self.range(-1.0..=1.0),
+                        );
+                        let length2 = v.magnitude2();
+
+                        if length2 > 1e-6 && length2 <= 1.0
+                        {
+                                return v / length2.sqrt();
+                        }
+                }
+        }
+}
+
+impl RngCore for Rng
+{
+        fn next_u32(&mut self) -> u32
+        {
+                self.inner.next_u32()
+        }
+
+        fn next_u64(&mut self) -> u64
+        {
+                self.inner.next_u64()
+        }
+
+        fn fill_bytes(
+                &mut self,
+                dst: &mut [u8],
+        )
+        {
+                self.inner.fill_bytes(dst);
+        }
+}
@@ -0,0 +1,244 @@
+//! Measuring distances in the scene, e.g. to check the scale of an
+//! imported asset.
+//!
+//! With [`Measurement::enabled`], set by "Measure distances" in the debug
+//! UI, two clicks in the scene pick the points to measure between, a
+//! third one starts over. The points are picked on the triangles of the
+//! models under the cursor, or on the ground plane where there's none,
+//! see [`Engine::pick_point`]. The "Measure" window shows the distance,
+//! how far apart the points are along every axis and the elevation of
+//! the line between them, which is drawn with its per-axis legs through
+//! the [`DebugDrawPass`](crate::renderer::debug_draw::DebugDrawPass), see
+//! [`crate::ui::measure`].
+//!
+//! From code:
+//!
+//! ```ignore
+//! let mut measurement = Measurement::new();
+//! measurement.push(Point3::new(0.0, 0.0, 0.0));
+//! measurement.push(Point3::new(3.0, 0.0, 4.0));
+//!
+//! assert_eq!(measurement.distance(), Some(5.0));
+//! ```
+
+use crate::engine::Engine;
+use crate::math::collision::{Plane, Ray};
+use crate::model::Model;
+use crate::renderer::debug_draw::DebugLines;
+use cgmath::{InnerSpace, Point3, Transform as _, Vector3};
+use std::collections::HashMap;
+
+const LINE_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const X_COLOR: [f32; 4] = [1.0, 0.25, 0.25, 1.0];
+const Y_COLOR: [f32; 4] = [0.25, 1.0, 0.25, 1.0];
+const Z_COLOR: [f32; 4] = [0.3, 0.5, 1.0, 1.0];
+
+/// Size of the crosses marking the points, in world units.
+const MARKER_SIZE: f32 = 0.05;
+
+/// Two points picked in the scene, owned by the
+/// [`EngineState`](crate::engine::EngineState).
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Measurement
+{
+        /// Clicks in the scene pick points instead of selecting models.
+        pub enabled: bool,
+
+        start: Option<Point3<f32>>,
+        end: Option<Point3<f32>>,
+}
+
+impl Measurement
+{
+        pub fn new() -> Self
+        {
+                Self::default()
+        }
+
+        pub fn start(&self) -> Option<Point3<f32>>
+        {
+                self.start
+        }
+
+        pub fn end(&self) -> Option<Point3<f32>>
+        {
+                self.end
+        }
+
+        /// Sets the start, then the end, and starts over once both are set.
+        pub fn push(
+                &mut self,
+                point: Point3<f32>,
+        )
+        {
+                match (self.start, self.end)
+                {
+                        (Some(_), None) => self.end = Some(point),
+                        _ =>
+                        {
+                                self.start = Some(point);
+                                self.end = None;
+                        }
+                }
+        }
+
+        pub fn clear(&mut self)
+        {
+                self.start = None;
+                self.end = None;
+        }
+
+        /// From the start to the end, `None` until both are picked.
+        pub fn delta(&self) -> Option<Vector3<f32>>
+        {
+                Some(self.end? - self.start?)
+        }
+
+        pub fn distance(&self) -> Option<f32>
+        {
+                self.delta().map(|d| d.magnitude())
+        }
+
+        /// Angle of the line above the ground plane in degrees, negative
+        /// when the end is below the start.
+        pub fn elevation(&self) -> Option<f32>
+        {
+                let delta = self.delta()?;
+                let horizontal = (delta.x * delta.x + delta.z * delta.z).sqrt();
+
+                Some(delta.y.atan2(horizontal).to_degrees())
+        }
+
+        /// The picked points, the line between them and its legs along the
+        /// X, Y and Z axes.
+        pub fn lines(
+                &self,
+                lines: &mut DebugLines,
+        )
+        {
+                for point in [self.start, self.end].into_iter().flatten()
+                {
+                        marker(lines, point);
+                }
+
+                if let (Some(start), Some(end)) = (self.start, self.end)
+                {
+                        legs(lines, start, end);
+                }
+        }
+}
+
+/// A cross at `point`.
+pub(crate) fn marker(
+        lines: &mut DebugLines,
+        point: Point3<f32>,
+)
+{
+        for (axis, color) in [
+                (Vector3::unit_x(), X_COLOR),
+                (Vector3::unit_y(), Y_COLOR),
+                (Vector3::unit_z(), Z_COLOR),
+        ]
+        {
+                let offset = axis * MARKER_SIZE;
+
+                lines.line(point - offset, point + offset, color);
+        }
+}
+
+/// The line from `start` to `end` and the path along the axes between
+/// them, X first.
+pub(crate) fn legs(
+        lines: &mut DebugLines,
+        start: Point3<f32>,
+        end: Point3<f32>,
+)
+{
+        let x = Point3::new(end.x, start.y, start.z);
+        let y = Point3::new(end.x, end.y, start.z);
+
+        lines.line(start, x, X_COLOR);
+        lines.line(x, y, Y_COLOR);
+        lines.line(y, end, Z_COLOR);
+        lines.line(start, end, LINE_COLOR);
+}
+
+/// Where `ray` first hits a triangle of a visible model, the ground plane
+/// when it hits none. Only models whose oriented bounds the ray hits are
+/// tested, skinned meshes in their bind pose.
+pub fn pick_point(
+        models: &HashMap<String, Model>,
+        ray: &Ray,
+) -> Option<Point3<f32>>
+{
+        let hit = models
+                .values()
+                .filter(|model| model.visible)
+                .filter(|model| {
+                        model.world_obb()
+                                .is_some_and(|obb| ray.intersect_obb(&obb).is_some())
+                })
+                .filter_map(|model| intersect_triangles(model, ray))
+                .min_by(f32::total_cmp)
+                .or_else(|| {
+                        ray.intersect_plane(&Plane::from_point_normal(
+                                Point3::new(0.0, 0.0, 0.0),
+                                Vector3::unit_y(),
+                        ))
+                })?;
+
+        Some(ray.at(hit))
+}
+
+/// Distance to the nearest triangle of `model` along `ray`.
+fn intersect_triangles(
+        model: &Model,
+        ray: &Ray,
+) -> Option<f32>
+{
+        let model_matrix = model.calculate_transform();
+
+        model.meshes
+                .iter()
+                .flat_map(|mesh| {
+                        let matrix = model_matrix * mesh.transform;
+
+                        let world: Vec<Point3<f32>> = mesh
+                                .vertices
+                                .iter()
+                                .map(|v| matrix.transform_point(Point3::from(v.position)))
+                                .collect();
+
+                        mesh.indices
+                                .chunks_exact(3)
+                                .filter_map(|t| {
+                                        ray.intersect_triangle(
+                                                world[t[0] as usize],
+                                                world[t[1] as usize],
+                                                world[t[2] as usize],
+                                        )
+                                })
+                                .collect::<Vec<f32>>()
+                })
+                .min_by(f32::total_cmp)
+}
+
+impl Engine
+{
+        /// Where `ray` first hits the scene, see [`pick_point`]. `None`
+        /// before the renderer is initialized or when it misses the ground
+        /// too.
+        pub fn pick_point(
+                &self,
+                ray: &Ray,
+        ) -> Option<Point3<f32>>
+        {
+                pick_point(&self.state.as_ref()?.models, ray)
+        }
+
+        /// The distance measured in the debug UI, see [`crate::measure`].
+        pub fn measurement(&mut self) -> Option<&mut Measurement>
+        {
+                Some(&mut self.state.as_mut()?.measurement)
+        }
+}
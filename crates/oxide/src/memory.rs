@@ -0,0 +1,398 @@
+//! GPU memory tracking and a memory budget.
+//!
+//! The engine estimates the GPU memory of every loaded model, its vertex,
+//! index and transform buffers and its textures, see
+//! [`Engine::gpu_memory`]. With a budget set, models that are over it and
+//! hidden are unloaded, the ones hidden longest first. That keeps large
+//! scenes under the 4 GB address space of `wasm32`:
+//!
+//! ```ignore
+//! let mut engine = EngineBuilder::new()
+//!         .with_memory_budget(1024 * 1024 * 1024)
+//!         .build()?;
+//! ```
+//!
+//! Visible models are never unloaded. An unloaded model publishes
+//! [`MemoryEvent::Evicted`] and is loaded again with
+//! [`Engine::restore_model`].
+//!
+//! [`Engine::asset_usage`] breaks the usage down into meshes, textures and
+//! shared materials, listed in the debug UI's "Assets" window.
+
+use crate::engine::Engine;
+use crate::handle::Handle;
+use crate::material::Material;
+use crate::material_registry::MaterialRegistry;
+use crate::model::Model;
+use std::collections::{BTreeMap, HashMap};
+
+/// Published on the event bus when a model was unloaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MemoryEvent
+{
+        Evicted
+        {
+                handle: String, bytes: u64
+        },
+}
+
+/// Estimated GPU memory of the loaded models.
+#[derive(Debug, Clone, Default)]
+pub struct GpuMemory
+{
+        /// Bytes by model handle.
+        pub models: BTreeMap<String, u64>,
+
+        pub total: u64,
+
+        /// See [`MemoryBudget::budget`].
+        pub budget: Option<u64>,
+}
+
+/// The loaded assets and their estimated GPU memory, see
+/// [`Engine::asset_usage`].
+#[derive(Debug, Clone, Default)]
+pub struct AssetUsage
+{
+        /// By handle.
+        pub models: Vec<ModelAsset>,
+        /// Materials of the [`MaterialRegistry`].
+        pub materials: Vec<MaterialAsset>,
+        pub total: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct ModelAsset
+{
+        pub handle: String,
+        /// File the model was loaded from, `None` for models made at
+        /// runtime.
+        pub file: Option<String>,
+        /// Loaded models sharing the file, this one included.
+        pub file_refs: usize,
+        pub visible: bool,
+        pub meshes: Vec<MeshAsset>,
+        pub textures: Vec<TextureAsset>,
+        pub bytes: u64,
+}
+
+/// The vertex, index and transform buffers of a mesh.
+#[derive(Debug, Clone)]
+pub struct MeshAsset
+{
+        pub name: String,
+        pub vertices: usize,
+        pub indices: u32,
+        pub bytes: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct TextureAsset
+{
+        /// Material and texture kind, e.g. `"wood (base color)"`.
+        pub name: String,
+        pub width: u32,
+        pub height: u32,
+        pub bytes: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct MaterialAsset
+{
+        pub handle: Handle<Material>,
+        pub name: String,
+        /// Material slots of loaded models using it.
+        pub users: usize,
+}
+
+impl AssetUsage
+{
+        /// Lists the `loaded` models, loaded from the files in `files` by
+        /// handle, and the shared `materials`.
+        pub fn collect(
+                loaded: &HashMap<String, Model>,
+                files: &HashMap<String, String>,
+                materials: &MaterialRegistry,
+        ) -> Self
+        {
+                let mut models: Vec<ModelAsset> = loaded
+                        .iter()
+                        .map(|(handle, model)| {
+                                let file = files.get(handle).cloned();
+
+                                ModelAsset {
+                                        handle: handle.clone(),
+                                        file_refs: file.as_ref().map_or(1, |file| {
+                                                files.iter()
+                                                        .filter(|(h, f)| *f == file && loaded.contains_key(*h))
+                                                        .count()
+                                        }),
+                                        file,
+                                        visible: model.visible,
+                                        meshes: model
+                                                .meshes
+                                                .iter()
+                                                .map(|mesh| MeshAsset {
+                                                        name: mesh.name.clone(),
+                                                        vertices: mesh.vertices.len(),
+                                                        indices: mesh.num_elements,
+                                                        bytes: mesh.gpu_memory(),
+                                                })
+                                                .collect(),
+                                        textures: model
+                                                .materials
+                                                .iter()
+                                                .flat_map(|m| {
+                                                        [
+                                                                ("base color", Some(&m.base_color_texture)),
+                                                                ("normal", m.normal_texture.as_ref()),
+                                                                (
+                                                                        "metallic roughness",
+                                                                        m.metallic_roughness_texture.as_ref(),
+                                                                ),
+                                                        ]
+                                                        .into_iter()
+                                                        .filter_map(|(kind, texture)| {
+                                                                let texture = texture?;
+                                                                let size = texture.texture.size();
+
+                                                                Some(TextureAsset {
+                                                                        name: format!("{} ({})", m.name, kind),
+                                                                        width: size.width,
+                                                                        height: size.height,
+                                                                        bytes: texture.gpu_memory(),
+                                                                })
+                                                        })
+                                                })
+                                                .collect(),
+                                        bytes: model.gpu_memory(),
+                                }
+                        })
+                        .collect();
+
+                models.sort_by(|a, b| a.handle.cmp(&b.handle));
+
+                let materials = materials
+                        .iter()
+                        .map(|(handle, desc)| MaterialAsset {
+                                handle,
+                                name: desc.name.clone(),
+                                users: loaded
+                                        .values()
+                                        .flat_map(|model| model.material_handles.values())
+                                        .filter(|&&used| used == handle)
+                                        .count(),
+                        })
+                        .collect();
+
+                Self {
+                        total: models.iter().map(|m| m.bytes).sum(),
+                        models,
+                        materials,
+                }
+        }
+}
+
+/// Budget and usage bookkeeping, owned by the [`Engine`].
+#[derive(Debug, Default)]
+pub struct MemoryBudget
+{
+        /// Bytes the models may use before hidden ones are unloaded.
+        pub budget: Option<u64>,
+
+        usage: GpuMemory,
+
+        /// Frame each model was last visible in.
+        last_visible: HashMap<String, u64>,
+
+        frame: u64,
+
+        /// Still over budget after evicting, warned about once.
+        over_budget: bool,
+}
+
+impl MemoryBudget
+{
+        pub fn new() -> Self
+        {
+                Self::default()
+        }
+
+        pub fn usage(&self) -> &GpuMemory
+        {
+                &self.usage
+        }
+
+        /// Measures the models and unloads hidden ones while over budget.
+        ///
+        /// Called by the engine once per frame.
+        pub(crate) fn update(engine: &mut Engine)
+        {
+                let Some(state) = engine.state.as_mut()
+                else
+                {
+                        return;
+                };
+
+                let memory = &mut engine.memory;
+
+                memory.frame += 1;
+                memory.last_visible
+                        .retain(|handle, _| state.models.contains_key(handle));
+
+                for (handle, model) in &state.models
+                {
+                        let last = memory.last_visible.entry(handle.clone()).or_default();
+
+                        if model.visible
+                        {
+                                *last = memory.frame;
+                        }
+                }
+
+                memory.usage = GpuMemory {
+                        models: state
+                                .models
+                                .iter()
+                                .map(|(handle, model)| (handle.clone(), model.gpu_memory()))
+                                .collect(),
+                        total: 0,
+                        budget: memory.budget,
+                };
+                memory.usage.total = memory.usage.models.values().sum();
+
+                let Some(budget) = memory.budget
+                else
+                {
+                        return;
+                };
+
+                if memory.usage.total <= budget
+                {
+                        memory.over_budget = false;
+
+                        return;
+                }
+
+                let mut hidden: Vec<(u64, String)> = state
+                        .models
+                        .iter()
+                        .filter(|(_, model)| !model.visible)
+                        .map(|(handle, _)| (memory.last_visible[handle], handle.clone()))
+                        .collect();
+
+                hidden.sort();
+
+                for (_, handle) in hidden
+                {
+                        if memory.usage.total <= budget
+                        {
+                                break;
+                        }
+
+                        state.models.remove(&handle);
+                        memory.last_visible.remove(&handle);
+
+                        let bytes = memory.usage.models.remove(&handle).unwrap_or(0);
+                        memory.usage.total -= bytes;
+
+                        log::info!(
+                                "Evicted {} to stay in the memory budget, {} bytes",
+                                handle,
+                                bytes
+                        );
+
+                        engine.events.publish(MemoryEvent::Evicted {
+                                handle,
+                                bytes,
+                        });
+                }
+
+                let over_budget = memory.usage.total > budget;
+
+                if over_budget && !memory.over_budget
+                {
+                        log::warn!(
+                                "Visible models use {} bytes, over the budget of {}",
+                                memory.usage.total,
+                                budget
+                        );
+                }
+
+                memory.over_budget = over_budget;
+        }
+}
+
+impl Engine
+{
+        /// Estimated GPU memory of the models, updated once per frame.
+        pub fn gpu_memory(&self) -> &GpuMemory
+        {
+                self.memory.usage()
+        }
+
+        /// Sets or removes the memory budget, see [`crate::memory`].
+        pub fn set_memory_budget(
+                &mut self,
+                bytes: Option<u64>,
+        )
+        {
+                self.memory.budget = bytes;
+        }
+
+        /// The loaded models, their meshes and textures and the shared
+        /// materials, see [`AssetUsage`]. Measured when called.
+        pub fn asset_usage(&self) -> AssetUsage
+        {
+                match &self.state
+                {
+                        Some(state) =>
+                        {
+                                AssetUsage::collect(&state.models, &self.model_map, &self.materials)
+                        }
+                        None => AssetUsage::default(),
+                }
+        }
+
+        /// Removes the model `handle` from the scene, freeing its GPU
+        /// memory. Its file is remembered, [`Engine::restore_model`] loads
+        /// it again. Returns false when no such model is loaded.
+        pub fn unload_model(
+                &mut self,
+                handle: &str,
+        ) -> bool
+        {
+                let removed = self
+                        .state
+                        .as_mut()
+                        .and_then(|state| state.models.remove(handle))
+                        .is_some();
+
+                if removed
+                {
+                        self.memory.last_visible.remove(handle);
+                        log::info!("Unloaded {}", handle);
+                }
+
+                removed
+        }
+
+        /// Loads an evicted model again from the file it was first loaded
+        /// from, see [`Engine::spawn_model`]. Returns false when the file is
+        /// unknown.
+        pub fn restore_model(
+                &mut self,
+                handle: &str,
+        ) -> bool
+        {
+                match self.model_map.get(handle).cloned()
+                {
+                        Some(file) =>
+                        {
+                                self.spawn_model(handle, file);
+                                true
+                        }
+                        None => false,
+                }
+        }
+}
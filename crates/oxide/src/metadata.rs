@@ -0,0 +1,285 @@
+//! Tags and key/value metadata of models, so behaviors find models by what
+//! they are instead of by their handles.
+//!
+//! Every [`Model::metadata`](crate::model::Model::metadata) starts with
+//! what the glTF file says in the `extras` of its scenes and root nodes,
+//! e.g. the custom properties Blender exports. A `tags` property, a list
+//! or a comma separated string, becomes the tags, every other property a
+//! value:
+//!
+//! ```json
+//! "extras": { "tags": ["enemy", "flying"], "health": 30 }
+//! ```
+//!
+//! More are added from code, and behaviors query them:
+//!
+//! ```ignore
+//! engine.tag_model("crate", "breakable");
+//!
+//! engine.register_behavior(|eng| {
+//!         for handle in eng.models_with_tag("enemy")
+//!         {
+//!                 // ...
+//!         }
+//! });
+//! ```
+//!
+//! The metadata is kept with
+//! [`Engine::save_game`](crate::engine::Engine::save_game).
+
+#[cfg(feature = "runtime")]
+use crate::engine::Engine;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Tags and values of a model.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Metadata
+{
+        pub tags: BTreeSet<String>,
+        pub values: BTreeMap<String, String>,
+}
+
+impl Metadata
+{
+        pub fn new() -> Self
+        {
+                Self::default()
+        }
+
+        pub fn is_empty(&self) -> bool
+        {
+                self.tags.is_empty() && self.values.is_empty()
+        }
+
+        pub fn has_tag(
+                &self,
+                tag: &str,
+        ) -> bool
+        {
+                self.tags.contains(tag)
+        }
+
+        /// Returns false when the tag was there already.
+        pub fn add_tag(
+                &mut self,
+                tag: impl Into<String>,
+        ) -> bool
+        {
+                self.tags.insert(tag.into())
+        }
+
+        pub fn remove_tag(
+                &mut self,
+                tag: &str,
+        ) -> bool
+        {
+                self.tags.remove(tag)
+        }
+
+        pub fn get(
+                &self,
+                key: &str,
+        ) -> Option<&str>
+        {
+                self.values.get(key).map(String::as_str)
+        }
+
+        /// The value of `key` parsed, e.g. as a number. `None` when it's
+        /// missing or doesn't parse.
+        pub fn parse<T: std::str::FromStr>(
+                &self,
+                key: &str,
+        ) -> Option<T>
+        {
+                self.get(key)?.parse().ok()
+        }
+
+        /// Replaces the value of `key`, returning the old one.
+        pub fn set(
+                &mut self,
+                key: impl Into<String>,
+                value: impl Into<String>,
+        ) -> Option<String>
+        {
+                self.values.insert(key.into(), value.into())
+        }
+
+        pub fn remove(
+                &mut self,
+                key: &str,
+        ) -> Option<String>
+        {
+                self.values.remove(key)
+        }
+
+        /// Adds the tags and values of `other`, its values replacing ours.
+        pub fn extend(
+                &mut self,
+                other: Metadata,
+        )
+        {
+                self.tags.extend(other.tags);
+                self.values.extend(other.values);
+        }
+
+        /// Tags and values from glTF `extras`, see [`crate::metadata`].
+        /// Strings are kept as they are, other values as JSON. Extras that
+        /// aren't an object have neither.
+        pub fn from_extras(extras: &serde_json::Value) -> Self
+        {
+                let mut metadata = Self::new();
+
+                let Some(object) = extras.as_object()
+                else
+                {
+                        return metadata;
+                };
+
+                for (key, value) in object
+                {
+                        match (key.as_str(), value)
+                        {
+                                ("tags", serde_json::Value::Array(tags)) =>
+                                {
+                                        metadata.tags.extend(tags
+                                                .iter()
+                                                .filter_map(|t| t.as_str())
+                                                .map(str::to_string));
+                                }
+                                ("tags", serde_json::Value::String(tags)) =>
+                                {
+                                        metadata.tags.extend(tags
+                                                .split(',')
+                                                .map(str::trim)
+                                                .filter(|t| !t.is_empty())
+                                                .map(str::to_string));
+                                }
+                                (_, serde_json::Value::String(value)) =>
+                                {
+                                        metadata.set(key.as_str(), value.as_str());
+                                }
+                                _ =>
+                                {
+                                        metadata.set(key.as_str(), value.to_string());
+                                }
+                        }
+                }
+
+                metadata
+        }
+
+        /// Lists the tags and values, read only.
+        #[cfg(feature = "ui")]
+        pub fn ui(
+                &self,
+                ui: &mut egui::Ui,
+        )
+        {
+                if self.is_empty()
+                {
+                        return;
+                }
+
+                ui.collapsing("Metadata", |ui| {
+                        if !self.tags.is_empty()
+                        {
+                                ui.horizontal_wrapped(|ui| {
+                                        ui.label("Tags:");
+
+                                        for tag in &self.tags
+                                        {
+                                                ui.code(tag);
+                                        }
+                                });
+                        }
+
+                        egui::Grid::new(ui.id().with("metadata_values"))
+                                .num_columns(2)
+                                .show(ui, |ui| {
+                                        for (key, value) in &self.values
+                                        {
+                                                ui.label(key);
+                                                ui.label(value);
+                                                ui.end_row();
+                                        }
+                                });
+                });
+        }
+}
+
+#[cfg(feature = "runtime")]
+impl Engine
+{
+        /// Handles of the models tagged `tag`, sorted.
+        pub fn models_with_tag(
+                &self,
+                tag: &str,
+        ) -> Vec<&str>
+        {
+                self.models_where(|metadata| metadata.has_tag(tag))
+        }
+
+        /// Handles of the models whose value of `key` is `value`, sorted.
+        pub fn models_with_value(
+                &self,
+                key: &str,
+                value: &str,
+        ) -> Vec<&str>
+        {
+                self.models_where(|metadata| metadata.get(key) == Some(value))
+        }
+
+        /// Handles of the models whose metadata matches, sorted.
+        pub fn models_where(
+                &self,
+                mut matches: impl FnMut(&Metadata) -> bool,
+        ) -> Vec<&str>
+        {
+                let Some(state) = self.state.as_ref()
+                else
+                {
+                        return Vec::new();
+                };
+
+                let mut handles: Vec<&str> = state
+                        .models
+                        .iter()
+                        .filter(|(_, model)| matches(&model.metadata))
+                        .map(|(handle, _)| handle.as_str())
+                        .collect();
+                handles.sort_unstable();
+
+                handles
+        }
+
+        pub fn model_metadata(
+                &self,
+                handle: &str,
+        ) -> Option<&Metadata>
+        {
+                Some(&self.state.as_ref()?.models.get(handle)?.metadata)
+        }
+
+        pub fn model_metadata_mut(
+                &mut self,
+                handle: &str,
+        ) -> Option<&mut Metadata>
+        {
+                Some(&mut self.state.as_mut()?.models.get_mut(handle)?.metadata)
+        }
+
+        /// Tags the model `handle`, returns false when there's no such
+        /// model.
+        pub fn tag_model(
+                &mut self,
+                handle: &str,
+                tag: impl Into<String>,
+        ) -> bool
+        {
+                self.model_metadata_mut(handle)
+                        .map(|metadata| metadata.add_tag(tag))
+                        .is_some()
+        }
+}
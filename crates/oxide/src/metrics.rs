@@ -0,0 +1,206 @@
+//! Performance numbers for game code and custom UIs.
+//!
+//! The engine records every frame and tick, [`Engine::metrics`] reads them
+//! without the debug window being open:
+//!
+//! ```ignore
+//! engine.register_behavior(|eng| {
+//!         let metrics = eng.metrics();
+//!
+//!         if metrics.frame_time_percentile(0.99).as_secs_f32() > 1.0 / 30.0
+//!         {
+//!                 log::warn!("Slow frames, {:.0} fps", metrics.fps());
+//!         }
+//! });
+//! ```
+
+use crate::engine::Engine;
+use crate::model::Model;
+use instant::Instant;
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+/// Frames kept for the averages and percentiles.
+const HISTORY: usize = 240;
+
+/// Window the tick rate is measured over.
+const TICK_WINDOW: Duration = Duration::from_secs(1);
+
+/// Rolling frame and tick statistics, owned by the [`Engine`].
+#[derive(Debug, Default)]
+pub struct Metrics
+{
+        /// Time between the last frames, oldest first.
+        frame_times: VecDeque<Duration>,
+
+        last_frame: Option<Instant>,
+
+        /// When the ticks of the last [`TICK_WINDOW`] ran.
+        ticks: VecDeque<Instant>,
+
+        /// Target ticks per second.
+        tps: u16,
+
+        /// Time the simulation is behind the wall clock.
+        tick_lag: Duration,
+
+        models: usize,
+        visible_models: usize,
+        draw_calls: usize,
+}
+
+impl Metrics
+{
+        pub fn new() -> Self
+        {
+                Self::default()
+        }
+
+        /// Average frames per second over the history.
+        pub fn fps(&self) -> f32
+        {
+                let total: Duration = self.frame_times.iter().sum();
+
+                if total.is_zero()
+                {
+                        return 0.0;
+                }
+
+                self.frame_times.len() as f32 / total.as_secs_f32()
+        }
+
+        /// Time between the last two frames.
+        pub fn frame_time(&self) -> Duration
+        {
+                self.frame_times.back().copied().unwrap_or_default()
+        }
+
+        /// Frame times of the last few seconds, oldest first.
+        pub fn frame_times(&self) -> impl Iterator<Item = Duration> + '_
+        {
+                self.frame_times.iter().copied()
+        }
+
+        /// Frame time below which `p` of the recent frames were, e.g. `0.99`
+        /// for the 99th percentile.
+        pub fn frame_time_percentile(
+                &self,
+                p: f32,
+        ) -> Duration
+        {
+                if self.frame_times.is_empty()
+                {
+                        return Duration::ZERO;
+                }
+
+                let mut sorted: Vec<Duration> = self.frame_times.iter().copied().collect();
+                sorted.sort();
+
+                let index = (p.clamp(0.0, 1.0) * (sorted.len() - 1) as f32).round() as usize;
+
+                sorted[index]
+        }
+
+        /// Ticks that ran during the last second.
+        pub fn tick_rate(&self) -> f32
+        {
+                self.ticks.len() as f32 / TICK_WINDOW.as_secs_f32()
+        }
+
+        /// Measured minus target tick rate, negative when the simulation
+        /// can't keep up.
+        pub fn tick_drift(&self) -> f32
+        {
+                self.tick_rate() - self.tps as f32
+        }
+
+        /// How far the simulation is behind the wall clock, more than one
+        /// tick interval means ticks are being skipped.
+        pub fn tick_lag(&self) -> Duration
+        {
+                self.tick_lag
+        }
+
+        /// Loaded models.
+        pub fn models(&self) -> usize
+        {
+                self.models
+        }
+
+        pub fn visible_models(&self) -> usize
+        {
+                self.visible_models
+        }
+
+        /// Meshes the geometry pass drew last frame.
+        pub fn draw_calls(&self) -> usize
+        {
+                self.draw_calls
+        }
+
+        /// Records a rendered frame.
+        ///
+        /// Called by the engine once per frame.
+        pub(crate) fn frame(
+                &mut self,
+                models: &HashMap<String, Model>,
+        )
+        {
+                let now = Instant::now();
+
+                if let Some(last) = self.last_frame
+                {
+                        if self.frame_times.len() == HISTORY
+                        {
+                                self.frame_times.pop_front();
+                        }
+
+                        self.frame_times.push_back(now - last);
+                }
+
+                self.last_frame = Some(now);
+
+                self.models = models.len();
+                self.visible_models = 0;
+                self.draw_calls = 0;
+
+                for model in models.values().filter(|m| m.visible)
+                {
+                        self.visible_models += 1;
+                        self.draw_calls += model.meshes.len();
+                }
+        }
+
+        /// Records the ticks run since the last call.
+        ///
+        /// Called by the engine after it caught up with the wall clock.
+        pub(crate) fn ticks(
+                &mut self,
+                count: u64,
+                tps: u16,
+                lag: Duration,
+        )
+        {
+                let now = Instant::now();
+
+                self.ticks.extend(std::iter::repeat_n(now, count as usize));
+
+                while let Some(first) = self.ticks.front()
+                        && now - *first > TICK_WINDOW
+                {
+                        self.ticks.pop_front();
+                }
+
+                self.tps = tps;
+                self.tick_lag = lag;
+        }
+}
+
+impl Engine
+{
+        /// Frame rate, tick rate and scene counts, see [`Metrics`].
+        pub fn metrics(&self) -> &Metrics
+        {
+                &self.metrics
+        }
+}
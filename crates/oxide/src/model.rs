@@ -1,8 +1,28 @@
+use crate::geometry::bounds::Aabb;
+use crate::geometry::collider::Collider;
 use crate::geometry::mesh::{Mesh, MeshData};
-use crate::material::{MaterialData, MaterialProperties};
-use crate::resources::create_transform_bind_group_layout;
-use cgmath::{Deg, EuclideanSpace, Euler, InnerSpace, Quaternion, Rad, Rotation3, Vector3};
+use crate::geometry::morph::Morph;
+use crate::geometry::node::{self, Node};
+use crate::geometry::skin::Skin;
+use crate::handle::Handle;
+use crate::material::{
+        MaterialData, MaterialProperties, SamplerDesc, create_material_bind_group_layout,
+};
+use crate::math::collision::Obb;
+use crate::metadata::Metadata;
+use crate::renderer::preprocess::ShaderDefines;
+use crate::renderer::probe::{ProbeTexture, create_probe_bind_group_layout};
+use crate::renderer::surface::ColorSpace;
+use crate::resources::extras::Extras;
+use crate::resources::{
+        create_skinned_transform_bind_group_layout, create_transform_bind_group_layout,
+};
+use crate::texture::{StreamSource, TextureLimits};
+use crate::transform::Transform;
+use cgmath::{Deg, InnerSpace, Quaternion, Rad, Rotation3, Vector3};
+use std::collections::HashMap;
 use std::ops::Range;
+use std::sync::{Arc, OnceLock};
 use std::time::Duration;
 use wgpu::util::DeviceExt;
 use wgpu::{BindGroupDescriptor, BindGroupEntry};
@@ -49,42 +69,173 @@ impl Vertex for ModelVertex
         }
 }
 
-pub trait Transform
-{
-        fn calculate_transform(&self) -> cgmath::Matrix4<f32>;
-}
-
 #[derive(Debug)]
 pub struct Model
 {
-        pub position: cgmath::Point3<f32>,
-        pub rotation: Quaternion<f32>,
-        pub euler_angles: [f32; 3],
+        /// Where the model is in the world.
+        pub transform: Transform,
+        /// Offset from `transform` posed by an animation, see
+        /// [`crate::animation`].
+        pub pose: Transform,
         pub rotation_speeds: [f32; 3],
         pub is_spinning: bool,
-        pub scale: Vector3<f32>,
+        /// Skipped by the geometry pass when `false`.
+        pub visible: bool,
+        /// Group the model is shown, hidden and selected with in the debug
+        /// UI, see [`crate::selection`]. 0 by default.
+        pub layer: u32,
+        /// Tags and values behaviors query, see [`crate::metadata`].
+        pub metadata: Metadata,
         pub meshes: Vec<Mesh>,
         pub materials: Vec<crate::material::Material>,
+        /// What the debug draw pass shows for this model.
+        pub debug: ModelDebug,
+        /// Transform of the last frame, for the velocity of
+        /// [`TemporalPass`](crate::renderer::temporal::TemporalPass). Set
+        /// by the engine after every frame, `None` before the first.
+        pub previous_transform: Option<cgmath::Matrix4<f32>>,
+        /// Custom shader the model is drawn with, see
+        /// [`crate::renderer::custom`].
+        pub shader: Option<String>,
+        /// Parameters of the custom shader, see [`Model::set_params`].
+        pub params: Option<wgpu::Buffer>,
+        /// Material slots using a shared material, see
+        /// [`crate::material_registry`].
+        pub material_handles: HashMap<usize, Handle<crate::material::Material>>,
+        /// Named points in model space other models attach to, besides the
+        /// mesh nodes, see [`crate::attachment`].
+        pub sockets: HashMap<String, Transform>,
+        /// Skeleton of the skinned meshes, see [`crate::geometry::skin`].
+        pub skin: Option<Skin>,
+        /// Turned to face the camera before every frame when set.
+        pub billboard: Option<Billboard>,
+        /// Collision shapes in model space, see
+        /// [`crate::geometry::collider`].
+        pub colliders: Vec<Collider>,
+        /// Probe the model reflects, drawn with the `REFLECTION_PROBE`
+        /// permutation when set. Assigned by the engine, see
+        /// [`crate::renderer::probe`].
+        pub reflection: Option<ProbeTexture>,
+        /// Weight of every morph target by name, see
+        /// [`Model::set_morph_weight`].
+        morph_weights: Vec<(String, f32)>,
+        /// A weight changed since the morphed vertices were uploaded.
+        morphs_changed: bool,
+        /// Hierarchy the meshes were imported with, see
+        /// [`crate::geometry::node`].
+        nodes: Vec<Node>,
+        /// A node was borrowed mutably since the meshes were placed.
+        nodes_changed: bool,
 }
 
-impl Transform for Model
+/// How a model turns so its +Z side faces the camera, see
+/// [`Model::billboard`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Billboard
 {
-        fn calculate_transform(&self) -> cgmath::Matrix4<f32>
-        {
-                let translation = cgmath::Matrix4::from_translation(self.position.to_vec());
-                let rotation = cgmath::Matrix4::from(self.rotation);
-                let scale = cgmath::Matrix4::from_nonuniform_scale(
-                        self.scale.x,
-                        self.scale.y,
-                        self.scale.z,
-                );
+        /// Faces the camera from any direction.
+        Spherical,
+        /// Turns around Y only and stays upright, for trees and characters.
+        Cylindrical,
+}
+
+impl Billboard
+{
+        /// Rotation of a model at `position` facing `camera`.
+        pub fn rotation(
+                &self,
+                position: cgmath::Point3<f32>,
+                camera: cgmath::Point3<f32>,
+        ) -> Quaternion<f32>
+        {
+                let to_camera = camera - position;
+                let yaw = Quaternion::from_angle_y(Rad(to_camera.x.atan2(to_camera.z)));
+
+                match self
+                {
+                        Billboard::Cylindrical => yaw,
+                        Billboard::Spherical =>
+                        {
+                                let horizontal = (to_camera.x * to_camera.x
+                                        + to_camera.z * to_camera.z)
+                                        .sqrt();
+
+                                yaw * Quaternion::from_angle_x(Rad(-to_camera.y.atan2(horizontal)))
+                        }
+                }
+        }
+}
+
+/// Debug visualizations of a model, drawn by
+/// [`DebugDrawPass`](crate::renderer::debug_draw::DebugDrawPass).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModelDebug
+{
+        /// World space bounding box of the model.
+        pub show_bounds: bool,
+        pub show_wireframe: bool,
+        pub show_normals: bool,
+        pub show_tangents: bool,
+        /// Edges of the colliders, see [`crate::geometry::collider`].
+        pub show_colliders: bool,
+        /// Length of normal and tangent lines in world units.
+        pub line_length: f32,
+}
+
+impl Default for ModelDebug
+{
+        fn default() -> Self
+        {
+                Self {
+                        show_bounds: false,
+                        show_wireframe: false,
+                        show_normals: false,
+                        show_tangents: false,
+                        show_colliders: false,
+                        line_length: 0.1,
+                }
+        }
+}
 
-                translation * rotation * scale
+impl ModelDebug
+{
+        pub fn any(&self) -> bool
+        {
+                self.show_bounds
+                        || self.show_wireframe
+                        || self.show_normals
+                        || self.show_tangents
+                        || self.show_colliders
+        }
+
+        #[cfg(feature = "ui")]
+        pub fn ui(
+                &mut self,
+                ui: &mut egui::Ui,
+        )
+        {
+                ui.collapsing("Debug Draw", |ui| {
+                        ui.checkbox(&mut self.show_bounds, "Bounding box");
+                        ui.checkbox(&mut self.show_wireframe, "Wireframe");
+                        ui.checkbox(&mut self.show_normals, "Normals");
+                        ui.checkbox(&mut self.show_tangents, "Tangents");
+                        ui.checkbox(&mut self.show_colliders, "Colliders");
+
+                        ui.add(egui::Slider::new(&mut self.line_length, 0.001..=10.0)
+                                .logarithmic(true)
+                                .text("Line length"));
+                });
         }
 }
 
 impl Model
 {
+        /// The model matrix, the transform with the pose applied.
+        pub fn calculate_transform(&self) -> cgmath::Matrix4<f32>
+        {
+                self.transform.matrix() * self.pose.matrix()
+        }
+
         pub fn from_data(
                 meshes: Vec<MeshData>,
                 materials: Vec<MaterialData>,
@@ -95,160 +246,262 @@ impl Model
                 transform_bind_group_layout: &wgpu::BindGroupLayout,
         ) -> Self
         {
-                // Convert GLB images to GPU textures
-                let gpu_textures: Vec<crate::texture::Texture> = images
-                .iter()
-                .enumerate()
-                .map(|(index, image)| {
-                    log::info!("IMAGE {} INFO: {:?} ({}x{})", index, image.format, image.width, image.height);
-
-                    let size = wgpu::Extent3d {
-                        width: image.width,
-                        height: image.height,
-                        depth_or_array_layers: 1,
-                    };
-
-                    // Determine bytes per pixel and convert if necessary
-                    let (final_pixels, bytes_per_pixel, target_format) = match image.format {
-                        gltf::image::Format::R8G8B8A8 => {
-                            // Already RGBA, use as-is
-                            (image.pixels.clone(), 4, wgpu::TextureFormat::Rgba8UnormSrgb)
-                        }
-                        gltf::image::Format::R8G8B8 => {
-                            // Convert RGB to RGBA
-                            let mut rgba_data = Vec::with_capacity(image.pixels.len() * 4 / 3);
-                            for chunk in image.pixels.chunks_exact(3) {
-                                rgba_data.extend_from_slice(chunk);
-                                rgba_data.push(255); // Add full alpha
-                            }
-                            (rgba_data, 4, wgpu::TextureFormat::Rgba8UnormSrgb)
-                        }
-                        gltf::image::Format::R8G8 => {
-                            // R8G8 format (2 bytes per pixel) - use the appropriate texture format
-                            // Convert to RGBA if needed, or use a two-channel format
-                            let mut rgba_data = Vec::with_capacity(image.pixels.len() * 2);
-                            for chunk in image.pixels.chunks_exact(2) {
-                                rgba_data.extend_from_slice(chunk);
-                                rgba_data.push(0); // Add the blue channel
-                                rgba_data.push(255); // Add alpha channel
-                            }
-                            (rgba_data, 4, wgpu::TextureFormat::Rgba8UnormSrgb)
-                        }
-                        _ => {
-                            log::warn!("Unknown image format {:?}, defaulting to RGBA", image.format);
-                            (image.pixels.clone(), 4, wgpu::TextureFormat::Rgba8UnormSrgb)
+                Self::from_data_with_limits(
+                        meshes,
+                        materials,
+                        images,
+                        device,
+                        queue,
+                        material_bind_group_layout,
+                        transform_bind_group_layout,
+                        TextureLimits::default(),
+                )
+        }
+
+        /// [`Model::from_data`] with the textures uploaded within `limits`.
+        #[allow(clippy::too_many_arguments)]
+        pub fn from_data_with_limits(
+                meshes: Vec<MeshData>,
+                materials: Vec<MaterialData>,
+                images: Vec<gltf::image::Data>,
+                device: &wgpu::Device,
+                queue: &wgpu::Queue,
+                material_bind_group_layout: &wgpu::BindGroupLayout,
+                transform_bind_group_layout: &wgpu::BindGroupLayout,
+                limits: TextureLimits,
+        ) -> Self
+        {
+                // Convert GLB images to GPU textures, colors are stored as sRGB
+                // and data like normals as is
+                let upload = |index: usize, image: &gltf::image::Data, color_space: ColorSpace| {
+                        log::info!(
+                                "IMAGE {} INFO: {:?} ({}x{})",
+                                index,
+                                image.format,
+                                image.width,
+                                image.height
+                        );
+
+                        let target_format = match color_space
+                        {
+                                ColorSpace::Srgb => wgpu::TextureFormat::Rgba8UnormSrgb,
+                                ColorSpace::Linear => wgpu::TextureFormat::Rgba8Unorm,
+                        };
+
+                        // Convert to RGBA if necessary
+                        let final_pixels = crate::texture::Texture::gltf_rgba(image);
+                        let bytes_per_pixel = 4;
+
+                        // Too large textures are scaled down instead of failing validation
+                        let (final_pixels, width, height) = crate::texture::Texture::fit_to_limit(
+                                final_pixels,
+                                image.width,
+                                image.height,
+                                limits.max_size(device),
+                                &format!("GLB Texture {}", index),
+                        );
+
+                        let size = wgpu::Extent3d {
+                                width,
+                                height,
+                                depth_or_array_layers: 1,
+                        };
+
+                        let texture = device.create_texture(&wgpu::TextureDescriptor {
+                                label: Some(&format!("GLB Texture {}", index)),
+                                size,
+                                mip_level_count: 1,
+                                sample_count: 1,
+                                dimension: wgpu::TextureDimension::D2,
+                                format: target_format,
+                                usage: wgpu::TextureUsages::TEXTURE_BINDING
+                                        | wgpu::TextureUsages::COPY_DST,
+                                view_formats: &[],
+                        });
+
+                        // Calculate bytes per row with proper alignment
+                        let unpadded_bytes_per_row: usize =
+                                bytes_per_pixel as usize * width as usize;
+                        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT as usize;
+                        let padded_bytes_per_row =
+                                ((unpadded_bytes_per_row + align - 1) / align) * align;
+
+                        log::debug!(
+                                "Texture {}: {}x{}, bpp: {}, unpadded: {}, padded: {}",
+                                index,
+                                width,
+                                height,
+                                bytes_per_pixel,
+                                unpadded_bytes_per_row,
+                                padded_bytes_per_row
+                        );
+
+                        // Verify the final data size matches expectations
+                        let expected_size = unpadded_bytes_per_row * height as usize;
+                        assert_eq!(
+                                final_pixels.len(),
+                                expected_size,
+                                "Image {}: Expected {} bytes, got {} bytes",
+                                index,
+                                expected_size,
+                                final_pixels.len()
+                        );
+
+                        // If padding is needed, create padded data
+                        let upload_data = if padded_bytes_per_row > unpadded_bytes_per_row
+                        {
+                                let mut padded_data =
+                                        Vec::with_capacity(padded_bytes_per_row * height as usize);
+
+                                for y in 0..height as usize
+                                {
+                                        let row_start = y * unpadded_bytes_per_row;
+                                        let row_end = row_start + unpadded_bytes_per_row;
+
+                                        // Add the actual row data
+                                        padded_data.extend_from_slice(
+                                                &final_pixels[row_start..row_end],
+                                        );
+
+                                        // Add padding zeros
+                                        padded_data.resize(
+                                                padded_data.len()
+                                                        + (padded_bytes_per_row
+                                                                - unpadded_bytes_per_row),
+                                                0,
+                                        );
+                                }
+                                padded_data
                         }
-                    };
-
-                    let texture = device.create_texture(&wgpu::TextureDescriptor {
-                        label: Some(&format!("GLB Texture {}", index)),
-                        size,
-                        mip_level_count: 1,
-                        sample_count: 1,
-                        dimension: wgpu::TextureDimension::D2,
-                        format: target_format,
-                        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
-                        view_formats: &[],
-                    });
+                        else
+                        {
+                                final_pixels
+                        };
+
+                        queue.write_texture(
+                                wgpu::TexelCopyTextureInfo {
+                                        texture: &texture,
+                                        mip_level: 0,
+                                        origin: wgpu::Origin3d::ZERO,
+                                        aspect: wgpu::TextureAspect::All,
+                                },
+                                &upload_data,
+                                wgpu::TexelCopyBufferLayout {
+                                        offset: 0,
+                                        bytes_per_row: Some(padded_bytes_per_row as u32),
+                                        rows_per_image: Some(height),
+                                },
+                                size,
+                        );
+
+                        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+                        let sampler = device.create_sampler(&wgpu::SamplerDescriptor::default());
 
-                    // Calculate bytes per row with proper alignment
-                    let unpadded_bytes_per_row: usize = bytes_per_pixel as usize * image.width as usize;
-                    let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT as usize;
-                    let padded_bytes_per_row = ((unpadded_bytes_per_row + align - 1) / align) * align;
-
-                    log::debug!("Texture {}: {}x{}, bpp: {}, unpadded: {}, padded: {}",
-            index, image.width, image.height, bytes_per_pixel,
-            unpadded_bytes_per_row, padded_bytes_per_row);
-
-                    // Verify the final data size matches expectations
-                    let expected_size = unpadded_bytes_per_row * image.height as usize;
-                    assert_eq!(
-                        final_pixels.len(),
-                        expected_size,
-                        "Image {}: Expected {} bytes, got {} bytes",
-                        index, expected_size, final_pixels.len()
-                    );
-
-                    // If padding is needed, create padded data
-                    let upload_data = if padded_bytes_per_row > unpadded_bytes_per_row {
-                        let mut padded_data = Vec::with_capacity(padded_bytes_per_row * image.height as usize);
-
-                        for y in 0..image.height as usize {
-                            let row_start = y * unpadded_bytes_per_row;
-                            let row_end = row_start + unpadded_bytes_per_row;
-
-                            // Add the actual row data
-                            padded_data.extend_from_slice(&final_pixels[row_start..row_end]);
-
-                            // Add padding zeros
-                            padded_data.resize(padded_data.len() + (padded_bytes_per_row - unpadded_bytes_per_row), 0);
+                        crate::texture::Texture {
+                                texture,
+                                view,
+                                sampler,
                         }
-                        padded_data
-                    } else {
-                        final_pixels
-                    };
-
-                    queue.write_texture(
-                        wgpu::TexelCopyTextureInfo {
-                            texture: &texture,
-                            mip_level: 0,
-                            origin: wgpu::Origin3d::ZERO,
-                            aspect: wgpu::TextureAspect::All,
-                        },
-                        &upload_data,
-                        wgpu::TexelCopyBufferLayout {
-                            offset: 0,
-                            bytes_per_row: Some(padded_bytes_per_row as u32),
-                            rows_per_image: Some(image.height),
-                        },
-                        size,
-                    );
-
-                    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
-                    let sampler = device.create_sampler(&wgpu::SamplerDescriptor::default());
-
-                    crate::texture::Texture {
-                        texture,
-                        view,
-                        sampler,
-                    }
-                })
-                .collect();
+                };
+
+                // Only the images materials use, once per color space
+                let mut gpu_textures: HashMap<(usize, ColorSpace), crate::texture::Texture> =
+                        HashMap::new();
+
+                // Streamed images by index, shared like the textures
+                let mut stream_sources: HashMap<usize, StreamSource> = HashMap::new();
+                let stream_initial_size = limits.stream_initial_size;
 
                 // Upload materials
                 let gpu_materials = materials
         .into_iter()
         .map(|mat| {
+            // Images are shared, the sampler comes from the material's texture
+            let mut texture = |index: Option<usize>, color_space: ColorSpace, sampler: &SamplerDesc| {
+                let index = index?;
+                let image = images.get(index)?;
+
+                let texture = gpu_textures
+                    .entry((index, color_space))
+                    .or_insert_with(|| upload(index, image, color_space))
+                    .clone();
+
+                Some(crate::texture::Texture {
+                    sampler: sampler.create_sampler(device),
+                    ..texture
+                })
+            };
+
+            // Streamed base color textures start small, see `crate::streaming`
+            let stream = stream_initial_size
+                .zip(mat.base_color_texture_index)
+                .and_then(|(initial_size, index)| {
+                    let image = images.get(index)?;
+
+                    let source = stream_sources.entry(index).or_insert_with(|| {
+                        let (pixels, width, height) = crate::texture::Texture::fit_to_limit(
+                            crate::texture::Texture::gltf_rgba(image),
+                            image.width,
+                            image.height,
+                            limits.max_size(device),
+                            &format!("GLB Texture {}", index),
+                        );
+
+                        let mut source = StreamSource {
+                            pixels: Arc::new(pixels),
+                            width,
+                            height,
+                            format: match mat.base_color_space {
+                                ColorSpace::Srgb => wgpu::TextureFormat::Rgba8UnormSrgb,
+                                ColorSpace::Linear => wgpu::TextureFormat::Rgba8Unorm,
+                            },
+                            resident_level: 0,
+                        };
+                        source.resident_level = source.level_for(initial_size);
+                        source
+                    });
+
+                    Some(source.clone())
+                });
+
             // Choose base color texture from GLB images
-            let base_color_texture = mat
-                .base_color_texture_index
-                .and_then(|idx| gpu_textures.get(idx).cloned())
-                .unwrap_or_else(|| crate::texture::Texture::create_dummy(device, queue));
+            let base_color_texture = match &stream {
+                Some(stream) => Some(crate::texture::Texture {
+                    sampler: mat.sampler.create_sampler(device),
+                    ..stream.upload(device, queue, stream.resident_level, &format!("{} Streamed", mat.name))
+                }),
+                None => texture(mat.base_color_texture_index, mat.base_color_space, &mat.sampler),
+            }
+            .unwrap_or_else(|| crate::texture::Texture::create_dummy(device, queue));
 
-            let normal_texture = mat
-                .normal_texture_index
-                .and_then(|idx| gpu_textures.get(idx).cloned());
+            let normal_texture = texture(mat.normal_texture_index, mat.normal_space, &mat.normal_sampler);
 
-            let metallic_roughness_texture = mat
-                .metallic_roughness_texture_index
-                .and_then(|idx| gpu_textures.get(idx).cloned());
+            let metallic_roughness_texture = texture(
+                mat.metallic_roughness_texture_index,
+                mat.metallic_roughness_space,
+                &mat.metallic_roughness_sampler,
+            );
 
             // Material uniform
             let material_properties = MaterialProperties {
                 base_color_factor: mat.base_color_factor,
                 metallic_factor: mat.metallic_factor,
                 roughness_factor: mat.roughness_factor,
-                _padding: [0.0; 2],
+                lod_bias: mat.sampler.lod_bias,
+                _padding: 0.0,
             };
 
-            let material_properties_buffer = device.create_buffer_init(
+            let properties_buffer = device.create_buffer_init(
                 &wgpu::util::BufferInitDescriptor {
                     label: Some("Material Properties Buffer"),
                     contents: bytemuck::cast_slice(&[material_properties]),
-                    usage: wgpu::BufferUsages::UNIFORM,
+                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
                 },
             );
 
+            // Bound in place of a missing normal map, see `Material::create_bind_group`
+            let normal_binding = normal_texture.as_ref().unwrap_or(&base_color_texture);
+
             let material_bind_group = device.create_bind_group(&BindGroupDescriptor {
                 layout: material_bind_group_layout,
                 entries: &[
@@ -262,7 +515,15 @@ impl Model
                     },
                     BindGroupEntry {
                         binding: 2,
-                        resource: material_properties_buffer.as_entire_binding(),
+                        resource: properties_buffer.as_entire_binding(),
+                    },
+                    BindGroupEntry {
+                        binding: 3,
+                        resource: wgpu::BindingResource::TextureView(&normal_binding.view),
+                    },
+                    BindGroupEntry {
+                        binding: 4,
+                        resource: wgpu::BindingResource::Sampler(&normal_binding.sampler),
                     },
                 ],
                 label: Some(&format!("{} Material Bind Group", mat.name)),
@@ -276,33 +537,80 @@ impl Model
                 base_color_factor: mat.base_color_factor,
                 metallic_factor: mat.metallic_factor,
                 roughness_factor: mat.roughness_factor,
+                sampler: mat.sampler,
+                properties_buffer,
                 material_bind_group,
+                stream,
+                extras: mat.extras,
             }
         })
         .collect::<Vec<_>>();
 
                 log::info!("from_data Called!");
 
+                // Targets of the same name in several meshes share a weight.
+                let mut morph_weights: Vec<(String, f32)> = Vec::new();
+
+                for target in meshes.iter().flat_map(|m| &m.morph_targets)
+                {
+                        if !morph_weights.iter().any(|(name, _)| *name == target.name)
+                        {
+                                morph_weights.push((target.name.clone(), target.weight));
+                        }
+                }
+
+                // Skinned meshes of one model share a skeleton.
+                let skin = meshes
+                        .iter()
+                        .find_map(|m| m.skin.as_ref())
+                        .map(|skin| Skin::new(skin.joints.clone()));
+
                 // Mesh upload stays the same
                 let gpu_meshes = meshes
                         .into_iter()
                         .map(|m| {
+                                // Written again by morph targets and
+                                // `Mesh::update_vertices`.
+                                let usage =
+                                        wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST;
+
                                 let vertex_buffer = device.create_buffer_init(
                                         &wgpu::util::BufferInitDescriptor {
                                                 label: Some(&format!("{} Vertex Buffer", m.name)),
                                                 contents: bytemuck::cast_slice(&m.vertices),
-                                                usage: wgpu::BufferUsages::VERTEX,
+                                                usage,
                                         },
                                 );
 
+                                let morph = (!m.morph_targets.is_empty()).then(|| Morph {
+                                        targets: m.morph_targets.clone(),
+                                        base: m.vertices.clone(),
+                                });
+
                                 let index_buffer = device.create_buffer_init(
                                         &wgpu::util::BufferInitDescriptor {
                                                 label: Some(&format!("{} Index Buffer", m.name)),
                                                 contents: bytemuck::cast_slice(&m.indices),
-                                                usage: wgpu::BufferUsages::INDEX,
+                                                usage: wgpu::BufferUsages::INDEX
+                                                        | wgpu::BufferUsages::COPY_DST,
                                         },
                                 );
 
+                                let skin_buffer = m.skin.as_ref().map(|skin| {
+                                        device.create_buffer_init(
+                                                &wgpu::util::BufferInitDescriptor {
+                                                        label: Some(&format!(
+                                                                "{} Skin Buffer",
+                                                                m.name
+                                                        )),
+                                                        contents: bytemuck::cast_slice(
+                                                                &skin.vertices,
+                                                        ),
+                                                        usage: wgpu::BufferUsages::VERTEX,
+                                                },
+                                        )
+                                });
+
                                 let transform_buffer = Self::create_transform_buffer(&device, &m);
                                 let transform_bind_group = Self::create_transform_bind_group(
                                         &device,
@@ -312,6 +620,7 @@ impl Model
                                 );
 
                                 Mesh {
+                                        bounds: m.bounds(),
                                         name: m.name,
                                         vertex_buffer,
                                         index_buffer,
@@ -319,19 +628,43 @@ impl Model
                                         material: m.material_id.unwrap_or(0),
                                         transform_buffer,
                                         transform_bind_group,
+                                        transform: m.transform,
+                                        node: m.node,
+                                        vertices: m.vertices,
+                                        indices: m.indices,
+                                        revision: 0,
+                                        barycentric_buffer: OnceLock::new(),
+                                        skin_buffer,
+                                        morph,
+                                        extras: m.extras,
                                 }
                         })
                         .collect::<Vec<_>>();
 
                 Model {
-                        position: cgmath::Point3::new(0.0, 0.0, 0.0),
-                        rotation: Quaternion::new(1.0, 0.0, 0.0, 0.0),
-                        euler_angles: [0.0, 0.0, 0.0],
+                        transform: Transform::IDENTITY,
+                        pose: Transform::IDENTITY,
                         rotation_speeds: [0.0, 0.0, 0.0],
                         is_spinning: false,
-                        scale: Vector3::new(1.0, 1.0, 1.0),
+                        visible: true,
+                        layer: 0,
+                        metadata: Metadata::new(),
                         meshes: gpu_meshes,
                         materials: gpu_materials,
+                        debug: ModelDebug::default(),
+                        previous_transform: None,
+                        shader: None,
+                        params: None,
+                        material_handles: HashMap::new(),
+                        sockets: HashMap::new(),
+                        skin,
+                        billboard: None,
+                        colliders: Vec::new(),
+                        reflection: None,
+                        morphs_changed: !morph_weights.iter().all(|(_, w)| *w == 0.0),
+                        morph_weights,
+                        nodes: Vec::new(),
+                        nodes_changed: false,
                 }
         }
 
@@ -382,6 +715,379 @@ impl Model
                 })
         }
 
+        /// [`Model::create_model_transform_bind_group`] with the joint
+        /// palette of [`Model::skin`], `None` when the model isn't skinned.
+        pub fn create_skinned_bind_group(
+                &self,
+                device: &wgpu::Device,
+        ) -> Option<wgpu::BindGroup>
+        {
+                let skin = self.skin.as_ref()?;
+
+                let palette = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                        label: Some("Joint Palette Buffer"),
+                        contents: &skin.palette_bytes(),
+                        usage: wgpu::BufferUsages::UNIFORM,
+                });
+
+                Some(device.create_bind_group(&BindGroupDescriptor {
+                        label: Some("skinned_transform_bind_group"),
+                        layout: &create_skinned_transform_bind_group_layout(device),
+                        entries: &[
+                                BindGroupEntry {
+                                        binding: 0,
+                                        resource: self
+                                                .create_model_transform_buffer(device)
+                                                .as_entire_binding(),
+                                },
+                                BindGroupEntry {
+                                        binding: 1,
+                                        resource: palette.as_entire_binding(),
+                                },
+                        ],
+                }))
+        }
+
+        /// [`Model::create_model_transform_bind_group`] with the joint
+        /// palette when `skinned` and the cubemap of [`Model::reflection`],
+        /// `None` without a probe, see
+        /// [`create_probe_bind_group_layout`].
+        pub fn create_probe_bind_group(
+                &self,
+                device: &wgpu::Device,
+                skinned: bool,
+        ) -> Option<wgpu::BindGroup>
+        {
+                let probe = self.reflection.as_ref()?;
+
+                let transform = self.create_model_transform_buffer(device);
+                let palette = self.skin.as_ref().filter(|_| skinned).map(|skin| {
+                        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                                label: Some("Joint Palette Buffer"),
+                                contents: &skin.palette_bytes(),
+                                usage: wgpu::BufferUsages::UNIFORM,
+                        })
+                });
+
+                let mut entries = vec![BindGroupEntry {
+                        binding: 0,
+                        resource: transform.as_entire_binding(),
+                }];
+
+                if let Some(palette) = &palette
+                {
+                        entries.push(BindGroupEntry {
+                                binding: 1,
+                                resource: palette.as_entire_binding(),
+                        });
+                }
+
+                entries.extend(probe.entries());
+
+                Some(device.create_bind_group(&BindGroupDescriptor {
+                        label: Some("probe_bind_group"),
+                        layout: &create_probe_bind_group_layout(device, palette.is_some()),
+                        entries: &entries,
+                }))
+        }
+
+        /// The geometry shader permutation for `mesh`, its material's
+        /// features, its skinning and its reflection probe.
+        pub fn defines(
+                &self,
+                mesh: &Mesh,
+        ) -> ShaderDefines
+        {
+                let mut defines = self.materials[mesh.material].defines();
+
+                if let Some(skin) = self.skin.as_ref().filter(|_| mesh.skin_buffer.is_some())
+                {
+                        for name in skin.defines().iter()
+                        {
+                                defines.insert(name);
+                        }
+                }
+
+                if self.reflection.is_some()
+                {
+                        defines.insert("REFLECTION_PROBE");
+                }
+
+                defines
+        }
+
+        /// Bind group of [`Model::previous_transform`], the current
+        /// transform before the first frame.
+        pub fn create_previous_transform_bind_group(
+                &self,
+                device: &wgpu::Device,
+        ) -> wgpu::BindGroup
+        {
+                let transform_data: [[f32; 4]; 4] = self
+                        .previous_transform
+                        .unwrap_or_else(|| self.calculate_transform())
+                        .into();
+
+                let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                        label: Some("Previous Transform Buffer"),
+                        contents: bytemuck::cast_slice(&transform_data),
+                        usage: wgpu::BufferUsages::UNIFORM,
+                });
+
+                device.create_bind_group(&BindGroupDescriptor {
+                        label: Some("previous_transform_bind_group"),
+                        layout: &create_transform_bind_group_layout(device),
+                        entries: &[BindGroupEntry {
+                                binding: 0,
+                                resource: buffer.as_entire_binding(),
+                        }],
+                })
+        }
+
+        /// A copy sharing the GPU geometry and textures, see
+        /// [`Mesh::duplicate`] and
+        /// [`Material::duplicate`](crate::material::Material::duplicate).
+        /// Transform, material factors, morph weights and custom shader
+        /// parameters are its own.
+        pub fn duplicate(
+                &self,
+                device: &wgpu::Device,
+                queue: &wgpu::Queue,
+        ) -> Self
+        {
+                let transform_layout = create_transform_bind_group_layout(device);
+
+                let params = self.params.as_ref().map(|params| {
+                        let copy = device.create_buffer(&wgpu::BufferDescriptor {
+                                label: Some("Params Buffer"),
+                                size: params.size(),
+                                usage: params.usage(),
+                                mapped_at_creation: false,
+                        });
+
+                        let mut encoder =
+                                device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                                        label: Some("Duplicate Params Encoder"),
+                                });
+                        encoder.copy_buffer_to_buffer(params, 0, &copy, 0, params.size());
+                        queue.submit([encoder.finish()]);
+
+                        copy
+                });
+
+                Self {
+                        transform: self.transform,
+                        pose: self.pose,
+                        rotation_speeds: self.rotation_speeds,
+                        is_spinning: self.is_spinning,
+                        visible: self.visible,
+                        layer: self.layer,
+                        metadata: self.metadata.clone(),
+                        meshes: self
+                                .meshes
+                                .iter()
+                                .map(|m| m.duplicate(device, &transform_layout))
+                                .collect(),
+                        materials: self.materials.iter().map(|m| m.duplicate(device)).collect(),
+                        debug: self.debug,
+                        previous_transform: None,
+                        shader: self.shader.clone(),
+                        params,
+                        material_handles: self.material_handles.clone(),
+                        sockets: self.sockets.clone(),
+                        skin: self.skin.clone(),
+                        billboard: self.billboard,
+                        colliders: self.colliders.clone(),
+                        reflection: self.reflection.clone(),
+                        morph_weights: self.morph_weights.clone(),
+                        // Uploads the morphed vertices into the new buffers.
+                        morphs_changed: !self.morph_weights.is_empty(),
+                        nodes: self.nodes.clone(),
+                        nodes_changed: false,
+                }
+        }
+
+        /// A static batch of `models` at the origin: their meshes with the
+        /// model and node transforms baked into the vertices, one mesh per
+        /// distinct material, so they're drawn in a few draw calls. Skinned
+        /// and morphed meshes are left out, as are billboards, whose
+        /// rotation follows the camera.
+        ///
+        /// Materials are distinct when their factors, sampler or textures
+        /// differ. The textures are shared, see
+        /// [`Material::duplicate`](crate::material::Material::duplicate).
+        pub fn merge(
+                models: &[&Model],
+                device: &wgpu::Device,
+        ) -> Self
+        {
+                let transform_layout = create_transform_bind_group_layout(device);
+
+                let texture = |t: Option<&crate::texture::Texture>| t.map(|t| t.texture.clone());
+                let same = |a: &crate::material::Material, b: &crate::material::Material| {
+                        a.desc() == b.desc()
+                                && a.sampler == b.sampler
+                                && a.base_color_texture.texture == b.base_color_texture.texture
+                                && texture(a.normal_texture.as_ref())
+                                        == texture(b.normal_texture.as_ref())
+                                && texture(a.metallic_roughness_texture.as_ref())
+                                        == texture(b.metallic_roughness_texture.as_ref())
+                };
+
+                let mut materials: Vec<crate::material::Material> = Vec::new();
+                let mut batches: Vec<MeshData> = Vec::new();
+
+                for model in models.iter().filter(|m| m.billboard.is_none())
+                {
+                        let model_matrix = model.calculate_transform();
+
+                        for mesh in model
+                                .meshes
+                                .iter()
+                                .filter(|m| m.skin_buffer.is_none() && m.morph.is_none())
+                        {
+                                let material = &model.materials[mesh.material];
+
+                                let slot = match materials.iter().position(|m| same(m, material))
+                                {
+                                        Some(slot) => slot,
+                                        None =>
+                                        {
+                                                materials.push(material.duplicate(device));
+                                                batches.push(MeshData {
+                                                        name: format!("batch_{}", material.name),
+                                                        vertices: Vec::new(),
+                                                        indices: Vec::new(),
+                                                        material_id: Some(batches.len()),
+                                                        transform: cgmath::SquareMatrix::identity(),
+                                                        node: None,
+                                                        skin: None,
+                                                        morph_targets: Vec::new(),
+                                                        extras: Extras::new(),
+                                                });
+
+                                                batches.len() - 1
+                                        }
+                                };
+
+                                batches[slot].append(
+                                        &mesh.vertices,
+                                        &mesh.indices,
+                                        &(model_matrix * mesh.transform),
+                                );
+                        }
+                }
+
+                Model {
+                        transform: Transform::IDENTITY,
+                        pose: Transform::IDENTITY,
+                        rotation_speeds: [0.0, 0.0, 0.0],
+                        is_spinning: false,
+                        visible: true,
+                        layer: models.first().map_or(0, |m| m.layer),
+                        metadata: Metadata::new(),
+                        meshes: batches
+                                .into_iter()
+                                .map(|data| Mesh::from_data(device, &transform_layout, data))
+                                .collect(),
+                        materials,
+                        debug: ModelDebug::default(),
+                        previous_transform: None,
+                        shader: None,
+                        params: None,
+                        material_handles: HashMap::new(),
+                        sockets: HashMap::new(),
+                        skin: None,
+                        billboard: None,
+                        // The batch is at the origin.
+                        colliders: models.iter().flat_map(|m| m.world_colliders()).collect(),
+                        reflection: None,
+                        morph_weights: Vec::new(),
+                        morphs_changed: false,
+                        nodes: Vec::new(),
+                        nodes_changed: false,
+                }
+        }
+
+        /// Uploads the parameters of the model's custom shader, the bytes
+        /// of a plain-old-data struct.
+        pub fn set_params(
+                &mut self,
+                device: &wgpu::Device,
+                queue: &wgpu::Queue,
+                bytes: &[u8],
+        )
+        {
+                let contents = crate::renderer::custom::pad_params(bytes);
+
+                match &self.params
+                {
+                        Some(buffer) if buffer.size() == contents.len() as u64 =>
+                        {
+                                queue.write_buffer(buffer, 0, &contents);
+                        }
+                        _ =>
+                        {
+                                self.params = Some(device.create_buffer_init(
+                                        &wgpu::util::BufferInitDescriptor {
+                                                label: Some("Params Buffer"),
+                                                contents: &contents,
+                                                // Copied by `Model::duplicate`.
+                                                usage: wgpu::BufferUsages::UNIFORM
+                                                        | wgpu::BufferUsages::COPY_DST
+                                                        | wgpu::BufferUsages::COPY_SRC,
+                                        },
+                                ));
+                        }
+                }
+        }
+
+        /// Bind group of the model transform and the parameters for a custom
+        /// shader, zeroed parameters when none were set.
+        pub fn create_custom_bind_group(
+                &self,
+                device: &wgpu::Device,
+        ) -> wgpu::BindGroup
+        {
+                let zeroed;
+
+                let params = match &self.params
+                {
+                        Some(buffer) => buffer,
+                        None =>
+                        {
+                                zeroed = device.create_buffer_init(
+                                        &wgpu::util::BufferInitDescriptor {
+                                                label: Some("Params Buffer"),
+                                                contents: &[0; 16],
+                                                usage: wgpu::BufferUsages::UNIFORM,
+                                        },
+                                );
+
+                                &zeroed
+                        }
+                };
+
+                device.create_bind_group(&BindGroupDescriptor {
+                        label: Some("custom_model_bind_group"),
+                        layout: &crate::renderer::custom::create_custom_model_bind_group_layout(
+                                device,
+                        ),
+                        entries: &[
+                                BindGroupEntry {
+                                        binding: 0,
+                                        resource: self
+                                                .create_model_transform_buffer(device)
+                                                .as_entire_binding(),
+                                },
+                                BindGroupEntry {
+                                        binding: 1,
+                                        resource: params.as_entire_binding(),
+                                },
+                        ],
+                })
+        }
+
         pub fn create_transform_bind_group(
                 device: &wgpu::Device,
                 transform_bind_group_layout: &wgpu::BindGroupLayout,
@@ -399,6 +1105,7 @@ impl Model
                 })
         }
 
+        #[cfg(feature = "ui")]
         pub fn rotation_ui(
                 &mut self,
                 ui: &mut egui::Ui,
@@ -406,24 +1113,23 @@ impl Model
         {
                 ui.heading("Rotation Controls");
 
-                // Euler angle editor (only affects initial orientation)
-                ui.collapsing("Euler Angles (Initial)", |ui| {
-                        ui.add(egui::DragValue::new(&mut self.euler_angles[0])
-                                .speed(1.0)
-                                .suffix("°"));
-                        ui.add(egui::DragValue::new(&mut self.euler_angles[1])
-                                .speed(1.0)
-                                .suffix("°"));
-                        ui.add(egui::DragValue::new(&mut self.euler_angles[2])
-                                .speed(1.0)
-                                .suffix("°"));
-
-                        self.rotation = Quaternion::from(Euler::new(
-                                Rad::from(Deg(self.euler_angles[0])),
-                                Rad::from(Deg(self.euler_angles[1])),
-                                Rad::from(Deg(self.euler_angles[2])),
-                        ))
-                        .normalize();
+                // Euler angle editor, written back only when dragged so the
+                // rotation doesn't drift through the conversion
+                ui.collapsing("Euler Angles", |ui| {
+                        let mut euler_angles = self.transform.euler_angles();
+                        let mut changed = false;
+
+                        for angle in &mut euler_angles
+                        {
+                                changed |= ui
+                                        .add(egui::DragValue::new(angle).speed(1.0).suffix("°"))
+                                        .changed();
+                        }
+
+                        if changed
+                        {
+                                self.transform.set_euler_angles(euler_angles);
+                        }
                 });
 
                 // Continuous rotation controls
@@ -447,12 +1153,10 @@ impl Model
 
                 // Current status
                 ui.collapsing("Current Status", |ui| {
-                        let euler = Euler::from(self.rotation);
+                        let [x, y, z] = self.transform.euler_angles();
                         ui.label(format!(
                                 "Current Euler: X: {:.1}°, Y: {:.1}°, Z: {:.1}°",
-                                euler.x.0.to_degrees(),
-                                euler.y.0.to_degrees(),
-                                euler.z.0.to_degrees()
+                                x, y, z
                         ));
 
                         ui.label(format!(
@@ -464,6 +1168,7 @@ impl Model
                 });
         }
 
+        #[cfg(feature = "ui")]
         pub fn ui(
                 &mut self,
                 ui: &mut egui::Ui,
@@ -473,16 +1178,46 @@ impl Model
                         .default_open(true)
                         .show(ui, |ui| {
                                 ui.label("Position");
-                                ui.add(egui::DragValue::new(&mut self.position.x));
-                                ui.add(egui::DragValue::new(&mut self.position.y));
-                                ui.add(egui::DragValue::new(&mut self.position.z));
+                                let position = &mut self.transform.position;
+                                ui.add(egui::DragValue::new(&mut position.x));
+                                ui.add(egui::DragValue::new(&mut position.y));
+                                ui.add(egui::DragValue::new(&mut position.z));
 
                                 self.rotation_ui(ui);
 
                                 ui.label("Scale");
-                                ui.add(egui::DragValue::new(&mut self.scale.x).speed(0.001));
-                                ui.add(egui::DragValue::new(&mut self.scale.y).speed(0.001));
-                                ui.add(egui::DragValue::new(&mut self.scale.z).speed(0.001));
+                                let scale = &mut self.transform.scale;
+                                ui.add(egui::DragValue::new(&mut scale.x).speed(0.001));
+                                ui.add(egui::DragValue::new(&mut scale.y).speed(0.001));
+                                ui.add(egui::DragValue::new(&mut scale.z).speed(0.001));
+
+                                ui.horizontal(|ui| {
+                                        ui.checkbox(&mut self.visible, "Visible");
+                                        ui.label("Layer");
+                                        ui.add(egui::DragValue::new(&mut self.layer));
+                                });
+
+                                if let Some(skin) = &mut self.skin
+                                {
+                                        skin.ui(ui);
+                                }
+
+                                if !self.morph_weights.is_empty()
+                                {
+                                        ui.label("Morph targets");
+
+                                        for (name, weight) in &mut self.morph_weights
+                                        {
+                                                self.morphs_changed |= ui
+                                                        .add(egui::Slider::new(weight, 0.0..=1.0)
+                                                                .text(name.as_str()))
+                                                        .changed();
+                                        }
+                                }
+
+                                self.metadata.ui(ui);
+
+                                self.debug.ui(ui);
                         });
         }
 
@@ -514,9 +1249,8 @@ impl Model
                         Rad::from(Deg(self.rotation_speeds[2] * delta_seconds)),
                 );
 
-                self.rotation = (z_rot * y_rot * x_rot * self.rotation).normalize();
-
-                self.update_euler_from_quat();
+                self.transform.rotation =
+                        (z_rot * y_rot * x_rot * self.transform.rotation).normalize();
         }
 
         pub fn toggle_spin(&mut self)
@@ -524,48 +1258,376 @@ impl Model
                 self.is_spinning = !self.is_spinning;
         }
 
-        fn update_euler_from_quat(&mut self)
+        pub fn set_rotation_speed(
+                &mut self,
+                axis: usize,
+                speed_deg_per_sec: f32,
+        )
+        {
+                if axis < 3
+                {
+                        self.rotation_speeds[axis] = speed_deg_per_sec;
+                }
+        }
+
+        /// Changes how material `slot` samples its base color texture.
+        pub fn set_sampler(
+                &mut self,
+                device: &wgpu::Device,
+                queue: &wgpu::Queue,
+                slot: usize,
+                sampler: SamplerDesc,
+        )
+        {
+                let Some(material) = self.materials.get_mut(slot)
+                else
+                {
+                        log::warn!("set_sampler: no material in slot {}", slot);
+                        return;
+                };
+
+                material.sampler = sampler;
+                material.base_color_texture.sampler = sampler.create_sampler(device);
+
+                queue.write_buffer(
+                        &material.properties_buffer,
+                        0,
+                        bytemuck::cast_slice(&[material.properties()]),
+                );
+
+                material.material_bind_group = material
+                        .create_bind_group(device, &create_material_bind_group_layout(device));
+        }
+
+        /// Replaces the streamed base color texture of material `slot` with
+        /// one starting at mip `level` of the source, see
+        /// [`crate::streaming`]. Returns false when the material isn't
+        /// streamed.
+        pub fn stream_material(
+                &mut self,
+                device: &wgpu::Device,
+                queue: &wgpu::Queue,
+                slot: usize,
+                level: u32,
+        ) -> bool
+        {
+                let Some(material) = self.materials.get_mut(slot)
+                else
+                {
+                        return false;
+                };
+
+                let Some(stream) = &mut material.stream
+                else
+                {
+                        return false;
+                };
+
+                stream.resident_level = level.min(stream.levels() - 1);
+
+                material.base_color_texture = crate::texture::Texture {
+                        sampler: material.sampler.create_sampler(device),
+                        ..stream.upload(
+                                device,
+                                queue,
+                                stream.resident_level,
+                                &format!("{} Streamed", material.name),
+                        )
+                };
+
+                material.material_bind_group = material
+                        .create_bind_group(device, &create_material_bind_group_layout(device));
+
+                true
+        }
+
+        /// Bounds of every mesh in model space.
+        pub fn bounds(&self) -> Option<Aabb>
+        {
+                self.meshes.iter().map(|m| m.bounds).reduce(Aabb::union)
+        }
+
+        /// Estimated bytes on the GPU, meshes and textures. Textures shared
+        /// between materials are counted once per material.
+        pub fn gpu_memory(&self) -> u64
+        {
+                let meshes: u64 = self.meshes.iter().map(|m| m.gpu_memory()).sum();
+
+                let textures: u64 = self
+                        .materials
+                        .iter()
+                        .flat_map(|m| {
+                                [
+                                        Some(&m.base_color_texture),
+                                        m.normal_texture.as_ref(),
+                                        m.metallic_roughness_texture.as_ref(),
+                                ]
+                        })
+                        .flatten()
+                        .map(|t| t.gpu_memory())
+                        .sum();
+
+                meshes + textures
+        }
+
+        /// Triangles of all meshes.
+        pub fn triangle_count(&self) -> u32
+        {
+                self.meshes.iter().map(|m| m.num_elements / 3).sum()
+        }
+
+        /// Bounds in world space, see [`Aabb::transform`].
+        pub fn world_bounds(&self) -> Option<Aabb>
+        {
+                self.bounds()
+                        .map(|b| b.transform(&self.calculate_transform()))
+        }
+
+        /// Bounds in world space, rotated with the model so they stay
+        /// tight, see [`Obb::from_aabb`].
+        pub fn world_obb(&self) -> Option<Obb>
         {
-                // Convert quaternion to Euler angles
-                let euler = Euler::from(self.rotation);
+                self.bounds()
+                        .map(|b| Obb::from_aabb(&b, &self.calculate_transform()))
+        }
 
-                // Convert radians to degrees and store
-                self.euler_angles = [
-                        euler.x.0.to_degrees(),
-                        euler.y.0.to_degrees(),
-                        euler.z.0.to_degrees(),
-                ];
+        /// Names and weights of the morph targets of every mesh, see
+        /// [`crate::geometry::morph`].
+        pub fn morph_weights(&self) -> &[(String, f32)]
+        {
+                &self.morph_weights
+        }
 
-                self.normalize_euler_angles();
+        pub fn morph_weight(
+                &self,
+                name: &str,
+        ) -> Option<f32>
+        {
+                self.morph_weights
+                        .iter()
+                        .find(|(n, _)| n == name)
+                        .map(|(_, w)| *w)
         }
 
-        fn normalize_euler_angles(&mut self)
+        /// Blends the morph target `name` in by `weight`, usually 0 to 1.
+        /// Returns false when the model has no such target.
+        pub fn set_morph_weight(
+                &mut self,
+                name: &str,
+                weight: f32,
+        ) -> bool
         {
-                for angle in &mut self.euler_angles
+                match self.morph_weights.iter_mut().find(|(n, _)| n == name)
                 {
-                        // Normalize to -180 to 180 range
-                        *angle = *angle % 360.0;
-                        if *angle > 180.0
+                        Some((_, w)) =>
                         {
-                                *angle -= 360.0;
+                                self.morphs_changed |= *w != weight;
+                                *w = weight;
+                                true
                         }
-                        else if *angle < -180.0
+                        None => false,
+                }
+        }
+
+        /// Uploads the morphed vertices when a weight changed since the last
+        /// call. Called by the engine before every frame.
+        pub fn update_morphs(
+                &mut self,
+                queue: &wgpu::Queue,
+        )
+        {
+                if !std::mem::take(&mut self.morphs_changed)
+                {
+                        return;
+                }
+
+                let weights = &self.morph_weights;
+                let weight = |name: &str| {
+                        weights.iter()
+                                .find(|(n, _)| n == name)
+                                .map_or(0.0, |(_, w)| *w)
+                };
+
+                for mesh in &mut self.meshes
+                {
+                        let Some(morph) = &mesh.morph
+                        else
                         {
-                                *angle += 360.0;
-                        }
+                                continue;
+                        };
+
+                        mesh.vertices = morph.apply(weight);
+                        mesh.revision += 1;
+                        mesh.barycentric_buffer = OnceLock::new();
+
+                        queue.write_buffer(
+                                &mesh.vertex_buffer,
+                                0,
+                                bytemuck::cast_slice(&mesh.vertices),
+                        );
                 }
         }
 
-        pub fn set_rotation_speed(
+        /// The model with the node hierarchy its meshes were imported with,
+        /// the [`Mesh::node`] indices pointing into `nodes`.
+        pub fn with_nodes(
+                mut self,
+                nodes: Vec<Node>,
+        ) -> Self
+        {
+                self.nodes = nodes;
+                self
+        }
+
+        pub fn with_billboard(
+                mut self,
+                billboard: Option<Billboard>,
+        ) -> Self
+        {
+                self.billboard = billboard;
+                self
+        }
+
+        pub fn with_metadata(
+                mut self,
+                metadata: Metadata,
+        ) -> Self
+        {
+                self.metadata = metadata;
+                self
+        }
+
+        pub fn with_colliders(
+                mut self,
+                colliders: Vec<Collider>,
+        ) -> Self
+        {
+                self.colliders = colliders;
+                self
+        }
+
+        /// The colliders where the model is in the world.
+        pub fn world_colliders(&self) -> Vec<Collider>
+        {
+                let matrix = self.calculate_transform();
+
+                self.colliders.iter().map(|c| c.transformed(&matrix)).collect()
+        }
+
+        /// The nodes the model was imported with, see
+        /// [`crate::geometry::node`].
+        pub fn nodes(&self) -> &[Node]
+        {
+                &self.nodes
+        }
+
+        /// The first node called `name`.
+        pub fn node(
+                &self,
+                name: &str,
+        ) -> Option<&Node>
+        {
+                self.nodes.iter().find(|n| n.name == name)
+        }
+
+        /// The first node called `name`, to move it and the meshes below it
+        /// before the next frame.
+        pub fn node_mut(
                 &mut self,
-                axis: usize,
-                speed_deg_per_sec: f32,
+                name: &str,
+        ) -> Option<&mut Node>
+        {
+                let node = self.nodes.iter_mut().find(|n| n.name == name)?;
+                self.nodes_changed = true;
+                Some(node)
+        }
+
+        /// The nodes with the property `key` in their `extras`, e.g. spawn
+        /// points or triggers placed in the scene, see
+        /// [`crate::resources::extras`].
+        pub fn nodes_with_extra<'a>(
+                &'a self,
+                key: &'a str,
+        ) -> impl Iterator<Item = &'a Node>
+        {
+                self.nodes.iter().filter(move |n| n.extras.get(key).is_some())
+        }
+
+        /// Node `name` in model space, its parents applied.
+        pub fn node_transform(
+                &self,
+                name: &str,
+        ) -> Option<Transform>
+        {
+                let index = self.nodes.iter().position(|n| n.name == name)?;
+                let matrices = node::model_matrices(&self.nodes);
+
+                Some(Transform::from_matrix(&matrices[index]))
+        }
+
+        /// Places the meshes at their nodes when one was borrowed mutably
+        /// since the last call. Called by the engine before every frame.
+        pub fn update_nodes(
+                &mut self,
+                queue: &wgpu::Queue,
         )
         {
-                if axis < 3
+                if !std::mem::take(&mut self.nodes_changed)
                 {
-                        self.rotation_speeds[axis] = speed_deg_per_sec;
+                        return;
                 }
+
+                let matrices = node::model_matrices(&self.nodes);
+
+                // The import options are applied to the roots, nothing is
+                // left between the nodes and the meshes.
+                for mesh in &mut self.meshes
+                {
+                        let Some(matrix) = mesh.node.and_then(|n| matrices.get(n))
+                        else
+                        {
+                                continue;
+                        };
+
+                        mesh.transform = *matrix;
+                        mesh.update_bounds();
+
+                        let transform_data: [[f32; 4]; 4] = mesh.transform.into();
+                        queue.write_buffer(
+                                &mesh.transform_buffer,
+                                0,
+                                bytemuck::cast_slice(&transform_data),
+                        );
+                }
+        }
+
+        /// Socket `name` in model space, one of [`Model::sockets`] or else
+        /// the node or the mesh called `name`.
+        pub fn socket(
+                &self,
+                name: &str,
+        ) -> Option<Transform>
+        {
+                self.sockets
+                        .get(name)
+                        .copied()
+                        .or_else(|| self.node_transform(name))
+                        .or_else(|| {
+                                self.meshes
+                                        .iter()
+                                        .find(|m| m.name == name)
+                                        .map(|m| Transform::from_matrix(&m.transform))
+                        })
+        }
+
+        /// Socket `name` in world space, posed with the model.
+        pub fn world_socket(
+                &self,
+                name: &str,
+        ) -> Option<Transform>
+        {
+                self.socket(name)
+                        .map(|socket| self.transform * self.pose * socket)
         }
 
         // Get Euler angles from quaternion (for demonstration)
@@ -582,6 +1644,12 @@ pub trait DrawModel<'a>
                 mesh: &'a Mesh,
                 instances: Range<u32>,
         );
+        /// Draws the mesh's [`Mesh::barycentric_buffer`].
+        fn draw_mesh_barycentric(
+                &mut self,
+                mesh: &'a Mesh,
+                device: &wgpu::Device,
+        );
 }
 impl<'a, 'b> DrawModel<'b> for wgpu::RenderPass<'a>
 where
@@ -605,4 +1673,13 @@ where
                 self.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
                 self.draw_indexed(0..mesh.num_elements, 0, instances);
         }
+        fn draw_mesh_barycentric(
+                &mut self,
+                mesh: &'b Mesh,
+                device: &wgpu::Device,
+        )
+        {
+                self.set_vertex_buffer(0, mesh.barycentric_buffer(device).slice(..));
+                self.draw(0..mesh.num_elements, 0..1);
+        }
 }
@@ -0,0 +1,310 @@
+//! WebSocket networking, enabled with the `net` feature.
+//!
+//! [`NetSystem`] is owned by the [`Engine`] and manages any number of client
+//! connections, opened with [`Engine::connect`]. Messages are any
+//! `serde` type, encoded with `bincode` and sent as one binary WebSocket
+//! message each, so both sides only need to agree on the type.
+//!
+//! Backends:
+//! - `wasm32`: the browser's `WebSocket`.
+//! - Native: `tungstenite` on a background thread per connection. Only `ws://`
+//!   URLs are supported, there is no TLS.
+//!
+//! Connection changes are published as [`NetEvent`]s on the engine's
+//! [`EventBus`](crate::events::EventBus), messages are polled with
+//! [`NetSystem::receive`].
+
+use crate::engine::Engine;
+use anyhow::Result;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::collections::{HashMap, VecDeque};
+
+#[cfg(not(target_arch = "wasm32"))]
+mod native;
+#[cfg(target_arch = "wasm32")]
+mod web;
+
+/// Identifies a connection opened with [`Engine::connect`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ConnectionId(pub u64);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState
+{
+        Connecting,
+        Open,
+        Closed,
+}
+
+/// Published on the event bus when a connection changes state.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NetEvent
+{
+        Connected(ConnectionId),
+        Disconnected
+        {
+                id: ConnectionId,
+                reason: String,
+        },
+        /// The connection failed, it's followed by
+        /// [`NetEvent::Disconnected`].
+        Error
+        {
+                id: ConnectionId,
+                message: String,
+        },
+}
+
+/// What a transport reports when polled.
+#[derive(Debug)]
+pub(crate) enum Incoming
+{
+        Open,
+        Message(Vec<u8>),
+        Closed(String),
+        Error(String),
+}
+
+/// Platform specific WebSocket.
+pub(crate) trait Transport
+{
+        /// Sends one binary message, queued until the socket is open.
+        fn send(
+                &mut self,
+                data: Vec<u8>,
+        );
+
+        fn poll(&mut self) -> Vec<Incoming>;
+
+        fn close(&mut self);
+}
+
+struct Connection
+{
+        url: String,
+
+        state: ConnectionState,
+
+        transport: Box<dyn Transport>,
+
+        inbox: VecDeque<Vec<u8>>,
+}
+
+/// Engine-level WebSocket connections.
+#[derive(Default)]
+pub struct NetSystem
+{
+        connections: HashMap<ConnectionId, Connection>,
+
+        next_id: u64,
+}
+
+impl std::fmt::Debug for NetSystem
+{
+        fn fmt(
+                &self,
+                f: &mut std::fmt::Formatter<'_>,
+        ) -> std::fmt::Result
+        {
+                f.debug_map()
+                        .entries(
+                                self.connections
+                                        .iter()
+                                        .map(|(id, c)| (id, (&c.url, c.state))),
+                        )
+                        .finish()
+        }
+}
+
+impl NetSystem
+{
+        pub fn new() -> Self
+        {
+                Self::default()
+        }
+
+        /// Starts connecting to `url`, e.g. `ws://127.0.0.1:9001`.
+        pub fn connect(
+                &mut self,
+                url: &str,
+        ) -> Result<ConnectionId>
+        {
+                let id = ConnectionId(self.next_id);
+                self.next_id += 1;
+
+                #[cfg(not(target_arch = "wasm32"))]
+                let transport: Box<dyn Transport> = Box::new(native::NativeSocket::connect(url)?);
+
+                #[cfg(target_arch = "wasm32")]
+                let transport: Box<dyn Transport> =
+                        Box::new(web::WebSocketTransport::connect(url)?);
+
+                log::info!("Connecting to {} ({:?})", url, id);
+
+                self.connections.insert(
+                        id,
+                        Connection {
+                                url: url.to_string(),
+                                state: ConnectionState::Connecting,
+                                transport,
+                                inbox: VecDeque::new(),
+                        },
+                );
+
+                Ok(id)
+        }
+
+        /// Closes the connection, a [`NetEvent::Disconnected`] follows.
+        pub fn close(
+                &mut self,
+                id: ConnectionId,
+        )
+        {
+                if let Some(connection) = self.connections.get_mut(&id)
+                {
+                        connection.transport.close();
+                }
+        }
+
+        pub fn state(
+                &self,
+                id: ConnectionId,
+        ) -> Option<ConnectionState>
+        {
+                self.connections.get(&id).map(|c| c.state)
+        }
+
+        /// Encodes `message` and sends it, or queues it while connecting.
+        pub fn send<T: Serialize>(
+                &mut self,
+                id: ConnectionId,
+                message: &T,
+        ) -> Result<()>
+        {
+                let connection = self
+                        .connections
+                        .get_mut(&id)
+                        .ok_or_else(|| anyhow::anyhow!("Unknown connection {:?}", id))?;
+
+                if connection.state == ConnectionState::Closed
+                {
+                        anyhow::bail!("Connection to {} is closed", connection.url);
+                }
+
+                connection.transport.send(encode(message)?);
+
+                Ok(())
+        }
+
+        /// Takes every message received on the connection since the last
+        /// call. Messages that don't decode as `T` are logged and dropped.
+        pub fn receive<T: DeserializeOwned>(
+                &mut self,
+                id: ConnectionId,
+        ) -> Vec<T>
+        {
+                let connection = match self.connections.get_mut(&id)
+                {
+                        Some(c) => c,
+                        None => return Vec::new(),
+                };
+
+                connection
+                        .inbox
+                        .drain(..)
+                        .filter_map(|data| match decode(&data)
+                        {
+                                Ok(message) => Some(message),
+                                Err(e) =>
+                                {
+                                        log::warn!(
+                                                "Dropping message from {}: {e:#}",
+                                                connection.url
+                                        );
+                                        None
+                                }
+                        })
+                        .collect()
+        }
+
+        /// Polls every connection and publishes state changes on the event
+        /// bus. Closed connections are dropped once their messages were read.
+        ///
+        /// Called by the engine once per frame.
+        pub(crate) fn update(engine: &mut Engine)
+        {
+                for (id, connection) in &mut engine.net.connections
+                {
+                        for incoming in connection.transport.poll()
+                        {
+                                match incoming
+                                {
+                                        Incoming::Open =>
+                                        {
+                                                log::info!("Connected to {}", connection.url);
+
+                                                connection.state = ConnectionState::Open;
+                                                engine.events.publish(NetEvent::Connected(*id));
+                                        }
+                                        Incoming::Message(data) => connection.inbox.push_back(data),
+                                        Incoming::Closed(reason) =>
+                                        {
+                                                log::info!(
+                                                        "Disconnected from {}: {}",
+                                                        connection.url,
+                                                        reason
+                                                );
+
+                                                connection.state = ConnectionState::Closed;
+                                                engine.events.publish(NetEvent::Disconnected {
+                                                        id: *id,
+                                                        reason,
+                                                });
+                                        }
+                                        Incoming::Error(message) =>
+                                        {
+                                                log::warn!(
+                                                        "Connection to {} failed: {}",
+                                                        connection.url,
+                                                        message
+                                                );
+
+                                                engine.events.publish(NetEvent::Error {
+                                                        id: *id,
+                                                        message,
+                                                });
+                                        }
+                                }
+                        }
+                }
+
+                engine.net
+                        .connections
+                        .retain(|_, c| c.state != ConnectionState::Closed || !c.inbox.is_empty());
+        }
+}
+
+/// Encodes a message the way [`NetSystem::send`] does, e.g. for a server.
+pub fn encode<T: Serialize>(message: &T) -> Result<Vec<u8>>
+{
+        Ok(bincode::serialize(message)?)
+}
+
+/// Decodes a message encoded with [`encode`].
+pub fn decode<T: DeserializeOwned>(data: &[u8]) -> Result<T>
+{
+        Ok(bincode::deserialize(data)?)
+}
+
+impl Engine
+{
+        /// See [`NetSystem::connect`].
+        pub fn connect(
+                &mut self,
+                url: &str,
+        ) -> Result<ConnectionId>
+        {
+                self.net.connect(url)
+        }
+}
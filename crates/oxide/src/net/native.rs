@@ -0,0 +1,167 @@
+//! `tungstenite` transport, one blocking socket per background thread.
+
+use super::{Incoming, Transport};
+use anyhow::Result;
+use std::net::TcpStream;
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::time::Duration;
+use tungstenite::stream::MaybeTlsStream;
+use tungstenite::{Message, WebSocket};
+
+/// How long the socket thread blocks on a read before sending queued
+/// messages.
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+enum Outgoing
+{
+        Send(Vec<u8>),
+        Close,
+}
+
+pub struct NativeSocket
+{
+        outgoing: Sender<Outgoing>,
+
+        incoming: Receiver<Incoming>,
+}
+
+impl NativeSocket
+{
+        pub fn connect(url: &str) -> Result<Self>
+        {
+                let (outgoing, outgoing_rx) = mpsc::channel();
+                let (incoming_tx, incoming) = mpsc::channel();
+
+                let url = url.to_string();
+
+                std::thread::Builder::new()
+                        .name(format!("oxide-net {}", url))
+                        .spawn(move || {
+                                let reason = match Self::run(&url, &outgoing_rx, &incoming_tx)
+                                {
+                                        Ok(reason) => reason,
+                                        Err(e) =>
+                                        {
+                                                let _ = incoming_tx
+                                                        .send(Incoming::Error(format!("{e:#}")));
+                                                "error".to_string()
+                                        }
+                                };
+
+                                let _ = incoming_tx.send(Incoming::Closed(reason));
+                        })?;
+
+                Ok(Self {
+                        outgoing,
+                        incoming,
+                })
+        }
+
+        /// Runs the connection until it closes, returns the close reason.
+        fn run(
+                url: &str,
+                outgoing: &Receiver<Outgoing>,
+                incoming: &Sender<Incoming>,
+        ) -> Result<String>
+        {
+                let (mut socket, _) = tungstenite::connect(url)?;
+
+                if let MaybeTlsStream::Plain(stream) = socket.get_mut()
+                {
+                        stream.set_read_timeout(Some(POLL_INTERVAL))?;
+                        stream.set_nodelay(true)?;
+                }
+
+                let _ = incoming.send(Incoming::Open);
+
+                loop
+                {
+                        if let Some(reason) = Self::send_queued(&mut socket, outgoing)?
+                        {
+                                return Ok(reason);
+                        }
+
+                        match socket.read()
+                        {
+                                Ok(Message::Binary(data)) =>
+                                {
+                                        let _ = incoming.send(Incoming::Message(data.to_vec()));
+                                }
+                                Ok(Message::Close(frame)) =>
+                                {
+                                        return Ok(frame
+                                                .map(|f| f.reason.to_string())
+                                                .unwrap_or_else(|| "closed by peer".to_string()));
+                                }
+                                Ok(_) =>
+                                {}
+                                Err(tungstenite::Error::Io(e))
+                                        if matches!(
+                                                e.kind(),
+                                                std::io::ErrorKind::WouldBlock
+                                                        | std::io::ErrorKind::TimedOut
+                                        ) =>
+                                {}
+                                Err(tungstenite::Error::ConnectionClosed) =>
+                                {
+                                        return Ok("closed".to_string());
+                                }
+                                Err(e) => return Err(e.into()),
+                        }
+                }
+        }
+
+        /// Sends what the engine queued. Returns the close reason once the
+        /// engine closed the connection or dropped it.
+        fn send_queued(
+                socket: &mut WebSocket<MaybeTlsStream<TcpStream>>,
+                outgoing: &Receiver<Outgoing>,
+        ) -> Result<Option<String>>
+        {
+                loop
+                {
+                        match outgoing.try_recv()
+                        {
+                                Ok(Outgoing::Send(data)) =>
+                                {
+                                        socket.write(Message::Binary(data.into()))?;
+                                }
+                                Ok(Outgoing::Close) | Err(TryRecvError::Disconnected) =>
+                                {
+                                        // Best effort, the peer may already be gone.
+                                        let _ = socket.close(None);
+                                        let _ = socket.flush();
+
+                                        return Ok(Some("closed locally".to_string()));
+                                }
+                                Err(TryRecvError::Empty) =>
+                                {
+                                        socket.flush()?;
+
+                                        return Ok(None);
+                                }
+                        }
+                }
+        }
+}
+
+impl Transport for NativeSocket
+{
+        fn send(
+                &mut self,
+                data: Vec<u8>,
+        )
+        {
+                let _ = self.outgoing.send(Outgoing::Send(data));
+        }
+
+        fn poll(&mut self) -> Vec<Incoming>
+        {
+                self.incoming.try_iter().collect()
+        }
+
+        fn close(&mut self)
+        {
+                let _ = self.outgoing.send(Outgoing::Close);
+        }
+}
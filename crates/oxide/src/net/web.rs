@@ -0,0 +1,154 @@
+//! Browser `WebSocket` transport.
+
+use super::{Incoming, Transport};
+use anyhow::Result;
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::JsCast;
+use wasm_bindgen::prelude::Closure;
+use web_sys::{BinaryType, CloseEvent, Event, MessageEvent, WebSocket};
+
+pub struct WebSocketTransport
+{
+        socket: WebSocket,
+
+        /// Filled by the socket callbacks, drained by [`Transport::poll`].
+        incoming: Rc<RefCell<Vec<Incoming>>>,
+
+        /// Messages sent before the socket opened.
+        pending: Vec<Vec<u8>>,
+
+        // Kept alive for as long as the socket can call them.
+        _on_open: Closure<dyn FnMut(Event)>,
+        _on_message: Closure<dyn FnMut(MessageEvent)>,
+        _on_close: Closure<dyn FnMut(CloseEvent)>,
+        _on_error: Closure<dyn FnMut(Event)>,
+}
+
+impl WebSocketTransport
+{
+        pub fn connect(url: &str) -> Result<Self>
+        {
+                let socket = WebSocket::new(url)
+                        .map_err(|e| anyhow::anyhow!("Failed to open {}: {:?}", url, e))?;
+
+                socket.set_binary_type(BinaryType::Arraybuffer);
+
+                let incoming = Rc::new(RefCell::new(Vec::new()));
+
+                let queue = incoming.clone();
+                let on_open = Closure::<dyn FnMut(Event)>::new(move |_| {
+                        queue.borrow_mut().push(Incoming::Open);
+                });
+
+                let queue = incoming.clone();
+                let on_message = Closure::<dyn FnMut(MessageEvent)>::new(move |e: MessageEvent| {
+                        match e.data().dyn_into::<js_sys::ArrayBuffer>()
+                        {
+                                Ok(buffer) =>
+                                {
+                                        let data = js_sys::Uint8Array::new(&buffer).to_vec();
+
+                                        queue.borrow_mut().push(Incoming::Message(data));
+                                }
+                                Err(_) => log::warn!("Ignoring text WebSocket message"),
+                        }
+                });
+
+                let queue = incoming.clone();
+                let on_close = Closure::<dyn FnMut(CloseEvent)>::new(move |e: CloseEvent| {
+                        let reason = if e.reason().is_empty()
+                        {
+                                format!("code {}", e.code())
+                        }
+                        else
+                        {
+                                e.reason()
+                        };
+
+                        queue.borrow_mut().push(Incoming::Closed(reason));
+                });
+
+                // The browser doesn't say what went wrong, a close event
+                // follows.
+                let queue = incoming.clone();
+                let on_error = Closure::<dyn FnMut(Event)>::new(move |_| {
+                        queue.borrow_mut()
+                                .push(Incoming::Error("WebSocket error".to_string()));
+                });
+
+                socket.set_onopen(Some(on_open.as_ref().unchecked_ref()));
+                socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+                socket.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+                socket.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+
+                Ok(Self {
+                        socket,
+                        incoming,
+                        pending: Vec::new(),
+                        _on_open: on_open,
+                        _on_message: on_message,
+                        _on_close: on_close,
+                        _on_error: on_error,
+                })
+        }
+
+        fn write(
+                &self,
+                data: &[u8],
+        )
+        {
+                if let Err(e) = self.socket.send_with_u8_array(data)
+                {
+                        log::warn!("WebSocket send failed: {:?}", e);
+                }
+        }
+}
+
+impl Transport for WebSocketTransport
+{
+        fn send(
+                &mut self,
+                data: Vec<u8>,
+        )
+        {
+                match self.socket.ready_state()
+                {
+                        WebSocket::CONNECTING => self.pending.push(data),
+                        WebSocket::OPEN => self.write(&data),
+                        _ =>
+                        {}
+                }
+        }
+
+        fn poll(&mut self) -> Vec<Incoming>
+        {
+                if self.socket.ready_state() == WebSocket::OPEN
+                {
+                        for data in std::mem::take(&mut self.pending)
+                        {
+                                self.write(&data);
+                        }
+                }
+
+                std::mem::take(&mut *self.incoming.borrow_mut())
+        }
+
+        fn close(&mut self)
+        {
+                let _ = self.socket.close();
+        }
+}
+
+impl Drop for WebSocketTransport
+{
+        fn drop(&mut self)
+        {
+                self.socket.set_onopen(None);
+                self.socket.set_onmessage(None);
+                self.socket.set_onclose(None);
+                self.socket.set_onerror(None);
+
+                let _ = self.socket.close();
+        }
+}
@@ -0,0 +1,511 @@
+//! Reflection probes, small cubemaps of the scene around a point that
+//! shiny materials reflect.
+//!
+//! A probe is captured once the loading screen is gone, and again when
+//! asked with [`Engine::capture_reflection_probe`], e.g. after a cell
+//! loaded. The scene is drawn with the lighting of the moment and without
+//! fog, at most [`ReflectionProbes::captures_per_frame`] probes per frame.
+//!
+//! Every model reflects the smallest probe whose volume holds the center of
+//! its bounds. The reflection is projected onto the volume, a box for rooms
+//! and corridors or a sphere for open places, so it lines up with the walls
+//! around the model instead of looking infinitely far away, see
+//! [`crate::renderer::probe`]:
+//!
+//! ```ignore
+//! engine.add_reflection_probe(
+//!         "hall",
+//!         ReflectionProbe::boxed(Aabb::new(
+//!                 Point3::new(-10.0, 0.0, -20.0),
+//!                 Point3::new(10.0, 6.0, 20.0),
+//!         ))
+//!         .with_position(Point3::new(0.0, 2.0, 0.0)),
+//! );
+//! ```
+//!
+//! [`ReflectionEvent::Captured`] is published after every capture.
+
+use crate::camera::{CameraUniform, Projection, create_camera_bind_group_layout};
+use crate::engine::{Engine, EngineState};
+use crate::geometry::bounds::Aabb;
+use crate::model::Model;
+use crate::renderer::graph::BackgroundPass;
+use crate::renderer::probe::{ProbeMipPass, ProbeTexture, ProbeUniform, face_view};
+use crate::texture::Texture;
+use cgmath::{Deg, EuclideanSpace, MetricSpace, Point3};
+use std::collections::BTreeMap;
+use wgpu::util::DeviceExt;
+
+/// Published on the event bus, with the name of the probe.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReflectionEvent
+{
+        /// The probe's cubemap was drawn again.
+        Captured(String),
+}
+
+/// Where the reflections of a probe are projected onto and the models
+/// reflecting it are.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProbeVolume
+{
+        Box(Aabb),
+        /// A sphere around the probe's position with this radius.
+        Sphere(f32),
+}
+
+/// A placed probe, see [`crate::reflection`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReflectionProbe
+{
+        /// Where the scene is captured from.
+        pub position: Point3<f32>,
+        pub volume: ProbeVolume,
+        /// Strength of the reflections, 1 by default.
+        pub intensity: f32,
+        /// Width and height of a cube face in pixels, 128 by default.
+        pub resolution: u32,
+}
+
+impl ReflectionProbe
+{
+        /// A probe projecting onto `bounds`, captured from their center.
+        pub fn boxed(bounds: Aabb) -> Self
+        {
+                Self {
+                        position: bounds.center(),
+                        volume: ProbeVolume::Box(bounds),
+                        intensity: 1.0,
+                        resolution: 128,
+                }
+        }
+
+        /// A probe projecting onto the sphere of `radius` around `position`.
+        pub fn sphere(
+                position: Point3<f32>,
+                radius: f32,
+        ) -> Self
+        {
+                Self {
+                        position,
+                        volume: ProbeVolume::Sphere(radius),
+                        intensity: 1.0,
+                        resolution: 128,
+                }
+        }
+
+        /// Captures from `position`, e.g. eye height in a room, instead. A
+        /// sphere moves along.
+        pub fn with_position(
+                mut self,
+                position: Point3<f32>,
+        ) -> Self
+        {
+                self.position = position;
+                self
+        }
+
+        pub fn with_intensity(
+                mut self,
+                intensity: f32,
+        ) -> Self
+        {
+                self.intensity = intensity;
+                self
+        }
+
+        pub fn with_resolution(
+                mut self,
+                resolution: u32,
+        ) -> Self
+        {
+                self.resolution = resolution;
+                self
+        }
+
+        /// Whether `point` is inside the volume.
+        pub fn contains(
+                &self,
+                point: Point3<f32>,
+        ) -> bool
+        {
+                match self.volume
+                {
+                        ProbeVolume::Box(bounds) => bounds.contains_point(point),
+                        ProbeVolume::Sphere(radius) => self.position.distance(point) <= radius,
+                }
+        }
+
+        /// Size of the volume, smaller probes win where volumes overlap.
+        fn size(&self) -> f32
+        {
+                match self.volume
+                {
+                        ProbeVolume::Box(bounds) =>
+                        {
+                                let size = bounds.size();
+                                size.x * size.y * size.z
+                        }
+                        ProbeVolume::Sphere(radius) =>
+                        {
+                                radius.powi(3) * std::f32::consts::PI * 4.0 / 3.0
+                        }
+                }
+        }
+
+        fn uniform(&self) -> ProbeUniform
+        {
+                let (center, extents, shape) = match self.volume
+                {
+                        ProbeVolume::Box(bounds) => (bounds.center(), bounds.size() * 0.5, 0.0),
+                        ProbeVolume::Sphere(radius) => (self.position, [radius; 3].into(), 1.0),
+                };
+
+                ProbeUniform {
+                        position: self.position.to_vec().extend(self.intensity).into(),
+                        center: center.to_vec().extend(shape).into(),
+                        extents: extents.extend(0.0).into(),
+                }
+        }
+}
+
+#[derive(Debug)]
+struct Probe
+{
+        probe: ReflectionProbe,
+
+        /// `None` until the first capture.
+        texture: Option<ProbeTexture>,
+
+        /// Captured with the next frames.
+        stale: bool,
+}
+
+/// The probes and their cubemaps, owned by the [`Engine`].
+#[derive(Debug)]
+pub struct ReflectionProbes
+{
+        /// Probes captured in one frame at most, capturing draws the scene six
+        /// times.
+        pub captures_per_frame: usize,
+
+        probes: BTreeMap<String, Probe>,
+
+        /// Created with the first capture.
+        mip_pass: Option<ProbeMipPass>,
+
+        /// Models may reflect a probe.
+        assigned: bool,
+}
+
+impl Default for ReflectionProbes
+{
+        fn default() -> Self
+        {
+                Self {
+                        captures_per_frame: 1,
+                        probes: BTreeMap::new(),
+                        mip_pass: None,
+                        assigned: false,
+                }
+        }
+}
+
+impl ReflectionProbes
+{
+        pub fn new() -> Self
+        {
+                Self::default()
+        }
+
+        pub fn probe(
+                &self,
+                name: &str,
+        ) -> Option<&ReflectionProbe>
+        {
+                self.probes.get(name).map(|p| &p.probe)
+        }
+
+        /// The probes by name.
+        pub fn probes(&self) -> impl Iterator<Item = (&str, &ReflectionProbe)>
+        {
+                self.probes
+                        .iter()
+                        .map(|(name, p)| (name.as_str(), &p.probe))
+        }
+
+        /// Whether the probe `name` waits for its capture.
+        pub fn is_stale(
+                &self,
+                name: &str,
+        ) -> bool
+        {
+                self.probes.get(name).is_some_and(|p| p.stale)
+        }
+
+        /// Captures the stale probes and hands every model the probe it
+        /// reflects.
+        ///
+        /// Called by the engine once per frame, after the loading screen.
+        pub(crate) fn update(engine: &mut Engine)
+        {
+                if engine.loading.is_active()
+                {
+                        return;
+                }
+
+                let Some(state) = engine.state.as_mut()
+                else
+                {
+                        return;
+                };
+
+                let probes = &mut engine.reflection_probes;
+
+                if probes.probes.is_empty() && !probes.assigned
+                {
+                        return;
+                }
+
+                let format = state.surface_manager.render_format();
+
+                // The cubemaps have the format of the pipelines drawing them.
+                for probe in probes.probes.values_mut()
+                {
+                        if probe.texture.as_ref().is_some_and(|t| t.format() != format)
+                        {
+                                probe.stale = true;
+                        }
+                }
+
+                let stale: Vec<String> = probes
+                        .probes
+                        .iter()
+                        .filter(|(_, p)| p.stale)
+                        .map(|(name, _)| name.clone())
+                        .take(probes.captures_per_frame)
+                        .collect();
+
+                for name in &stale
+                {
+                        let probe = probes.probes.get_mut(name).unwrap();
+
+                        let texture = match probe.texture.take()
+                        {
+                                Some(texture)
+                                        if texture.format() == format
+                                                && texture.resolution()
+                                                        == probe.probe.resolution.max(1) =>
+                                {
+                                        texture
+                                }
+                                _ => ProbeTexture::new(
+                                        &state.device,
+                                        format,
+                                        probe.probe.resolution,
+                                ),
+                        };
+
+                        let mip_pass = match &mut probes.mip_pass
+                        {
+                                Some(pass) if pass.format() == format => pass,
+                                pass => pass.insert(ProbeMipPass::new(&state.device, format)),
+                        };
+
+                        state.capture_probe(&probe.probe, &texture, mip_pass);
+
+                        log::info!("Captured reflection probe {}", name);
+
+                        probe.texture = Some(texture);
+                        probe.stale = false;
+                }
+
+                let volumes: Vec<(&ReflectionProbe, &ProbeTexture)> = probes
+                        .probes
+                        .values()
+                        .filter_map(|p| Some((&p.probe, p.texture.as_ref()?)))
+                        .collect();
+
+                for model in state.models.values_mut()
+                {
+                        let center = model
+                                .world_bounds()
+                                .map_or(model.transform.position, |b| b.center());
+
+                        model.reflection = volumes
+                                .iter()
+                                .filter(|(probe, _)| probe.contains(center))
+                                .min_by(|a, b| a.0.size().total_cmp(&b.0.size()))
+                                .map(|(_, texture)| (*texture).clone());
+                }
+
+                probes.assigned = !volumes.is_empty();
+
+                for name in stale
+                {
+                        engine.events.publish(ReflectionEvent::Captured(name));
+                }
+        }
+}
+
+impl EngineState
+{
+        /// Draws the visible models around `probe` into the faces of
+        /// `texture` and filters its mip levels.
+        fn capture_probe(
+                &mut self,
+                probe: &ReflectionProbe,
+                texture: &ProbeTexture,
+                mip_pass: &ProbeMipPass,
+        )
+        {
+                let clear = self
+                        .render_graph
+                        .pass_mut::<BackgroundPass>()
+                        .map_or(wgpu::Color::BLACK, |pass| pass.color());
+
+                let depth_texture = Texture::create_depth_texture(
+                        &self.device,
+                        &wgpu::SurfaceConfiguration {
+                                width: texture.resolution(),
+                                height: texture.resolution(),
+                                ..self.surface_manager.configuration.clone()
+                        },
+                        "Reflection Probe Depth",
+                );
+
+                let mut encoder =
+                        self.device
+                                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                                        label: Some("Reflection Probe Encoder"),
+                                });
+
+                let models: Vec<&Model> = self.models.values().filter(|m| m.visible).collect();
+
+                for face in 0..6
+                {
+                        let camera = self.probe_camera(probe.position, face);
+
+                        self.draw_offscreen(
+                                &models,
+                                &camera,
+                                &texture.face_view(face, 0),
+                                &depth_texture,
+                                clear,
+                                &mut encoder,
+                        );
+                }
+
+                mip_pass.record(&self.device, &mut encoder, texture);
+                texture.write_uniform(&self.queue, &probe.uniform());
+
+                self.queue.submit(std::iter::once(encoder.finish()));
+        }
+
+        /// The camera drawing cube face `face` from `position`. Exposure and
+        /// fog are left to the frame reflecting the probe.
+        fn probe_camera(
+                &self,
+                position: Point3<f32>,
+                face: usize,
+        ) -> wgpu::BindGroup
+        {
+                let mut projection = Projection::new(
+                        Deg(90.0),
+                        self.camera.projection.znear,
+                        self.camera.projection.zfar,
+                );
+                projection.reversed_z = self.camera.projection.reversed_z;
+
+                let mut uniform = CameraUniform::new();
+                uniform.view_position = position.to_homogeneous().into();
+                uniform.view_proj = (projection.calc_matrix() * face_view(position, face)).into();
+                uniform.environment = self.camera.uniform.environment;
+                uniform.environment.fog[3] = 0.0;
+                uniform.environment.exposure[0] = 1.0;
+
+                let buffer = self
+                        .device
+                        .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                                label: Some("Reflection Probe Camera Buffer"),
+                                contents: bytemuck::cast_slice(&[uniform]),
+                                usage: wgpu::BufferUsages::UNIFORM,
+                        });
+
+                self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                        layout: &create_camera_bind_group_layout(&self.device),
+                        entries: &[wgpu::BindGroupEntry {
+                                binding: 0,
+                                resource: buffer.as_entire_binding(),
+                        }],
+                        label: Some("reflection_probe_camera_bind_group"),
+                })
+        }
+}
+
+impl Engine
+{
+        /// Adds a probe, replacing any probe named `name`, captured with the
+        /// next frames, see [`crate::reflection`].
+        pub fn add_reflection_probe(
+                &mut self,
+                name: impl Into<String>,
+                probe: ReflectionProbe,
+        )
+        {
+                self.reflection_probes.probes.insert(
+                        name.into(),
+                        Probe {
+                                probe,
+                                texture: None,
+                                stale: true,
+                        },
+                );
+        }
+
+        /// Removes the probe `name`, returns false when there's no such
+        /// probe. Its models reflect the next probe around them or none.
+        pub fn remove_reflection_probe(
+                &mut self,
+                name: &str,
+        ) -> bool
+        {
+                self.reflection_probes.probes.remove(name).is_some()
+        }
+
+        pub fn reflection_probe(
+                &self,
+                name: &str,
+        ) -> Option<&ReflectionProbe>
+        {
+                self.reflection_probes.probe(name)
+        }
+
+        /// Captures the probe `name` again with the next frames, e.g. after
+        /// the scene around it changed. Returns false when there's no such
+        /// probe.
+        pub fn capture_reflection_probe(
+                &mut self,
+                name: &str,
+        ) -> bool
+        {
+                match self.reflection_probes.probes.get_mut(name)
+                {
+                        Some(probe) =>
+                        {
+                                probe.stale = true;
+                                true
+                        }
+                        None => false,
+                }
+        }
+
+        /// Captures every probe again with the next frames.
+        pub fn capture_reflection_probes(&mut self)
+        {
+                for probe in self.reflection_probes.probes.values_mut()
+                {
+                        probe.stale = true;
+                }
+        }
+}
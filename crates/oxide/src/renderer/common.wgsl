@@ -0,0 +1,192 @@
+// Declarations, the vertex stage and the lighting shared by `shader.wgsl`
+// and custom shaders, see `custom.rs`. Included by the first and prepended
+// to the others.
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+    @location(2) normal: vec3<f32>,
+};
+
+struct VertexOutput {
+    // Invariant so the depth pre-pass and the geometry pass get the same
+    // depth, see `DepthPrePass`.
+    @builtin(position) @invariant clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) world_position: vec3<f32>,
+    @location(2) world_normal: vec3<f32>,
+};
+
+struct Environment {
+    sun_direction: vec4<f32>,
+    sun_color: vec4<f32>,
+    ambient: vec4<f32>,
+    // Fog color, with the density in `w`.
+    fog: vec4<f32>,
+    // Exposure in `x`.
+    exposure: vec4<f32>,
+};
+
+struct CameraUniform {
+    view_pos: vec4<f32>,
+    view_proj: mat4x4<f32>,
+    environment: Environment,
+};
+
+struct MeshTransform {
+    model: mat4x4<f32>,
+};
+
+struct ModelTransform {
+    model: mat4x4<f32>,
+};
+
+struct MaterialProperties {
+    base_color_factor: vec4<f32>,
+    metallic_factor: f32,
+    roughness_factor: f32,
+    lod_bias: f32,
+    // Padding to meet alignment requirements
+    _padding: f32,
+};
+
+@group(0) @binding(0) var<uniform> camera: CameraUniform;
+#ifdef CULLED
+// The transforms of every draw of the frame, by instance index, for meshes
+// drawn together from the shared buffers, see `culling.rs`.
+struct DrawTransform {
+    mesh: mat4x4<f32>,
+    model: mat4x4<f32>,
+};
+
+@group(1) @binding(0) var<storage, read> draw_transforms: array<DrawTransform>;
+#else
+@group(1) @binding(0) var<uniform> transform: MeshTransform;
+#endif
+@group(2) @binding(0) var base_color_texture: texture_2d<f32>;
+@group(2) @binding(1) var base_color_sampler: sampler;
+@group(2) @binding(2) var<uniform> material_props: MaterialProperties;
+// The base color texture again when the material has no normal map.
+@group(2) @binding(3) var normal_texture: texture_2d<f32>;
+@group(2) @binding(4) var normal_sampler: sampler;
+#ifndef CULLED
+@group(3) @binding(0) var<uniform> model_transform: ModelTransform;
+#endif
+
+#ifdef SKINNED
+// The joints moving a vertex, see `skin.rs`.
+struct SkinInput {
+    @location(3) joints: vec4<u32>,
+    @location(4) weights: vec4<f32>,
+};
+
+// The joint palette, `MAX_JOINTS` long. With dual quaternion skinning the
+// real and dual part of a joint are the first two columns.
+@group(3) @binding(1) var<uniform> joints: array<mat4x4<f32>, 256>;
+
+#ifdef DUAL_QUATERNION_SKINNING
+
+// Blends the joints as dual quaternions, keeping the volume around
+// twisting joints.
+fn skin_matrix(skin: SkinInput) -> mat4x4<f32> {
+    let first = joints[skin.joints.x][0];
+    var real = vec4<f32>(0.0);
+    var dual = vec4<f32>(0.0);
+
+    for (var i = 0u; i < 4u; i++) {
+        let joint = joints[skin.joints[i]];
+        // Along the shorter arc, q and -q are the same rotation.
+        let weight = select(skin.weights[i], -skin.weights[i], dot(joint[0], first) < 0.0);
+
+        real += joint[0] * weight;
+        dual += joint[1] * weight;
+    }
+
+    let norm = length(real);
+    real /= norm;
+    dual /= norm;
+
+    let r = real.xyz;
+    let w = real.w;
+    let translation = 2.0 * (w * dual.xyz - dual.w * r + cross(r, dual.xyz));
+
+    return mat4x4<f32>(
+        vec4<f32>(1.0 - 2.0 * (r.y * r.y + r.z * r.z), 2.0 * (r.x * r.y + w * r.z), 2.0 * (r.x * r.z - w * r.y), 0.0),
+        vec4<f32>(2.0 * (r.x * r.y - w * r.z), 1.0 - 2.0 * (r.x * r.x + r.z * r.z), 2.0 * (r.y * r.z + w * r.x), 0.0),
+        vec4<f32>(2.0 * (r.x * r.z + w * r.y), 2.0 * (r.y * r.z - w * r.x), 1.0 - 2.0 * (r.x * r.x + r.y * r.y), 0.0),
+        vec4<f32>(translation, 1.0),
+    );
+}
+#else
+// Blends the joint matrices.
+fn skin_matrix(skin: SkinInput) -> mat4x4<f32> {
+    return joints[skin.joints.x] * skin.weights.x
+        + joints[skin.joints.y] * skin.weights.y
+        + joints[skin.joints.z] * skin.weights.z
+        + joints[skin.joints.w] * skin.weights.w;
+}
+#endif
+#endif
+
+@vertex
+fn vs_main(
+    model: VertexInput,
+#ifdef SKINNED
+    skin: SkinInput,
+#endif
+#ifdef CULLED
+    @builtin(instance_index) instance: u32,
+#endif
+) -> VertexOutput {
+    var out: VertexOutput;
+
+#ifdef CULLED
+    let draw = draw_transforms[instance];
+    let mesh_transform = draw.mesh;
+    let model_matrix = draw.model;
+#else
+#ifdef SKINNED
+    let mesh_transform = transform.model * skin_matrix(skin);
+#else
+    let mesh_transform = transform.model;
+#endif
+    let model_matrix = model_transform.model;
+#endif
+
+    let world_position = mesh_transform * vec4<f32>(model.position, 1.0);
+    let model_position = model_matrix * world_position;
+    out.clip_position = camera.view_proj * model_position;
+    out.tex_coords = model.tex_coords;
+    out.world_position = model_position.xyz;
+
+    // Fine for uniform scales, which is what models use.
+    let normal = model_matrix * mesh_transform * vec4<f32>(model.normal, 0.0);
+    out.world_normal = normal.xyz;
+
+    return out;
+}
+
+// Sun and ambient light of the environment.
+fn environment_light(color: vec3<f32>, world_normal: vec3<f32>) -> vec3<f32> {
+    let environment = camera.environment;
+
+    let normal = normalize(world_normal);
+    let diffuse = max(dot(normal, environment.sun_direction.xyz), 0.0);
+
+    return color * (environment.ambient.rgb + environment.sun_color.rgb * diffuse);
+}
+
+// Fog and exposure of the environment over a lit color.
+fn environment_fog(lit: vec3<f32>, world_position: vec3<f32>) -> vec3<f32> {
+    let environment = camera.environment;
+
+    let distance = length(world_position - camera.view_pos.xyz);
+    let fog = 1.0 - exp(-environment.fog.w * distance);
+
+    return mix(lit, environment.fog.rgb, fog) * environment.exposure.x;
+}
+
+// Sun and ambient light, fog and exposure of the environment.
+fn shade(color: vec3<f32>, world_position: vec3<f32>, world_normal: vec3<f32>) -> vec3<f32> {
+    return environment_fog(environment_light(color, world_normal), world_position);
+}
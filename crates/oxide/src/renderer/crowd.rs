@@ -0,0 +1,868 @@
+//! Crowds of one skinned model drawn instanced, for background characters
+//! and stress tests.
+//!
+//! Animating every member of a crowd on the CPU doesn't scale, so a crowd
+//! plays a [`BakedAnimation`] instead: the joint palette of every frame of
+//! an animation stored in a texture, read by the vertex shader. Members
+//! only differ in where they stand and how far into the animation they are:
+//!
+//! ```ignore
+//! let skin = engine.state.as_ref().unwrap().models["villager"].skin.clone().unwrap();
+//! let walk = BakedAnimation::bake(&skin, walk_clip.duration(), 30.0, |skin, time| {
+//!         walk_clip.pose(skin, time)
+//! });
+//!
+//! let members = (0..500)
+//!         .map(|i| CrowdInstance::new(Transform::from_position(spots[i])).with_time_offset(i as f32 * 0.37))
+//!         .collect();
+//!
+//! engine.add_crowd("villagers", Crowd::new("villager", walk).with_instances(members));
+//! ```
+//!
+//! Baked animations always loop and blend joints linearly. The crowd model
+//! is drawn by the crowd only, the geometry pass skips it, and only its
+//! skinned meshes.
+//!
+//! With the `gpu-culling` feature the members outside of the view are
+//! culled on the GPU and not drawn, see [`crate::renderer::culling`].
+
+#[cfg(feature = "runtime")]
+use crate::engine::Engine;
+use crate::geometry::mesh::Mesh;
+use crate::geometry::skin::{MAX_JOINTS, Skin};
+use crate::model::{Model, Vertex};
+#[cfg(feature = "gpu-culling")]
+use crate::renderer::culling::{CrowdCulling, GpuCulling};
+use crate::renderer::graph::{Attachment, AttachmentLoad, DEPTH, RenderPass, SURFACE};
+use crate::renderer::pipeline::{PipelineKind, PipelineManager};
+use crate::texture::Texture;
+use crate::transform::Transform;
+use std::any::Any;
+use std::collections::{BTreeMap, HashMap};
+use wgpu::util::DeviceExt;
+
+/// Texels of a row of the animation texture are padded to a multiple of
+/// this, rows are copied in 256 byte steps.
+const ROW_TEXELS: u32 = 256 / 16;
+
+/// The joint palette of every frame of an animation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BakedAnimation
+{
+        /// Frames per second the animation was baked at.
+        pub fps: f32,
+        /// Joints per frame.
+        pub joints: u32,
+        /// Palette matrices of every frame, frame by frame.
+        pub frames: Vec<[[f32; 4]; 4]>,
+}
+
+impl BakedAnimation
+{
+        /// Bakes `duration` seconds at `fps` frames per second, `pose`
+        /// posing `skin` at the time of every frame.
+        pub fn bake(
+                skin: &Skin,
+                duration: f32,
+                fps: f32,
+                mut pose: impl FnMut(&mut Skin, f32),
+        ) -> Self
+        {
+                let mut skin = skin.clone();
+                let count = ((duration * fps).ceil() as usize).max(1);
+                let mut frames = Vec::with_capacity(count * skin.joints.len());
+
+                for frame in 0..count
+                {
+                        pose(&mut skin, frame as f32 / fps);
+                        frames.extend(skin.palette().into_iter().map(Into::<[[f32; 4]; 4]>::into));
+                }
+
+                Self {
+                        fps,
+                        joints: skin.joints.len().min(MAX_JOINTS) as u32,
+                        frames,
+                }
+        }
+
+        pub fn frame_count(&self) -> u32
+        {
+                self.frames.len() as u32 / self.joints.max(1)
+        }
+
+        /// Seconds until the animation starts over.
+        pub fn duration(&self) -> f32
+        {
+                self.frame_count() as f32 / self.fps
+        }
+
+        /// Width of the texture in texels, four per joint.
+        fn width(&self) -> u32
+        {
+                (self.joints * 4).next_multiple_of(ROW_TEXELS)
+        }
+
+        /// One row per frame, a joint's matrix being four texels, one per
+        /// column.
+        fn texels(&self) -> Vec<[f32; 4]>
+        {
+                let mut texels = vec![[0.0; 4]; (self.width() * self.frame_count()) as usize];
+
+                for (frame, palette) in self.frames.chunks(self.joints.max(1) as usize).enumerate()
+                {
+                        let row = frame * self.width() as usize;
+
+                        for (joint, matrix) in palette.iter().enumerate()
+                        {
+                                texels[row + joint * 4..row + joint * 4 + 4]
+                                        .copy_from_slice(matrix);
+                        }
+                }
+
+                texels
+        }
+}
+
+/// A member of a crowd.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CrowdInstance
+{
+        pub transform: Transform,
+        /// Seconds into the animation at the start, so members don't move
+        /// in lockstep.
+        pub time_offset: f32,
+        /// Playback rate, 2.0 plays twice as fast.
+        pub speed: f32,
+}
+
+impl CrowdInstance
+{
+        pub fn new(transform: Transform) -> Self
+        {
+                Self {
+                        transform,
+                        time_offset: 0.0,
+                        speed: 1.0,
+                }
+        }
+
+        pub fn with_time_offset(
+                mut self,
+                time_offset: f32,
+        ) -> Self
+        {
+                self.time_offset = time_offset;
+                self
+        }
+
+        pub fn with_speed(
+                mut self,
+                speed: f32,
+        ) -> Self
+        {
+                self.speed = speed;
+                self
+        }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct CrowdInstanceRaw
+{
+        transform: [[f32; 4]; 4],
+        /// Time offset and speed.
+        animation: [f32; 4],
+}
+
+impl From<&CrowdInstance> for CrowdInstanceRaw
+{
+        fn from(instance: &CrowdInstance) -> Self
+        {
+                Self {
+                        transform: instance.transform.matrix().into(),
+                        animation: [instance.time_offset, instance.speed, 0.0, 0.0],
+                }
+        }
+}
+
+/// Instance buffer layout of the crowd pipeline.
+pub struct CrowdInstanceLayout;
+
+impl Vertex for CrowdInstanceLayout
+{
+        fn desc() -> wgpu::VertexBufferLayout<'static>
+        {
+                const ATTRIBUTES: [wgpu::VertexAttribute; 5] = wgpu::vertex_attr_array![
+                        5 => Float32x4,
+                        6 => Float32x4,
+                        7 => Float32x4,
+                        8 => Float32x4,
+                        9 => Float32x4,
+                ];
+
+                wgpu::VertexBufferLayout {
+                        array_stride: size_of::<CrowdInstanceRaw>() as wgpu::BufferAddress,
+                        step_mode: wgpu::VertexStepMode::Instance,
+                        attributes: &ATTRIBUTES,
+                }
+        }
+}
+
+/// Time and layout of a crowd's animation, `@group(3) @binding(3)` of
+/// `crowd.wgsl`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct CrowdUniform
+{
+        time: f32,
+        fps: f32,
+        frames: u32,
+        joints: u32,
+}
+
+/// Layout of `@group(3)` of the crowd pipeline, the model transform, the
+/// animation texture and the [`CrowdUniform`].
+pub fn create_crowd_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout
+{
+        let uniform = |binding| wgpu::BindGroupLayoutEntry {
+                binding,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                },
+                count: None,
+        };
+
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                        uniform(0),
+                        wgpu::BindGroupLayoutEntry {
+                                binding: 2,
+                                visibility: wgpu::ShaderStages::VERTEX,
+                                ty: wgpu::BindingType::Texture {
+                                        sample_type: wgpu::TextureSampleType::Float {
+                                                filterable: false,
+                                        },
+                                        view_dimension: wgpu::TextureViewDimension::D2,
+                                        multisampled: false,
+                                },
+                                count: None,
+                        },
+                        uniform(3),
+                ],
+                label: Some("crowd_bind_group_layout"),
+        })
+}
+
+/// Members of one skinned model playing a baked animation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Crowd
+{
+        /// Handle of the skinned model every member is drawn as.
+        pub model: String,
+        pub animation: BakedAnimation,
+        pub instances: Vec<CrowdInstance>,
+        pub visible: bool,
+}
+
+impl Crowd
+{
+        pub fn new(
+                model: impl Into<String>,
+                animation: BakedAnimation,
+        ) -> Self
+        {
+                Self {
+                        model: model.into(),
+                        animation,
+                        instances: Vec::new(),
+                        visible: true,
+                }
+        }
+
+        pub fn with_instances(
+                mut self,
+                instances: Vec<CrowdInstance>,
+        ) -> Self
+        {
+                self.instances = instances;
+                self
+        }
+}
+
+/// The crowds by name, owned by the [`Engine`].
+#[derive(Debug, Default)]
+pub struct Crowds
+{
+        crowds: BTreeMap<String, Crowd>,
+        /// Changed with every edit, the [`CrowdPass`] uploads the crowds
+        /// again when it differs.
+        revision: u64,
+}
+
+impl Crowds
+{
+        pub fn new() -> Self
+        {
+                Self::default()
+        }
+
+        /// Adds `crowd`, replacing the crowd `name`.
+        pub fn insert(
+                &mut self,
+                name: &str,
+                crowd: Crowd,
+        )
+        {
+                self.crowds.insert(name.to_string(), crowd);
+                self.revision += 1;
+        }
+
+        pub fn remove(
+                &mut self,
+                name: &str,
+        ) -> Option<Crowd>
+        {
+                self.revision += 1;
+                self.crowds.remove(name)
+        }
+
+        pub fn get(
+                &self,
+                name: &str,
+        ) -> Option<&Crowd>
+        {
+                self.crowds.get(name)
+        }
+
+        /// The crowd `name`, uploaded again on the next frame.
+        pub fn get_mut(
+                &mut self,
+                name: &str,
+        ) -> Option<&mut Crowd>
+        {
+                self.revision += 1;
+                self.crowds.get_mut(name)
+        }
+
+        pub fn revision(&self) -> u64
+        {
+                self.revision
+        }
+
+        /// Handles of the models of the visible crowds.
+        pub fn models(&self) -> impl Iterator<Item = &str>
+        {
+                self.crowds
+                        .values()
+                        .filter(|c| c.visible)
+                        .map(|c| c.model.as_str())
+        }
+}
+
+/// A crowd on the GPU.
+#[derive(Debug)]
+struct CrowdBatch
+{
+        model: String,
+        instance_buffer: wgpu::Buffer,
+        count: u32,
+        fps: f32,
+        frames: u32,
+        joints: u32,
+        /// Holds the [`CrowdUniform`], written every frame.
+        uniform: wgpu::Buffer,
+        /// Holds the crowd model's transform, written every frame.
+        transform: wgpu::Buffer,
+        bind_group: wgpu::BindGroup,
+        /// The members in the view, once the model is loaded.
+        #[cfg(feature = "gpu-culling")]
+        culling: Option<CrowdCulling>,
+}
+
+impl CrowdBatch
+{
+        /// Lists the members in the view for the draws of `model`'s skinned
+        /// meshes.
+        #[cfg(feature = "gpu-culling")]
+        fn cull(
+                &mut self,
+                culling: &GpuCulling,
+                model: &Model,
+                device: &wgpu::Device,
+                queue: &wgpu::Queue,
+                encoder: &mut wgpu::CommandEncoder,
+        )
+        {
+                let index_counts: Vec<u32> = model
+                        .meshes
+                        .iter()
+                        .filter(|m| m.skin_buffer.is_some())
+                        .map(|m| m.num_elements)
+                        .collect();
+
+                let Some(bounds) = model.bounds()
+                else
+                {
+                        self.culling = None;
+                        return;
+                };
+
+                if self.culling
+                        .as_ref()
+                        .is_none_or(|c| c.meshes() != index_counts.len() as u32)
+                {
+                        self.culling = Some(culling.create_crowd_culling(
+                                device,
+                                &self.instance_buffer,
+                                self.count,
+                                index_counts.len() as u32,
+                        ));
+                }
+
+                if let Some(crowd) = &self.culling
+                {
+                        culling.cull_crowd(
+                                queue,
+                                encoder,
+                                crowd,
+                                model.calculate_transform(),
+                                bounds,
+                                &index_counts,
+                        );
+                }
+        }
+
+        /// The members drawn, the ones in the view when culled.
+        fn instances(&self) -> &wgpu::Buffer
+        {
+                #[cfg(feature = "gpu-culling")]
+                if let Some(culling) = &self.culling
+                {
+                        return culling.visible();
+                }
+
+                &self.instance_buffer
+        }
+
+        /// Draws the `index`th skinned mesh for every member drawn.
+        fn draw_mesh(
+                &self,
+                render_pass: &mut wgpu::RenderPass,
+                #[allow(unused_variables)] index: u32,
+                mesh: &Mesh,
+        )
+        {
+                #[cfg(feature = "gpu-culling")]
+                if let Some(culling) = &self.culling
+                {
+                        culling.draw(render_pass, index);
+                        return;
+                }
+
+                render_pass.draw_indexed(0..mesh.num_elements, 0, 0..self.count);
+        }
+}
+
+/// Draws the [`Crowds`] after the geometry pass.
+#[derive(Debug)]
+pub struct CrowdPass
+{
+        pub name: String,
+        pub enabled: bool,
+        /// Seconds the crowds have been playing.
+        pub time: f32,
+        /// Culls the members outside of the view on the GPU, see
+        /// [`crate::renderer::culling`].
+        #[cfg(feature = "gpu-culling")]
+        pub cull: bool,
+        /// Revision of the crowds `batches` were uploaded from.
+        revision: Option<u64>,
+        /// Crowds waiting for their buffers.
+        pending: Option<Vec<Crowd>>,
+        batches: Vec<CrowdBatch>,
+        layout: wgpu::BindGroupLayout,
+}
+
+impl CrowdPass
+{
+        pub fn new(device: &wgpu::Device) -> Self
+        {
+                Self {
+                        name: "crowd_pass".to_string(),
+                        enabled: true,
+                        time: 0.0,
+                        #[cfg(feature = "gpu-culling")]
+                        cull: true,
+                        revision: None,
+                        pending: None,
+                        batches: Vec::new(),
+                        layout: create_crowd_bind_group_layout(device),
+                }
+        }
+
+        /// Advances the animations by `dt` seconds and uploads the crowds
+        /// again when they changed since the last call.
+        pub fn sync(
+                &mut self,
+                crowds: &Crowds,
+                dt: f32,
+        )
+        {
+                self.time += dt;
+
+                if self.revision != Some(crowds.revision())
+                {
+                        self.revision = Some(crowds.revision());
+                        self.pending = Some(crowds
+                                .crowds
+                                .values()
+                                .filter(|c| c.visible && !c.instances.is_empty())
+                                .cloned()
+                                .collect());
+                }
+        }
+
+        /// Uploads `crowd`, with an instance buffer the culling shader can
+        /// read when `storage`.
+        fn upload(
+                crowd: Crowd,
+                layout: &wgpu::BindGroupLayout,
+                storage: bool,
+                device: &wgpu::Device,
+                encoder: &mut wgpu::CommandEncoder,
+        ) -> CrowdBatch
+        {
+                let animation = &crowd.animation;
+                let size = wgpu::Extent3d {
+                        width: animation.width(),
+                        height: animation.frame_count().max(1),
+                        depth_or_array_layers: 1,
+                };
+
+                let texture = device.create_texture(&wgpu::TextureDescriptor {
+                        label: Some("Crowd Animation Texture"),
+                        size,
+                        mip_level_count: 1,
+                        sample_count: 1,
+                        dimension: wgpu::TextureDimension::D2,
+                        format: wgpu::TextureFormat::Rgba32Float,
+                        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                        view_formats: &[],
+                });
+
+                let mut texels = animation.texels();
+                texels.resize((size.width * size.height) as usize, [0.0; 4]);
+
+                let staging = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                        label: Some("Crowd Animation Staging Buffer"),
+                        contents: bytemuck::cast_slice(&texels),
+                        usage: wgpu::BufferUsages::COPY_SRC,
+                });
+
+                encoder.copy_buffer_to_texture(
+                        wgpu::TexelCopyBufferInfo {
+                                buffer: &staging,
+                                layout: wgpu::TexelCopyBufferLayout {
+                                        offset: 0,
+                                        bytes_per_row: Some(size.width * 16),
+                                        rows_per_image: Some(size.height),
+                                },
+                        },
+                        texture.as_image_copy(),
+                        size,
+                );
+
+                let instances: Vec<CrowdInstanceRaw> =
+                        crowd.instances.iter().map(Into::into).collect();
+
+                let texture = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+                let uniform = device.create_buffer(&wgpu::BufferDescriptor {
+                        label: Some("Crowd Uniform Buffer"),
+                        size: std::mem::size_of::<CrowdUniform>() as u64,
+                        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                        mapped_at_creation: false,
+                });
+
+                let transform = device.create_buffer(&wgpu::BufferDescriptor {
+                        label: Some("Crowd Transform Buffer"),
+                        size: std::mem::size_of::<[[f32; 4]; 4]>() as u64,
+                        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                        mapped_at_creation: false,
+                });
+
+                let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                        label: Some("crowd_bind_group"),
+                        layout,
+                        entries: &[
+                                wgpu::BindGroupEntry {
+                                        binding: 0,
+                                        resource: transform.as_entire_binding(),
+                                },
+                                wgpu::BindGroupEntry {
+                                        binding: 2,
+                                        resource: wgpu::BindingResource::TextureView(&texture),
+                                },
+                                wgpu::BindGroupEntry {
+                                        binding: 3,
+                                        resource: uniform.as_entire_binding(),
+                                },
+                        ],
+                });
+
+                CrowdBatch {
+                        instance_buffer: device.create_buffer_init(
+                                &wgpu::util::BufferInitDescriptor {
+                                        label: Some("Crowd Instance Buffer"),
+                                        contents: bytemuck::cast_slice(&instances),
+                                        usage: if storage
+                                        {
+                                                wgpu::BufferUsages::VERTEX
+                                                        | wgpu::BufferUsages::STORAGE
+                                        }
+                                        else
+                                        {
+                                                wgpu::BufferUsages::VERTEX
+                                        },
+                                },
+                        ),
+                        count: instances.len() as u32,
+                        fps: animation.fps,
+                        frames: animation.frame_count(),
+                        joints: animation.joints,
+                        uniform,
+                        transform,
+                        bind_group,
+                        model: crowd.model,
+                        #[cfg(feature = "gpu-culling")]
+                        culling: None,
+                }
+        }
+}
+
+impl RenderPass for CrowdPass
+{
+        fn name(&self) -> &str
+        {
+                self.name.as_str()
+        }
+
+        fn as_any(&self) -> &dyn Any
+        {
+                self
+        }
+
+        fn as_any_mut(&mut self) -> &mut dyn Any
+        {
+                self
+        }
+
+        #[cfg(feature = "ui")]
+        fn ui(
+                &mut self,
+                ui: &mut egui::Ui,
+        )
+        {
+                let members: u32 = self.batches.iter().map(|b| b.count).sum();
+
+                egui::CollapsingHeader::new(&self.name)
+                        .default_open(true)
+                        .show(ui, |ui| {
+                                ui.label(format!("Members: {}", members));
+                                ui.label(format!("Crowds: {}", self.batches.len()));
+
+                                #[cfg(feature = "gpu-culling")]
+                                ui.checkbox(&mut self.cull, "Cull members outside of the view");
+                        });
+        }
+
+        fn enabled(&mut self) -> bool
+        {
+                self.enabled
+        }
+
+        fn attachments(&self) -> Vec<Attachment>
+        {
+                vec![
+                        Attachment::new(SURFACE, AttachmentLoad::Load, true),
+                        Attachment::new(DEPTH, AttachmentLoad::Load, true),
+                ]
+        }
+
+        fn set_enabled(
+                &mut self,
+                value: bool,
+        )
+        {
+                self.enabled = value;
+        }
+
+        fn record(
+                &mut self,
+                view: &wgpu::TextureView,
+                encoder: &mut wgpu::CommandEncoder,
+                camera: &wgpu::BindGroup,
+                pipeline_manager: &PipelineManager,
+                depth_texture: &Texture,
+                models: Option<&HashMap<String, Model>>,
+                device: &wgpu::Device,
+                queue: &wgpu::Queue,
+        )
+        {
+                #[cfg(feature = "gpu-culling")]
+                let storage = pipeline_manager.culling.is_some();
+                #[cfg(not(feature = "gpu-culling"))]
+                let storage = false;
+
+                if let Some(pending) = self.pending.take()
+                {
+                        self.batches = pending
+                                .into_iter()
+                                .map(|crowd| {
+                                        Self::upload(crowd, &self.layout, storage, device, encoder)
+                                })
+                                .collect();
+                }
+
+                let Some(models) = models
+                else
+                {
+                        return;
+                };
+
+                if self.batches.is_empty()
+                {
+                        return;
+                }
+
+                #[cfg(feature = "gpu-culling")]
+                for batch in &mut self.batches
+                {
+                        match (
+                                pipeline_manager.culling.as_ref().filter(|_| self.cull),
+                                models.get(&batch.model),
+                        )
+                        {
+                                (Some(culling), Some(model)) =>
+                                {
+                                        batch.cull(culling, model, device, queue, encoder)
+                                }
+                                _ => batch.culling = None,
+                        }
+                }
+
+                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        label: Some(&self.name),
+                        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                                view,
+                                resolve_target: None,
+                                ops: wgpu::Operations {
+                                        load: wgpu::LoadOp::Load,
+                                        store: wgpu::StoreOp::Store,
+                                },
+                        })],
+                        depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                                view: &depth_texture.view,
+                                depth_ops: Some(wgpu::Operations {
+                                        load: wgpu::LoadOp::Load,
+                                        store: wgpu::StoreOp::Store,
+                                }),
+                                stencil_ops: None,
+                        }),
+                        occlusion_query_set: None,
+                        timestamp_writes: None,
+                });
+
+                pipeline_manager.apply_viewport(&mut render_pass);
+                render_pass.set_pipeline(pipeline_manager.get(PipelineKind::Crowd));
+                render_pass.set_bind_group(0, camera, &[]);
+
+                for batch in &self.batches
+                {
+                        // Still loading.
+                        let Some(model) = models.get(&batch.model)
+                        else
+                        {
+                                continue;
+                        };
+
+                        let transform: [[f32; 4]; 4] = model.calculate_transform().into();
+
+                        queue.write_buffer(
+                                &batch.uniform,
+                                0,
+                                bytemuck::bytes_of(&CrowdUniform {
+                                        time: self.time,
+                                        fps: batch.fps,
+                                        frames: batch.frames,
+                                        joints: batch.joints,
+                                }),
+                        );
+                        queue.write_buffer(&batch.transform, 0, bytemuck::cast_slice(&transform));
+
+                        render_pass.set_bind_group(3, &batch.bind_group, &[]);
+                        render_pass.set_vertex_buffer(2, batch.instances().slice(..));
+
+                        for (index, (mesh, skin_buffer)) in model
+                                .meshes
+                                .iter()
+                                .filter_map(|m| Some((m, m.skin_buffer.as_ref()?)))
+                                .enumerate()
+                        {
+                                let material = &model.materials[mesh.material];
+
+                                render_pass.set_bind_group(1, &mesh.transform_bind_group, &[]);
+                                render_pass.set_bind_group(2, &material.material_bind_group, &[]);
+                                render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+                                render_pass.set_vertex_buffer(1, skin_buffer.slice(..));
+                                render_pass.set_index_buffer(
+                                        mesh.index_buffer.slice(..),
+                                        wgpu::IndexFormat::Uint32,
+                                );
+                                batch.draw_mesh(&mut render_pass, index as u32, mesh);
+                        }
+                }
+        }
+}
+
+#[cfg(feature = "runtime")]
+impl Engine
+{
+        /// Draws `crowd` until it's removed, replacing the crowd `name`.
+        pub fn add_crowd(
+                &mut self,
+                name: &str,
+                crowd: Crowd,
+        )
+        {
+                self.crowds.insert(name, crowd);
+        }
+
+        pub fn remove_crowd(
+                &mut self,
+                name: &str,
+        ) -> Option<Crowd>
+        {
+                self.crowds.remove(name)
+        }
+
+        pub fn crowd(
+                &self,
+                name: &str,
+        ) -> Option<&Crowd>
+        {
+                self.crowds.get(name)
+        }
+
+        /// The crowd `name` to change, e.g. to move its members.
+        pub fn crowd_mut(
+                &mut self,
+                name: &str,
+        ) -> Option<&mut Crowd>
+        {
+                self.crowds.get_mut(name)
+        }
+}
@@ -0,0 +1,87 @@
+// The members of a crowd, one instance each, skinned from a baked
+// animation. See `crowd.rs`, built with `SKINNED` for `SkinInput`.
+
+#include "common.wgsl"
+
+struct CrowdUniform {
+    time: f32,
+    fps: f32,
+    frames: u32,
+    joints: u32,
+};
+
+// Palette of every frame, a row per frame and four texels per joint.
+@group(3) @binding(2) var animation: texture_2d<f32>;
+@group(3) @binding(3) var<uniform> crowd: CrowdUniform;
+
+struct CrowdInstanceInput {
+    @location(5) transform_0: vec4<f32>,
+    @location(6) transform_1: vec4<f32>,
+    @location(7) transform_2: vec4<f32>,
+    @location(8) transform_3: vec4<f32>,
+    // Time offset and speed.
+    @location(9) animation: vec4<f32>,
+};
+
+fn baked_joint(joint: u32, frame: u32) -> mat4x4<f32> {
+    let x = i32(min(joint, crowd.joints - 1u) * 4u);
+    let y = i32(frame);
+
+    return mat4x4<f32>(
+        textureLoad(animation, vec2<i32>(x, y), 0),
+        textureLoad(animation, vec2<i32>(x + 1, y), 0),
+        textureLoad(animation, vec2<i32>(x + 2, y), 0),
+        textureLoad(animation, vec2<i32>(x + 3, y), 0),
+    );
+}
+
+// Blends the joints between the two frames around the instance's time.
+fn baked_skin_matrix(skin: SkinInput, time: f32) -> mat4x4<f32> {
+    let frames = f32(max(crowd.frames, 1u));
+    let position = fract(time * crowd.fps / frames) * frames;
+    let first = u32(position) % max(crowd.frames, 1u);
+    let second = (first + 1u) % max(crowd.frames, 1u);
+    let blend = fract(position);
+
+    var matrix = mat4x4<f32>(vec4<f32>(0.0), vec4<f32>(0.0), vec4<f32>(0.0), vec4<f32>(0.0));
+
+    for (var i = 0u; i < 4u; i++) {
+        let joint = skin.joints[i];
+        let blended = baked_joint(joint, first) * (1.0 - blend) + baked_joint(joint, second) * blend;
+
+        matrix += blended * skin.weights[i];
+    }
+
+    return matrix;
+}
+
+@vertex
+fn vs_crowd(
+    model: VertexInput,
+    skin: SkinInput,
+    instance: CrowdInstanceInput,
+) -> VertexOutput {
+    var out: VertexOutput;
+
+    let placement = mat4x4<f32>(instance.transform_0, instance.transform_1, instance.transform_2, instance.transform_3);
+    let time = crowd.time * instance.animation.y + instance.animation.x;
+    let member = placement * model_transform.model * transform.model * baked_skin_matrix(skin, time);
+
+    let world_position = member * vec4<f32>(model.position, 1.0);
+    out.clip_position = camera.view_proj * world_position;
+    out.tex_coords = model.tex_coords;
+    out.world_position = world_position.xyz;
+
+    let normal = member * vec4<f32>(model.normal, 0.0);
+    out.world_normal = normal.xyz;
+
+    return out;
+}
+
+@fragment
+fn fs_crowd(in: VertexOutput) -> @location(0) vec4<f32> {
+    let texture_color = textureSampleBias(base_color_texture, base_color_sampler, in.tex_coords, material_props.lod_bias);
+    let final_color = texture_color * material_props.base_color_factor;
+
+    return vec4<f32>(shade(final_color.rgb, in.world_position, in.world_normal), final_color.a);
+}
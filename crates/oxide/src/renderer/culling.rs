@@ -0,0 +1,2083 @@
+//! Frustum and occlusion culling on the GPU, with the `gpu-culling`
+//! feature.
+//!
+//! Every frame [`GpuCulling::prepare`] uploads the world bounds of the
+//! meshes of the visible models, with one indirect draw per mesh in the
+//! order the model passes draw them. Two compute passes then turn off the
+//! draws of meshes that can't be seen:
+//!
+//! - [`FrustumCullingPass`], before the
+//!   [`DepthPrePass`](crate::renderer::graph::DepthPrePass), the meshes outside
+//!   of the view.
+//! - [`OcclusionCullingPass`], after it, the meshes behind the depth it drew.
+//!   The depth is reduced to a pyramid of mip levels holding the farthest depth
+//!   of the texels below (Hi-Z), where the bounds of a mesh are tested against
+//!   a few texels whatever their size on screen. Only runs with the pre-pass
+//!   active, and saves the shading of the
+//!   [`GeometryPass`](crate::renderer::graph::GeometryPass).
+//!
+//! The draws are issued from what the compute passes wrote, so the CPU
+//! never waits on the results. A disabled culling pass leaves the meshes
+//! drawn.
+//!
+//! When the device has `MULTI_DRAW_INDIRECT` and `INDIRECT_FIRST_INSTANCE`
+//! the model passes draw most meshes together. Meshes that aren't skinned,
+//! of models without a custom shader or a reflection probe, are copied into
+//! one shared vertex and index buffer and sorted into batches sharing the
+//! material and its shader permutation. The depth pre-pass draws them with
+//! one `multi_draw_indexed_indirect`, the geometry pass with one per batch.
+//! Their shaders are the `CULLED` permutations, which read the transforms
+//! of a draw from a storage buffer by its instance index instead of the
+//! mesh and model bind groups.
+//!
+//! With `MULTI_DRAW_INDIRECT_COUNT` the culling passes also compact the
+//! visible draws to the front of the depth pre-pass and of every batch and
+//! count them, the hidden draws aren't issued at all. Without it they stay
+//! in place with no instances. The other meshes are drawn one by one
+//! through [`PipelineManager::draw_mesh`], with their own indirect draw.
+//!
+//! The members of a [`CrowdPass`](crate::renderer::crowd::CrowdPass) are
+//! culled against the view into a list of the visible ones, which the
+//! crowd is drawn from, see [`CrowdCulling`].
+//!
+//! Needs compute shaders and indirect draws, which WebGL doesn't have,
+//! meshes are drawn directly there.
+
+#[cfg(feature = "runtime")]
+use crate::engine::EngineState;
+use crate::geometry::bounds::Aabb;
+use crate::geometry::mesh::Mesh;
+use crate::model::{Model, ModelVertex};
+use crate::renderer::graph::{Attachment, AttachmentLoad, DEPTH, RenderPass};
+#[cfg(feature = "runtime")]
+use crate::renderer::pipeline::FillMode;
+use crate::renderer::pipeline::PipelineManager;
+use crate::renderer::preprocess::ShaderDefines;
+use crate::texture::Texture;
+use std::any::Any;
+use std::collections::{HashMap, HashSet};
+
+/// Threads per workgroup of the culling shaders.
+const WORKGROUP_SIZE: u32 = 64;
+
+/// Width and height of the workgroups building the depth pyramid.
+const HIZ_WORKGROUP_SIZE: u32 = 8;
+
+/// Size of a `wgpu::util::DrawIndexedIndirectArgs`.
+const DRAW_ARGS_SIZE: u64 = 20;
+
+/// A draw not drawn together with others, see [`DrawSource`].
+const NONE: u32 = u32::MAX;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct CullingUniform
+{
+        view_proj: [[f32; 4]; 4],
+        viewport: [f32; 4],
+        draw_count: u32,
+        reversed_z: u32,
+        hiz_levels: u32,
+        /// The visible draws are compacted and counted, with
+        /// `MULTI_DRAW_INDIRECT_COUNT`.
+        compact: u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct DrawBounds
+{
+        min: [f32; 3],
+        index_count: u32,
+        max: [f32; 3],
+        _padding: u32,
+}
+
+/// Where a draw is in the shared buffers and the multi-draws, `depth` is
+/// [`NONE`] when it's drawn alone.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct DrawSource
+{
+        first_index: u32,
+        base_vertex: i32,
+        /// Position in the draws of the depth pre-pass.
+        depth: u32,
+        /// Index of the geometry pass batch.
+        batch: u32,
+        /// Where the draws of the batch start.
+        batch_start: u32,
+        /// Position in the batch.
+        batch_position: u32,
+}
+
+/// The crowd culled by [`GpuCulling::cull_crowd`].
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct CrowdCullingUniform
+{
+        model: [[f32; 4]; 4],
+        min: [f32; 3],
+        count: u32,
+        max: [f32; 3],
+        meshes: u32,
+}
+
+fn storage_buffer_entry(
+        binding: u32,
+        read_only: bool,
+) -> wgpu::BindGroupLayoutEntry
+{
+        wgpu::BindGroupLayoutEntry {
+                binding,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage {
+                                read_only,
+                        },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                },
+                count: None,
+        }
+}
+
+fn uniform_entry(binding: u32) -> wgpu::BindGroupLayoutEntry
+{
+        wgpu::BindGroupLayoutEntry {
+                binding,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                },
+                count: None,
+        }
+}
+
+fn texture_entry(
+        binding: u32,
+        sample_type: wgpu::TextureSampleType,
+) -> wgpu::BindGroupLayoutEntry
+{
+        wgpu::BindGroupLayoutEntry {
+                binding,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Texture {
+                        sample_type,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                },
+                count: None,
+        }
+}
+
+fn hiz_target_entry() -> wgpu::BindGroupLayoutEntry
+{
+        wgpu::BindGroupLayoutEntry {
+                binding: 6,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::StorageTexture {
+                        access: wgpu::StorageTextureAccess::WriteOnly,
+                        format: wgpu::TextureFormat::R32Float,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                },
+                count: None,
+        }
+}
+
+const HIZ_SAMPLE_TYPE: wgpu::TextureSampleType = wgpu::TextureSampleType::Float {
+        filterable: false,
+};
+
+/// Layouts of the entry points of `culling.wgsl`, all in group 0, but
+/// `compact`'s, see [`MultiDraw`].
+fn create_culling_bind_group_layouts(device: &wgpu::Device) -> [wgpu::BindGroupLayout; 5]
+{
+        let layout = |label: &str, entries: &[wgpu::BindGroupLayoutEntry]| {
+                device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                        label: Some(label),
+                        entries,
+                })
+        };
+
+        [
+                layout(
+                        "frustum_culling_bind_group_layout",
+                        &[
+                                uniform_entry(0),
+                                storage_buffer_entry(1, true),
+                                storage_buffer_entry(2, false),
+                        ],
+                ),
+                layout(
+                        "occlusion_culling_bind_group_layout",
+                        &[
+                                uniform_entry(0),
+                                storage_buffer_entry(1, true),
+                                storage_buffer_entry(2, false),
+                                texture_entry(3, HIZ_SAMPLE_TYPE),
+                        ],
+                ),
+                layout(
+                        "hiz_copy_bind_group_layout",
+                        &[
+                                uniform_entry(0),
+                                texture_entry(4, wgpu::TextureSampleType::Depth),
+                                hiz_target_entry(),
+                        ],
+                ),
+                layout(
+                        "hiz_reduce_bind_group_layout",
+                        &[texture_entry(5, HIZ_SAMPLE_TYPE), hiz_target_entry()],
+                ),
+                layout(
+                        "crowd_culling_bind_group_layout",
+                        &[
+                                uniform_entry(0),
+                                uniform_entry(11),
+                                storage_buffer_entry(12, true),
+                                storage_buffer_entry(13, false),
+                                storage_buffer_entry(14, false),
+                        ],
+                ),
+        ]
+}
+
+/// Layout of `@group(1)` of the `CULLED` permutations of the model shader,
+/// the mesh and model transform of every draw.
+pub fn create_draw_transform_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout
+{
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Storage {
+                                        read_only: true,
+                                },
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                        },
+                        count: None,
+                }],
+                label: Some("draw_transform_bind_group_layout"),
+        })
+}
+
+/// A bind group of whole buffers.
+fn buffer_bind_group(
+        device: &wgpu::Device,
+        label: &str,
+        layout: &wgpu::BindGroupLayout,
+        buffers: &[(u32, &wgpu::Buffer)],
+) -> wgpu::BindGroup
+{
+        let entries: Vec<wgpu::BindGroupEntry> = buffers
+                .iter()
+                .map(|(binding, buffer)| wgpu::BindGroupEntry {
+                        binding: *binding,
+                        resource: buffer.as_entire_binding(),
+                })
+                .collect();
+
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some(label),
+                layout,
+                entries: &entries,
+        })
+}
+
+fn create_buffer(
+        device: &wgpu::Device,
+        label: &str,
+        size: u64,
+        usage: wgpu::BufferUsages,
+) -> wgpu::Buffer
+{
+        device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size,
+                usage,
+                mapped_at_creation: false,
+        })
+}
+
+/// A mesh copied into the [`SharedGeometry`].
+#[derive(Debug, Clone, Copy)]
+struct SharedMesh
+{
+        revision: u64,
+        base_vertex: u32,
+        first_index: u32,
+        /// Vertices and indices there's room for in place.
+        vertex_room: u32,
+        index_room: u32,
+}
+
+impl SharedMesh
+{
+        fn fits(
+                &self,
+                mesh: &Mesh,
+        ) -> bool
+        {
+                mesh.vertices.len() as u32 <= self.vertex_room
+                        && mesh.indices.len() as u32 <= self.index_room
+        }
+}
+
+/// The vertices and indices of the meshes drawn together, in one buffer
+/// each. A mesh is appended when it's first drawn and written again in
+/// place when it changes. Once the buffers are full they're packed again
+/// with the meshes drawn that frame only.
+#[derive(Debug)]
+struct SharedGeometry
+{
+        vertex_buffer: wgpu::Buffer,
+        index_buffer: wgpu::Buffer,
+        /// By the vertex and index buffer of the mesh, which its duplicates
+        /// share.
+        meshes: HashMap<(wgpu::Buffer, wgpu::Buffer), SharedMesh>,
+        /// Vertices and indices taken.
+        vertex_count: u32,
+        index_count: u32,
+}
+
+impl SharedGeometry
+{
+        fn new(
+                device: &wgpu::Device,
+                vertex_capacity: u32,
+                index_capacity: u32,
+        ) -> Self
+        {
+                Self {
+                        vertex_buffer: create_buffer(
+                                device,
+                                "Culling Shared Vertex Buffer",
+                                vertex_capacity as u64 * size_of::<ModelVertex>() as u64,
+                                wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                        ),
+                        index_buffer: create_buffer(
+                                device,
+                                "Culling Shared Index Buffer",
+                                index_capacity as u64 * size_of::<u32>() as u64,
+                                wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
+                        ),
+                        meshes: HashMap::new(),
+                        vertex_count: 0,
+                        index_count: 0,
+                }
+        }
+
+        fn vertex_capacity(&self) -> u32
+        {
+                (self.vertex_buffer.size() / size_of::<ModelVertex>() as u64) as u32
+        }
+
+        fn index_capacity(&self) -> u32
+        {
+                (self.index_buffer.size() / size_of::<u32>() as u64) as u32
+        }
+
+        fn key(mesh: &Mesh) -> (wgpu::Buffer, wgpu::Buffer)
+        {
+                (mesh.vertex_buffer.clone(), mesh.index_buffer.clone())
+        }
+
+        /// Where every mesh of `meshes` is in the buffers, copying the ones
+        /// that aren't there yet or changed.
+        // wgpu handles hash by identity, not by what they hold.
+        #[allow(clippy::mutable_key_type)]
+        fn place(
+                &mut self,
+                device: &wgpu::Device,
+                queue: &wgpu::Queue,
+                meshes: &[&Mesh],
+        ) -> Vec<SharedMesh>
+        {
+                let mut seen = HashSet::new();
+                let (mut vertices, mut indices) = (0, 0);
+                let (mut appended_vertices, mut appended_indices) = (0, 0);
+
+                for mesh in meshes.iter().filter(|m| seen.insert(Self::key(m)))
+                {
+                        vertices += mesh.vertices.len() as u32;
+                        indices += mesh.indices.len() as u32;
+
+                        if !self.meshes
+                                .get(&Self::key(mesh))
+                                .is_some_and(|s| s.fits(mesh))
+                        {
+                                appended_vertices += mesh.vertices.len() as u32;
+                                appended_indices += mesh.indices.len() as u32;
+                        }
+                }
+
+                if self.vertex_count + appended_vertices > self.vertex_capacity()
+                        || self.index_count + appended_indices > self.index_capacity()
+                {
+                        if vertices > self.vertex_capacity() || indices > self.index_capacity()
+                        {
+                                *self = Self::new(
+                                        device,
+                                        vertices.next_power_of_two().max(self.vertex_capacity()),
+                                        indices.next_power_of_two().max(self.index_capacity()),
+                                );
+                        }
+                        else
+                        {
+                                self.meshes.clear();
+                                self.vertex_count = 0;
+                                self.index_count = 0;
+                        }
+                }
+
+                let mut placed = Vec::with_capacity(meshes.len());
+
+                for mesh in meshes
+                {
+                        let key = Self::key(mesh);
+
+                        if let Some(shared) = self.meshes.get_mut(&key).filter(|s| s.fits(mesh))
+                        {
+                                if shared.revision != mesh.revision
+                                {
+                                        shared.revision = mesh.revision;
+                                        Self::write(
+                                                queue,
+                                                &self.vertex_buffer,
+                                                &self.index_buffer,
+                                                shared,
+                                                mesh,
+                                        );
+                                }
+
+                                placed.push(*shared);
+                                continue;
+                        }
+
+                        let shared = SharedMesh {
+                                revision: mesh.revision,
+                                base_vertex: self.vertex_count,
+                                first_index: self.index_count,
+                                vertex_room: mesh.vertices.len() as u32,
+                                index_room: mesh.indices.len() as u32,
+                        };
+
+                        Self::write(queue, &self.vertex_buffer, &self.index_buffer, &shared, mesh);
+
+                        self.vertex_count += shared.vertex_room;
+                        self.index_count += shared.index_room;
+                        self.meshes.insert(key, shared);
+                        placed.push(shared);
+                }
+
+                placed
+        }
+
+        fn write(
+                queue: &wgpu::Queue,
+                vertex_buffer: &wgpu::Buffer,
+                index_buffer: &wgpu::Buffer,
+                shared: &SharedMesh,
+                mesh: &Mesh,
+        )
+        {
+                if !mesh.vertices.is_empty()
+                {
+                        queue.write_buffer(
+                                vertex_buffer,
+                                shared.base_vertex as u64 * size_of::<ModelVertex>() as u64,
+                                bytemuck::cast_slice(&mesh.vertices),
+                        );
+                }
+
+                if !mesh.indices.is_empty()
+                {
+                        queue.write_buffer(
+                                index_buffer,
+                                shared.first_index as u64 * size_of::<u32>() as u64,
+                                bytemuck::cast_slice(&mesh.indices),
+                        );
+                }
+        }
+}
+
+/// A mesh drawn this frame, with its mesh and model transform.
+type Draw<'a> = (&'a Model, &'a Mesh, [[[f32; 4]; 4]; 2]);
+
+/// Meshes the geometry pass draws with one multi-draw.
+#[derive(Debug)]
+struct Batch
+{
+        /// The `CULLED` permutation of their shader.
+        pipeline: wgpu::RenderPipeline,
+        material: wgpu::BindGroup,
+        /// Where their draws start.
+        start: u32,
+        len: u32,
+}
+
+/// The per-draw buffers of the [`MultiDraw`], replaced with the ones of
+/// [`GpuCulling`] when the draws outgrow them.
+#[derive(Debug)]
+struct MultiDrawBuffers
+{
+        /// A [`DrawSource`] per draw.
+        sources: wgpu::Buffer,
+        /// The mesh and model transform of every draw.
+        transforms: wgpu::Buffer,
+        /// The draws of the depth pre-pass, on the shared buffers.
+        depth_args: wgpu::Buffer,
+        /// The draws of the batches, one batch after the other.
+        batch_args: wgpu::Buffer,
+        /// How many draws of the depth pre-pass, then of every batch, are
+        /// issued.
+        counts: wgpu::Buffer,
+        compact_bind_group: wgpu::BindGroup,
+        transform_bind_group: wgpu::BindGroup,
+}
+
+impl MultiDrawBuffers
+{
+        fn new(
+                device: &wgpu::Device,
+                capacity: u32,
+                compact_layout: &wgpu::BindGroupLayout,
+                transform_layout: &wgpu::BindGroupLayout,
+                uniform: &wgpu::Buffer,
+                args: &wgpu::Buffer,
+        ) -> Self
+        {
+                let capacity = capacity as u64;
+                let draws = wgpu::BufferUsages::STORAGE
+                        | wgpu::BufferUsages::INDIRECT
+                        | wgpu::BufferUsages::COPY_DST;
+
+                let sources = create_buffer(
+                        device,
+                        "Culling Source Buffer",
+                        capacity * size_of::<DrawSource>() as u64,
+                        wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+                );
+                let transforms = create_buffer(
+                        device,
+                        "Culling Transform Buffer",
+                        capacity * size_of::<[[[f32; 4]; 4]; 2]>() as u64,
+                        wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+                );
+                let depth_args = create_buffer(
+                        device,
+                        "Culling Depth Draw Buffer",
+                        capacity * DRAW_ARGS_SIZE,
+                        draws,
+                );
+                let batch_args = create_buffer(
+                        device,
+                        "Culling Batch Draw Buffer",
+                        capacity * DRAW_ARGS_SIZE,
+                        draws,
+                );
+                let counts = create_buffer(
+                        device,
+                        "Culling Count Buffer",
+                        (capacity + 1) * size_of::<u32>() as u64,
+                        draws,
+                );
+
+                Self {
+                        compact_bind_group: buffer_bind_group(
+                                device,
+                                "compact_bind_group",
+                                compact_layout,
+                                &[
+                                        (0, uniform),
+                                        (2, args),
+                                        (7, &sources),
+                                        (8, &depth_args),
+                                        (9, &batch_args),
+                                        (10, &counts),
+                                ],
+                        ),
+                        transform_bind_group: buffer_bind_group(
+                                device,
+                                "draw_transform_bind_group",
+                                transform_layout,
+                                &[(0, &transforms)],
+                        ),
+                        sources,
+                        transforms,
+                        depth_args,
+                        batch_args,
+                        counts,
+                }
+        }
+}
+
+/// Draws the meshes sharing a pipeline and material together, when the
+/// device has multi-draws.
+#[derive(Debug)]
+struct MultiDraw
+{
+        /// Only the visible draws are issued, counted on the GPU, with
+        /// `MULTI_DRAW_INDIRECT_COUNT`.
+        count: bool,
+        compact_pipeline: wgpu::ComputePipeline,
+        compact_layout: wgpu::BindGroupLayout,
+        /// `@group(1)` of the `CULLED` permutations.
+        transform_layout: wgpu::BindGroupLayout,
+        /// Depth of the `CULLED` permutation, built with the engine's bind
+        /// group layouts. Nothing is drawn together until it's set.
+        depth_pipeline: Option<wgpu::RenderPipeline>,
+        shared: SharedGeometry,
+        buffers: MultiDrawBuffers,
+
+        /// Of this frame's draws.
+        sources: Vec<DrawSource>,
+        /// Draws of the depth pre-pass.
+        depth_count: u32,
+        batches: Vec<Batch>,
+}
+
+impl MultiDraw
+{
+        /// Whether the device has multi-draws, and whether with a count
+        /// buffer.
+        fn available(device: &wgpu::Device) -> Option<bool>
+        {
+                let features = device.features();
+                let needed = wgpu::Features::MULTI_DRAW_INDIRECT
+                        | wgpu::Features::INDIRECT_FIRST_INSTANCE;
+
+                (features.contains(needed)
+                        && device.limits().max_storage_buffers_per_shader_stage >= 5)
+                        .then(|| features.contains(wgpu::Features::MULTI_DRAW_INDIRECT_COUNT))
+        }
+
+        fn compact_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout
+        {
+                device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                        label: Some("compact_bind_group_layout"),
+                        entries: &[
+                                uniform_entry(0),
+                                storage_buffer_entry(2, false),
+                                storage_buffer_entry(7, true),
+                                storage_buffer_entry(8, false),
+                                storage_buffer_entry(9, false),
+                                storage_buffer_entry(10, false),
+                        ],
+                })
+        }
+
+        fn new(
+                device: &wgpu::Device,
+                count: bool,
+                compact_pipeline: wgpu::ComputePipeline,
+                compact_layout: wgpu::BindGroupLayout,
+                capacity: u32,
+                uniform: &wgpu::Buffer,
+                args: &wgpu::Buffer,
+        ) -> Self
+        {
+                let transform_layout = create_draw_transform_bind_group_layout(device);
+
+                Self {
+                        count,
+                        buffers: MultiDrawBuffers::new(
+                                device,
+                                capacity,
+                                &compact_layout,
+                                &transform_layout,
+                                uniform,
+                                args,
+                        ),
+                        compact_pipeline,
+                        compact_layout,
+                        transform_layout,
+                        depth_pipeline: None,
+                        shared: SharedGeometry::new(device, 1 << 16, 1 << 16),
+                        sources: Vec::new(),
+                        depth_count: 0,
+                        batches: Vec::new(),
+                }
+        }
+
+        /// Places the meshes of `draws` drawn together in the shared
+        /// buffers and sorts them into batches, every draw visible.
+        #[allow(clippy::mutable_key_type)]
+        fn prepare(
+                &mut self,
+                device: &wgpu::Device,
+                queue: &wgpu::Queue,
+                draws: &[Draw],
+                permutations: &HashMap<ShaderDefines, wgpu::RenderPipeline>,
+        )
+        {
+                self.sources.clear();
+                self.batches.clear();
+                self.depth_count = 0;
+
+                if self.depth_pipeline.is_none()
+                {
+                        return;
+                }
+
+                // The `CULLED` permutation of every mesh drawn together.
+                let culled: Vec<Option<(ShaderDefines, &wgpu::RenderPipeline)>> = draws
+                        .iter()
+                        .map(|(model, mesh, _)| {
+                                let defines = model.defines(mesh).with("CULLED");
+
+                                GpuCulling::batchable(model, mesh)
+                                        .then(|| permutations.get(&defines))
+                                        .flatten()
+                                        .map(|pipeline| (defines, pipeline))
+                        })
+                        .collect();
+
+                let meshes: Vec<&Mesh> = draws
+                        .iter()
+                        .zip(&culled)
+                        .filter(|(_, culled)| culled.is_some())
+                        .map(|((_, mesh, _), _)| *mesh)
+                        .collect();
+                let mut placed = self.shared.place(device, queue, &meshes).into_iter();
+
+                let mut keys = HashMap::new();
+
+                for ((model, mesh, _), culled) in draws.iter().zip(culled)
+                {
+                        let mut source = DrawSource {
+                                first_index: 0,
+                                base_vertex: 0,
+                                depth: NONE,
+                                batch: NONE,
+                                batch_start: 0,
+                                batch_position: 0,
+                        };
+
+                        if let Some((defines, pipeline)) = culled
+                                && let Some(shared) = placed.next()
+                        {
+                                let material = &model.materials[mesh.material].material_bind_group;
+                                let batch = *keys
+                                        .entry((defines, material.clone()))
+                                        .or_insert_with(|| {
+                                                self.batches.push(Batch {
+                                                        pipeline: pipeline.clone(),
+                                                        material: material.clone(),
+                                                        start: 0,
+                                                        len: 0,
+                                                });
+
+                                                self.batches.len() as u32 - 1
+                                        });
+
+                                source.first_index = shared.first_index;
+                                source.base_vertex = shared.base_vertex as i32;
+                                source.depth = self.depth_count;
+                                source.batch = batch;
+                                source.batch_position = self.batches[batch as usize].len;
+
+                                self.depth_count += 1;
+                                self.batches[batch as usize].len += 1;
+                        }
+
+                        self.sources.push(source);
+                }
+
+                let mut start = 0;
+
+                for batch in &mut self.batches
+                {
+                        batch.start = start;
+                        start += batch.len;
+                }
+
+                let mut depth_args = vec![0; self.depth_count as usize * DRAW_ARGS_SIZE as usize];
+                let mut batch_args = depth_args.clone();
+
+                for (slot, source) in self.sources.iter_mut().enumerate()
+                {
+                        if source.depth == NONE
+                        {
+                                continue;
+                        }
+
+                        source.batch_start = self.batches[source.batch as usize].start;
+
+                        let args = wgpu::util::DrawIndexedIndirectArgs {
+                                index_count: draws[slot].1.num_elements,
+                                instance_count: 1,
+                                first_index: source.first_index,
+                                base_vertex: source.base_vertex,
+                                first_instance: slot as u32,
+                        };
+
+                        let at = |position: u32| {
+                                position as usize * DRAW_ARGS_SIZE as usize
+                                        ..(position as usize + 1) * DRAW_ARGS_SIZE as usize
+                        };
+
+                        depth_args[at(source.depth)].copy_from_slice(args.as_bytes());
+                        batch_args[at(source.batch_start + source.batch_position)]
+                                .copy_from_slice(args.as_bytes());
+                }
+
+                if self.depth_count == 0
+                {
+                        return;
+                }
+
+                let transforms: Vec<[[[f32; 4]; 4]; 2]> =
+                        draws.iter().map(|(_, _, transforms)| *transforms).collect();
+                let counts: Vec<u32> = std::iter::once(self.depth_count)
+                        .chain(self.batches.iter().map(|b| b.len))
+                        .collect();
+
+                queue.write_buffer(&self.buffers.sources, 0, bytemuck::cast_slice(&self.sources));
+                queue.write_buffer(&self.buffers.transforms, 0, bytemuck::cast_slice(&transforms));
+                queue.write_buffer(&self.buffers.depth_args, 0, &depth_args);
+                queue.write_buffer(&self.buffers.batch_args, 0, &batch_args);
+                queue.write_buffer(&self.buffers.counts, 0, bytemuck::cast_slice(&counts));
+        }
+
+        /// Binds the transforms and the shared buffers.
+        fn bind(
+                &self,
+                render_pass: &mut wgpu::RenderPass,
+        )
+        {
+                render_pass.set_bind_group(1, &self.buffers.transform_bind_group, &[]);
+                render_pass.set_vertex_buffer(0, self.shared.vertex_buffer.slice(..));
+                render_pass.set_index_buffer(
+                        self.shared.index_buffer.slice(..),
+                        wgpu::IndexFormat::Uint32,
+                );
+        }
+
+        /// Issues the `len` draws from `start` in `args`, as many as the
+        /// `count`th count when they're compacted.
+        fn issue(
+                &self,
+                render_pass: &mut wgpu::RenderPass,
+                args: &wgpu::Buffer,
+                start: u32,
+                count: u32,
+                len: u32,
+        )
+        {
+                if self.count
+                {
+                        render_pass.multi_draw_indexed_indirect_count(
+                                args,
+                                start as u64 * DRAW_ARGS_SIZE,
+                                &self.buffers.counts,
+                                count as u64 * size_of::<u32>() as u64,
+                                len,
+                        );
+                }
+                else
+                {
+                        render_pass.multi_draw_indexed_indirect(
+                                args,
+                                start as u64 * DRAW_ARGS_SIZE,
+                                len,
+                        );
+                }
+        }
+}
+
+/// The members of a crowd in the view, culled by
+/// [`GpuCulling::cull_crowd`] and drawn with one indirect draw per skinned
+/// mesh.
+#[derive(Debug)]
+pub struct CrowdCulling
+{
+        uniform_buffer: wgpu::Buffer,
+        /// The members in the view, laid out like the crowd's instance
+        /// buffer.
+        visible: wgpu::Buffer,
+        /// A `wgpu::util::DrawIndexedIndirectArgs` per skinned mesh.
+        args: wgpu::Buffer,
+        bind_group: wgpu::BindGroup,
+        count: u32,
+        meshes: u32,
+}
+
+impl CrowdCulling
+{
+        /// Skinned meshes it has draws for.
+        pub fn meshes(&self) -> u32
+        {
+                self.meshes
+        }
+
+        /// Instance buffer of the members in the view.
+        pub fn visible(&self) -> &wgpu::Buffer
+        {
+                &self.visible
+        }
+
+        /// Draws the `mesh`th skinned mesh for the members in the view.
+        pub fn draw(
+                &self,
+                render_pass: &mut wgpu::RenderPass,
+                mesh: u32,
+        )
+        {
+                render_pass.draw_indexed_indirect(&self.args, mesh as u64 * DRAW_ARGS_SIZE);
+        }
+}
+
+/// The compute pipelines and the draws of the frame, owned by the
+/// [`PipelineManager`].
+#[derive(Debug)]
+pub struct GpuCulling
+{
+        frustum_pipeline: wgpu::ComputePipeline,
+        occlusion_pipeline: wgpu::ComputePipeline,
+        copy_depth_pipeline: wgpu::ComputePipeline,
+        reduce_pipeline: wgpu::ComputePipeline,
+        crowd_pipeline: wgpu::ComputePipeline,
+
+        layouts: [wgpu::BindGroupLayout; 5],
+
+        uniform: CullingUniform,
+        uniform_buffer: wgpu::Buffer,
+        bounds_buffer: wgpu::Buffer,
+        /// A `wgpu::util::DrawIndexedIndirectArgs` per mesh, on the mesh's
+        /// own buffers.
+        args_buffer: wgpu::Buffer,
+        /// Draws the buffers have room for.
+        capacity: u32,
+        frustum_bind_group: wgpu::BindGroup,
+        multi_draw: Option<MultiDraw>,
+}
+
+impl GpuCulling
+{
+        /// Whether the device runs compute shaders and indirect draws with
+        /// enough storage bindings.
+        pub fn supported(
+                adapter: &wgpu::Adapter,
+                device: &wgpu::Device,
+        ) -> bool
+        {
+                let limits = device.limits();
+
+                adapter.get_downlevel_capabilities().flags.contains(
+                        wgpu::DownlevelFlags::COMPUTE_SHADERS
+                                | wgpu::DownlevelFlags::INDIRECT_EXECUTION,
+                ) && limits.max_storage_buffers_per_shader_stage >= 3
+                        && limits.max_storage_textures_per_shader_stage >= 1
+        }
+
+        /// Whether `mesh` of `model` can be drawn together with others, it
+        /// isn't skinned and the model has neither a custom shader nor a
+        /// reflection probe.
+        pub fn batchable(
+                model: &Model,
+                mesh: &Mesh,
+        ) -> bool
+        {
+                (model.skin.is_none() || mesh.skin_buffer.is_none())
+                        && model.shader.is_none()
+                        && model.reflection.is_none()
+        }
+
+        pub fn new(
+                device: &wgpu::Device,
+                cache: Option<&wgpu::PipelineCache>,
+        ) -> Self
+        {
+                let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                        label: Some("Culling Shader"),
+                        source: wgpu::ShaderSource::Wgsl(include_str!("culling.wgsl").into()),
+                });
+
+                let layouts = create_culling_bind_group_layouts(device);
+
+                let pipeline = |entry_point: &str, layout: &wgpu::BindGroupLayout| {
+                        let pipeline_layout =
+                                device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                                        label: Some("Culling Pipeline Layout"),
+                                        bind_group_layouts: &[layout],
+                                        push_constant_ranges: &[],
+                                });
+
+                        device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                                label: Some(entry_point),
+                                layout: Some(&pipeline_layout),
+                                module: &shader,
+                                entry_point: Some(entry_point),
+                                compilation_options: Default::default(),
+                                cache,
+                        })
+                };
+
+                let frustum_pipeline = pipeline("frustum", &layouts[0]);
+                let occlusion_pipeline = pipeline("occlusion", &layouts[1]);
+                let copy_depth_pipeline = pipeline("copy_depth", &layouts[2]);
+                let reduce_pipeline = pipeline("reduce", &layouts[3]);
+                let crowd_pipeline = pipeline("cull_crowd", &layouts[4]);
+
+                let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                        label: Some("Culling Buffer"),
+                        size: std::mem::size_of::<CullingUniform>() as u64,
+                        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                        mapped_at_creation: false,
+                });
+
+                let capacity = 256;
+                let (bounds_buffer, args_buffer) = Self::create_draw_buffers(device, capacity);
+
+                let frustum_bind_group = Self::create_frustum_bind_group(
+                        device,
+                        &layouts[0],
+                        &uniform_buffer,
+                        &bounds_buffer,
+                        &args_buffer,
+                );
+
+                let multi_draw = MultiDraw::available(device).map(|count| {
+                        let layout = MultiDraw::compact_layout(device);
+
+                        MultiDraw::new(
+                                device,
+                                count,
+                                pipeline("compact", &layout),
+                                layout,
+                                capacity,
+                                &uniform_buffer,
+                                &args_buffer,
+                        )
+                });
+
+                let mut uniform: CullingUniform = bytemuck::Zeroable::zeroed();
+                uniform.compact = multi_draw.as_ref().is_some_and(|m| m.count) as u32;
+
+                Self {
+                        frustum_pipeline,
+                        occlusion_pipeline,
+                        copy_depth_pipeline,
+                        reduce_pipeline,
+                        crowd_pipeline,
+                        layouts,
+                        uniform,
+                        uniform_buffer,
+                        bounds_buffer,
+                        args_buffer,
+                        capacity,
+                        frustum_bind_group,
+                        multi_draw,
+                }
+        }
+
+        fn create_draw_buffers(
+                device: &wgpu::Device,
+                capacity: u32,
+        ) -> (wgpu::Buffer, wgpu::Buffer)
+        {
+                let bounds = device.create_buffer(&wgpu::BufferDescriptor {
+                        label: Some("Culling Bounds Buffer"),
+                        size: capacity as u64 * std::mem::size_of::<DrawBounds>() as u64,
+                        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+                        mapped_at_creation: false,
+                });
+
+                let args = device.create_buffer(&wgpu::BufferDescriptor {
+                        label: Some("Culling Draw Buffer"),
+                        size: capacity as u64 * DRAW_ARGS_SIZE,
+                        usage: wgpu::BufferUsages::STORAGE
+                                | wgpu::BufferUsages::INDIRECT
+                                | wgpu::BufferUsages::COPY_DST,
+                        mapped_at_creation: false,
+                });
+
+                (bounds, args)
+        }
+
+        fn create_frustum_bind_group(
+                device: &wgpu::Device,
+                layout: &wgpu::BindGroupLayout,
+                uniform: &wgpu::Buffer,
+                bounds: &wgpu::Buffer,
+                args: &wgpu::Buffer,
+        ) -> wgpu::BindGroup
+        {
+                buffer_bind_group(
+                        device,
+                        "frustum_culling_bind_group",
+                        layout,
+                        &[(0, uniform), (1, bounds), (2, args)],
+                )
+        }
+
+        /// Whether meshes are drawn together, see
+        /// [`crate::renderer::culling`].
+        pub fn multi_draw(&self) -> bool
+        {
+                self.multi_draw.is_some()
+        }
+
+        /// Layout of `@group(1)` of the `CULLED` permutations, when meshes
+        /// are drawn together.
+        pub fn draw_transform_layout(&self) -> Option<&wgpu::BindGroupLayout>
+        {
+                self.multi_draw.as_ref().map(|m| &m.transform_layout)
+        }
+
+        /// Uploads the bounds of the meshes of the visible `models` and
+        /// draws them all until a culling pass turns some off. Meshes with
+        /// their `CULLED` permutation in `permutations` are drawn together.
+        pub fn prepare(
+                &mut self,
+                device: &wgpu::Device,
+                queue: &wgpu::Queue,
+                models: &HashMap<String, Model>,
+                permutations: &HashMap<ShaderDefines, wgpu::RenderPipeline>,
+        )
+        {
+                let mut bounds = Vec::new();
+                let mut args = Vec::new();
+                let mut draws = Vec::new();
+
+                // In the order the model passes draw them.
+                for model in models.values().filter(|m| m.visible)
+                {
+                        let transform = model.calculate_transform();
+
+                        for mesh in model.meshes.iter()
+                        {
+                                let world = mesh.bounds.transform(&transform);
+
+                                bounds.push(DrawBounds {
+                                        min: world.min.into(),
+                                        index_count: mesh.num_elements,
+                                        max: world.max.into(),
+                                        _padding: 0,
+                                });
+
+                                args.extend_from_slice(
+                                        wgpu::util::DrawIndexedIndirectArgs {
+                                                index_count: mesh.num_elements,
+                                                instance_count: 1,
+                                                first_index: 0,
+                                                base_vertex: 0,
+                                                first_instance: 0,
+                                        }
+                                        .as_bytes(),
+                                );
+
+                                draws.push((
+                                        model,
+                                        mesh,
+                                        [mesh.transform.into(), transform.into()],
+                                ));
+                        }
+                }
+
+                let count = bounds.len() as u32;
+
+                if count > self.capacity
+                {
+                        self.capacity = count.next_power_of_two();
+
+                        (self.bounds_buffer, self.args_buffer) =
+                                Self::create_draw_buffers(device, self.capacity);
+
+                        self.frustum_bind_group = Self::create_frustum_bind_group(
+                                device,
+                                &self.layouts[0],
+                                &self.uniform_buffer,
+                                &self.bounds_buffer,
+                                &self.args_buffer,
+                        );
+
+                        if let Some(multi_draw) = &mut self.multi_draw
+                        {
+                                multi_draw.buffers = MultiDrawBuffers::new(
+                                        device,
+                                        self.capacity,
+                                        &multi_draw.compact_layout,
+                                        &multi_draw.transform_layout,
+                                        &self.uniform_buffer,
+                                        &self.args_buffer,
+                                );
+                        }
+                }
+
+                self.uniform.draw_count = count;
+
+                if count > 0
+                {
+                        queue.write_buffer(&self.bounds_buffer, 0, bytemuck::cast_slice(&bounds));
+                        queue.write_buffer(&self.args_buffer, 0, &args);
+                }
+
+                if let Some(multi_draw) = &mut self.multi_draw
+                {
+                        multi_draw.prepare(device, queue, &draws, permutations);
+                }
+        }
+
+        /// Number of draws prepared this frame.
+        pub fn count(&self) -> u32
+        {
+                self.uniform.draw_count
+        }
+
+        /// Sets the camera the draws are culled for, `viewport` is where the
+        /// scene is on the `width` by `height` depth texture in pixels. Must
+        /// be called after [`GpuCulling::prepare`].
+        pub fn set_view(
+                &mut self,
+                queue: &wgpu::Queue,
+                view_proj: [[f32; 4]; 4],
+                viewport: [f32; 4],
+                (width, height): (u32, u32),
+                reversed_z: bool,
+        )
+        {
+                self.uniform.view_proj = view_proj;
+                self.uniform.viewport = viewport;
+                self.uniform.reversed_z = reversed_z as u32;
+                self.uniform.hiz_levels = mip_levels(width, height);
+
+                queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[self.uniform]));
+        }
+
+        /// Draws the mesh prepared in `slot` with its indirect draw, false
+        /// when there's no such slot this frame.
+        pub fn draw_mesh<'a>(
+                &self,
+                render_pass: &mut wgpu::RenderPass<'a>,
+                slot: u32,
+                mesh: &'a Mesh,
+        ) -> bool
+        {
+                if slot >= self.count()
+                {
+                        return false;
+                }
+
+                render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+                render_pass
+                        .set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                render_pass.draw_indexed_indirect(&self.args_buffer, slot as u64 * DRAW_ARGS_SIZE);
+
+                true
+        }
+
+        /// Draws the depth of the meshes drawn together with one
+        /// multi-draw. Leaves its own pipeline set.
+        pub fn draw_depth(
+                &self,
+                render_pass: &mut wgpu::RenderPass,
+        )
+        {
+                let Some((multi_draw, pipeline)) = self
+                        .multi_draw
+                        .as_ref()
+                        .filter(|m| m.depth_count > 0)
+                        .and_then(|m| Some((m, m.depth_pipeline.as_ref()?)))
+                else
+                {
+                        return;
+                };
+
+                render_pass.set_pipeline(pipeline);
+                multi_draw.bind(render_pass);
+                multi_draw.issue(
+                        render_pass,
+                        &multi_draw.buffers.depth_args,
+                        0,
+                        0,
+                        multi_draw.depth_count,
+                );
+        }
+
+        /// Draws the batches of the geometry pass, one multi-draw each with
+        /// its pipeline and material. Leaves the last one's set.
+        pub fn draw_batches(
+                &self,
+                render_pass: &mut wgpu::RenderPass,
+        )
+        {
+                let Some(multi_draw) = self.multi_draw.as_ref().filter(|m| !m.batches.is_empty())
+                else
+                {
+                        return;
+                };
+
+                multi_draw.bind(render_pass);
+
+                for (index, batch) in multi_draw.batches.iter().enumerate()
+                {
+                        render_pass.set_pipeline(&batch.pipeline);
+                        render_pass.set_bind_group(2, &batch.material, &[]);
+                        multi_draw.issue(
+                                render_pass,
+                                &multi_draw.buffers.batch_args,
+                                batch.start,
+                                index as u32 + 1,
+                                batch.len,
+                        );
+                }
+        }
+
+        /// Whether the mesh prepared in `slot` is drawn together with
+        /// others instead of by [`GpuCulling::draw_mesh`].
+        pub fn in_batch(
+                &self,
+                slot: u32,
+        ) -> bool
+        {
+                self.multi_draw
+                        .as_ref()
+                        .and_then(|m| m.sources.get(slot as usize))
+                        .is_some_and(|s| s.depth != NONE)
+        }
+
+        /// Culling of the `count` members in the instance buffer
+        /// `instances`, drawn with `meshes` skinned meshes.
+        pub fn create_crowd_culling(
+                &self,
+                device: &wgpu::Device,
+                instances: &wgpu::Buffer,
+                count: u32,
+                meshes: u32,
+        ) -> CrowdCulling
+        {
+                let uniform_buffer = create_buffer(
+                        device,
+                        "Crowd Culling Buffer",
+                        size_of::<CrowdCullingUniform>() as u64,
+                        wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                );
+                let visible = create_buffer(
+                        device,
+                        "Crowd Visible Instance Buffer",
+                        instances.size(),
+                        wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::STORAGE,
+                );
+                let args = create_buffer(
+                        device,
+                        "Crowd Draw Buffer",
+                        meshes.max(1) as u64 * DRAW_ARGS_SIZE,
+                        wgpu::BufferUsages::STORAGE
+                                | wgpu::BufferUsages::INDIRECT
+                                | wgpu::BufferUsages::COPY_DST,
+                );
+
+                CrowdCulling {
+                        bind_group: buffer_bind_group(
+                                device,
+                                "crowd_culling_bind_group",
+                                &self.layouts[4],
+                                &[
+                                        (0, &self.uniform_buffer),
+                                        (11, &uniform_buffer),
+                                        (12, instances),
+                                        (13, &visible),
+                                        (14, &args),
+                                ],
+                        ),
+                        uniform_buffer,
+                        visible,
+                        args,
+                        count,
+                        meshes,
+                }
+        }
+
+        /// Lists the members of `crowd` in the view. `model` is the crowd's
+        /// model, the `index_counts` those of its skinned meshes, `bounds`
+        /// its bounds grown by half their size since the animation may
+        /// reach beyond the rest pose.
+        pub fn cull_crowd(
+                &self,
+                queue: &wgpu::Queue,
+                encoder: &mut wgpu::CommandEncoder,
+                crowd: &CrowdCulling,
+                model: cgmath::Matrix4<f32>,
+                bounds: Aabb,
+                index_counts: &[u32],
+        )
+        {
+                let size = bounds.size();
+                let grow = size.x.max(size.y).max(size.z) * 0.5;
+
+                queue.write_buffer(
+                        &crowd.uniform_buffer,
+                        0,
+                        bytemuck::bytes_of(&CrowdCullingUniform {
+                                model: model.into(),
+                                min: bounds.min.map(|v| v - grow).into(),
+                                count: crowd.count,
+                                max: bounds.max.map(|v| v + grow).into(),
+                                meshes: crowd.meshes,
+                        }),
+                );
+
+                let args: Vec<u8> = index_counts
+                        .iter()
+                        .flat_map(|&index_count| {
+                                wgpu::util::DrawIndexedIndirectArgs {
+                                        index_count,
+                                        instance_count: 0,
+                                        first_index: 0,
+                                        base_vertex: 0,
+                                        first_instance: 0,
+                                }
+                                .as_bytes()
+                                .to_vec()
+                        })
+                        .collect();
+
+                if !args.is_empty()
+                {
+                        queue.write_buffer(&crowd.args, 0, &args);
+                }
+
+                let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                        label: Some("Crowd Culling"),
+                        timestamp_writes: None,
+                });
+
+                pass.set_pipeline(&self.crowd_pipeline);
+                pass.set_bind_group(0, &crowd.bind_group, &[]);
+                pass.dispatch_workgroups(crowd.count.div_ceil(WORKGROUP_SIZE), 1, 1);
+        }
+
+        /// Clears the counts of the compacted draws before they're
+        /// compacted again.
+        fn clear_counts(
+                &self,
+                encoder: &mut wgpu::CommandEncoder,
+        )
+        {
+                if let Some(multi_draw) = self.multi_draw.as_ref().filter(|m| m.count)
+                {
+                        encoder.clear_buffer(&multi_draw.buffers.counts, 0, None);
+                }
+        }
+
+        /// Writes the culled draws of the meshes drawn together into the
+        /// multi-draws.
+        fn compact(
+                &self,
+                pass: &mut wgpu::ComputePass,
+        )
+        {
+                if let Some(multi_draw) = self.multi_draw.as_ref().filter(|m| m.depth_count > 0)
+                {
+                        pass.set_pipeline(&multi_draw.compact_pipeline);
+                        pass.set_bind_group(0, &multi_draw.buffers.compact_bind_group, &[]);
+                        pass.dispatch_workgroups(self.count().div_ceil(WORKGROUP_SIZE), 1, 1);
+                }
+        }
+
+        fn cull_frustum(
+                &self,
+                encoder: &mut wgpu::CommandEncoder,
+                label: &str,
+        )
+        {
+                self.clear_counts(encoder);
+
+                let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                        label: Some(label),
+                        timestamp_writes: None,
+                });
+
+                pass.set_pipeline(&self.frustum_pipeline);
+                pass.set_bind_group(0, &self.frustum_bind_group, &[]);
+                pass.dispatch_workgroups(self.count().div_ceil(WORKGROUP_SIZE), 1, 1);
+
+                self.compact(&mut pass);
+        }
+
+        fn create_occlusion_bind_group(
+                &self,
+                device: &wgpu::Device,
+                hiz: &wgpu::TextureView,
+        ) -> wgpu::BindGroup
+        {
+                device.create_bind_group(&wgpu::BindGroupDescriptor {
+                        layout: &self.layouts[1],
+                        entries: &[
+                                wgpu::BindGroupEntry {
+                                        binding: 0,
+                                        resource: self.uniform_buffer.as_entire_binding(),
+                                },
+                                wgpu::BindGroupEntry {
+                                        binding: 1,
+                                        resource: self.bounds_buffer.as_entire_binding(),
+                                },
+                                wgpu::BindGroupEntry {
+                                        binding: 2,
+                                        resource: self.args_buffer.as_entire_binding(),
+                                },
+                                wgpu::BindGroupEntry {
+                                        binding: 3,
+                                        resource: wgpu::BindingResource::TextureView(hiz),
+                                },
+                        ],
+                        label: Some("occlusion_culling_bind_group"),
+                })
+        }
+
+        fn cull_occlusion(
+                &self,
+                encoder: &mut wgpu::CommandEncoder,
+                label: &str,
+                hiz: &Hiz,
+        )
+        {
+                self.clear_counts(encoder);
+
+                let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                        label: Some(label),
+                        timestamp_writes: None,
+                });
+
+                let groups = |width: u32, height: u32| {
+                        (width.div_ceil(HIZ_WORKGROUP_SIZE), height.div_ceil(HIZ_WORKGROUP_SIZE))
+                };
+
+                let (x, y) = groups(hiz.width, hiz.height);
+
+                pass.set_pipeline(&self.copy_depth_pipeline);
+                pass.set_bind_group(0, &hiz.copy_bind_group, &[]);
+                pass.dispatch_workgroups(x, y, 1);
+
+                pass.set_pipeline(&self.reduce_pipeline);
+
+                for (level, bind_group) in hiz.reduce_bind_groups.iter().enumerate()
+                {
+                        let level = level as u32 + 1;
+                        let (x, y) =
+                                groups((hiz.width >> level).max(1), (hiz.height >> level).max(1));
+
+                        pass.set_bind_group(0, bind_group, &[]);
+                        pass.dispatch_workgroups(x, y, 1);
+                }
+
+                pass.set_pipeline(&self.occlusion_pipeline);
+                pass.set_bind_group(0, &hiz.occlusion.1, &[]);
+                pass.dispatch_workgroups(self.count().div_ceil(WORKGROUP_SIZE), 1, 1);
+
+                self.compact(&mut pass);
+        }
+}
+
+/// Levels of a full mip chain of a `width` by `height` texture.
+fn mip_levels(
+        width: u32,
+        height: u32,
+) -> u32
+{
+        u32::BITS - width.max(height).max(1).leading_zeros()
+}
+
+/// The depth pyramid, the size of the depth texture, with the bind groups
+/// building it and culling against it.
+#[derive(Debug)]
+struct Hiz
+{
+        width: u32,
+        height: u32,
+        /// The depth texture the pyramid is built from.
+        depth: wgpu::TextureView,
+        /// Every mip level.
+        view: wgpu::TextureView,
+        copy_bind_group: wgpu::BindGroup,
+        /// One per mip level after the first.
+        reduce_bind_groups: Vec<wgpu::BindGroup>,
+        /// With the draw buffer of [`GpuCulling`] it binds, which is
+        /// replaced when the draws outgrow it.
+        occlusion: (wgpu::Buffer, wgpu::BindGroup),
+}
+
+impl Hiz
+{
+        fn new(
+                device: &wgpu::Device,
+                culling: &GpuCulling,
+                depth_texture: &Texture,
+        ) -> Self
+        {
+                let wgpu::Extent3d {
+                        width,
+                        height,
+                        ..
+                } = depth_texture.texture.size();
+                let mip_level_count = mip_levels(width, height);
+
+                let texture = device.create_texture(&wgpu::TextureDescriptor {
+                        label: Some("Hi-Z"),
+                        size: wgpu::Extent3d {
+                                width,
+                                height,
+                                depth_or_array_layers: 1,
+                        },
+                        mip_level_count,
+                        sample_count: 1,
+                        dimension: wgpu::TextureDimension::D2,
+                        format: wgpu::TextureFormat::R32Float,
+                        usage: wgpu::TextureUsages::STORAGE_BINDING
+                                | wgpu::TextureUsages::TEXTURE_BINDING,
+                        view_formats: &[],
+                });
+
+                let levels: Vec<wgpu::TextureView> = (0..mip_level_count)
+                        .map(|level| {
+                                texture.create_view(&wgpu::TextureViewDescriptor {
+                                        label: Some("Hi-Z Level"),
+                                        base_mip_level: level,
+                                        mip_level_count: Some(1),
+                                        ..Default::default()
+                                })
+                        })
+                        .collect();
+
+                let copy_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                        layout: &culling.layouts[2],
+                        entries: &[
+                                wgpu::BindGroupEntry {
+                                        binding: 0,
+                                        resource: culling.uniform_buffer.as_entire_binding(),
+                                },
+                                wgpu::BindGroupEntry {
+                                        binding: 4,
+                                        resource: wgpu::BindingResource::TextureView(
+                                                &depth_texture.view,
+                                        ),
+                                },
+                                wgpu::BindGroupEntry {
+                                        binding: 6,
+                                        resource: wgpu::BindingResource::TextureView(&levels[0]),
+                                },
+                        ],
+                        label: Some("hiz_copy_bind_group"),
+                });
+
+                let reduce_bind_groups = levels
+                        .windows(2)
+                        .map(|levels| {
+                                device.create_bind_group(&wgpu::BindGroupDescriptor {
+                                        layout: &culling.layouts[3],
+                                        entries: &[
+                                                wgpu::BindGroupEntry {
+                                                        binding: 5,
+                                                        resource:
+                                                                wgpu::BindingResource::TextureView(
+                                                                        &levels[0],
+                                                                ),
+                                                },
+                                                wgpu::BindGroupEntry {
+                                                        binding: 6,
+                                                        resource:
+                                                                wgpu::BindingResource::TextureView(
+                                                                        &levels[1],
+                                                                ),
+                                                },
+                                        ],
+                                        label: Some("hiz_reduce_bind_group"),
+                                })
+                        })
+                        .collect();
+
+                let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+                Self {
+                        width,
+                        height,
+                        depth: depth_texture.view.clone(),
+                        occlusion: (
+                                culling.args_buffer.clone(),
+                                culling.create_occlusion_bind_group(device, &view),
+                        ),
+                        view,
+                        copy_bind_group,
+                        reduce_bind_groups,
+                }
+        }
+
+        /// Whether the pyramid was built for `depth_texture`.
+        fn matches(
+                &self,
+                depth_texture: &Texture,
+        ) -> bool
+        {
+                let size = depth_texture.texture.size();
+
+                self.width == size.width
+                        && self.height == size.height
+                        && self.depth == depth_texture.view
+        }
+
+        /// Binds the draw buffers of `culling` again when they were replaced.
+        fn update(
+                &mut self,
+                device: &wgpu::Device,
+                culling: &GpuCulling,
+        )
+        {
+                if self.occlusion.0 != culling.args_buffer
+                {
+                        self.occlusion = (
+                                culling.args_buffer.clone(),
+                                culling.create_occlusion_bind_group(device, &self.view),
+                        );
+                }
+        }
+}
+
+/// Turns off the draws of meshes outside of the view, see
+/// [`crate::renderer::culling`].
+pub struct FrustumCullingPass
+{
+        pub name: String,
+        pub enabled: bool,
+}
+
+impl FrustumCullingPass
+{
+        pub fn new() -> Self
+        {
+                Self {
+                        name: "frustum_culling".to_string(),
+                        enabled: true,
+                }
+        }
+}
+
+impl Default for FrustumCullingPass
+{
+        fn default() -> Self
+        {
+                Self::new()
+        }
+}
+
+impl RenderPass for FrustumCullingPass
+{
+        fn name(&self) -> &str
+        {
+                self.name.as_str()
+        }
+
+        fn as_any(&self) -> &dyn Any
+        {
+                self
+        }
+
+        fn as_any_mut(&mut self) -> &mut dyn Any
+        {
+                self
+        }
+
+        #[cfg(feature = "ui")]
+        fn ui(
+                &mut self,
+                ui: &mut egui::Ui,
+        )
+        {
+                egui::CollapsingHeader::new(&self.name)
+                        .default_open(true)
+                        .show(ui, |ui| {
+                                ui.label("Skips the meshes outside of the view on the GPU.");
+                        });
+        }
+
+        fn enabled(&mut self) -> bool
+        {
+                self.enabled
+        }
+
+        fn set_enabled(
+                &mut self,
+                value: bool,
+        )
+        {
+                self.enabled = value
+        }
+
+        fn record(
+                &mut self,
+                #[allow(unused_variables)] view: &wgpu::TextureView,
+                encoder: &mut wgpu::CommandEncoder,
+                #[allow(unused_variables)] camera: &wgpu::BindGroup,
+                pipeline_manager: &PipelineManager,
+                #[allow(unused_variables)] depth_texture: &Texture,
+                #[allow(unused_variables)] models: Option<&HashMap<String, Model>>,
+                #[allow(unused_variables)] device: &wgpu::Device,
+                #[allow(unused_variables)] queue: &wgpu::Queue,
+        )
+        {
+                if let Some(culling) = pipeline_manager.culling.as_ref().filter(|c| c.count() > 0)
+                {
+                        culling.cull_frustum(encoder, &self.name);
+                }
+        }
+}
+
+/// Turns off the draws of meshes behind the depth of the
+/// [`DepthPrePass`](crate::renderer::graph::DepthPrePass), see
+/// [`crate::renderer::culling`].
+pub struct OcclusionCullingPass
+{
+        pub name: String,
+        pub enabled: bool,
+        hiz: Option<Hiz>,
+}
+
+impl OcclusionCullingPass
+{
+        pub fn new() -> Self
+        {
+                Self {
+                        name: "occlusion_culling".to_string(),
+                        enabled: true,
+                        hiz: None,
+                }
+        }
+}
+
+impl Default for OcclusionCullingPass
+{
+        fn default() -> Self
+        {
+                Self::new()
+        }
+}
+
+impl RenderPass for OcclusionCullingPass
+{
+        fn name(&self) -> &str
+        {
+                self.name.as_str()
+        }
+
+        fn as_any(&self) -> &dyn Any
+        {
+                self
+        }
+
+        fn as_any_mut(&mut self) -> &mut dyn Any
+        {
+                self
+        }
+
+        #[cfg(feature = "ui")]
+        fn ui(
+                &mut self,
+                ui: &mut egui::Ui,
+        )
+        {
+                egui::CollapsingHeader::new(&self.name)
+                        .default_open(true)
+                        .show(ui, |ui| {
+                                ui.label("Skips shading the meshes hidden behind the depth pre-pass.");
+                        });
+        }
+
+        fn enabled(&mut self) -> bool
+        {
+                self.enabled
+        }
+
+        fn attachments(&self) -> Vec<Attachment>
+        {
+                // Read in the shader, not rendered to.
+                vec![Attachment::new(DEPTH, AttachmentLoad::Load, false)]
+        }
+
+        fn set_enabled(
+                &mut self,
+                value: bool,
+        )
+        {
+                self.enabled = value
+        }
+
+        fn record(
+                &mut self,
+                #[allow(unused_variables)] view: &wgpu::TextureView,
+                encoder: &mut wgpu::CommandEncoder,
+                #[allow(unused_variables)] camera: &wgpu::BindGroup,
+                pipeline_manager: &PipelineManager,
+                depth_texture: &Texture,
+                #[allow(unused_variables)] models: Option<&HashMap<String, Model>>,
+                device: &wgpu::Device,
+                #[allow(unused_variables)] queue: &wgpu::Queue,
+        )
+        {
+                let Some(culling) = pipeline_manager.culling.as_ref().filter(|c| c.count() > 0)
+                else
+                {
+                        return;
+                };
+
+                if !pipeline_manager.depth_prepass_active()
+                {
+                        return;
+                }
+
+                if self.hiz
+                        .as_ref()
+                        .is_none_or(|hiz| !hiz.matches(depth_texture))
+                {
+                        self.hiz = Some(Hiz::new(device, culling, depth_texture));
+                }
+
+                if let Some(hiz) = self.hiz.as_mut()
+                {
+                        hiz.update(device, culling);
+                        culling.cull_occlusion(encoder, &self.name, hiz);
+                }
+        }
+}
+
+#[cfg(feature = "runtime")]
+impl EngineState
+{
+        /// Creates the [`GpuCulling`] when the device supports it, and the
+        /// depth pipeline of the meshes drawn together.
+        pub fn build_culling(&mut self)
+        {
+                if self.pipeline_manager.culling.is_none()
+                {
+                        if !GpuCulling::supported(&self.adapter, &self.device)
+                        {
+                                log::info!("GPU culling unsupported, meshes are drawn directly");
+                                return;
+                        }
+
+                        self.pipeline_manager.culling = Some(GpuCulling::new(
+                                &self.device,
+                                self.pipeline_manager.cache.as_ref(),
+                        ));
+                }
+
+                let Some(transform_layout) = self
+                        .pipeline_manager
+                        .culling
+                        .as_ref()
+                        .and_then(|c| c.draw_transform_layout())
+                        .cloned()
+                else
+                {
+                        return;
+                };
+
+                let depth_pipeline = self.pipeline_manager.create_depth_prepass_pipeline(
+                        &self.device,
+                        &[
+                                &self.camera.get_bind_group_layout(&self.device),
+                                &transform_layout,
+                        ],
+                        &ShaderDefines::new().with("CULLED"),
+                );
+
+                if let Some(multi_draw) = self
+                        .pipeline_manager
+                        .culling
+                        .as_mut()
+                        .and_then(|c| c.multi_draw.as_mut())
+                {
+                        multi_draw.depth_pipeline = Some(depth_pipeline);
+                }
+        }
+
+        /// The `CULLED` permutations of the meshes drawn together, none
+        /// without multi-draws or with the wireframe overlay, which draws
+        /// the meshes unindexed.
+        pub(crate) fn culled_permutations(&self) -> Vec<ShaderDefines>
+        {
+                let multi_draw = self
+                        .pipeline_manager
+                        .culling
+                        .as_ref()
+                        .is_some_and(|c| c.multi_draw());
+
+                if !multi_draw || self.pipeline_manager.fill_mode == FillMode::WireframeOverlay
+                {
+                        return Vec::new();
+                }
+
+                self.models
+                        .values()
+                        .flat_map(|model| {
+                                model.meshes
+                                        .iter()
+                                        .filter(|mesh| GpuCulling::batchable(model, mesh))
+                                        .map(|mesh| model.defines(mesh).with("CULLED"))
+                        })
+                        .collect()
+        }
+
+        /// Builds a `CULLED` permutation, which takes the transforms of its
+        /// draws in `@group(1)` and has no `@group(3)`.
+        pub(crate) fn build_culled_permutation(
+                &mut self,
+                defines: &ShaderDefines,
+                material_bind_group_layout: &wgpu::BindGroupLayout,
+        )
+        {
+                let Some(transform_layout) = self
+                        .pipeline_manager
+                        .culling
+                        .as_ref()
+                        .and_then(|c| c.draw_transform_layout())
+                        .cloned()
+                else
+                {
+                        return;
+                };
+
+                self.pipeline_manager.build_geometry_permutation(
+                        &self.device,
+                        &self.surface_manager.render_configuration(),
+                        &[
+                                &self.camera.get_bind_group_layout(&self.device),
+                                &transform_layout,
+                                material_bind_group_layout,
+                        ],
+                        defines,
+                );
+        }
+
+        /// Uploads the draws of the frame, see [`GpuCulling::prepare`].
+        pub fn prepare_culling(&mut self)
+        {
+                let configuration = &self.surface_manager.configuration;
+                let size = (configuration.width, configuration.height);
+                let viewport = self.pipeline_manager.viewport_rect(size.0, size.1);
+                let reversed_z = self.pipeline_manager.reversed_z;
+                let pipeline_manager = &mut self.pipeline_manager;
+
+                if let Some(culling) = pipeline_manager.culling.as_mut()
+                {
+                        culling.prepare(
+                                &self.device,
+                                &self.queue,
+                                &self.models,
+                                &pipeline_manager.geometry_permutations,
+                        );
+                        culling.set_view(
+                                &self.queue,
+                                self.camera.uniform.view_proj,
+                                viewport,
+                                size,
+                                reversed_z,
+                        );
+                }
+        }
+}
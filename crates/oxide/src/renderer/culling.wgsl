@@ -0,0 +1,297 @@
+// Frustum and occlusion culling of the mesh draws, the depth pyramid the
+// occlusion test reads, the compaction of the draws of meshes drawn
+// together and the culling of crowd members. See `culling.rs`.
+
+struct Culling {
+    view_proj: mat4x4<f32>,
+    // Where the scene is on the depth texture in pixels, offset in `xy`
+    // and size in `zw`.
+    viewport: vec4<f32>,
+    draw_count: u32,
+    reversed_z: u32,
+    // Mip levels of the depth pyramid.
+    hiz_levels: u32,
+    // The visible draws of meshes drawn together are compacted and
+    // counted, they're left in place otherwise.
+    compact: u32,
+};
+
+// World bounds of a mesh.
+struct DrawBounds {
+    min: vec3<f32>,
+    index_count: u32,
+    max: vec3<f32>,
+    _padding: u32,
+};
+
+// `wgpu::util::DrawIndexedIndirectArgs`.
+struct DrawArgs {
+    index_count: u32,
+    instance_count: u32,
+    first_index: u32,
+    base_vertex: i32,
+    first_instance: u32,
+};
+
+// Where a draw is in the shared buffers and the multi-draws, `depth` is
+// `NONE` when the mesh is drawn alone.
+struct DrawSource {
+    first_index: u32,
+    base_vertex: i32,
+    depth: u32,
+    batch: u32,
+    batch_start: u32,
+    batch_position: u32,
+};
+
+// A crowd, its members are tested with the bounds of its model.
+struct CrowdCulling {
+    model: mat4x4<f32>,
+    min: vec3<f32>,
+    count: u32,
+    max: vec3<f32>,
+    meshes: u32,
+};
+
+// `CrowdInstanceRaw`, see `crowd.rs`.
+struct CrowdMember {
+    transform: mat4x4<f32>,
+    animation: vec4<f32>,
+};
+
+// `DrawArgs` counting the members in the view.
+struct CrowdDrawArgs {
+    index_count: u32,
+    instance_count: atomic<u32>,
+    first_index: u32,
+    base_vertex: i32,
+    first_instance: u32,
+};
+
+const NONE: u32 = 0xffffffffu;
+
+@group(0) @binding(0) var<uniform> culling: Culling;
+@group(0) @binding(1) var<storage, read> bounds: array<DrawBounds>;
+@group(0) @binding(2) var<storage, read_write> draws: array<DrawArgs>;
+// The depth pyramid, every mip level.
+@group(0) @binding(3) var hiz: texture_2d<f32>;
+@group(0) @binding(4) var depth: texture_depth_2d;
+// The level below the one written.
+@group(0) @binding(5) var hiz_source: texture_2d<f32>;
+@group(0) @binding(6) var hiz_target: texture_storage_2d<r32float, write>;
+@group(0) @binding(7) var<storage, read> sources: array<DrawSource>;
+// The draws of the depth pre-pass, and of the batches of the geometry pass
+// one after the other.
+@group(0) @binding(8) var<storage, read_write> depth_draws: array<DrawArgs>;
+@group(0) @binding(9) var<storage, read_write> batch_draws: array<DrawArgs>;
+// Draws issued of the depth pre-pass, then of every batch.
+@group(0) @binding(10) var<storage, read_write> counts: array<atomic<u32>>;
+@group(0) @binding(11) var<uniform> crowd: CrowdCulling;
+@group(0) @binding(12) var<storage, read> members: array<CrowdMember>;
+@group(0) @binding(13) var<storage, read_write> visible_members: array<CrowdMember>;
+// One per skinned mesh of the crowd.
+@group(0) @binding(14) var<storage, read_write> crowd_draws: array<CrowdDrawArgs>;
+
+// Corner `i` of the box from `low` to `high`.
+fn box_corner(low: vec3<f32>, high: vec3<f32>, i: u32) -> vec4<f32> {
+    return vec4<f32>(select(low, high, vec3<bool>((i & 1u) != 0u, (i & 2u) != 0u, (i & 4u) != 0u)), 1.0);
+}
+
+// Corner `i` of the bounds in clip space.
+fn corner(b: DrawBounds, i: u32) -> vec4<f32> {
+    return culling.view_proj * box_corner(b.min, b.max, i);
+}
+
+// Whether the box from `low` to `high`, taken to clip space by `to_clip`,
+// is outside the view. Outside when every corner is beyond the same clip
+// plane.
+fn outside_view(low: vec3<f32>, high: vec3<f32>, to_clip: mat4x4<f32>) -> bool {
+    var below = vec3<bool>(true);
+    var above = vec3<bool>(true);
+
+    for (var c = 0u; c < 8u; c += 1u) {
+        let p = to_clip * box_corner(low, high, c);
+
+        below = below & (p.xyz < vec3<f32>(-p.w, -p.w, 0.0));
+        above = above & (p.xyz > vec3<f32>(p.w));
+    }
+
+    return any(below) || any(above);
+}
+
+// Depth growing with distance, whether it's reversed or not.
+fn distance_depth(z: f32) -> f32 {
+    return select(z, 1.0 - z, culling.reversed_z != 0u);
+}
+
+// Turns off the draws of meshes outside the view.
+@compute @workgroup_size(64)
+fn frustum(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.x;
+
+    if i >= culling.draw_count {
+        return;
+    }
+
+    let b = bounds[i];
+
+    if outside_view(b.min, b.max, culling.view_proj) {
+        draws[i].instance_count = 0u;
+    }
+}
+
+// Turns off the draws of meshes behind the depth in the pyramid. The
+// screen rectangle of the bounds is covered by at most 2x2 texels of the
+// level picked, the nearest corner must be farther than all of them.
+@compute @workgroup_size(64)
+fn occlusion(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.x;
+
+    if i >= culling.draw_count || draws[i].instance_count == 0u {
+        return;
+    }
+
+    let b = bounds[i];
+
+    var low = vec2<f32>(1.0);
+    var high = vec2<f32>(0.0);
+    var nearest = 1.0;
+
+    for (var c = 0u; c < 8u; c += 1u) {
+        let p = corner(b, c);
+
+        // Crosses the near plane, the camera may be inside.
+        if p.w <= 0.0 {
+            return;
+        }
+
+        let ndc = p.xyz / p.w;
+        let uv = vec2<f32>(ndc.x * 0.5 + 0.5, 0.5 - ndc.y * 0.5);
+
+        low = min(low, uv);
+        high = max(high, uv);
+        nearest = min(nearest, distance_depth(ndc.z));
+    }
+
+    let origin = culling.viewport.xy;
+    let last = origin + culling.viewport.zw - 1.0;
+
+    let first_pixel = vec2<u32>(clamp(floor(origin + clamp(low, vec2<f32>(0.0), vec2<f32>(1.0)) * culling.viewport.zw), origin, last));
+    let last_pixel = vec2<u32>(clamp(floor(origin + clamp(high, vec2<f32>(0.0), vec2<f32>(1.0)) * culling.viewport.zw), origin, last));
+
+    let extent = last_pixel - first_pixel + 1u;
+    let level = min(culling.hiz_levels - 1u, u32(ceil(log2(f32(max(extent.x, extent.y))))));
+
+    let texels = textureDimensions(hiz, level) - 1u;
+    let a = min(first_pixel >> vec2<u32>(level), texels);
+    let z = min(last_pixel >> vec2<u32>(level), texels);
+
+    let farthest = max(
+        max(textureLoad(hiz, a, level).r, textureLoad(hiz, vec2<u32>(z.x, a.y), level).r),
+        max(textureLoad(hiz, vec2<u32>(a.x, z.y), level).r, textureLoad(hiz, z, level).r),
+    );
+
+    if nearest > farthest {
+        draws[i].instance_count = 0u;
+    }
+}
+
+// The first level of the pyramid, the depth texture as distance.
+@compute @workgroup_size(8, 8)
+fn copy_depth(@builtin(global_invocation_id) id: vec3<u32>) {
+    if any(id.xy >= textureDimensions(depth)) {
+        return;
+    }
+
+    textureStore(hiz_target, id.xy, vec4<f32>(distance_depth(textureLoad(depth, id.xy, 0)), 0.0, 0.0, 1.0));
+}
+
+// The farthest depth of the texels below a texel of the next level.
+@compute @workgroup_size(8, 8)
+fn reduce(@builtin(global_invocation_id) id: vec3<u32>) {
+    let size = textureDimensions(hiz_target);
+
+    if any(id.xy >= size) {
+        return;
+    }
+
+    let source = textureDimensions(hiz_source);
+
+    // The odd row or column at the far edge of the level below would be
+    // left out otherwise.
+    let extra = select(vec2<u32>(0u), source & vec2<u32>(1u), id.xy == size - 1u);
+
+    var farthest = 0.0;
+
+    for (var y = 0u; y < 2u + extra.y; y += 1u) {
+        for (var x = 0u; x < 2u + extra.x; x += 1u) {
+            let texel = min(id.xy * 2u + vec2<u32>(x, y), source - 1u);
+
+            farthest = max(farthest, textureLoad(hiz_source, texel, 0).r);
+        }
+    }
+
+    textureStore(hiz_target, id.xy, vec4<f32>(farthest, 0.0, 0.0, 1.0));
+}
+
+// Writes the culled draws of meshes drawn together into the multi-draws,
+// on the shared buffers. Compacted, only the visible ones are written, to
+// the front of the depth pre-pass draws and of their batch, and counted.
+// In place otherwise, the hidden ones with no instances.
+@compute @workgroup_size(64)
+fn compact(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.x;
+
+    if i >= culling.draw_count {
+        return;
+    }
+
+    let source = sources[i];
+
+    if source.depth == NONE {
+        return;
+    }
+
+    var draw = draws[i];
+    draw.first_index = source.first_index;
+    draw.base_vertex = source.base_vertex;
+    // Where the shaders find the transforms of the draw.
+    draw.first_instance = i;
+
+    if culling.compact == 0u {
+        depth_draws[source.depth] = draw;
+        batch_draws[source.batch_start + source.batch_position] = draw;
+
+        return;
+    }
+
+    if draw.instance_count == 0u {
+        return;
+    }
+
+    depth_draws[atomicAdd(&counts[0], 1u)] = draw;
+    batch_draws[source.batch_start + atomicAdd(&counts[source.batch + 1u], 1u)] = draw;
+}
+
+// Lists the crowd members in the view and counts them in the draws of
+// every skinned mesh.
+@compute @workgroup_size(64)
+fn cull_crowd(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.x;
+
+    if i >= crowd.count {
+        return;
+    }
+
+    let member = members[i];
+
+    if outside_view(crowd.min, crowd.max, culling.view_proj * member.transform * crowd.model) {
+        return;
+    }
+
+    visible_members[atomicAdd(&crowd_draws[0].instance_count, 1u)] = member;
+
+    for (var mesh = 1u; mesh < crowd.meshes; mesh += 1u) {
+        atomicAdd(&crowd_draws[mesh].instance_count, 1u);
+    }
+}
@@ -0,0 +1,100 @@
+//! Custom model shaders and their parameters.
+//!
+//! A custom shader is WGSL with a `fs_main` fragment entry point. The
+//! engine prepends [`PRELUDE`], the declarations, bindings, `vs_main` and
+//! `shade` of its own model shader, so a custom shader only writes the
+//! fragment stage. It's preprocessed like the engine's shaders, see
+//! [`crate::renderer::preprocess`]. Its parameters are a uniform at `@group(3)
+//! @binding(1)`, filled from a plain-old-data struct per model:
+//!
+//! ```ignore
+//! #[repr(C)]
+//! #[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+//! struct GlowParams
+//! {
+//!         glow: f32,
+//!         _padding: [f32; 3],
+//! }
+//!
+//! engine.add_shader("glow", r#"
+//!     struct GlowParams { glow: f32 };
+//!
+//!     @group(3) @binding(1) var<uniform> params: GlowParams;
+//!
+//!     @fragment
+//!     fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+//!         let color = textureSample(base_color_texture, base_color_sampler, in.tex_coords);
+//!         return vec4<f32>(shade(color.rgb, in.world_position, in.world_normal) * (1.0 + params.glow), color.a);
+//!     }
+//! "#);
+//!
+//! engine.set_model_shader("ball", Some("glow"));
+//! engine.set_material_params("ball", GlowParams { glow: 1.0, _padding: [0.0; 3] });
+//! ```
+//!
+//! Custom shaders are used with
+//! [`FillMode::Fill`](crate::renderer::pipeline::FillMode::Fill),
+//! the other fill modes draw the model with the engine's shader. A shader
+//! that doesn't compile is logged and the model keeps the engine's shader.
+
+use crate::renderer::preprocess::{ShaderDefines, preprocess};
+use std::collections::HashMap;
+
+/// Prepended to every custom shader.
+pub const PRELUDE: &str = include_str!("common.wgsl");
+
+/// Layout of `@group(3)` of custom shaders, the model transform and the
+/// parameters.
+pub fn create_custom_model_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout
+{
+        let uniform = |binding| wgpu::BindGroupLayoutEntry {
+                binding,
+                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                },
+                count: None,
+        };
+
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[uniform(0), uniform(1)],
+                label: Some("custom_model_bind_group_layout"),
+        })
+}
+
+/// `bytes` padded to whole 16 byte rows, uniforms are read in those.
+pub fn pad_params(bytes: &[u8]) -> Vec<u8>
+{
+        let mut padded = bytes.to_vec();
+
+        padded.resize(bytes.len().next_multiple_of(16).max(16), 0);
+
+        padded
+}
+
+/// The full source of a custom shader, or why it doesn't compile.
+pub fn source(
+        wgsl: &str,
+        includes: &HashMap<String, String>,
+) -> Result<String, String>
+{
+        let source =
+                preprocess(&format!("{}\n{}", PRELUDE, wgsl), includes, &ShaderDefines::new())?;
+
+        // Checked up front, wgpu would only report it as an uncaptured
+        // error. naga isn't exposed on the web.
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+                let module = wgpu::naga::front::wgsl::parse_str(&source)
+                        .map_err(|e| e.emit_to_string(&source))?;
+
+                if !module.entry_points.iter().any(|e| e.name == "fs_main")
+                {
+                        return Err("no `fs_main` entry point".to_string());
+                }
+        }
+
+        Ok(source)
+}
@@ -0,0 +1,434 @@
+//! Debug line drawing.
+//!
+//! [`DebugDrawPass`] draws the visualizations enabled in each model's
+//! [`ModelDebug`](crate::model::ModelDebug): the world space bounding box, a
+//! wireframe overlay, the vertex normals and tangents and the colliders,
+//! see [`crate::geometry::collider`]. The lines are
+//! rebuilt on the CPU every frame, so it's meant for inspecting a few models,
+//! e.g. imported GLBs with a wrong scale or flipped normals.
+//!
+//! Other lines, e.g. gizmos, are queued with [`Engine::debug_lines`] and
+//! drawn once in the next frame.
+
+#[cfg(feature = "runtime")]
+use crate::engine::Engine;
+use crate::geometry::bounds::Aabb;
+use crate::geometry::collider::ColliderShape;
+use crate::geometry::mesh::Mesh;
+use crate::model::{Model, Vertex};
+use crate::renderer::graph::{Attachment, AttachmentLoad, DEPTH, RenderPass, SURFACE};
+use crate::renderer::pipeline::{PipelineKind, PipelineManager};
+use crate::texture::Texture;
+use cgmath::{InnerSpace, Matrix, Matrix3, Matrix4, Point3, SquareMatrix, Transform as _, Vector3};
+use std::any::Any;
+use std::collections::HashMap;
+use wgpu::util::DeviceExt;
+
+const BOUNDS_COLOR: [f32; 4] = [1.0, 0.85, 0.1, 1.0];
+const WIREFRAME_COLOR: [f32; 4] = [0.9, 0.9, 0.9, 0.5];
+const NORMAL_COLOR: [f32; 4] = [0.2, 0.6, 1.0, 1.0];
+const TANGENT_COLOR: [f32; 4] = [1.0, 0.3, 0.3, 1.0];
+const COLLIDER_COLOR: [f32; 4] = [0.3, 1.0, 0.4, 1.0];
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct DebugVertex
+{
+        pub position: [f32; 3],
+        pub color: [f32; 4],
+}
+
+impl Vertex for DebugVertex
+{
+        fn desc() -> wgpu::VertexBufferLayout<'static>
+        {
+                wgpu::VertexBufferLayout {
+                        array_stride: size_of::<DebugVertex>() as wgpu::BufferAddress,
+                        step_mode: wgpu::VertexStepMode::Vertex,
+                        attributes: &[
+                                wgpu::VertexAttribute {
+                                        offset: 0,
+                                        shader_location: 0,
+                                        format: wgpu::VertexFormat::Float32x3,
+                                },
+                                wgpu::VertexAttribute {
+                                        offset: size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                                        shader_location: 1,
+                                        format: wgpu::VertexFormat::Float32x4,
+                                },
+                        ],
+                }
+        }
+}
+
+/// World space line list.
+#[derive(Debug, Default)]
+pub struct DebugLines
+{
+        pub vertices: Vec<DebugVertex>,
+}
+
+impl DebugLines
+{
+        pub fn new() -> Self
+        {
+                Self::default()
+        }
+
+        pub fn is_empty(&self) -> bool
+        {
+                self.vertices.is_empty()
+        }
+
+        pub fn line(
+                &mut self,
+                from: Point3<f32>,
+                to: Point3<f32>,
+                color: [f32; 4],
+        )
+        {
+                self.vertices.push(DebugVertex {
+                        position: from.into(),
+                        color,
+                });
+                self.vertices.push(DebugVertex {
+                        position: to.into(),
+                        color,
+                });
+        }
+
+        pub fn aabb(
+                &mut self,
+                aabb: &Aabb,
+                color: [f32; 4],
+        )
+        {
+                let c = aabb.corners();
+
+                for i in 0..4
+                {
+                        self.line(c[i], c[(i + 1) % 4], color);
+                        self.line(c[i + 4], c[(i + 1) % 4 + 4], color);
+                        self.line(c[i], c[i + 4], color);
+                }
+        }
+
+        /// Lines for everything enabled in `model.debug`.
+        pub fn model(
+                &mut self,
+                model: &Model,
+        )
+        {
+                let debug = model.debug;
+
+                if debug.show_bounds
+                        && let Some(bounds) = model.world_bounds()
+                {
+                        self.aabb(&bounds, BOUNDS_COLOR);
+                }
+
+                if debug.show_colliders
+                {
+                        for collider in model.world_colliders()
+                        {
+                                match collider.shape
+                                {
+                                        ColliderShape::Aabb(aabb) => self.aabb(&aabb, COLLIDER_COLOR),
+                                        _ =>
+                                        {
+                                                for [a, b, c] in collider.triangles()
+                                                {
+                                                        self.line(a, b, COLLIDER_COLOR);
+                                                        self.line(b, c, COLLIDER_COLOR);
+                                                        self.line(c, a, COLLIDER_COLOR);
+                                                }
+                                        }
+                                }
+                        }
+                }
+
+                if !(debug.show_wireframe || debug.show_normals || debug.show_tangents)
+                {
+                        return;
+                }
+
+                let model_matrix = model.calculate_transform();
+
+                for mesh in &model.meshes
+                {
+                        let matrix = model_matrix * mesh.transform;
+                        let normal_matrix = normal_matrix(&matrix);
+
+                        let world: Vec<Point3<f32>> = mesh
+                                .vertices
+                                .iter()
+                                .map(|v| matrix.transform_point(Point3::from(v.position)))
+                                .collect();
+
+                        if debug.show_wireframe
+                        {
+                                for triangle in mesh.indices.chunks_exact(3)
+                                {
+                                        for i in 0..3
+                                        {
+                                                self.line(
+                                                        world[triangle[i] as usize],
+                                                        world[triangle[(i + 1) % 3] as usize],
+                                                        WIREFRAME_COLOR,
+                                                );
+                                        }
+                                }
+                        }
+
+                        if debug.show_normals
+                        {
+                                for (p, v) in world.iter().zip(&mesh.vertices)
+                                {
+                                        let n = normal_matrix * Vector3::from(v.normal);
+
+                                        self.direction(*p, n, debug.line_length, NORMAL_COLOR);
+                                }
+                        }
+
+                        if debug.show_tangents
+                        {
+                                for (p, t) in world.iter().zip(tangents(mesh))
+                                {
+                                        self.direction(
+                                                *p,
+                                                matrix.transform_vector(t),
+                                                debug.line_length,
+                                                TANGENT_COLOR,
+                                        );
+                                }
+                        }
+                }
+        }
+
+        fn direction(
+                &mut self,
+                from: Point3<f32>,
+                direction: Vector3<f32>,
+                length: f32,
+                color: [f32; 4],
+        )
+        {
+                if direction.magnitude2() > f32::EPSILON
+                {
+                        self.line(from, from + direction.normalize() * length, color);
+                }
+        }
+}
+
+/// Transforms normals correctly under non-uniform scale.
+fn normal_matrix(matrix: &Matrix4<f32>) -> Matrix3<f32>
+{
+        let m = Matrix3::from_cols(matrix.x.truncate(), matrix.y.truncate(), matrix.z.truncate());
+
+        m.invert().map(|m| m.transpose()).unwrap_or(m)
+}
+
+/// Per vertex tangents from the texture coordinates, orthogonal to the
+/// normal. Vertices without usable coordinates get a zero tangent.
+fn tangents(mesh: &Mesh) -> Vec<Vector3<f32>>
+{
+        let mut tangents = vec![Vector3::new(0.0, 0.0, 0.0); mesh.vertices.len()];
+
+        for triangle in mesh.indices.chunks_exact(3)
+        {
+                let [a, b, c] = [0, 1, 2].map(|i| &mesh.vertices[triangle[i] as usize]);
+
+                let e1 = Vector3::from(b.position) - Vector3::from(a.position);
+                let e2 = Vector3::from(c.position) - Vector3::from(a.position);
+
+                let du1 = b.tex_coords[0] - a.tex_coords[0];
+                let dv1 = b.tex_coords[1] - a.tex_coords[1];
+                let du2 = c.tex_coords[0] - a.tex_coords[0];
+                let dv2 = c.tex_coords[1] - a.tex_coords[1];
+
+                let det = du1 * dv2 - du2 * dv1;
+
+                if det.abs() < f32::EPSILON
+                {
+                        continue;
+                }
+
+                let tangent = (e1 * dv2 - e2 * dv1) / det;
+
+                for &i in triangle
+                {
+                        tangents[i as usize] += tangent;
+                }
+        }
+
+        tangents.iter()
+                .zip(&mesh.vertices)
+                .map(|(t, v)| {
+                        let n = Vector3::from(v.normal);
+
+                        // Gram-Schmidt
+                        t - n * n.dot(*t)
+                })
+                .collect()
+}
+
+#[derive(Debug)]
+pub struct DebugDrawPass
+{
+        pub name: String,
+        pub enabled: bool,
+        /// Queued lines, cleared after they were drawn.
+        pub lines: DebugLines,
+}
+
+impl DebugDrawPass
+{
+        pub fn new() -> Self
+        {
+                Self {
+                        name: "debug_draw_pass".to_string(),
+                        enabled: true,
+                        lines: DebugLines::new(),
+                }
+        }
+}
+
+impl Default for DebugDrawPass
+{
+        fn default() -> Self
+        {
+                Self::new()
+        }
+}
+
+impl RenderPass for DebugDrawPass
+{
+        fn name(&self) -> &str
+        {
+                self.name.as_str()
+        }
+
+        fn as_any(&self) -> &dyn Any
+        {
+                self
+        }
+
+        fn as_any_mut(&mut self) -> &mut dyn Any
+        {
+                self
+        }
+
+        #[cfg(feature = "ui")]
+        fn ui(
+                &mut self,
+                ui: &mut egui::Ui,
+        )
+        {
+                egui::CollapsingHeader::new(&self.name)
+                        .default_open(true)
+                        .show(ui, |ui| {
+                                ui.label("Toggled per model in the Models window.");
+                        });
+        }
+
+        fn enabled(&mut self) -> bool
+        {
+                self.enabled
+        }
+
+        fn attachments(&self) -> Vec<Attachment>
+        {
+                vec![
+                        Attachment::new(SURFACE, AttachmentLoad::Load, true),
+                        Attachment::new(DEPTH, AttachmentLoad::Load, true),
+                ]
+        }
+
+        fn set_enabled(
+                &mut self,
+                value: bool,
+        )
+        {
+                self.enabled = value;
+        }
+
+        fn record(
+                &mut self,
+                view: &wgpu::TextureView,
+                encoder: &mut wgpu::CommandEncoder,
+                camera: &wgpu::BindGroup,
+                pipeline_manager: &PipelineManager,
+                depth_texture: &Texture,
+                models: Option<&HashMap<String, Model>>,
+                device: &wgpu::Device,
+                #[allow(unused_variables)] queue: &wgpu::Queue,
+        )
+        {
+                let mut lines = std::mem::take(&mut self.lines);
+
+                for model in models
+                        .into_iter()
+                        .flat_map(|m| m.values())
+                        .filter(|m| m.visible && m.debug.any())
+                {
+                        lines.model(model);
+                }
+
+                if lines.is_empty()
+                {
+                        return;
+                }
+
+                let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                        label: Some("Debug Lines Vertex Buffer"),
+                        contents: bytemuck::cast_slice(&lines.vertices),
+                        usage: wgpu::BufferUsages::VERTEX,
+                });
+
+                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        label: Some(&self.name),
+                        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                                view,
+                                resolve_target: None,
+                                ops: wgpu::Operations {
+                                        load: wgpu::LoadOp::Load,
+                                        store: wgpu::StoreOp::Store,
+                                },
+                        })],
+                        depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                                view: &depth_texture.view,
+                                depth_ops: Some(wgpu::Operations {
+                                        load: wgpu::LoadOp::Load,
+                                        store: wgpu::StoreOp::Store,
+                                }),
+                                stencil_ops: None,
+                        }),
+                        occlusion_query_set: None,
+                        timestamp_writes: None,
+                });
+
+                pipeline_manager.apply_viewport(&mut render_pass);
+                render_pass.set_pipeline(pipeline_manager.get(PipelineKind::DebugLines));
+
+                render_pass.set_bind_group(0, camera, &[]);
+                render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+
+                render_pass.draw(0..lines.vertices.len() as u32, 0..1);
+        }
+}
+
+#[cfg(feature = "runtime")]
+impl Engine
+{
+        /// Lines drawn once in the next frame, `None` before the renderer
+        /// is initialized or while the debug draw pass is disabled.
+        pub fn debug_lines(&mut self) -> Option<&mut DebugLines>
+        {
+                self.state
+                        .as_mut()?
+                        .render_graph
+                        .pass_mut::<DebugDrawPass>()
+                        .filter(|pass| pass.enabled)
+                        .map(|pass| &mut pass.lines)
+        }
+}
@@ -0,0 +1,548 @@
+//! Intermediate targets of the render graph drawn over the scene, to see
+//! what a pass left behind when the picture looks wrong.
+//!
+//! The [`DebugViewPass`] runs last and shows one target full screen, or
+//! every target as a thumbnail in the lower right corner. Both are picked
+//! in the pass's section of the debug UI, or from code:
+//!
+//! ```ignore
+//! if let Some(pass) = state.render_graph.pass_mut::<DebugViewPass>()
+//! {
+//!         pass.view = DebugTarget::Depth;
+//!         pass.thumbnails = true;
+//! }
+//! ```
+//!
+//! The graph has two targets to show: the depth texture, near bright and
+//! far dark, and the velocity target of the
+//! [`TemporalPass`](crate::renderer::temporal::TemporalPass), grey where
+//! nothing moved and red and green along the screen's x and y. Velocity is
+//! only there while TAA or motion blur is on. The renderer keeps no
+//! normals, shadow map or ambient occlusion target to show.
+
+use crate::renderer::graph::{Attachment, AttachmentLoad, DEPTH, RenderPass, SURFACE};
+use crate::renderer::pipeline::PipelineManager;
+use crate::renderer::viewport::Viewport;
+use crate::texture::Texture;
+use std::any::Any;
+use std::collections::HashMap;
+
+/// Width of a thumbnail, a fraction of the scene's.
+const THUMBNAIL_SIZE: f32 = 0.25;
+
+/// Gap around the thumbnails in pixels.
+const THUMBNAIL_MARGIN: f32 = 8.0;
+
+/// Target shown by the [`DebugViewPass`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DebugTarget
+{
+        /// The scene as rendered.
+        #[default]
+        Off,
+        Depth,
+        Velocity,
+}
+
+impl DebugTarget
+{
+        fn id(self) -> u32
+        {
+                match self
+                {
+                        DebugTarget::Velocity => 1,
+                        DebugTarget::Off | DebugTarget::Depth => 0,
+                }
+        }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct DebugViewUniform
+{
+        shown: u32,
+        reversed_z: u32,
+        depth_power: f32,
+        velocity_scale: f32,
+        source: [f32; 4],
+        dest: [f32; 4],
+}
+
+fn create_debug_view_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout
+{
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                        wgpu::BindGroupLayoutEntry {
+                                binding: 0,
+                                visibility: wgpu::ShaderStages::FRAGMENT,
+                                ty: wgpu::BindingType::Buffer {
+                                        ty: wgpu::BufferBindingType::Uniform,
+                                        has_dynamic_offset: false,
+                                        min_binding_size: None,
+                                },
+                                count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                                binding: 1,
+                                visibility: wgpu::ShaderStages::FRAGMENT,
+                                ty: wgpu::BindingType::Texture {
+                                        sample_type: wgpu::TextureSampleType::Depth,
+                                        view_dimension: wgpu::TextureViewDimension::D2,
+                                        multisampled: false,
+                                },
+                                count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                                binding: 2,
+                                visibility: wgpu::ShaderStages::FRAGMENT,
+                                ty: wgpu::BindingType::Texture {
+                                        sample_type: wgpu::TextureSampleType::Float {
+                                                filterable: true,
+                                        },
+                                        view_dimension: wgpu::TextureViewDimension::D2,
+                                        multisampled: false,
+                                },
+                                count: None,
+                        },
+                ],
+                label: Some("debug_view_bind_group_layout"),
+        })
+}
+
+/// Built on the first frame a view is shown.
+#[derive(Debug)]
+struct Pipeline
+{
+        pipeline: wgpu::RenderPipeline,
+        layout: wgpu::BindGroupLayout,
+        /// Bound while there's no velocity target.
+        empty_velocity: wgpu::TextureView,
+        /// One per view drawn in a frame, kept between frames.
+        slots: Vec<Slot>,
+}
+
+/// Uniform buffer and bind group of one drawn view.
+#[derive(Debug)]
+struct Slot
+{
+        buffer: wgpu::Buffer,
+        /// The depth and velocity views `bind_group` was created with.
+        views: Option<(wgpu::TextureView, wgpu::TextureView)>,
+        bind_group: Option<wgpu::BindGroup>,
+}
+
+impl Pipeline
+{
+        fn new(
+                device: &wgpu::Device,
+                format: wgpu::TextureFormat,
+                pipeline_manager: &PipelineManager,
+        ) -> Self
+        {
+                let layout = create_debug_view_bind_group_layout(device);
+
+                let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                        label: Some("Debug View Shader"),
+                        source: wgpu::ShaderSource::Wgsl(include_str!("debug_view.wgsl").into()),
+                });
+
+                let pipeline_layout =
+                        device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                                label: Some("Debug View Pipeline Layout"),
+                                bind_group_layouts: &[&layout],
+                                push_constant_ranges: &[],
+                        });
+
+                let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                        label: Some("Debug View Pipeline"),
+                        layout: Some(&pipeline_layout),
+                        vertex: wgpu::VertexState {
+                                module: &shader,
+                                entry_point: Some("vs_main"),
+                                buffers: &[],
+                                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        },
+                        fragment: Some(wgpu::FragmentState {
+                                module: &shader,
+                                entry_point: Some("fs_main"),
+                                targets: &[Some(wgpu::ColorTargetState {
+                                        format,
+                                        blend: None,
+                                        write_mask: wgpu::ColorWrites::ALL,
+                                })],
+                                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        }),
+                        primitive: wgpu::PrimitiveState::default(),
+                        depth_stencil: None,
+                        multisample: wgpu::MultisampleState::default(),
+                        multiview: None,
+                        cache: pipeline_manager.cache.as_ref(),
+                });
+
+                let empty_velocity = device
+                        .create_texture(&wgpu::TextureDescriptor {
+                                label: Some("Empty Velocity"),
+                                size: wgpu::Extent3d {
+                                        width: 1,
+                                        height: 1,
+                                        depth_or_array_layers: 1,
+                                },
+                                mip_level_count: 1,
+                                sample_count: 1,
+                                dimension: wgpu::TextureDimension::D2,
+                                format: wgpu::TextureFormat::Rgba16Float,
+                                usage: wgpu::TextureUsages::TEXTURE_BINDING,
+                                view_formats: &[],
+                        })
+                        .create_view(&wgpu::TextureViewDescriptor::default());
+
+                Self {
+                        pipeline,
+                        layout,
+                        empty_velocity,
+                        slots: Vec::new(),
+                }
+        }
+
+        /// Writes `uniforms` to a slot each, rebuilding the bind groups when
+        /// the depth or velocity target was recreated.
+        fn prepare(
+                &mut self,
+                device: &wgpu::Device,
+                queue: &wgpu::Queue,
+                depth: &wgpu::TextureView,
+                velocity: Option<&wgpu::TextureView>,
+                uniforms: &[DebugViewUniform],
+        )
+        {
+                let velocity = velocity.unwrap_or(&self.empty_velocity);
+
+                while self.slots.len() < uniforms.len()
+                {
+                        self.slots.push(Slot {
+                                buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                                        label: Some("Debug View Buffer"),
+                                        size: std::mem::size_of::<DebugViewUniform>() as u64,
+                                        usage: wgpu::BufferUsages::UNIFORM
+                                                | wgpu::BufferUsages::COPY_DST,
+                                        mapped_at_creation: false,
+                                }),
+                                views: None,
+                                bind_group: None,
+                        });
+                }
+
+                for (slot, uniform) in self.slots.iter_mut().zip(uniforms)
+                {
+                        queue.write_buffer(&slot.buffer, 0, bytemuck::cast_slice(&[*uniform]));
+
+                        if slot.views
+                                .as_ref()
+                                .is_some_and(|(d, v)| d == depth && v == velocity)
+                        {
+                                continue;
+                        }
+
+                        slot.bind_group =
+                                Some(device.create_bind_group(&wgpu::BindGroupDescriptor {
+                                        layout: &self.layout,
+                                        entries: &[
+                                                wgpu::BindGroupEntry {
+                                                        binding: 0,
+                                                        resource: slot.buffer.as_entire_binding(),
+                                                },
+                                                wgpu::BindGroupEntry {
+                                                        binding: 1,
+                                                        resource:
+                                                                wgpu::BindingResource::TextureView(
+                                                                        depth,
+                                                                ),
+                                                },
+                                                wgpu::BindGroupEntry {
+                                                        binding: 2,
+                                                        resource:
+                                                                wgpu::BindingResource::TextureView(
+                                                                        velocity,
+                                                                ),
+                                                },
+                                        ],
+                                        label: Some("debug_view_bind_group"),
+                                }));
+                        slot.views = Some((depth.clone(), velocity.clone()));
+                }
+        }
+}
+
+/// Draws intermediate targets over the scene, see
+/// [`crate::renderer::debug_view`].
+#[derive(Debug)]
+pub struct DebugViewPass
+{
+        pub name: String,
+        pub enabled: bool,
+        /// Target shown full screen.
+        pub view: DebugTarget,
+        /// Shows every target in a corner of the scene.
+        pub thumbnails: bool,
+        /// Exponent the depth is raised to, lower tells far surfaces apart
+        /// better.
+        pub depth_power: f32,
+        /// Screen-space motion is multiplied by it before it's shown.
+        pub velocity_scale: f32,
+        /// Set by the graph every frame, see
+        /// [`RenderGraph::link_debug_views`](crate::renderer::graph::RenderGraph::link_debug_views).
+        pub(crate) velocity: Option<wgpu::TextureView>,
+        format: wgpu::TextureFormat,
+        pipeline: Option<Pipeline>,
+}
+
+impl DebugViewPass
+{
+        /// `format` is the format the passes render in.
+        pub fn new(format: wgpu::TextureFormat) -> Self
+        {
+                Self {
+                        name: "debug_view_pass".to_string(),
+                        enabled: true,
+                        view: DebugTarget::Off,
+                        thumbnails: false,
+                        depth_power: 0.25,
+                        velocity_scale: 20.0,
+                        velocity: None,
+                        format,
+                        pipeline: None,
+                }
+        }
+
+        /// Targets there are to show this frame.
+        pub fn available(&self) -> Vec<DebugTarget>
+        {
+                let mut targets = vec![DebugTarget::Depth];
+
+                if self.velocity.is_some()
+                {
+                        targets.push(DebugTarget::Velocity);
+                }
+
+                targets
+        }
+
+        fn uniform(
+                &self,
+                target: DebugTarget,
+                reversed_z: bool,
+                source: [f32; 4],
+                dest: [f32; 4],
+        ) -> DebugViewUniform
+        {
+                DebugViewUniform {
+                        shown: target.id(),
+                        reversed_z: reversed_z as u32,
+                        depth_power: self.depth_power.max(0.01),
+                        velocity_scale: self.velocity_scale,
+                        source,
+                        dest,
+                }
+        }
+
+        /// Where each shown target is drawn inside the scene's `rect`.
+        fn layout(
+                &self,
+                rect: [f32; 4],
+        ) -> Vec<(DebugTarget, [f32; 4])>
+        {
+                let mut views = Vec::new();
+
+                if self.view != DebugTarget::Off && self.available().contains(&self.view)
+                {
+                        views.push((self.view, rect));
+                }
+
+                if self.thumbnails
+                {
+                        let [x, y, width, height] = rect;
+
+                        let w = (width * THUMBNAIL_SIZE).floor();
+                        let h = (height * THUMBNAIL_SIZE).floor();
+
+                        for (i, target) in self.available().into_iter().rev().enumerate()
+                        {
+                                views.push((
+                                        target,
+                                        [
+                                                x + width - (w + THUMBNAIL_MARGIN) * (i + 1) as f32,
+                                                y + height - h - THUMBNAIL_MARGIN,
+                                                w,
+                                                h,
+                                        ],
+                                ));
+                        }
+                }
+
+                views
+        }
+}
+
+impl RenderPass for DebugViewPass
+{
+        fn name(&self) -> &str
+        {
+                self.name.as_str()
+        }
+
+        fn as_any(&self) -> &dyn Any
+        {
+                self
+        }
+
+        fn as_any_mut(&mut self) -> &mut dyn Any
+        {
+                self
+        }
+
+        #[cfg(feature = "ui")]
+        fn ui(
+                &mut self,
+                ui: &mut egui::Ui,
+        )
+        {
+                egui::CollapsingHeader::new(&self.name)
+                        .default_open(true)
+                        .show(ui, |ui| {
+                                let available = self.available();
+
+                                egui::ComboBox::from_label("View")
+                                        .selected_text(format!("{:?}", self.view))
+                                        .show_ui(ui, |ui| {
+                                                ui.selectable_value(
+                                                        &mut self.view,
+                                                        DebugTarget::Off,
+                                                        "Off",
+                                                );
+
+                                                for target in available
+                                                {
+                                                        ui.selectable_value(
+                                                                &mut self.view,
+                                                                target,
+                                                                format!("{:?}", target),
+                                                        );
+                                                }
+                                        });
+
+                                ui.checkbox(&mut self.thumbnails, "Thumbnails");
+                                ui.add(egui::Slider::new(&mut self.depth_power, 0.02..=1.0)
+                                        .text("Depth power"));
+                                ui.add(egui::Slider::new(&mut self.velocity_scale, 1.0..=200.0)
+                                        .logarithmic(true)
+                                        .text("Velocity scale"));
+
+                                if self.velocity.is_none()
+                                {
+                                        ui.weak("Velocity needs TAA or motion blur");
+                                }
+                        });
+        }
+
+        fn enabled(&mut self) -> bool
+        {
+                self.enabled
+        }
+
+        fn attachments(&self) -> Vec<Attachment>
+        {
+                vec![
+                        Attachment::new(SURFACE, AttachmentLoad::Load, true),
+                        // Read in the shader, not rendered to.
+                        Attachment::new(DEPTH, AttachmentLoad::Load, false),
+                ]
+        }
+
+        fn set_enabled(
+                &mut self,
+                value: bool,
+        )
+        {
+                self.enabled = value;
+        }
+
+        fn record(
+                &mut self,
+                view: &wgpu::TextureView,
+                encoder: &mut wgpu::CommandEncoder,
+                #[allow(unused_variables)] camera: &wgpu::BindGroup,
+                pipeline_manager: &PipelineManager,
+                depth_texture: &Texture,
+                #[allow(unused_variables)] models: Option<&HashMap<String, crate::model::Model>>,
+                device: &wgpu::Device,
+                queue: &wgpu::Queue,
+        )
+        {
+                let size = depth_texture.texture.size();
+                let rect = pipeline_manager.viewport_rect(size.width, size.height);
+
+                let views = self.layout(rect);
+
+                if views.is_empty()
+                {
+                        return;
+                }
+
+                if self.pipeline.is_none()
+                {
+                        self.pipeline = Some(Pipeline::new(device, self.format, pipeline_manager));
+                }
+
+                let uniforms: Vec<_> = views
+                        .iter()
+                        .map(|(target, dest)| {
+                                self.uniform(*target, pipeline_manager.reversed_z, rect, *dest)
+                        })
+                        .collect();
+
+                let Some(pipeline) = &mut self.pipeline
+                else
+                {
+                        return;
+                };
+
+                pipeline.prepare(
+                        device,
+                        queue,
+                        &depth_texture.view,
+                        self.velocity.as_ref(),
+                        &uniforms,
+                );
+
+                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        label: Some(&self.name),
+                        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                                view,
+                                resolve_target: None,
+                                ops: wgpu::Operations {
+                                        load: wgpu::LoadOp::Load,
+                                        store: wgpu::StoreOp::Store,
+                                },
+                        })],
+                        depth_stencil_attachment: None,
+                        occlusion_query_set: None,
+                        timestamp_writes: None,
+                });
+
+                render_pass.set_pipeline(&pipeline.pipeline);
+
+                for ((_, dest), slot) in views.into_iter().zip(&pipeline.slots)
+                {
+                        Viewport {
+                                x: dest[0].max(0.0) as u32,
+                                y: dest[1].max(0.0) as u32,
+                                width: dest[2].max(1.0) as u32,
+                                height: dest[3].max(1.0) as u32,
+                        }
+                        .apply(&mut render_pass);
+
+                        render_pass.set_bind_group(0, slot.bind_group.as_ref(), &[]);
+                        render_pass.draw(0..3, 0..1);
+                }
+        }
+}
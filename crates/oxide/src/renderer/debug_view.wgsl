@@ -0,0 +1,54 @@
+// Intermediate targets of the render graph drawn over the scene. See
+// `debug_view.rs`.
+
+struct DebugView {
+    // 0 depth, 1 velocity.
+    shown: u32,
+    reversed_z: u32,
+    // Exponent the depth is raised to, lower spreads the far values.
+    depth_power: f32,
+    // Screen-space motion is multiplied by it before it's shown.
+    velocity_scale: f32,
+    // Where the scene is in the targets, offset in `xy` and size in `zw`,
+    // in pixels.
+    source: vec4<f32>,
+    // Where the view is drawn, in pixels like `source`.
+    dest: vec4<f32>,
+};
+
+@group(0) @binding(0) var<uniform> view: DebugView;
+@group(0) @binding(1) var depth: texture_depth_2d;
+@group(0) @binding(2) var velocity: texture_2d<f32>;
+
+// One triangle covering the screen, the viewport cuts it to `dest`.
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+    return vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let uv = clamp((position.xy - view.dest.xy) / view.dest.zw, vec2<f32>(0.0), vec2<f32>(1.0));
+    let texel = vec2<i32>(view.source.xy + uv * (view.source.zw - 1.0));
+
+    if view.shown == 0u {
+        let d = textureLoad(depth, texel, 0);
+
+        // Near is bright with either depth direction.
+        var near = 1.0 - d;
+        if view.reversed_z != 0u {
+            near = d;
+        }
+
+        let value = pow(clamp(near, 0.0, 1.0), view.depth_power);
+
+        return vec4<f32>(vec3<f32>(value), 1.0);
+    }
+
+    // Grey where nothing moved, red and green along x and y.
+    let motion = textureLoad(velocity, texel, 0).xy * view.velocity_scale;
+
+    return vec4<f32>(clamp(vec2<f32>(0.5) + motion, vec2<f32>(0.0), vec2<f32>(1.0)), 0.5, 1.0);
+}
@@ -0,0 +1,183 @@
+//! Gamma-correct blit for surfaces without an sRGB format.
+//!
+//! The passes write linear colors and rely on an sRGB target to encode them.
+//! Some platforms only offer linear surface formats, where the same output
+//! looks washed out. There the passes render into an sRGB offscreen texture
+//! instead, and [`GammaBlit::record`] copies it onto the surface, encoding
+//! the colors in the shader.
+//!
+//! The HDR target, see [`crate::renderer::temporal`], is copied onto the
+//! surface the same way, encoded only when the surface can't.
+
+/// Offscreen target and the pipeline copying it onto the surface.
+#[derive(Debug)]
+pub struct GammaBlit
+{
+        pipeline: wgpu::RenderPipeline,
+        bind_group_layout: wgpu::BindGroupLayout,
+        bind_group: wgpu::BindGroup,
+        view: wgpu::TextureView,
+        format: wgpu::TextureFormat,
+}
+
+impl GammaBlit
+{
+        /// Format of the offscreen target the passes render into.
+        pub const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+        /// `format` is the format of the offscreen target, `encode` whether
+        /// the colors are encoded to sRGB when copied.
+        pub fn new(
+                device: &wgpu::Device,
+                config: &wgpu::SurfaceConfiguration,
+                format: wgpu::TextureFormat,
+                encode: bool,
+        ) -> Self
+        {
+                let bind_group_layout =
+                        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                                label: Some("Gamma Blit Bind Group Layout"),
+                                entries: &[wgpu::BindGroupLayoutEntry {
+                                        binding: 0,
+                                        visibility: wgpu::ShaderStages::FRAGMENT,
+                                        ty: wgpu::BindingType::Texture {
+                                                sample_type: wgpu::TextureSampleType::Float {
+                                                        filterable: false,
+                                                },
+                                                view_dimension: wgpu::TextureViewDimension::D2,
+                                                multisampled: false,
+                                        },
+                                        count: None,
+                                }],
+                        });
+
+                let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                        label: Some("Gamma Blit Shader"),
+                        source: wgpu::ShaderSource::Wgsl(include_str!("gamma_blit.wgsl").into()),
+                });
+
+                let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                        label: Some("Gamma Blit Pipeline Layout"),
+                        bind_group_layouts: &[&bind_group_layout],
+                        push_constant_ranges: &[],
+                });
+
+                let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                        label: Some("Gamma Blit Pipeline"),
+                        layout: Some(&layout),
+                        vertex: wgpu::VertexState {
+                                module: &shader,
+                                entry_point: Some("vs_main"),
+                                buffers: &[],
+                                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        },
+                        fragment: Some(wgpu::FragmentState {
+                                module: &shader,
+                                entry_point: Some(if encode { "fs_main" } else { "fs_copy" }),
+                                targets: &[Some(wgpu::ColorTargetState {
+                                        format: config.format,
+                                        blend: None,
+                                        write_mask: wgpu::ColorWrites::ALL,
+                                })],
+                                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        }),
+                        primitive: wgpu::PrimitiveState::default(),
+                        depth_stencil: None,
+                        multisample: wgpu::MultisampleState::default(),
+                        multiview: None,
+                        cache: None,
+                });
+
+                let (view, bind_group) =
+                        Self::create_target(device, &bind_group_layout, config, format);
+
+                Self {
+                        pipeline,
+                        bind_group_layout,
+                        bind_group,
+                        view,
+                        format,
+                }
+        }
+
+        /// Recreates the offscreen target at the new surface size.
+        pub fn resize(
+                &mut self,
+                device: &wgpu::Device,
+                config: &wgpu::SurfaceConfiguration,
+        )
+        {
+                (self.view, self.bind_group) =
+                        Self::create_target(device, &self.bind_group_layout, config, self.format);
+        }
+
+        /// The offscreen target the passes render into.
+        pub fn view(&self) -> &wgpu::TextureView
+        {
+                &self.view
+        }
+
+        /// Copies the offscreen target onto `surface`.
+        pub fn record(
+                &self,
+                encoder: &mut wgpu::CommandEncoder,
+                surface: &wgpu::TextureView,
+        )
+        {
+                let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        label: Some("Gamma Blit Pass"),
+                        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                                view: surface,
+                                resolve_target: None,
+                                ops: wgpu::Operations {
+                                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                                        store: wgpu::StoreOp::Store,
+                                },
+                        })],
+                        depth_stencil_attachment: None,
+                        timestamp_writes: None,
+                        occlusion_query_set: None,
+                });
+
+                pass.set_pipeline(&self.pipeline);
+                pass.set_bind_group(0, &self.bind_group, &[]);
+                pass.draw(0..3, 0..1);
+        }
+
+        fn create_target(
+                device: &wgpu::Device,
+                layout: &wgpu::BindGroupLayout,
+                config: &wgpu::SurfaceConfiguration,
+                format: wgpu::TextureFormat,
+        ) -> (wgpu::TextureView, wgpu::BindGroup)
+        {
+                let texture = device.create_texture(&wgpu::TextureDescriptor {
+                        label: Some("Gamma Blit Target"),
+                        size: wgpu::Extent3d {
+                                width: config.width.max(1),
+                                height: config.height.max(1),
+                                depth_or_array_layers: 1,
+                        },
+                        mip_level_count: 1,
+                        sample_count: 1,
+                        dimension: wgpu::TextureDimension::D2,
+                        format,
+                        usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                                | wgpu::TextureUsages::TEXTURE_BINDING,
+                        view_formats: &[],
+                });
+
+                let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+                let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                        label: Some("Gamma Blit Bind Group"),
+                        layout,
+                        entries: &[wgpu::BindGroupEntry {
+                                binding: 0,
+                                resource: wgpu::BindingResource::TextureView(&view),
+                        }],
+                });
+
+                (view, bind_group)
+        }
+}
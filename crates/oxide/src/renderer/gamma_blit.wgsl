@@ -0,0 +1,35 @@
+// Copies the sRGB offscreen target onto a surface without an sRGB format,
+// encoding the linear colors by hand, or the HDR target onto any surface.
+// See `gamma_blit.rs`.
+
+@group(0) @binding(0)
+var source: texture_2d<f32>;
+
+// One triangle covering the screen.
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+    return vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+}
+
+fn linear_to_srgb(color: vec3<f32>) -> vec3<f32> {
+    let low = color * 12.92;
+    let high = 1.055 * pow(color, vec3<f32>(1.0 / 2.4)) - 0.055;
+
+    return select(high, low, color <= vec3<f32>(0.0031308));
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    // Loading returns the decoded, linear color.
+    let color = textureLoad(source, vec2<i32>(position.xy), 0);
+
+    return vec4<f32>(linear_to_srgb(color.rgb), color.a);
+}
+
+// Onto a surface that encodes by itself, or shows the values as they are.
+@fragment
+fn fs_copy(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    return textureLoad(source, vec2<i32>(position.xy), 0);
+}
@@ -0,0 +1,187 @@
+//! GPU time of the render passes.
+//!
+//! A timestamp is written before the first pass and after every pass, the
+//! differences are the passes' GPU times. They're read back a frame or
+//! more later, frames recorded while a read back is in flight aren't
+//! timed. Needs `TIMESTAMP_QUERY` and `TIMESTAMP_QUERY_INSIDE_ENCODERS`,
+//! see [`GpuTimer::supported`], and isn't used with the
+//! `parallel-recording` feature.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// Passes timed per frame at most.
+const CAPACITY: u32 = 64;
+
+#[derive(Debug)]
+pub struct GpuTimer
+{
+        query_set: wgpu::QuerySet,
+        resolve: wgpu::Buffer,
+        readback: wgpu::Buffer,
+        /// Nanoseconds per timestamp tick.
+        period: f32,
+        /// Passes timed in the frame being recorded.
+        recording: Vec<String>,
+        /// Passes of the timestamps being read back.
+        in_flight: Option<Vec<String>>,
+        /// The read back was asked for.
+        requested: bool,
+        /// The read back is mapped.
+        mapped: Arc<AtomicBool>,
+}
+
+impl GpuTimer
+{
+        pub fn supported(device: &wgpu::Device) -> bool
+        {
+                device.features().contains(
+                        wgpu::Features::TIMESTAMP_QUERY
+                                | wgpu::Features::TIMESTAMP_QUERY_INSIDE_ENCODERS,
+                )
+        }
+
+        pub fn new(
+                device: &wgpu::Device,
+                queue: &wgpu::Queue,
+        ) -> Self
+        {
+                let size = (CAPACITY + 1) as u64 * wgpu::QUERY_SIZE as u64;
+
+                Self {
+                        query_set: device.create_query_set(&wgpu::QuerySetDescriptor {
+                                label: Some("Pass Timestamps"),
+                                ty: wgpu::QueryType::Timestamp,
+                                count: CAPACITY + 1,
+                        }),
+                        resolve: device.create_buffer(&wgpu::BufferDescriptor {
+                                label: Some("Pass Timestamps Resolve"),
+                                size,
+                                usage: wgpu::BufferUsages::QUERY_RESOLVE
+                                        | wgpu::BufferUsages::COPY_SRC,
+                                mapped_at_creation: false,
+                        }),
+                        readback: device.create_buffer(&wgpu::BufferDescriptor {
+                                label: Some("Pass Timestamps Readback"),
+                                size,
+                                usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                                mapped_at_creation: false,
+                        }),
+                        period: queue.get_timestamp_period(),
+                        recording: Vec::new(),
+                        in_flight: None,
+                        requested: false,
+                        mapped: Arc::new(AtomicBool::new(false)),
+                }
+        }
+
+        /// Starts timing a frame, false while the last one is read back.
+        pub fn begin(
+                &mut self,
+                encoder: &mut wgpu::CommandEncoder,
+        ) -> bool
+        {
+                if self.in_flight.is_some()
+                {
+                        return false;
+                }
+
+                self.recording.clear();
+                encoder.write_timestamp(&self.query_set, 0);
+
+                true
+        }
+
+        /// Ends the time of `pass`, which started at the end of the last one.
+        pub fn mark(
+                &mut self,
+                encoder: &mut wgpu::CommandEncoder,
+                pass: &str,
+        )
+        {
+                if self.recording.len() as u32 >= CAPACITY
+                {
+                        return;
+                }
+
+                self.recording.push(pass.to_string());
+                encoder.write_timestamp(&self.query_set, self.recording.len() as u32);
+        }
+
+        /// Copies the timestamps of the frame out for reading.
+        pub fn end(
+                &mut self,
+                encoder: &mut wgpu::CommandEncoder,
+        )
+        {
+                let count = self.recording.len() as u32 + 1;
+                let size = count as u64 * wgpu::QUERY_SIZE as u64;
+
+                encoder.resolve_query_set(&self.query_set, 0..count, &self.resolve, 0);
+                encoder.copy_buffer_to_buffer(&self.resolve, 0, &self.readback, 0, size);
+
+                self.in_flight = Some(std::mem::take(&mut self.recording));
+        }
+
+        /// Maps the copied timestamps, must be called after the frame was
+        /// submitted.
+        pub fn after_submit(&mut self)
+        {
+                if self.in_flight.is_none() || std::mem::replace(&mut self.requested, true)
+                {
+                        return;
+                }
+
+                let mapped = self.mapped.clone();
+
+                self.readback
+                        .slice(..)
+                        .map_async(wgpu::MapMode::Read, move |result| {
+                                if result.is_ok()
+                                {
+                                        mapped.store(true, Ordering::Release);
+                                }
+                        });
+        }
+
+        /// The pass times of a frame once they've been read back.
+        pub fn collect(
+                &mut self,
+                device: &wgpu::Device,
+        ) -> Vec<(String, Duration)>
+        {
+                let _ = device.poll(wgpu::PollType::Poll);
+
+                if !self.mapped.load(Ordering::Acquire)
+                {
+                        return Vec::new();
+                }
+
+                let passes = self.in_flight.take().unwrap_or_default();
+
+                let timestamps: Vec<u64> = {
+                        let data = self.readback.slice(..).get_mapped_range();
+
+                        bytemuck::cast_slice::<u8, u64>(&data)[..=passes.len()].to_vec()
+                };
+
+                self.readback.unmap();
+                self.mapped.store(false, Ordering::Release);
+                self.requested = false;
+
+                passes.into_iter()
+                        .zip(timestamps.windows(2))
+                        .map(|(pass, t)| {
+                                let ticks = t[1].saturating_sub(t[0]);
+
+                                (
+                                        pass,
+                                        Duration::from_nanos(
+                                                (ticks as f64 * self.period as f64) as u64,
+                                        ),
+                                )
+                        })
+                        .collect()
+        }
+}
@@ -1,8 +1,122 @@
-use crate::renderer::pipeline::{PipelineKind, PipelineManager};
+use crate::renderer::debug_view::DebugViewPass;
+use crate::renderer::gpu_timer::GpuTimer;
+use crate::renderer::light_shafts::LightShaftPass;
+use crate::renderer::pipeline::{FillMode, PipelineKind, PipelineManager};
+use crate::renderer::temporal::TemporalPass;
 use crate::texture::Texture;
 use derivative::Derivative;
+use instant::Instant;
+use serde::{Deserialize, Serialize};
 use std::any::Any;
 use std::collections::HashMap;
+use std::time::Duration;
+
+/// Resource name of the surface texture that is presented.
+pub const SURFACE: &str = "surface";
+
+/// Resource name of the shared depth texture.
+pub const DEPTH: &str = "depth";
+
+/// What a pass does with an attachment when it begins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttachmentLoad
+{
+        Clear,
+        Load,
+}
+
+/// A texture a pass renders to, by resource name, e.g. [`SURFACE`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attachment
+{
+        pub resource: String,
+        pub load: AttachmentLoad,
+        /// Whether the pass keeps what it rendered.
+        pub store: bool,
+}
+
+impl Attachment
+{
+        pub fn new(
+                resource: impl Into<String>,
+                load: AttachmentLoad,
+                store: bool,
+        ) -> Self
+        {
+                Self {
+                        resource: resource.into(),
+                        load,
+                        store,
+                }
+        }
+}
+
+/// CPU time a pass took to record its commands, or GPU time it took to
+/// run them, see [`RenderGraph::gpu_timing`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PassTiming
+{
+        pub last: Duration,
+        /// Exponential moving average, steadier to read than `last`.
+        pub average: Duration,
+}
+
+impl PassTiming
+{
+        fn push(
+                &mut self,
+                time: Duration,
+        )
+        {
+                self.average = if self.average.is_zero()
+                {
+                        time
+                }
+                else
+                {
+                        self.average.mul_f32(0.95) + time.mul_f32(0.05)
+                };
+                self.last = time;
+        }
+}
+
+/// Edge of the graph, the pass `to` uses what `from` stored in `resource`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dependency
+{
+        pub from: usize,
+        pub to: usize,
+        pub resource: String,
+}
+
+/// Likely draw order mistakes found by [`RenderGraph::analyze`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GraphIssue
+{
+        /// The pass loads a resource no earlier pass stored anything in.
+        ReadBeforeWrite
+        {
+                pass: usize, resource: String
+        },
+        /// The pass clears a resource before anyone used what `overwritten`
+        /// stored in it.
+        Overwrite
+        {
+                pass: usize,
+                overwritten: usize,
+                resource: String,
+        },
+}
+
+/// Dependencies between the enabled passes, in execution order.
+#[derive(Debug, Clone, Default)]
+pub struct GraphAnalysis
+{
+        pub dependencies: Vec<Dependency>,
+        pub issues: Vec<GraphIssue>,
+        /// Last pass that stored each resource, i.e. what ends up in it.
+        pub outputs: HashMap<String, usize>,
+}
 
 #[derive(Derivative)]
 #[derivative(Debug)]
@@ -10,6 +124,17 @@ pub struct RenderGraph
 {
         #[derivative(Debug = "ignore")]
         pub passes: Vec<Box<dyn RenderPass>>,
+
+        /// Texture formats by resource name, shown in the debug UI.
+        pub formats: HashMap<String, wgpu::TextureFormat>,
+
+        /// Recording time by pass name.
+        timings: HashMap<String, PassTiming>,
+
+        /// GPU time by pass name, see [`RenderGraph::enable_gpu_timing`].
+        gpu_timings: HashMap<String, PassTiming>,
+
+        gpu_timer: Option<GpuTimer>,
 }
 
 impl RenderGraph
@@ -18,6 +143,177 @@ impl RenderGraph
         {
                 Self {
                         passes: Vec::new(),
+                        formats: HashMap::new(),
+                        timings: HashMap::new(),
+                        gpu_timings: HashMap::new(),
+                        gpu_timer: None,
+                }
+        }
+
+        pub fn set_format(
+                &mut self,
+                resource: impl Into<String>,
+                format: wgpu::TextureFormat,
+        )
+        {
+                self.formats.insert(resource.into(), format);
+        }
+
+        pub fn timing(
+                &self,
+                pass: &str,
+        ) -> Option<PassTiming>
+        {
+                self.timings.get(pass).copied()
+        }
+
+        /// Times the passes on the GPU when the device supports it, see
+        /// [`crate::renderer::gpu_timer`].
+        pub fn enable_gpu_timing(
+                &mut self,
+                device: &wgpu::Device,
+                queue: &wgpu::Queue,
+        )
+        {
+                if GpuTimer::supported(device)
+                {
+                        self.gpu_timer = Some(GpuTimer::new(device, queue));
+                }
+        }
+
+        pub fn gpu_timing(
+                &self,
+                pass: &str,
+        ) -> Option<PassTiming>
+        {
+                self.gpu_timings.get(pass).copied()
+        }
+
+        /// GPU time of all passes.
+        pub fn gpu_total(&self) -> Option<Duration>
+        {
+                self.gpu_timer.as_ref()?;
+
+                Some(self.gpu_timings.values().map(|t| t.average).sum())
+        }
+
+        /// Reads the GPU times back, must be called after the frame was
+        /// submitted.
+        pub fn after_submit(&mut self)
+        {
+                if let Some(timer) = self.gpu_timer.as_mut()
+                {
+                        timer.after_submit();
+                }
+        }
+
+        /// Works out which enabled pass uses the output of which, and flags
+        /// passes whose order looks wrong.
+        pub fn analyze(&mut self) -> GraphAnalysis
+        {
+                let mut analysis = GraphAnalysis::default();
+
+                // Writer of each resource and whether a later pass used it.
+                let mut writers: HashMap<String, (usize, bool)> = HashMap::new();
+
+                for (i, pass) in self.passes.iter_mut().enumerate()
+                {
+                        if !pass.enabled()
+                        {
+                                continue;
+                        }
+
+                        for attachment in pass.attachments()
+                        {
+                                let resource = attachment.resource;
+
+                                match (attachment.load, writers.get_mut(&resource))
+                                {
+                                        (AttachmentLoad::Load, Some((from, used))) =>
+                                        {
+                                                *used = true;
+
+                                                analysis.dependencies.push(Dependency {
+                                                        from: *from,
+                                                        to: i,
+                                                        resource: resource.clone(),
+                                                });
+                                        }
+                                        (AttachmentLoad::Load, None) =>
+                                        {
+                                                analysis.issues.push(GraphIssue::ReadBeforeWrite {
+                                                        pass: i,
+                                                        resource: resource.clone(),
+                                                });
+                                        }
+                                        (AttachmentLoad::Clear, Some((from, false))) =>
+                                        {
+                                                analysis.issues.push(GraphIssue::Overwrite {
+                                                        pass: i,
+                                                        overwritten: *from,
+                                                        resource: resource.clone(),
+                                                });
+                                        }
+                                        (AttachmentLoad::Clear, _) =>
+                                        {}
+                                }
+
+                                if attachment.store
+                                {
+                                        writers.insert(resource, (i, false));
+                                }
+                        }
+                }
+
+                analysis.outputs = writers.into_iter().map(|(r, (i, _))| (r, i)).collect();
+
+                analysis
+        }
+
+        /// First pass of type `T`.
+        pub fn pass_mut<T: 'static>(&mut self) -> Option<&mut T>
+        {
+                self.passes
+                        .iter_mut()
+                        .find_map(|p| p.as_any_mut().downcast_mut::<T>())
+        }
+
+        /// Hands the environment to every [`BackgroundPass`] and
+        /// [`LightShaftPass`].
+        pub fn set_environment(
+                &mut self,
+                environment: &EnvironmentSettings,
+        )
+        {
+                for pass in self.passes.iter_mut()
+                {
+                        if let Some(background) = pass.as_any_mut().downcast_mut::<BackgroundPass>()
+                        {
+                                background.environment = environment.color();
+                        }
+
+                        if let Some(shafts) = pass.as_any_mut().downcast_mut::<LightShaftPass>()
+                        {
+                                shafts.set_environment(environment);
+                        }
+                }
+        }
+
+        /// Hands the targets other passes keep to every [`DebugViewPass`].
+        /// Called by [`RenderGraph::execute`].
+        pub fn link_debug_views(&mut self)
+        {
+                let velocity = self
+                        .pass_mut::<TemporalPass>()
+                        .and_then(|pass| pass.velocity())
+                        .map(|texture| texture.view.clone());
+
+                for pass in self.passes.iter_mut()
+                {
+                        if let Some(debug) = pass.as_any_mut().downcast_mut::<DebugViewPass>()
+                        {
+                                debug.velocity = velocity.clone();
+                        }
                 }
         }
 
@@ -29,41 +325,214 @@ impl RenderGraph
                 self.passes.push(pass);
         }
 
+        /// Records the enabled passes in order.
+        ///
+        /// Returns command buffers to submit before `encoder`. They're only
+        /// used with the `parallel-recording` feature, which records groups
+        /// of consecutive passes on their own threads and encoders, otherwise
+        /// everything is recorded into `encoder`.
+        #[must_use]
         pub fn execute(
                 &mut self,
                 view: &wgpu::TextureView,
-                encoder: &mut wgpu::CommandEncoder,
+                #[allow(unused_variables)] encoder: &mut wgpu::CommandEncoder,
                 pipeline_manager: &PipelineManager,
                 camera: &wgpu::BindGroup,
                 depth_texture: &Texture,
                 models: Option<&HashMap<String, crate::model::Model>>,
                 device: &wgpu::Device,
-        )
+                queue: &wgpu::Queue,
+        ) -> Vec<wgpu::CommandBuffer>
         {
-                for pass in self.passes.iter_mut()
+                self.link_debug_views();
+
+                #[cfg(all(feature = "parallel-recording", not(target_arch = "wasm32")))]
+                return self.execute_parallel(
+                        view,
+                        pipeline_manager,
+                        camera,
+                        depth_texture,
+                        models,
+                        device,
+                        queue,
+                );
+
+                #[cfg(not(all(feature = "parallel-recording", not(target_arch = "wasm32"))))]
                 {
-                        if pass.enabled()
+                        if let Some(timer) = self.gpu_timer.as_mut()
                         {
-                                pass.record(
-                                        &view,
-                                        encoder,
-                                        &camera,
-                                        &pipeline_manager,
-                                        depth_texture,
-                                        models,
-                                        device,
-                                );
+                                let times = timer.collect(device);
+
+                                // Passes turned off since aren't timed anymore.
+                                if !times.is_empty()
+                                {
+                                        self.gpu_timings.retain(|pass, _| {
+                                                times.iter().any(|(p, _)| p == pass)
+                                        });
+                                }
+
+                                for (pass, time) in times
+                                {
+                                        self.gpu_timings.entry(pass).or_default().push(time);
+                                }
+                        }
+
+                        // Not timed while the last frame is read back.
+                        let mut gpu_timer = self.gpu_timer.as_mut();
+
+                        if let Some(timer) = gpu_timer.as_mut()
+                                && !timer.begin(encoder)
+                        {
+                                gpu_timer = None;
+                        }
+
+                        for pass in self.passes.iter_mut()
+                        {
+                                if pass.enabled()
+                                {
+                                        let start = Instant::now();
+
+                                        pass.record(
+                                                &view,
+                                                encoder,
+                                                &camera,
+                                                &pipeline_manager,
+                                                depth_texture,
+                                                models,
+                                                device,
+                                                queue,
+                                        );
+
+                                        self.timings
+                                                .entry(pass.name().to_string())
+                                                .or_default()
+                                                .push(start.elapsed());
+
+                                        if let Some(timer) = gpu_timer.as_mut()
+                                        {
+                                                timer.mark(encoder, pass.name());
+                                        }
+                                }
+                        }
+
+                        if let Some(timer) = gpu_timer
+                        {
+                                timer.end(encoder);
                         }
+
+                        Vec::new()
                 }
         }
 
+        /// Splits the enabled passes into one group of consecutive passes
+        /// per core, records the groups on scoped threads and returns their
+        /// command buffers in pass order.
+        #[cfg(all(feature = "parallel-recording", not(target_arch = "wasm32")))]
+        fn execute_parallel(
+                &mut self,
+                view: &wgpu::TextureView,
+                pipeline_manager: &PipelineManager,
+                camera: &wgpu::BindGroup,
+                depth_texture: &Texture,
+                models: Option<&HashMap<String, crate::model::Model>>,
+                device: &wgpu::Device,
+                queue: &wgpu::Queue,
+        ) -> Vec<wgpu::CommandBuffer>
+        {
+                let mut enabled: Vec<&mut Box<dyn RenderPass>> = self
+                        .passes
+                        .iter_mut()
+                        .filter_map(|p| p.enabled().then_some(p))
+                        .collect();
+
+                if enabled.is_empty()
+                {
+                        return Vec::new();
+                }
+
+                let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+                let group_size = enabled.len().div_ceil(threads);
+
+                let groups: Vec<(wgpu::CommandBuffer, Vec<(String, Duration)>)> =
+                        std::thread::scope(|scope| {
+                                let handles: Vec<_> = enabled
+                                        .chunks_mut(group_size)
+                                        .map(|group| {
+                                                scope.spawn(move || {
+                                                        let mut encoder = device.create_command_encoder(
+                                                                &wgpu::CommandEncoderDescriptor {
+                                                                        label: Some("Render Pass Group Encoder"),
+                                                                },
+                                                        );
+
+                                                        let mut timings = Vec::new();
+
+                                                        for pass in group
+                                                        {
+                                                                let start = Instant::now();
+
+                                                                pass.record(
+                                                                        view,
+                                                                        &mut encoder,
+                                                                        camera,
+                                                                        pipeline_manager,
+                                                                        depth_texture,
+                                                                        models,
+                                                                        device,
+                                                                        queue,
+                                                                );
+
+                                                                timings.push((
+                                                                        pass.name().to_string(),
+                                                                        start.elapsed(),
+                                                                ));
+                                                        }
+
+                                                        (encoder.finish(), timings)
+                                                })
+                                        })
+                                        .collect();
+
+                                handles.into_iter()
+                                        .map(|h| h.join().expect("Render pass recording panicked"))
+                                        .collect()
+                        });
+
+                groups.into_iter()
+                        .map(|(buffer, timings)| {
+                                for (name, duration) in timings
+                                {
+                                        self.timings.entry(name).or_default().push(duration);
+                                }
+
+                                buffer
+                        })
+                        .collect()
+        }
+
         pub fn passes_mut(&mut self) -> &mut Vec<Box<dyn RenderPass>>
         {
                 &mut self.passes
         }
 }
 
-pub trait RenderPass
+/// `Send` when passes are recorded on several threads, see
+/// [`RenderGraph::execute`].
+#[cfg(all(feature = "parallel-recording", not(target_arch = "wasm32")))]
+pub trait PassBounds: Send {}
+
+#[cfg(all(feature = "parallel-recording", not(target_arch = "wasm32")))]
+impl<T: Send> PassBounds for T {}
+
+/// `Send` when passes are recorded on several threads, see
+/// [`RenderGraph::execute`].
+#[cfg(not(all(feature = "parallel-recording", not(target_arch = "wasm32"))))]
+pub trait PassBounds {}
+
+#[cfg(not(all(feature = "parallel-recording", not(target_arch = "wasm32"))))]
+impl<T> PassBounds for T {}
+
+pub trait RenderPass: PassBounds
 {
         fn name(&self) -> &str;
 
@@ -71,6 +540,7 @@ pub trait RenderPass
 
         fn as_any_mut(&mut self) -> &mut dyn Any;
 
+        #[cfg(feature = "ui")]
         fn ui(
                 &mut self,
                 ui: &mut egui::Ui,
@@ -78,6 +548,14 @@ pub trait RenderPass
 
         fn enabled(&mut self) -> bool;
 
+        /// Textures the pass renders to, used to draw the graph and to find
+        /// draw order issues. Passes that don't declare any show up without
+        /// connections.
+        fn attachments(&self) -> Vec<Attachment>
+        {
+                Vec::new()
+        }
+
         fn set_enabled(
                 &mut self,
                 value: bool,
@@ -92,15 +570,192 @@ pub trait RenderPass
                 depth_texture: &Texture,
                 models: Option<&HashMap<String, crate::model::Model>>,
                 device: &wgpu::Device,
+                queue: &wgpu::Queue,
         );
 }
 
+/// Global background and lighting of the scene, stored in
+/// [`Config`](crate::config::Config). The background is used by every
+/// [`BackgroundPass`] that doesn't override it, a skybox is meant to be
+/// drawn over `clear_color`. Animated by the
+/// [`Environment`](crate::environment::Environment) cycle.
+///
+/// The defaults light everything fully without a sun or fog, so models
+/// look like their textures.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EnvironmentSettings
+{
+        /// Linear RGBA.
+        pub clear_color: [f32; 4],
+        /// Direction towards the sun.
+        pub sun_direction: [f32; 3],
+        /// Linear RGB, added on surfaces facing the sun.
+        pub sun_color: [f32; 3],
+        /// Linear RGB, added everywhere.
+        pub ambient: [f32; 3],
+        /// Linear RGB, what distant surfaces fade to.
+        pub fog_color: [f32; 3],
+        /// How quickly surfaces fade into the fog per unit of distance, 0
+        /// for no fog.
+        pub fog_density: f32,
+        /// Multiplies the final color.
+        pub exposure: f32,
+        /// Light shafts around objects in front of the sun, see
+        /// [`LightShaftPass`](crate::renderer::light_shafts::LightShaftPass).
+        pub sun_shafts: bool,
+        /// Lens flare while the sun is on screen.
+        pub sun_flare: bool,
+}
+
+impl Default for EnvironmentSettings
+{
+        fn default() -> Self
+        {
+                Self {
+                        clear_color: [0.05, 0.05, 0.05, 1.0],
+                        sun_direction: [0.0, 1.0, 0.0],
+                        sun_color: [0.0; 3],
+                        ambient: [1.0; 3],
+                        fog_color: [0.05, 0.05, 0.05],
+                        fog_density: 0.0,
+                        exposure: 1.0,
+                        sun_shafts: false,
+                        sun_flare: false,
+                }
+        }
+}
+
+/// [`EnvironmentSettings`] as laid out in the camera uniform.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct EnvironmentUniform
+{
+        pub sun_direction: [f32; 4],
+        pub sun_color: [f32; 4],
+        pub ambient: [f32; 4],
+        /// Fog color, with the density in `w`.
+        pub fog: [f32; 4],
+        /// Exposure in `x`.
+        pub exposure: [f32; 4],
+}
+
+impl Default for EnvironmentUniform
+{
+        fn default() -> Self
+        {
+                EnvironmentSettings::default().uniform()
+        }
+}
+
+impl EnvironmentSettings
+{
+        pub fn color(&self) -> wgpu::Color
+        {
+                let [r, g, b, a] = self.clear_color;
+
+                wgpu::Color {
+                        r: r as f64,
+                        g: g as f64,
+                        b: b as f64,
+                        a: a as f64,
+                }
+        }
+
+        pub fn uniform(&self) -> EnvironmentUniform
+        {
+                let [x, y, z] = self.sun_direction;
+                let length = (x * x + y * y + z * z).sqrt().max(f32::EPSILON);
+
+                let extend = |[r, g, b]: [f32; 3], w: f32| [r, g, b, w];
+
+                EnvironmentUniform {
+                        sun_direction: [x / length, y / length, z / length, 0.0],
+                        sun_color: extend(self.sun_color, 0.0),
+                        ambient: extend(self.ambient, 0.0),
+                        fog: extend(self.fog_color, self.fog_density),
+                        exposure: [self.exposure, 0.0, 0.0, 0.0],
+                }
+        }
+
+        #[cfg(feature = "ui")]
+        pub fn ui(
+                &mut self,
+                ui: &mut egui::Ui,
+        )
+        {
+                ui.horizontal(|ui| {
+                        ui.label("Clear Color");
+                        ui.color_edit_button_rgba_unmultiplied(&mut self.clear_color);
+                });
+
+                egui::CollapsingHeader::new("Lighting")
+                        .default_open(false)
+                        .show(ui, |ui| {
+                                ui.horizontal(|ui| {
+                                        ui.label("Sun");
+                                        ui.color_edit_button_rgb(&mut self.sun_color);
+                                        ui.label("Ambient");
+                                        ui.color_edit_button_rgb(&mut self.ambient);
+                                });
+
+                                ui.horizontal(|ui| {
+                                        ui.label("Sun Direction");
+
+                                        for axis in self.sun_direction.iter_mut()
+                                        {
+                                                ui.add(egui::DragValue::new(axis)
+                                                        .speed(0.01)
+                                                        .range(-1.0..=1.0));
+                                        }
+                                });
+
+                                ui.horizontal(|ui| {
+                                        ui.label("Fog");
+                                        ui.color_edit_button_rgb(&mut self.fog_color);
+                                        ui.add(egui::Slider::new(&mut self.fog_density, 0.0..=0.2)
+                                                .text("Density"));
+                                });
+
+                                ui.add(egui::Slider::new(&mut self.exposure, 0.0..=4.0)
+                                        .text("Exposure"));
+
+                                ui.horizontal(|ui| {
+                                        ui.checkbox(&mut self.sun_shafts, "Light shafts");
+                                        ui.checkbox(&mut self.sun_flare, "Lens flare");
+                                });
+                        });
+        }
+}
+
+/// Clears the surface to the environment's clear color, or its own.
 #[derive(Debug)]
 pub struct BackgroundPass
 {
         pub name: String,
         pub enabled: bool,
-        pub clear_color: wgpu::Color,
+        /// Used instead of the environment's color when set.
+        pub clear_color: Option<wgpu::Color>,
+        /// Set by [`RenderGraph::set_environment`].
+        pub environment: wgpu::Color,
+}
+
+impl BackgroundPass
+{
+        pub fn new(name: impl Into<String>) -> Self
+        {
+                Self {
+                        name: name.into(),
+                        enabled: true,
+                        clear_color: None,
+                        environment: EnvironmentSettings::default().color(),
+                }
+        }
+
+        pub fn color(&self) -> wgpu::Color
+        {
+                self.clear_color.unwrap_or(self.environment)
+        }
 }
 
 impl RenderPass for BackgroundPass
@@ -120,6 +775,7 @@ impl RenderPass for BackgroundPass
                 self
         }
 
+        #[cfg(feature = "ui")]
         fn ui(
                 &mut self,
                 ui: &mut egui::Ui,
@@ -128,34 +784,30 @@ impl RenderPass for BackgroundPass
                 egui::CollapsingHeader::new(&self.name)
                         .default_open(true)
                         .show(ui, |ui| {
-                                // Enable/disable pass
+                                let mut overridden = self.clear_color.is_some();
 
-                                // Interactive color picker
-                                let mut color = [
-                                        self.clear_color.r as f32,
-                                        self.clear_color.g as f32,
-                                        self.clear_color.b as f32,
-                                        self.clear_color.a as f32,
-                                ];
+                                ui.checkbox(&mut overridden, "Override environment");
+
+                                if !overridden
+                                {
+                                        self.clear_color = None;
+                                        return;
+                                }
+
+                                let c = self.color();
+                                let mut color = [c.r as f32, c.g as f32, c.b as f32, c.a as f32];
 
                                 ui.horizontal(|ui| {
                                         ui.label("Color");
-                                        if ui.color_edit_button_rgba_unmultiplied(&mut color)
-                                                .changed()
-                                        {
-                                                self.clear_color = wgpu::Color {
-                                                        r: color[0] as f64,
-                                                        g: color[1] as f64,
-                                                        b: color[2] as f64,
-                                                        a: color[3] as f64,
-                                                };
-                                        }
+                                        ui.color_edit_button_rgba_unmultiplied(&mut color);
                                 });
 
-                                // Info fields
-                                ui.label("LoadOp: Clear");
-                                ui.label("StoreOp: Store");
-                                ui.label("Depth/stencil attachment: None");
+                                self.clear_color = Some(wgpu::Color {
+                                        r: color[0] as f64,
+                                        g: color[1] as f64,
+                                        b: color[2] as f64,
+                                        a: color[3] as f64,
+                                });
                         });
         }
 
@@ -164,6 +816,11 @@ impl RenderPass for BackgroundPass
                 self.enabled
         }
 
+        fn attachments(&self) -> Vec<Attachment>
+        {
+                vec![Attachment::new(SURFACE, AttachmentLoad::Clear, true)]
+        }
+
         fn set_enabled(
                 &mut self,
                 value: bool,
@@ -177,20 +834,29 @@ impl RenderPass for BackgroundPass
                 view: &wgpu::TextureView,
                 encoder: &mut wgpu::CommandEncoder,
                 #[allow(unused_variables)] camera: &wgpu::BindGroup,
-                #[allow(unused_variables)] pipeline_manager: &PipelineManager,
+                pipeline_manager: &PipelineManager,
                 #[allow(unused_variables)] depth_texture: &Texture,
                 #[allow(unused_variables)] obj_model: Option<&HashMap<String, crate::model::Model>>,
                 #[allow(unused_variables)] device: &wgpu::Device,
+                #[allow(unused_variables)] queue: &wgpu::Queue,
         )
         {
+                // A clear covers the whole target, with a viewport it clears
+                // the bars and the color is drawn inside.
+                let clear = match pipeline_manager.viewport
+                {
+                        Some(_) => wgpu::Color::BLACK,
+                        None => self.color(),
+                };
+
                 // For a background pass, we typically don't need depth testing
-                let _render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                         label: Some(self.name()),
                         color_attachments: &[Some(wgpu::RenderPassColorAttachment {
                                 view,
                                 resolve_target: None,
                                 ops: wgpu::Operations {
-                                        load: wgpu::LoadOp::Clear(self.clear_color),
+                                        load: wgpu::LoadOp::Clear(clear),
                                         store: wgpu::StoreOp::Store,
                                 },
                         })],
@@ -198,6 +864,14 @@ impl RenderPass for BackgroundPass
                         occlusion_query_set: None,
                         timestamp_writes: None,
                 });
+
+                if pipeline_manager.viewport.is_some()
+                {
+                        pipeline_manager.apply_viewport(&mut render_pass);
+                        render_pass.set_pipeline(pipeline_manager.get(PipelineKind::Fill));
+                        render_pass.set_blend_constant(self.color());
+                        render_pass.draw(0..3, 0..1);
+                }
         }
 }
 
@@ -205,6 +879,9 @@ pub struct GeometryPass
 {
         pub name: String,
         pub enabled: bool,
+        /// Keeps the depth of the [`DepthPrePass`] instead of clearing it,
+        /// set by the engine.
+        pub load_depth: bool,
 }
 
 impl RenderPass for GeometryPass
@@ -224,6 +901,7 @@ impl RenderPass for GeometryPass
                 self
         }
 
+        #[cfg(feature = "ui")]
         fn ui(
                 &mut self,
                 ui: &mut egui::Ui,
@@ -232,11 +910,6 @@ impl RenderPass for GeometryPass
                 egui::CollapsingHeader::new(&self.name)
                         .default_open(true)
                         .show(ui, |ui| {
-                                // Info fields
-                                ui.label("LoadOp: Load");
-                                ui.label("StoreOp: Store");
-                                ui.label("Depth/stencil attachment: None");
-
                                 if ui.button("Refresh Geometry").clicked()
                                 {
                                         // This could trigger a refresh of
@@ -250,6 +923,23 @@ impl RenderPass for GeometryPass
                 self.enabled
         }
 
+        fn attachments(&self) -> Vec<Attachment>
+        {
+                let depth = if self.load_depth
+                {
+                        AttachmentLoad::Load
+                }
+                else
+                {
+                        AttachmentLoad::Clear
+                };
+
+                vec![
+                        Attachment::new(SURFACE, AttachmentLoad::Load, true),
+                        Attachment::new(DEPTH, depth, true),
+                ]
+        }
+
         fn set_enabled(
                 &mut self,
                 value: bool,
@@ -267,6 +957,7 @@ impl RenderPass for GeometryPass
                 depth_texture: &Texture,
                 models: Option<&HashMap<String, crate::model::Model>>,
                 device: &wgpu::Device,
+                #[allow(unused_variables)] queue: &wgpu::Queue,
         )
         {
                 let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
@@ -282,7 +973,14 @@ impl RenderPass for GeometryPass
                         depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
                                 view: &depth_texture.view,
                                 depth_ops: Some(wgpu::Operations {
-                                        load: wgpu::LoadOp::Clear(1.0),
+                                        load: if self.load_depth
+                                        {
+                                                wgpu::LoadOp::Load
+                                        }
+                                        else
+                                        {
+                                                wgpu::LoadOp::Clear(pipeline_manager.depth_clear())
+                                        },
                                         store: wgpu::StoreOp::Store,
                                 }),
                                 stencil_ops: None,
@@ -291,32 +989,286 @@ impl RenderPass for GeometryPass
                         timestamp_writes: None,
                 });
 
-                render_pass.set_pipeline(pipeline_manager.get(PipelineKind::Geometry));
-
+                pipeline_manager.apply_viewport(&mut render_pass);
                 render_pass.set_bind_group(0, camera, &[]);
+                pipeline_manager.draw_geometry_batches(&mut render_pass);
 
                 use crate::model::DrawModel;
 
-                for model in models.unwrap().values()
+                let mut slot = 0;
+
+                for model in models.unwrap().values().filter(|m| m.visible)
+                {
+                        let meshes = model.meshes.len() as u32;
+
+                        if (slot..slot + meshes).all(|s| pipeline_manager.in_batch(s))
+                        {
+                                slot += meshes;
+                                continue;
+                        }
+
+                        // Custom shaders only replace the filled look.
+                        let custom = model
+                                .shader
+                                .as_ref()
+                                .filter(|_| pipeline_manager.fill_mode == FillMode::Fill)
+                                .and_then(|name| pipeline_manager.custom_pipelines.get(name));
+
+                        match custom
+                        {
+                                Some(pipeline) =>
+                                {
+                                        render_pass.set_pipeline(pipeline);
+                                        render_pass.set_bind_group(
+                                                3,
+                                                &model.create_custom_bind_group(device),
+                                                &[],
+                                        );
+                                }
+                                None =>
+                                {
+                                        render_pass.set_bind_group(
+                                                3,
+                                                &model.create_model_transform_bind_group(device),
+                                                &[],
+                                        );
+                                }
+                        }
+
+                        // Custom shaders don't skin, skinned meshes are drawn with
+                        // the joint palette and the rest without.
+                        let skinned = custom
+                                .is_none()
+                                .then(|| model.create_skinned_bind_group(device))
+                                .flatten();
+
+                        // Nor sample reflection probes, models in one are drawn
+                        // with its cubemap, skinned meshes with the palette too.
+                        let probe = custom
+                                .is_none()
+                                .then(|| model.create_probe_bind_group(device, false))
+                                .flatten();
+                        let skinned_probe = probe
+                                .as_ref()
+                                .and(skinned.as_ref())
+                                .and_then(|_| model.create_probe_bind_group(device, true));
+
+                        for mesh in model.meshes.iter()
+                        {
+                                if pipeline_manager.in_batch(slot)
+                                {
+                                        slot += 1;
+                                        continue;
+                                }
+
+                                render_pass.set_bind_group(1, &mesh.transform_bind_group, &[]);
+
+                                let material = &model.materials[mesh.material];
+                                render_pass.set_bind_group(2, &material.material_bind_group, &[]);
+
+                                if let Some(skinned) = &skinned
+                                {
+                                        match &mesh.skin_buffer
+                                        {
+                                                Some(skin_buffer) =>
+                                                {
+                                                        render_pass.set_bind_group(3, skinned, &[]);
+                                                        render_pass.set_vertex_buffer(1, skin_buffer.slice(..));
+                                                }
+                                                None => render_pass.set_bind_group(
+                                                        3,
+                                                        &model.create_model_transform_bind_group(device),
+                                                        &[],
+                                                ),
+                                        }
+                                }
+
+                                if let Some(probe) = &probe
+                                {
+                                        let probe = match (&skinned_probe, &mesh.skin_buffer)
+                                        {
+                                                (Some(skinned_probe), Some(_)) => skinned_probe,
+                                                _ => probe,
+                                        };
+
+                                        render_pass.set_bind_group(3, probe, &[]);
+                                }
+
+                                // The permutation for the material's features,
+                                // skinning and probe.
+                                if custom.is_none()
+                                {
+                                        render_pass.set_pipeline(
+                                                pipeline_manager.geometry_pipeline(&model.defines(mesh)),
+                                        );
+                                }
+
+                                if pipeline_manager.fill_mode == FillMode::WireframeOverlay
+                                {
+                                        render_pass.draw_mesh_barycentric(mesh, device);
+                                }
+                                else
+                                {
+                                        pipeline_manager.draw_mesh(&mut render_pass, slot, mesh);
+                                }
+
+                                slot += 1;
+                        }
+                }
+        }
+}
+
+/// Draws the depth of every model before the [`GeometryPass`], which then
+/// only shades the nearest surface of each pixel instead of everything
+/// drawn over each other. Worth it in scenes with a lot of overdraw, e.g.
+/// large maps, where shading costs more than drawing the models twice.
+/// Only used with [`FillMode::Fill`], see
+/// [`PipelineManager::depth_prepass`].
+pub struct DepthPrePass
+{
+        pub name: String,
+        pub enabled: bool,
+}
+
+impl DepthPrePass
+{
+        pub fn new(enabled: bool) -> Self
+        {
+                Self {
+                        name: "depth_prepass".to_string(),
+                        enabled,
+                }
+        }
+}
+
+impl RenderPass for DepthPrePass
+{
+        fn name(&self) -> &str
+        {
+                self.name.as_str()
+        }
+
+        fn as_any(&self) -> &dyn Any
+        {
+                self
+        }
+
+        fn as_any_mut(&mut self) -> &mut dyn Any
+        {
+                self
+        }
+
+        #[cfg(feature = "ui")]
+        fn ui(
+                &mut self,
+                ui: &mut egui::Ui,
+        )
+        {
+                egui::CollapsingHeader::new(&self.name)
+                        .default_open(true)
+                        .show(ui, |ui| {
+                                ui.label("Compare the GPU time of the geometry pass with it on and off.");
+                        });
+        }
+
+        fn enabled(&mut self) -> bool
+        {
+                self.enabled
+        }
+
+        fn attachments(&self) -> Vec<Attachment>
+        {
+                vec![Attachment::new(DEPTH, AttachmentLoad::Clear, true)]
+        }
+
+        fn set_enabled(
+                &mut self,
+                value: bool,
+        )
+        {
+                self.enabled = value
+        }
+
+        fn record(
+                &mut self,
+                #[allow(unused_variables)] view: &wgpu::TextureView,
+                encoder: &mut wgpu::CommandEncoder,
+                camera: &wgpu::BindGroup,
+                pipeline_manager: &PipelineManager,
+                depth_texture: &Texture,
+                models: Option<&HashMap<String, crate::model::Model>>,
+                device: &wgpu::Device,
+                #[allow(unused_variables)] queue: &wgpu::Queue,
+        )
+        {
+                if !pipeline_manager.depth_prepass_active()
+                {
+                        return;
+                }
+
+                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        label: Some(&self.name),
+                        color_attachments: &[],
+                        depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                                view: &depth_texture.view,
+                                depth_ops: Some(wgpu::Operations {
+                                        load: wgpu::LoadOp::Clear(pipeline_manager.depth_clear()),
+                                        store: wgpu::StoreOp::Store,
+                                }),
+                                stencil_ops: None,
+                        }),
+                        occlusion_query_set: None,
+                        timestamp_writes: None,
+                });
+
+                pipeline_manager.apply_viewport(&mut render_pass);
+                render_pass.set_bind_group(0, camera, &[]);
+                pipeline_manager.draw_depth_batch(&mut render_pass);
+                render_pass.set_pipeline(pipeline_manager.get(PipelineKind::DepthPrepass));
+
+                let mut slot = 0;
+
+                for model in models
+                        .into_iter()
+                        .flat_map(|m| m.values())
+                        .filter(|m| m.visible)
                 {
+                        let meshes = model.meshes.len() as u32;
+
+                        if (slot..slot + meshes).all(|s| pipeline_manager.in_batch(s))
+                        {
+                                slot += meshes;
+                                continue;
+                        }
+
                         render_pass.set_bind_group(
                                 3,
-                                &model.create_model_transform_bind_group(&device),
+                                &model.create_model_transform_bind_group(device),
                                 &[],
                         );
 
                         for mesh in model.meshes.iter()
                         {
-                                render_pass.set_bind_group(1, &mesh.transform_bind_group, &[]);
+                                // Skinned meshes write their depth in the
+                                // geometry pass, meshes drawn together did
+                                // above.
+                                if (model.skin.is_some() && mesh.skin_buffer.is_some())
+                                        || pipeline_manager.in_batch(slot)
+                                {
+                                        slot += 1;
+                                        continue;
+                                }
 
-                                let material_index = mesh.material;
+                                render_pass.set_bind_group(1, &mesh.transform_bind_group, &[]);
+                                // Unused, the layout is the geometry pipeline's.
                                 render_pass.set_bind_group(
                                         2,
-                                        &model.materials[material_index].material_bind_group,
+                                        &model.materials[mesh.material].material_bind_group,
                                         &[],
                                 );
 
-                                render_pass.draw_mesh(mesh);
+                                pipeline_manager.draw_mesh(&mut render_pass, slot, mesh);
+                                slot += 1;
                         }
                 }
         }
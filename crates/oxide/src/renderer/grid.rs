@@ -0,0 +1,273 @@
+//! Reference grid on the XZ plane and the world axes.
+//!
+//! The grid follows the camera and fades out with distance, the X, Y and Z
+//! axes are drawn from the origin in red, green and blue. Everything is
+//! generated in `grid.wgsl` from the instance index, one instance per line,
+//! so the pass has no vertex buffers.
+//!
+//! Enabled with
+//! [`EngineBuilder::with_grid`](crate::engine::EngineBuilder::with_grid)
+//! or from the debug UI.
+
+use crate::renderer::graph::{Attachment, AttachmentLoad, DEPTH, RenderPass, SURFACE};
+use crate::renderer::pipeline::{PipelineKind, PipelineManager};
+use crate::texture::Texture;
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+use std::collections::HashMap;
+
+/// Upper bound of lines per side, keeps tiny spacings from drawing
+/// millions of lines.
+const MAX_HALF_LINES: u32 = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GridSettings
+{
+        pub enabled: bool,
+
+        /// Distance between two grid lines, every tenth line is brighter.
+        pub spacing: f32,
+
+        /// Distance from the camera where the grid is gone.
+        pub fade_distance: f32,
+
+        pub show_axes: bool,
+}
+
+impl Default for GridSettings
+{
+        fn default() -> Self
+        {
+                Self {
+                        enabled: false,
+                        spacing: 1.0,
+                        fade_distance: 50.0,
+                        show_axes: true,
+                }
+        }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct GridUniform
+{
+        spacing: f32,
+        fade_distance: f32,
+        half_lines: u32,
+        _padding: u32,
+}
+
+pub fn create_grid_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout
+{
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Uniform,
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                        },
+                        count: None,
+                }],
+                label: Some("grid_bind_group_layout"),
+        })
+}
+
+/// Uniform buffer of the pass and its bind group, created on the first
+/// frame.
+#[derive(Debug)]
+struct Resources
+{
+        buffer: wgpu::Buffer,
+        bind_group: wgpu::BindGroup,
+}
+
+impl Resources
+{
+        fn new(device: &wgpu::Device) -> Self
+        {
+                let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                        label: Some("Grid Buffer"),
+                        size: std::mem::size_of::<GridUniform>() as u64,
+                        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                        mapped_at_creation: false,
+                });
+
+                let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                        layout: &create_grid_bind_group_layout(device),
+                        entries: &[wgpu::BindGroupEntry {
+                                binding: 0,
+                                resource: buffer.as_entire_binding(),
+                        }],
+                        label: Some("grid_bind_group"),
+                });
+
+                Self {
+                        buffer,
+                        bind_group,
+                }
+        }
+}
+
+#[derive(Debug)]
+pub struct GridPass
+{
+        pub name: String,
+        pub enabled: bool,
+        pub spacing: f32,
+        pub fade_distance: f32,
+        pub show_axes: bool,
+        resources: Option<Resources>,
+}
+
+impl GridPass
+{
+        pub fn new(settings: &GridSettings) -> Self
+        {
+                Self {
+                        name: "grid_pass".to_string(),
+                        enabled: settings.enabled,
+                        spacing: settings.spacing,
+                        fade_distance: settings.fade_distance,
+                        show_axes: settings.show_axes,
+                        resources: None,
+                }
+        }
+
+        fn uniform(&self) -> GridUniform
+        {
+                let spacing = self.spacing.max(0.01);
+                let fade_distance = self.fade_distance.max(spacing);
+
+                GridUniform {
+                        spacing,
+                        fade_distance,
+                        half_lines: ((fade_distance / spacing).ceil() as u32).min(MAX_HALF_LINES),
+                        _padding: 0,
+                }
+        }
+}
+
+impl RenderPass for GridPass
+{
+        fn name(&self) -> &str
+        {
+                self.name.as_str()
+        }
+
+        fn as_any(&self) -> &dyn Any
+        {
+                self
+        }
+
+        fn as_any_mut(&mut self) -> &mut dyn Any
+        {
+                self
+        }
+
+        #[cfg(feature = "ui")]
+        fn ui(
+                &mut self,
+                ui: &mut egui::Ui,
+        )
+        {
+                egui::CollapsingHeader::new(&self.name)
+                        .default_open(true)
+                        .show(ui, |ui| {
+                                ui.horizontal(|ui| {
+                                        ui.label("Spacing");
+                                        ui.add(egui::DragValue::new(&mut self.spacing)
+                                                .speed(0.05)
+                                                .range(0.01..=100.0));
+                                });
+
+                                ui.horizontal(|ui| {
+                                        ui.label("Fade distance");
+                                        ui.add(egui::DragValue::new(&mut self.fade_distance)
+                                                .speed(0.5)
+                                                .range(1.0..=1000.0));
+                                });
+
+                                ui.checkbox(&mut self.show_axes, "Axes");
+                        });
+        }
+
+        fn enabled(&mut self) -> bool
+        {
+                self.enabled
+        }
+
+        fn attachments(&self) -> Vec<Attachment>
+        {
+                vec![
+                        Attachment::new(SURFACE, AttachmentLoad::Load, true),
+                        Attachment::new(DEPTH, AttachmentLoad::Load, true),
+                ]
+        }
+
+        fn set_enabled(
+                &mut self,
+                value: bool,
+        )
+        {
+                self.enabled = value;
+        }
+
+        fn record(
+                &mut self,
+                view: &wgpu::TextureView,
+                encoder: &mut wgpu::CommandEncoder,
+                camera: &wgpu::BindGroup,
+                pipeline_manager: &PipelineManager,
+                depth_texture: &Texture,
+                #[allow(unused_variables)] models: Option<&HashMap<String, crate::model::Model>>,
+                device: &wgpu::Device,
+                queue: &wgpu::Queue,
+        )
+        {
+                let uniform = self.uniform();
+
+                let resources = self.resources.get_or_insert_with(|| Resources::new(device));
+
+                queue.write_buffer(&resources.buffer, 0, bytemuck::cast_slice(&[uniform]));
+
+                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        label: Some(&self.name),
+                        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                                view,
+                                resolve_target: None,
+                                ops: wgpu::Operations {
+                                        load: wgpu::LoadOp::Load,
+                                        store: wgpu::StoreOp::Store,
+                                },
+                        })],
+                        depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                                view: &depth_texture.view,
+                                depth_ops: Some(wgpu::Operations {
+                                        load: wgpu::LoadOp::Load,
+                                        store: wgpu::StoreOp::Store,
+                                }),
+                                stencil_ops: None,
+                        }),
+                        occlusion_query_set: None,
+                        timestamp_writes: None,
+                });
+
+                pipeline_manager.apply_viewport(&mut render_pass);
+                render_pass.set_pipeline(pipeline_manager.get(PipelineKind::Grid));
+
+                render_pass.set_bind_group(0, camera, &[]);
+                render_pass.set_bind_group(1, &resources.bind_group, &[]);
+
+                // Lines parallel to Z and X, then the three axes.
+                let mut instances = 2 * (2 * uniform.half_lines + 1);
+
+                if self.show_axes
+                {
+                        instances += 3;
+                }
+
+                render_pass.draw(0..2, 0..instances);
+        }
+}
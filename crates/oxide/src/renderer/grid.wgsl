@@ -0,0 +1,89 @@
+struct CameraUniform {
+    view_pos: vec4<f32>,
+    view_proj: mat4x4<f32>,
+};
+
+struct Grid {
+    spacing: f32,
+    fade_distance: f32,
+    // Lines on each side of the camera, per direction.
+    half_lines: u32,
+    _padding: u32,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) world_position: vec3<f32>,
+    @location(1) color: vec4<f32>,
+};
+
+@group(0) @binding(0) var<uniform> camera: CameraUniform;
+@group(1) @binding(0) var<uniform> grid: Grid;
+
+// Every instance is one line, the two vertices are its ends. The first
+// instances are the grid lines parallel to Z, then the ones parallel to X,
+// then the X, Y and Z axes.
+@vertex
+fn vs_main(
+    @builtin(vertex_index) vertex: u32,
+    @builtin(instance_index) instance: u32,
+) -> VertexOutput {
+    var out: VertexOutput;
+
+    let lines = 2u * grid.half_lines + 1u;
+
+    var start: vec3<f32>;
+    var end: vec3<f32>;
+
+    if instance < 2u * lines {
+        // Snapped to major lines so the grid doesn't move with the camera.
+        let major = grid.spacing * 10.0;
+        let origin = floor(camera.view_pos.xz / major) * major;
+        let extent = f32(grid.half_lines) * grid.spacing;
+
+        let along_z = instance < lines;
+        let step = i32(select(instance - lines, instance, along_z)) - i32(grid.half_lines);
+        let offset = f32(step) * grid.spacing;
+
+        if along_z {
+            start = vec3<f32>(origin.x + offset, 0.0, origin.y - extent);
+            end = vec3<f32>(origin.x + offset, 0.0, origin.y + extent);
+        } else {
+            start = vec3<f32>(origin.x - extent, 0.0, origin.y + offset);
+            end = vec3<f32>(origin.x + extent, 0.0, origin.y + offset);
+        }
+
+        if step % 10 == 0 {
+            out.color = vec4<f32>(0.7, 0.7, 0.7, 0.6);
+        } else {
+            out.color = vec4<f32>(0.5, 0.5, 0.5, 0.3);
+        }
+    } else {
+        let axis = instance - 2u * lines;
+        let direction = vec3<f32>(f32(axis == 0u), f32(axis == 1u), f32(axis == 2u));
+
+        start = vec3<f32>(0.0);
+        end = direction * grid.fade_distance;
+
+        out.color = vec4<f32>(direction, 1.0);
+    }
+
+    let world_position = mix(start, end, f32(vertex));
+
+    out.clip_position = camera.view_proj * vec4<f32>(world_position, 1.0);
+    out.world_position = world_position;
+
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let distance = length(in.world_position - camera.view_pos.xyz);
+    let fade = 1.0 - smoothstep(grid.fade_distance * 0.5, grid.fade_distance, distance);
+
+    if fade <= 0.0 {
+        discard;
+    }
+
+    return vec4<f32>(in.color.rgb, in.color.a * fade);
+}
@@ -0,0 +1,216 @@
+//! Highlighting models with a rim.
+//!
+//! [`HighlightPass`] draws the models queued with [`Engine::highlight`] a
+//! second time on top of themselves, brightening them towards their
+//! silhouette in the given color. Like [`Engine::debug_lines`], queued
+//! models are drawn once in the next frame, so they're queued every frame
+//! they stay highlighted. The debug UI highlights the model under the
+//! cursor, see [`crate::ui::hover`].
+//!
+//! Skinned meshes aren't highlighted, the pass would draw their bind pose.
+
+#[cfg(feature = "runtime")]
+use crate::engine::Engine;
+use crate::model::{DrawModel, Model};
+use crate::renderer::graph::{Attachment, AttachmentLoad, DEPTH, RenderPass, SURFACE};
+use crate::renderer::pipeline::{PipelineKind, PipelineManager};
+use crate::texture::Texture;
+use std::any::Any;
+use std::collections::HashMap;
+
+#[derive(Debug)]
+pub struct HighlightPass
+{
+        pub name: String,
+        pub enabled: bool,
+        /// Queued models by handle with their rim color, cleared after they
+        /// were drawn.
+        pub models: Vec<(String, [f32; 3])>,
+}
+
+impl HighlightPass
+{
+        pub fn new() -> Self
+        {
+                Self {
+                        name: "highlight_pass".to_string(),
+                        enabled: true,
+                        models: Vec::new(),
+                }
+        }
+}
+
+impl Default for HighlightPass
+{
+        fn default() -> Self
+        {
+                Self::new()
+        }
+}
+
+impl RenderPass for HighlightPass
+{
+        fn name(&self) -> &str
+        {
+                self.name.as_str()
+        }
+
+        fn as_any(&self) -> &dyn Any
+        {
+                self
+        }
+
+        fn as_any_mut(&mut self) -> &mut dyn Any
+        {
+                self
+        }
+
+        #[cfg(feature = "ui")]
+        fn ui(
+                &mut self,
+                ui: &mut egui::Ui,
+        )
+        {
+                egui::CollapsingHeader::new(&self.name)
+                        .default_open(true)
+                        .show(ui, |ui| {
+                                ui.label("Outlines the hovered and selected models.");
+                        });
+        }
+
+        fn enabled(&mut self) -> bool
+        {
+                self.enabled
+        }
+
+        fn attachments(&self) -> Vec<Attachment>
+        {
+                vec![
+                        Attachment::new(SURFACE, AttachmentLoad::Load, true),
+                        Attachment::new(DEPTH, AttachmentLoad::Load, true),
+                ]
+        }
+
+        fn set_enabled(
+                &mut self,
+                value: bool,
+        )
+        {
+                self.enabled = value;
+        }
+
+        fn record(
+                &mut self,
+                view: &wgpu::TextureView,
+                encoder: &mut wgpu::CommandEncoder,
+                camera: &wgpu::BindGroup,
+                pipeline_manager: &PipelineManager,
+                depth_texture: &Texture,
+                models: Option<&HashMap<String, Model>>,
+                device: &wgpu::Device,
+                #[allow(unused_variables)] queue: &wgpu::Queue,
+        )
+        {
+                let queued = std::mem::take(&mut self.models);
+
+                let Some(models) = models
+                else
+                {
+                        return;
+                };
+
+                if queued.is_empty()
+                {
+                        return;
+                }
+
+                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        label: Some(&self.name),
+                        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                                view,
+                                resolve_target: None,
+                                ops: wgpu::Operations {
+                                        load: wgpu::LoadOp::Load,
+                                        store: wgpu::StoreOp::Store,
+                                },
+                        })],
+                        depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                                view: &depth_texture.view,
+                                depth_ops: Some(wgpu::Operations {
+                                        load: wgpu::LoadOp::Load,
+                                        store: wgpu::StoreOp::Store,
+                                }),
+                                stencil_ops: None,
+                        }),
+                        occlusion_query_set: None,
+                        timestamp_writes: None,
+                });
+
+                pipeline_manager.apply_viewport(&mut render_pass);
+                render_pass.set_pipeline(pipeline_manager.get(PipelineKind::Highlight));
+                render_pass.set_bind_group(0, camera, &[]);
+
+                for (handle, color) in &queued
+                {
+                        let Some(model) = models.get(handle).filter(|m| m.visible)
+                        else
+                        {
+                                continue;
+                        };
+
+                        let [r, g, b] = *color;
+                        render_pass.set_blend_constant(wgpu::Color {
+                                r: r as f64,
+                                g: g as f64,
+                                b: b as f64,
+                                a: 1.0,
+                        });
+
+                        render_pass.set_bind_group(
+                                3,
+                                &model.create_model_transform_bind_group(device),
+                                &[],
+                        );
+
+                        for mesh in model
+                                .meshes
+                                .iter()
+                                .filter(|mesh| model.skin.is_none() || mesh.skin_buffer.is_none())
+                        {
+                                render_pass.set_bind_group(1, &mesh.transform_bind_group, &[]);
+                                render_pass.set_bind_group(
+                                        2,
+                                        &model.materials[mesh.material].material_bind_group,
+                                        &[],
+                                );
+
+                                // Not culled, the slots of the GPU culling
+                                // are the geometry pass's.
+                                render_pass.draw_mesh(mesh);
+                        }
+                }
+        }
+}
+
+#[cfg(feature = "runtime")]
+impl Engine
+{
+        /// Outlines the model `handle` in `color` in the next frame, see
+        /// [`crate::renderer::highlight`]. Does nothing before the renderer
+        /// is initialized or while the highlight pass is disabled.
+        pub fn highlight(
+                &mut self,
+                handle: impl Into<String>,
+                color: [f32; 3],
+        )
+        {
+                if let Some(pass) = self
+                        .state
+                        .as_mut()
+                        .and_then(|state| state.render_graph.pass_mut::<HighlightPass>())
+                        .filter(|pass| pass.enabled)
+                {
+                        pass.models.push((handle.into(), color));
+                }
+        }
+}
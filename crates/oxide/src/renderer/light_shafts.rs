@@ -0,0 +1,307 @@
+//! Light shafts and lens flare of the sun.
+//!
+//! The pass marches from every pixel towards the sun on screen through the
+//! depth texture, pixels where nothing was drawn let the light through, and
+//! adds the sun color on top of the scene. The flare is a glow around the
+//! sun and ghosts along the line through the center of the screen, faded by
+//! how much of the sun is uncovered.
+//!
+//! Both are toggled on the sun, see
+//! [`EnvironmentSettings::sun_shafts`](crate::renderer::graph::EnvironmentSettings::sun_shafts)
+//! and
+//! [`EnvironmentSettings::sun_flare`](crate::renderer::graph::EnvironmentSettings::sun_flare),
+//! and follow the day/night cycle.
+
+use crate::renderer::graph::{
+        Attachment, AttachmentLoad, DEPTH, EnvironmentSettings, RenderPass, SURFACE,
+};
+use crate::renderer::pipeline::{PipelineKind, PipelineManager};
+use crate::texture::Texture;
+use std::any::Any;
+use std::collections::HashMap;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct LightShaftUniform
+{
+        intensity: f32,
+        decay: f32,
+        flare_intensity: f32,
+        samples: u32,
+        shafts: u32,
+        flare: u32,
+        reversed_z: u32,
+        _padding: u32,
+        viewport: [f32; 4],
+}
+
+pub fn create_light_shafts_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout
+{
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                        wgpu::BindGroupLayoutEntry {
+                                binding: 0,
+                                visibility: wgpu::ShaderStages::FRAGMENT,
+                                ty: wgpu::BindingType::Buffer {
+                                        ty: wgpu::BufferBindingType::Uniform,
+                                        has_dynamic_offset: false,
+                                        min_binding_size: None,
+                                },
+                                count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                                binding: 1,
+                                visibility: wgpu::ShaderStages::FRAGMENT,
+                                ty: wgpu::BindingType::Texture {
+                                        sample_type: wgpu::TextureSampleType::Depth,
+                                        view_dimension: wgpu::TextureViewDimension::D2,
+                                        multisampled: false,
+                                },
+                                count: None,
+                        },
+                ],
+                label: Some("light_shafts_bind_group_layout"),
+        })
+}
+
+/// GPU objects of the pass, created on the first frame it draws.
+#[derive(Debug)]
+struct Resources
+{
+        layout: wgpu::BindGroupLayout,
+        buffer: wgpu::Buffer,
+        /// Rebuilt when the depth texture is, e.g. on resize.
+        bind_group: Option<(wgpu::TextureView, wgpu::BindGroup)>,
+}
+
+impl Resources
+{
+        fn new(device: &wgpu::Device) -> Self
+        {
+                Self {
+                        layout: create_light_shafts_bind_group_layout(device),
+                        buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                                label: Some("Light Shafts Buffer"),
+                                size: std::mem::size_of::<LightShaftUniform>() as u64,
+                                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                                mapped_at_creation: false,
+                        }),
+                        bind_group: None,
+                }
+        }
+
+        fn bind_group(
+                &mut self,
+                device: &wgpu::Device,
+                depth: &wgpu::TextureView,
+        ) -> &wgpu::BindGroup
+        {
+                if self.bind_group
+                        .as_ref()
+                        .is_none_or(|(view, _)| view != depth)
+                {
+                        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                                layout: &self.layout,
+                                entries: &[
+                                        wgpu::BindGroupEntry {
+                                                binding: 0,
+                                                resource: self.buffer.as_entire_binding(),
+                                        },
+                                        wgpu::BindGroupEntry {
+                                                binding: 1,
+                                                resource: wgpu::BindingResource::TextureView(depth),
+                                        },
+                                ],
+                                label: Some("light_shafts_bind_group"),
+                        });
+
+                        self.bind_group = Some((depth.clone(), bind_group));
+                }
+
+                &self.bind_group.as_ref().unwrap().1
+        }
+}
+
+#[derive(Debug)]
+pub struct LightShaftPass
+{
+        pub name: String,
+        pub enabled: bool,
+        /// Brightness of the shafts.
+        pub intensity: f32,
+        /// How much less every step towards the sun adds, 0 to 1.
+        pub decay: f32,
+        /// Steps towards the sun per pixel.
+        pub samples: u32,
+        /// Brightness of the flare.
+        pub flare_intensity: f32,
+        /// Set by [`RenderGraph::set_environment`](crate::renderer::graph::RenderGraph::set_environment).
+        pub shafts: bool,
+        /// Set by [`RenderGraph::set_environment`](crate::renderer::graph::RenderGraph::set_environment).
+        pub flare: bool,
+        resources: Option<Resources>,
+}
+
+impl LightShaftPass
+{
+        pub fn new() -> Self
+        {
+                Self {
+                        name: "light_shafts_pass".to_string(),
+                        enabled: true,
+                        intensity: 0.6,
+                        decay: 0.97,
+                        samples: 48,
+                        flare_intensity: 1.0,
+                        shafts: false,
+                        flare: false,
+                        resources: None,
+                }
+        }
+
+        pub fn set_environment(
+                &mut self,
+                environment: &EnvironmentSettings,
+        )
+        {
+                self.shafts = environment.sun_shafts;
+                self.flare = environment.sun_flare;
+        }
+
+        fn uniform(
+                &self,
+                reversed_z: bool,
+                viewport: [f32; 4],
+        ) -> LightShaftUniform
+        {
+                LightShaftUniform {
+                        intensity: self.intensity,
+                        decay: self.decay.clamp(0.0, 1.0),
+                        flare_intensity: self.flare_intensity,
+                        samples: self.samples.clamp(1, 256),
+                        shafts: self.shafts as u32,
+                        flare: self.flare as u32,
+                        reversed_z: reversed_z as u32,
+                        _padding: 0,
+                        viewport,
+                }
+        }
+}
+
+impl Default for LightShaftPass
+{
+        fn default() -> Self
+        {
+                Self::new()
+        }
+}
+
+impl RenderPass for LightShaftPass
+{
+        fn name(&self) -> &str
+        {
+                self.name.as_str()
+        }
+
+        fn as_any(&self) -> &dyn Any
+        {
+                self
+        }
+
+        fn as_any_mut(&mut self) -> &mut dyn Any
+        {
+                self
+        }
+
+        #[cfg(feature = "ui")]
+        fn ui(
+                &mut self,
+                ui: &mut egui::Ui,
+        )
+        {
+                egui::CollapsingHeader::new(&self.name)
+                        .default_open(true)
+                        .show(ui, |ui| {
+                                ui.add(egui::Slider::new(&mut self.intensity, 0.0..=2.0)
+                                        .text("Intensity"));
+                                ui.add(egui::Slider::new(&mut self.decay, 0.8..=1.0).text("Decay"));
+                                ui.add(egui::Slider::new(&mut self.samples, 8..=128)
+                                        .text("Samples"));
+                                ui.add(egui::Slider::new(&mut self.flare_intensity, 0.0..=2.0)
+                                        .text("Flare"));
+                        });
+        }
+
+        fn enabled(&mut self) -> bool
+        {
+                self.enabled
+        }
+
+        fn attachments(&self) -> Vec<Attachment>
+        {
+                vec![
+                        Attachment::new(SURFACE, AttachmentLoad::Load, true),
+                        // Read in the shader, not rendered to.
+                        Attachment::new(DEPTH, AttachmentLoad::Load, false),
+                ]
+        }
+
+        fn set_enabled(
+                &mut self,
+                value: bool,
+        )
+        {
+                self.enabled = value;
+        }
+
+        fn record(
+                &mut self,
+                view: &wgpu::TextureView,
+                encoder: &mut wgpu::CommandEncoder,
+                camera: &wgpu::BindGroup,
+                pipeline_manager: &PipelineManager,
+                depth_texture: &Texture,
+                #[allow(unused_variables)] models: Option<&HashMap<String, crate::model::Model>>,
+                device: &wgpu::Device,
+                queue: &wgpu::Queue,
+        )
+        {
+                if !self.shafts && !self.flare
+                {
+                        return;
+                }
+
+                let size = depth_texture.texture.size();
+                let viewport = pipeline_manager.viewport_rect(size.width, size.height);
+                let uniform = self.uniform(pipeline_manager.reversed_z, viewport);
+
+                let resources = self.resources.get_or_insert_with(|| Resources::new(device));
+
+                queue.write_buffer(&resources.buffer, 0, bytemuck::cast_slice(&[uniform]));
+
+                let bind_group = resources.bind_group(device, &depth_texture.view);
+
+                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        label: Some(&self.name),
+                        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                                view,
+                                resolve_target: None,
+                                ops: wgpu::Operations {
+                                        load: wgpu::LoadOp::Load,
+                                        store: wgpu::StoreOp::Store,
+                                },
+                        })],
+                        depth_stencil_attachment: None,
+                        occlusion_query_set: None,
+                        timestamp_writes: None,
+                });
+
+                pipeline_manager.apply_viewport(&mut render_pass);
+                render_pass.set_pipeline(pipeline_manager.get(PipelineKind::LightShafts));
+
+                render_pass.set_bind_group(0, camera, &[]);
+                render_pass.set_bind_group(1, bind_group, &[]);
+
+                render_pass.draw(0..3, 0..1);
+        }
+}
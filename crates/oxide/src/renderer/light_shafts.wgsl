@@ -0,0 +1,139 @@
+// Light shafts and lens flare of the sun, added on top of the scene. See
+// `light_shafts.rs`.
+
+struct Environment {
+    sun_direction: vec4<f32>,
+    sun_color: vec4<f32>,
+    ambient: vec4<f32>,
+    fog: vec4<f32>,
+    exposure: vec4<f32>,
+};
+
+struct CameraUniform {
+    view_pos: vec4<f32>,
+    view_proj: mat4x4<f32>,
+    environment: Environment,
+};
+
+struct LightShafts {
+    intensity: f32,
+    // Falloff of every step towards the sun.
+    decay: f32,
+    flare_intensity: f32,
+    samples: u32,
+    shafts: u32,
+    flare: u32,
+    reversed_z: u32,
+    _padding: u32,
+    // Where the scene is on the screen in pixels, offset in `xy` and size
+    // in `zw`. Texture coordinates below are the viewport's.
+    viewport: vec4<f32>,
+};
+
+@group(0) @binding(0) var<uniform> camera: CameraUniform;
+@group(1) @binding(0) var<uniform> settings: LightShafts;
+@group(1) @binding(1) var depth: texture_depth_2d;
+
+// One triangle covering the screen.
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+    return vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+}
+
+// 1 where nothing was drawn, the sun shines through there. Off screen
+// counts as the nearest edge.
+fn sky(uv: vec2<f32>) -> f32 {
+    let low = vec2<i32>(settings.viewport.xy);
+    let high = low + vec2<i32>(settings.viewport.zw) - 1;
+    let texel = clamp(vec2<i32>(settings.viewport.xy + uv * settings.viewport.zw), low, high);
+    let value = textureLoad(depth, texel, 0);
+
+    if settings.reversed_z != 0u {
+        return select(0.0, 1.0, value <= 0.0);
+    }
+
+    return select(0.0, 1.0, value >= 1.0);
+}
+
+fn shafts(uv: vec2<f32>, sun: vec2<f32>) -> f32 {
+    let steps = max(settings.samples, 1u);
+    let delta = (sun - uv) / f32(steps);
+
+    var position = uv;
+    var weight = 1.0;
+    var light = 0.0;
+
+    for (var i = 0u; i < steps; i++) {
+        position += delta;
+        light += sky(position) * weight;
+        weight *= settings.decay;
+    }
+
+    return light / f32(steps) * settings.intensity;
+}
+
+fn flare(uv: vec2<f32>, sun: vec2<f32>, aspect: f32) -> f32 {
+    // How much of the sun is uncovered.
+    let spread = 0.01;
+    let visible = (sky(sun) + sky(sun + vec2<f32>(spread, 0.0)) + sky(sun - vec2<f32>(spread, 0.0))
+        + sky(sun + vec2<f32>(0.0, spread)) + sky(sun - vec2<f32>(0.0, spread))) / 5.0;
+
+    // Glow around the sun, ghosts along the line through the center.
+    let scale = vec2<f32>(aspect, 1.0);
+    let axis = vec2<f32>(0.5) - sun;
+
+    var light = 0.02 / (length((uv - sun) * scale) + 0.02) * 0.3;
+
+    let ghosts = array<vec2<f32>, 4>(
+        vec2<f32>(0.6, 0.05),
+        vec2<f32>(1.2, 0.03),
+        vec2<f32>(1.5, 0.08),
+        vec2<f32>(2.1, 0.04),
+    );
+
+    for (var i = 0u; i < 4u; i++) {
+        let center = sun + axis * ghosts[i].x;
+        let radius = ghosts[i].y;
+        let distance = length((uv - center) * scale);
+
+        light += (1.0 - smoothstep(radius * 0.7, radius, distance)) * 0.15;
+    }
+
+    // Fades out as the sun nears the edge of the screen.
+    let edge = 1.0 - smoothstep(0.4, 0.7, length(sun - vec2<f32>(0.5)));
+
+    return light * visible * edge * settings.flare_intensity;
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    // The sun is infinitely far away, a direction.
+    let clip = camera.view_proj * vec4<f32>(camera.environment.sun_direction.xyz, 0.0);
+
+    // Behind the camera.
+    if clip.w <= 0.0 {
+        return vec4<f32>(0.0);
+    }
+
+    let ndc = clip.xy / clip.w;
+    let sun = vec2<f32>(ndc.x * 0.5 + 0.5, 0.5 - ndc.y * 0.5);
+
+    let size = settings.viewport.zw;
+    let uv = (position.xy - settings.viewport.xy) / size;
+
+    var light = 0.0;
+
+    if settings.shafts != 0u {
+        light += shafts(uv, sun);
+    }
+
+    if settings.flare != 0u {
+        light += flare(uv, sun, size.x / size.y);
+    }
+
+    let color = camera.environment.sun_color.rgb * light * camera.environment.exposure.x;
+
+    return vec4<f32>(color, 0.0);
+}
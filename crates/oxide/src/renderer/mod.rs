@@ -1,6 +1,25 @@
+#[cfg(feature = "gpu-culling")]
+pub mod culling;
+pub mod crowd;
+pub mod custom;
+pub mod debug_draw;
+pub mod debug_view;
+pub mod gamma_blit;
+pub mod gpu_timer;
 pub mod graph;
+pub mod grid;
+pub mod highlight;
+pub mod light_shafts;
 pub mod pipeline;
+pub mod polyline;
+pub mod preprocess;
+pub mod probe;
+pub mod readback;
 pub mod renderer;
 pub mod resource;
 pub mod shader;
+pub mod splash;
 pub mod surface;
+pub mod temporal;
+pub mod tilemap;
+pub mod viewport;
@@ -1,11 +1,49 @@
-use crate::engine::FillMode;
-use crate::model::Vertex;
+use crate::model::{DrawModel, Vertex};
+#[cfg(feature = "gpu-culling")]
+use crate::renderer::culling::GpuCulling;
+use crate::renderer::preprocess::{ShaderDefines, preprocess};
+use crate::renderer::viewport::Viewport;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Serialize, Deserialize)]
+pub enum FillMode
+{
+        Fill = 0,
+        Wireframe = 1,
+        Vertex = 2,
+        /// Filled with the triangle edges drawn on top. Done in the shader
+        /// with barycentric coordinates, so unlike `Wireframe` it doesn't
+        /// need `POLYGON_MODE_LINE` and works on WebGL.
+        WireframeOverlay = 3,
+        /// A checker of the texture coordinates, 8 squares per unit, shows
+        /// stretched or flipped UVs.
+        UvChecker = 4,
+        /// The world space normals as colors, before normal maps.
+        Normals = 5,
+        /// Every fragment adds a little red, ignoring depth, so pixels get
+        /// brighter the more often they're shaded.
+        Overdraw = 6,
+        /// The mip level the base color texture is sampled at, blue for the
+        /// full size through green and yellow to red for level 6 and
+        /// smaller, shows textures that are too large for their size on
+        /// screen.
+        MipLevel = 7,
+}
+
 #[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
 pub enum PipelineKind
 {
         Geometry,
+        Grid,
+        DebugLines,
+        Polyline,
+        Tiles,
+        Crowd,
+        LightShafts,
+        DepthPrepass,
+        Highlight,
+        Fill,
         Texture,
         Lighting,
         PostProcess,
@@ -15,6 +53,49 @@ pub enum PipelineKind
 pub struct PipelineManager
 {
         pub render_pipelines: HashMap<PipelineKind, wgpu::RenderPipeline>,
+
+        /// Pipelines of custom shaders by name, see
+        /// [`crate::renderer::custom`].
+        pub custom_pipelines: HashMap<String, wgpu::RenderPipeline>,
+
+        /// Geometry pipelines of the permutations other than the plain one
+        /// in `render_pipelines`, see [`PipelineManager::geometry_pipeline`].
+        pub geometry_permutations: HashMap<ShaderDefines, wgpu::RenderPipeline>,
+
+        /// Sources `#include` finds by name, see
+        /// [`crate::renderer::preprocess`].
+        pub includes: HashMap<String, String>,
+
+        /// Compiled pipelines kept between runs, see
+        /// [`PipelineManager::load_cache`].
+        pub cache: Option<wgpu::PipelineCache>,
+
+        /// Where the cache is saved to.
+        cache_file: Option<std::path::PathBuf>,
+
+        /// Fill mode the geometry pipeline was last built with.
+        pub fill_mode: FillMode,
+
+        /// The geometry pipelines only shade the depth the
+        /// [`DepthPrePass`](crate::renderer::graph::DepthPrePass) left,
+        /// with [`FillMode::Fill`]. Set by the engine from whether the pass
+        /// is enabled, the pipelines are built again when it changes.
+        pub depth_prepass: bool,
+
+        /// Pipelines are built for reversed-Z depth, see
+        /// [`Projection::reversed_z`](crate::camera::Projection::reversed_z).
+        pub reversed_z: bool,
+
+        /// Where the scene is drawn on the surface, all of it when `None`.
+        /// Set by the engine from
+        /// [`CameraConfig::locked_aspect`](crate::camera::CameraConfig::locked_aspect),
+        /// see [`crate::renderer::viewport`].
+        pub viewport: Option<Viewport>,
+
+        /// Culls the meshes on the GPU when the device supports it, see
+        /// [`crate::renderer::culling`].
+        #[cfg(feature = "gpu-culling")]
+        pub culling: Option<GpuCulling>,
 }
 
 impl PipelineManager
@@ -25,9 +106,266 @@ impl PipelineManager
 
                 Self {
                         render_pipelines: map,
+                        custom_pipelines: HashMap::new(),
+                        geometry_permutations: HashMap::new(),
+                        includes: HashMap::from([
+                                (
+                                        "common.wgsl".to_string(),
+                                        include_str!("common.wgsl").to_string(),
+                                ),
+                                (
+                                        "shader.wgsl".to_string(),
+                                        include_str!("shader.wgsl").to_string(),
+                                ),
+                                (
+                                        "tilemap.wgsl".to_string(),
+                                        include_str!("tilemap.wgsl").to_string(),
+                                ),
+                                ("crowd.wgsl".to_string(), include_str!("crowd.wgsl").to_string()),
+                        ]),
+                        cache: None,
+                        cache_file: None,
+                        fill_mode: FillMode::Fill,
+                        depth_prepass: false,
+                        viewport: None,
+                        reversed_z: false,
+                        #[cfg(feature = "gpu-culling")]
+                        culling: None,
+                }
+        }
+
+        /// Creates the pipeline cache from the file in `dir` saved by an
+        /// earlier run on the same adapter, or an empty one. Only some
+        /// backends support it, e.g. Vulkan, elsewhere nothing is cached.
+        /// Must be called before the pipelines are built.
+        #[cfg(not(target_arch = "wasm32"))]
+        pub fn load_cache(
+                &mut self,
+                device: &wgpu::Device,
+                adapter: &wgpu::AdapterInfo,
+                dir: &std::path::Path,
+        )
+        {
+                if !device.features().contains(wgpu::Features::PIPELINE_CACHE)
+                {
+                        log::info!("Pipeline cache unsupported on {:?}", adapter.backend);
+                        return;
+                }
+
+                let Some(key) = wgpu::util::pipeline_cache_key(adapter)
+                else
+                {
+                        return;
+                };
+
+                let path = dir.join(key);
+
+                let data = match std::fs::read(&path)
+                {
+                        Ok(data) => Some(data),
+                        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+                        Err(e) =>
+                        {
+                                log::warn!("Failed to read {}: {}", path.display(), e);
+                                None
+                        }
+                };
+
+                // SAFETY: The data was saved by `save_cache` for this
+                // adapter, the key holds the driver version. wgpu checks its
+                // header and starts empty when it doesn't match.
+                let cache = unsafe {
+                        device.create_pipeline_cache(&wgpu::PipelineCacheDescriptor {
+                                label: Some("Pipeline Cache"),
+                                data: data.as_deref(),
+                                fallback: true,
+                        })
+                };
+
+                self.cache = Some(cache);
+                self.cache_file = Some(path);
+        }
+
+        /// Writes the pipeline cache to the file it was loaded from.
+        #[cfg(not(target_arch = "wasm32"))]
+        pub fn save_cache(&self) -> anyhow::Result<()>
+        {
+                use anyhow::Context;
+
+                let (Some(cache), Some(path)) = (&self.cache, &self.cache_file)
+                else
+                {
+                        return Ok(());
+                };
+
+                let Some(data) = cache.get_data()
+                else
+                {
+                        return Ok(());
+                };
+
+                if let Some(dir) = path.parent()
+                {
+                        std::fs::create_dir_all(dir)?;
+                }
+
+                // Renamed into place, a crash while writing doesn't leave a
+                // broken cache behind.
+                let temporary = path.with_extension("tmp");
+
+                std::fs::write(&temporary, data)
+                        .with_context(|| format!("Failed to write {}", temporary.display()))?;
+                std::fs::rename(&temporary, path)
+                        .with_context(|| format!("Failed to write {}", path.display()))
+        }
+
+        /// `compare` as written for regular depth, flipped for reversed-Z.
+        pub fn depth_compare(
+                &self,
+                compare: wgpu::CompareFunction,
+        ) -> wgpu::CompareFunction
+        {
+                if !self.reversed_z
+                {
+                        return compare;
+                }
+
+                match compare
+                {
+                        wgpu::CompareFunction::Less => wgpu::CompareFunction::Greater,
+                        wgpu::CompareFunction::LessEqual => wgpu::CompareFunction::GreaterEqual,
+                        wgpu::CompareFunction::Greater => wgpu::CompareFunction::Less,
+                        wgpu::CompareFunction::GreaterEqual => wgpu::CompareFunction::LessEqual,
+                        compare => compare,
+                }
+        }
+
+        /// The depth pre-pass runs, see [`PipelineManager::depth_prepass`].
+        pub fn depth_prepass_active(&self) -> bool
+        {
+                self.depth_prepass && self.fill_mode == FillMode::Fill
+        }
+
+        /// Depth test of models, equal to the depth of the pre-pass when
+        /// `prepass`, which already wrote it.
+        fn geometry_depth_state(
+                &self,
+                prepass: bool,
+        ) -> wgpu::DepthStencilState
+        {
+                wgpu::DepthStencilState {
+                        format: crate::texture::Texture::DEPTH_FORMAT,
+                        depth_write_enabled: !prepass,
+                        depth_compare: if prepass
+                        {
+                                wgpu::CompareFunction::Equal
+                        }
+                        else
+                        {
+                                self.depth_compare(wgpu::CompareFunction::Less)
+                        },
+                        stencil: wgpu::StencilState::default(),
+                        bias: wgpu::DepthBiasState::default(),
+                }
+        }
+
+        /// Depth the depth texture is cleared to, the far plane.
+        pub fn depth_clear(&self) -> f32
+        {
+                if self.reversed_z { 0.0 } else { 1.0 }
+        }
+
+        /// Draws the `slot`th mesh the model passes draw this frame, with its
+        /// indirect draw when it was culled on the GPU, see
+        /// [`crate::renderer::culling`].
+        pub fn draw_mesh<'a>(
+                &self,
+                render_pass: &mut wgpu::RenderPass<'a>,
+                #[allow(unused_variables)] slot: u32,
+                mesh: &'a crate::geometry::mesh::Mesh,
+        )
+        {
+                #[cfg(feature = "gpu-culling")]
+                if self.culling
+                        .as_ref()
+                        .is_some_and(|c| c.draw_mesh(render_pass, slot, mesh))
+                {
+                        return;
+                }
+
+                render_pass.draw_mesh(mesh);
+        }
+
+        /// Draws the depth of the meshes drawn together, see
+        /// [`crate::renderer::culling`].
+        pub fn draw_depth_batch(
+                &self,
+                #[allow(unused_variables)] render_pass: &mut wgpu::RenderPass,
+        )
+        {
+                #[cfg(feature = "gpu-culling")]
+                if let Some(culling) = &self.culling
+                {
+                        culling.draw_depth(render_pass);
+                }
+        }
+
+        /// Draws the meshes drawn together in the geometry pass, see
+        /// [`crate::renderer::culling`].
+        pub fn draw_geometry_batches(
+                &self,
+                #[allow(unused_variables)] render_pass: &mut wgpu::RenderPass,
+        )
+        {
+                #[cfg(feature = "gpu-culling")]
+                if let Some(culling) = &self.culling
+                {
+                        culling.draw_batches(render_pass);
+                }
+        }
+
+        /// Whether the `slot`th mesh is drawn together with others instead
+        /// of by [`PipelineManager::draw_mesh`].
+        pub fn in_batch(
+                &self,
+                #[allow(unused_variables)] slot: u32,
+        ) -> bool
+        {
+                #[cfg(feature = "gpu-culling")]
+                if let Some(culling) = &self.culling
+                {
+                        return culling.in_batch(slot);
+                }
+
+                false
+        }
+
+        /// Draws only inside the [`PipelineManager::viewport`] in
+        /// `render_pass`, for the passes drawing the scene.
+        pub fn apply_viewport(
+                &self,
+                render_pass: &mut wgpu::RenderPass,
+        )
+        {
+                if let Some(viewport) = self.viewport
+                {
+                        viewport.apply(render_pass);
                 }
         }
 
+        /// The [`PipelineManager::viewport`] on a `width` by `height`
+        /// target, offset and size in pixels.
+        pub fn viewport_rect(
+                &self,
+                width: u32,
+                height: u32,
+        ) -> [f32; 4]
+        {
+                self.viewport
+                        .unwrap_or(Viewport::full(width, height))
+                        .rect()
+        }
+
         pub fn get(
                 &self,
                 kind: PipelineKind,
@@ -48,15 +386,70 @@ impl PipelineManager
                         .expect("Pipeline not found")
         }
 
+        /// The include `name` preprocessed for `defines`.
+        pub fn shader_source(
+                &self,
+                name: &str,
+                defines: &ShaderDefines,
+        ) -> Result<String, String>
+        {
+                preprocess(&format!("#include \"{}\"", name), &self.includes, defines)
+        }
+
         /// Loads the shader module data from the `wgsl` file.
-        pub fn load_shader_module(device: &wgpu::Device) -> wgpu::ShaderModule
+        pub fn load_shader_module(
+                &self,
+                device: &wgpu::Device,
+        ) -> wgpu::ShaderModule
         {
+                let source = self
+                        .shader_source("shader.wgsl", &ShaderDefines::new())
+                        .expect("Model shader doesn't preprocess");
+
                 device.create_shader_module(wgpu::ShaderModuleDescriptor {
                         label: Some("Shader"),
-                        source: wgpu::ShaderSource::Wgsl(include_str!("shader.wgsl").into()),
+                        source: wgpu::ShaderSource::Wgsl(source.into()),
                 })
         }
 
+        /// The geometry pipeline of a permutation, the plain one until the
+        /// permutation is built.
+        pub fn geometry_pipeline(
+                &self,
+                defines: &ShaderDefines,
+        ) -> &wgpu::RenderPipeline
+        {
+                self.geometry_permutations
+                        .get(defines)
+                        .unwrap_or_else(|| self.get(PipelineKind::Geometry))
+        }
+
+        /// Builds the geometry pipeline of a permutation when it isn't
+        /// cached, for the fill mode of the plain one.
+        pub fn build_geometry_permutation(
+                &mut self,
+                device: &wgpu::Device,
+                config: &wgpu::SurfaceConfiguration,
+                bind_groups: &[&wgpu::BindGroupLayout],
+                defines: &ShaderDefines,
+        )
+        {
+                if defines.is_empty() || self.geometry_permutations.contains_key(defines)
+                {
+                        return;
+                }
+
+                let pipeline = self.create_geometry_pipeline(
+                        device,
+                        config,
+                        bind_groups,
+                        &self.fill_mode,
+                        defines,
+                );
+
+                self.geometry_permutations.insert(defines.clone(), pipeline);
+        }
+
         pub fn new_render_pipeline_layout(
                 device: &wgpu::Device,
                 bind_groups: &[&wgpu::BindGroupLayout],
@@ -69,6 +462,8 @@ impl PipelineManager
                 })
         }
 
+        /// Builds the plain geometry pipeline, the permutations are built
+        /// again as they're used.
         pub fn build_geometry_pipeline(
                 &mut self,
                 device: &wgpu::Device,
@@ -76,6 +471,31 @@ impl PipelineManager
                 bind_groups: &[&wgpu::BindGroupLayout],
                 fill_mode: &FillMode,
         )
+        {
+                let pipeline = self.create_geometry_pipeline(
+                        device,
+                        config,
+                        bind_groups,
+                        fill_mode,
+                        &ShaderDefines::new(),
+                );
+
+                self.render_pipelines
+                        .insert(PipelineKind::Geometry, pipeline);
+
+                self.geometry_permutations.clear();
+
+                self.fill_mode = *fill_mode;
+        }
+
+        fn create_geometry_pipeline(
+                &self,
+                device: &wgpu::Device,
+                config: &wgpu::SurfaceConfiguration,
+                bind_groups: &[&wgpu::BindGroupLayout],
+                fill_mode: &FillMode,
+                defines: &ShaderDefines,
+        ) -> wgpu::RenderPipeline
         {
                 let polygon_mode = match fill_mode
                 {
@@ -94,11 +514,91 @@ impl PipelineManager
                                 }
                         }
                         FillMode::Vertex => wgpu::PolygonMode::Point,
+                        FillMode::WireframeOverlay
+                        | FillMode::UvChecker
+                        | FillMode::Normals
+                        | FillMode::Overdraw
+                        | FillMode::MipLevel => wgpu::PolygonMode::Fill,
+                };
+
+                // The overlay draws meshes unindexed with barycentric
+                // coordinates, see `Mesh::barycentric_buffer`.
+                let (vertex_entry, fragment_entry, vertex_layout) = match fill_mode
+                {
+                        FillMode::WireframeOverlay => (
+                                "vs_wireframe",
+                                "fs_wireframe",
+                                crate::geometry::mesh::BarycentricVertex::desc(),
+                        ),
+                        FillMode::UvChecker =>
+                        {
+                                ("vs_main", "fs_uv_checker", crate::model::ModelVertex::desc())
+                        }
+                        FillMode::Normals =>
+                        {
+                                ("vs_main", "fs_normals", crate::model::ModelVertex::desc())
+                        }
+                        FillMode::Overdraw =>
+                        {
+                                ("vs_main", "fs_overdraw", crate::model::ModelVertex::desc())
+                        }
+                        FillMode::MipLevel =>
+                        {
+                                ("vs_main", "fs_mip_level", crate::model::ModelVertex::desc())
+                        }
+                        _ => ("vs_main", "fs_main", crate::model::ModelVertex::desc()),
                 };
 
+                // Overdraw adds up every fragment instead of keeping the
+                // nearest.
+                let overdraw = *fill_mode == FillMode::Overdraw;
+                let blend = if overdraw
+                {
+                        wgpu::BlendState {
+                                color: wgpu::BlendComponent {
+                                        src_factor: wgpu::BlendFactor::One,
+                                        dst_factor: wgpu::BlendFactor::One,
+                                        operation: wgpu::BlendOperation::Add,
+                                },
+                                alpha: wgpu::BlendComponent::OVER,
+                        }
+                }
+                else
+                {
+                        wgpu::BlendState::ALPHA_BLENDING
+                };
+
+                // Skinned meshes aren't in the depth pre-pass, they test and
+                // write depth here.
+                let skinned =
+                        defines.contains("SKINNED") && *fill_mode != FillMode::WireframeOverlay;
+                let prepass = *fill_mode == FillMode::Fill && self.depth_prepass && !skinned;
+
+                let mut depth_stencil = self.geometry_depth_state(prepass);
+
+                if overdraw
+                {
+                        depth_stencil.depth_write_enabled = false;
+                        depth_stencil.depth_compare = wgpu::CompareFunction::Always;
+                }
+
+                let skin_layout = crate::geometry::skin::SkinVertex::desc();
+                let buffers = if skinned
+                {
+                        vec![vertex_layout, skin_layout]
+                }
+                else
+                {
+                        vec![vertex_layout]
+                };
+
+                let source = self
+                        .shader_source("shader.wgsl", defines)
+                        .expect("Model shader doesn't preprocess");
+
                 let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
                         label: Some("Geometry Shader"),
-                        source: wgpu::ShaderSource::Wgsl(include_str!("shader.wgsl").into()),
+                        source: wgpu::ShaderSource::Wgsl(source.into()),
                 });
 
                 let render_pipeline_layout =
@@ -108,9 +608,69 @@ impl PipelineManager
                                 push_constant_ranges: &[],
                         });
 
-                let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
                         label: Some("Geometry Pipeline"),
                         layout: Some(&render_pipeline_layout),
+                        vertex: wgpu::VertexState {
+                                module: &shader,
+                                entry_point: Some(vertex_entry),
+                                buffers: &buffers,
+                                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        },
+                        fragment: Some(wgpu::FragmentState {
+                                module: &shader,
+                                entry_point: Some(fragment_entry),
+                                targets: &[Some(wgpu::ColorTargetState {
+                                        format: config.format,
+                                        blend: Some(blend),
+                                        write_mask: wgpu::ColorWrites::ALL,
+                                })],
+                                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        }),
+                        primitive: wgpu::PrimitiveState {
+                                topology: wgpu::PrimitiveTopology::TriangleList,
+                                strip_index_format: None,
+                                front_face: wgpu::FrontFace::Ccw,
+                                cull_mode: Some(wgpu::Face::Back),
+                                polygon_mode,
+                                conservative: false,
+                                unclipped_depth: false,
+                        },
+                        depth_stencil: Some(depth_stencil),
+                        multisample: wgpu::MultisampleState::default(),
+                        multiview: None,
+                        cache: self.cache.as_ref(),
+                })
+        }
+
+        /// Models with the custom shader `name`, `source` being the full
+        /// source from [`crate::renderer::custom::source`]. Always filled.
+        pub fn build_custom_pipeline(
+                &mut self,
+                device: &wgpu::Device,
+                config: &wgpu::SurfaceConfiguration,
+                bind_groups: &[&wgpu::BindGroupLayout],
+                name: &str,
+                source: &str,
+        )
+        {
+                let prepass = self.depth_prepass;
+
+                let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                        label: Some(name),
+                        source: wgpu::ShaderSource::Wgsl(source.into()),
+                });
+
+                let render_pipeline_layout =
+                        device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                                label: Some("Custom Pipeline Layout"),
+                                bind_group_layouts: bind_groups,
+                                push_constant_ranges: &[],
+                        });
+
+                let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                        label: Some(name),
+                        layout: Some(&render_pipeline_layout),
                         vertex: wgpu::VertexState {
                                 module: &shader,
                                 entry_point: Some("vs_main"),
@@ -132,23 +692,617 @@ impl PipelineManager
                                 strip_index_format: None,
                                 front_face: wgpu::FrontFace::Ccw,
                                 cull_mode: Some(wgpu::Face::Back),
-                                polygon_mode,
+                                polygon_mode: wgpu::PolygonMode::Fill,
                                 conservative: false,
                                 unclipped_depth: false,
                         },
-                        depth_stencil: Some(wgpu::DepthStencilState {
-                                format: crate::texture::Texture::DEPTH_FORMAT,
-                                depth_write_enabled: true,
-                                depth_compare: wgpu::CompareFunction::Less,
-                                stencil: wgpu::StencilState::default(),
-                                bias: wgpu::DepthBiasState::default(),
-                        }),
+                        depth_stencil: Some(self.geometry_depth_state(prepass)),
                         multisample: wgpu::MultisampleState::default(),
                         multiview: None,
-                        cache: None,
+                        cache: self.cache.as_ref(),
                 });
 
-                self.render_pipelines
-                        .insert(PipelineKind::Geometry, pipeline);
+                self.custom_pipelines.insert(name.to_string(), pipeline);
+        }
+
+        /// Lines of the reference grid and the axes, see
+        /// [`GridPass`](crate::renderer::grid::GridPass).
+        pub fn build_grid_pipeline(
+                &mut self,
+                device: &wgpu::Device,
+                config: &wgpu::SurfaceConfiguration,
+                bind_groups: &[&wgpu::BindGroupLayout],
+        )
+        {
+                let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                        label: Some("Grid Shader"),
+                        source: wgpu::ShaderSource::Wgsl(include_str!("grid.wgsl").into()),
+                });
+
+                let render_pipeline_layout =
+                        device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                                label: Some("Grid Pipeline Layout"),
+                                bind_group_layouts: bind_groups,
+                                push_constant_ranges: &[],
+                        });
+
+                let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                        label: Some("Grid Pipeline"),
+                        layout: Some(&render_pipeline_layout),
+                        vertex: wgpu::VertexState {
+                                module: &shader,
+                                entry_point: Some("vs_main"),
+                                buffers: &[],
+                                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        },
+                        fragment: Some(wgpu::FragmentState {
+                                module: &shader,
+                                entry_point: Some("fs_main"),
+                                targets: &[Some(wgpu::ColorTargetState {
+                                        format: config.format,
+                                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                                        write_mask: wgpu::ColorWrites::ALL,
+                                })],
+                                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        }),
+                        primitive: wgpu::PrimitiveState {
+                                topology: wgpu::PrimitiveTopology::LineList,
+                                strip_index_format: None,
+                                front_face: wgpu::FrontFace::Ccw,
+                                cull_mode: None,
+                                polygon_mode: wgpu::PolygonMode::Fill,
+                                conservative: false,
+                                unclipped_depth: false,
+                        },
+                        // Tested against the scene but doesn't occlude it.
+                        depth_stencil: Some(wgpu::DepthStencilState {
+                                format: crate::texture::Texture::DEPTH_FORMAT,
+                                depth_write_enabled: false,
+                                depth_compare: self.depth_compare(wgpu::CompareFunction::Less),
+                                stencil: wgpu::StencilState::default(),
+                                bias: wgpu::DepthBiasState::default(),
+                        }),
+                        multisample: wgpu::MultisampleState::default(),
+                        multiview: None,
+                        cache: self.cache.as_ref(),
+                });
+
+                self.render_pipelines.insert(PipelineKind::Grid, pipeline);
+        }
+
+        /// World space lines of the
+        /// [`DebugDrawPass`](crate::renderer::debug_draw::DebugDrawPass).
+        pub fn build_debug_lines_pipeline(
+                &mut self,
+                device: &wgpu::Device,
+                config: &wgpu::SurfaceConfiguration,
+                bind_groups: &[&wgpu::BindGroupLayout],
+        )
+        {
+                let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                        label: Some("Debug Lines Shader"),
+                        source: wgpu::ShaderSource::Wgsl(include_str!("debug_lines.wgsl").into()),
+                });
+
+                let render_pipeline_layout =
+                        device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                                label: Some("Debug Lines Pipeline Layout"),
+                                bind_group_layouts: bind_groups,
+                                push_constant_ranges: &[],
+                        });
+
+                let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                        label: Some("Debug Lines Pipeline"),
+                        layout: Some(&render_pipeline_layout),
+                        vertex: wgpu::VertexState {
+                                module: &shader,
+                                entry_point: Some("vs_main"),
+                                buffers: &[crate::renderer::debug_draw::DebugVertex::desc()],
+                                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        },
+                        fragment: Some(wgpu::FragmentState {
+                                module: &shader,
+                                entry_point: Some("fs_main"),
+                                targets: &[Some(wgpu::ColorTargetState {
+                                        format: config.format,
+                                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                                        write_mask: wgpu::ColorWrites::ALL,
+                                })],
+                                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        }),
+                        primitive: wgpu::PrimitiveState {
+                                topology: wgpu::PrimitiveTopology::LineList,
+                                strip_index_format: None,
+                                front_face: wgpu::FrontFace::Ccw,
+                                cull_mode: None,
+                                polygon_mode: wgpu::PolygonMode::Fill,
+                                conservative: false,
+                                unclipped_depth: false,
+                        },
+                        // `LessEqual` so the wireframe shows on top of its own
+                        // triangles.
+                        depth_stencil: Some(wgpu::DepthStencilState {
+                                format: crate::texture::Texture::DEPTH_FORMAT,
+                                depth_write_enabled: false,
+                                depth_compare: self.depth_compare(wgpu::CompareFunction::LessEqual),
+                                stencil: wgpu::StencilState::default(),
+                                bias: wgpu::DepthBiasState::default(),
+                        }),
+                        multisample: wgpu::MultisampleState::default(),
+                        multiview: None,
+                        cache: self.cache.as_ref(),
+                });
+
+                self.render_pipelines
+                        .insert(PipelineKind::DebugLines, pipeline);
+        }
+
+        /// The tiles of the tile maps, the model pipeline with an instance
+        /// buffer of cell transforms, see
+        /// [`TileMapPass`](crate::renderer::tilemap::TileMapPass).
+        pub fn build_tiles_pipeline(
+                &mut self,
+                device: &wgpu::Device,
+                config: &wgpu::SurfaceConfiguration,
+                bind_groups: &[&wgpu::BindGroupLayout],
+        )
+        {
+                let source = self
+                        .shader_source("tilemap.wgsl", &ShaderDefines::new())
+                        .expect("Tile map shader doesn't preprocess");
+
+                let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                        label: Some("Tiles Shader"),
+                        source: wgpu::ShaderSource::Wgsl(source.into()),
+                });
+
+                let render_pipeline_layout =
+                        device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                                label: Some("Tiles Pipeline Layout"),
+                                bind_group_layouts: bind_groups,
+                                push_constant_ranges: &[],
+                        });
+
+                let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                        label: Some("Tiles Pipeline"),
+                        layout: Some(&render_pipeline_layout),
+                        vertex: wgpu::VertexState {
+                                module: &shader,
+                                entry_point: Some("vs_tiles"),
+                                buffers: &[
+                                        crate::model::ModelVertex::desc(),
+                                        crate::renderer::tilemap::TileInstance::desc(),
+                                ],
+                                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        },
+                        fragment: Some(wgpu::FragmentState {
+                                module: &shader,
+                                entry_point: Some("fs_tiles"),
+                                targets: &[Some(wgpu::ColorTargetState {
+                                        format: config.format,
+                                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                                        write_mask: wgpu::ColorWrites::ALL,
+                                })],
+                                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        }),
+                        primitive: wgpu::PrimitiveState {
+                                topology: wgpu::PrimitiveTopology::TriangleList,
+                                strip_index_format: None,
+                                front_face: wgpu::FrontFace::Ccw,
+                                cull_mode: Some(wgpu::Face::Back),
+                                polygon_mode: wgpu::PolygonMode::Fill,
+                                conservative: false,
+                                unclipped_depth: false,
+                        },
+                        // Drawn after the depth pre-pass, not in it.
+                        depth_stencil: Some(self.geometry_depth_state(false)),
+                        multisample: wgpu::MultisampleState::default(),
+                        multiview: None,
+                        cache: self.cache.as_ref(),
+                });
+
+                self.render_pipelines.insert(PipelineKind::Tiles, pipeline);
+        }
+
+        /// The members of the crowds, skinned models with an instance buffer
+        /// of placements and animation times, see
+        /// [`CrowdPass`](crate::renderer::crowd::CrowdPass).
+        pub fn build_crowd_pipeline(
+                &mut self,
+                device: &wgpu::Device,
+                config: &wgpu::SurfaceConfiguration,
+                bind_groups: &[&wgpu::BindGroupLayout],
+        )
+        {
+                let source = self
+                        .shader_source("crowd.wgsl", &ShaderDefines::new().with("SKINNED"))
+                        .expect("Crowd shader doesn't preprocess");
+
+                let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                        label: Some("Crowd Shader"),
+                        source: wgpu::ShaderSource::Wgsl(source.into()),
+                });
+
+                let render_pipeline_layout =
+                        device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                                label: Some("Crowd Pipeline Layout"),
+                                bind_group_layouts: bind_groups,
+                                push_constant_ranges: &[],
+                        });
+
+                let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                        label: Some("Crowd Pipeline"),
+                        layout: Some(&render_pipeline_layout),
+                        vertex: wgpu::VertexState {
+                                module: &shader,
+                                entry_point: Some("vs_crowd"),
+                                buffers: &[
+                                        crate::model::ModelVertex::desc(),
+                                        crate::geometry::skin::SkinVertex::desc(),
+                                        crate::renderer::crowd::CrowdInstanceLayout::desc(),
+                                ],
+                                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        },
+                        fragment: Some(wgpu::FragmentState {
+                                module: &shader,
+                                entry_point: Some("fs_crowd"),
+                                targets: &[Some(wgpu::ColorTargetState {
+                                        format: config.format,
+                                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                                        write_mask: wgpu::ColorWrites::ALL,
+                                })],
+                                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        }),
+                        primitive: wgpu::PrimitiveState {
+                                topology: wgpu::PrimitiveTopology::TriangleList,
+                                strip_index_format: None,
+                                front_face: wgpu::FrontFace::Ccw,
+                                cull_mode: Some(wgpu::Face::Back),
+                                polygon_mode: wgpu::PolygonMode::Fill,
+                                conservative: false,
+                                unclipped_depth: false,
+                        },
+                        // Drawn after the depth pre-pass, not in it.
+                        depth_stencil: Some(self.geometry_depth_state(false)),
+                        multisample: wgpu::MultisampleState::default(),
+                        multiview: None,
+                        cache: self.cache.as_ref(),
+                });
+
+                self.render_pipelines.insert(PipelineKind::Crowd, pipeline);
+        }
+
+        /// Lines with a width, see
+        /// [`PolylinePass`](crate::renderer::polyline::PolylinePass).
+        pub fn build_polyline_pipeline(
+                &mut self,
+                device: &wgpu::Device,
+                config: &wgpu::SurfaceConfiguration,
+                bind_groups: &[&wgpu::BindGroupLayout],
+        )
+        {
+                let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                        label: Some("Polyline Shader"),
+                        source: wgpu::ShaderSource::Wgsl(include_str!("polyline.wgsl").into()),
+                });
+
+                let render_pipeline_layout =
+                        device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                                label: Some("Polyline Pipeline Layout"),
+                                bind_group_layouts: bind_groups,
+                                push_constant_ranges: &[],
+                        });
+
+                let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                        label: Some("Polyline Pipeline"),
+                        layout: Some(&render_pipeline_layout),
+                        vertex: wgpu::VertexState {
+                                module: &shader,
+                                entry_point: Some("vs_main"),
+                                buffers: &[crate::renderer::polyline::PolylineVertex::desc()],
+                                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        },
+                        fragment: Some(wgpu::FragmentState {
+                                module: &shader,
+                                entry_point: Some("fs_main"),
+                                targets: &[Some(wgpu::ColorTargetState {
+                                        format: config.format,
+                                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                                        write_mask: wgpu::ColorWrites::ALL,
+                                })],
+                                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        }),
+                        primitive: wgpu::PrimitiveState {
+                                topology: wgpu::PrimitiveTopology::TriangleList,
+                                strip_index_format: None,
+                                front_face: wgpu::FrontFace::Ccw,
+                                // Quads face either way depending on the turn.
+                                cull_mode: None,
+                                polygon_mode: wgpu::PolygonMode::Fill,
+                                conservative: false,
+                                unclipped_depth: false,
+                        },
+                        depth_stencil: Some(wgpu::DepthStencilState {
+                                format: crate::texture::Texture::DEPTH_FORMAT,
+                                depth_write_enabled: false,
+                                depth_compare: self.depth_compare(wgpu::CompareFunction::LessEqual),
+                                stencil: wgpu::StencilState::default(),
+                                bias: wgpu::DepthBiasState::default(),
+                        }),
+                        multisample: wgpu::MultisampleState::default(),
+                        multiview: None,
+                        cache: self.cache.as_ref(),
+                });
+
+                self.render_pipelines
+                        .insert(PipelineKind::Polyline, pipeline);
+        }
+
+        /// Light shafts and lens flare added on top of the scene, see
+        /// [`LightShaftPass`](crate::renderer::light_shafts::LightShaftPass).
+        pub fn build_light_shafts_pipeline(
+                &mut self,
+                device: &wgpu::Device,
+                config: &wgpu::SurfaceConfiguration,
+                bind_groups: &[&wgpu::BindGroupLayout],
+        )
+        {
+                let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                        label: Some("Light Shafts Shader"),
+                        source: wgpu::ShaderSource::Wgsl(include_str!("light_shafts.wgsl").into()),
+                });
+
+                let render_pipeline_layout =
+                        device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                                label: Some("Light Shafts Pipeline Layout"),
+                                bind_group_layouts: bind_groups,
+                                push_constant_ranges: &[],
+                        });
+
+                // Adds the light to what's there.
+                let additive = wgpu::BlendComponent {
+                        src_factor: wgpu::BlendFactor::One,
+                        dst_factor: wgpu::BlendFactor::One,
+                        operation: wgpu::BlendOperation::Add,
+                };
+
+                let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                        label: Some("Light Shafts Pipeline"),
+                        layout: Some(&render_pipeline_layout),
+                        vertex: wgpu::VertexState {
+                                module: &shader,
+                                entry_point: Some("vs_main"),
+                                buffers: &[],
+                                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        },
+                        fragment: Some(wgpu::FragmentState {
+                                module: &shader,
+                                entry_point: Some("fs_main"),
+                                targets: &[Some(wgpu::ColorTargetState {
+                                        format: config.format,
+                                        blend: Some(wgpu::BlendState {
+                                                color: additive,
+                                                alpha: wgpu::BlendComponent::OVER,
+                                        }),
+                                        write_mask: wgpu::ColorWrites::ALL,
+                                })],
+                                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        }),
+                        primitive: wgpu::PrimitiveState::default(),
+                        // Reads the depth texture instead.
+                        depth_stencil: None,
+                        multisample: wgpu::MultisampleState::default(),
+                        multiview: None,
+                        cache: self.cache.as_ref(),
+                });
+
+                self.render_pipelines
+                        .insert(PipelineKind::LightShafts, pipeline);
+        }
+
+        /// Depth of the models only, see
+        /// [`DepthPrePass`](crate::renderer::graph::DepthPrePass). Takes the
+        /// geometry pipeline's bind groups.
+        pub fn build_depth_prepass_pipeline(
+                &mut self,
+                device: &wgpu::Device,
+                bind_groups: &[&wgpu::BindGroupLayout],
+        )
+        {
+                let pipeline =
+                        self.create_depth_prepass_pipeline(device, bind_groups, &ShaderDefines::new());
+
+                self.render_pipelines
+                        .insert(PipelineKind::DepthPrepass, pipeline);
+        }
+
+        /// Depth of the models for the permutation `defines`.
+        pub fn create_depth_prepass_pipeline(
+                &self,
+                device: &wgpu::Device,
+                bind_groups: &[&wgpu::BindGroupLayout],
+                defines: &ShaderDefines,
+        ) -> wgpu::RenderPipeline
+        {
+                let source = self
+                        .shader_source("shader.wgsl", defines)
+                        .expect("Model shader doesn't preprocess");
+
+                let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                        label: Some("Depth Pre-Pass Shader"),
+                        source: wgpu::ShaderSource::Wgsl(source.into()),
+                });
+
+                let render_pipeline_layout =
+                        device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                                label: Some("Depth Pre-Pass Pipeline Layout"),
+                                bind_group_layouts: bind_groups,
+                                push_constant_ranges: &[],
+                        });
+
+                device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                        label: Some("Depth Pre-Pass Pipeline"),
+                        layout: Some(&render_pipeline_layout),
+                        vertex: wgpu::VertexState {
+                                module: &shader,
+                                entry_point: Some("vs_main"),
+                                buffers: &[crate::model::ModelVertex::desc()],
+                                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        },
+                        fragment: None,
+                        primitive: wgpu::PrimitiveState {
+                                topology: wgpu::PrimitiveTopology::TriangleList,
+                                strip_index_format: None,
+                                front_face: wgpu::FrontFace::Ccw,
+                                cull_mode: Some(wgpu::Face::Back),
+                                polygon_mode: wgpu::PolygonMode::Fill,
+                                conservative: false,
+                                unclipped_depth: false,
+                        },
+                        depth_stencil: Some(self.geometry_depth_state(false)),
+                        multisample: wgpu::MultisampleState::default(),
+                        multiview: None,
+                        cache: self.cache.as_ref(),
+                })
+        }
+
+        /// Models drawn again on top of themselves, the rim added in the
+        /// blend constant's color, see
+        /// [`HighlightPass`](crate::renderer::highlight::HighlightPass).
+        /// Takes the geometry pipeline's bind groups.
+        pub fn build_highlight_pipeline(
+                &mut self,
+                device: &wgpu::Device,
+                config: &wgpu::SurfaceConfiguration,
+                bind_groups: &[&wgpu::BindGroupLayout],
+        )
+        {
+                let source = self
+                        .shader_source("shader.wgsl", &ShaderDefines::new())
+                        .expect("Model shader doesn't preprocess");
+
+                let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                        label: Some("Highlight Shader"),
+                        source: wgpu::ShaderSource::Wgsl(source.into()),
+                });
+
+                let render_pipeline_layout =
+                        device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                                label: Some("Highlight Pipeline Layout"),
+                                bind_group_layouts: bind_groups,
+                                push_constant_ranges: &[],
+                        });
+
+                let additive = wgpu::BlendComponent {
+                        src_factor: wgpu::BlendFactor::Constant,
+                        dst_factor: wgpu::BlendFactor::One,
+                        operation: wgpu::BlendOperation::Add,
+                };
+
+                let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                        label: Some("Highlight Pipeline"),
+                        layout: Some(&render_pipeline_layout),
+                        vertex: wgpu::VertexState {
+                                module: &shader,
+                                entry_point: Some("vs_main"),
+                                buffers: &[crate::model::ModelVertex::desc()],
+                                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        },
+                        fragment: Some(wgpu::FragmentState {
+                                module: &shader,
+                                entry_point: Some("fs_highlight"),
+                                targets: &[Some(wgpu::ColorTargetState {
+                                        format: config.format,
+                                        blend: Some(wgpu::BlendState {
+                                                color: additive,
+                                                alpha: wgpu::BlendComponent::OVER,
+                                        }),
+                                        write_mask: wgpu::ColorWrites::COLOR,
+                                })],
+                                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        }),
+                        primitive: wgpu::PrimitiveState {
+                                topology: wgpu::PrimitiveTopology::TriangleList,
+                                strip_index_format: None,
+                                front_face: wgpu::FrontFace::Ccw,
+                                cull_mode: Some(wgpu::Face::Back),
+                                polygon_mode: wgpu::PolygonMode::Fill,
+                                conservative: false,
+                                unclipped_depth: false,
+                        },
+                        // Only where the model itself is in front.
+                        depth_stencil: Some(wgpu::DepthStencilState {
+                                format: crate::texture::Texture::DEPTH_FORMAT,
+                                depth_write_enabled: false,
+                                depth_compare: self.depth_compare(wgpu::CompareFunction::LessEqual),
+                                stencil: wgpu::StencilState::default(),
+                                bias: wgpu::DepthBiasState::default(),
+                        }),
+                        multisample: wgpu::MultisampleState::default(),
+                        multiview: None,
+                        cache: self.cache.as_ref(),
+                });
+
+                self.render_pipelines.insert(PipelineKind::Highlight, pipeline);
+        }
+
+        /// Fills what it draws with the blend constant, the
+        /// [`BackgroundPass`](crate::renderer::graph::BackgroundPass) color
+        /// inside the viewport.
+        pub fn build_fill_pipeline(
+                &mut self,
+                device: &wgpu::Device,
+                config: &wgpu::SurfaceConfiguration,
+        )
+        {
+                let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                        label: Some("Fill Shader"),
+                        source: wgpu::ShaderSource::Wgsl(include_str!("fill.wgsl").into()),
+                });
+
+                let render_pipeline_layout =
+                        device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                                label: Some("Fill Pipeline Layout"),
+                                bind_group_layouts: &[],
+                                push_constant_ranges: &[],
+                        });
+
+                // The shader writes 1, scaled to the constant.
+                let constant = wgpu::BlendComponent {
+                        src_factor: wgpu::BlendFactor::Constant,
+                        dst_factor: wgpu::BlendFactor::Zero,
+                        operation: wgpu::BlendOperation::Add,
+                };
+
+                let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                        label: Some("Fill Pipeline"),
+                        layout: Some(&render_pipeline_layout),
+                        vertex: wgpu::VertexState {
+                                module: &shader,
+                                entry_point: Some("vs_main"),
+                                buffers: &[],
+                                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        },
+                        fragment: Some(wgpu::FragmentState {
+                                module: &shader,
+                                entry_point: Some("fs_main"),
+                                targets: &[Some(wgpu::ColorTargetState {
+                                        format: config.format,
+                                        blend: Some(wgpu::BlendState {
+                                                color: constant,
+                                                alpha: constant,
+                                        }),
+                                        write_mask: wgpu::ColorWrites::ALL,
+                                })],
+                                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        }),
+                        primitive: wgpu::PrimitiveState::default(),
+                        depth_stencil: None,
+                        multisample: wgpu::MultisampleState::default(),
+                        multiview: None,
+                        cache: self.cache.as_ref(),
+                });
+
+                self.render_pipelines.insert(PipelineKind::Fill, pipeline);
         }
 }
@@ -0,0 +1,644 @@
+//! Lines with a width, for trajectories, routes and vector overlays.
+//!
+//! Unlike the [debug lines](crate::renderer::debug_draw), which are one
+//! pixel wide and queued every frame, polylines are kept until they're
+//! removed and rebuilt only when one changes:
+//!
+//! ```ignore
+//! engine.add_polyline(
+//!         "route",
+//!         Polyline {
+//!                 width: LineWidth::World(0.5),
+//!                 join: LineJoin::Round,
+//!                 dash: Some(Dash { length: 2.0, gap: 1.0 }),
+//!                 ..Polyline::new(waypoints)
+//!         },
+//! );
+//!
+//! // A trajectory growing every frame.
+//! if let Some(trail) = engine.polyline_mut("trail")
+//! {
+//!         trail.points.push(position);
+//! }
+//! ```
+//!
+//! Every segment is a quad facing the camera, expanded in `polyline.wgsl`
+//! so the width holds on screen whatever the distance, or shrinks with it
+//! for [`LineWidth::World`]. The corners between segments are filled by
+//! the [`LineJoin`], the ends are cut square at the points.
+
+#[cfg(feature = "runtime")]
+use crate::engine::Engine;
+use crate::model::{Model, Vertex};
+use crate::renderer::graph::{Attachment, AttachmentLoad, DEPTH, RenderPass, SURFACE};
+use crate::renderer::pipeline::{PipelineKind, PipelineManager};
+use crate::texture::Texture;
+use cgmath::{MetricSpace, Point3};
+use std::any::Any;
+use std::collections::{BTreeMap, HashMap};
+use wgpu::util::DeviceExt;
+
+/// `PolylineVertex::kind`, see `polyline.wgsl`.
+const SEGMENT: u32 = 0;
+const JOIN: u32 = 1;
+const ROUND: u32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LineWidth
+{
+        /// The same on screen at any distance.
+        Pixels(f32),
+        /// Thinner further away, like geometry.
+        World(f32),
+}
+
+/// How the corner between two segments is filled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineJoin
+{
+        /// Extends the edges until they meet, see
+        /// [`Polyline::miter_limit`].
+        #[default]
+        Miter,
+        /// Cuts the corner off.
+        Bevel,
+        Round,
+}
+
+/// Dashes along a line, in world units.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Dash
+{
+        pub length: f32,
+        pub gap: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polyline
+{
+        /// World space points, consecutive equal points are skipped.
+        pub points: Vec<Point3<f32>>,
+        /// Linear RGBA.
+        pub color: [f32; 4],
+        pub width: LineWidth,
+        pub join: LineJoin,
+        /// Miters reaching further from their point than this many widths
+        /// are beveled, sharp corners would spike out otherwise.
+        pub miter_limit: f32,
+        pub dash: Option<Dash>,
+        /// Connects the last point back to the first.
+        pub closed: bool,
+        /// Drawn over the scene instead of hidden behind it.
+        pub overlay: bool,
+}
+
+impl Polyline
+{
+        /// A white line 2 pixels wide through `points`.
+        pub fn new(points: Vec<Point3<f32>>) -> Self
+        {
+                Self {
+                        points,
+                        color: [1.0, 1.0, 1.0, 1.0],
+                        width: LineWidth::Pixels(2.0),
+                        join: LineJoin::Miter,
+                        miter_limit: 2.0,
+                        dash: None,
+                        closed: false,
+                        overlay: false,
+                }
+        }
+
+        /// Appends the triangles of the line to `vertices` and `indices`.
+        fn build(
+                &self,
+                vertices: &mut Vec<PolylineVertex>,
+                indices: &mut Vec<u32>,
+        )
+        {
+                let mut points: Vec<Point3<f32>> = Vec::with_capacity(self.points.len());
+
+                for &point in &self.points
+                {
+                        if points
+                                .last()
+                                .is_none_or(|last| last.distance2(point) > f32::EPSILON)
+                        {
+                                points.push(point);
+                        }
+                }
+
+                let closed = self.closed && points.len() > 2;
+
+                if closed && points[0].distance2(points[points.len() - 1]) <= f32::EPSILON
+                {
+                        points.pop();
+                }
+
+                if points.len() < 2
+                {
+                        return;
+                }
+
+                let count = points.len();
+                let segments = if closed { count } else { count - 1 };
+
+                let mut distances = Vec::with_capacity(count + 1);
+                let mut distance = 0.0;
+
+                for i in 0..=segments
+                {
+                        distances.push(distance);
+
+                        if i < segments
+                        {
+                                distance += points[i].distance(points[(i + 1) % count]);
+                        }
+                }
+
+                let template = PolylineVertex {
+                        position: [0.0; 3],
+                        distance: 0.0,
+                        previous: [0.0; 3],
+                        width: match self.width
+                        {
+                                LineWidth::Pixels(width) => width.max(0.0),
+                                LineWidth::World(width) => -width.abs(),
+                        },
+                        next: [0.0; 3],
+                        miter_limit: match self.join
+                        {
+                                LineJoin::Miter => self.miter_limit.max(0.0),
+                                _ => -1.0,
+                        },
+                        corner: [0.0; 2],
+                        dash: self
+                                .dash
+                                .map_or([0.0; 2], |d| [d.length.max(0.0), d.gap.max(0.0)]),
+                        color: self.color,
+                        kind: SEGMENT,
+                        overlay: self.overlay as u32,
+                };
+
+                let mut quad = |corners: [PolylineVertex; 4], triangles: [u32; 6]| {
+                        let base = vertices.len() as u32;
+
+                        vertices.extend(corners);
+                        indices.extend(triangles.map(|i| base + i));
+                };
+
+                for i in 0..segments
+                {
+                        let a = points[i];
+                        let b = points[(i + 1) % count];
+
+                        let corner = |end: bool, side: f32| PolylineVertex {
+                                position: if end { b.into() } else { a.into() },
+                                distance: distances[i + end as usize],
+                                previous: a.into(),
+                                next: b.into(),
+                                corner: [end as u32 as f32, side],
+                                ..template
+                        };
+
+                        quad(
+                                [
+                                        corner(false, -1.0),
+                                        corner(false, 1.0),
+                                        corner(true, -1.0),
+                                        corner(true, 1.0),
+                                ],
+                                [0, 2, 1, 1, 2, 3],
+                        );
+                }
+
+                let joins = if closed { 0..count } else { 1..count - 1 };
+
+                for i in joins
+                {
+                        let vertex = |corner: [f32; 2], kind: u32| PolylineVertex {
+                                position: points[i].into(),
+                                distance: distances[i],
+                                previous: points[(i + count - 1) % count].into(),
+                                next: points[(i + 1) % count].into(),
+                                corner,
+                                kind,
+                                ..template
+                        };
+
+                        match self.join
+                        {
+                                LineJoin::Round => quad(
+                                        [
+                                                vertex([-1.0, -1.0], ROUND),
+                                                vertex([1.0, -1.0], ROUND),
+                                                vertex([-1.0, 1.0], ROUND),
+                                                vertex([1.0, 1.0], ROUND),
+                                        ],
+                                        [0, 1, 2, 2, 1, 3],
+                                ),
+                                // The center, both segment ends and the tip.
+                                LineJoin::Miter | LineJoin::Bevel => quad(
+                                        [0.0, 1.0, 2.0, 3.0].map(|c| vertex([c, 0.0], JOIN)),
+                                        [0, 1, 2, 0, 2, 3],
+                                ),
+                        }
+                }
+        }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct PolylineVertex
+{
+        position: [f32; 3],
+        /// Along the line to `position`, for the dashes.
+        distance: f32,
+        previous: [f32; 3],
+        /// Pixels, world units when negative.
+        width: f32,
+        next: [f32; 3],
+        /// Bevel when negative.
+        miter_limit: f32,
+        corner: [f32; 2],
+        dash: [f32; 2],
+        color: [f32; 4],
+        kind: u32,
+        overlay: u32,
+}
+
+impl PolylineVertex
+{
+        const ATTRIBUTES: [wgpu::VertexAttribute; 11] = wgpu::vertex_attr_array![
+                0 => Float32x3,
+                1 => Float32,
+                2 => Float32x3,
+                3 => Float32,
+                4 => Float32x3,
+                5 => Float32,
+                6 => Float32x2,
+                7 => Float32x2,
+                8 => Float32x4,
+                9 => Uint32,
+                10 => Uint32,
+        ];
+}
+
+impl Vertex for PolylineVertex
+{
+        fn desc() -> wgpu::VertexBufferLayout<'static>
+        {
+                wgpu::VertexBufferLayout {
+                        array_stride: size_of::<PolylineVertex>() as wgpu::BufferAddress,
+                        step_mode: wgpu::VertexStepMode::Vertex,
+                        attributes: &Self::ATTRIBUTES,
+                }
+        }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct PolylineUniform
+{
+        viewport: [f32; 4],
+        reversed_z: u32,
+        _padding: [u32; 3],
+}
+
+pub fn create_polyline_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout
+{
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Uniform,
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                        },
+                        count: None,
+                }],
+                label: Some("polyline_bind_group_layout"),
+        })
+}
+
+/// The polylines by name, owned by the [`Engine`].
+#[derive(Debug, Default)]
+pub struct Polylines
+{
+        polylines: BTreeMap<String, Polyline>,
+        /// Changed with every edit, the [`PolylinePass`] rebuilds its mesh
+        /// when it differs.
+        revision: u64,
+}
+
+impl Polylines
+{
+        pub fn new() -> Self
+        {
+                Self::default()
+        }
+
+        /// Adds `polyline`, replacing the one named `name`.
+        pub fn insert(
+                &mut self,
+                name: &str,
+                polyline: Polyline,
+        )
+        {
+                self.polylines.insert(name.to_string(), polyline);
+                self.revision += 1;
+        }
+
+        pub fn remove(
+                &mut self,
+                name: &str,
+        ) -> Option<Polyline>
+        {
+                self.revision += 1;
+                self.polylines.remove(name)
+        }
+
+        pub fn get(
+                &self,
+                name: &str,
+        ) -> Option<&Polyline>
+        {
+                self.polylines.get(name)
+        }
+
+        /// The polyline `name`, rebuilt on the next frame.
+        pub fn get_mut(
+                &mut self,
+                name: &str,
+        ) -> Option<&mut Polyline>
+        {
+                self.revision += 1;
+                self.polylines.get_mut(name)
+        }
+
+        pub fn clear(&mut self)
+        {
+                self.polylines.clear();
+                self.revision += 1;
+        }
+
+        pub fn revision(&self) -> u64
+        {
+                self.revision
+        }
+
+        fn build(&self) -> (Vec<PolylineVertex>, Vec<u32>)
+        {
+                let mut vertices = Vec::new();
+                let mut indices = Vec::new();
+
+                for polyline in self.polylines.values()
+                {
+                        polyline.build(&mut vertices, &mut indices);
+                }
+
+                (vertices, indices)
+        }
+}
+
+#[derive(Debug)]
+struct PolylineMesh
+{
+        vertex_buffer: wgpu::Buffer,
+        index_buffer: wgpu::Buffer,
+        count: u32,
+}
+
+/// Draws the [`Polylines`] over the scene.
+#[derive(Debug)]
+pub struct PolylinePass
+{
+        pub name: String,
+        pub enabled: bool,
+        /// Revision of the polylines `mesh` was built from.
+        revision: Option<u64>,
+        /// Triangles waiting for their buffers.
+        pending: Option<(Vec<PolylineVertex>, Vec<u32>)>,
+        mesh: Option<PolylineMesh>,
+        /// Holds the [`PolylineUniform`], written every frame.
+        uniform: wgpu::Buffer,
+        bind_group: wgpu::BindGroup,
+}
+
+impl PolylinePass
+{
+        pub fn new(device: &wgpu::Device) -> Self
+        {
+                let uniform = device.create_buffer(&wgpu::BufferDescriptor {
+                        label: Some("Polyline Buffer"),
+                        size: std::mem::size_of::<PolylineUniform>() as u64,
+                        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                        mapped_at_creation: false,
+                });
+
+                let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                        layout: &create_polyline_bind_group_layout(device),
+                        entries: &[wgpu::BindGroupEntry {
+                                binding: 0,
+                                resource: uniform.as_entire_binding(),
+                        }],
+                        label: Some("polyline_bind_group"),
+                });
+
+                Self {
+                        name: "polyline_pass".to_string(),
+                        enabled: true,
+                        revision: None,
+                        pending: None,
+                        mesh: None,
+                        uniform,
+                        bind_group,
+                }
+        }
+
+        /// Rebuilds the triangles when `polylines` changed since the last
+        /// call.
+        pub fn sync(
+                &mut self,
+                polylines: &Polylines,
+        )
+        {
+                if self.revision != Some(polylines.revision())
+                {
+                        self.revision = Some(polylines.revision());
+                        self.pending = Some(polylines.build());
+                }
+        }
+}
+
+impl RenderPass for PolylinePass
+{
+        fn name(&self) -> &str
+        {
+                self.name.as_str()
+        }
+
+        fn as_any(&self) -> &dyn Any
+        {
+                self
+        }
+
+        fn as_any_mut(&mut self) -> &mut dyn Any
+        {
+                self
+        }
+
+        #[cfg(feature = "ui")]
+        fn ui(
+                &mut self,
+                ui: &mut egui::Ui,
+        )
+        {
+                let triangles = self.mesh.as_ref().map_or(0, |m| m.count / 3);
+
+                egui::CollapsingHeader::new(&self.name)
+                        .default_open(true)
+                        .show(ui, |ui| {
+                                ui.label(format!("Triangles: {}", triangles));
+                        });
+        }
+
+        fn enabled(&mut self) -> bool
+        {
+                self.enabled
+        }
+
+        fn attachments(&self) -> Vec<Attachment>
+        {
+                vec![
+                        Attachment::new(SURFACE, AttachmentLoad::Load, true),
+                        Attachment::new(DEPTH, AttachmentLoad::Load, true),
+                ]
+        }
+
+        fn set_enabled(
+                &mut self,
+                value: bool,
+        )
+        {
+                self.enabled = value;
+        }
+
+        fn record(
+                &mut self,
+                view: &wgpu::TextureView,
+                encoder: &mut wgpu::CommandEncoder,
+                camera: &wgpu::BindGroup,
+                pipeline_manager: &PipelineManager,
+                depth_texture: &Texture,
+                #[allow(unused_variables)] models: Option<&HashMap<String, Model>>,
+                device: &wgpu::Device,
+                queue: &wgpu::Queue,
+        )
+        {
+                if let Some((vertices, indices)) = self.pending.take()
+                {
+                        self.mesh = (!indices.is_empty()).then(|| PolylineMesh {
+                                vertex_buffer: device.create_buffer_init(
+                                        &wgpu::util::BufferInitDescriptor {
+                                                label: Some("Polyline Vertex Buffer"),
+                                                contents: bytemuck::cast_slice(&vertices),
+                                                usage: wgpu::BufferUsages::VERTEX,
+                                        },
+                                ),
+                                index_buffer: device.create_buffer_init(
+                                        &wgpu::util::BufferInitDescriptor {
+                                                label: Some("Polyline Index Buffer"),
+                                                contents: bytemuck::cast_slice(&indices),
+                                                usage: wgpu::BufferUsages::INDEX,
+                                        },
+                                ),
+                                count: indices.len() as u32,
+                        });
+                }
+
+                let Some(mesh) = self.mesh.as_ref()
+                else
+                {
+                        return;
+                };
+
+                let size = depth_texture.texture.size();
+
+                queue.write_buffer(
+                        &self.uniform,
+                        0,
+                        bytemuck::cast_slice(&[PolylineUniform {
+                                viewport: pipeline_manager.viewport_rect(size.width, size.height),
+                                reversed_z: pipeline_manager.reversed_z as u32,
+                                _padding: [0; 3],
+                        }]),
+                );
+
+                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        label: Some(&self.name),
+                        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                                view,
+                                resolve_target: None,
+                                ops: wgpu::Operations {
+                                        load: wgpu::LoadOp::Load,
+                                        store: wgpu::StoreOp::Store,
+                                },
+                        })],
+                        depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                                view: &depth_texture.view,
+                                depth_ops: Some(wgpu::Operations {
+                                        load: wgpu::LoadOp::Load,
+                                        store: wgpu::StoreOp::Store,
+                                }),
+                                stencil_ops: None,
+                        }),
+                        occlusion_query_set: None,
+                        timestamp_writes: None,
+                });
+
+                pipeline_manager.apply_viewport(&mut render_pass);
+                render_pass.set_pipeline(pipeline_manager.get(PipelineKind::Polyline));
+
+                render_pass.set_bind_group(0, camera, &[]);
+                render_pass.set_bind_group(1, &self.bind_group, &[]);
+                render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+                render_pass
+                        .set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+
+                render_pass.draw_indexed(0..mesh.count, 0, 0..1);
+        }
+}
+
+#[cfg(feature = "runtime")]
+impl Engine
+{
+        /// Draws `polyline` until it's removed, replacing the polyline
+        /// `name`.
+        pub fn add_polyline(
+                &mut self,
+                name: &str,
+                polyline: Polyline,
+        )
+        {
+                self.polylines.insert(name, polyline);
+        }
+
+        pub fn remove_polyline(
+                &mut self,
+                name: &str,
+        ) -> Option<Polyline>
+        {
+                self.polylines.remove(name)
+        }
+
+        /// The polyline `name` to change, e.g. to add points to.
+        pub fn polyline_mut(
+                &mut self,
+                name: &str,
+        ) -> Option<&mut Polyline>
+        {
+                self.polylines.get_mut(name)
+        }
+}
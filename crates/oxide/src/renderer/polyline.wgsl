@@ -0,0 +1,200 @@
+// Polylines expanded into screen space quads. See `polyline.rs`.
+
+struct CameraUniform {
+    view_pos: vec4<f32>,
+    view_proj: mat4x4<f32>,
+};
+
+struct Polylines {
+    // Where the scene is on the screen in pixels, offset in `xy` and size
+    // in `zw`.
+    viewport: vec4<f32>,
+    reversed_z: u32,
+    _padding: vec3<u32>,
+};
+
+// `PolylineVertex::kind`.
+const SEGMENT: u32 = 0u;
+const JOIN: u32 = 1u;
+const ROUND: u32 = 2u;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) distance: f32,
+    @location(2) previous: vec3<f32>,
+    // Pixels, world units when negative.
+    @location(3) width: f32,
+    @location(4) next: vec3<f32>,
+    // Miter length limit in widths, bevel below 0.
+    @location(5) miter_limit: f32,
+    @location(6) corner: vec2<f32>,
+    // Length of the dashes and the gaps between them.
+    @location(7) dash: vec2<f32>,
+    @location(8) color: vec4<f32>,
+    @location(9) kind: u32,
+    @location(10) overlay: u32,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+    @location(1) corner: vec2<f32>,
+    @location(2) distance: f32,
+    @location(3) dash: vec2<f32>,
+    @location(4) @interpolate(flat) kind: u32,
+};
+
+@group(0) @binding(0) var<uniform> camera: CameraUniform;
+@group(1) @binding(0) var<uniform> polylines: Polylines;
+
+// Nearest `w` a point is kept at, points behind the camera are moved up to
+// it along their segment.
+const NEAR_W: f32 = 0.0001;
+
+fn clip(position: vec3<f32>) -> vec4<f32> {
+    return camera.view_proj * vec4<f32>(position, 1.0);
+}
+
+// `a` moved towards `b` until it's in front of the camera.
+fn clip_near(a: vec4<f32>, b: vec4<f32>) -> vec4<f32> {
+    if a.w >= NEAR_W || b.w < NEAR_W {
+        return a;
+    }
+
+    return mix(a, b, (NEAR_W - a.w) / (b.w - a.w));
+}
+
+// Pixels from the center of the viewport, y up.
+fn screen(c: vec4<f32>) -> vec2<f32> {
+    return c.xy / max(c.w, NEAR_W) * 0.5 * polylines.viewport.zw;
+}
+
+// `c` moved by `offset` pixels.
+fn offset_clip(c: vec4<f32>, offset: vec2<f32>) -> vec4<f32> {
+    return c + vec4<f32>(offset / (0.5 * polylines.viewport.zw) * c.w, 0.0, 0.0);
+}
+
+fn direction(start: vec2<f32>, end: vec2<f32>) -> vec2<f32> {
+    let d = end - start;
+
+    if dot(d, d) < 1e-8 {
+        return vec2<f32>(1.0, 0.0);
+    }
+
+    return normalize(d);
+}
+
+fn perpendicular(d: vec2<f32>) -> vec2<f32> {
+    return vec2<f32>(-d.y, d.x);
+}
+
+// Half the width in pixels. World widths are measured across the view ray
+// at `anchor`.
+fn half_width(in: VertexInput, anchor: vec4<f32>) -> f32 {
+    if in.width >= 0.0 {
+        return in.width * 0.5;
+    }
+
+    let along = in.next - in.previous;
+    var across = cross(along, camera.view_pos.xyz - in.position);
+
+    if dot(across, across) < 1e-12 {
+        across = cross(along, vec3<f32>(0.0, 1.0, 0.0));
+    }
+
+    if dot(across, across) < 1e-12 {
+        return 0.0;
+    }
+
+    let side = clip(in.position + normalize(across) * (-in.width * 0.5));
+
+    return length(screen(side) - screen(anchor));
+}
+
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+
+    let anchor = clip(in.position);
+    let half = half_width(in, anchor);
+
+    var position = anchor;
+
+    if in.kind == SEGMENT {
+        // `corner.x` is 0 at `previous` and 1 at `next`, `corner.y` the side.
+        let a = clip_near(clip(in.previous), clip(in.next));
+        let b = clip_near(clip(in.next), clip(in.previous));
+        let normal = perpendicular(direction(screen(a), screen(b)));
+
+        position = offset_clip(select(a, b, in.corner.x > 0.5), normal * in.corner.y * half);
+    } else if in.kind == JOIN {
+        // The outer side of the turn, `corner.x` is the center, the end of
+        // the previous segment, the tip and the start of the next one.
+        let p = screen(anchor);
+        let d0 = direction(screen(clip(in.previous)), p);
+        let d1 = direction(p, screen(clip(in.next)));
+        let side = select(1.0, -1.0, d0.x * d1.y - d0.y * d1.x > 0.0);
+
+        let n0 = perpendicular(d0) * side * half;
+        let n1 = perpendicular(d1) * side * half;
+
+        var offset = vec2<f32>(0.0);
+
+        switch u32(in.corner.x) {
+            case 1u: {
+                offset = n0;
+            }
+            case 2u: {
+                // Bevel where the miter would be too long.
+                offset = (n0 + n1) * 0.5;
+
+                let miter = n0 + n1;
+
+                if in.miter_limit >= 0.0 && dot(miter, miter) > 1e-8 {
+                    let tip = normalize(miter) * half * half / max(dot(normalize(miter), n0), 1e-4);
+
+                    if length(tip) <= in.miter_limit * half * 2.0 {
+                        offset = tip;
+                    }
+                }
+            }
+            case 3u: {
+                offset = n1;
+            }
+            default: {}
+        }
+
+        position = offset_clip(anchor, offset);
+    } else {
+        position = offset_clip(anchor, in.corner * half);
+    }
+
+    // Over everything drawn before, at the near plane.
+    if in.overlay != 0u {
+        position.z = select(0.0, position.w, polylines.reversed_z != 0u);
+    }
+
+    out.clip_position = position;
+    out.color = in.color;
+    out.corner = in.corner;
+    out.distance = in.distance;
+    out.dash = in.dash;
+    out.kind = in.kind;
+
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    if in.kind == ROUND && dot(in.corner, in.corner) > 1.0 {
+        discard;
+    }
+
+    let period = in.dash.x + in.dash.y;
+
+    if in.dash.y > 0.0 && period > 0.0 && in.distance - floor(in.distance / period) * period > in.dash.x {
+        discard;
+    }
+
+    return in.color;
+}
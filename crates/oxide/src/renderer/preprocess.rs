@@ -0,0 +1,180 @@
+//! A small WGSL preprocessor.
+//!
+//! Shaders are split into files pulled in with `#include "name"`, and
+//! parts of them are compiled only for some permutations:
+//!
+//! ```wgsl
+//! #include "common.wgsl"
+//!
+//! #ifdef HAS_NORMAL_MAP
+//! fn perturb_normal(...) -> vec3<f32> { ... }
+//! #else
+//! ...
+//! #endif
+//! ```
+//!
+//! `#ifndef` is the negation of `#ifdef`, blocks nest. Every file is
+//! included once, later includes of it are skipped. Includes are looked up
+//! in [`PipelineManager::includes`](crate::renderer::pipeline::PipelineManager::includes),
+//! which pipelines are built and cached per [`ShaderDefines`] in.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// The names defined for one permutation of a shader, the key its pipeline
+/// is cached under.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ShaderDefines(BTreeSet<String>);
+
+impl ShaderDefines
+{
+        pub fn new() -> Self
+        {
+                Self::default()
+        }
+
+        pub fn with(
+                mut self,
+                name: &str,
+        ) -> Self
+        {
+                self.insert(name);
+                self
+        }
+
+        pub fn insert(
+                &mut self,
+                name: &str,
+        )
+        {
+                self.0.insert(name.to_string());
+        }
+
+        pub fn contains(
+                &self,
+                name: &str,
+        ) -> bool
+        {
+                self.0.contains(name)
+        }
+
+        pub fn is_empty(&self) -> bool
+        {
+                self.0.is_empty()
+        }
+
+        pub fn iter(&self) -> impl Iterator<Item = &str>
+        {
+                self.0.iter().map(String::as_str)
+        }
+}
+
+/// An open `#ifdef` or `#ifndef`.
+struct Block
+{
+        /// Lines of the enclosing block are emitted.
+        outer: bool,
+        /// The condition held.
+        taken: bool,
+        in_else: bool,
+}
+
+/// `source` with its includes pulled in and the blocks of other
+/// permutations left out.
+pub fn preprocess(
+        source: &str,
+        includes: &HashMap<String, String>,
+        defines: &ShaderDefines,
+) -> Result<String, String>
+{
+        let mut output = String::with_capacity(source.len());
+        let mut included = HashSet::new();
+
+        expand(source, "<source>", includes, defines, &mut included, &mut output)?;
+
+        Ok(output)
+}
+
+fn expand<'a>(
+        source: &'a str,
+        file: &str,
+        includes: &'a HashMap<String, String>,
+        defines: &ShaderDefines,
+        included: &mut HashSet<&'a str>,
+        output: &mut String,
+) -> Result<(), String>
+{
+        let mut blocks: Vec<Block> = Vec::new();
+
+        for (number, line) in source.lines().enumerate()
+        {
+                let error = |message: &str| format!("{}:{}: {}", file, number + 1, message);
+
+                let active = blocks
+                        .last()
+                        .is_none_or(|b| b.outer && (b.taken != b.in_else));
+
+                let directive = line.trim_start();
+
+                if let Some(name) = directive.strip_prefix("#ifdef ")
+                {
+                        blocks.push(Block {
+                                outer: active,
+                                taken: defines.contains(name.trim()),
+                                in_else: false,
+                        });
+                }
+                else if let Some(name) = directive.strip_prefix("#ifndef ")
+                {
+                        blocks.push(Block {
+                                outer: active,
+                                taken: !defines.contains(name.trim()),
+                                in_else: false,
+                        });
+                }
+                else if directive.starts_with("#else")
+                {
+                        match blocks.last_mut()
+                        {
+                                Some(block) if !block.in_else => block.in_else = true,
+                                _ => return Err(error("#else without #ifdef")),
+                        }
+                }
+                else if directive.starts_with("#endif")
+                {
+                        if blocks.pop().is_none()
+                        {
+                                return Err(error("#endif without #ifdef"));
+                        }
+                }
+                else if let Some(name) = directive.strip_prefix("#include ")
+                {
+                        if !active
+                        {
+                                continue;
+                        }
+
+                        let name = name.trim().trim_matches('"');
+
+                        let (name, include) = includes
+                                .get_key_value(name)
+                                .ok_or_else(|| error(&format!("no include \"{}\"", name)))?;
+
+                        if included.insert(name.as_str())
+                        {
+                                expand(include, name, includes, defines, included, output)?;
+                        }
+                }
+                else if active
+                {
+                        output.push_str(line);
+                        output.push('\n');
+                }
+        }
+
+        if !blocks.is_empty()
+        {
+                return Err(format!("{}: #ifdef without #endif", file));
+        }
+
+        Ok(())
+}
@@ -0,0 +1,423 @@
+//! Cubemaps of reflection probes and what samples them, see
+//! [`crate::reflection`].
+//!
+//! A probe's scene is drawn into the six layers of a [`ProbeTexture`], one
+//! camera per face from [`face_view`], and the smaller mip levels are
+//! filtered down from it by [`ProbeMipPass`]. Models inside a probe's
+//! bounds are drawn with the `REFLECTION_PROBE` permutation of the model
+//! shader, which binds the texture and its [`ProbeUniform`] next to the
+//! model transform, see [`create_probe_bind_group_layout`]. Rougher
+//! materials sample blurrier levels.
+//!
+//! The faces are drawn as seen along `z` mirrored and the shader samples
+//! with `z` mirrored too, so the faces come out the way cube lookups expect
+//! them without flipping the winding of the right handed cameras.
+
+use bytemuck::{Pod, Zeroable};
+use cgmath::{Matrix4, Point3, Vector3};
+use wgpu::util::DeviceExt;
+
+/// Direction a face camera looks along and its up, by cube layer: +X, -X,
+/// +Y, -Y, +Z and -Z.
+const FACES: [([f32; 3], [f32; 3]); 6] = [
+        ([1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+        ([-1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+        ([0.0, 1.0, 0.0], [0.0, 0.0, 1.0]),
+        ([0.0, -1.0, 0.0], [0.0, 0.0, -1.0]),
+        ([0.0, 0.0, -1.0], [0.0, 1.0, 0.0]),
+        ([0.0, 0.0, 1.0], [0.0, 1.0, 0.0]),
+];
+
+/// View matrix of the camera drawing cube layer `face` from `position`,
+/// seen with a 90 degree field of view and an aspect ratio of 1.
+pub fn face_view(
+        position: Point3<f32>,
+        face: usize,
+) -> Matrix4<f32>
+{
+        let (forward, up) = FACES[face];
+
+        Matrix4::look_to_rh(position, Vector3::from(forward), Vector3::from(up))
+}
+
+/// Where a probe was captured and the volume its reflections are projected
+/// onto, as the model shader reads it.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Pod, Zeroable)]
+pub struct ProbeUniform
+{
+        /// Capture position, with the intensity in `w`.
+        pub position: [f32; 4],
+        /// Center of the volume, with `w` 0 for a box and 1 for a sphere.
+        pub center: [f32; 4],
+        /// Half the size of the box or the radius of the sphere in `x`, with
+        /// the number of mip levels in `w`.
+        pub extents: [f32; 4],
+}
+
+/// A probe's cubemap and uniform, cheap to clone, shared by the models it
+/// reflects on.
+#[derive(Debug, Clone)]
+pub struct ProbeTexture
+{
+        texture: wgpu::Texture,
+        view: wgpu::TextureView,
+        sampler: wgpu::Sampler,
+        uniform: wgpu::Buffer,
+}
+
+impl ProbeTexture
+{
+        /// A `resolution` sized cubemap of `format` with every mip level.
+        pub fn new(
+                device: &wgpu::Device,
+                format: wgpu::TextureFormat,
+                resolution: u32,
+        ) -> Self
+        {
+                let resolution = resolution.max(1);
+
+                let texture = device.create_texture(&wgpu::TextureDescriptor {
+                        label: Some("Reflection Probe"),
+                        size: wgpu::Extent3d {
+                                width: resolution,
+                                height: resolution,
+                                depth_or_array_layers: 6,
+                        },
+                        mip_level_count: resolution.ilog2() + 1,
+                        sample_count: 1,
+                        dimension: wgpu::TextureDimension::D2,
+                        format,
+                        usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                                | wgpu::TextureUsages::TEXTURE_BINDING,
+                        view_formats: &[],
+                });
+
+                let view = texture.create_view(&wgpu::TextureViewDescriptor {
+                        label: Some("Reflection Probe View"),
+                        dimension: Some(wgpu::TextureViewDimension::Cube),
+                        ..Default::default()
+                });
+
+                let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+                        label: Some("Reflection Probe Sampler"),
+                        mag_filter: wgpu::FilterMode::Linear,
+                        min_filter: wgpu::FilterMode::Linear,
+                        mipmap_filter: wgpu::FilterMode::Linear,
+                        ..Default::default()
+                });
+
+                let uniform = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                        label: Some("Reflection Probe Uniform"),
+                        contents: bytemuck::bytes_of(&ProbeUniform::default()),
+                        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                });
+
+                Self {
+                        texture,
+                        view,
+                        sampler,
+                        uniform,
+                }
+        }
+
+        pub fn resolution(&self) -> u32
+        {
+                self.texture.width()
+        }
+
+        pub fn format(&self) -> wgpu::TextureFormat
+        {
+                self.texture.format()
+        }
+
+        pub fn mip_level_count(&self) -> u32
+        {
+                self.texture.mip_level_count()
+        }
+
+        /// One mip level of one layer, to draw into.
+        pub fn face_view(
+                &self,
+                face: usize,
+                mip_level: u32,
+        ) -> wgpu::TextureView
+        {
+                self.texture.create_view(&wgpu::TextureViewDescriptor {
+                        label: Some("Reflection Probe Face"),
+                        dimension: Some(wgpu::TextureViewDimension::D2),
+                        base_mip_level: mip_level,
+                        mip_level_count: Some(1),
+                        base_array_layer: face as u32,
+                        array_layer_count: Some(1),
+                        ..Default::default()
+                })
+        }
+
+        /// Sets the position and volume the shader projects onto, the mip
+        /// count is filled in.
+        pub fn write_uniform(
+                &self,
+                queue: &wgpu::Queue,
+                uniform: &ProbeUniform,
+        )
+        {
+                let mut uniform = *uniform;
+                uniform.extents[3] = self.mip_level_count() as f32;
+
+                queue.write_buffer(&self.uniform, 0, bytemuck::bytes_of(&uniform));
+        }
+
+        /// The bindings after the model transform, and the joint palette of
+        /// skinned models, see [`create_probe_bind_group_layout`].
+        pub(crate) fn entries(&self) -> [wgpu::BindGroupEntry<'_>; 3]
+        {
+                [
+                        wgpu::BindGroupEntry {
+                                binding: 2,
+                                resource: self.uniform.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                                binding: 3,
+                                resource: wgpu::BindingResource::TextureView(&self.view),
+                        },
+                        wgpu::BindGroupEntry {
+                                binding: 4,
+                                resource: wgpu::BindingResource::Sampler(&self.sampler),
+                        },
+                ]
+        }
+}
+
+/// The model transform layout, with the joint palette in binding 1 when
+/// `skinned`, and the probe's uniform, cubemap and sampler in bindings 2 to
+/// 4.
+pub fn create_probe_bind_group_layout(
+        device: &wgpu::Device,
+        skinned: bool,
+) -> wgpu::BindGroupLayout
+{
+        let uniform = |binding, visibility| wgpu::BindGroupLayoutEntry {
+                binding,
+                visibility,
+                ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                },
+                count: None,
+        };
+
+        let mut entries = vec![uniform(0, wgpu::ShaderStages::VERTEX)];
+
+        if skinned
+        {
+                entries.push(uniform(1, wgpu::ShaderStages::VERTEX));
+        }
+
+        entries.extend([
+                uniform(2, wgpu::ShaderStages::FRAGMENT),
+                wgpu::BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                                sample_type: wgpu::TextureSampleType::Float {
+                                        filterable: true,
+                                },
+                                view_dimension: wgpu::TextureViewDimension::Cube,
+                                multisampled: false,
+                        },
+                        count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                        binding: 4,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                },
+        ]);
+
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &entries,
+                label: Some(
+                        if skinned
+                        {
+                                "skinned_probe_bind_group_layout"
+                        }
+                        else
+                        {
+                                "probe_bind_group_layout"
+                        },
+                ),
+        })
+}
+
+/// Fills the smaller mip levels of a [`ProbeTexture`] from its first, each
+/// pixel the average of the four below it.
+#[derive(Debug)]
+pub struct ProbeMipPass
+{
+        pipeline: wgpu::RenderPipeline,
+        bind_group_layout: wgpu::BindGroupLayout,
+        sampler: wgpu::Sampler,
+        format: wgpu::TextureFormat,
+}
+
+impl ProbeMipPass
+{
+        pub fn new(
+                device: &wgpu::Device,
+                format: wgpu::TextureFormat,
+        ) -> Self
+        {
+                let bind_group_layout =
+                        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                                label: Some("Probe Mip Bind Group Layout"),
+                                entries: &[
+                                        wgpu::BindGroupLayoutEntry {
+                                                binding: 0,
+                                                visibility: wgpu::ShaderStages::FRAGMENT,
+                                                ty: wgpu::BindingType::Texture {
+                                                        sample_type:
+                                                                wgpu::TextureSampleType::Float {
+                                                                        filterable: true,
+                                                                },
+                                                        view_dimension:
+                                                                wgpu::TextureViewDimension::D2,
+                                                        multisampled: false,
+                                                },
+                                                count: None,
+                                        },
+                                        wgpu::BindGroupLayoutEntry {
+                                                binding: 1,
+                                                visibility: wgpu::ShaderStages::FRAGMENT,
+                                                ty: wgpu::BindingType::Sampler(
+                                                        wgpu::SamplerBindingType::Filtering,
+                                                ),
+                                                count: None,
+                                        },
+                                ],
+                        });
+
+                let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                        label: Some("Probe Mip Shader"),
+                        source: wgpu::ShaderSource::Wgsl(include_str!("probe.wgsl").into()),
+                });
+
+                let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                        label: Some("Probe Mip Pipeline Layout"),
+                        bind_group_layouts: &[&bind_group_layout],
+                        push_constant_ranges: &[],
+                });
+
+                let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                        label: Some("Probe Mip Pipeline"),
+                        layout: Some(&layout),
+                        vertex: wgpu::VertexState {
+                                module: &shader,
+                                entry_point: Some("vs_main"),
+                                buffers: &[],
+                                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        },
+                        fragment: Some(wgpu::FragmentState {
+                                module: &shader,
+                                entry_point: Some("fs_main"),
+                                targets: &[Some(wgpu::ColorTargetState {
+                                        format,
+                                        blend: None,
+                                        write_mask: wgpu::ColorWrites::ALL,
+                                })],
+                                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        }),
+                        primitive: wgpu::PrimitiveState::default(),
+                        depth_stencil: None,
+                        multisample: wgpu::MultisampleState::default(),
+                        multiview: None,
+                        cache: None,
+                });
+
+                let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+                        label: Some("Probe Mip Sampler"),
+                        mag_filter: wgpu::FilterMode::Linear,
+                        min_filter: wgpu::FilterMode::Linear,
+                        ..Default::default()
+                });
+
+                Self {
+                        pipeline,
+                        bind_group_layout,
+                        sampler,
+                        format,
+                }
+        }
+
+        /// Format of the textures the pass filters.
+        pub fn format(&self) -> wgpu::TextureFormat
+        {
+                self.format
+        }
+
+        /// Filters every level after the first of every face of `probe`,
+        /// which must have the pass's format.
+        pub fn record(
+                &self,
+                device: &wgpu::Device,
+                encoder: &mut wgpu::CommandEncoder,
+                probe: &ProbeTexture,
+        )
+        {
+                for face in 0..6
+                {
+                        for mip_level in 1..probe.mip_level_count()
+                        {
+                                let source = probe.face_view(face, mip_level - 1);
+                                let target = probe.face_view(face, mip_level);
+
+                                let bind_group = device
+                                        .create_bind_group(&wgpu::BindGroupDescriptor {
+                                        label: Some("Probe Mip Bind Group"),
+                                        layout: &self.bind_group_layout,
+                                        entries: &[
+                                                wgpu::BindGroupEntry {
+                                                        binding: 0,
+                                                        resource:
+                                                                wgpu::BindingResource::TextureView(
+                                                                        &source,
+                                                                ),
+                                                },
+                                                wgpu::BindGroupEntry {
+                                                        binding: 1,
+                                                        resource: wgpu::BindingResource::Sampler(
+                                                                &self.sampler,
+                                                        ),
+                                                },
+                                        ],
+                                });
+
+                                let mut pass =
+                                        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                                                label: Some("Probe Mip Pass"),
+                                                color_attachments: &[Some(
+                                                        wgpu::RenderPassColorAttachment {
+                                                                view: &target,
+                                                                resolve_target: None,
+                                                                ops: wgpu::Operations {
+                                                                        load: wgpu::LoadOp::Clear(
+                                                                                wgpu::Color::BLACK,
+                                                                        ),
+                                                                        store: wgpu::StoreOp::Store,
+                                                                },
+                                                        },
+                                                )],
+                                                depth_stencil_attachment: None,
+                                                timestamp_writes: None,
+                                                occlusion_query_set: None,
+                                        });
+
+                                pass.set_pipeline(&self.pipeline);
+                                pass.set_bind_group(0, &bind_group, &[]);
+                                pass.draw(0..3, 0..1);
+                        }
+                }
+        }
+}
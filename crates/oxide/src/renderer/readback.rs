@@ -0,0 +1,263 @@
+//! Reading buffers and textures back from the GPU without stalling it.
+//!
+//! A [`Readback`] records a copy into a buffer the CPU can map. Once the
+//! commands were submitted it's mapped in the background, a frame or more
+//! later the bytes are handed to a callback or a future:
+//!
+//! ```ignore
+//! engine.on_render(move |frame| {
+//!         let readback = Readback::texture(frame.device, frame.encoder, &target);
+//!
+//!         // Mapped by the engine after the frame was submitted.
+//!         frame.read_back(readback, |result| match result
+//!         {
+//!                 Ok(pixels) => save_png(&pixels),
+//!                 Err(e) => log::error!("Readback failed: {}", e),
+//!         });
+//! });
+//! ```
+//!
+//! Outside of the frame, submit the encoder and call [`Readback::map`]
+//! or await [`Readback::read`]. Native callbacks run when the device is
+//! polled, the engine polls it every frame, see [`poll`]. On the web the
+//! browser runs them on its own.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+pub type ReadbackResult = Result<Vec<u8>, wgpu::BufferAsyncError>;
+
+/// Gets the bytes of a [`Readback`].
+#[cfg(not(target_arch = "wasm32"))]
+pub type ReadbackCallback = Box<dyn FnOnce(ReadbackResult) + Send>;
+
+/// Gets the bytes of a [`Readback`].
+#[cfg(target_arch = "wasm32")]
+pub type ReadbackCallback = Box<dyn FnOnce(ReadbackResult)>;
+
+/// Rows of a texture copy, buffer rows are padded to
+/// [`wgpu::COPY_BYTES_PER_ROW_ALIGNMENT`].
+#[derive(Debug, Clone, Copy)]
+struct Rows
+{
+        padded: u32,
+        unpadded: u32,
+        count: u32,
+}
+
+/// A copy of GPU memory on its way to the CPU.
+#[derive(Debug)]
+pub struct Readback
+{
+        buffer: wgpu::Buffer,
+        rows: Option<Rows>,
+}
+
+impl Readback
+{
+        /// Copies `size` bytes of `source` from `offset`, both multiples of
+        /// 4. `source` needs [`wgpu::BufferUsages::COPY_SRC`].
+        pub fn buffer(
+                device: &wgpu::Device,
+                encoder: &mut wgpu::CommandEncoder,
+                source: &wgpu::Buffer,
+                offset: u64,
+                size: u64,
+        ) -> Self
+        {
+                let buffer = Self::create_buffer(device, size);
+
+                encoder.copy_buffer_to_buffer(source, offset, &buffer, 0, size);
+
+                Self {
+                        buffer,
+                        rows: None,
+                }
+        }
+
+        /// Copies the first mip level of `texture`, tightly packed rows of
+        /// texels in its format. `texture` needs
+        /// [`wgpu::TextureUsages::COPY_SRC`] and a format with a block size,
+        /// not depth-stencil.
+        pub fn texture(
+                device: &wgpu::Device,
+                encoder: &mut wgpu::CommandEncoder,
+                texture: &wgpu::Texture,
+        ) -> Self
+        {
+                let size = texture.size();
+                let block = texture.format().block_copy_size(None).unwrap_or(4);
+
+                let unpadded = size.width * block;
+                let padded = unpadded.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+
+                let buffer = Self::create_buffer(device, padded as u64 * size.height as u64);
+
+                encoder.copy_texture_to_buffer(
+                        texture.as_image_copy(),
+                        wgpu::TexelCopyBufferInfo {
+                                buffer: &buffer,
+                                layout: wgpu::TexelCopyBufferLayout {
+                                        offset: 0,
+                                        bytes_per_row: Some(padded),
+                                        rows_per_image: Some(size.height),
+                                },
+                        },
+                        wgpu::Extent3d {
+                                depth_or_array_layers: 1,
+                                ..size
+                        },
+                );
+
+                Self {
+                        buffer,
+                        rows: Some(Rows {
+                                padded,
+                                unpadded,
+                                count: size.height,
+                        }),
+                }
+        }
+
+        fn create_buffer(
+                device: &wgpu::Device,
+                size: u64,
+        ) -> wgpu::Buffer
+        {
+                device.create_buffer(&wgpu::BufferDescriptor {
+                        label: Some("Readback Buffer"),
+                        size,
+                        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                        mapped_at_creation: false,
+                })
+        }
+
+        /// Maps the copy and hands its bytes to `callback`. Must be called
+        /// after the commands were submitted.
+        pub fn map(
+                self,
+                callback: impl FnOnce(ReadbackResult) + wgpu::WasmNotSend + 'static,
+        )
+        {
+                let Self {
+                        buffer,
+                        rows,
+                } = self;
+
+                let mapped = buffer.clone();
+
+                buffer.slice(..)
+                        .map_async(wgpu::MapMode::Read, move |result| {
+                                callback(result.map(|()| {
+                                        let bytes =
+                                                unpad(&mapped.slice(..).get_mapped_range(), rows);
+
+                                        mapped.unmap();
+
+                                        bytes
+                                }));
+                        });
+        }
+
+        /// The bytes of the copy, once mapped. Must be called after the
+        /// commands were submitted. The native future blocks on the device
+        /// when polled, await it where that's fine, e.g. in tests or tools.
+        pub fn read(
+                self,
+                #[allow(unused_variables)] device: &wgpu::Device,
+        ) -> ReadbackFuture
+        {
+                let shared = Arc::new(Mutex::new(Shared::default()));
+                let callback_shared = shared.clone();
+
+                self.map(move |result| {
+                        let mut shared = callback_shared.lock().unwrap();
+
+                        shared.result = Some(result);
+
+                        if let Some(waker) = shared.waker.take()
+                        {
+                                waker.wake();
+                        }
+                });
+
+                ReadbackFuture {
+                        shared,
+                        #[cfg(not(target_arch = "wasm32"))]
+                        device: device.clone(),
+                }
+        }
+}
+
+/// The rows of a texture copy without their padding.
+fn unpad(
+        bytes: &[u8],
+        rows: Option<Rows>,
+) -> Vec<u8>
+{
+        let Some(rows) = rows
+        else
+        {
+                return bytes.to_vec();
+        };
+
+        bytes.chunks(rows.padded as usize)
+                .take(rows.count as usize)
+                .flat_map(|row| &row[..rows.unpadded as usize])
+                .copied()
+                .collect()
+}
+
+#[derive(Default)]
+struct Shared
+{
+        result: Option<ReadbackResult>,
+        waker: Option<Waker>,
+}
+
+/// See [`Readback::read`].
+pub struct ReadbackFuture
+{
+        shared: Arc<Mutex<Shared>>,
+        #[cfg(not(target_arch = "wasm32"))]
+        device: wgpu::Device,
+}
+
+impl Future for ReadbackFuture
+{
+        type Output = ReadbackResult;
+
+        fn poll(
+                self: Pin<&mut Self>,
+                cx: &mut Context<'_>,
+        ) -> Poll<Self::Output>
+        {
+                // Nothing else would run the callback natively.
+                #[cfg(not(target_arch = "wasm32"))]
+                if self.shared.lock().unwrap().result.is_none()
+                {
+                        let _ = self.device.poll(wgpu::PollType::Wait);
+                }
+
+                let mut shared = self.shared.lock().unwrap();
+
+                match shared.result.take()
+                {
+                        Some(result) => Poll::Ready(result),
+                        None =>
+                        {
+                                shared.waker = Some(cx.waker().clone());
+                                Poll::Pending
+                        }
+                }
+        }
+}
+
+/// Runs the callbacks of the readbacks that finished, without waiting.
+/// Called by the engine every frame.
+pub fn poll(device: &wgpu::Device)
+{
+        let _ = device.poll(wgpu::PollType::Poll);
+}
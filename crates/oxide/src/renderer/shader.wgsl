@@ -1,63 +1,217 @@
-struct VertexInput {
-    @location(0) position: vec3<f32>,
-    @location(1) tex_coords: vec2<f32>,
-    @location(2) normal: vec3<f32>,
-};
+// The engine's model shader, built per material feature set, see
+// `preprocess.rs`.
 
-struct VertexOutput {
-    @builtin(position) clip_position: vec4<f32>,
-    @location(0) tex_coords: vec2<f32>,
-};
+#include "common.wgsl"
 
-struct CameraUniform {
-    view_pos: vec4<f32>,
-    view_proj: mat4x4<f32>,
-};
+#ifdef HAS_NORMAL_MAP
+// Bends the normal by the normal map, with the tangent frame from the
+// screen space derivatives since meshes don't carry tangents.
+fn perturb_normal(normal: vec3<f32>, position: vec3<f32>, uv: vec2<f32>) -> vec3<f32> {
+    let n = normalize(normal);
 
-struct MeshTransform {
-    model: mat4x4<f32>,
-};
+    let dp1 = dpdx(position);
+    let dp2 = dpdy(position);
+    let duv1 = dpdx(uv);
+    let duv2 = dpdy(uv);
 
-struct ModelTransform {
-    model: mat4x4<f32>,
+    let dp2perp = cross(dp2, n);
+    let dp1perp = cross(n, dp1);
+    let tangent = dp2perp * duv1.x + dp1perp * duv2.x;
+    // glTF texture coordinates go down, its normal maps up.
+    let bitangent = -(dp2perp * duv1.y + dp1perp * duv2.y);
+
+    let scale = inverseSqrt(max(max(dot(tangent, tangent), dot(bitangent, bitangent)), 1e-12));
+    let tbn = mat3x3<f32>(tangent * scale, bitangent * scale, n);
+
+    let sampled = textureSample(normal_texture, normal_sampler, uv).xyz * 2.0 - 1.0;
+
+    return normalize(tbn * sampled);
+}
+#endif
+
+#ifdef REFLECTION_PROBE
+// The probe reflecting on the model, see `probe.rs`.
+struct ReflectionProbe {
+    // Capture position, with the intensity in `w`.
+    position: vec4<f32>,
+    // Center of the volume, with `w` 0 for a box and 1 for a sphere.
+    center: vec4<f32>,
+    // Half the size of the box or the radius in `x`, with the mip count in `w`.
+    extents: vec4<f32>,
 };
 
-struct MaterialProperties {
-    base_color_factor: vec4<f32>,
-    metallic_factor: f32,
-    roughness_factor: f32,
-    // Padding to meet alignment requirements
-    _padding: vec2<f32>,
+@group(3) @binding(2) var<uniform> probe: ReflectionProbe;
+@group(3) @binding(3) var probe_texture: texture_cube<f32>;
+@group(3) @binding(4) var probe_sampler: sampler;
+
+// Where the ray from `position` along `direction` leaves the probe's
+// volume, seen from the capture position. Nearby walls line up with the
+// reflection then instead of looking infinitely far away.
+fn probe_direction(position: vec3<f32>, direction: vec3<f32>) -> vec3<f32> {
+    var distance = 0.0;
+
+    if (probe.center.w > 0.5) {
+        let offset = position - probe.center.xyz;
+        let b = dot(offset, direction);
+        let c = dot(offset, offset) - probe.extents.x * probe.extents.x;
+
+        distance = -b + sqrt(max(b * b - c, 0.0));
+    } else {
+        let low = (probe.center.xyz - probe.extents.xyz - position) / direction;
+        let high = (probe.center.xyz + probe.extents.xyz - position) / direction;
+        let far = max(low, high);
+
+        distance = max(min(far.x, min(far.y, far.z)), 0.0);
+    }
+
+    return position + direction * distance - probe.position.xyz;
+}
+
+// The probe's reflection of the surroundings, blurrier the rougher the
+// material and stronger at grazing angles.
+fn probe_reflection(color: vec3<f32>, world_position: vec3<f32>, world_normal: vec3<f32>) -> vec3<f32> {
+    let n = normalize(world_normal);
+    let v = normalize(camera.view_pos.xyz - world_position);
+    let r = reflect(-v, n);
+
+    let roughness = clamp(material_props.roughness_factor, 0.0, 1.0);
+    let f0 = mix(vec3<f32>(0.04), color, clamp(material_props.metallic_factor, 0.0, 1.0));
+    let grazing = pow(1.0 - clamp(dot(n, v), 0.0, 1.0), 5.0);
+    let fresnel = f0 + (max(vec3<f32>(1.0 - roughness), f0) - f0) * grazing;
+
+    // The faces are captured with `z` mirrored.
+    let direction = probe_direction(world_position, r) * vec3<f32>(1.0, 1.0, -1.0);
+    let level = roughness * max(probe.extents.w - 1.0, 0.0);
+    let reflected = textureSampleLevel(probe_texture, probe_sampler, direction, level).rgb;
+
+    return reflected * fresnel * probe.position.w;
+}
+#endif
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Sample the texture at the correct UV coordinates
+    let texture_color = textureSampleBias(base_color_texture, base_color_sampler, in.tex_coords, material_props.lod_bias);
+
+    // Apply any material color tint
+    let final_color = texture_color * material_props.base_color_factor;
+
+#ifdef HAS_NORMAL_MAP
+    let normal = perturb_normal(in.world_normal, in.world_position, in.tex_coords);
+#else
+    let normal = in.world_normal;
+#endif
+
+#ifdef REFLECTION_PROBE
+    // Metals reflect instead of scattering light.
+    let diffuse = final_color.rgb * (1.0 - clamp(material_props.metallic_factor, 0.0, 1.0));
+    let lit = environment_light(diffuse, normal) + probe_reflection(final_color.rgb, in.world_position, normal);
+
+    return vec4<f32>(environment_fog(lit, in.world_position), final_color.a);
+#else
+    return vec4<f32>(shade(final_color.rgb, in.world_position, normal), final_color.a);
+#endif
+}
+
+// Fill mode `WireframeOverlay`, meshes are drawn unindexed and every corner
+// of a triangle has one of (1, 0, 0), (0, 1, 0) or (0, 0, 1).
+struct WireframeVertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+    @location(2) normal: vec3<f32>,
+    @location(3) barycentric: vec3<f32>,
 };
 
-@group(0) @binding(0) var<uniform> camera: CameraUniform;
-@group(1) @binding(0) var<uniform> transform: MeshTransform;
-@group(2) @binding(0) var base_color_texture: texture_2d<f32>;
-@group(2) @binding(1) var base_color_sampler: sampler;
-@group(2) @binding(2) var<uniform> material_props: MaterialProperties;
-@group(3) @binding(0) var<uniform> model_transform: ModelTransform;
+struct WireframeVertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) barycentric: vec3<f32>,
+    @location(2) world_position: vec3<f32>,
+    @location(3) world_normal: vec3<f32>,
+};
 
+#ifndef CULLED
+// Meshes drawn together have no wireframe permutation, see `culling.rs`.
 @vertex
-fn vs_main(
-    model: VertexInput
-) -> VertexOutput {
-    var out: VertexOutput;
+fn vs_wireframe(
+    model: WireframeVertexInput
+) -> WireframeVertexOutput {
+    var out: WireframeVertexOutput;
 
     let world_position = transform.model * vec4<f32>(model.position, 1.0);
     let model_position = model_transform.model * world_position;
     out.clip_position = camera.view_proj * model_position;
     out.tex_coords = model.tex_coords;
+    out.barycentric = model.barycentric;
+    out.world_position = model_position.xyz;
+
+    let normal = model_transform.model * transform.model * vec4<f32>(model.normal, 0.0);
+    out.world_normal = normal.xyz;
 
     return out;
 }
+#endif
 
 @fragment
-fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    // Sample the texture at the correct UV coordinates
-    let texture_color = textureSample(base_color_texture, base_color_sampler, in.tex_coords);
+fn fs_wireframe(in: WireframeVertexOutput) -> @location(0) vec4<f32> {
+    let texture_color = textureSampleBias(base_color_texture, base_color_sampler, in.tex_coords, material_props.lod_bias);
+    let tinted = texture_color * material_props.base_color_factor;
+    let final_color = vec4<f32>(shade(tinted.rgb, in.world_position, in.world_normal), tinted.a);
 
-    // Apply any material color tint
-    let final_color = texture_color * material_props.base_color_factor;
+    // Close to an edge when one coordinate is close to 0, measured in pixels
+    // so lines have the same width at any distance.
+    let width = fwidth(in.barycentric);
+    let edge = smoothstep(vec3<f32>(0.0), width * 1.5, in.barycentric);
+    let line = 1.0 - min(edge.x, min(edge.y, edge.z));
+
+    return vec4<f32>(mix(final_color.rgb, vec3<f32>(1.0), line), max(final_color.a, line));
+}
+
+// Fill mode `UvChecker`, 8 squares per unit of the texture coordinates.
+@fragment
+fn fs_uv_checker(in: VertexOutput) -> @location(0) vec4<f32> {
+    let cell = vec2<i32>(floor(in.tex_coords * 8.0));
+    let odd = f32((cell.x + cell.y) & 1);
 
-    return final_color;
-}
\ No newline at end of file
+    // Tinted by the coordinates so flipped or repeated UVs stand out.
+    let tint = vec3<f32>(fract(in.tex_coords), 0.5);
+
+    return vec4<f32>(mix(vec3<f32>(0.15), tint, odd), 1.0);
+}
+
+// Fill mode `Normals`, the world space normal from -1..1 to 0..1.
+@fragment
+fn fs_normals(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(normalize(in.world_normal) * 0.5 + 0.5, 1.0);
+}
+
+// Fill mode `Overdraw`, added up by the pipeline's blending.
+@fragment
+fn fs_overdraw(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(0.1, 0.02, 0.0, 1.0);
+}
+
+// Fill mode `MipLevel`, the level from how many texels a pixel covers.
+@fragment
+fn fs_mip_level(in: VertexOutput) -> @location(0) vec4<f32> {
+    let texels = in.tex_coords * vec2<f32>(textureDimensions(base_color_texture));
+    let footprint = max(dot(dpdx(texels), dpdx(texels)), dot(dpdy(texels), dpdy(texels)));
+    let level = clamp(0.5 * log2(max(footprint, 1e-8)) + material_props.lod_bias, 0.0, 6.0);
+
+    // Blue, cyan, green, yellow, red.
+    let t = level / 6.0;
+    let color = clamp(vec3<f32>(t * 2.0 - 1.0, 2.0 - abs(t * 4.0 - 2.0), 1.0 - t * 2.0), vec3<f32>(0.0), vec3<f32>(1.0));
+
+    return vec4<f32>(color, 1.0);
+}
+
+// Rim of a highlighted model, see `HighlightPass`. Scaled to the highlight
+// color by the pipeline's blend constant and added on top.
+@fragment
+fn fs_highlight(in: VertexOutput) -> @location(0) vec4<f32> {
+    let n = normalize(in.world_normal);
+    let v = normalize(camera.view_pos.xyz - in.world_position);
+    let rim = pow(1.0 - clamp(abs(dot(n, v)), 0.0, 1.0), 3.0);
+
+    return vec4<f32>(vec3<f32>(0.15 + 0.85 * rim), 0.0);
+}
@@ -0,0 +1,288 @@
+//! Pass drawing the loading screen, see [`crate::loading`].
+//!
+//! It covers the whole target with a background color, an optional image
+//! and a progress bar. Where they go is given in pixels by the caller,
+//! [`SplashUniform`], so the pass knows nothing about the layout.
+
+use bytemuck::{Pod, Zeroable};
+use wgpu::util::DeviceExt;
+
+/// Colors, rectangles in pixels and the progress of the loading screen.
+/// Colors are linear, rectangles `[x, y, width, height]` from the top left,
+/// an empty one isn't drawn.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Pod, Zeroable)]
+pub struct SplashUniform
+{
+        pub background: [f32; 4],
+        pub bar: [f32; 4],
+        pub track: [f32; 4],
+        pub bar_rect: [f32; 4],
+        pub image_rect: [f32; 4],
+        /// 0 to 1.
+        pub progress: f32,
+        pub _padding: [f32; 3],
+}
+
+#[derive(Debug)]
+pub struct SplashPass
+{
+        pipeline: wgpu::RenderPipeline,
+        bind_group_layout: wgpu::BindGroupLayout,
+        bind_group: wgpu::BindGroup,
+        uniform: wgpu::Buffer,
+        sampler: wgpu::Sampler,
+        format: wgpu::TextureFormat,
+        /// Size of the image, `None` until one is set.
+        image_size: Option<(u32, u32)>,
+}
+
+impl SplashPass
+{
+        /// A pass drawing into targets of `format`, without an image.
+        pub fn new(
+                device: &wgpu::Device,
+                queue: &wgpu::Queue,
+                format: wgpu::TextureFormat,
+        ) -> Self
+        {
+                let bind_group_layout =
+                        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                                label: Some("Splash Bind Group Layout"),
+                                entries: &[
+                                        wgpu::BindGroupLayoutEntry {
+                                                binding: 0,
+                                                visibility: wgpu::ShaderStages::FRAGMENT,
+                                                ty: wgpu::BindingType::Buffer {
+                                                        ty: wgpu::BufferBindingType::Uniform,
+                                                        has_dynamic_offset: false,
+                                                        min_binding_size: None,
+                                                },
+                                                count: None,
+                                        },
+                                        wgpu::BindGroupLayoutEntry {
+                                                binding: 1,
+                                                visibility: wgpu::ShaderStages::FRAGMENT,
+                                                ty: wgpu::BindingType::Texture {
+                                                        sample_type:
+                                                                wgpu::TextureSampleType::Float {
+                                                                        filterable: true,
+                                                                },
+                                                        view_dimension:
+                                                                wgpu::TextureViewDimension::D2,
+                                                        multisampled: false,
+                                                },
+                                                count: None,
+                                        },
+                                        wgpu::BindGroupLayoutEntry {
+                                                binding: 2,
+                                                visibility: wgpu::ShaderStages::FRAGMENT,
+                                                ty: wgpu::BindingType::Sampler(
+                                                        wgpu::SamplerBindingType::Filtering,
+                                                ),
+                                                count: None,
+                                        },
+                                ],
+                        });
+
+                let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                        label: Some("Splash Shader"),
+                        source: wgpu::ShaderSource::Wgsl(include_str!("splash.wgsl").into()),
+                });
+
+                let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                        label: Some("Splash Pipeline Layout"),
+                        bind_group_layouts: &[&bind_group_layout],
+                        push_constant_ranges: &[],
+                });
+
+                let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                        label: Some("Splash Pipeline"),
+                        layout: Some(&layout),
+                        vertex: wgpu::VertexState {
+                                module: &shader,
+                                entry_point: Some("vs_main"),
+                                buffers: &[],
+                                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        },
+                        fragment: Some(wgpu::FragmentState {
+                                module: &shader,
+                                entry_point: Some("fs_main"),
+                                targets: &[Some(wgpu::ColorTargetState {
+                                        format,
+                                        blend: None,
+                                        write_mask: wgpu::ColorWrites::ALL,
+                                })],
+                                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        }),
+                        primitive: wgpu::PrimitiveState::default(),
+                        depth_stencil: None,
+                        multisample: wgpu::MultisampleState::default(),
+                        multiview: None,
+                        cache: None,
+                });
+
+                let uniform = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                        label: Some("Splash Uniform"),
+                        contents: bytemuck::bytes_of(&SplashUniform::default()),
+                        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                });
+
+                let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+                        label: Some("Splash Sampler"),
+                        mag_filter: wgpu::FilterMode::Linear,
+                        min_filter: wgpu::FilterMode::Linear,
+                        ..Default::default()
+                });
+
+                let image = Self::create_image(device, queue, 1, 1, &[255; 4]);
+                let bind_group = Self::create_bind_group(
+                        device,
+                        &bind_group_layout,
+                        &uniform,
+                        &image,
+                        &sampler,
+                );
+
+                Self {
+                        pipeline,
+                        bind_group_layout,
+                        bind_group,
+                        uniform,
+                        sampler,
+                        format,
+                        image_size: None,
+                }
+        }
+
+        /// Format of the targets the pass draws into.
+        pub fn format(&self) -> wgpu::TextureFormat
+        {
+                self.format
+        }
+
+        /// Size of the image in pixels, `None` without one.
+        pub fn image_size(&self) -> Option<(u32, u32)>
+        {
+                self.image_size
+        }
+
+        /// Shows `image`, in sRGB RGBA, in the image rectangle.
+        pub fn set_image(
+                &mut self,
+                device: &wgpu::Device,
+                queue: &wgpu::Queue,
+                image: &image::RgbaImage,
+        )
+        {
+                let (width, height) = image.dimensions();
+                let view = Self::create_image(device, queue, width, height, image.as_raw());
+
+                self.bind_group = Self::create_bind_group(
+                        device,
+                        &self.bind_group_layout,
+                        &self.uniform,
+                        &view,
+                        &self.sampler,
+                );
+                self.image_size = Some((width, height));
+        }
+
+        /// Draws the loading screen over all of `view`.
+        pub fn record(
+                &self,
+                queue: &wgpu::Queue,
+                encoder: &mut wgpu::CommandEncoder,
+                view: &wgpu::TextureView,
+                uniform: &SplashUniform,
+        )
+        {
+                queue.write_buffer(&self.uniform, 0, bytemuck::bytes_of(uniform));
+
+                let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        label: Some("Splash Pass"),
+                        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                                view,
+                                resolve_target: None,
+                                ops: wgpu::Operations {
+                                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                                        store: wgpu::StoreOp::Store,
+                                },
+                        })],
+                        depth_stencil_attachment: None,
+                        timestamp_writes: None,
+                        occlusion_query_set: None,
+                });
+
+                pass.set_pipeline(&self.pipeline);
+                pass.set_bind_group(0, &self.bind_group, &[]);
+                pass.draw(0..3, 0..1);
+        }
+
+        fn create_image(
+                device: &wgpu::Device,
+                queue: &wgpu::Queue,
+                width: u32,
+                height: u32,
+                rgba: &[u8],
+        ) -> wgpu::TextureView
+        {
+                let size = wgpu::Extent3d {
+                        width,
+                        height,
+                        depth_or_array_layers: 1,
+                };
+
+                let texture = device.create_texture(&wgpu::TextureDescriptor {
+                        label: Some("Splash Image"),
+                        size,
+                        mip_level_count: 1,
+                        sample_count: 1,
+                        dimension: wgpu::TextureDimension::D2,
+                        format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                        view_formats: &[],
+                });
+
+                queue.write_texture(
+                        texture.as_image_copy(),
+                        rgba,
+                        wgpu::TexelCopyBufferLayout {
+                                offset: 0,
+                                bytes_per_row: Some(4 * width),
+                                rows_per_image: Some(height),
+                        },
+                        size,
+                );
+
+                texture.create_view(&wgpu::TextureViewDescriptor::default())
+        }
+
+        fn create_bind_group(
+                device: &wgpu::Device,
+                layout: &wgpu::BindGroupLayout,
+                uniform: &wgpu::Buffer,
+                image: &wgpu::TextureView,
+                sampler: &wgpu::Sampler,
+        ) -> wgpu::BindGroup
+        {
+                device.create_bind_group(&wgpu::BindGroupDescriptor {
+                        label: Some("Splash Bind Group"),
+                        layout,
+                        entries: &[
+                                wgpu::BindGroupEntry {
+                                        binding: 0,
+                                        resource: uniform.as_entire_binding(),
+                                },
+                                wgpu::BindGroupEntry {
+                                        binding: 1,
+                                        resource: wgpu::BindingResource::TextureView(image),
+                                },
+                                wgpu::BindGroupEntry {
+                                        binding: 2,
+                                        resource: wgpu::BindingResource::Sampler(sampler),
+                                },
+                        ],
+                })
+        }
+}
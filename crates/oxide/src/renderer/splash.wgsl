@@ -0,0 +1,58 @@
+// The loading screen, a background color, an image and a progress bar.
+// See `splash.rs`.
+
+struct Splash {
+    background: vec4<f32>,
+    bar: vec4<f32>,
+    track: vec4<f32>,
+    // x, y, width and height in pixels.
+    bar_rect: vec4<f32>,
+    image_rect: vec4<f32>,
+    progress: f32,
+}
+
+@group(0) @binding(0)
+var<uniform> splash: Splash;
+
+@group(0) @binding(1)
+var image: texture_2d<f32>;
+
+@group(0) @binding(2)
+var image_sampler: sampler;
+
+// One triangle covering the screen.
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+    return vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+}
+
+// Position of `pixel` in `rect`, 0 to 1 inside it.
+fn rect_uv(pixel: vec2<f32>, rect: vec4<f32>) -> vec2<f32> {
+    return (pixel - rect.xy) / max(rect.zw, vec2<f32>(1.0));
+}
+
+fn inside(uv: vec2<f32>) -> bool {
+    return all(uv >= vec2<f32>(0.0)) && all(uv <= vec2<f32>(1.0));
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    var color = splash.background;
+
+    let image_uv = rect_uv(position.xy, splash.image_rect);
+    let texel = textureSample(image, image_sampler, clamp(image_uv, vec2<f32>(0.0), vec2<f32>(1.0)));
+
+    if (inside(image_uv) && splash.image_rect.z > 0.0) {
+        color = vec4<f32>(mix(color.rgb, texel.rgb, texel.a), 1.0);
+    }
+
+    let bar_uv = rect_uv(position.xy, splash.bar_rect);
+
+    if (inside(bar_uv) && splash.bar_rect.z > 0.0) {
+        color = select(splash.track, splash.bar, bar_uv.x <= splash.progress);
+    }
+
+    return color;
+}
@@ -1,53 +1,265 @@
+use crate::renderer::gamma_blit::GammaBlit;
+use crate::texture::Texture;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "input")]
 use std::sync::Arc;
+#[cfg(feature = "input")]
 use winit::dpi::PhysicalSize;
 
+/// How the colors the passes write end up on the display.
+#[derive(Debug, Default, PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize)]
+pub enum ColorSpace
+{
+        /// The passes write linear colors that are encoded to sRGB when
+        /// stored, correct for lit and blended output. Surfaces without an
+        /// sRGB format get a [`GammaBlit`].
+        #[default]
+        Srgb,
+
+        /// The passes write to a linear surface and the values are shown as
+        /// they are, for content that is already gamma encoded.
+        Linear,
+}
+
 #[derive(Debug)]
 pub struct SurfaceManager
 {
         pub surface: wgpu::Surface<'static>,
+        /// Configuration of the surface itself, the passes render in
+        /// [`SurfaceManager::render_format`].
         pub configuration: wgpu::SurfaceConfiguration,
         pub capabilities: wgpu::SurfaceCapabilities,
+        /// The surface has a size and frames can be acquired, false while the
+        /// window is minimized.
         pub is_surface_configured: bool,
+        pub color_space: ColorSpace,
+        /// Recreated together with the surface, so both always have the same
+        /// size.
+        depth_texture: Option<Texture>,
+        /// The surface has no format for the color space, see [`GammaBlit`].
+        needs_blit: bool,
+        /// The passes render into [`SurfaceManager::HDR_FORMAT`], see
+        /// [`crate::renderer::temporal`].
+        hdr: bool,
+        /// Created with the first [`SurfaceManager::configure`].
+        blit: Option<GammaBlit>,
 }
 
 impl SurfaceManager
 {
+        /// Format of the HDR target.
+        pub const HDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+        /// Creates the surface of the engine's window.
+        #[cfg(feature = "input")]
         pub fn new(
                 instance: &wgpu::Instance,
                 window: Arc<winit::window::Window>,
                 size: &PhysicalSize<u32>,
                 adapter: &wgpu::Adapter,
+                color_space: ColorSpace,
+                hdr: bool,
         ) -> anyhow::Result<Self>
         {
                 let surface = instance.create_surface(window.clone())?;
 
-                let capabilities = surface.get_capabilities(adapter);
+                Ok(Self::from_surface(
+                        surface,
+                        size.width,
+                        size.height,
+                        adapter,
+                        color_space,
+                        hdr,
+                ))
+        }
 
-                let format = capabilities.formats[0];
+        /// Wraps a surface created by the application, for using the renderer
+        /// without the engine's window.
+        pub fn from_surface(
+                surface: wgpu::Surface<'static>,
+                width: u32,
+                height: u32,
+                adapter: &wgpu::Adapter,
+                color_space: ColorSpace,
+                hdr: bool,
+        ) -> Self
+        {
+                let capabilities = surface.get_capabilities(adapter);
 
-                let configuration = Self::get_config(&size, &capabilities, format);
+                let (format, needs_blit) = Self::choose_format(&capabilities, color_space);
 
-                //let depth = Self::create_depth_texture(device, &configuration);
+                let configuration = Self::get_config(width, height, &capabilities, format);
 
-                Ok(Self {
+                Self {
                         surface,
                         configuration,
                         capabilities,
                         is_surface_configured: false,
-                })
+                        color_space,
+                        depth_texture: None,
+                        needs_blit,
+                        hdr,
+                        blit: None,
+                }
+        }
+
+        /// Picks the first surface format matching `color_space`, and whether
+        /// the output needs a [`GammaBlit`] because none does.
+        fn choose_format(
+                capabilities: &wgpu::SurfaceCapabilities,
+                color_space: ColorSpace,
+        ) -> (wgpu::TextureFormat, bool)
+        {
+                let srgb = color_space == ColorSpace::Srgb;
+
+                if let Some(format) = capabilities.formats.iter().find(|f| f.is_srgb() == srgb)
+                {
+                        return (*format, false);
+                }
+
+                let format = capabilities.formats[0];
+
+                if srgb
+                {
+                        log::info!("No sRGB surface format, encoding {:?} in a blit", format);
+
+                        return (format, true);
+                }
+
+                log::warn!("No linear surface format, using {:?}", format);
+
+                (format, false)
+        }
+
+        /// Format the passes render in.
+        pub fn render_format(&self) -> wgpu::TextureFormat
+        {
+                if self.hdr
+                {
+                        return Self::HDR_FORMAT;
+                }
+
+                if self.needs_blit
+                {
+                        return GammaBlit::FORMAT;
+                }
+
+                self.configuration.format
+        }
+
+        /// Whether the passes render into the HDR target.
+        pub fn hdr(&self) -> bool
+        {
+                self.hdr
+        }
+
+        /// Format the UI renders in. With the HDR target it's drawn onto the
+        /// surface after [`SurfaceManager::finish_frame`], egui writes 8 bit
+        /// colors.
+        pub fn ui_format(&self) -> wgpu::TextureFormat
+        {
+                if self.hdr
+                {
+                        return self.configuration.format;
+                }
+
+                self.render_format()
         }
 
+        /// The surface configuration with [`SurfaceManager::render_format`],
+        /// for building pipelines.
+        pub fn render_configuration(&self) -> wgpu::SurfaceConfiguration
+        {
+                wgpu::SurfaceConfiguration {
+                        format: self.render_format(),
+                        ..self.configuration.clone()
+                }
+        }
+
+        /// Resizes the surface and the depth texture, clamped to the largest
+        /// texture the device supports. A zero size, e.g. a minimized window,
+        /// stops frames from being acquired until the next resize.
+        pub fn resize(
+                &mut self,
+                device: &wgpu::Device,
+                width: u32,
+                height: u32,
+        )
+        {
+                let max_dimension = device.limits().max_texture_dimension_2d;
+
+                self.configuration.width = width.min(max_dimension);
+                self.configuration.height = height.min(max_dimension);
+
+                self.configure(device);
+        }
+
+        /// Applies [`SurfaceManager::configuration`] and recreates the
+        /// textures sized by it.
+        pub fn configure(
+                &mut self,
+                device: &wgpu::Device,
+        )
+        {
+                if self.configuration.width == 0 || self.configuration.height == 0
+                {
+                        self.is_surface_configured = false;
+
+                        return;
+                }
+
+                self.surface.configure(device, &self.configuration);
+
+                self.depth_texture = Some(Texture::create_depth_texture(
+                        device,
+                        &self.configuration,
+                        "depth_texture",
+                ));
+
+                if self.needs_blit || self.hdr
+                {
+                        match self.blit.as_mut()
+                        {
+                                Some(blit) => blit.resize(device, &self.configuration),
+                                None =>
+                                {
+                                        self.blit = Some(GammaBlit::new(
+                                                device,
+                                                &self.configuration,
+                                                self.render_format(),
+                                                self.needs_blit,
+                                        ))
+                                }
+                        }
+                }
+
+                self.is_surface_configured = true;
+        }
+
+        /// Depth texture of the current surface size, `None` before the
+        /// surface was configured.
+        pub fn depth_texture(&self) -> Option<&Texture>
+        {
+                self.depth_texture.as_ref()
+        }
+
+        #[cfg(feature = "input")]
         pub fn build_configuration(
                 &mut self,
                 size: &PhysicalSize<u32>,
         )
         {
-                self.configuration =
-                        Self::get_config(size, &self.capabilities, self.configuration.format);
+                self.configuration = Self::get_config(
+                        size.width,
+                        size.height,
+                        &self.capabilities,
+                        self.configuration.format,
+                );
         }
 
         pub fn get_config(
-                size: &PhysicalSize<u32>,
+                width: u32,
+                height: u32,
                 capabilities: &wgpu::SurfaceCapabilities,
                 format: wgpu::TextureFormat,
         ) -> wgpu::SurfaceConfiguration
@@ -55,8 +267,8 @@ impl SurfaceManager
                 wgpu::SurfaceConfiguration {
                         usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
                         format,
-                        width: size.width,
-                        height: size.height,
+                        width,
+                        height,
                         present_mode: wgpu::PresentMode::Fifo, // vsync
                         desired_maximum_frame_latency: 2,
                         alpha_mode: capabilities.alpha_modes[0],
@@ -76,8 +288,15 @@ impl SurfaceManager
         ///
         /// If a SurfaceTexture referencing this surface is alive when the
         /// swapchain is recreated, recreating the swapchain will panic
+        ///
+        /// With a [`GammaBlit`] or the HDR target the view is the offscreen
+        /// target, see [`SurfaceManager::finish_frame`].
+        ///
+        /// Returns `None` while the surface can't be drawn to. An outdated or
+        /// lost surface, e.g. after a resize the window events haven't caught
+        /// up with, is configured again and drawn to next frame.
         pub fn acquire_frame(
-                &self,
+                &mut self,
                 device: &wgpu::Device,
         ) -> anyhow::Result<Option<(wgpu::SurfaceTexture, wgpu::TextureView, wgpu::CommandEncoder)>>
         {
@@ -86,15 +305,26 @@ impl SurfaceManager
                         return Ok(None);
                 }
 
-                let output = self.surface.get_current_texture().map_err(|e| match e
+                let output = match self.surface.get_current_texture()
                 {
-                        wgpu::SurfaceError::Outdated => anyhow::anyhow!("Surface outdated"),
-                        e => anyhow::anyhow!(e),
-                })?;
+                        Ok(output) => output,
+                        Err(wgpu::SurfaceError::Outdated | wgpu::SurfaceError::Lost) =>
+                        {
+                                self.configure(device);
+
+                                return Ok(None);
+                        }
+                        Err(wgpu::SurfaceError::Timeout) => return Ok(None),
+                        Err(e) => return Err(e.into()),
+                };
 
-                let view = output
-                        .texture
-                        .create_view(&wgpu::TextureViewDescriptor::default());
+                let view = match &self.blit
+                {
+                        Some(blit) => blit.view().clone(),
+                        None => output
+                                .texture
+                                .create_view(&wgpu::TextureViewDescriptor::default()),
+                };
 
                 let encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
                         label: Some("Main Render Encoder"),
@@ -102,4 +332,22 @@ impl SurfaceManager
 
                 Ok(Some((output, view, encoder)))
         }
+
+        /// Copies the offscreen target onto the surface when there is a
+        /// [`GammaBlit`], must be recorded before `encoder` is submitted.
+        pub fn finish_frame(
+                &self,
+                output: &wgpu::SurfaceTexture,
+                encoder: &mut wgpu::CommandEncoder,
+        )
+        {
+                if let Some(blit) = &self.blit
+                {
+                        let view = output
+                                .texture
+                                .create_view(&wgpu::TextureViewDescriptor::default());
+
+                        blit.record(encoder, &view);
+                }
+        }
 }
@@ -0,0 +1,831 @@
+//! Temporal antialiasing and motion blur.
+//!
+//! The [`TemporalPass`] first draws the visible models into a velocity
+//! target, how far every pixel moved on screen since the last frame, using
+//! the previous camera and
+//! [`Model::previous_transform`](crate::model::Model::previous_transform).
+//! TAA offsets the projection by a fraction of a pixel every frame and
+//! blends the result with the reprojected history. Motion blur averages the
+//! colors along the motion of every pixel, all of it or only the camera's
+//! or the objects' part.
+//!
+//! Both read the scene, so they need the HDR target, a `Rgba16Float`
+//! texture the passes render into before it's copied onto the surface.
+//! It's created when TAA or motion blur is enabled in the config before
+//! the engine starts:
+//!
+//! ```ignore
+//! let mut engine = EngineBuilder::new()
+//!         .with_taa()
+//!         .with_motion_blur(MotionBlur::Objects)
+//!         .build()?;
+//! ```
+
+use crate::camera::{CameraUniform, create_camera_bind_group_layout};
+use crate::renderer::graph::{Attachment, AttachmentLoad, DEPTH, RenderPass, SURFACE};
+use crate::renderer::pipeline::PipelineManager;
+use crate::resources::create_transform_bind_group_layout;
+use crate::texture::Texture;
+use cgmath::Matrix4;
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+use std::collections::HashMap;
+
+/// Format of the velocity target.
+const VELOCITY_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// Sub-pixel offsets of the projection, the Halton sequence in base 2 and 3.
+const JITTER: [[f32; 2]; 8] = [
+        [0.5, 0.333_333],
+        [0.25, 0.666_667],
+        [0.75, 0.111_111],
+        [0.125, 0.444_444],
+        [0.625, 0.777_778],
+        [0.375, 0.222_222],
+        [0.875, 0.555_556],
+        [0.062_5, 0.888_889],
+];
+
+/// Which motion is blurred.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MotionBlur
+{
+        #[default]
+        Off,
+        /// Everything that moved on screen.
+        Full,
+        /// Only what the camera moving caused.
+        Camera,
+        /// Only what the objects moving caused.
+        Objects,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TemporalSettings
+{
+        pub taa: bool,
+        pub motion_blur: MotionBlur,
+        /// Scales the blur, 1 blurs over the distance moved in a frame.
+        pub blur_strength: f32,
+        pub blur_samples: u32,
+}
+
+impl Default for TemporalSettings
+{
+        fn default() -> Self
+        {
+                Self {
+                        taa: false,
+                        motion_blur: MotionBlur::Off,
+                        blur_strength: 1.0,
+                        blur_samples: 8,
+                }
+        }
+}
+
+impl TemporalSettings
+{
+        /// Whether anything needs the HDR target.
+        pub fn enabled(&self) -> bool
+        {
+                self.taa || self.motion_blur != MotionBlur::Off
+        }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct TemporalUniform
+{
+        blend: f32,
+        blur_strength: f32,
+        blur_samples: u32,
+        blur_mode: u32,
+        history_valid: u32,
+        _padding: [u32; 3],
+        viewport: [f32; 4],
+}
+
+fn create_temporal_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout
+{
+        let texture = |binding| wgpu::BindGroupLayoutEntry {
+                binding,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float {
+                                filterable: true,
+                        },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                },
+                count: None,
+        };
+
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                        texture(0),
+                        texture(1),
+                        texture(2),
+                        wgpu::BindGroupLayoutEntry {
+                                binding: 3,
+                                visibility: wgpu::ShaderStages::FRAGMENT,
+                                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                                count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                                binding: 4,
+                                visibility: wgpu::ShaderStages::FRAGMENT,
+                                ty: wgpu::BindingType::Buffer {
+                                        ty: wgpu::BufferBindingType::Uniform,
+                                        has_dynamic_offset: false,
+                                        min_binding_size: None,
+                                },
+                                count: None,
+                        },
+                ],
+                label: Some("temporal_bind_group_layout"),
+        })
+}
+
+/// Pipelines, built on the first frame.
+#[derive(Debug)]
+struct Pipelines
+{
+        velocity: wgpu::RenderPipeline,
+        resolve: wgpu::RenderPipeline,
+        blur: wgpu::RenderPipeline,
+        copy: wgpu::RenderPipeline,
+        layout: wgpu::BindGroupLayout,
+        sampler: wgpu::Sampler,
+        /// Written once per frame, read by every fullscreen draw.
+        uniform: wgpu::Buffer,
+}
+
+impl Pipelines
+{
+        fn new(
+                device: &wgpu::Device,
+                format: wgpu::TextureFormat,
+                pipeline_manager: &PipelineManager,
+        ) -> Self
+        {
+                let camera_layout = create_camera_bind_group_layout(device);
+                let transform_layout = create_transform_bind_group_layout(device);
+                let layout = create_temporal_bind_group_layout(device);
+
+                let velocity_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                        label: Some("Velocity Shader"),
+                        source: wgpu::ShaderSource::Wgsl(include_str!("velocity.wgsl").into()),
+                });
+
+                let velocity_layout =
+                        device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                                label: Some("Velocity Pipeline Layout"),
+                                bind_group_layouts: &[
+                                        &camera_layout,
+                                        &transform_layout,
+                                        &transform_layout,
+                                        &transform_layout,
+                                ],
+                                push_constant_ranges: &[],
+                        });
+
+                use crate::model::Vertex;
+
+                let velocity = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                        label: Some("Velocity Pipeline"),
+                        layout: Some(&velocity_layout),
+                        vertex: wgpu::VertexState {
+                                module: &velocity_shader,
+                                entry_point: Some("vs_main"),
+                                buffers: &[crate::model::ModelVertex::desc()],
+                                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        },
+                        fragment: Some(wgpu::FragmentState {
+                                module: &velocity_shader,
+                                entry_point: Some("fs_main"),
+                                targets: &[Some(wgpu::ColorTargetState {
+                                        format: VELOCITY_FORMAT,
+                                        blend: None,
+                                        write_mask: wgpu::ColorWrites::ALL,
+                                })],
+                                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        }),
+                        primitive: wgpu::PrimitiveState {
+                                cull_mode: Some(wgpu::Face::Back),
+                                ..Default::default()
+                        },
+                        // Only the surfaces the geometry pass left in the
+                        // depth texture.
+                        depth_stencil: Some(wgpu::DepthStencilState {
+                                format: Texture::DEPTH_FORMAT,
+                                depth_write_enabled: false,
+                                depth_compare: pipeline_manager
+                                        .depth_compare(wgpu::CompareFunction::LessEqual),
+                                stencil: wgpu::StencilState::default(),
+                                bias: wgpu::DepthBiasState::default(),
+                        }),
+                        multisample: wgpu::MultisampleState::default(),
+                        multiview: None,
+                        cache: pipeline_manager.cache.as_ref(),
+                });
+
+                let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                        label: Some("Temporal Shader"),
+                        source: wgpu::ShaderSource::Wgsl(include_str!("temporal.wgsl").into()),
+                });
+
+                let fullscreen_layout =
+                        device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                                label: Some("Temporal Pipeline Layout"),
+                                bind_group_layouts: &[&layout],
+                                push_constant_ranges: &[],
+                        });
+
+                let fullscreen = |entry_point: &str| {
+                        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                                label: Some(entry_point),
+                                layout: Some(&fullscreen_layout),
+                                vertex: wgpu::VertexState {
+                                        module: &shader,
+                                        entry_point: Some("vs_main"),
+                                        buffers: &[],
+                                        compilation_options:
+                                                wgpu::PipelineCompilationOptions::default(),
+                                },
+                                fragment: Some(wgpu::FragmentState {
+                                        module: &shader,
+                                        entry_point: Some(entry_point),
+                                        targets: &[Some(wgpu::ColorTargetState {
+                                                format,
+                                                blend: None,
+                                                write_mask: wgpu::ColorWrites::ALL,
+                                        })],
+                                        compilation_options:
+                                                wgpu::PipelineCompilationOptions::default(),
+                                }),
+                                primitive: wgpu::PrimitiveState::default(),
+                                depth_stencil: None,
+                                multisample: wgpu::MultisampleState::default(),
+                                multiview: None,
+                                cache: pipeline_manager.cache.as_ref(),
+                        })
+                };
+
+                let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+                        label: Some("Temporal Sampler"),
+                        mag_filter: wgpu::FilterMode::Linear,
+                        min_filter: wgpu::FilterMode::Linear,
+                        ..Default::default()
+                });
+
+                Self {
+                        velocity,
+                        resolve: fullscreen("fs_resolve"),
+                        blur: fullscreen("fs_blur"),
+                        copy: fullscreen("fs_copy"),
+                        layout,
+                        sampler,
+                        uniform: device.create_buffer(&wgpu::BufferDescriptor {
+                                label: Some("Temporal Buffer"),
+                                size: std::mem::size_of::<TemporalUniform>() as u64,
+                                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                                mapped_at_creation: false,
+                        }),
+                }
+        }
+}
+
+/// Textures at the size of the surface.
+#[derive(Debug)]
+struct Targets
+{
+        size: wgpu::Extent3d,
+        velocity: Texture,
+        /// Written alternately, the other one is last frame's.
+        history: [Texture; 2],
+        /// Motion blur renders here before it's copied back.
+        scratch: Texture,
+        /// Bind groups of the fullscreen draws by the color and history
+        /// they read, dropped with the targets on resize.
+        bind_groups: HashMap<(wgpu::TextureView, wgpu::TextureView), wgpu::BindGroup>,
+}
+
+impl Targets
+{
+        fn new(
+                device: &wgpu::Device,
+                size: wgpu::Extent3d,
+                format: wgpu::TextureFormat,
+        ) -> Self
+        {
+                let target = |label, format| {
+                        let texture = device.create_texture(&wgpu::TextureDescriptor {
+                                label: Some(label),
+                                size,
+                                mip_level_count: 1,
+                                sample_count: 1,
+                                dimension: wgpu::TextureDimension::D2,
+                                format,
+                                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                                        | wgpu::TextureUsages::TEXTURE_BINDING,
+                                view_formats: &[],
+                        });
+
+                        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+                        let sampler = device.create_sampler(&wgpu::SamplerDescriptor::default());
+
+                        Texture {
+                                texture,
+                                view,
+                                sampler,
+                        }
+                };
+
+                Self {
+                        size,
+                        velocity: target("Velocity Target", VELOCITY_FORMAT),
+                        history: [target("TAA History", format), target("TAA History", format)],
+                        scratch: target("Motion Blur Target", format),
+                        bind_groups: HashMap::new(),
+                }
+        }
+
+        fn bind_group(
+                &mut self,
+                device: &wgpu::Device,
+                pipelines: &Pipelines,
+                color: &wgpu::TextureView,
+                history: &wgpu::TextureView,
+        ) -> &wgpu::BindGroup
+        {
+                let velocity = &self.velocity.view;
+
+                self.bind_groups
+                        .entry((color.clone(), history.clone()))
+                        .or_insert_with(|| {
+                                device.create_bind_group(&wgpu::BindGroupDescriptor {
+                                        layout: &pipelines.layout,
+                                        entries: &[
+                                                wgpu::BindGroupEntry {
+                                                        binding: 0,
+                                                        resource:
+                                                                wgpu::BindingResource::TextureView(
+                                                                        color,
+                                                                ),
+                                                },
+                                                wgpu::BindGroupEntry {
+                                                        binding: 1,
+                                                        resource:
+                                                                wgpu::BindingResource::TextureView(
+                                                                        history,
+                                                                ),
+                                                },
+                                                wgpu::BindGroupEntry {
+                                                        binding: 2,
+                                                        resource:
+                                                                wgpu::BindingResource::TextureView(
+                                                                        velocity,
+                                                                ),
+                                                },
+                                                wgpu::BindGroupEntry {
+                                                        binding: 3,
+                                                        resource: wgpu::BindingResource::Sampler(
+                                                                &pipelines.sampler,
+                                                        ),
+                                                },
+                                                wgpu::BindGroupEntry {
+                                                        binding: 4,
+                                                        resource: pipelines
+                                                                .uniform
+                                                                .as_entire_binding(),
+                                                },
+                                        ],
+                                        label: Some("temporal_bind_group"),
+                                })
+                        })
+        }
+}
+
+/// Velocity, TAA and motion blur, see [`crate::renderer::temporal`].
+#[derive(Debug)]
+pub struct TemporalPass
+{
+        pub name: String,
+        pub enabled: bool,
+        pub settings: TemporalSettings,
+        /// How much of the current frame goes into the history, lower is
+        /// smoother but smears more.
+        pub blend: f32,
+        /// The scene is rendered into the HDR target, without it the pass
+        /// does nothing.
+        available: bool,
+        format: wgpu::TextureFormat,
+        pipelines: Option<Pipelines>,
+        targets: Option<Targets>,
+        /// History the next frame reads.
+        current: usize,
+        history_valid: bool,
+        frame: usize,
+        previous_view_proj: Option<Matrix4<f32>>,
+}
+
+impl TemporalPass
+{
+        /// `format` is the format the passes render in, `available` whether
+        /// that's the HDR target.
+        pub fn new(
+                settings: &TemporalSettings,
+                format: wgpu::TextureFormat,
+                available: bool,
+        ) -> Self
+        {
+                if settings.enabled() && !available
+                {
+                        log::warn!(
+                                "TAA and motion blur need the HDR target, enable them before the engine starts"
+                        );
+                }
+
+                Self {
+                        name: "temporal_pass".to_string(),
+                        enabled: true,
+                        settings: *settings,
+                        blend: 0.1,
+                        available,
+                        format,
+                        pipelines: None,
+                        targets: None,
+                        current: 0,
+                        history_valid: false,
+                        frame: 0,
+                        previous_view_proj: None,
+                }
+        }
+
+        fn active(&self) -> bool
+        {
+                self.enabled && self.available && self.settings.enabled()
+        }
+
+        /// The velocity target, while TAA or motion blur is on.
+        pub fn velocity(&self) -> Option<&Texture>
+        {
+                self.targets
+                        .as_ref()
+                        .filter(|_| self.active())
+                        .map(|targets| &targets.velocity)
+        }
+
+        /// Hands the last frame's camera to `uniform` and offsets its
+        /// projection for TAA. Called before the camera is uploaded.
+        pub fn prepare(
+                &mut self,
+                uniform: &mut CameraUniform,
+                width: u32,
+                height: u32,
+        )
+        {
+                let view_proj = Matrix4::from(uniform.view_proj);
+
+                uniform.previous_view_proj = self.previous_view_proj.unwrap_or(view_proj).into();
+                uniform.jitter = [0.0; 4];
+
+                self.previous_view_proj = Some(view_proj);
+
+                if !self.active() || !self.settings.taa
+                {
+                        return;
+                }
+
+                self.frame = (self.frame + 1) % JITTER.len();
+
+                // From a pixel to NDC, which spans 2 units across the screen.
+                let [x, y] = JITTER[self.frame];
+                let jitter = [
+                        (x - 0.5) * 2.0 / width.max(1) as f32,
+                        (y - 0.5) * 2.0 / height.max(1) as f32,
+                ];
+
+                let offset =
+                        Matrix4::from_translation(cgmath::Vector3::new(jitter[0], jitter[1], 0.0));
+
+                uniform.view_proj = (offset * view_proj).into();
+                uniform.jitter = [jitter[0], jitter[1], 0.0, 0.0];
+        }
+
+        fn uniform(
+                &self,
+                viewport: [f32; 4],
+        ) -> TemporalUniform
+        {
+                TemporalUniform {
+                        blend: self.blend.clamp(0.01, 1.0),
+                        blur_strength: self.settings.blur_strength,
+                        blur_samples: self.settings.blur_samples.clamp(2, 64),
+                        blur_mode: match self.settings.motion_blur
+                        {
+                                MotionBlur::Camera => 1,
+                                MotionBlur::Objects => 2,
+                                MotionBlur::Off | MotionBlur::Full => 0,
+                        },
+                        history_valid: self.history_valid as u32,
+                        _padding: [0; 3],
+                        viewport,
+                }
+        }
+
+        /// Bind group reading `color` and `history`, created on first use.
+        fn bind_group(
+                &mut self,
+                device: &wgpu::Device,
+                color: &wgpu::TextureView,
+                history: &wgpu::TextureView,
+        ) -> Option<wgpu::BindGroup>
+        {
+                let (Some(pipelines), Some(targets)) = (&self.pipelines, &mut self.targets)
+                else
+                {
+                        return None;
+                };
+
+                Some(targets
+                        .bind_group(device, pipelines, color, history)
+                        .clone())
+        }
+
+        /// Draws a fullscreen triangle with `pipeline` into `target`, reading
+        /// the color and history of `bind_group`.
+        fn fullscreen(
+                &self,
+                encoder: &mut wgpu::CommandEncoder,
+                pipeline: &wgpu::RenderPipeline,
+                target: &wgpu::TextureView,
+                bind_group: &wgpu::BindGroup,
+        )
+        {
+                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        label: Some(&self.name),
+                        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                                view: target,
+                                resolve_target: None,
+                                ops: wgpu::Operations {
+                                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                                        store: wgpu::StoreOp::Store,
+                                },
+                        })],
+                        depth_stencil_attachment: None,
+                        occlusion_query_set: None,
+                        timestamp_writes: None,
+                });
+
+                render_pass.set_pipeline(pipeline);
+                render_pass.set_bind_group(0, bind_group, &[]);
+                render_pass.draw(0..3, 0..1);
+        }
+
+        fn record_velocity(
+                &self,
+                encoder: &mut wgpu::CommandEncoder,
+                camera: &wgpu::BindGroup,
+                pipeline_manager: &PipelineManager,
+                depth_texture: &Texture,
+                models: Option<&HashMap<String, crate::model::Model>>,
+                device: &wgpu::Device,
+        )
+        {
+                let (Some(pipelines), Some(targets)) = (&self.pipelines, &self.targets)
+                else
+                {
+                        return;
+                };
+
+                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        label: Some("velocity_pass"),
+                        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                                view: &targets.velocity.view,
+                                resolve_target: None,
+                                ops: wgpu::Operations {
+                                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                                        store: wgpu::StoreOp::Store,
+                                },
+                        })],
+                        depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                                view: &depth_texture.view,
+                                depth_ops: Some(wgpu::Operations {
+                                        load: wgpu::LoadOp::Load,
+                                        store: wgpu::StoreOp::Store,
+                                }),
+                                stencil_ops: None,
+                        }),
+                        occlusion_query_set: None,
+                        timestamp_writes: None,
+                });
+
+                pipeline_manager.apply_viewport(&mut render_pass);
+                render_pass.set_pipeline(&pipelines.velocity);
+                render_pass.set_bind_group(0, camera, &[]);
+
+                use crate::model::DrawModel;
+
+                for model in models
+                        .into_iter()
+                        .flat_map(|m| m.values())
+                        .filter(|m| m.visible)
+                {
+                        render_pass.set_bind_group(
+                                2,
+                                &model.create_model_transform_bind_group(device),
+                                &[],
+                        );
+                        render_pass.set_bind_group(
+                                3,
+                                &model.create_previous_transform_bind_group(device),
+                                &[],
+                        );
+
+                        for mesh in model.meshes.iter()
+                        {
+                                render_pass.set_bind_group(1, &mesh.transform_bind_group, &[]);
+                                render_pass.draw_mesh(mesh);
+                        }
+                }
+        }
+}
+
+impl RenderPass for TemporalPass
+{
+        fn name(&self) -> &str
+        {
+                self.name.as_str()
+        }
+
+        fn as_any(&self) -> &dyn Any
+        {
+                self
+        }
+
+        fn as_any_mut(&mut self) -> &mut dyn Any
+        {
+                self
+        }
+
+        #[cfg(feature = "ui")]
+        fn ui(
+                &mut self,
+                ui: &mut egui::Ui,
+        )
+        {
+                egui::CollapsingHeader::new(&self.name)
+                        .default_open(true)
+                        .show(ui, |ui| {
+                                if !self.available
+                                {
+                                        ui.label("Needs the HDR target, enable TAA or motion blur before starting");
+                                        return;
+                                }
+
+                                ui.checkbox(&mut self.settings.taa, "TAA");
+                                ui.add(egui::Slider::new(&mut self.blend, 0.02..=1.0).text("Blend"));
+
+                                egui::ComboBox::from_label("Motion Blur")
+                                        .selected_text(format!("{:?}", self.settings.motion_blur))
+                                        .show_ui(ui, |ui| {
+                                                for mode in [
+                                                        MotionBlur::Off,
+                                                        MotionBlur::Full,
+                                                        MotionBlur::Camera,
+                                                        MotionBlur::Objects,
+                                                ]
+                                                {
+                                                        ui.selectable_value(
+                                                                &mut self.settings.motion_blur,
+                                                                mode,
+                                                                format!("{:?}", mode),
+                                                        );
+                                                }
+                                        });
+
+                                ui.add(egui::Slider::new(&mut self.settings.blur_strength, 0.0..=4.0)
+                                        .text("Strength"));
+                                ui.add(egui::Slider::new(&mut self.settings.blur_samples, 2..=32)
+                                        .text("Samples"));
+                        });
+        }
+
+        fn enabled(&mut self) -> bool
+        {
+                self.enabled
+        }
+
+        fn attachments(&self) -> Vec<Attachment>
+        {
+                vec![
+                        Attachment::new(SURFACE, AttachmentLoad::Load, true),
+                        Attachment::new(DEPTH, AttachmentLoad::Load, false),
+                ]
+        }
+
+        fn set_enabled(
+                &mut self,
+                value: bool,
+        )
+        {
+                self.enabled = value;
+        }
+
+        fn record(
+                &mut self,
+                view: &wgpu::TextureView,
+                encoder: &mut wgpu::CommandEncoder,
+                camera: &wgpu::BindGroup,
+                pipeline_manager: &PipelineManager,
+                depth_texture: &Texture,
+                models: Option<&HashMap<String, crate::model::Model>>,
+                device: &wgpu::Device,
+                queue: &wgpu::Queue,
+        )
+        {
+                if !self.active()
+                {
+                        self.history_valid = false;
+                        return;
+                }
+
+                if self.pipelines.is_none()
+                {
+                        self.pipelines =
+                                Some(Pipelines::new(device, self.format, pipeline_manager));
+                }
+
+                let size = depth_texture.texture.size();
+
+                if self.targets
+                        .as_ref()
+                        .is_none_or(|targets| targets.size != size)
+                {
+                        self.targets = Some(Targets::new(device, size, self.format));
+                        self.history_valid = false;
+                }
+
+                self.record_velocity(
+                        encoder,
+                        camera,
+                        pipeline_manager,
+                        depth_texture,
+                        models,
+                        device,
+                );
+
+                let viewport = pipeline_manager.viewport_rect(size.width, size.height);
+
+                let uniform = self.uniform(viewport);
+
+                let (Some(pipelines), Some(targets)) = (&self.pipelines, &self.targets)
+                else
+                {
+                        return;
+                };
+
+                queue.write_buffer(&pipelines.uniform, 0, bytemuck::cast_slice(&[uniform]));
+
+                // Cheap handles, so the bind groups can be looked up on `self`.
+                let (resolve, blur, copy) =
+                        (pipelines.resolve.clone(), pipelines.blur.clone(), pipelines.copy.clone());
+
+                let previous = targets.history[1 - self.current].view.clone();
+                let resolved = targets.history[self.current].view.clone();
+                let scratch = targets.scratch.view.clone();
+                let unused = targets.history[0].view.clone();
+
+                if self.settings.taa
+                        && let (Some(resolve_group), Some(copy_group)) = (
+                                self.bind_group(device, view, &previous),
+                                self.bind_group(device, &resolved, &previous),
+                        )
+                {
+                        self.fullscreen(encoder, &resolve, &resolved, &resolve_group);
+                        self.fullscreen(encoder, &copy, view, &copy_group);
+                }
+
+                if self.settings.motion_blur != MotionBlur::Off
+                        && let (Some(blur_group), Some(copy_group)) = (
+                                self.bind_group(device, view, &unused),
+                                self.bind_group(device, &scratch, &scratch),
+                        )
+                {
+                        self.fullscreen(encoder, &blur, &scratch, &blur_group);
+                        self.fullscreen(encoder, &copy, view, &copy_group);
+                }
+
+                if self.settings.taa
+                {
+                        self.current = 1 - self.current;
+                        self.history_valid = true;
+                }
+                else
+                {
+                        self.history_valid = false;
+                }
+        }
+}
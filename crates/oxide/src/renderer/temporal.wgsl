@@ -0,0 +1,116 @@
+// Temporal antialiasing, motion blur and the copy back onto the scene. See
+// `temporal.rs`.
+
+struct Temporal {
+    // How much of the current frame goes into the history.
+    blend: f32,
+    blur_strength: f32,
+    blur_samples: u32,
+    // 0 full motion, 1 the camera's, 2 the objects'.
+    blur_mode: u32,
+    history_valid: u32,
+    _padding0: u32,
+    _padding1: u32,
+    _padding2: u32,
+    // Where the scene is on the screen in pixels, offset in `xy` and size
+    // in `zw`.
+    viewport: vec4<f32>,
+};
+
+@group(0) @binding(0) var color: texture_2d<f32>;
+@group(0) @binding(1) var history: texture_2d<f32>;
+@group(0) @binding(2) var velocity: texture_2d<f32>;
+@group(0) @binding(3) var linear_sampler: sampler;
+@group(0) @binding(4) var<uniform> settings: Temporal;
+
+// One triangle covering the screen.
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+    return vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+}
+
+fn load(texture: texture_2d<f32>, texel: vec2<i32>) -> vec4<f32> {
+    let size = vec2<i32>(textureDimensions(texture));
+
+    return textureLoad(texture, clamp(texel, vec2<i32>(0), size - 1), 0);
+}
+
+// The velocity is in texture coordinates of the viewport, scaled to the
+// screen's.
+fn motion(texel: vec2<i32>) -> vec4<f32> {
+    let scale = settings.viewport.zw / vec2<f32>(textureDimensions(color));
+
+    return load(velocity, texel) * vec4<f32>(scale, scale);
+}
+
+// Blends the reprojected history with this frame, the history clamped to
+// the colors around the pixel so it doesn't smear.
+@fragment
+fn fs_resolve(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let texel = vec2<i32>(position.xy);
+    let current = load(color, texel);
+
+    var low = current.rgb;
+    var high = current.rgb;
+
+    for (var y = -1; y <= 1; y++) {
+        for (var x = -1; x <= 1; x++) {
+            let neighbor = load(color, texel + vec2<i32>(x, y)).rgb;
+
+            low = min(low, neighbor);
+            high = max(high, neighbor);
+        }
+    }
+
+    let size = vec2<f32>(textureDimensions(color));
+    let uv = position.xy / size;
+    let previous_uv = uv - motion(texel).xy;
+
+    let low_uv = settings.viewport.xy / size;
+    let high_uv = (settings.viewport.xy + settings.viewport.zw) / size;
+    let on_screen = all(previous_uv >= low_uv) && all(previous_uv <= high_uv);
+
+    if settings.history_valid == 0u || !on_screen {
+        return current;
+    }
+
+    let previous = clamp(textureSampleLevel(history, linear_sampler, previous_uv, 0.0).rgb, low, high);
+
+    return vec4<f32>(mix(previous, current.rgb, settings.blend), current.a);
+}
+
+// Averages the colors along the motion of the pixel.
+@fragment
+fn fs_blur(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let texel = vec2<i32>(position.xy);
+    let full = motion(texel);
+
+    var delta = full.xy;
+
+    if settings.blur_mode == 1u {
+        delta = full.zw;
+    } else if settings.blur_mode == 2u {
+        delta = full.xy - full.zw;
+    }
+
+    let size = vec2<f32>(textureDimensions(color));
+    let uv = position.xy / size;
+    let samples = max(settings.blur_samples, 2u);
+    let step = delta * settings.blur_strength / f32(samples - 1u);
+    let start = uv - step * f32(samples - 1u) * 0.5;
+
+    var sum = vec4<f32>(0.0);
+
+    for (var i = 0u; i < samples; i++) {
+        sum += textureSampleLevel(color, linear_sampler, start + step * f32(i), 0.0);
+    }
+
+    return sum / f32(samples);
+}
+
+@fragment
+fn fs_copy(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    return load(color, vec2<i32>(position.xy));
+}
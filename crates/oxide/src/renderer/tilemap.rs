@@ -0,0 +1,603 @@
+//! Grids of tiles for board and arcade style games.
+//!
+//! A [`TileMap`] lies flat on the XZ plane, cell `(x, y)` being the `x`th
+//! along +X and the `y`th along +Z from its origin. Every cell shows one
+//! model of the map's palette or nothing, drawn instanced so a large map is
+//! one draw per palette model and mesh:
+//!
+//! ```ignore
+//! engine.add_model("grass", "grass_tile.glb");
+//! engine.add_model("wall", "wall_tile.glb");
+//!
+//! let mut map = TileMap::new(20, 20, 1.0);
+//! map.palette = vec!["grass".to_string(), "wall".to_string()];
+//! map.fill(Some(0));
+//! map.set(5, 7, Some(1));
+//!
+//! engine.add_tilemap("level", map);
+//!
+//! // Which cell the player stands on.
+//! let cell = engine.tilemap("level").and_then(|m| m.world_to_cell(position));
+//! ```
+//!
+//! The palette models are drawn on their cells only, the geometry pass
+//! skips them. Their rotation and scale turn and size every tile, their
+//! position moves the tiles off the cell centers, so it's usually the
+//! origin.
+
+#[cfg(feature = "runtime")]
+use crate::engine::Engine;
+use crate::model::{Model, Vertex};
+use crate::renderer::graph::{Attachment, AttachmentLoad, DEPTH, RenderPass, SURFACE};
+use crate::renderer::pipeline::{PipelineKind, PipelineManager};
+use crate::texture::Texture;
+use crate::transform::Transform;
+use cgmath::{Point3, Vector3};
+use std::any::Any;
+use std::collections::{BTreeMap, HashMap};
+use wgpu::util::DeviceExt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileMap
+{
+        /// Cells along X.
+        pub width: u32,
+        /// Cells along Z.
+        pub height: u32,
+        /// Width and depth of a cell in world units.
+        pub tile_size: f32,
+        /// Corner of cell `(0, 0)` furthest towards -X and -Z.
+        pub origin: Point3<f32>,
+        /// Model handles the cells refer to by index.
+        pub palette: Vec<String>,
+        pub visible: bool,
+        /// Palette index of every cell, row by row.
+        cells: Vec<Option<usize>>,
+}
+
+impl TileMap
+{
+        /// `width` by `height` empty cells of `tile_size`, starting at the
+        /// world origin.
+        pub fn new(
+                width: u32,
+                height: u32,
+                tile_size: f32,
+        ) -> Self
+        {
+                Self {
+                        width,
+                        height,
+                        tile_size,
+                        origin: Point3::new(0.0, 0.0, 0.0),
+                        palette: Vec::new(),
+                        visible: true,
+                        cells: vec![None; width as usize * height as usize],
+                }
+        }
+
+        /// The same map with cell `(0, 0)` at `origin`.
+        pub fn with_origin(
+                mut self,
+                origin: Point3<f32>,
+        ) -> Self
+        {
+                self.origin = origin;
+                self
+        }
+
+        /// The same map centered on `center` instead.
+        pub fn centered_on(
+                self,
+                center: Point3<f32>,
+        ) -> Self
+        {
+                let half = Vector3::new(self.width as f32, 0.0, self.height as f32)
+                        * self.tile_size
+                        * 0.5;
+
+                self.with_origin(center - half)
+        }
+
+        pub fn contains(
+                &self,
+                x: u32,
+                y: u32,
+        ) -> bool
+        {
+                x < self.width && y < self.height
+        }
+
+        /// The palette index of the cell `(x, y)`, `None` when it's empty
+        /// or outside the map.
+        pub fn get(
+                &self,
+                x: u32,
+                y: u32,
+        ) -> Option<usize>
+        {
+                self.index(x, y).and_then(|i| self.cells[i])
+        }
+
+        /// Sets the cell `(x, y)` to the palette model `tile`, `None`
+        /// empties it. `false` when it's outside the map.
+        pub fn set(
+                &mut self,
+                x: u32,
+                y: u32,
+                tile: Option<usize>,
+        ) -> bool
+        {
+                match self.index(x, y)
+                {
+                        Some(i) =>
+                        {
+                                self.cells[i] = tile;
+                                true
+                        }
+                        None => false,
+                }
+        }
+
+        /// Sets every cell to `tile`.
+        pub fn fill(
+                &mut self,
+                tile: Option<usize>,
+        )
+        {
+                self.cells.fill(tile);
+        }
+
+        /// The cells with a tile and their palette index.
+        pub fn tiles(&self) -> impl Iterator<Item = ((u32, u32), usize)> + '_
+        {
+                self.cells.iter().enumerate().filter_map(|(i, tile)| {
+                        tile.map(|tile| ((i as u32 % self.width, i as u32 / self.width), tile))
+                })
+        }
+
+        /// Center of the cell `(x, y)` in world space, also for cells
+        /// outside the map.
+        pub fn cell_to_world(
+                &self,
+                x: i32,
+                y: i32,
+        ) -> Point3<f32>
+        {
+                self.origin + Vector3::new(x as f32 + 0.5, 0.0, y as f32 + 0.5) * self.tile_size
+        }
+
+        /// The cell `point` is over or under, `None` outside the map.
+        pub fn world_to_cell(
+                &self,
+                point: Point3<f32>,
+        ) -> Option<(u32, u32)>
+        {
+                let (x, y) = self.world_to_cell_unbounded(point);
+
+                (x >= 0 && y >= 0 && self.contains(x as u32, y as u32))
+                        .then_some((x as u32, y as u32))
+        }
+
+        /// The cell `point` is over or under, counting on past the edges of
+        /// the map.
+        pub fn world_to_cell_unbounded(
+                &self,
+                point: Point3<f32>,
+        ) -> (i32, i32)
+        {
+                let local = (point - self.origin) / self.tile_size;
+
+                (local.x.floor() as i32, local.z.floor() as i32)
+        }
+
+        fn index(
+                &self,
+                x: u32,
+                y: u32,
+        ) -> Option<usize>
+        {
+                self.contains(x, y)
+                        .then(|| y as usize * self.width as usize + x as usize)
+        }
+
+        /// Translations to the centers of the cells by palette index.
+        fn instances(&self) -> Vec<Vec<TileInstance>>
+        {
+                let mut instances = vec![Vec::new(); self.palette.len()];
+
+                for ((x, y), tile) in self.tiles()
+                {
+                        if let Some(instances) = instances.get_mut(tile)
+                        {
+                                let center = self.cell_to_world(x as i32, y as i32);
+
+                                instances.push(Transform::from_position(center).into());
+                        }
+                }
+
+                instances
+        }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct TileInstance
+{
+        cell: [[f32; 4]; 4],
+}
+
+impl From<Transform> for TileInstance
+{
+        fn from(transform: Transform) -> Self
+        {
+                Self {
+                        cell: transform.matrix().into(),
+                }
+        }
+}
+
+impl Vertex for TileInstance
+{
+        fn desc() -> wgpu::VertexBufferLayout<'static>
+        {
+                use std::mem;
+                wgpu::VertexBufferLayout {
+                        array_stride: mem::size_of::<TileInstance>() as wgpu::BufferAddress,
+                        step_mode: wgpu::VertexStepMode::Instance,
+                        attributes: &[
+                                wgpu::VertexAttribute {
+                                        offset: 0,
+                                        shader_location: 5,
+                                        format: wgpu::VertexFormat::Float32x4,
+                                },
+                                wgpu::VertexAttribute {
+                                        offset: mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
+                                        shader_location: 6,
+                                        format: wgpu::VertexFormat::Float32x4,
+                                },
+                                wgpu::VertexAttribute {
+                                        offset: mem::size_of::<[f32; 8]>() as wgpu::BufferAddress,
+                                        shader_location: 7,
+                                        format: wgpu::VertexFormat::Float32x4,
+                                },
+                                wgpu::VertexAttribute {
+                                        offset: mem::size_of::<[f32; 12]>() as wgpu::BufferAddress,
+                                        shader_location: 8,
+                                        format: wgpu::VertexFormat::Float32x4,
+                                },
+                        ],
+                }
+        }
+}
+
+/// The tile maps by name, owned by the [`Engine`].
+#[derive(Debug, Default)]
+pub struct TileMaps
+{
+        maps: BTreeMap<String, TileMap>,
+        /// Changed with every edit, the [`TileMapPass`] rebuilds its
+        /// instances when it differs.
+        revision: u64,
+}
+
+impl TileMaps
+{
+        pub fn new() -> Self
+        {
+                Self::default()
+        }
+
+        /// Adds `map`, replacing the map `name`.
+        pub fn insert(
+                &mut self,
+                name: &str,
+                map: TileMap,
+        )
+        {
+                self.maps.insert(name.to_string(), map);
+                self.revision += 1;
+        }
+
+        pub fn remove(
+                &mut self,
+                name: &str,
+        ) -> Option<TileMap>
+        {
+                self.revision += 1;
+                self.maps.remove(name)
+        }
+
+        pub fn get(
+                &self,
+                name: &str,
+        ) -> Option<&TileMap>
+        {
+                self.maps.get(name)
+        }
+
+        /// The map `name`, redrawn on the next frame.
+        pub fn get_mut(
+                &mut self,
+                name: &str,
+        ) -> Option<&mut TileMap>
+        {
+                self.revision += 1;
+                self.maps.get_mut(name)
+        }
+
+        pub fn revision(&self) -> u64
+        {
+                self.revision
+        }
+
+        /// Handles of the models in the palettes of the visible maps.
+        pub fn palette_models(&self) -> impl Iterator<Item = &str>
+        {
+                self.maps
+                        .values()
+                        .filter(|m| m.visible)
+                        .flat_map(|m| m.palette.iter().map(String::as_str))
+        }
+
+        /// Instances of every palette model of the visible maps.
+        fn batches(&self) -> Vec<(String, Vec<TileInstance>)>
+        {
+                self.maps
+                        .values()
+                        .filter(|m| m.visible)
+                        .flat_map(|m| m.palette.iter().cloned().zip(m.instances()))
+                        .filter(|(_, instances)| !instances.is_empty())
+                        .collect()
+        }
+}
+
+/// One palette model of a map, drawn once per cell showing it.
+#[derive(Debug)]
+struct TileBatch
+{
+        model: String,
+        instance_buffer: wgpu::Buffer,
+        count: u32,
+}
+
+/// Draws the [`TileMaps`] after the geometry pass.
+#[derive(Debug)]
+pub struct TileMapPass
+{
+        pub name: String,
+        pub enabled: bool,
+        /// Revision of the maps `batches` were built from.
+        revision: Option<u64>,
+        /// Instances waiting for their buffers.
+        pending: Option<Vec<(String, Vec<TileInstance>)>>,
+        batches: Vec<TileBatch>,
+}
+
+impl TileMapPass
+{
+        pub fn new() -> Self
+        {
+                Self {
+                        name: "tilemap_pass".to_string(),
+                        enabled: true,
+                        revision: None,
+                        pending: None,
+                        batches: Vec::new(),
+                }
+        }
+
+        /// Rebuilds the instances when `maps` changed since the last call.
+        pub fn sync(
+                &mut self,
+                maps: &TileMaps,
+        )
+        {
+                if self.revision != Some(maps.revision())
+                {
+                        self.revision = Some(maps.revision());
+                        self.pending = Some(maps.batches());
+                }
+        }
+}
+
+impl Default for TileMapPass
+{
+        fn default() -> Self
+        {
+                Self::new()
+        }
+}
+
+impl RenderPass for TileMapPass
+{
+        fn name(&self) -> &str
+        {
+                self.name.as_str()
+        }
+
+        fn as_any(&self) -> &dyn Any
+        {
+                self
+        }
+
+        fn as_any_mut(&mut self) -> &mut dyn Any
+        {
+                self
+        }
+
+        #[cfg(feature = "ui")]
+        fn ui(
+                &mut self,
+                ui: &mut egui::Ui,
+        )
+        {
+                let tiles: u32 = self.batches.iter().map(|b| b.count).sum();
+
+                egui::CollapsingHeader::new(&self.name)
+                        .default_open(true)
+                        .show(ui, |ui| {
+                                ui.label(format!("Tiles: {}", tiles));
+                                ui.label(format!("Batches: {}", self.batches.len()));
+                        });
+        }
+
+        fn enabled(&mut self) -> bool
+        {
+                self.enabled
+        }
+
+        fn attachments(&self) -> Vec<Attachment>
+        {
+                vec![
+                        Attachment::new(SURFACE, AttachmentLoad::Load, true),
+                        Attachment::new(DEPTH, AttachmentLoad::Load, true),
+                ]
+        }
+
+        fn set_enabled(
+                &mut self,
+                value: bool,
+        )
+        {
+                self.enabled = value;
+        }
+
+        fn record(
+                &mut self,
+                view: &wgpu::TextureView,
+                encoder: &mut wgpu::CommandEncoder,
+                camera: &wgpu::BindGroup,
+                pipeline_manager: &PipelineManager,
+                depth_texture: &Texture,
+                models: Option<&HashMap<String, Model>>,
+                device: &wgpu::Device,
+                #[allow(unused_variables)] queue: &wgpu::Queue,
+        )
+        {
+                if let Some(pending) = self.pending.take()
+                {
+                        self.batches = pending
+                                .into_iter()
+                                .map(|(model, instances)| TileBatch {
+                                        model,
+                                        instance_buffer: device.create_buffer_init(
+                                                &wgpu::util::BufferInitDescriptor {
+                                                        label: Some("Tile Instance Buffer"),
+                                                        contents: bytemuck::cast_slice(&instances),
+                                                        usage: wgpu::BufferUsages::VERTEX,
+                                                },
+                                        ),
+                                        count: instances.len() as u32,
+                                })
+                                .collect();
+                }
+
+                let Some(models) = models
+                else
+                {
+                        return;
+                };
+
+                if self.batches.is_empty()
+                {
+                        return;
+                }
+
+                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        label: Some(&self.name),
+                        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                                view,
+                                resolve_target: None,
+                                ops: wgpu::Operations {
+                                        load: wgpu::LoadOp::Load,
+                                        store: wgpu::StoreOp::Store,
+                                },
+                        })],
+                        depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                                view: &depth_texture.view,
+                                depth_ops: Some(wgpu::Operations {
+                                        load: wgpu::LoadOp::Load,
+                                        store: wgpu::StoreOp::Store,
+                                }),
+                                stencil_ops: None,
+                        }),
+                        occlusion_query_set: None,
+                        timestamp_writes: None,
+                });
+
+                pipeline_manager.apply_viewport(&mut render_pass);
+                render_pass.set_pipeline(pipeline_manager.get(PipelineKind::Tiles));
+                render_pass.set_bind_group(0, camera, &[]);
+
+                for batch in &self.batches
+                {
+                        // Still loading.
+                        let Some(model) = models.get(&batch.model)
+                        else
+                        {
+                                continue;
+                        };
+
+                        render_pass.set_bind_group(
+                                3,
+                                &model.create_model_transform_bind_group(device),
+                                &[],
+                        );
+                        render_pass.set_vertex_buffer(1, batch.instance_buffer.slice(..));
+
+                        for mesh in &model.meshes
+                        {
+                                let material = &model.materials[mesh.material];
+
+                                render_pass.set_bind_group(1, &mesh.transform_bind_group, &[]);
+                                render_pass.set_bind_group(2, &material.material_bind_group, &[]);
+                                render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+                                render_pass.set_index_buffer(
+                                        mesh.index_buffer.slice(..),
+                                        wgpu::IndexFormat::Uint32,
+                                );
+                                render_pass.draw_indexed(0..mesh.num_elements, 0, 0..batch.count);
+                        }
+                }
+        }
+}
+
+#[cfg(feature = "runtime")]
+impl Engine
+{
+        /// Draws `map` until it's removed, replacing the map `name`.
+        pub fn add_tilemap(
+                &mut self,
+                name: &str,
+                map: TileMap,
+        )
+        {
+                self.tilemaps.insert(name, map);
+        }
+
+        pub fn remove_tilemap(
+                &mut self,
+                name: &str,
+        ) -> Option<TileMap>
+        {
+                self.tilemaps.remove(name)
+        }
+
+        pub fn tilemap(
+                &self,
+                name: &str,
+        ) -> Option<&TileMap>
+        {
+                self.tilemaps.get(name)
+        }
+
+        /// The map `name` to change, e.g. to set cells.
+        pub fn tilemap_mut(
+                &mut self,
+                name: &str,
+        ) -> Option<&mut TileMap>
+        {
+                self.tilemaps.get_mut(name)
+        }
+}
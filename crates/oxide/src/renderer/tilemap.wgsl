@@ -0,0 +1,40 @@
+// The models of a tile map, one instance per cell. See `tilemap.rs`.
+
+#include "common.wgsl"
+
+// Translation to the center of the cell.
+struct InstanceInput {
+    @location(5) cell_0: vec4<f32>,
+    @location(6) cell_1: vec4<f32>,
+    @location(7) cell_2: vec4<f32>,
+    @location(8) cell_3: vec4<f32>,
+};
+
+@vertex
+fn vs_tiles(
+    model: VertexInput,
+    instance: InstanceInput,
+) -> VertexOutput {
+    var out: VertexOutput;
+
+    let cell = mat4x4<f32>(instance.cell_0, instance.cell_1, instance.cell_2, instance.cell_3);
+    let tile = cell * model_transform.model * transform.model;
+
+    let world_position = tile * vec4<f32>(model.position, 1.0);
+    out.clip_position = camera.view_proj * world_position;
+    out.tex_coords = model.tex_coords;
+    out.world_position = world_position.xyz;
+
+    let normal = tile * vec4<f32>(model.normal, 0.0);
+    out.world_normal = normal.xyz;
+
+    return out;
+}
+
+@fragment
+fn fs_tiles(in: VertexOutput) -> @location(0) vec4<f32> {
+    let texture_color = textureSampleBias(base_color_texture, base_color_sampler, in.tex_coords, material_props.lod_bias);
+    let final_color = texture_color * material_props.base_color_factor;
+
+    return vec4<f32>(shade(final_color.rgb, in.world_position, in.world_normal), final_color.a);
+}
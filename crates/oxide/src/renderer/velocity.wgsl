@@ -0,0 +1,68 @@
+// Screen space motion of every pixel since the last frame, for TAA and
+// motion blur. See `temporal.rs`.
+
+struct Environment {
+    sun_direction: vec4<f32>,
+    sun_color: vec4<f32>,
+    ambient: vec4<f32>,
+    fog: vec4<f32>,
+    exposure: vec4<f32>,
+};
+
+struct CameraUniform {
+    view_pos: vec4<f32>,
+    view_proj: mat4x4<f32>,
+    environment: Environment,
+    previous_view_proj: mat4x4<f32>,
+    // Offset of this frame's `view_proj` in NDC, in `xy`.
+    jitter: vec4<f32>,
+};
+
+struct Transform {
+    model: mat4x4<f32>,
+};
+
+@group(0) @binding(0) var<uniform> camera: CameraUniform;
+@group(1) @binding(0) var<uniform> mesh_transform: Transform;
+@group(2) @binding(0) var<uniform> model_transform: Transform;
+@group(3) @binding(0) var<uniform> previous_model_transform: Transform;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) current: vec4<f32>,
+    // Where the vertex was last frame.
+    @location(1) previous: vec4<f32>,
+    // Where it would have been if only the camera moved.
+    @location(2) camera_previous: vec4<f32>,
+};
+
+@vertex
+fn vs_main(@location(0) position: vec3<f32>) -> VertexOutput {
+    var out: VertexOutput;
+
+    let local = mesh_transform.model * vec4<f32>(position, 1.0);
+    let world = model_transform.model * local;
+    let previous_world = previous_model_transform.model * local;
+
+    out.clip_position = camera.view_proj * world;
+    out.current = out.clip_position;
+    out.previous = camera.previous_view_proj * previous_world;
+    out.camera_previous = camera.previous_view_proj * world;
+
+    return out;
+}
+
+// NDC difference as a difference of texture coordinates.
+fn to_uv(delta: vec2<f32>) -> vec2<f32> {
+    return delta * vec2<f32>(0.5, -0.5);
+}
+
+// Full motion in `xy`, the camera's part of it in `zw`.
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let current = in.current.xy / in.current.w - camera.jitter.xy;
+    let previous = in.previous.xy / in.previous.w;
+    let camera_previous = in.camera_previous.xy / in.camera_previous.w;
+
+    return vec4<f32>(to_uv(current - previous), to_uv(current - camera_previous));
+}
@@ -0,0 +1,98 @@
+//! Where on the surface the scene is drawn.
+//!
+//! With [`CameraConfig::locked_aspect`](crate::camera::CameraConfig::locked_aspect)
+//! the scene keeps its aspect ratio whatever the window's instead of being
+//! stretched. The rest of the surface is left black, bars above and below
+//! on a wider window (letterboxing), left and right on a narrower one
+//! (pillarboxing). The scene passes draw inside it with
+//! [`PipelineManager::apply_viewport`](crate::renderer::pipeline::PipelineManager::apply_viewport),
+//! full screen passes reading the scene map their pixels with
+//! [`PipelineManager::viewport_rect`](crate::renderer::pipeline::PipelineManager::viewport_rect).
+
+/// A rectangle of the surface in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Viewport
+{
+        pub x: u32,
+        pub y: u32,
+        pub width: u32,
+        pub height: u32,
+}
+
+impl Viewport
+{
+        /// All of a `width` by `height` surface.
+        pub fn full(
+                width: u32,
+                height: u32,
+        ) -> Self
+        {
+                Self {
+                        x: 0,
+                        y: 0,
+                        width,
+                        height,
+                }
+        }
+
+        /// The largest rectangle of `aspect`, width over height, centered on
+        /// a `width` by `height` surface.
+        pub fn fit(
+                width: u32,
+                height: u32,
+                aspect: f32,
+        ) -> Self
+        {
+                if width == 0 || height == 0 || aspect <= 0.0 || !aspect.is_finite()
+                {
+                        return Self::full(width, height);
+                }
+
+                let surface = width as f32 / height as f32;
+
+                // Whole pixels, so the scissor matches the viewport.
+                let (fit_width, fit_height) = if surface > aspect
+                {
+                        (((height as f32 * aspect).round() as u32).clamp(1, width), height)
+                }
+                else
+                {
+                        (width, ((width as f32 / aspect).round() as u32).clamp(1, height))
+                };
+
+                Self {
+                        x: (width - fit_width) / 2,
+                        y: (height - fit_height) / 2,
+                        width: fit_width,
+                        height: fit_height,
+                }
+        }
+
+        pub fn aspect(&self) -> f32
+        {
+                self.width as f32 / self.height.max(1) as f32
+        }
+
+        /// Offset and size as floats, how shaders take it.
+        pub fn rect(&self) -> [f32; 4]
+        {
+                [
+                        self.x as f32,
+                        self.y as f32,
+                        self.width as f32,
+                        self.height as f32,
+                ]
+        }
+
+        /// Draws only inside the rectangle in `render_pass`.
+        pub fn apply(
+                &self,
+                render_pass: &mut wgpu::RenderPass,
+        )
+        {
+                let [x, y, width, height] = self.rect();
+
+                render_pass.set_viewport(x, y, width, height, 0.0, 1.0);
+                render_pass.set_scissor_rect(self.x, self.y, self.width, self.height);
+        }
+}
@@ -0,0 +1,318 @@
+//! Deterministic input recording and playback.
+//!
+//! A [`Replay`] stores the pressed keys of every engine tick and the seed of
+//! [`Engine::rng`]. Playing it back seeds the RNG again and feeds the keys
+//! to [`Engine::pressed_keys`] tick by tick, ignoring the keyboard, so game
+//! logic that only reads input and randomness through the engine runs the
+//! same way again. That makes demo replays and reproducible bug reports
+//! possible.
+//!
+//! Timers and coroutines of the [`Scheduler`](crate::scheduler::Scheduler)
+//! count ticks too, so [`Engine::after`] and friends fire on the same tick
+//! during playback.
+//!
+//! The replay doesn't capture the game's own state, games should reset it on
+//! [`ReplayEvent::Started`], published on the
+//! [`EventBus`](crate::events::EventBus) before the first tick is fed.
+//!
+//! Replays are stored as TOML, see [`Replay::to_toml`].
+
+use crate::engine::Engine;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::time::Duration;
+use winit::keyboard::KeyCode;
+
+/// Bumped when the file format changes.
+const VERSION: u32 = 1;
+
+/// Input of a recorded session.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Replay
+{
+        pub version: u32,
+
+        /// Seed [`Engine::rng`] was reset to when the recording started.
+        pub seed: u64,
+
+        /// Ticks per second of the recording, restored on playback.
+        pub tps: u16,
+
+        /// Number of recorded ticks.
+        pub ticks: u64,
+
+        /// The pressed keys, stored only for the ticks where they changed.
+        pub input: Vec<TickInput>,
+
+        /// Hashes of every tick when a determinism audit ran while
+        /// recording, playback is compared against them, see
+        /// [`crate::audit`].
+        #[serde(default)]
+        pub hashes: Option<Vec<u64>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TickInput
+{
+        /// Tick since the start of the recording.
+        pub tick: u64,
+
+        pub pressed: Vec<KeyCode>,
+}
+
+impl Replay
+{
+        pub fn to_toml(&self) -> Result<String>
+        {
+                Ok(toml::to_string(self)?)
+        }
+
+        pub fn from_toml(source: &str) -> Result<Self>
+        {
+                let replay: Self = toml::from_str(source)?;
+
+                if replay.version != VERSION
+                {
+                        anyhow::bail!(
+                                "Replay version {} isn't supported, expected {}",
+                                replay.version,
+                                VERSION
+                        );
+                }
+
+                Ok(replay)
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        pub fn save(
+                &self,
+                path: impl AsRef<std::path::Path>,
+        ) -> Result<()>
+        {
+                std::fs::write(path, self.to_toml()?)?;
+
+                Ok(())
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self>
+        {
+                Self::from_toml(&std::fs::read_to_string(path)?)
+        }
+
+        /// Length of the recording.
+        pub fn duration(&self) -> Duration
+        {
+                Duration::from_secs_f64(self.ticks as f64 / self.tps.max(1) as f64)
+        }
+}
+
+/// Published on the event bus when playback starts and ends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayEvent
+{
+        Started,
+        Finished,
+}
+
+#[derive(Debug, Default)]
+enum Mode
+{
+        #[default]
+        Off,
+        Recording(Replay),
+        Playing
+        {
+                replay: Replay,
+                /// Index of the next entry of `replay.input`.
+                cursor: usize,
+        },
+}
+
+/// Records or plays back input, owned by the [`Engine`].
+#[derive(Debug, Default)]
+pub struct ReplaySystem
+{
+        mode: Mode,
+
+        /// Ticks since recording or playback started.
+        tick: u64,
+
+        /// Keys of the last recorded entry.
+        last_pressed: Vec<KeyCode>,
+}
+
+impl ReplaySystem
+{
+        pub fn new() -> Self
+        {
+                Self::default()
+        }
+
+        pub fn is_recording(&self) -> bool
+        {
+                matches!(self.mode, Mode::Recording(_))
+        }
+
+        pub fn is_playing(&self) -> bool
+        {
+                matches!(self.mode, Mode::Playing { .. })
+        }
+
+        /// Ticks since recording or playback started.
+        pub fn tick(&self) -> u64
+        {
+                self.tick
+        }
+
+        /// Records or feeds the input of one tick.
+        ///
+        /// Called by the engine every time the tick advances, before the
+        /// behaviors see it.
+        pub(crate) fn update(engine: &mut Engine)
+        {
+                let tick = engine.replay.tick;
+
+                match &mut engine.replay.mode
+                {
+                        Mode::Off => return,
+                        Mode::Recording(replay) =>
+                        {
+                                let mut pressed: Vec<KeyCode> =
+                                        engine.pressed_keys.iter().copied().collect();
+                                pressed.sort_by_key(|k| *k as u32);
+
+                                if tick == 0 || pressed != engine.replay.last_pressed
+                                {
+                                        replay.input.push(TickInput {
+                                                tick,
+                                                pressed: pressed.clone(),
+                                        });
+
+                                        engine.replay.last_pressed = pressed;
+                                }
+
+                                replay.ticks = tick + 1;
+                        }
+                        Mode::Playing {
+                                replay,
+                                cursor,
+                        } =>
+                        {
+                                if tick >= replay.ticks
+                                {
+                                        log::info!("Replay finished after {} ticks", tick);
+
+                                        engine.replay.mode = Mode::Off;
+                                        engine.pressed_keys.clear();
+                                        engine.events.publish(ReplayEvent::Finished);
+
+                                        return;
+                                }
+
+                                while let Some(input) = replay.input.get(*cursor)
+                                        && input.tick <= tick
+                                {
+                                        engine.pressed_keys =
+                                                input.pressed.iter().copied().collect();
+                                        *cursor += 1;
+                                }
+                        }
+                }
+
+                engine.replay.tick += 1;
+        }
+}
+
+impl Engine
+{
+        /// Starts recording input with a fresh seed for [`Engine::rng`].
+        pub fn start_recording(&mut self)
+        {
+                self.start_recording_with_seed(rand::random());
+        }
+
+        pub fn start_recording_with_seed(
+                &mut self,
+                seed: u64,
+        )
+        {
+                log::info!("Recording replay, seed {}", seed);
+
+                self.rng.reseed(seed);
+
+                self.replay.tick = 0;
+                self.replay.last_pressed.clear();
+                self.replay.mode = Mode::Recording(Replay {
+                        version: VERSION,
+                        seed,
+                        tps: self.tps,
+                        ticks: 0,
+                        input: Vec::new(),
+                        hashes: None,
+                });
+
+                self.audit_recording();
+        }
+
+        /// Stops recording and returns what was recorded.
+        pub fn stop_recording(&mut self) -> Option<Replay>
+        {
+                match std::mem::take(&mut self.replay.mode)
+                {
+                        Mode::Recording(mut replay) =>
+                        {
+                                log::info!("Recorded {} ticks", replay.ticks);
+
+                                replay.hashes = self.audit_hashes();
+
+                                Some(replay)
+                        }
+                        mode =>
+                        {
+                                self.replay.mode = mode;
+
+                                None
+                        }
+                }
+        }
+
+        /// Plays `replay` back from the next tick on. Stops a recording
+        /// without keeping it.
+        pub fn play_replay(
+                &mut self,
+                replay: Replay,
+        )
+        {
+                log::info!("Playing replay, {} ticks at {} tps", replay.ticks, replay.tps);
+
+                self.rng.reseed(replay.seed);
+
+                self.tps = replay.tps;
+                self.tps_interval = Duration::from_secs_f32(1.0 / replay.tps.max(1) as f32);
+
+                self.pressed_keys = HashSet::new();
+
+                self.audit_replay(&replay);
+
+                self.replay.tick = 0;
+                self.replay.mode = Mode::Playing {
+                        replay,
+                        cursor: 0,
+                };
+
+                self.events.publish(ReplayEvent::Started);
+        }
+
+        /// Stops playback early, the keyboard is used again.
+        pub fn stop_replay(&mut self)
+        {
+                if self.replay.is_playing()
+                {
+                        self.replay.mode = Mode::Off;
+                        self.pressed_keys.clear();
+                        self.events.publish(ReplayEvent::Finished);
+                }
+        }
+}
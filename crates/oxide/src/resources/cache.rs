@@ -0,0 +1,236 @@
+//! Decoded GLB files kept on disk between runs, so heavy scenes don't
+//! decode their textures again on every start.
+//!
+//! An entry holds what decoding a file gives: the glTF document, its
+//! buffers and its images as raw pixels. It is named after a hash of the
+//! file's contents, an edited file gets a new entry and entries of the
+//! same contents are shared by every path and model. Building the meshes
+//! and materials from the decoded file is cheap and done on every load,
+//! so [`ImportOptions`](crate::resources::import::ImportOptions) don't
+//! change the entry.
+//!
+//! The cache is turned on with
+//! [`EngineBuilder::with_import_cache`](crate::engine::EngineBuilder::with_import_cache),
+//! or per model with
+//! [`ImportOptions::cache`](crate::resources::import::ImportOptions::cache).
+//! Old entries aren't removed, delete the directory to clear it. Native
+//! only.
+
+use anyhow::{Context, Result};
+use std::hash::Hasher;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Start of every entry.
+const MAGIC: &[u8; 4] = b"OXIC";
+
+/// Bumped when the file format changes.
+const VERSION: u32 = 1;
+
+/// What decoding a GLB gives.
+pub type Decoded = (gltf::Document, Vec<gltf::buffer::Data>, Vec<gltf::image::Data>);
+
+/// The entry of a file with the contents `bytes`.
+///
+/// The hash comes from the standard library and may change with the
+/// toolchain, which only misses entries.
+pub fn entry_path(
+        dir: &Path,
+        bytes: &[u8],
+) -> PathBuf
+{
+        let mut hasher = std::hash::DefaultHasher::new();
+        hasher.write(bytes);
+
+        dir.join(format!("{:016x}-{}.bin", hasher.finish(), bytes.len()))
+}
+
+/// The decoded file with the contents `bytes`, `None` without an entry. A
+/// broken entry is logged and ignored.
+pub fn read(
+        dir: &Path,
+        bytes: &[u8],
+) -> Option<Decoded>
+{
+        let path = entry_path(dir, bytes);
+
+        if !path.exists()
+        {
+                return None;
+        }
+
+        match read_entry(&path)
+        {
+                Ok(decoded) =>
+                {
+                        log::info!("Loaded {} from the import cache", path.display());
+                        Some(decoded)
+                }
+                Err(e) =>
+                {
+                        log::warn!("Ignoring import cache entry {}: {:#}", path.display(), e);
+                        None
+                }
+        }
+}
+
+/// Keeps the `decoded` file with the contents `bytes`. Written next to the
+/// entry first, so an interrupted write leaves no broken entry.
+pub fn write(
+        dir: &Path,
+        bytes: &[u8],
+        decoded: &Decoded,
+) -> Result<()>
+{
+        std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+
+        let path = entry_path(dir, bytes);
+        let partial = path.with_extension("partial");
+
+        write_entry(&partial, decoded)
+                .with_context(|| format!("Failed to write {}", partial.display()))?;
+        std::fs::rename(&partial, &path)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+
+        log::info!("Wrote {} to the import cache", path.display());
+
+        Ok(())
+}
+
+fn write_entry(
+        path: &Path,
+        (doc, buffers, images): &Decoded,
+) -> Result<()>
+{
+        let mut file = BufWriter::new(std::fs::File::create(path)?);
+
+        file.write_all(MAGIC)?;
+        file.write_all(&VERSION.to_le_bytes())?;
+
+        write_bytes(&mut file, &serde_json::to_vec(doc.as_json())?)?;
+
+        file.write_all(&(buffers.len() as u32).to_le_bytes())?;
+
+        for buffer in buffers
+        {
+                write_bytes(&mut file, &buffer.0)?;
+        }
+
+        file.write_all(&(images.len() as u32).to_le_bytes())?;
+
+        for image in images
+        {
+                file.write_all(&[format_id(image.format)])?;
+                file.write_all(&image.width.to_le_bytes())?;
+                file.write_all(&image.height.to_le_bytes())?;
+                write_bytes(&mut file, &image.pixels)?;
+        }
+
+        file.flush()?;
+
+        Ok(())
+}
+
+fn read_entry(path: &Path) -> Result<Decoded>
+{
+        let mut file = BufReader::new(std::fs::File::open(path)?);
+
+        let mut magic = [0; 4];
+        file.read_exact(&mut magic)?;
+
+        if &magic != MAGIC
+        {
+                anyhow::bail!("Not an import cache entry");
+        }
+
+        let version = read_u32(&mut file)?;
+
+        if version != VERSION
+        {
+                anyhow::bail!("Version {} instead of {}", version, VERSION);
+        }
+
+        let json = serde_json::from_slice(&read_bytes(&mut file)?)?;
+        let doc = gltf::Document::from_json(json)?;
+
+        let buffers = (0..read_u32(&mut file)?)
+                .map(|_| read_bytes(&mut file).map(gltf::buffer::Data))
+                .collect::<Result<_>>()?;
+
+        let images = (0..read_u32(&mut file)?)
+                .map(|_| {
+                        let mut format = [0; 1];
+                        file.read_exact(&mut format)?;
+
+                        Ok(gltf::image::Data {
+                                format: format_from_id(format[0])?,
+                                width: read_u32(&mut file)?,
+                                height: read_u32(&mut file)?,
+                                pixels: read_bytes(&mut file)?,
+                        })
+                })
+                .collect::<Result<_>>()?;
+
+        Ok((doc, buffers, images))
+}
+
+fn write_bytes(
+        file: &mut impl Write,
+        bytes: &[u8],
+) -> Result<()>
+{
+        file.write_all(&(bytes.len() as u64).to_le_bytes())?;
+        file.write_all(bytes)?;
+
+        Ok(())
+}
+
+fn read_bytes(file: &mut impl Read) -> Result<Vec<u8>>
+{
+        let mut len = [0; 8];
+        file.read_exact(&mut len)?;
+
+        let mut bytes = Vec::new();
+        file.take(u64::from_le_bytes(len)).read_to_end(&mut bytes)?;
+
+        if bytes.len() as u64 != u64::from_le_bytes(len)
+        {
+                anyhow::bail!("Truncated entry");
+        }
+
+        Ok(bytes)
+}
+
+fn read_u32(file: &mut impl Read) -> Result<u32>
+{
+        let mut value = [0; 4];
+        file.read_exact(&mut value)?;
+
+        Ok(u32::from_le_bytes(value))
+}
+
+const FORMATS: [gltf::image::Format; 10] = [
+        gltf::image::Format::R8,
+        gltf::image::Format::R8G8,
+        gltf::image::Format::R8G8B8,
+        gltf::image::Format::R8G8B8A8,
+        gltf::image::Format::R16,
+        gltf::image::Format::R16G16,
+        gltf::image::Format::R16G16B16,
+        gltf::image::Format::R16G16B16A16,
+        gltf::image::Format::R32G32B32FLOAT,
+        gltf::image::Format::R32G32B32A32FLOAT,
+];
+
+fn format_id(format: gltf::image::Format) -> u8
+{
+        FORMATS.iter().position(|&f| f == format).unwrap_or(0) as u8
+}
+
+fn format_from_id(id: u8) -> Result<gltf::image::Format>
+{
+        FORMATS.get(id as usize)
+                .copied()
+                .ok_or_else(|| anyhow::anyhow!("Unknown image format {}", id))
+}
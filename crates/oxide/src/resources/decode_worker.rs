@@ -0,0 +1,166 @@
+//! Texture decoding in a web worker, see the `web-worker-decode` feature.
+//!
+//! Decoding the PNG and JPEG textures of a large GLB takes long enough on the
+//! main thread to stall the page. With the feature on, the images are handed
+//! to a worker that decodes them with `createImageBitmap` and an
+//! `OffscreenCanvas`, and the loader awaits the pixels instead. Parsing the
+//! GLB and uploading to the GPU stay on the main thread.
+//!
+//! Browsers without `OffscreenCanvas` in workers fail the load, so the
+//! feature is meant for builds that target recent browsers only.
+
+use anyhow::{Context, Result};
+use std::cell::RefCell;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Blob, BlobPropertyBag, MessageChannel, MessageEvent, Url, Worker};
+
+/// Decodes one image per message and replies on the port sent with it.
+const SCRIPT: &str = r#"
+self.onmessage = async ({ data: { bytes, mime, port } }) => {
+        try {
+                const bitmap = await createImageBitmap(new Blob([bytes], { type: mime }), {
+                        premultiplyAlpha: "none",
+                        colorSpaceConversion: "none",
+                });
+                const canvas = new OffscreenCanvas(bitmap.width, bitmap.height);
+                const context = canvas.getContext("2d");
+                context.drawImage(bitmap, 0, 0);
+                const pixels = context.getImageData(0, 0, bitmap.width, bitmap.height).data.buffer;
+                port.postMessage({ width: bitmap.width, height: bitmap.height, pixels }, [pixels]);
+        } catch (error) {
+                port.postMessage({ error: String(error) });
+        }
+};
+"#;
+
+thread_local! {
+        static WORKER: RefCell<Option<Worker>> = const { RefCell::new(None) };
+}
+
+/// Same as [`gltf::import_slice`], with the images decoded by the worker.
+pub(crate) async fn import_slice(
+        bytes: &[u8]
+) -> Result<(gltf::Document, Vec<gltf::buffer::Data>, Vec<gltf::image::Data>)>
+{
+        let gltf::Gltf {
+                document,
+                blob,
+        } = gltf::Gltf::from_slice(bytes)
+                .map_err(|e| anyhow::anyhow!("Failed to import GLB: {:?}", e))?;
+
+        let buffers = gltf::import_buffers(&document, None, blob)
+                .map_err(|e| anyhow::anyhow!("Failed to import GLB buffers: {:?}", e))?;
+
+        let mut images = Vec::new();
+
+        for image in document.images()
+        {
+                let gltf::image::Source::View {
+                        view,
+                        mime_type,
+                } = image.source()
+                else
+                {
+                        // Only embedded images are decoded in the worker.
+                        let images =
+                                gltf::import_images(&document, None, &buffers).map_err(|e| {
+                                        anyhow::anyhow!("Failed to import GLB images: {:?}", e)
+                                })?;
+
+                        return Ok((document, buffers, images));
+                };
+
+                let start = view.offset();
+                let data = &buffers[view.buffer().index()][start..start + view.length()];
+
+                images.push(decode(data, mime_type).await?);
+        }
+
+        Ok((document, buffers, images))
+}
+
+/// Decodes an encoded image into RGBA pixels on the worker.
+async fn decode(
+        bytes: &[u8],
+        mime_type: &str,
+) -> Result<gltf::image::Data>
+{
+        let worker = worker()?;
+        let channel = MessageChannel::new().map_err(js_error)?;
+        let port = channel.port1();
+
+        // Resolved with the reply's `MessageEvent`.
+        let reply = js_sys::Promise::new(&mut |resolve, _| {
+                port.set_onmessage(Some(&resolve));
+        });
+
+        let array = js_sys::Uint8Array::from(bytes);
+        let message = js_sys::Object::new();
+
+        js_sys::Reflect::set(&message, &"bytes".into(), &array).map_err(js_error)?;
+        js_sys::Reflect::set(&message, &"mime".into(), &mime_type.into()).map_err(js_error)?;
+        js_sys::Reflect::set(&message, &"port".into(), &channel.port2()).map_err(js_error)?;
+
+        worker.post_message_with_transfer(
+                &message,
+                &js_sys::Array::of2(&array.buffer(), &channel.port2()),
+        )
+        .map_err(js_error)?;
+
+        let event: MessageEvent = JsFuture::from(reply)
+                .await
+                .map_err(js_error)?
+                .dyn_into()
+                .map_err(js_error)?;
+
+        port.set_onmessage(None);
+        port.close();
+
+        let data = event.data();
+        let field = |name: &str| js_sys::Reflect::get(&data, &name.into()).map_err(js_error);
+
+        if let Some(error) = field("error")?.as_string()
+        {
+                anyhow::bail!("Failed to decode {} image: {}", mime_type, error);
+        }
+
+        Ok(gltf::image::Data {
+                pixels: js_sys::Uint8Array::new(&field("pixels")?).to_vec(),
+                format: gltf::image::Format::R8G8B8A8,
+                width: field("width")?.as_f64().context("Missing image width")? as u32,
+                height: field("height")?.as_f64().context("Missing image height")? as u32,
+        })
+}
+
+/// The decoding worker, started on first use.
+fn worker() -> Result<Worker>
+{
+        WORKER.with(|worker| {
+                if let Some(worker) = worker.borrow().as_ref()
+                {
+                        return Ok(worker.clone());
+                }
+
+                let options = BlobPropertyBag::new();
+                options.set_type("text/javascript");
+
+                let blob = Blob::new_with_str_sequence_and_options(
+                        &js_sys::Array::of1(&SCRIPT.into()),
+                        &options,
+                )
+                .map_err(js_error)?;
+
+                let url = Url::create_object_url_with_blob(&blob).map_err(js_error)?;
+                let started = Worker::new(&url).map_err(js_error)?;
+
+                *worker.borrow_mut() = Some(started.clone());
+
+                Ok(started)
+        })
+}
+
+fn js_error(error: JsValue) -> anyhow::Error
+{
+        anyhow::anyhow!("{:?}", error)
+}
@@ -0,0 +1,295 @@
+//! What went wrong with a model file that didn't import.
+//!
+//! A GLB that fails to parse, or parses without anything to draw, fails the
+//! load with an [`ImportError`] listing every problem found in the file
+//! instead of the first error of the parser. It's an `anyhow` error like the
+//! others of the loader, and can be told apart by downcasting:
+//!
+//! ```ignore
+//! match pollster::block_on(load_model("car.glb", None, &options, device, queue, materials, transforms, limits))
+//! {
+//!         Err(e) => match e.downcast_ref::<ImportError>()
+//!         {
+//!                 Some(error) if error.has(|i| matches!(i, ImportIssue::UnsupportedCompression(_))) =>
+//!                 {
+//!                         log::warn!("Export car.glb without Draco compression");
+//!                 }
+//!                 _ => log::error!("{:#}", e),
+//!         },
+//!         Ok(model) => (),
+//! }
+//! ```
+
+use std::fmt;
+
+/// Largest buffer a device is guaranteed to allow, larger ones don't
+/// upload on every adapter.
+pub const BUFFER_LIMIT: u64 = 256 << 20;
+
+/// Extensions compressing geometry or textures, which the loader can't
+/// decode.
+const COMPRESSION_EXTENSIONS: &[&str] = &[
+        "KHR_draco_mesh_compression",
+        "EXT_meshopt_compression",
+        "KHR_mesh_quantization",
+        "KHR_texture_basisu",
+        "EXT_texture_webp",
+];
+
+/// One problem of a model file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImportIssue
+{
+        /// Not a binary glTF, with the first four bytes found instead of
+        /// `glTF`.
+        NotBinary([u8; 4]),
+        /// A binary glTF version other than 2.
+        Version(u32),
+        /// Shorter than its header says, e.g. cut off by a download.
+        Truncated
+        {
+                declared: u64, actual: u64
+        },
+        /// Extensions the file requires and the loader doesn't know.
+        MissingExtensions(Vec<String>),
+        /// Compression extensions used by the file, see
+        /// [`COMPRESSION_EXTENSIONS`].
+        UnsupportedCompression(Vec<String>),
+        OversizedBuffer
+        {
+                index: usize, length: u64
+        },
+        /// A mesh primitive without positions, skipped.
+        EmptyPrimitive
+        {
+                mesh: String, primitive: usize
+        },
+        /// Nothing in the imported scenes to draw.
+        NoMeshes,
+        /// The error of the glTF parser.
+        Parser(String),
+}
+
+impl fmt::Display for ImportIssue
+{
+        fn fmt(
+                &self,
+                f: &mut fmt::Formatter<'_>,
+        ) -> fmt::Result
+        {
+                match self
+                {
+                        ImportIssue::NotBinary(magic) =>
+                        {
+                                write!(f, "not a binary glTF, starts with {:?}", magic)
+                        }
+                        ImportIssue::Version(version) =>
+                        {
+                                write!(f, "binary glTF version {}, only 2 is supported", version)
+                        }
+                        ImportIssue::Truncated {
+                                declared,
+                                actual,
+                        } => write!(f, "truncated, {} of {} bytes", actual, declared),
+                        ImportIssue::MissingExtensions(names) =>
+                        {
+                                write!(f, "requires unsupported extensions {}", names.join(", "))
+                        }
+                        ImportIssue::UnsupportedCompression(names) => write!(
+                                f,
+                                "compressed with {}, export it uncompressed",
+                                names.join(", ")
+                        ),
+                        ImportIssue::OversizedBuffer {
+                                index,
+                                length,
+                        } => write!(
+                                f,
+                                "buffer {} holds {} MiB, more than the {} MiB every GPU allows",
+                                index,
+                                length >> 20,
+                                BUFFER_LIMIT >> 20
+                        ),
+                        ImportIssue::EmptyPrimitive {
+                                mesh,
+                                primitive,
+                        } =>
+                        {
+                                write!(
+                                        f,
+                                        "primitive {} of mesh {:?} has no positions",
+                                        primitive, mesh
+                                )
+                        }
+                        ImportIssue::NoMeshes => write!(f, "no meshes to draw"),
+                        ImportIssue::Parser(error) => write!(f, "{}", error),
+                }
+        }
+}
+
+/// A model file that didn't import, with everything found wrong with it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportError
+{
+        pub path: String,
+        pub issues: Vec<ImportIssue>,
+}
+
+impl ImportError
+{
+        /// Whether one of the issues matches `predicate`.
+        pub fn has(
+                &self,
+                predicate: impl Fn(&ImportIssue) -> bool,
+        ) -> bool
+        {
+                self.issues.iter().any(predicate)
+        }
+}
+
+impl fmt::Display for ImportError
+{
+        fn fmt(
+                &self,
+                f: &mut fmt::Formatter<'_>,
+        ) -> fmt::Result
+        {
+                write!(f, "Failed to import {}", self.path)?;
+
+                for issue in &self.issues
+                {
+                        write!(f, "\n  - {}", issue)?;
+                }
+
+                Ok(())
+        }
+}
+
+impl std::error::Error for ImportError {}
+
+/// The problems of the GLB `bytes` the parser failed on with `error`.
+pub fn parse_failure(
+        path: &str,
+        bytes: &[u8],
+        error: &gltf::Error,
+) -> ImportError
+{
+        let mut issues = inspect_binary(bytes);
+        issues.push(ImportIssue::Parser(format!("{:?}", error)));
+
+        ImportError {
+                path: path.to_string(),
+                issues,
+        }
+}
+
+/// The problems of an imported `document` none of whose meshes could be
+/// read.
+pub fn no_meshes(
+        path: &str,
+        document: &gltf::Document,
+) -> ImportError
+{
+        let mut issues = inspect_document(document);
+
+        for mesh in document.meshes()
+        {
+                for primitive in mesh.primitives()
+                {
+                        if primitive.get(&gltf::Semantic::Positions).is_none()
+                        {
+                                issues.push(ImportIssue::EmptyPrimitive {
+                                        mesh: mesh.name().unwrap_or("Unnamed").to_string(),
+                                        primitive: primitive.index(),
+                                });
+                        }
+                }
+        }
+
+        issues.push(ImportIssue::NoMeshes);
+
+        ImportError {
+                path: path.to_string(),
+                issues,
+        }
+}
+
+/// The header of a GLB, and its JSON when the header is fine.
+fn inspect_binary(bytes: &[u8]) -> Vec<ImportIssue>
+{
+        let Some(header) = bytes.get(..12)
+        else
+        {
+                return vec![ImportIssue::Truncated {
+                        declared: 12,
+                        actual: bytes.len() as u64,
+                }];
+        };
+
+        let word = |at: usize| {
+                u32::from_le_bytes([header[at], header[at + 1], header[at + 2], header[at + 3]])
+        };
+        let magic = [header[0], header[1], header[2], header[3]];
+
+        if &magic != b"glTF"
+        {
+                return vec![ImportIssue::NotBinary(magic)];
+        }
+
+        if word(4) != 2
+        {
+                return vec![ImportIssue::Version(word(4))];
+        }
+
+        let mut issues = Vec::new();
+
+        if (word(8) as usize) > bytes.len()
+        {
+                issues.push(ImportIssue::Truncated {
+                        declared: word(8) as u64,
+                        actual: bytes.len() as u64,
+                });
+        }
+
+        if let Ok(gltf) = gltf::Gltf::from_slice_without_validation(bytes)
+        {
+                issues.extend(inspect_document(&gltf.document));
+        }
+
+        issues
+}
+
+/// Extensions and buffer sizes of `document`.
+fn inspect_document(document: &gltf::Document) -> Vec<ImportIssue>
+{
+        let mut issues = Vec::new();
+
+        let (compression, missing): (Vec<String>, Vec<String>) = document
+                .extensions_required()
+                .filter(|name| !gltf::json::extensions::SUPPORTED_EXTENSIONS.contains(name))
+                .map(str::to_string)
+                .partition(|name| COMPRESSION_EXTENSIONS.contains(&name.as_str()));
+
+        if !missing.is_empty()
+        {
+                issues.push(ImportIssue::MissingExtensions(missing));
+        }
+
+        if !compression.is_empty()
+        {
+                issues.push(ImportIssue::UnsupportedCompression(compression));
+        }
+
+        for buffer in document.buffers()
+        {
+                if buffer.length() as u64 > BUFFER_LIMIT
+                {
+                        issues.push(ImportIssue::OversizedBuffer {
+                                index: buffer.index(),
+                                length: buffer.length() as u64,
+                        });
+                }
+        }
+
+        issues
+}
@@ -0,0 +1,99 @@
+//! Application data of glTF nodes, meshes and materials kept through the
+//! import.
+//!
+//! glTF objects carry JSON the format doesn't define in their `extras`,
+//! e.g. the custom properties Blender exports with "Include > Custom
+//! Properties", and in extensions of their own. Both are kept as
+//! [`Extras`] on the [`Node`](crate::geometry::node::Node),
+//! [`Mesh`](crate::geometry::mesh::Mesh) and
+//! [`Material`](crate::material::Material) they were on, so level data
+//! authored with the scene survives into the engine:
+//!
+//! ```ignore
+//! let level = state.models.get("level").unwrap();
+//!
+//! for node in level.nodes_with_extra("spawn")
+//! {
+//!         let team: String = node.extras.parse("spawn").unwrap_or_default();
+//!         let at = level.node_transform(&node.name).unwrap();
+//!
+//!         // ...
+//! }
+//! ```
+//!
+//! The `extras` of scenes and root nodes also become the model's tags and
+//! values, see [`crate::metadata`].
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// The `extras` and unknown extensions of a glTF object.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Extras
+{
+        /// The `extras` JSON as it was, `Null` without any.
+        pub value: Value,
+
+        /// Extensions by name the importer doesn't use itself.
+        pub extensions: Map<String, Value>,
+}
+
+impl Extras
+{
+        pub fn new() -> Self
+        {
+                Self::default()
+        }
+
+        /// From the `extras` and extensions of a glTF object. `extras` that
+        /// aren't valid JSON are dropped.
+        pub fn from_gltf(
+                extras: &gltf::json::Extras,
+                extensions: Option<&Map<String, Value>>,
+        ) -> Self
+        {
+                let value = extras
+                        .as_ref()
+                        .and_then(|extras| serde_json::from_str(extras.get()).ok())
+                        .unwrap_or_default();
+
+                Self {
+                        value,
+                        extensions: extensions.cloned().unwrap_or_default(),
+                }
+        }
+
+        pub fn is_empty(&self) -> bool
+        {
+                self.value.is_null() && self.extensions.is_empty()
+        }
+
+        /// Property `key` of the `extras`, `None` when they aren't an
+        /// object.
+        pub fn get(
+                &self,
+                key: &str,
+        ) -> Option<&Value>
+        {
+                self.value.get(key)
+        }
+
+        /// Property `key` of the `extras` as a `T`, `None` when it's
+        /// missing or doesn't deserialize.
+        pub fn parse<T: serde::de::DeserializeOwned>(
+                &self,
+                key: &str,
+        ) -> Option<T>
+        {
+                serde_json::from_value(self.get(key)?.clone()).ok()
+        }
+
+        /// The extension `name`, e.g. `"EXT_my_game_trigger"`.
+        pub fn extension(
+                &self,
+                name: &str,
+        ) -> Option<&Value>
+        {
+                self.extensions.get(name)
+        }
+}
@@ -0,0 +1,277 @@
+//! Fixes for badly scaled or oriented assets, applied when a model is
+//! loaded instead of in behavior code:
+//!
+//! ```ignore
+//! engine.add_model_with(
+//!         "house",
+//!         "house.glb",
+//!         ImportOptions {
+//!                 scale: 0.01,
+//!                 up_axis: UpAxis::Z,
+//!                 recenter: true,
+//!                 ..ImportOptions::default()
+//!         },
+//! );
+//! ```
+//!
+//! The options are kept per model handle, models spawned again, e.g. by
+//! [`Engine::restore_model`](crate::engine::Engine::restore_model) or a save
+//! game, get them too.
+
+use crate::geometry::bounds::Aabb;
+use crate::geometry::collider::{COLLISION_PREFIX, Collider, ColliderKind};
+use crate::geometry::mesh::MeshData;
+use crate::geometry::node::Node;
+use crate::model::Billboard;
+use crate::resources::extras::Extras;
+use cgmath::{EuclideanSpace, Matrix4, Point3, SquareMatrix, Transform as _, Vector4};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// The axis pointing up in an asset, the engine's is Y.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum UpAxis
+{
+        #[default]
+        Y,
+        /// Blender and most CAD tools.
+        Z,
+}
+
+impl UpAxis
+{
+        /// Rotates the axis onto Y.
+        pub fn to_y_up(&self) -> Matrix4<f32>
+        {
+                match self
+                {
+                        UpAxis::Y => Matrix4::identity(),
+                        // (x, y, z) to (x, z, -y).
+                        UpAxis::Z => Matrix4::from_cols(
+                                Vector4::new(1.0, 0.0, 0.0, 0.0),
+                                Vector4::new(0.0, 0.0, -1.0, 0.0),
+                                Vector4::new(0.0, 1.0, 0.0, 0.0),
+                                Vector4::new(0.0, 0.0, 0.0, 1.0),
+                        ),
+                }
+        }
+}
+
+/// Scene of a file holding several, see
+/// [`gltf_scenes`](crate::resources::gltf_scenes) for those of a file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SceneSelector
+{
+        Index(usize),
+        Name(String),
+}
+
+/// How a model is changed when it's loaded, see
+/// [`Engine::add_model_with`](crate::engine::Engine::add_model_with).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImportOptions
+{
+        /// Multiplies every position, e.g. `0.01` for an asset in
+        /// centimeters.
+        pub scale: f32,
+        /// Up axis of the asset, turned into Y.
+        pub up_axis: UpAxis,
+        /// Moves the center of the model's bounds to its origin, which it
+        /// is then positioned and rotated around.
+        pub recenter: bool,
+        /// Reverses the winding of every triangle, for assets whose faces
+        /// are culled from the outside.
+        pub flip_winding: bool,
+        /// Joins the meshes sharing a material into one, fewer draw calls
+        /// for assets split into many small parts.
+        pub merge_by_material: bool,
+        /// The only scene imported, every scene of the file when `None`.
+        pub scene: Option<SceneSelector>,
+        /// Turns the model to face the camera, see
+        /// [`Model::billboard`](crate::model::Model::billboard).
+        pub billboard: Option<Billboard>,
+        /// Generates a collider of this kind for every mesh, see
+        /// [`crate::geometry::collider`].
+        pub colliders: Option<ColliderKind>,
+        /// Only the meshes of nodes named `COL_...` get colliders, and
+        /// they aren't drawn. Needs `colliders`.
+        pub collision_nodes: bool,
+        /// Directory the decoded file is kept in between runs, see
+        /// [`crate::resources::cache`]. Filled in from
+        /// [`Config::import_cache`](crate::config::Config::import_cache)
+        /// when the engine loads the model. Native only.
+        #[serde(skip)]
+        pub cache: Option<PathBuf>,
+}
+
+impl Default for ImportOptions
+{
+        fn default() -> Self
+        {
+                Self {
+                        scale: 1.0,
+                        up_axis: UpAxis::Y,
+                        recenter: false,
+                        flip_winding: false,
+                        merge_by_material: false,
+                        scene: None,
+                        billboard: None,
+                        colliders: None,
+                        collision_nodes: false,
+                        cache: None,
+                }
+        }
+}
+
+impl ImportOptions
+{
+        /// Applies the options to the meshes and nodes of a loaded model,
+        /// returning the colliders generated for it.
+        pub fn apply(
+                &self,
+                meshes: &mut Vec<MeshData>,
+                nodes: &mut [Node],
+        ) -> Vec<Collider>
+        {
+                let sources = self.collider_sources(meshes, nodes);
+
+                if self.merge_by_material
+                {
+                        *meshes = merge_by_material(std::mem::take(meshes));
+                }
+
+                let mut root = Matrix4::from_scale(self.scale) * self.up_axis.to_y_up();
+
+                if self.recenter
+                {
+                        let bounds = meshes
+                                .iter()
+                                .map(|m| m.bounds().transform(&root))
+                                .reduce(Aabb::union);
+
+                        if let Some(bounds) = bounds
+                        {
+                                root = Matrix4::from_translation(-bounds.center().to_vec()) * root;
+                        }
+                }
+
+                // The meshes following nodes stay where their nodes are.
+                for node in nodes.iter_mut().filter(|n| n.parent.is_none())
+                {
+                        node.transform = crate::transform::Transform::from_matrix(
+                                &(root * node.transform.matrix()),
+                        );
+                }
+
+                for mesh in meshes.iter_mut()
+                {
+                        mesh.transform = root * mesh.transform;
+
+                        if self.flip_winding
+                        {
+                                for triangle in mesh.indices.chunks_exact_mut(3)
+                                {
+                                        triangle.swap(1, 2);
+                                }
+                        }
+                }
+
+                let Some(kind) = self.colliders
+                else
+                {
+                        return Vec::new();
+                };
+
+                sources.into_iter()
+                        .filter_map(|(name, vertices, indices)| {
+                                let vertices =
+                                        vertices.into_iter().map(|v| root.transform_point(v)).collect();
+
+                                Collider::from_triangles(name, kind, vertices, indices)
+                        })
+                        .collect()
+        }
+
+        /// Names, model space vertices and indices of the meshes colliders
+        /// are generated for, taking the meshes of collision nodes out of
+        /// `meshes`.
+        fn collider_sources(
+                &self,
+                meshes: &mut Vec<MeshData>,
+                nodes: &[Node],
+        ) -> Vec<(String, Vec<Point3<f32>>, Vec<u32>)>
+        {
+                if self.colliders.is_none()
+                {
+                        return Vec::new();
+                }
+
+                // Named after their node, the mesh without one.
+                let name = |mesh: &MeshData| {
+                        mesh.node
+                                .and_then(|i| nodes.get(i))
+                                .map_or(&mesh.name, |n| &n.name)
+                                .clone()
+                };
+
+                let source = |mesh: &MeshData| {
+                        let vertices = mesh
+                                .vertices
+                                .iter()
+                                .map(|v| mesh.transform.transform_point(Point3::from(v.position)))
+                                .collect();
+
+                        (name(mesh), vertices, mesh.indices.clone())
+                };
+
+                if self.collision_nodes
+                {
+                        let (collision, rest): (Vec<_>, Vec<_>) = std::mem::take(meshes)
+                                .into_iter()
+                                .partition(|mesh| name(mesh).starts_with(COLLISION_PREFIX));
+                        *meshes = rest;
+
+                        collision.iter().map(source).collect()
+                }
+                else
+                {
+                        meshes.iter().map(source).collect()
+                }
+        }
+}
+
+/// One mesh per material, the node transforms baked into the vertices.
+/// Skinned and morphed meshes are kept as they are, their offsets are
+/// relative to their own vertices.
+fn merge_by_material(meshes: Vec<MeshData>) -> Vec<MeshData>
+{
+        let mut merged: BTreeMap<Option<usize>, MeshData> = BTreeMap::new();
+        let (skinned, meshes): (Vec<_>, Vec<_>) = meshes
+                .into_iter()
+                .partition(|m| m.skin.is_some() || !m.morph_targets.is_empty());
+
+        for mesh in meshes
+        {
+                let target = merged.entry(mesh.material_id).or_insert_with(|| MeshData {
+                        name: match mesh.material_id
+                        {
+                                Some(id) => format!("material_{}", id),
+                                None => "no_material".to_string(),
+                        },
+                        vertices: Vec::new(),
+                        indices: Vec::new(),
+                        material_id: mesh.material_id,
+                        transform: Matrix4::identity(),
+                        node: None,
+                        skin: None,
+                        morph_targets: Vec::new(),
+                        extras: Extras::new(),
+                });
+
+                target.append(&mesh.vertices, &mesh.indices, &mesh.transform);
+        }
+
+        merged.into_values().chain(skinned).collect()
+}
@@ -0,0 +1,851 @@
+//! Reading models, images and text from the `resources/` directory,
+//! fetched over HTTP on `wasm`. Re-exported by [`crate::resources`].
+
+use crate::geometry::mesh::MeshData;
+use crate::geometry::morph::MorphTarget;
+use crate::geometry::node::Node;
+use crate::geometry::skin::{Joint, SkinData, SkinVertex};
+use crate::material::{MaterialData, SamplerDesc};
+use crate::metadata::Metadata;
+use crate::model::{Model, ModelVertex};
+use crate::resources::ModelData;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::resources::cache;
+#[cfg(all(feature = "web-worker-decode", target_arch = "wasm32"))]
+use crate::resources::decode_worker;
+use crate::resources::diagnostics;
+use crate::resources::extras::Extras;
+use crate::resources::import::{ImportOptions, SceneSelector};
+use crate::texture::TextureLimits;
+use crate::transform::Transform;
+use cgmath::{Matrix4, Quaternion, SquareMatrix, Vector3};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_resources() -> PathBuf
+{
+        if let Ok(dir) = std::env::var("CARGO_MANIFEST_DIR")
+        {
+                let res = Path::new(&dir).join("resources");
+
+                if res.exists()
+                {
+                        return res;
+                }
+        }
+
+        if let Ok(dir) = std::env::var("EXAMPLE_RESOURCES")
+        {
+                return dir.into();
+        }
+
+        panic!("No resources folder found!");
+}
+
+/// Gets the resource path, `resources/` directory.
+///
+/// This function has 2 variants, one for `wasm` and this one for native.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn resource_path(
+        file_name: &str,
+        #[allow(unused_variables)] crate_name: Option<&str>,
+) -> PathBuf
+{
+        load_resources().join(file_name)
+}
+
+/// Gets the resource path, `resources/` directory.
+///
+/// This function has 2 variants, one for native and this one for `wasm`.
+#[cfg(target_arch = "wasm32")]
+pub fn resource_path(
+        file_name: &str,
+        #[allow(unused_variables)] crate_name: Option<&str>,
+) -> String
+{
+        if file_name.starts_with('/')
+        {
+                return file_name.to_string();
+        }
+
+        let window = web_sys::window().expect("no global `window` exists");
+
+        let location = window.location();
+
+        let hostname = location.hostname().unwrap_or_default();
+
+        let is_github_pages = hostname.contains("github.io");
+
+        let pathname = location.pathname().unwrap_or_default();
+
+        let crate_name = pathname.split('/').nth(2).unwrap_or("").to_string();
+
+        if crate_name.is_empty()
+        {
+                panic!("No crate name found!");
+        }
+
+        let mut origin = "docs";
+
+        if is_github_pages
+        {
+                origin = "oxide";
+        }
+
+        format!("/{}/{}/resources/{}", origin, crate_name, file_name)
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn load_resources() -> PathBuf
+{
+        PathBuf::from("/resources/")
+}
+
+/// Reads a text file from the `resources/` directory, fetched over HTTP on
+/// `wasm`.
+pub async fn load_string(
+        file_name: &str,
+        crate_name: Option<&str>,
+) -> anyhow::Result<String>
+{
+        #[cfg(target_arch = "wasm32")]
+        {
+                use wasm_bindgen::JsCast;
+                use web_sys::Response;
+
+                let window =
+                        web_sys::window().ok_or_else(|| anyhow::anyhow!("No window available"))?;
+
+                let full_path = resource_path(file_name, crate_name);
+
+                let resp_value =
+                        wasm_bindgen_futures::JsFuture::from(window.fetch_with_str(&full_path))
+                                .await
+                                .map_err(|e| {
+                                        anyhow::anyhow!("Failed to fetch {}: {:?}", full_path, e)
+                                })?;
+
+                let resp: Response = resp_value
+                        .dyn_into()
+                        .map_err(|e| anyhow::anyhow!("Failed to convert to Response: {:?}", e))?;
+
+                if !resp.ok()
+                {
+                        return Err(anyhow::anyhow!("HTTP error: {}", resp.status()));
+                }
+
+                let text = wasm_bindgen_futures::JsFuture::from(
+                        resp.text()
+                                .map_err(|e| anyhow::anyhow!("Failed to get text: {:?}", e))?,
+                )
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to await text: {:?}", e))?;
+
+                text.as_string()
+                        .ok_or_else(|| anyhow::anyhow!("{} is not a text file", full_path))
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+                let path = resource_path(file_name, crate_name);
+
+                std::fs::read_to_string(&path)
+                        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))
+        }
+}
+
+/// Reads and decodes an image from the `resources/` directory, fetched
+/// over HTTP on `wasm`.
+pub async fn load_image(
+        file_name: &str,
+        crate_name: Option<&str>,
+) -> anyhow::Result<image::RgbaImage>
+{
+        #[cfg(not(target_arch = "wasm32"))]
+        let path = resource_path(file_name, crate_name)
+                .to_string_lossy()
+                .to_string();
+
+        #[cfg(target_arch = "wasm32")]
+        let path = resource_path(file_name, crate_name);
+
+        let bytes = load_bytes(&path, crate_name).await?;
+
+        Ok(image::load_from_memory(&bytes)
+                .map_err(|e| anyhow::anyhow!("Failed to decode {}: {}", path, e))?
+                .to_rgba8())
+}
+
+/// Main function that is responsible for loading in 3D Models.
+///
+/// The textures are uploaded within `limits`, pass
+/// [`Engine::texture_limits`](crate::engine::Engine::texture_limits) to
+/// load like the engine does.
+#[allow(clippy::too_many_arguments)]
+pub async fn load_model(
+        file_name: &str,
+        crate_name: Option<&str>,
+        options: &ImportOptions,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        material_bind_group_layout: &wgpu::BindGroupLayout,
+        transform_bind_group_layout: &wgpu::BindGroupLayout,
+        limits: TextureLimits,
+) -> anyhow::Result<Model>
+{
+        Ok(load_model_data(file_name, crate_name, options)
+                .await?
+                .upload_with_limits(
+                        device,
+                        queue,
+                        material_bind_group_layout,
+                        transform_bind_group_layout,
+                        limits,
+                ))
+}
+
+/// The part of [`load_model`] running on the CPU, the model is uploaded
+/// with [`ModelData::upload`].
+pub async fn load_model_data(
+        file_name: &str,
+        crate_name: Option<&str>,
+        options: &ImportOptions,
+) -> anyhow::Result<ModelData>
+{
+        #[cfg(not(target_arch = "wasm32"))]
+        let path = resource_path(file_name, crate_name)
+                .to_string_lossy()
+                .to_string();
+
+        #[cfg(target_arch = "wasm32")]
+        let path = resource_path(file_name, crate_name);
+
+        let mut data = if file_name.ends_with(".obj")
+        {
+                anyhow::bail!("OBJ format not supported yet.");
+        }
+        else if file_name.ends_with(".glb")
+        {
+                load_gltf(&path, crate_name, options.scene.as_ref(), options.cache.as_deref())
+                        .await?
+        }
+        else if IMAGE_EXTENSIONS.iter().any(|e| file_name.ends_with(e))
+        {
+                load_image_plane(&path, crate_name).await?
+        }
+        else
+        {
+                anyhow::bail!("Unsupported format: {}", file_name);
+        };
+
+        data.colliders = options.apply(&mut data.meshes, &mut data.nodes);
+        data.billboard = options.billboard;
+
+        Ok(data)
+}
+
+/// Images [`load_model`] loads as a textured plane.
+const IMAGE_EXTENSIONS: &[&str] = &[".png", ".jpg", ".jpeg", ".tga"];
+
+/// A plane showing the image at `path`, its longer side 1 unit long and
+/// its front facing +Z. The back shows the image mirrored.
+async fn load_image_plane(
+        path: &str,
+        crate_name: Option<&str>,
+) -> anyhow::Result<ModelData>
+{
+        let bytes = load_bytes(path, crate_name).await?;
+        let image = image::load_from_memory(&bytes)
+                .map_err(|e| anyhow::anyhow!("Failed to decode {}: {}", path, e))?
+                .to_rgba8();
+
+        let (width, height) = image.dimensions();
+        let longer = width.max(height).max(1) as f32;
+        let (x, y) = (width as f32 / longer * 0.5, height as f32 / longer * 0.5);
+
+        let corner = |position: [f32; 3], tex_coords: [f32; 2], normal: [f32; 3]| ModelVertex {
+                position,
+                tex_coords,
+                normal,
+        };
+
+        let vertices = vec![
+                corner([-x, y, 0.0], [0.0, 0.0], [0.0, 0.0, 1.0]),
+                corner([-x, -y, 0.0], [0.0, 1.0], [0.0, 0.0, 1.0]),
+                corner([x, -y, 0.0], [1.0, 1.0], [0.0, 0.0, 1.0]),
+                corner([x, y, 0.0], [1.0, 0.0], [0.0, 0.0, 1.0]),
+                corner([-x, y, 0.0], [0.0, 0.0], [0.0, 0.0, -1.0]),
+                corner([-x, -y, 0.0], [0.0, 1.0], [0.0, 0.0, -1.0]),
+                corner([x, -y, 0.0], [1.0, 1.0], [0.0, 0.0, -1.0]),
+                corner([x, y, 0.0], [1.0, 0.0], [0.0, 0.0, -1.0]),
+        ];
+
+        let name = std::path::Path::new(path)
+                .file_stem()
+                .map_or_else(|| "image".to_string(), |s| s.to_string_lossy().to_string());
+
+        let mesh = MeshData {
+                name: name.clone(),
+                vertices,
+                indices: vec![0, 1, 2, 2, 3, 0, 4, 6, 5, 6, 4, 7],
+                material_id: Some(0),
+                transform: Matrix4::identity(),
+                node: None,
+                skin: None,
+                morph_targets: Vec::new(),
+                extras: Extras::new(),
+        };
+
+        let material = MaterialData {
+                name,
+                base_color_texture_index: Some(0),
+                metallic_factor: 0.0,
+                ..MaterialData::default()
+        };
+
+        let image = gltf::image::Data {
+                pixels: image.into_raw(),
+                format: gltf::image::Format::R8G8B8A8,
+                width,
+                height,
+        };
+
+        Ok(ModelData {
+                meshes: vec![mesh],
+                nodes: Vec::new(),
+                materials: vec![material],
+                images: vec![image],
+                billboard: None,
+                metadata: Metadata::new(),
+                colliders: Vec::new(),
+        })
+}
+
+/// A scene of a glTF file, see [`gltf_scenes`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GltfScene
+{
+        pub index: usize,
+        pub name: Option<String>,
+        /// The scene the file asks to be shown first.
+        pub is_default: bool,
+}
+
+/// The scenes of the model file `file_name`, to pick one with
+/// [`ImportOptions::scene`].
+pub async fn gltf_scenes(
+        file_name: &str,
+        crate_name: Option<&str>,
+) -> anyhow::Result<Vec<GltfScene>>
+{
+        #[cfg(not(target_arch = "wasm32"))]
+        let path = resource_path(file_name, crate_name)
+                .to_string_lossy()
+                .to_string();
+
+        #[cfg(target_arch = "wasm32")]
+        let path = resource_path(file_name, crate_name);
+
+        if !file_name.ends_with(".glb")
+        {
+                anyhow::bail!("Unsupported format: {}", file_name);
+        }
+
+        let (doc, _, _) = load_glb(&path, crate_name, None).await?;
+        let default = doc.default_scene().map(|s| s.index());
+
+        Ok(doc.scenes()
+                .map(|scene| GltfScene {
+                        index: scene.index(),
+                        name: scene.name().map(str::to_string),
+                        is_default: Some(scene.index()) == default,
+                })
+                .collect())
+}
+
+/// The scenes of `doc` to import, every scene without a `selector`.
+fn select_scenes<'a>(
+        doc: &'a gltf::Document,
+        selector: Option<&SceneSelector>,
+) -> anyhow::Result<Vec<gltf::Scene<'a>>>
+{
+        let scene = match selector
+        {
+                None => return Ok(doc.scenes().collect()),
+                Some(SceneSelector::Index(index)) => doc.scenes().nth(*index),
+                Some(SceneSelector::Name(name)) => doc.scenes().find(|s| s.name() == Some(name)),
+        };
+
+        let Some(scene) = scene
+        else
+        {
+                let available: Vec<String> = doc
+                        .scenes()
+                        .map(|s| match s.name()
+                        {
+                                Some(name) => format!("{} {:?}", s.index(), name),
+                                None => s.index().to_string(),
+                        })
+                        .collect();
+
+                anyhow::bail!("No scene {:?}, the file has [{}]", selector, available.join(", "));
+        };
+
+        Ok(vec![scene])
+}
+
+/// The meshes, nodes, materials and images of the scenes of the GLB at
+/// `path`, with the metadata in the `extras` of the scenes and their root
+/// nodes, see [`crate::metadata`]. The decoded file is kept in `cache` on
+/// native, see [`cache`].
+pub async fn load_gltf(
+        path: &str,
+        crate_name: Option<&str>,
+        scene: Option<&SceneSelector>,
+        cache: Option<&std::path::Path>,
+) -> anyhow::Result<ModelData>
+{
+        log::info!("Loading 3D model from: {:?}", path);
+
+        let (doc, buffers, images) = if path.ends_with(".glb")
+        {
+                load_glb(path, crate_name, cache).await?
+        }
+        else
+        {
+                anyhow::bail!("Unsupported format: {}", path);
+        };
+
+        println!("Found {} embedded images", images.len());
+
+        let mut meshes = Vec::new();
+        let mut nodes = Vec::new();
+        let mut materials = Vec::new();
+        let mut metadata = Metadata::new();
+
+        for mat in doc.materials()
+        {
+                let name = mat.name().unwrap_or("unnamed").to_string();
+                let pbr = mat.pbr_metallic_roughness();
+
+                println!("Processing material: {}", name);
+
+                // Textures pair an image with a sampler, several textures
+                // can share one image.
+                let base_color = pbr.base_color_texture().map(|info| info.texture());
+                let metallic_roughness =
+                        pbr.metallic_roughness_texture().map(|info| info.texture());
+                let normal = mat.normal_texture().map(|info| info.texture());
+
+                let image_index = |texture: &Option<gltf::Texture>| {
+                        texture.as_ref().map(|t| t.source().index())
+                };
+
+                let sampler = |texture: &Option<gltf::Texture>| {
+                        texture.as_ref()
+                                .map(|t| SamplerDesc::from_gltf(&t.sampler()))
+                                .unwrap_or_default()
+                };
+
+                materials.push(MaterialData {
+                        name: name.clone(),
+                        base_color_texture: None,
+                        base_color_factor: pbr.base_color_factor(),
+                        metallic_factor: pbr.metallic_factor(),
+                        roughness_factor: pbr.roughness_factor(),
+                        base_color_texture_index: image_index(&base_color),
+                        normal_texture_index: image_index(&normal),
+                        normal_texture: None,
+                        metallic_roughness_texture: None,
+                        metallic_roughness_texture_index: image_index(&metallic_roughness),
+                        sampler: sampler(&base_color),
+                        normal_sampler: sampler(&normal),
+                        metallic_roughness_sampler: sampler(&metallic_roughness),
+                        extras: Extras::from_gltf(mat.extras(), mat.extensions()),
+                        ..MaterialData::default()
+                });
+        }
+
+        let skins: Vec<Vec<Joint>> = doc
+                .skins()
+                .map(|skin| read_skin(&doc, &skin, &buffers))
+                .collect();
+
+        for scene in select_scenes(&doc, scene)?
+        {
+                metadata.extend(read_metadata(scene.extras()));
+
+                for node in scene.nodes()
+                {
+                        metadata.extend(read_metadata(node.extras()));
+
+                        process_node(
+                                &node,
+                                &buffers,
+                                &skins,
+                                &mut meshes,
+                                &mut nodes,
+                                None,
+                                Matrix4::identity(),
+                        );
+                }
+        }
+
+        if meshes.is_empty()
+        {
+                return Err(diagnostics::no_meshes(path, &doc).into());
+        }
+
+        Ok(ModelData {
+                meshes,
+                nodes,
+                materials,
+                images,
+                billboard: None,
+                metadata,
+                colliders: Vec::new(),
+        })
+}
+
+/// Tags and values in glTF `extras`, see [`Metadata::from_extras`].
+fn read_metadata(extras: &gltf::json::Extras) -> Metadata
+{
+        Metadata::from_extras(&Extras::from_gltf(extras, None).value)
+}
+
+/// The joints of `skin`, roots relative to the model with the transforms
+/// of the nodes above them.
+fn read_skin(
+        doc: &gltf::Document,
+        skin: &gltf::Skin,
+        buffers: &[gltf::buffer::Data],
+) -> Vec<Joint>
+{
+        // Parents and model space transforms of every node.
+        let mut parents = HashMap::new();
+        let mut globals = HashMap::new();
+        let mut stack: Vec<_> = doc
+                .scenes()
+                .flat_map(|s| s.nodes())
+                .map(|n| (n, Matrix4::identity()))
+                .collect();
+
+        while let Some((node, parent_transform)) = stack.pop()
+        {
+                let global = parent_transform * get_node_transform(&node);
+
+                for child in node.children()
+                {
+                        parents.insert(child.index(), node.index());
+                        stack.push((child, global));
+                }
+
+                globals.insert(node.index(), global);
+        }
+
+        let nodes: Vec<usize> = skin.joints().map(|j| j.index()).collect();
+
+        let inverse_binds: Vec<Matrix4<f32>> = skin
+                .reader(|b| Some(&buffers[b.index()]))
+                .read_inverse_bind_matrices()
+                .map(|m| m.map(Matrix4::from).collect())
+                .unwrap_or_default();
+
+        skin.joints()
+                .enumerate()
+                .map(|(i, node)| {
+                        let parent_node = parents.get(&node.index());
+                        let parent = parent_node.and_then(|p| nodes.iter().position(|n| n == p));
+
+                        let local = get_node_transform(&node);
+                        let local = match (parent, parent_node)
+                        {
+                                (None, Some(p)) => globals.get(p).map_or(local, |g| g * local),
+                                _ => local,
+                        };
+
+                        Joint {
+                                name: node.name().unwrap_or("Unnamed").to_string(),
+                                parent,
+                                transform: crate::transform::Transform::from_matrix(&local),
+                                inverse_bind: inverse_binds
+                                        .get(i)
+                                        .copied()
+                                        .unwrap_or_else(Matrix4::identity),
+                        }
+                })
+                .collect()
+}
+
+/// The bytes of the file at `path`, fetched on the web.
+async fn load_bytes(
+        path: &str,
+        #[allow(unused_variables)] crate_name: Option<&str>,
+) -> anyhow::Result<Vec<u8>>
+{
+        #[cfg(target_arch = "wasm32")]
+        {
+                use wasm_bindgen::JsCast;
+                use web_sys::Response;
+
+                let window =
+                        web_sys::window().ok_or_else(|| anyhow::anyhow!("No window available"))?;
+
+                let full_path = resource_path(path, crate_name);
+
+                log::info!("Fetching {}", full_path);
+
+                let resp_value =
+                        wasm_bindgen_futures::JsFuture::from(window.fetch_with_str(&full_path))
+                                .await
+                                .map_err(|e| {
+                                        anyhow::anyhow!("Failed to fetch {}: {:?}", path, e)
+                                })?;
+
+                let resp: Response = resp_value
+                        .dyn_into()
+                        .map_err(|e| anyhow::anyhow!("Failed to convert to Response: {:?}", e))?;
+
+                if !resp.ok()
+                {
+                        return Err(anyhow::anyhow!("HTTP error: {}", resp.status()));
+                }
+
+                let array_buffer =
+                        wasm_bindgen_futures::JsFuture::from(resp.array_buffer().map_err(|e| {
+                                anyhow::anyhow!("Failed to get array buffer: {:?}", e)
+                        })?)
+                        .await
+                        .map_err(|e| anyhow::anyhow!("Failed to await array buffer: {:?}", e))?;
+
+                Ok(js_sys::Uint8Array::new(&array_buffer).to_vec())
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        Ok(std::fs::read(path)?)
+}
+
+async fn load_glb(
+        path: &str,
+        crate_name: Option<&str>,
+        #[allow(unused_variables)] cache: Option<&std::path::Path>,
+) -> anyhow::Result<(gltf::Document, Vec<gltf::buffer::Data>, Vec<gltf::image::Data>)>
+{
+        let bytes = load_bytes(path, crate_name).await?;
+
+        #[cfg(all(target_arch = "wasm32", feature = "web-worker-decode"))]
+        return decode_worker::import_slice(&bytes).await;
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(decoded) = cache.and_then(|dir| cache::read(dir, &bytes))
+        {
+                return Ok(decoded);
+        }
+
+        #[cfg(not(all(target_arch = "wasm32", feature = "web-worker-decode")))]
+        {
+                let decoded = gltf::import_slice(&bytes)
+                        .map_err(|e| diagnostics::parse_failure(path, &bytes, &e))?;
+
+                #[cfg(not(target_arch = "wasm32"))]
+                if let Some(dir) = cache
+                        && let Err(e) = cache::write(dir, &bytes, &decoded)
+                {
+                        log::warn!("Failed to cache {}: {:#}", path, e);
+                }
+
+                Ok(decoded)
+        }
+}
+
+fn process_node(
+        node: &gltf::Node,
+        buffers: &[gltf::buffer::Data],
+        skins: &[Vec<Joint>],
+        meshes: &mut Vec<MeshData>,
+        nodes: &mut Vec<Node>,
+        parent: Option<usize>,
+        parent_transform: Matrix4<f32>,
+)
+{
+        // Calculate this node's transform
+        let local_transform = get_node_transform(node);
+        let node_transform = parent_transform * local_transform;
+
+        let index = nodes.len();
+        nodes.push(Node {
+                name: node
+                        .name()
+                        .map_or_else(|| format!("node_{}", node.index()), str::to_string),
+                parent,
+                children: Vec::new(),
+                transform: Transform::from_matrix(&local_transform),
+                extras: Extras::from_gltf(node.extras(), node.extensions()),
+        });
+
+        if let Some(parent) = parent
+        {
+                nodes[parent].children.push(index);
+        }
+
+        // Process mesh if this node has one
+        if let Some(mesh) = node.mesh()
+        {
+                let mesh_name = mesh.name().unwrap_or("Unnamed").to_string();
+                let extras = Extras::from_gltf(mesh.extras(), mesh.extensions());
+
+                // Not part of glTF, but how exporters like Blender name morph
+                // targets.
+                let target_names: Vec<String> = extras.parse("targetNames").unwrap_or_default();
+
+                for (primitive_index, primitive) in mesh.primitives().enumerate()
+                {
+                        let reader = primitive.reader(|b| Some(&buffers[b.index()]));
+
+                        let positions: Vec<[f32; 3]> = reader
+                                .read_positions()
+                                .map(|iter| iter.collect())
+                                .unwrap_or_default();
+
+                        if positions.is_empty()
+                        {
+                                continue;
+                        }
+
+                        let normals: Option<Vec<[f32; 3]>> =
+                                reader.read_normals().map(|iter| iter.collect());
+                        let has_normals = normals.is_some();
+                        let normals =
+                                normals.unwrap_or_else(|| vec![[0.0, 0.0, 0.0]; positions.len()]);
+
+                        let texcoords: Vec<[f32; 2]> = reader
+                                .read_tex_coords(0)
+                                .map(|tc| tc.into_f32().collect())
+                                .unwrap_or_else(|| vec![[0.0, 0.0]; positions.len()]);
+
+                        let indices: Vec<u32> = reader
+                                .read_indices()
+                                .map(|i| i.into_u32().collect())
+                                .unwrap_or_else(|| (0..positions.len() as u32).collect());
+
+                        let vertices: Vec<ModelVertex> = positions
+                                .iter()
+                                .enumerate()
+                                .map(|(i, pos)| ModelVertex {
+                                        position: *pos,
+                                        normal: normals[i],
+                                        tex_coords: texcoords[i],
+                                })
+                                .collect();
+
+                        // The joints place skinned vertices in model space,
+                        // the node transform doesn't apply.
+                        let skin = node.skin().and_then(|skin| {
+                                let joints = skins.get(skin.index())?.clone();
+                                let indices: Vec<[u16; 4]> =
+                                        reader.read_joints(0)?.into_u16().collect();
+                                let weights: Vec<[f32; 4]> =
+                                        reader.read_weights(0)?.into_f32().collect();
+
+                                let vertices = indices
+                                        .iter()
+                                        .zip(&weights)
+                                        .map(|(i, w)| {
+                                                SkinVertex {
+                                                        joints: i.map(u32::from),
+                                                        weights: *w,
+                                                }
+                                                .normalized(joints.len())
+                                        })
+                                        .collect::<Vec<_>>();
+
+                                (vertices.len() == positions.len()).then_some(SkinData {
+                                        vertices,
+                                        joints,
+                                })
+                        });
+
+                        let morph_targets = reader
+                                .read_morph_targets()
+                                .enumerate()
+                                .map(|(i, (offsets, normals, _))| MorphTarget {
+                                        name: target_names
+                                                .get(i)
+                                                .cloned()
+                                                .unwrap_or_else(|| format!("target_{}", i)),
+                                        weight: mesh
+                                                .weights()
+                                                .and_then(|w| w.get(i))
+                                                .copied()
+                                                .unwrap_or(0.0),
+                                        positions: offsets
+                                                .map(|o| o.collect())
+                                                .unwrap_or_else(|| vec![[0.0; 3]; positions.len()]),
+                                        normals: normals.map(|n| n.collect()).unwrap_or_default(),
+                                })
+                                .collect();
+
+                        // Create a unique name for each primitive
+                        let primitive_name = if mesh.primitives().count() > 1
+                        {
+                                format!("{}_primitive_{}", mesh_name, primitive_index)
+                        }
+                        else
+                        {
+                                mesh_name.clone()
+                        };
+
+                        let mut mesh = MeshData {
+                                name: primitive_name,
+                                vertices,
+                                indices,
+                                material_id: primitive.material().index(),
+                                transform: if skin.is_some()
+                                {
+                                        Matrix4::identity()
+                                }
+                                else
+                                {
+                                        node_transform // Store the transform
+                                },
+                                node: skin.is_none().then_some(index),
+                                skin,
+                                morph_targets,
+                                extras: extras.clone(),
+                        };
+
+                        // Flat, as glTF asks of loaders when they're missing.
+                        if !has_normals
+                        {
+                                mesh.recalculate_normals(cgmath::Deg(0.0));
+                        }
+
+                        meshes.push(mesh);
+                }
+        }
+
+        // Process child nodes recursively
+        for child in node.children()
+        {
+                process_node(&child, buffers, skins, meshes, nodes, Some(index), node_transform);
+        }
+}
+
+fn get_node_transform(node: &gltf::Node) -> Matrix4<f32>
+{
+        let (translation, rotation, scale) = node.transform().decomposed();
+
+        // Convert to cgmath types
+        let translation_vec = Vector3::new(translation[0], translation[1], translation[2]);
+        let rotation_quat = Quaternion::new(rotation[3], rotation[0], rotation[1], rotation[2]);
+        let scale_vec = Vector3::new(scale[0], scale[1], scale[2]);
+
+        // Create transformation matrix
+        Matrix4::from_translation(translation_vec)
+                * Matrix4::from(rotation_quat)
+                * Matrix4::from_nonuniform_scale(scale_vec[0], scale_vec[1], scale_vec[2])
+}
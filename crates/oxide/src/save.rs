@@ -0,0 +1,507 @@
+//! Save games: user state of behaviors together with the scene.
+//!
+//! Behaviors keep the state they want saved in an `Rc<RefCell<T>>` and
+//! register it under a key with [`Engine::register_saveable`]. A save slot
+//! then holds every registered value, the transforms of the loaded models,
+//! the camera and the environment:
+//!
+//! ```ignore
+//! let score = Rc::new(RefCell::new(Score::default()));
+//! engine.register_saveable("score", score.clone());
+//!
+//! engine.register_behavior(move |eng| {
+//!         if eng.pressed_keys.contains(&KeyCode::F9)
+//!         {
+//!                 eng.save_game("slot1").ok();
+//!         }
+//! });
+//! ```
+//!
+//! Slots are JSON files in the `saves/` directory on native and
+//! `localStorage` entries on the web.
+
+use crate::engine::Engine;
+use crate::metadata::Metadata;
+use crate::renderer::graph::EnvironmentSettings;
+use anyhow::{Context, Result};
+use cgmath::{Point3, Quaternion, Rad, Vector3};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
+
+/// Bumped when the file format changes.
+const VERSION: u32 = 1;
+
+/// Published on the event bus after a slot was written or applied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SaveEvent
+{
+        Saved(String),
+        Loaded(String),
+}
+
+/// Contents of a save slot.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SaveData
+{
+        pub version: u32,
+
+        pub scene: SceneSnapshot,
+
+        /// Registered state by key.
+        pub state: BTreeMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SceneSnapshot
+{
+        pub camera: Option<CameraSnapshot>,
+
+        #[serde(default)]
+        pub environment: Option<EnvironmentSettings>,
+
+        pub models: Vec<ModelSnapshot>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CameraSnapshot
+{
+        pub position: [f32; 3],
+        pub yaw: f32,
+        pub pitch: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelSnapshot
+{
+        pub handle: String,
+
+        /// File the model was loaded from, used to spawn it again when it
+        /// isn't loaded.
+        pub file: Option<String>,
+
+        pub position: [f32; 3],
+
+        /// Quaternion as `[w, x, y, z]`.
+        pub rotation: [f32; 4],
+
+        pub scale: [f32; 3],
+
+        pub visible: bool,
+
+        /// See [`Model::layer`](crate::model::Model::layer).
+        #[serde(default)]
+        pub layer: u32,
+
+        /// See [`crate::metadata`].
+        #[serde(default)]
+        pub metadata: Metadata,
+
+        pub is_spinning: bool,
+
+        pub rotation_speeds: [f32; 3],
+}
+
+impl ModelSnapshot
+{
+        fn from_model(
+                handle: &str,
+                file: Option<&String>,
+                model: &crate::model::Model,
+        ) -> Self
+        {
+                let r = model.transform.rotation;
+
+                Self {
+                        handle: handle.to_string(),
+                        file: file.cloned(),
+                        position: model.transform.position.into(),
+                        rotation: [r.s, r.v.x, r.v.y, r.v.z],
+                        scale: model.transform.scale.into(),
+                        visible: model.visible,
+                        layer: model.layer,
+                        metadata: model.metadata.clone(),
+                        is_spinning: model.is_spinning,
+                        rotation_speeds: model.rotation_speeds,
+                }
+        }
+
+        fn apply(
+                &self,
+                model: &mut crate::model::Model,
+        )
+        {
+                let [w, x, y, z] = self.rotation;
+
+                model.transform.position = Point3::from(self.position);
+                model.transform.rotation = Quaternion::new(w, x, y, z);
+                model.transform.scale = Vector3::from(self.scale);
+                model.visible = self.visible;
+                model.layer = self.layer;
+                model.metadata = self.metadata.clone();
+                model.is_spinning = self.is_spinning;
+                model.rotation_speeds = self.rotation_speeds;
+        }
+}
+
+/// State shared with a behavior, see [`Engine::register_saveable`].
+trait Saveable
+{
+        fn save(&self) -> Result<serde_json::Value>;
+
+        fn load(
+                &self,
+                value: serde_json::Value,
+        ) -> Result<()>;
+}
+
+impl<T: Serialize + DeserializeOwned> Saveable for Rc<RefCell<T>>
+{
+        fn save(&self) -> Result<serde_json::Value>
+        {
+                Ok(serde_json::to_value(&*self.borrow())?)
+        }
+
+        fn load(
+                &self,
+                value: serde_json::Value,
+        ) -> Result<()>
+        {
+                *self.borrow_mut() = serde_json::from_value(value)?;
+
+                Ok(())
+        }
+}
+
+/// Registered state and the storage of save slots, owned by the [`Engine`].
+#[derive(Default)]
+pub struct SaveSystem
+{
+        saveables: BTreeMap<String, Box<dyn Saveable>>,
+
+        /// Model transforms waiting for a model that is still loading.
+        pending: HashMap<String, ModelSnapshot>,
+}
+
+impl std::fmt::Debug for SaveSystem
+{
+        fn fmt(
+                &self,
+                f: &mut std::fmt::Formatter<'_>,
+        ) -> std::fmt::Result
+        {
+                f.debug_struct("SaveSystem")
+                        .field("saveables", &self.saveables.keys().collect::<Vec<_>>())
+                        .field("pending", &self.pending.len())
+                        .finish()
+        }
+}
+
+impl SaveSystem
+{
+        pub fn new() -> Self
+        {
+                Self::default()
+        }
+
+        /// Slot names end up in file names, so only a safe subset is allowed.
+        fn check_slot(slot: &str) -> Result<()>
+        {
+                let valid = !slot.is_empty()
+                        && slot.chars()
+                                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+                if !valid
+                {
+                        anyhow::bail!(
+                                "Invalid save slot {:?}, use letters, digits, '-' and '_'",
+                                slot
+                        );
+                }
+
+                Ok(())
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        fn path(slot: &str) -> std::path::PathBuf
+        {
+                std::path::Path::new("saves").join(format!("{}.json", slot))
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        fn write(
+                slot: &str,
+                data: &str,
+        ) -> Result<()>
+        {
+                let path = Self::path(slot);
+
+                if let Some(dir) = path.parent()
+                {
+                        std::fs::create_dir_all(dir)?;
+                }
+
+                std::fs::write(&path, data)
+                        .with_context(|| format!("Failed to write {}", path.display()))
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        fn read(slot: &str) -> Result<Option<String>>
+        {
+                match std::fs::read_to_string(Self::path(slot))
+                {
+                        Ok(data) => Ok(Some(data)),
+                        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+                        Err(e) => Err(e.into()),
+                }
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        fn storage() -> Result<web_sys::Storage>
+        {
+                web_sys::window()
+                        .and_then(|w| w.local_storage().ok().flatten())
+                        .context("localStorage is unavailable")
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        fn write(
+                slot: &str,
+                data: &str,
+        ) -> Result<()>
+        {
+                Self::storage()?
+                        .set_item(&format!("oxide.save.{}", slot), data)
+                        .map_err(|e| anyhow::anyhow!("Failed to save {}: {:?}", slot, e))
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        fn read(slot: &str) -> Result<Option<String>>
+        {
+                Self::storage()?
+                        .get_item(&format!("oxide.save.{}", slot))
+                        .map_err(|e| anyhow::anyhow!("Failed to load {}: {:?}", slot, e))
+        }
+
+        /// Applies transforms of models that finished loading since
+        /// [`Engine::load_game`].
+        ///
+        /// Called by the engine once per frame.
+        pub(crate) fn update(engine: &mut Engine)
+        {
+                if engine.saves.pending.is_empty()
+                {
+                        return;
+                }
+
+                let models = match engine.state.as_mut()
+                {
+                        Some(s) => &mut s.models,
+                        None => return,
+                };
+
+                engine.saves
+                        .pending
+                        .retain(|handle, snapshot| match models.get_mut(handle)
+                        {
+                                Some(model) =>
+                                {
+                                        snapshot.apply(model);
+                                        false
+                                }
+                                None => true,
+                        });
+        }
+}
+
+impl Engine
+{
+        /// Saves `state` with every game saved from now on and restores it
+        /// on load, under `key`.
+        pub fn register_saveable<T>(
+                &mut self,
+                key: impl Into<String>,
+                state: Rc<RefCell<T>>,
+        ) where
+                T: 'static + Serialize + DeserializeOwned,
+        {
+                self.saves.saveables.insert(key.into(), Box::new(state));
+        }
+
+        /// Snapshot of the camera and the loaded models.
+        pub fn scene_snapshot(&self) -> SceneSnapshot
+        {
+                let state = match self.state.as_ref()
+                {
+                        Some(s) => s,
+                        None =>
+                        {
+                                return SceneSnapshot {
+                                        environment: Some(self.config.environment),
+                                        ..Default::default()
+                                };
+                        }
+                };
+
+                let core = &state.camera.core;
+
+                let mut models: Vec<ModelSnapshot> = state
+                        .models
+                        .iter()
+                        .map(|(handle, model)| {
+                                ModelSnapshot::from_model(handle, self.model_map.get(handle), model)
+                        })
+                        .collect();
+
+                models.sort_by(|a, b| a.handle.cmp(&b.handle));
+
+                SceneSnapshot {
+                        camera: Some(CameraSnapshot {
+                                position: core.position.into(),
+                                yaw: core.yaw.0,
+                                pitch: core.pitch.0,
+                        }),
+                        environment: Some(self.config.environment),
+                        models,
+                }
+        }
+
+        /// Writes the registered state and the scene to `slot`.
+        pub fn save_game(
+                &mut self,
+                slot: &str,
+        ) -> Result<()>
+        {
+                SaveSystem::check_slot(slot)?;
+
+                let mut state = BTreeMap::new();
+
+                for (key, saveable) in &self.saves.saveables
+                {
+                        let value = saveable
+                                .save()
+                                .with_context(|| format!("Failed to save {:?}", key))?;
+
+                        state.insert(key.clone(), value);
+                }
+
+                let data = SaveData {
+                        version: VERSION,
+                        scene: self.scene_snapshot(),
+                        state,
+                };
+
+                SaveSystem::write(slot, &serde_json::to_string_pretty(&data)?)?;
+
+                log::info!("Saved game to slot {}", slot);
+
+                self.events.publish(SaveEvent::Saved(slot.to_string()));
+
+                Ok(())
+        }
+
+        /// Restores `slot`. Models in the save that aren't loaded are
+        /// spawned, models that aren't in the save are left alone.
+        pub fn load_game(
+                &mut self,
+                slot: &str,
+        ) -> Result<()>
+        {
+                SaveSystem::check_slot(slot)?;
+
+                let data = SaveSystem::read(slot)?
+                        .with_context(|| format!("No saved game in slot {}", slot))?;
+
+                let data: SaveData = serde_json::from_str(&data)
+                        .with_context(|| format!("Save slot {} is corrupted", slot))?;
+
+                if data.version != VERSION
+                {
+                        anyhow::bail!(
+                                "Save version {} isn't supported, expected {}",
+                                data.version,
+                                VERSION
+                        );
+                }
+
+                for (key, value) in data.state
+                {
+                        match self.saves.saveables.get(&key)
+                        {
+                                Some(saveable) => saveable
+                                        .load(value)
+                                        .with_context(|| format!("Failed to load {:?}", key))?,
+                                None =>
+                                {
+                                        log::warn!("Save slot {} has unknown state {:?}", slot, key)
+                                }
+                        }
+                }
+
+                self.apply_scene(data.scene);
+
+                log::info!("Loaded game from slot {}", slot);
+
+                self.events.publish(SaveEvent::Loaded(slot.to_string()));
+
+                Ok(())
+        }
+
+        pub fn has_save(
+                &self,
+                slot: &str,
+        ) -> bool
+        {
+                SaveSystem::check_slot(slot).is_ok()
+                        && matches!(SaveSystem::read(slot), Ok(Some(_)))
+        }
+
+        fn apply_scene(
+                &mut self,
+                scene: SceneSnapshot,
+        )
+        {
+                for snapshot in scene.models
+                {
+                        let loaded = self
+                                .state
+                                .as_ref()
+                                .is_some_and(|s| s.models.contains_key(&snapshot.handle));
+
+                        if !loaded
+                        {
+                                match &snapshot.file
+                                {
+                                        Some(file) => self
+                                                .spawn_model(snapshot.handle.clone(), file.clone()),
+                                        None =>
+                                        {
+                                                log::warn!(
+                                                        "Can't restore model {:?}, its file is unknown",
+                                                        snapshot.handle
+                                                );
+                                                continue;
+                                        }
+                                }
+                        }
+
+                        self.saves.pending.insert(snapshot.handle.clone(), snapshot);
+                }
+
+                if let Some(environment) = scene.environment
+                {
+                        self.config.environment = environment;
+                }
+
+                if let (Some(state), Some(camera)) = (self.state.as_mut(), scene.camera)
+                {
+                        state.camera.core.position = Point3::from(camera.position);
+                        state.camera.core.yaw = Rad(camera.yaw);
+                        state.camera.core.pitch = Rad(camera.pitch);
+                }
+
+                SaveSystem::update(self);
+        }
+}
@@ -0,0 +1,382 @@
+//! Timers and coroutines for behaviors.
+//!
+//! Instead of counting ticks by hand, behaviors can defer work:
+//! - [`Engine::after`] runs a callback once after a delay.
+//! - [`Engine::every`] runs a callback repeatedly.
+//! - [`Engine::start_coroutine`] resumes a closure once per engine tick until
+//!   it returns [`Yield::Done`]. Returning [`Yield::Wait`] sleeps it for a
+//!   while, so multi-step sequences read top to bottom with a small state
+//!   variable instead of tick bookkeeping.
+//!
+//! Delays are converted to engine ticks when they are scheduled, and the
+//! scheduler is advanced once per tick, right after
+//! [`crate::replay::ReplaySystem`] applied the tick's input. A timer therefore
+//! fires on the same tick when a replay is played back, and
+//! [`crate::audit::DeterminismAudit`] sees its effect on that tick.
+
+use crate::engine::Engine;
+
+/// Identifies a timer or coroutine, used for cancellation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TaskId(pub u64);
+
+/// What a coroutine wants to happen after it was resumed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Yield
+{
+        /// Resume on the next tick.
+        NextTick,
+        /// Resume after the given number of seconds, rounded to whole ticks.
+        Wait(f32),
+        /// The coroutine is finished and is dropped.
+        Done,
+}
+
+pub type TimerCallback = Box<dyn FnMut(&mut Engine)>;
+
+pub type CoroutineFn = Box<dyn FnMut(&mut Engine) -> Yield>;
+
+struct Timer
+{
+        id: TaskId,
+        /// Value of [`Scheduler::ticks`] the timer fires on.
+        due: u64,
+        /// `Some` for repeating timers, in ticks.
+        interval: Option<u64>,
+        callback: TimerCallback,
+}
+
+struct Coroutine
+{
+        id: TaskId,
+        wake_at: Option<u64>,
+        resume: CoroutineFn,
+}
+
+/// Pending timers and coroutines of an [`Engine`].
+pub struct Scheduler
+{
+        timers: Vec<Timer>,
+
+        coroutines: Vec<Coroutine>,
+
+        /// Tasks cancelled while the scheduler was running them.
+        cancelled: Vec<TaskId>,
+
+        /// Ticks run so far, unlike [`Engine::current_tick`] this doesn't wrap.
+        ticks: u64,
+
+        next_id: u64,
+}
+
+impl std::fmt::Debug for Scheduler
+{
+        fn fmt(
+                &self,
+                f: &mut std::fmt::Formatter<'_>,
+        ) -> std::fmt::Result
+        {
+                f.debug_struct("Scheduler")
+                        .field("timers", &self.timers.len())
+                        .field("coroutines", &self.coroutines.len())
+                        .field("ticks", &self.ticks)
+                        .field("next_id", &self.next_id)
+                        .finish()
+        }
+}
+
+impl Default for Scheduler
+{
+        fn default() -> Self
+        {
+                Self::new()
+        }
+}
+
+impl Scheduler
+{
+        pub fn new() -> Self
+        {
+                Self {
+                        timers: Vec::new(),
+                        coroutines: Vec::new(),
+                        cancelled: Vec::new(),
+                        ticks: 0,
+                        next_id: 0,
+                }
+        }
+
+        fn next_id(&mut self) -> TaskId
+        {
+                let id = TaskId(self.next_id);
+                self.next_id += 1;
+
+                id
+        }
+
+        fn add_timer(
+                &mut self,
+                delay: u64,
+                interval: Option<u64>,
+                callback: TimerCallback,
+        ) -> TaskId
+        {
+                let id = self.next_id();
+
+                self.timers.push(Timer {
+                        id,
+                        due: self.ticks + delay,
+                        interval,
+                        callback,
+                });
+
+                id
+        }
+
+        /// Removes a timer or coroutine. Returns `false` if it isn't pending,
+        /// e.g. a one-shot timer cancelling itself from its own callback.
+        pub fn cancel(
+                &mut self,
+                id: TaskId,
+        ) -> bool
+        {
+                let count = self.len();
+
+                self.timers.retain(|t| t.id != id);
+                self.coroutines.retain(|c| c.id != id);
+
+                if self.len() != count
+                {
+                        return true;
+                }
+
+                // The task may be running right now and is then taken out of
+                // the lists, remember it so it isn't put back.
+                self.cancelled.push(id);
+
+                false
+        }
+
+        pub fn is_pending(
+                &self,
+                id: TaskId,
+        ) -> bool
+        {
+                self.timers.iter().any(|t| t.id == id) || self.coroutines.iter().any(|c| c.id == id)
+        }
+
+        /// Number of pending timers and coroutines.
+        pub fn len(&self) -> usize
+        {
+                self.timers.len() + self.coroutines.len()
+        }
+
+        pub fn is_empty(&self) -> bool
+        {
+                self.len() == 0
+        }
+
+        /// Ticks run so far.
+        pub fn ticks(&self) -> u64
+        {
+                self.ticks
+        }
+
+        /// Advances by one tick, fires the timers due on it and resumes
+        /// coroutines.
+        ///
+        /// Called by the engine once per tick. Like behaviors, tasks are
+        /// taken out of the engine while they run so they can get
+        /// `&mut Engine` and schedule new tasks.
+        pub(crate) fn update(engine: &mut Engine)
+        {
+                engine.scheduler.ticks += 1;
+
+                let now = engine.scheduler.ticks;
+
+                let mut timers = std::mem::take(&mut engine.scheduler.timers);
+
+                let mut i = 0;
+                while i < timers.len()
+                {
+                        if engine.scheduler.take_cancelled(timers[i].id)
+                        {
+                                timers.remove(i);
+                                continue;
+                        }
+
+                        if timers[i].due > now
+                        {
+                                i += 1;
+                                continue;
+                        }
+
+                        let timer = &mut timers[i];
+
+                        (timer.callback)(engine);
+
+                        let cancelled = engine.scheduler.take_cancelled(timer.id);
+
+                        match timer.interval
+                        {
+                                Some(interval) if !cancelled =>
+                                {
+                                        timer.due = now + interval;
+                                        i += 1;
+                                }
+                                _ =>
+                                {
+                                        timers.remove(i);
+                                }
+                        }
+                }
+
+                timers.append(&mut engine.scheduler.timers);
+                engine.scheduler.timers = timers;
+
+                Self::resume_coroutines(engine, now);
+
+                engine.scheduler.cancelled.clear();
+        }
+
+        fn resume_coroutines(
+                engine: &mut Engine,
+                now: u64,
+        )
+        {
+                let mut coroutines = std::mem::take(&mut engine.scheduler.coroutines);
+
+                coroutines.retain_mut(|c| {
+                        if engine.scheduler.take_cancelled(c.id)
+                        {
+                                return false;
+                        }
+
+                        if c.wake_at.is_some_and(|t| t > now)
+                        {
+                                return true;
+                        }
+
+                        let result = (c.resume)(engine);
+
+                        if engine.scheduler.take_cancelled(c.id)
+                        {
+                                return false;
+                        }
+
+                        match result
+                        {
+                                Yield::NextTick =>
+                                {
+                                        c.wake_at = None;
+                                        true
+                                }
+                                Yield::Wait(seconds) =>
+                                {
+                                        c.wake_at = Some(now + engine.seconds_to_ticks(seconds));
+                                        true
+                                }
+                                Yield::Done => false,
+                        }
+                });
+
+                coroutines.append(&mut engine.scheduler.coroutines);
+                engine.scheduler.coroutines = coroutines;
+        }
+
+        fn take_cancelled(
+                &mut self,
+                id: TaskId,
+        ) -> bool
+        {
+                let len = self.cancelled.len();
+
+                self.cancelled.retain(|c| *c != id);
+
+                self.cancelled.len() != len
+        }
+}
+
+impl Engine
+{
+        /// Whole ticks in `seconds` at the current [`Engine::tps`], at least
+        /// one.
+        fn seconds_to_ticks(
+                &self,
+                seconds: f32,
+        ) -> u64
+        {
+                (seconds.max(0.0) / self.tps_interval.as_secs_f32())
+                        .round()
+                        .max(1.0) as u64
+        }
+
+        /// Runs `f` once, `seconds` from now, rounded to whole ticks.
+        pub fn after<F>(
+                &mut self,
+                seconds: f32,
+                f: F,
+        ) -> TaskId
+        where
+                F: 'static + FnOnce(&mut Engine),
+        {
+                let mut f = Some(f);
+                let delay = self.seconds_to_ticks(seconds);
+
+                self.scheduler.add_timer(
+                        delay,
+                        None,
+                        Box::new(move |engine| {
+                                if let Some(f) = f.take()
+                                {
+                                        f(engine);
+                                }
+                        }),
+                )
+        }
+
+        /// Runs `f` every `seconds`, starting `seconds` from now, until
+        /// cancelled.
+        pub fn every<F>(
+                &mut self,
+                seconds: f32,
+                f: F,
+        ) -> TaskId
+        where
+                F: 'static + FnMut(&mut Engine),
+        {
+                let interval = self.seconds_to_ticks(seconds);
+
+                self.scheduler
+                        .add_timer(interval, Some(interval), Box::new(f))
+        }
+
+        /// Resumes `f` once per tick, starting with the next one, until it
+        /// returns [`Yield::Done`].
+        pub fn start_coroutine<F>(
+                &mut self,
+                f: F,
+        ) -> TaskId
+        where
+                F: 'static + FnMut(&mut Engine) -> Yield,
+        {
+                let id = self.scheduler.next_id();
+
+                self.scheduler.coroutines.push(Coroutine {
+                        id,
+                        wake_at: None,
+                        resume: Box::new(f),
+                });
+
+                id
+        }
+
+        /// See [`Scheduler::cancel`].
+        pub fn cancel_task(
+                &mut self,
+                id: TaskId,
+        ) -> bool
+        {
+                self.scheduler.cancel(id)
+        }
+}
@@ -0,0 +1,623 @@
+//! [Rhai](https://rhai.rs) scripting, enabled with the `scripting` feature.
+//!
+//! Scripts are loaded from the `resources/` directory at runtime, so game
+//! logic can be changed without recompiling the wasm module. On native,
+//! scripts are reloaded when the file changes.
+//!
+//! A script can be attached to the engine with [`Engine::add_script`] or to a
+//! model with [`Engine::attach_script`], in which case the constant `MODEL`
+//! holds the model's handle. Top level statements run once when the script is
+//! loaded, after that the engine calls these functions if they are defined:
+//! - `tick(dt)` once per engine tick.
+//! - `on_event(name, value)` for every event sent with [`ScriptSystem::send`].
+//!
+//! Scripts only see a restricted API, they can't touch the file system or
+//! the GPU:
+//!
+//! ```text
+//! position(model) -> [x, y, z]      set_position(model, x, y, z)
+//! translate(model, dx, dy, dz)      scale(model) -> [x, y, z]
+//! set_scale(model, x, y, z)         set_rotation(model, x°, y°, z°)
+//! rotate(model, x°, y°, z°)         models() -> [handles]
+//! key_down("KeyW") -> bool          emit(name) / emit(name, value)
+//! spawn(handle, "file.glb")         print(text)
+//! ```
+//!
+//! Events emitted by scripts are collected with [`ScriptSystem::drain_events`].
+
+use crate::engine::Engine;
+use crate::transform::Transform;
+use anyhow::Result;
+use cgmath::{Deg, Euler, Point3, Quaternion, Vector3};
+use rhai::{AST, Array, CallFnOptions, Dynamic, FLOAT, Scope};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+/// Operation budget of a single call, stops runaway loops from freezing the
+/// frame.
+const MAX_OPERATIONS: u64 = 500_000;
+
+/// An event emitted by a script with `emit(name, value)`.
+#[derive(Debug, Clone)]
+pub struct ScriptEvent
+{
+        pub name: String,
+        pub value: Dynamic,
+}
+
+/// Snapshot of the engine that the registered functions work on, written
+/// back after the scripts ran.
+#[derive(Debug, Default)]
+struct ScriptWorld
+{
+        transforms: HashMap<String, Transform>,
+        dirty: HashSet<String>,
+        keys: HashSet<String>,
+        emitted: Vec<ScriptEvent>,
+        spawns: Vec<(String, String)>,
+}
+
+/// Script sources that finished loading, by index into the script list.
+type LoadedSources = Rc<RefCell<Vec<(usize, Result<String>)>>>;
+
+struct Script
+{
+        file: String,
+
+        /// Model the script is attached to.
+        model: Option<String>,
+
+        ast: Option<AST>,
+
+        scope: Scope<'static>,
+
+        has_tick: bool,
+
+        has_on_event: bool,
+
+        #[cfg(not(target_arch = "wasm32"))]
+        modified: Option<std::time::SystemTime>,
+}
+
+/// Loaded scripts and the `rhai` engine running them.
+pub struct ScriptSystem
+{
+        rhai: rhai::Engine,
+
+        world: Rc<RefCell<ScriptWorld>>,
+
+        scripts: Vec<Script>,
+
+        loaded: LoadedSources,
+
+        /// Events sent to the scripts, delivered on the next tick.
+        inbox: Vec<ScriptEvent>,
+
+        /// Events emitted by the scripts.
+        outbox: Vec<ScriptEvent>,
+
+        last_tick: u8,
+
+        #[cfg(not(target_arch = "wasm32"))]
+        last_reload_check: instant::Instant,
+}
+
+impl std::fmt::Debug for ScriptSystem
+{
+        fn fmt(
+                &self,
+                f: &mut std::fmt::Formatter<'_>,
+        ) -> std::fmt::Result
+        {
+                f.debug_struct("ScriptSystem")
+                        .field("scripts", &self.scripts.iter().map(|s| &s.file).collect::<Vec<_>>())
+                        .field("inbox", &self.inbox.len())
+                        .field("outbox", &self.outbox.len())
+                        .finish()
+        }
+}
+
+impl Default for ScriptSystem
+{
+        fn default() -> Self
+        {
+                Self::new()
+        }
+}
+
+impl ScriptSystem
+{
+        pub fn new() -> Self
+        {
+                let world = Rc::new(RefCell::new(ScriptWorld::default()));
+
+                Self {
+                        rhai: Self::create_engine(&world),
+                        world,
+                        scripts: Vec::new(),
+                        loaded: Rc::new(RefCell::new(Vec::new())),
+                        inbox: Vec::new(),
+                        outbox: Vec::new(),
+                        last_tick: 0,
+                        #[cfg(not(target_arch = "wasm32"))]
+                        last_reload_check: instant::Instant::now(),
+                }
+        }
+
+        fn create_engine(world: &Rc<RefCell<ScriptWorld>>) -> rhai::Engine
+        {
+                let mut rhai = rhai::Engine::new();
+
+                rhai.set_max_operations(MAX_OPERATIONS);
+                rhai.set_max_call_levels(32);
+                rhai.disable_symbol("eval");
+                rhai.set_module_resolver(rhai::module_resolvers::DummyModuleResolver::new());
+
+                rhai.on_print(|text| log::info!("[script] {}", text));
+                rhai.on_debug(|text, _, pos| log::debug!("[script {}] {}", pos, text));
+
+                let w = world.clone();
+                rhai.register_fn("position", move |model: &str| -> Array {
+                        w.borrow()
+                                .transforms
+                                .get(model)
+                                .map(|t| to_array([t.position.x, t.position.y, t.position.z]))
+                                .unwrap_or_default()
+                });
+
+                let w = world.clone();
+                rhai.register_fn(
+                        "set_position",
+                        move |model: &str, x: FLOAT, y: FLOAT, z: FLOAT| {
+                                w.borrow_mut().modify(model, |t| {
+                                        t.position = Point3::new(x as f32, y as f32, z as f32);
+                                });
+                        },
+                );
+
+                let w = world.clone();
+                rhai.register_fn("translate", move |model: &str, x: FLOAT, y: FLOAT, z: FLOAT| {
+                        w.borrow_mut().modify(model, |t| {
+                                t.position += Vector3::new(x as f32, y as f32, z as f32);
+                        });
+                });
+
+                let w = world.clone();
+                rhai.register_fn("scale", move |model: &str| -> Array {
+                        w.borrow()
+                                .transforms
+                                .get(model)
+                                .map(|t| to_array([t.scale.x, t.scale.y, t.scale.z]))
+                                .unwrap_or_default()
+                });
+
+                let w = world.clone();
+                rhai.register_fn("set_scale", move |model: &str, x: FLOAT, y: FLOAT, z: FLOAT| {
+                        w.borrow_mut().modify(model, |t| {
+                                t.scale = Vector3::new(x as f32, y as f32, z as f32);
+                        });
+                });
+
+                let w = world.clone();
+                rhai.register_fn(
+                        "set_rotation",
+                        move |model: &str, x: FLOAT, y: FLOAT, z: FLOAT| {
+                                w.borrow_mut().modify(model, |t| {
+                                        t.rotation = euler(x, y, z);
+                                });
+                        },
+                );
+
+                let w = world.clone();
+                rhai.register_fn("rotate", move |model: &str, x: FLOAT, y: FLOAT, z: FLOAT| {
+                        w.borrow_mut().modify(model, |t| {
+                                t.rotation = euler(x, y, z) * t.rotation;
+                        });
+                });
+
+                let w = world.clone();
+                rhai.register_fn("models", move || -> Array {
+                        w.borrow()
+                                .transforms
+                                .keys()
+                                .map(|k| Dynamic::from(k.clone()))
+                                .collect()
+                });
+
+                let w = world.clone();
+                rhai.register_fn("key_down", move |key: &str| -> bool {
+                        w.borrow().keys.contains(key)
+                });
+
+                let w = world.clone();
+                rhai.register_fn("emit", move |name: &str| {
+                        w.borrow_mut().emitted.push(ScriptEvent {
+                                name: name.to_string(),
+                                value: Dynamic::UNIT,
+                        });
+                });
+
+                let w = world.clone();
+                rhai.register_fn("emit", move |name: &str, value: Dynamic| {
+                        w.borrow_mut().emitted.push(ScriptEvent {
+                                name: name.to_string(),
+                                value,
+                        });
+                });
+
+                let w = world.clone();
+                rhai.register_fn("spawn", move |handle: &str, file_name: &str| {
+                        w.borrow_mut()
+                                .spawns
+                                .push((handle.to_string(), file_name.to_string()));
+                });
+
+                rhai
+        }
+
+        fn add(
+                &mut self,
+                file_name: &str,
+                model: Option<String>,
+        )
+        {
+                let index = self.scripts.len();
+
+                self.scripts.push(Script {
+                        file: file_name.to_string(),
+                        model,
+                        ast: None,
+                        scope: Scope::new(),
+                        has_tick: false,
+                        has_on_event: false,
+                        #[cfg(not(target_arch = "wasm32"))]
+                        modified: None,
+                });
+
+                self.load(index);
+        }
+
+        /// Reads the source of a script, it's compiled on the next update.
+        fn load(
+                &mut self,
+                index: usize,
+        )
+        {
+                let file = self.scripts[index].file.clone();
+
+                #[cfg(not(target_arch = "wasm32"))]
+                {
+                        let path = crate::resources::resource_path(&file, None);
+
+                        self.scripts[index].modified =
+                                std::fs::metadata(&path).and_then(|m| m.modified()).ok();
+
+                        let source = pollster::block_on(crate::resources::load_string(&file, None));
+
+                        self.loaded.borrow_mut().push((index, source));
+                }
+
+                #[cfg(target_arch = "wasm32")]
+                {
+                        let loaded = self.loaded.clone();
+
+                        wasm_bindgen_futures::spawn_local(async move {
+                                let source = crate::resources::load_string(&file, None).await;
+
+                                loaded.borrow_mut().push((index, source));
+                        });
+                }
+        }
+
+        /// Queues an event for the `on_event` function of every script.
+        pub fn send(
+                &mut self,
+                name: impl Into<String>,
+                value: impl Into<Dynamic>,
+        )
+        {
+                self.inbox.push(ScriptEvent {
+                        name: name.into(),
+                        value: value.into(),
+                });
+        }
+
+        /// Takes the events emitted by scripts since the last call.
+        pub fn drain_events(&mut self) -> Vec<ScriptEvent>
+        {
+                std::mem::take(&mut self.outbox)
+        }
+
+        /// Files of the loaded scripts.
+        pub fn files(&self) -> Vec<&str>
+        {
+                self.scripts.iter().map(|s| s.file.as_str()).collect()
+        }
+
+        /// Compiles newly loaded sources and runs the scripts once per tick.
+        ///
+        /// Called by the engine after the behaviors.
+        pub(crate) fn update(engine: &mut Engine)
+        {
+                #[cfg(not(target_arch = "wasm32"))]
+                engine.scripts.check_reload();
+
+                let loaded = std::mem::take(&mut *engine.scripts.loaded.borrow_mut());
+
+                let new_tick = engine.current_tick != engine.scripts.last_tick;
+
+                if loaded.is_empty() && !new_tick
+                {
+                        return;
+                }
+
+                engine.scripts.last_tick = engine.current_tick;
+
+                engine.scripts
+                        .snapshot(engine.state.as_ref().map(|s| &s.models), &engine.pressed_keys);
+
+                for (index, source) in loaded
+                {
+                        engine.scripts.compile(index, source);
+                }
+
+                if new_tick
+                {
+                        let dt = engine.tps_interval.as_secs_f32();
+
+                        engine.scripts.run(dt);
+                }
+
+                Self::write_back(engine);
+        }
+
+        fn snapshot(
+                &mut self,
+                models: Option<&HashMap<String, crate::model::Model>>,
+                keys: &HashSet<winit::keyboard::KeyCode>,
+        )
+        {
+                let mut world = self.world.borrow_mut();
+
+                world.transforms = models
+                        .map(|models| {
+                                models.iter()
+                                        .map(|(name, m)| (name.clone(), m.transform))
+                                        .collect()
+                        })
+                        .unwrap_or_default();
+
+                world.dirty.clear();
+
+                world.keys = keys.iter().map(|k| format!("{:?}", k)).collect();
+        }
+
+        fn compile(
+                &mut self,
+                index: usize,
+                source: Result<String>,
+        )
+        {
+                let script = &mut self.scripts[index];
+
+                let result = source.and_then(|source| {
+                        self.rhai
+                                .compile(source)
+                                .map_err(|e| anyhow::anyhow!("{}", e))
+                });
+
+                let ast = match result
+                {
+                        Ok(ast) => ast,
+                        Err(e) =>
+                        {
+                                log::error!("Script {}: {:#}", script.file, e);
+
+                                script.ast = None;
+
+                                return;
+                        }
+                };
+
+                log::info!("Loaded script {}", script.file);
+
+                script.has_tick = ast.iter_functions().any(|f| f.name == "tick");
+                script.has_on_event = ast.iter_functions().any(|f| f.name == "on_event");
+
+                script.scope = Scope::new();
+
+                if let Some(model) = &script.model
+                {
+                        script.scope.push_constant("MODEL", model.clone());
+                }
+
+                match self.rhai.run_ast_with_scope(&mut script.scope, &ast)
+                {
+                        Ok(()) => script.ast = Some(ast),
+                        Err(e) =>
+                        {
+                                log::error!("Script {}: {}", script.file, e);
+
+                                script.ast = None;
+                        }
+                }
+        }
+
+        fn run(
+                &mut self,
+                dt: f32,
+        )
+        {
+                let inbox = std::mem::take(&mut self.inbox);
+
+                for script in &mut self.scripts
+                {
+                        let ast = match &script.ast
+                        {
+                                Some(ast) => ast,
+                                None => continue,
+                        };
+
+                        let mut result = Ok(Dynamic::UNIT);
+
+                        if script.has_on_event
+                        {
+                                for event in &inbox
+                                {
+                                        result = self.rhai.call_fn_with_options::<Dynamic>(
+                                                options(),
+                                                &mut script.scope,
+                                                ast,
+                                                "on_event",
+                                                (event.name.clone(), event.value.clone()),
+                                        );
+
+                                        if result.is_err()
+                                        {
+                                                break;
+                                        }
+                                }
+                        }
+
+                        if script.has_tick && result.is_ok()
+                        {
+                                result = self.rhai.call_fn_with_options::<Dynamic>(
+                                        options(),
+                                        &mut script.scope,
+                                        ast,
+                                        "tick",
+                                        (dt as FLOAT,),
+                                );
+                        }
+
+                        // Stop a failing script instead of logging the same
+                        // error every tick, it runs again once reloaded.
+                        if let Err(e) = result
+                        {
+                                log::error!("Script {} stopped: {}", script.file, e);
+
+                                script.ast = None;
+                        }
+                }
+        }
+
+        fn write_back(engine: &mut Engine)
+        {
+                let world = engine.scripts.world.clone();
+                let mut world = world.borrow_mut();
+
+                let dirty: Vec<String> = world.dirty.drain().collect();
+
+                if let Some(state) = engine.state.as_mut()
+                {
+                        for name in dirty
+                        {
+                                if let (Some(model), Some(t)) =
+                                        (state.models.get_mut(&name), world.transforms.get(&name))
+                                {
+                                        model.transform = *t;
+                                }
+                        }
+                }
+
+                engine.scripts.outbox.append(&mut world.emitted);
+
+                let spawns = std::mem::take(&mut world.spawns);
+
+                drop(world);
+
+                for (handle, file_name) in spawns
+                {
+                        engine.spawn_model(handle, file_name);
+                }
+        }
+
+        /// Reloads scripts whose file changed, checked about once a second.
+        #[cfg(not(target_arch = "wasm32"))]
+        fn check_reload(&mut self)
+        {
+                if self.last_reload_check.elapsed() < std::time::Duration::from_secs(1)
+                {
+                        return;
+                }
+
+                self.last_reload_check = instant::Instant::now();
+
+                for index in 0..self.scripts.len()
+                {
+                        let path = crate::resources::resource_path(&self.scripts[index].file, None);
+
+                        let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok();
+
+                        if modified.is_some() && modified != self.scripts[index].modified
+                        {
+                                log::info!("Reloading script {}", self.scripts[index].file);
+
+                                self.load(index);
+                        }
+                }
+        }
+}
+
+impl ScriptWorld
+{
+        fn modify(
+                &mut self,
+                model: &str,
+                f: impl FnOnce(&mut Transform),
+        )
+        {
+                match self.transforms.get_mut(model)
+                {
+                        Some(t) =>
+                        {
+                                f(t);
+                                self.dirty.insert(model.to_string());
+                        }
+                        None => log::warn!("[script] unknown model {:?}", model),
+                }
+        }
+}
+
+/// The top level statements already ran when the script was loaded.
+fn options() -> CallFnOptions<'static>
+{
+        CallFnOptions::new().eval_ast(false)
+}
+
+fn to_array(v: [f32; 3]) -> Array
+{
+        v.iter().map(|c| Dynamic::from(*c as FLOAT)).collect()
+}
+
+fn euler(
+        x: FLOAT,
+        y: FLOAT,
+        z: FLOAT,
+) -> Quaternion<f32>
+{
+        Quaternion::from(Euler::new(Deg(x as f32), Deg(y as f32), Deg(z as f32)))
+}
+
+impl Engine
+{
+        /// Runs a script from `resources/` that isn't tied to a model.
+        pub fn add_script(
+                &mut self,
+                file_name: &str,
+        )
+        {
+                self.scripts.add(file_name, None);
+        }
+
+        /// Runs a script from `resources/` with `MODEL` set to `model`.
+        pub fn attach_script(
+                &mut self,
+                model: impl Into<String>,
+                file_name: &str,
+        )
+        {
+                self.scripts.add(file_name, Some(model.into()));
+        }
+}
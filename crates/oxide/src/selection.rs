@@ -0,0 +1,244 @@
+//! Several models selected at once in the debug UI and edited together.
+//!
+//! Clicking a model, in the scene or in the models window, selects it. With
+//! Shift held the click adds it to the selection or takes it out again,
+//! and dragging a box over the scene selects the models whose bounds have
+//! their center inside it, see [`crate::ui::hover`]. The last model
+//! selected is the primary one, framed by
+//! [`Config::frame_key`](crate::config::Config::frame_key) and duplicated
+//! by Ctrl+D.
+//!
+//! The "Selection" window edits the visibility, layer, tint and parent of
+//! every selected model at once, see [`crate::ui::selection`], and merges
+//! them into a static batch. From code:
+//!
+//! ```ignore
+//! engine.select_model(Some("crate"));
+//! engine.toggle_selected("barrel");
+//!
+//! let handles = engine.selection().to_vec();
+//! engine.merge_models(&handles, "props");
+//! ```
+
+use crate::engine::Engine;
+use crate::model::Model;
+
+/// Handles of the selected models, owned by the
+/// [`EngineState`](crate::engine::EngineState).
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Selection
+{
+        /// In the order they were selected, the primary last.
+        handles: Vec<String>,
+}
+
+impl Selection
+{
+        pub fn new() -> Self
+        {
+                Self::default()
+        }
+
+        pub fn len(&self) -> usize
+        {
+                self.handles.len()
+        }
+
+        pub fn is_empty(&self) -> bool
+        {
+                self.handles.is_empty()
+        }
+
+        pub fn handles(&self) -> &[String]
+        {
+                &self.handles
+        }
+
+        /// The model selected last.
+        pub fn primary(&self) -> Option<&str>
+        {
+                self.handles.last().map(String::as_str)
+        }
+
+        pub fn contains(
+                &self,
+                handle: &str,
+        ) -> bool
+        {
+                self.handles.iter().any(|h| h == handle)
+        }
+
+        /// Selects only `handle`, nothing for `None`.
+        pub fn set(
+                &mut self,
+                handle: Option<&str>,
+        )
+        {
+                self.handles.clear();
+                self.handles.extend(handle.map(str::to_string));
+        }
+
+        /// Adds `handle`, making it the primary one.
+        pub fn add(
+                &mut self,
+                handle: &str,
+        )
+        {
+                self.remove(handle);
+                self.handles.push(handle.to_string());
+        }
+
+        pub fn remove(
+                &mut self,
+                handle: &str,
+        )
+        {
+                self.handles.retain(|h| h != handle);
+        }
+
+        /// Adds `handle` or takes it out when it's selected.
+        pub fn toggle(
+                &mut self,
+                handle: &str,
+        )
+        {
+                if self.contains(handle)
+                {
+                        self.remove(handle);
+                }
+                else
+                {
+                        self.add(handle);
+                }
+        }
+
+        pub fn clear(&mut self)
+        {
+                self.handles.clear();
+        }
+
+        pub fn retain(
+                &mut self,
+                keep: impl FnMut(&String) -> bool,
+        )
+        {
+                self.handles.retain(keep);
+        }
+}
+
+impl Engine
+{
+        /// Selects the model `handle` in the debug UI's models window, the
+        /// one [`Config::frame_key`](crate::config::Config::frame_key)
+        /// frames. `None` clears the selection.
+        pub fn select_model(
+                &mut self,
+                handle: Option<&str>,
+        )
+        {
+                if let Some(state) = self.state.as_mut()
+                {
+                        state.selection.set(handle);
+                }
+        }
+
+        /// Adds the model `handle` to the selection or takes it out, like
+        /// a Shift+click.
+        pub fn toggle_selected(
+                &mut self,
+                handle: &str,
+        )
+        {
+                if let Some(state) = self.state.as_mut()
+                {
+                        state.selection.toggle(handle);
+                }
+        }
+
+        /// The primary selected model, see [`crate::selection`].
+        pub fn selected_model(&self) -> Option<&str>
+        {
+                self.state.as_ref()?.selection.primary()
+        }
+
+        /// Every selected model, the primary one last.
+        pub fn selection(&self) -> &[String]
+        {
+                self.state
+                        .as_ref()
+                        .map_or(&[], |state| state.selection.handles())
+        }
+
+        /// Shows or hides every model on `layer`, see
+        /// [`Model::layer`](crate::model::Model::layer).
+        pub fn set_layer_visible(
+                &mut self,
+                layer: u32,
+                visible: bool,
+        )
+        {
+                if let Some(state) = self.state.as_mut()
+                {
+                        for model in state.models.values_mut().filter(|m| m.layer == layer)
+                        {
+                                model.visible = visible;
+                        }
+                }
+        }
+
+        /// Merges the models `handles` into one static batch `new_handle`,
+        /// see [`Model::merge`], hides them and selects the batch. The
+        /// batch replaces a model with that handle. Returns false when
+        /// none of the models are loaded.
+        ///
+        /// The originals are kept, showing them again and unloading the
+        /// batch undoes the merge.
+        pub fn merge_models(
+                &mut self,
+                handles: &[String],
+                new_handle: impl Into<String>,
+        ) -> bool
+        {
+                let new_handle = new_handle.into();
+
+                let Some(state) = self.state.as_mut()
+                else
+                {
+                        return false;
+                };
+
+                let models: Vec<&Model> = handles
+                        .iter()
+                        .filter(|h| **h != new_handle)
+                        .filter_map(|h| state.models.get(h))
+                        .collect();
+
+                if models.is_empty()
+                {
+                        log::warn!("merge_models: none of {:?} are loaded", handles);
+                        return false;
+                }
+
+                let batch = Model::merge(&models, &state.device);
+
+                log::info!(
+                        "Merged {} models into {}, {} draw calls",
+                        models.len(),
+                        new_handle,
+                        batch.meshes.len()
+                );
+
+                for handle in handles
+                {
+                        if let Some(model) = state.models.get_mut(handle)
+                        {
+                                model.visible = false;
+                        }
+                }
+
+                state.models.insert(new_handle.clone(), batch);
+                state.selection.set(Some(&new_handle));
+
+                true
+        }
+}
@@ -0,0 +1,156 @@
+//! Quitting the app from game code, and cleanup before it exits.
+//!
+//! [`Engine::request_exit`] ends the event loop after the current event.
+//! Before [`EngineRunner::run`](crate::engine::EngineRunner::run) returns,
+//! the engine tears down in order:
+//!
+//! 1. waits for the GPU to finish the submitted frames,
+//! 2. saves the pipeline cache, see
+//!    [`EngineBuilder::with_pipeline_cache`](crate::engine::EngineBuilder::with_pipeline_cache),
+//! 3. writes the config file if the settings changed, see
+//!    [`Config::load_file`](crate::config::Config::load_file),
+//! 4. runs the [`Engine::on_shutdown`] hooks, in the order they were
+//!    registered.
+//!
+//! ```ignore
+//! engine.on_shutdown(|eng| {
+//!         eng.save_game("autosave");
+//! });
+//!
+//! engine.register_behavior(|eng| {
+//!         if eng.pressed_keys.contains(&KeyCode::Escape)
+//!         {
+//!                 eng.request_exit();
+//!         }
+//! });
+//! ```
+
+use crate::engine::Engine;
+use winit::event_loop::ActiveEventLoop;
+
+pub type ShutdownHook = Box<dyn FnOnce(&mut Engine)>;
+
+/// Exit request and cleanup hooks, owned by the [`Engine`].
+#[derive(Default)]
+pub struct Shutdown
+{
+        hooks: Vec<ShutdownHook>,
+
+        exit_requested: bool,
+
+        /// Teardown ran, winit may report exiting more than once.
+        done: bool,
+
+        /// Other engines run on the same event loop, see
+        /// [`EngineRunner::with_engines`](crate::engine::EngineRunner::with_engines).
+        pub(crate) shares_event_loop: bool,
+}
+
+impl std::fmt::Debug for Shutdown
+{
+        fn fmt(
+                &self,
+                f: &mut std::fmt::Formatter<'_>,
+        ) -> std::fmt::Result
+        {
+                f.debug_struct("Shutdown")
+                        .field("hooks", &self.hooks.len())
+                        .field("exit_requested", &self.exit_requested)
+                        .field("done", &self.done)
+                        .field("shares_event_loop", &self.shares_event_loop)
+                        .finish()
+        }
+}
+
+impl Shutdown
+{
+        pub fn new() -> Self
+        {
+                Self::default()
+        }
+
+        /// See [`Engine::request_exit`].
+        pub fn exit_requested(&self) -> bool
+        {
+                self.exit_requested
+        }
+
+        /// The engine tore down and can be dropped.
+        pub fn exited(&self) -> bool
+        {
+                self.done
+        }
+
+        /// Ends the engine. Alone on the event loop, the loop exits and tears
+        /// the engine down, otherwise it's torn down right away and the
+        /// others keep running.
+        pub(crate) fn exit(
+                engine: &mut Engine,
+                event_loop: &ActiveEventLoop,
+        )
+        {
+                if engine.shutdown.shares_event_loop
+                {
+                        Self::run(engine);
+                }
+                else
+                {
+                        event_loop.exit();
+                }
+        }
+
+        /// Tears the engine down, see [`crate::shutdown`].
+        ///
+        /// Called by the engine when the event loop exits.
+        pub(crate) fn run(engine: &mut Engine)
+        {
+                if std::mem::replace(&mut engine.shutdown.done, true)
+                {
+                        return;
+                }
+
+                if let Some(state) = engine.state.as_ref()
+                        && let Err(e) = state.device.poll(wgpu::PollType::Wait)
+                {
+                        log::warn!("Failed to wait for the GPU: {}", e);
+                }
+
+                #[cfg(not(target_arch = "wasm32"))]
+                if let Some(state) = engine.state.as_ref()
+                        && let Err(e) = state.pipeline_manager.save_cache()
+                {
+                        log::warn!("Failed to save the pipeline cache: {}", e);
+                }
+
+                engine.config.save_file_if_changed();
+
+                // Hooks registered by a hook run too.
+                while !engine.shutdown.hooks.is_empty()
+                {
+                        for hook in std::mem::take(&mut engine.shutdown.hooks)
+                        {
+                                hook(engine);
+                        }
+                }
+        }
+}
+
+impl Engine
+{
+        /// Exits the app after the current event, without asking the
+        /// [`Engine::on_window_event`] hooks. See [`crate::shutdown`].
+        pub fn request_exit(&mut self)
+        {
+                self.shutdown.exit_requested = true;
+        }
+
+        /// Runs `hook` once before the app exits, see [`crate::shutdown`].
+        pub fn on_shutdown<F>(
+                &mut self,
+                hook: F,
+        ) where
+                F: 'static + FnOnce(&mut Engine),
+        {
+                self.shutdown.hooks.push(Box::new(hook));
+        }
+}
@@ -0,0 +1,267 @@
+//! Snapping of models edited in the debug UI, for placing a scene by hand.
+//!
+//! With [`SnapSettings::enabled`], positions edited in the models window
+//! land on multiples of [`SnapSettings::translate`], Euler angles on
+//! multiples of [`SnapSettings::rotate`] and scales on multiples of
+//! [`SnapSettings::scale`]. Only what was edited is snapped, a model
+//! that was only moved keeps its rotation. With [`SnapSettings::surface`]
+//! a moved model also drops onto whatever is below it.
+//!
+//! The settings are part of the [`Config`](crate::config::Config) and
+//! shown next to the grid in the debug UI:
+//!
+//! ```ignore
+//! let mut engine = EngineBuilder::new().with_debug_ui().build()?;
+//!
+//! engine.config.snapping = SnapSettings {
+//!         enabled: true,
+//!         translate: 0.5,
+//!         ..Default::default()
+//! };
+//! ```
+//!
+//! Placing models under the cursor, e.g. from a tool of the game, works
+//! with [`Engine::place_on_surface`]. The placed scene is kept with
+//! [`Engine::save_game`](crate::engine::Engine::save_game).
+
+use crate::engine::Engine;
+use crate::math::collision::Ray;
+use crate::model::Model;
+use crate::transform::Transform;
+use cgmath::{Point3, Vector3};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SnapSettings
+{
+        pub enabled: bool,
+
+        /// Distance positions snap to, in world units.
+        pub translate: f32,
+
+        /// Angle the Euler angles snap to, in degrees.
+        pub rotate: f32,
+
+        /// Step scales snap to.
+        pub scale: f32,
+
+        /// Drops moved models onto the model below them, see
+        /// [`Engine::place_on_surface`].
+        pub surface: bool,
+}
+
+impl Default for SnapSettings
+{
+        fn default() -> Self
+        {
+                Self {
+                        enabled: false,
+                        translate: 1.0,
+                        rotate: 15.0,
+                        scale: 0.1,
+                        surface: false,
+                }
+        }
+}
+
+/// `value` rounded to the nearest multiple of `step`, as is for steps of
+/// zero or less.
+fn snap(
+        value: f32,
+        step: f32,
+) -> f32
+{
+        if step > 0.0
+        {
+                (value / step).round() * step
+        }
+        else
+        {
+                value
+        }
+}
+
+impl SnapSettings
+{
+        pub fn snap_position(
+                &self,
+                position: Point3<f32>,
+        ) -> Point3<f32>
+        {
+                position.map(|v| snap(v, self.translate))
+        }
+
+        /// Euler angles in degrees, see [`Transform::euler_angles`].
+        pub fn snap_euler_angles(
+                &self,
+                angles: [f32; 3],
+        ) -> [f32; 3]
+        {
+                angles.map(|v| snap(v, self.rotate))
+        }
+
+        /// Snapped scales stay above zero.
+        pub fn snap_scale(
+                &self,
+                scale: Vector3<f32>,
+        ) -> Vector3<f32>
+        {
+                scale.map(|v| {
+                        let snapped = snap(v, self.scale);
+
+                        if snapped.abs() > f32::EPSILON
+                        {
+                                snapped
+                        }
+                        else
+                        {
+                                v
+                        }
+                })
+        }
+
+        /// Snaps the parts of `transform` that differ from `before`.
+        pub fn apply(
+                &self,
+                before: &Transform,
+                transform: &mut Transform,
+        )
+        {
+                if transform.position != before.position
+                {
+                        transform.position = self.snap_position(transform.position);
+                }
+
+                if transform.rotation != before.rotation
+                {
+                        transform
+                                .set_euler_angles(self.snap_euler_angles(transform.euler_angles()));
+                }
+
+                if transform.scale != before.scale
+                {
+                        transform.scale = self.snap_scale(transform.scale);
+                }
+        }
+
+        #[cfg(feature = "ui")]
+        pub fn ui(
+                &mut self,
+                ui: &mut egui::Ui,
+                grid_spacing: f32,
+        )
+        {
+                egui::CollapsingHeader::new("Snapping").show(ui, |ui| {
+                        ui.checkbox(&mut self.enabled, "Enabled");
+
+                        ui.horizontal(|ui| {
+                                ui.label("Translate");
+                                ui.add(egui::DragValue::new(&mut self.translate)
+                                        .speed(0.05)
+                                        .range(0.0..=100.0));
+
+                                if ui.button("Grid").clicked()
+                                {
+                                        self.translate = grid_spacing;
+                                }
+                        });
+
+                        ui.horizontal(|ui| {
+                                ui.label("Rotate");
+                                ui.add(egui::DragValue::new(&mut self.rotate)
+                                        .speed(1.0)
+                                        .range(0.0..=180.0)
+                                        .suffix("°"));
+                        });
+
+                        ui.horizontal(|ui| {
+                                ui.label("Scale");
+                                ui.add(egui::DragValue::new(&mut self.scale)
+                                        .speed(0.01)
+                                        .range(0.0..=10.0));
+                        });
+
+                        ui.checkbox(&mut self.surface, "Drop onto surfaces");
+                });
+        }
+}
+
+/// Puts the model `handle` where `ray` first hits another visible model,
+/// the bottom of its bounds on the hit. Tested against the oriented
+/// bounds like [`Engine::raycast`]. Returns false when nothing was hit.
+pub(crate) fn place_on_surface(
+        models: &mut HashMap<String, Model>,
+        handle: &str,
+        ray: &Ray,
+) -> bool
+{
+        let hit = models
+                .iter()
+                .filter(|(other, model)| other.as_str() != handle && model.visible)
+                .filter_map(|(_, model)| ray.intersect_obb(&model.world_obb()?))
+                .min_by(f32::total_cmp);
+
+        let Some(distance) = hit
+        else
+        {
+                return false;
+        };
+
+        let Some(model) = models.get_mut(handle)
+        else
+        {
+                return false;
+        };
+
+        let point = ray.at(distance);
+
+        // From the position to the bottom of the bounds.
+        let height = model
+                .world_bounds()
+                .map_or(0.0, |b| model.transform.position.y - b.min.y);
+
+        model.transform.position = Point3::new(point.x, point.y + height, point.z);
+
+        true
+}
+
+/// Drops the model `handle` straight down onto the model below it, from
+/// the top of its bounds.
+#[cfg(feature = "ui")]
+pub(crate) fn drop_to_surface(
+        models: &mut HashMap<String, Model>,
+        handle: &str,
+) -> bool
+{
+        let Some(model) = models.get(handle)
+        else
+        {
+                return false;
+        };
+
+        let position = model.transform.position;
+        let top = model.world_bounds().map_or(position.y, |b| b.max.y);
+
+        let ray = Ray::new(Point3::new(position.x, top, position.z), -Vector3::unit_y());
+
+        place_on_surface(models, handle, &ray)
+}
+
+impl Engine
+{
+        /// Puts the model `handle` where `ray` first hits another model,
+        /// e.g. the ray under the cursor from
+        /// [`Camera::ray`](crate::camera::Camera::ray). Returns false when
+        /// nothing was hit or there's no such model.
+        pub fn place_on_surface(
+                &mut self,
+                handle: &str,
+                ray: &Ray,
+        ) -> bool
+        {
+                self.state
+                        .as_mut()
+                        .is_some_and(|state| place_on_surface(&mut state.models, handle, ray))
+        }
+}
@@ -0,0 +1,547 @@
+//! Steering behaviors for simple AI.
+//!
+//! An agent is a model moved by the engine every tick towards, away from
+//! or around a target, accelerating and turning within its
+//! [`SteeringParams`]:
+//!
+//! ```ignore
+//! // A paddle following the ball up and down.
+//! engine.steer(
+//!         "right_paddle",
+//!         Steering::Arrive(SteeringTarget::model("ball")),
+//!         SteeringParams {
+//!                 max_speed: 8.0,
+//!                 axes: Vector3::unit_y(),
+//!                 ..Default::default()
+//!         },
+//! );
+//!
+//! // Switched by game logic later.
+//! if let Some(agent) = engine.agent_mut("right_paddle")
+//! {
+//!         agent.steering = Steering::Wander;
+//! }
+//! ```
+//!
+//! Agents are stepped on engine ticks with the
+//! [`Scheduler`](crate::scheduler::Scheduler), so they move the same way on
+//! every machine and in replays, and are drawn between their last two ticks
+//! by [`Engine::lerp_alpha`]. Setting the position of an agent's model
+//! directly moves the agent there. The [`seek`], [`flee`], [`arrive`] and
+//! [`wander`] functions behind them work on plain vectors, for behaviors
+//! moving things themselves.
+
+use crate::engine::Engine;
+use cgmath::{EuclideanSpace, InnerSpace, Point3, Quaternion, Rad, Rotation3, Vector3, Zero};
+use rand::Rng;
+use std::collections::BTreeMap;
+
+/// Where an agent steers to or away from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SteeringTarget
+{
+        Point(Point3<f32>),
+        /// The position of a model, the agent stands still while it's
+        /// missing.
+        Model(String),
+}
+
+impl SteeringTarget
+{
+        pub fn model(handle: impl Into<String>) -> Self
+        {
+                Self::Model(handle.into())
+        }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Steering
+{
+        /// Heads to the target at full speed, overshooting it.
+        Seek(SteeringTarget),
+        /// Runs from the target while it's within `panic_distance`, slows
+        /// down further away.
+        Flee
+        {
+                target: SteeringTarget,
+                panic_distance: f32,
+        },
+        /// Heads to the target and slows down to stop on it, see
+        /// [`SteeringParams::slowing_radius`].
+        Arrive(SteeringTarget),
+        /// Roams around randomly, see [`SteeringParams::wander_radius`].
+        Wander,
+        /// Slows down to a stop.
+        Idle,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SteeringParams
+{
+        /// World units per second.
+        pub max_speed: f32,
+        /// World units per second squared.
+        pub max_acceleration: f32,
+        /// Distance to the target [`Steering::Arrive`] starts slowing down
+        /// at.
+        pub slowing_radius: f32,
+        /// Radius of the circle ahead of the agent [`Steering::Wander`]
+        /// picks its heading on, larger turns more.
+        pub wander_radius: f32,
+        /// Distance of that circle ahead of the agent.
+        pub wander_distance: f32,
+        /// How far the heading on the circle moves per second, in radians.
+        pub wander_jitter: f32,
+        /// Scale of the movement along X, Y and Z, e.g.
+        /// [`Vector3::unit_y`] for a paddle moving up and down only.
+        pub axes: Vector3<f32>,
+        /// Turns the model about Y so its +Z faces where it moves.
+        pub face_movement: bool,
+}
+
+impl Default for SteeringParams
+{
+        fn default() -> Self
+        {
+                Self {
+                        max_speed: 5.0,
+                        max_acceleration: 20.0,
+                        slowing_radius: 2.0,
+                        wander_radius: 1.0,
+                        wander_distance: 2.0,
+                        wander_jitter: 3.0,
+                        axes: Vector3::new(1.0, 0.0, 1.0),
+                        face_movement: false,
+                }
+        }
+}
+
+/// A model moved by a [`Steering`] behavior.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Agent
+{
+        pub steering: Steering,
+        pub params: SteeringParams,
+        /// World units per second, kept between ticks.
+        pub velocity: Vector3<f32>,
+        /// Heading on the wander circle in radians.
+        pub wander_angle: f32,
+        /// Position on the previous and the latest tick, `None` until the
+        /// first tick.
+        ticks: Option<(Point3<f32>, Point3<f32>)>,
+        /// Position the model was last drawn at, to notice it being moved.
+        drawn: Point3<f32>,
+}
+
+impl Agent
+{
+        pub fn new(
+                steering: Steering,
+                params: SteeringParams,
+        ) -> Self
+        {
+                Self {
+                        steering,
+                        params,
+                        velocity: Vector3::zero(),
+                        wander_angle: 0.0,
+                        ticks: None,
+                        drawn: Point3::origin(),
+                }
+        }
+}
+
+/// The acceleration turning `velocity` into `desired`, within
+/// `max_acceleration`.
+fn steer_towards(
+        velocity: Vector3<f32>,
+        desired: Vector3<f32>,
+        params: &SteeringParams,
+) -> Vector3<f32>
+{
+        truncate(desired - velocity, params.max_acceleration)
+}
+
+fn truncate(
+        v: Vector3<f32>,
+        max: f32,
+) -> Vector3<f32>
+{
+        let length = v.magnitude();
+
+        if length > max && length > 0.0
+        {
+                v * (max / length)
+        }
+        else
+        {
+                v
+        }
+}
+
+/// `v` scaled by `axes`, the movement an agent is allowed.
+fn mask(
+        v: Vector3<f32>,
+        axes: Vector3<f32>,
+) -> Vector3<f32>
+{
+        Vector3::new(v.x * axes.x, v.y * axes.y, v.z * axes.z)
+}
+
+/// The acceleration heading from `position` to `target` at full speed.
+pub fn seek(
+        position: Point3<f32>,
+        velocity: Vector3<f32>,
+        target: Point3<f32>,
+        params: &SteeringParams,
+) -> Vector3<f32>
+{
+        let offset = mask(target - position, params.axes);
+
+        if offset.magnitude2() < f32::EPSILON
+        {
+                return steer_towards(velocity, Vector3::zero(), params);
+        }
+
+        steer_towards(velocity, offset.normalize() * params.max_speed, params)
+}
+
+/// The acceleration running from `target` while it's closer than
+/// `panic_distance`, braking otherwise.
+pub fn flee(
+        position: Point3<f32>,
+        velocity: Vector3<f32>,
+        target: Point3<f32>,
+        panic_distance: f32,
+        params: &SteeringParams,
+) -> Vector3<f32>
+{
+        let offset = mask(position - target, params.axes);
+        let distance = offset.magnitude();
+
+        if distance > panic_distance
+        {
+                return steer_towards(velocity, Vector3::zero(), params);
+        }
+
+        // Straight on the target, any way out will do.
+        let away = if distance > f32::EPSILON
+        {
+                offset / distance
+        }
+        else
+        {
+                mask(Vector3::unit_x(), params.axes)
+        };
+
+        steer_towards(velocity, away * params.max_speed, params)
+}
+
+/// The acceleration heading from `position` to `target`, slowing down
+/// within [`SteeringParams::slowing_radius`] to stop on it.
+pub fn arrive(
+        position: Point3<f32>,
+        velocity: Vector3<f32>,
+        target: Point3<f32>,
+        params: &SteeringParams,
+) -> Vector3<f32>
+{
+        let offset = mask(target - position, params.axes);
+        let distance = offset.magnitude();
+
+        if distance < f32::EPSILON
+        {
+                return steer_towards(velocity, Vector3::zero(), params);
+        }
+
+        let speed = if params.slowing_radius > 0.0
+        {
+                params.max_speed * (distance / params.slowing_radius).min(1.0)
+        }
+        else
+        {
+                params.max_speed
+        };
+
+        steer_towards(velocity, offset / distance * speed, params)
+}
+
+/// The acceleration towards a point moving randomly on a circle ahead of
+/// the agent. `angle` is its heading on the circle, moved by up to
+/// [`SteeringParams::wander_jitter`] times `dt`.
+pub fn wander(
+        position: Point3<f32>,
+        velocity: Vector3<f32>,
+        angle: &mut f32,
+        dt: f32,
+        rng: &mut impl Rng,
+        params: &SteeringParams,
+) -> Vector3<f32>
+{
+        *angle += rng.random_range(-1.0..=1.0) * params.wander_jitter * dt;
+
+        let heading = if velocity.magnitude2() > f32::EPSILON
+        {
+                velocity.normalize()
+        }
+        else
+        {
+                mask(Vector3::unit_z(), params.axes)
+        };
+
+        // On the plane the agent moves in, or XZ for an agent moving
+        // freely.
+        let (sin, cos) = angle.sin_cos();
+        let around = if params.axes.y != 0.0 && params.axes.z == 0.0
+        {
+                Vector3::new(cos, sin, 0.0)
+        }
+        else
+        {
+                Vector3::new(cos, 0.0, sin)
+        };
+
+        let target = position + heading * params.wander_distance + around * params.wander_radius;
+
+        seek(position, velocity, target, params)
+}
+
+/// The steering agents by model handle.
+#[derive(Debug)]
+pub struct SteeringSystem
+{
+        agents: BTreeMap<String, Agent>,
+}
+
+impl Default for SteeringSystem
+{
+        fn default() -> Self
+        {
+                Self::new()
+        }
+}
+
+impl SteeringSystem
+{
+        pub fn new() -> Self
+        {
+                Self {
+                        agents: BTreeMap::new(),
+                }
+        }
+
+        pub fn len(&self) -> usize
+        {
+                self.agents.len()
+        }
+
+        pub fn is_empty(&self) -> bool
+        {
+                self.agents.is_empty()
+        }
+
+        /// Whether `model` is a steering agent.
+        pub fn moves(
+                &self,
+                model: &str,
+        ) -> bool
+        {
+                self.agents.contains_key(model)
+        }
+
+        /// Where the agent moving `model` is on the latest tick, the model
+        /// itself is drawn between ticks.
+        pub fn position(
+                &self,
+                model: &str,
+        ) -> Option<Point3<f32>>
+        {
+                self.agents.get(model)?.ticks.map(|(_, current)| current)
+        }
+
+        /// Moves every agent by one tick.
+        ///
+        /// Called by the engine once per tick.
+        pub(crate) fn update(engine: &mut Engine)
+        {
+                let dt = engine.tps_interval.as_secs_f32();
+
+                let Some(state) = engine.state.as_mut()
+                else
+                {
+                        return;
+                };
+
+                let agents = &mut engine.steering.agents;
+
+                // Agents are moved after all targets were read, so the order
+                // agents are stepped in doesn't matter.
+                let mut positions = BTreeMap::new();
+
+                for (handle, agent) in agents.iter_mut()
+                {
+                        let Some(model) = state.models.get(handle)
+                        else
+                        {
+                                continue;
+                        };
+
+                        let position = match agent.ticks
+                        {
+                                Some((_, current)) if model.transform.position == agent.drawn =>
+                                {
+                                        current
+                                }
+                                _ => model.transform.position,
+                        };
+
+                        positions.insert(handle.clone(), position);
+                }
+
+                for (handle, agent) in agents.iter_mut()
+                {
+                        let target = |target: &SteeringTarget| match target
+                        {
+                                SteeringTarget::Point(point) => Some(*point),
+                                SteeringTarget::Model(handle) =>
+                                {
+                                        positions.get(handle).copied().or_else(|| {
+                                                state.models
+                                                        .get(handle)
+                                                        .map(|m| m.transform.position)
+                                        })
+                                }
+                        };
+
+                        let Some(&position) = positions.get(handle)
+                        else
+                        {
+                                continue;
+                        };
+
+                        let params = &agent.params;
+                        let stop = steer_towards(agent.velocity, Vector3::zero(), params);
+
+                        let acceleration = match &agent.steering
+                        {
+                                Steering::Seek(t) => target(t).map_or(stop, |t| {
+                                        seek(position, agent.velocity, t, params)
+                                }),
+                                Steering::Flee {
+                                        target: t,
+                                        panic_distance,
+                                } => target(t).map_or(stop, |t| {
+                                        flee(position, agent.velocity, t, *panic_distance, params)
+                                }),
+                                Steering::Arrive(t) => target(t).map_or(stop, |t| {
+                                        arrive(position, agent.velocity, t, params)
+                                }),
+                                Steering::Wander => wander(
+                                        position,
+                                        agent.velocity,
+                                        &mut agent.wander_angle,
+                                        dt,
+                                        &mut engine.rng,
+                                        params,
+                                ),
+                                Steering::Idle => stop,
+                        };
+
+                        agent.velocity = mask(
+                                truncate(agent.velocity + acceleration * dt, params.max_speed),
+                                params.axes,
+                        );
+
+                        agent.ticks = Some((position, position + agent.velocity * dt));
+
+                        let Some(model) = state.models.get_mut(handle)
+                        else
+                        {
+                                continue;
+                        };
+
+                        agent.drawn = model.transform.position;
+
+                        let flat = Vector3::new(agent.velocity.x, 0.0, agent.velocity.z);
+
+                        if agent.params.face_movement && flat.magnitude2() > 1e-4
+                        {
+                                model.transform.rotation =
+                                        Quaternion::from_angle_y(Rad(flat.x.atan2(flat.z)));
+                        }
+                }
+        }
+
+        /// Places every agent's model between its last two ticks.
+        ///
+        /// Called by the engine once per frame.
+        pub(crate) fn interpolate(engine: &mut Engine)
+        {
+                let Some(state) = engine.state.as_mut()
+                else
+                {
+                        return;
+                };
+
+                let alpha = engine.lerp_alpha.clamp(0.0, 1.0);
+
+                for (handle, agent) in engine.steering.agents.iter_mut()
+                {
+                        let (Some((previous, current)), Some(model)) =
+                                (agent.ticks, state.models.get_mut(handle))
+                        else
+                        {
+                                continue;
+                        };
+
+                        // Moved by game logic since the last frame, the next
+                        // tick carries on from there.
+                        if model.transform.position != agent.drawn
+                        {
+                                continue;
+                        }
+
+                        model.transform.position = previous + (current - previous) * alpha;
+                        agent.drawn = model.transform.position;
+                }
+        }
+}
+
+impl Engine
+{
+        /// Moves `model` with `steering` every tick until
+        /// [`Engine::stop_steering`], replacing its current behavior.
+        pub fn steer(
+                &mut self,
+                model: impl Into<String>,
+                steering: Steering,
+                params: SteeringParams,
+        ) -> &mut Agent
+        {
+                let model = model.into();
+
+                self.steering
+                        .agents
+                        .insert(model.clone(), Agent::new(steering, params));
+
+                self.steering.agents.get_mut(&model).unwrap()
+        }
+
+        /// Stops moving `model`, where it is.
+        pub fn stop_steering(
+                &mut self,
+                model: &str,
+        ) -> Option<Agent>
+        {
+                self.steering.agents.remove(model)
+        }
+
+        /// The agent moving `model`, to change its behavior or parameters.
+        pub fn agent_mut(
+                &mut self,
+                model: &str,
+        ) -> Option<&mut Agent>
+        {
+                self.steering.agents.get_mut(model)
+        }
+}
@@ -0,0 +1,225 @@
+//! Texture streaming with resident mip levels.
+//!
+//! Large scenes load their base color textures small and get the larger
+//! mip levels as the camera comes close, so loading is quicker and GPU
+//! memory doesn't spike:
+//!
+//! ```ignore
+//! let mut engine = EngineBuilder::new()
+//!         .with_texture_streaming(256, Some(512 * 1024 * 1024))
+//!         .build()?;
+//! ```
+//!
+//! Every frame the on-screen size of each model is estimated from its
+//! bounds and the camera. Materials whose texture is too coarse for it are
+//! queued, the ones missing the most detail first, and a few are uploaded
+//! per frame while the streamed textures stay under the budget. Over the
+//! budget, e.g. after the camera moved on, textures with more detail than
+//! their model needs go back to smaller levels. Larger levels also wait
+//! for the frame's upload budget, see [`crate::upload`].
+//!
+//! The full resolution pixels stay in memory on the CPU to be uploaded
+//! from. Only models loaded after streaming is turned on are streamed.
+
+use crate::engine::Engine;
+use crate::texture::StreamSource;
+use cgmath::{InnerSpace, MetricSpace};
+
+/// Streaming settings and the last measured usage, owned by the [`Engine`].
+#[derive(Debug)]
+pub struct TextureStreaming
+{
+        /// Bytes the streamed textures may use together, `None` for no
+        /// limit.
+        pub budget: Option<u64>,
+
+        /// Largest side base color textures of models loaded afterwards
+        /// start with, `None` uploads them whole and doesn't stream them.
+        pub initial_size: Option<u32>,
+
+        /// Textures uploaded per frame at most, spreading out the work.
+        pub uploads_per_frame: usize,
+
+        resident: u64,
+}
+
+impl Default for TextureStreaming
+{
+        fn default() -> Self
+        {
+                Self {
+                        budget: None,
+                        initial_size: None,
+                        uploads_per_frame: 2,
+                        resident: 0,
+                }
+        }
+}
+
+/// A material whose resident level differs from the one it needs.
+struct Request
+{
+        handle: String,
+        slot: usize,
+        level: u32,
+        /// Levels between the resident and the needed one.
+        priority: u32,
+        /// Bytes the change adds, negative when it frees memory.
+        cost: i64,
+        /// Bytes the change uploads.
+        bytes: u64,
+}
+
+impl TextureStreaming
+{
+        pub fn new() -> Self
+        {
+                Self::default()
+        }
+
+        /// Bytes the streamed textures used last frame.
+        pub fn resident_bytes(&self) -> u64
+        {
+                self.resident
+        }
+
+        /// Uploads the levels the models need next.
+        ///
+        /// Called by the engine once per frame.
+        pub(crate) fn update(engine: &mut Engine)
+        {
+                let Some(state) = engine.state.as_mut()
+                else
+                {
+                        return;
+                };
+
+                let streaming = &mut engine.texture_streaming;
+
+                let camera = state.camera.core.position;
+                let screen_height = state.surface_manager.configuration.height as f32;
+                let half_fov = (state.camera.projection.fovy.0 / 2.0).tan();
+
+                let mut upgrades = Vec::new();
+                let mut downgrades = Vec::new();
+
+                streaming.resident = 0;
+
+                for (handle, model) in &state.models
+                {
+                        // Hidden models need no more than the coarsest level.
+                        let coverage = match model.world_bounds()
+                        {
+                                Some(bounds) if model.visible =>
+                                {
+                                        let radius = bounds.size().magnitude() / 2.0;
+                                        let distance = bounds.center().distance(camera).max(radius);
+
+                                        radius / (distance * half_fov) * screen_height
+                                }
+                                _ => 1.0,
+                        };
+
+                        for (slot, material) in model.materials.iter().enumerate()
+                        {
+                                let Some(stream) = &material.stream
+                                else
+                                {
+                                        continue;
+                                };
+
+                                streaming.resident += stream.bytes(stream.resident_level);
+
+                                let level = stream.level_for(coverage.ceil() as u32);
+
+                                let request = Request {
+                                        handle: handle.clone(),
+                                        slot,
+                                        level,
+                                        priority: level.abs_diff(stream.resident_level),
+                                        cost: Self::cost(stream, level),
+                                        bytes: stream.bytes(level),
+                                };
+
+                                match level.cmp(&stream.resident_level)
+                                {
+                                        std::cmp::Ordering::Less => upgrades.push(request),
+                                        std::cmp::Ordering::Greater => downgrades.push(request),
+                                        std::cmp::Ordering::Equal =>
+                                        {}
+                                }
+                        }
+                }
+
+                let budget = streaming.budget.unwrap_or(u64::MAX);
+                let mut resident = streaming.resident as i64;
+                let mut uploads = 0;
+
+                // Memory is freed first, the most oversized textures first.
+                downgrades.sort_by_key(|request| std::cmp::Reverse(request.priority));
+
+                for request in downgrades
+                {
+                        if resident as u64 <= budget || uploads == streaming.uploads_per_frame
+                        {
+                                break;
+                        }
+
+                        Self::apply(state, &request);
+
+                        resident += request.cost;
+                        uploads += 1;
+                }
+
+                upgrades.sort_by_key(|request| std::cmp::Reverse(request.priority));
+
+                for request in upgrades
+                {
+                        if uploads == streaming.uploads_per_frame
+                        {
+                                break;
+                        }
+
+                        if (resident + request.cost) as u64 > budget
+                        {
+                                continue;
+                        }
+
+                        if !engine.uploads.try_spend(request.bytes)
+                        {
+                                break;
+                        }
+
+                        Self::apply(state, &request);
+
+                        resident += request.cost;
+                        uploads += 1;
+                }
+
+                streaming.resident = resident as u64;
+        }
+
+        fn cost(
+                stream: &StreamSource,
+                level: u32,
+        ) -> i64
+        {
+                stream.bytes(level) as i64 - stream.bytes(stream.resident_level) as i64
+        }
+
+        fn apply(
+                state: &mut crate::engine::EngineState,
+                request: &Request,
+        )
+        {
+                if let Some(model) = state.models.get_mut(&request.handle)
+                {
+                        model.stream_material(
+                                &state.device,
+                                &state.queue,
+                                request.slot,
+                                request.level,
+                        );
+                }
+        }
+}
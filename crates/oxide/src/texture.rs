@@ -1,4 +1,114 @@
 use image::{ImageBuffer, Rgba};
+use std::sync::Arc;
+
+/// How the textures of a model are uploaded, set per engine, see
+/// [`ModelData::upload_with_limits`](crate::resources::ModelData::upload_with_limits).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TextureLimits
+{
+        /// Largest side of loaded textures, e.g. to save memory on the web.
+        /// Larger ones are scaled down on load. The device limit applies
+        /// either way. See
+        /// [`EngineBuilder::with_max_texture_size`](crate::engine::EngineBuilder::with_max_texture_size).
+        pub max_size: Option<u32>,
+        /// Largest side streamed base color textures start with, see
+        /// [`crate::streaming`]. `None` uploads them whole.
+        pub stream_initial_size: Option<u32>,
+}
+
+impl TextureLimits
+{
+        /// Largest side a texture loaded on `device` may have.
+        pub fn max_size(
+                &self,
+                device: &wgpu::Device,
+        ) -> u32
+        {
+                let limit = device.limits().max_texture_dimension_2d;
+
+                self.max_size.map_or(limit, |size| size.min(limit))
+        }
+}
+
+/// Full resolution pixels of a streamed texture, of which only the mip
+/// levels from [`StreamSource::resident_level`] down are on the GPU.
+#[derive(Debug, Clone)]
+pub struct StreamSource
+{
+        /// RGBA8, shared by the materials using the image.
+        pub pixels: Arc<Vec<u8>>,
+        pub width: u32,
+        pub height: u32,
+        pub format: wgpu::TextureFormat,
+        /// First mip level on the GPU, 0 is full resolution.
+        pub resident_level: u32,
+}
+
+impl StreamSource
+{
+        /// Mip levels down to 1x1.
+        pub fn levels(&self) -> u32
+        {
+                self.width.max(self.height).max(1).ilog2() + 1
+        }
+
+        pub fn level_size(
+                &self,
+                level: u32,
+        ) -> (u32, u32)
+        {
+                ((self.width >> level).max(1), (self.height >> level).max(1))
+        }
+
+        /// Smallest level that is still `side` texels across.
+        pub fn level_for(
+                &self,
+                side: u32,
+        ) -> u32
+        {
+                let full = self.width.max(self.height).max(1);
+
+                (full / side.clamp(1, full)).ilog2().min(self.levels() - 1)
+        }
+
+        /// Bytes on the GPU with `level` resident.
+        pub fn bytes(
+                &self,
+                level: u32,
+        ) -> u64
+        {
+                (level..self.levels())
+                        .map(|l| self.level_size(l))
+                        .map(|(w, h)| w as u64 * h as u64 * 4)
+                        .sum()
+        }
+
+        /// Uploads `level` and the mips below it.
+        pub fn upload(
+                &self,
+                device: &wgpu::Device,
+                queue: &wgpu::Queue,
+                level: u32,
+                label: &str,
+        ) -> Texture
+        {
+                let (width, height) = self.level_size(level);
+
+                let pixels = match level
+                {
+                        0 => self.pixels.to_vec(),
+                        _ => Texture::resize_rgba(
+                                &self.pixels,
+                                self.width,
+                                self.height,
+                                width,
+                                height,
+                        ),
+                };
+
+                Texture::from_rgba_mips(device, queue, pixels, width, height, self.format, label)
+        }
+}
 
 #[derive(Debug, Clone)]
 pub struct Texture
@@ -10,6 +120,194 @@ pub struct Texture
 
 impl Texture
 {
+        /// Scales RGBA8 `pixels` down so neither side exceeds `max`, keeping
+        /// the aspect ratio. Returns the pixels and their size.
+        pub fn fit_to_limit(
+                pixels: Vec<u8>,
+                width: u32,
+                height: u32,
+                max: u32,
+                label: &str,
+        ) -> (Vec<u8>, u32, u32)
+        {
+                let expected = width as usize * height as usize * 4;
+
+                // Malformed data is left for the upload to report.
+                if (width <= max && height <= max) || pixels.len() != expected
+                {
+                        return (pixels, width, height);
+                }
+
+                let image = ImageBuffer::<Rgba<u8>, _>::from_raw(width, height, pixels)
+                        .expect("length checked above");
+
+                let scale = max as f64 / width.max(height) as f64;
+                let new_width = ((width as f64 * scale) as u32).clamp(1, max);
+                let new_height = ((height as f64 * scale) as u32).clamp(1, max);
+
+                log::warn!(
+                        "{} is {}x{}, over the limit of {}, scaled down to {}x{}",
+                        label,
+                        width,
+                        height,
+                        max,
+                        new_width,
+                        new_height
+                );
+
+                let resized = image::imageops::resize(
+                        &image,
+                        new_width,
+                        new_height,
+                        image::imageops::FilterType::Triangle,
+                );
+
+                (resized.into_raw(), new_width, new_height)
+        }
+
+        /// Pixels of a glTF image as RGBA8.
+        pub fn gltf_rgba(image: &gltf::image::Data) -> Vec<u8>
+        {
+                match image.format
+                {
+                        gltf::image::Format::R8G8B8A8 => image.pixels.clone(),
+                        gltf::image::Format::R8G8B8 => image
+                                .pixels
+                                .chunks_exact(3)
+                                .flat_map(|c| [c[0], c[1], c[2], 255])
+                                .collect(),
+                        gltf::image::Format::R8G8 => image
+                                .pixels
+                                .chunks_exact(2)
+                                .flat_map(|c| [c[0], c[1], 0, 255])
+                                .collect(),
+                        _ =>
+                        {
+                                log::warn!(
+                                        "Unknown image format {:?}, defaulting to RGBA",
+                                        image.format
+                                );
+                                image.pixels.clone()
+                        }
+                }
+        }
+
+        /// Scales RGBA8 pixels to `width` by `height`.
+        pub fn resize_rgba(
+                pixels: &[u8],
+                from_width: u32,
+                from_height: u32,
+                width: u32,
+                height: u32,
+        ) -> Vec<u8>
+        {
+                let Some(image) =
+                        ImageBuffer::<Rgba<u8>, _>::from_raw(from_width, from_height, pixels)
+                else
+                {
+                        return vec![0; width as usize * height as usize * 4];
+                };
+
+                image::imageops::resize(
+                        &image,
+                        width,
+                        height,
+                        image::imageops::FilterType::Triangle,
+                )
+                .into_raw()
+        }
+
+        /// A texture with a full mip chain, the smaller levels scaled down
+        /// from `pixels` on the CPU.
+        pub fn from_rgba_mips(
+                device: &wgpu::Device,
+                queue: &wgpu::Queue,
+                pixels: Vec<u8>,
+                width: u32,
+                height: u32,
+                format: wgpu::TextureFormat,
+                label: &str,
+        ) -> Self
+        {
+                let levels = width.max(height).max(1).ilog2() + 1;
+
+                let texture = device.create_texture(&wgpu::TextureDescriptor {
+                        label: Some(label),
+                        size: wgpu::Extent3d {
+                                width,
+                                height,
+                                depth_or_array_layers: 1,
+                        },
+                        mip_level_count: levels,
+                        sample_count: 1,
+                        dimension: wgpu::TextureDimension::D2,
+                        format,
+                        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                        view_formats: &[],
+                });
+
+                let (mut pixels, mut w, mut h) = (pixels, width, height);
+
+                for level in 0..levels
+                {
+                        if level > 0
+                        {
+                                let (next_w, next_h) = ((w / 2).max(1), (h / 2).max(1));
+
+                                pixels = Self::resize_rgba(&pixels, w, h, next_w, next_h);
+                                (w, h) = (next_w, next_h);
+                        }
+
+                        queue.write_texture(
+                                wgpu::TexelCopyTextureInfo {
+                                        aspect: wgpu::TextureAspect::All,
+                                        texture: &texture,
+                                        mip_level: level,
+                                        origin: wgpu::Origin3d::ZERO,
+                                },
+                                &pixels,
+                                wgpu::TexelCopyBufferLayout {
+                                        offset: 0,
+                                        bytes_per_row: Some(4 * w),
+                                        rows_per_image: Some(h),
+                                },
+                                wgpu::Extent3d {
+                                        width: w,
+                                        height: h,
+                                        depth_or_array_layers: 1,
+                                },
+                        );
+                }
+
+                Self {
+                        view: texture.create_view(&wgpu::TextureViewDescriptor::default()),
+                        sampler: Self::create_sampler(device),
+                        texture,
+                }
+        }
+
+        /// Estimated bytes on the GPU, every mip level included.
+        pub fn gpu_memory(&self) -> u64
+        {
+                let format = self.texture.format();
+                let (block_width, block_height) = format.block_dimensions();
+                let block_size = format.block_copy_size(None).unwrap_or(4) as u64;
+
+                let size = self.texture.size();
+
+                (0..self.texture.mip_level_count())
+                        .map(|level| {
+                                let width = (size.width >> level).max(1).div_ceil(block_width);
+                                let height = (size.height >> level).max(1).div_ceil(block_height);
+
+                                width as u64
+                                        * height as u64
+                                        * size.depth_or_array_layers as u64
+                                        * block_size
+                        })
+                        .sum()
+        }
+
         /// Create a 1x1 white texture to use as a fallback
         pub fn create_dummy(
                 device: &wgpu::Device,
@@ -0,0 +1,289 @@
+//! Where something is, how it's turned and how large it is.
+//!
+//! [`Model`](crate::model::Model) keeps its placement in one [`Transform`]
+//! instead of loose fields, so the rotation can't disagree with a copy of
+//! it in Euler angles:
+//!
+//! ```ignore
+//! let model = state.models.get_mut("crate").unwrap();
+//!
+//! model.transform = Transform::from_position((0.0, 1.0, -4.0))
+//!         .with_euler_angles([0.0, 45.0, 0.0])
+//!         .with_uniform_scale(2.0);
+//!
+//! model.transform.position.y += 0.5;
+//! ```
+//!
+//! Transforms are relative to the world today. Multiplying a parent's
+//! transform with a child's gives the child's in the parent's space, which
+//! is how world transforms come out of local ones.
+
+use cgmath::{
+        Deg, ElementWise, EuclideanSpace, Euler, InnerSpace, Matrix3, Matrix4, Point3, Quaternion,
+        Rad, Rotation, SquareMatrix, Vector3,
+};
+use std::ops::Mul;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform
+{
+        pub position: Point3<f32>,
+        pub rotation: Quaternion<f32>,
+        pub scale: Vector3<f32>,
+}
+
+impl Default for Transform
+{
+        fn default() -> Self
+        {
+                Self::IDENTITY
+        }
+}
+
+impl Transform
+{
+        /// At the origin, not rotated, at scale 1.
+        pub const IDENTITY: Self = Self {
+                position: Point3::new(0.0, 0.0, 0.0),
+                rotation: Quaternion::new(1.0, 0.0, 0.0, 0.0),
+                scale: Vector3::new(1.0, 1.0, 1.0),
+        };
+
+        pub fn new(
+                position: Point3<f32>,
+                rotation: Quaternion<f32>,
+                scale: Vector3<f32>,
+        ) -> Self
+        {
+                Self {
+                        position,
+                        rotation,
+                        scale,
+                }
+        }
+
+        /// Splits `matrix` into position, rotation and scale. Shear, which a
+        /// transform can't express, is lost.
+        pub fn from_matrix(matrix: &Matrix4<f32>) -> Self
+        {
+                let axes = Matrix3::from_cols(
+                        matrix.x.truncate(),
+                        matrix.y.truncate(),
+                        matrix.z.truncate(),
+                );
+                let mut scale =
+                        Vector3::new(axes.x.magnitude(), axes.y.magnitude(), axes.z.magnitude());
+
+                // A mirrored matrix keeps its mirroring in the scale.
+                if axes.determinant() < 0.0
+                {
+                        scale.x = -scale.x;
+                }
+
+                let rotation = if scale.x * scale.y * scale.z == 0.0
+                {
+                        Quaternion::new(1.0, 0.0, 0.0, 0.0)
+                }
+                else
+                {
+                        Quaternion::from(Matrix3::from_cols(
+                                axes.x / scale.x,
+                                axes.y / scale.y,
+                                axes.z / scale.z,
+                        ))
+                        .normalize()
+                };
+
+                Self {
+                        position: Point3::from_vec(matrix.w.truncate()),
+                        rotation,
+                        scale,
+                }
+        }
+
+        pub fn from_position(position: impl Into<Point3<f32>>) -> Self
+        {
+                Self {
+                        position: position.into(),
+                        ..Self::IDENTITY
+                }
+        }
+
+        pub fn with_position(
+                self,
+                position: impl Into<Point3<f32>>,
+        ) -> Self
+        {
+                Self {
+                        position: position.into(),
+                        ..self
+                }
+        }
+
+        pub fn with_rotation(
+                self,
+                rotation: Quaternion<f32>,
+        ) -> Self
+        {
+                Self {
+                        rotation,
+                        ..self
+                }
+        }
+
+        /// See [`Transform::set_euler_angles`].
+        pub fn with_euler_angles(
+                mut self,
+                degrees: [f32; 3],
+        ) -> Self
+        {
+                self.set_euler_angles(degrees);
+                self
+        }
+
+        pub fn with_scale(
+                self,
+                scale: Vector3<f32>,
+        ) -> Self
+        {
+                Self {
+                        scale,
+                        ..self
+                }
+        }
+
+        pub fn with_uniform_scale(
+                self,
+                scale: f32,
+        ) -> Self
+        {
+                self.with_scale(Vector3::new(scale, scale, scale))
+        }
+
+        /// The rotation as angles about X, Y and Z in degrees, each -180 to
+        /// 180.
+        pub fn euler_angles(&self) -> [f32; 3]
+        {
+                let euler = Euler::from(self.rotation);
+
+                [euler.x, euler.y, euler.z].map(|angle| {
+                        let degrees = angle.0.to_degrees() % 360.0;
+
+                        if degrees > 180.0
+                        {
+                                degrees - 360.0
+                        }
+                        else if degrees < -180.0
+                        {
+                                degrees + 360.0
+                        }
+                        else
+                        {
+                                degrees
+                        }
+                })
+        }
+
+        /// Replaces the rotation with angles about X, Y and Z in degrees.
+        pub fn set_euler_angles(
+                &mut self,
+                degrees: [f32; 3],
+        )
+        {
+                let [x, y, z] = degrees.map(|d| Rad::from(Deg(d)));
+
+                self.rotation = Quaternion::from(Euler::new(x, y, z)).normalize();
+        }
+
+        /// Scales, then rotates, then moves.
+        pub fn matrix(&self) -> Matrix4<f32>
+        {
+                Matrix4::from_translation(self.position.to_vec())
+                        * Matrix4::from(self.rotation)
+                        * Matrix4::from_nonuniform_scale(self.scale.x, self.scale.y, self.scale.z)
+        }
+
+        pub fn transform_point(
+                &self,
+                point: Point3<f32>,
+        ) -> Point3<f32>
+        {
+                self.position + self.transform_vector(point.to_vec())
+        }
+
+        /// `vector` scaled and rotated, not moved.
+        pub fn transform_vector(
+                &self,
+                vector: Vector3<f32>,
+        ) -> Vector3<f32>
+        {
+                self.rotation
+                        .rotate_vector(vector.mul_element_wise(self.scale))
+        }
+
+        /// The local +Z axis in world space.
+        pub fn forward(&self) -> Vector3<f32>
+        {
+                self.rotation.rotate_vector(Vector3::unit_z())
+        }
+
+        pub fn right(&self) -> Vector3<f32>
+        {
+                self.rotation.rotate_vector(Vector3::unit_x())
+        }
+
+        pub fn up(&self) -> Vector3<f32>
+        {
+                self.rotation.rotate_vector(Vector3::unit_y())
+        }
+
+        /// Between `self` at 0 and `other` at 1, the rotation spherically
+        /// along the shorter arc, see [`crate::math::interpolation`].
+        pub fn lerp(
+                &self,
+                other: &Self,
+                t: f32,
+        ) -> Self
+        {
+                Self {
+                        position: self.position + (other.position - self.position) * t,
+                        rotation: self.rotation.slerp(other.rotation, t),
+                        scale: self.scale + (other.scale - self.scale) * t,
+                }
+        }
+}
+
+impl Mul for Transform
+{
+        type Output = Transform;
+
+        /// `rhs` placed in the space of `self`. Exact unless `self` scales
+        /// unevenly and `rhs` is rotated, which a transform can't express.
+        fn mul(
+                self,
+                rhs: Transform,
+        ) -> Transform
+        {
+                Transform {
+                        position: self.transform_point(rhs.position),
+                        rotation: (self.rotation * rhs.rotation).normalize(),
+                        scale: self.scale.mul_element_wise(rhs.scale),
+                }
+        }
+}
+
+impl From<Point3<f32>> for Transform
+{
+        fn from(position: Point3<f32>) -> Self
+        {
+                Self::from_position(position)
+        }
+}
+
+impl From<Transform> for Matrix4<f32>
+{
+        fn from(transform: Transform) -> Self
+        {
+                transform.matrix()
+        }
+}
@@ -0,0 +1,776 @@
+//! Tweening of model transforms, morph weights and arbitrary values.
+//!
+//! Behaviors schedule a [`Tween`] through the engine, e.g.
+//! `engine.tween_position("ball", target, Duration::from_secs_f32(0.5),
+//! Ease::OutQuad)`, and the engine advances it once per frame. A tween is a
+//! queue of steps played one after another, so animations can be chained with
+//! the `then_*` methods, and [`Tween::on_complete`] runs a callback after the
+//! last step.
+//!
+//! The start value of a step is read from the model when the step begins,
+//! not when it is scheduled, so chained steps continue from wherever the
+//! previous one ended.
+
+use crate::engine::Engine;
+use crate::model::Model;
+use cgmath::{Point3, Quaternion, Vector3, VectorSpace};
+use instant::Instant;
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+/// Easing curve applied to the normalized time of a step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Ease
+{
+        #[default]
+        Linear,
+        InQuad,
+        OutQuad,
+        InOutQuad,
+        InCubic,
+        OutCubic,
+        InOutCubic,
+        InSine,
+        OutSine,
+        InOutSine,
+        /// Overshoots the target slightly before settling.
+        OutBack,
+}
+
+impl Ease
+{
+        /// Maps `t` in `0.0..=1.0` onto the curve.
+        pub fn apply(
+                &self,
+                t: f32,
+        ) -> f32
+        {
+                use std::f32::consts::PI;
+
+                let t = t.clamp(0.0, 1.0);
+
+                match self
+                {
+                        Ease::Linear => t,
+                        Ease::InQuad => t * t,
+                        Ease::OutQuad => 1.0 - (1.0 - t) * (1.0 - t),
+                        Ease::InOutQuad =>
+                        {
+                                if t < 0.5
+                                {
+                                        2.0 * t * t
+                                }
+                                else
+                                {
+                                        1.0 - (-2.0 * t + 2.0).powi(2) / 2.0
+                                }
+                        }
+                        Ease::InCubic => t * t * t,
+                        Ease::OutCubic => 1.0 - (1.0 - t).powi(3),
+                        Ease::InOutCubic =>
+                        {
+                                if t < 0.5
+                                {
+                                        4.0 * t * t * t
+                                }
+                                else
+                                {
+                                        1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+                                }
+                        }
+                        Ease::InSine => 1.0 - (t * PI / 2.0).cos(),
+                        Ease::OutSine => (t * PI / 2.0).sin(),
+                        Ease::InOutSine => -((PI * t).cos() - 1.0) / 2.0,
+                        Ease::OutBack =>
+                        {
+                                let c1 = 1.70158;
+                                let c3 = c1 + 1.0;
+
+                                1.0 + c3 * (t - 1.0).powi(3) + c1 * (t - 1.0).powi(2)
+                        }
+                }
+        }
+}
+
+/// Identifies a scheduled [`Tween`], used for cancellation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TweenId(pub u64);
+
+/// Callback receiving the interpolated value of [`Engine::tween_value`].
+pub type ValueSetter = Box<dyn FnMut(&mut Engine, f32)>;
+
+/// Callback run once a tween has finished every step.
+pub type TweenCallback = Box<dyn FnOnce(&mut Engine)>;
+
+/// What a single step animates.
+enum Track
+{
+        Position
+        {
+                model: String,
+                from: Option<Point3<f32>>,
+                to: Point3<f32>,
+        },
+        Scale
+        {
+                model: String,
+                from: Option<Vector3<f32>>,
+                to: Vector3<f32>,
+        },
+        Rotation
+        {
+                model: String,
+                from: Option<Quaternion<f32>>,
+                to: Quaternion<f32>,
+        },
+        Morph
+        {
+                model: String,
+                target: String,
+                from: Option<f32>,
+                to: f32,
+        },
+        Value
+        {
+                from: f32,
+                to: f32,
+                set: ValueSetter,
+        },
+        Wait,
+}
+
+impl Track
+{
+        fn model(&self) -> Option<&str>
+        {
+                match self
+                {
+                        Track::Position {
+                                model, ..
+                        }
+                        | Track::Scale {
+                                model, ..
+                        }
+                        | Track::Rotation {
+                                model, ..
+                        }
+                        | Track::Morph {
+                                model, ..
+                        } => Some(model),
+                        Track::Value {
+                                ..
+                        }
+                        | Track::Wait => None,
+                }
+        }
+
+        /// Reads the start value from the model if it hasn't been yet.
+        ///
+        /// Returns `false` if the model doesn't exist.
+        fn capture(
+                &mut self,
+                models: &HashMap<String, Model>,
+        ) -> bool
+        {
+                let m = match self.model()
+                {
+                        None => return true,
+                        Some(name) => match models.get(name)
+                        {
+                                None => return false,
+                                Some(m) => m,
+                        },
+                };
+
+                match self
+                {
+                        Track::Position {
+                                from, ..
+                        } => *from = from.or(Some(m.transform.position)),
+                        Track::Scale {
+                                from, ..
+                        } => *from = from.or(Some(m.transform.scale)),
+                        Track::Rotation {
+                                from, ..
+                        } => *from = from.or(Some(m.transform.rotation)),
+                        Track::Morph {
+                                target,
+                                from,
+                                ..
+                        } => *from = from.or(m.morph_weight(target)).or(Some(0.0)),
+                        Track::Value {
+                                ..
+                        }
+                        | Track::Wait =>
+                        {}
+                }
+
+                true
+        }
+
+        fn apply(
+                &mut self,
+                engine: &mut Engine,
+                t: f32,
+        )
+        {
+                let models = engine.state.as_mut().map(|s| &mut s.models);
+
+                match self
+                {
+                        Track::Position {
+                                model,
+                                from: Some(from),
+                                to,
+                        } =>
+                        {
+                                if let Some(m) = models.and_then(|m| m.get_mut(model))
+                                {
+                                        m.transform.position = *from + (*to - *from) * t;
+                                }
+                        }
+                        Track::Scale {
+                                model,
+                                from: Some(from),
+                                to,
+                        } =>
+                        {
+                                if let Some(m) = models.and_then(|m| m.get_mut(model))
+                                {
+                                        m.transform.scale = from.lerp(*to, t);
+                                }
+                        }
+                        Track::Rotation {
+                                model,
+                                from: Some(from),
+                                to,
+                        } =>
+                        {
+                                if let Some(m) = models.and_then(|m| m.get_mut(model))
+                                {
+                                        m.transform.rotation = from.slerp(*to, t);
+                                }
+                        }
+                        Track::Morph {
+                                model,
+                                target,
+                                from: Some(from),
+                                to,
+                        } =>
+                        {
+                                if let Some(m) = models.and_then(|m| m.get_mut(model))
+                                {
+                                        m.set_morph_weight(target, *from + (*to - *from) * t);
+                                }
+                        }
+                        Track::Value {
+                                from,
+                                to,
+                                set,
+                        } =>
+                        {
+                                let value = *from + (*to - *from) * t;
+
+                                set(engine, value);
+                        }
+                        _ =>
+                        {}
+                }
+        }
+}
+
+struct Step
+{
+        track: Track,
+        duration: Duration,
+        ease: Ease,
+}
+
+/// A chain of interpolations, created by the `Engine::tween_*` methods.
+pub struct Tween
+{
+        id: TweenId,
+
+        steps: VecDeque<Step>,
+
+        /// Time spent in the front step.
+        elapsed: Duration,
+
+        on_complete: Option<TweenCallback>,
+}
+
+impl Tween
+{
+        fn new(
+                id: TweenId,
+                track: Track,
+                duration: Duration,
+                ease: Ease,
+        ) -> Self
+        {
+                let mut tween = Self {
+                        id,
+                        steps: VecDeque::new(),
+                        elapsed: Duration::ZERO,
+                        on_complete: None,
+                };
+
+                tween.push(track, duration, ease);
+
+                tween
+        }
+
+        fn push(
+                &mut self,
+                track: Track,
+                duration: Duration,
+                ease: Ease,
+        ) -> &mut Self
+        {
+                self.steps.push_back(Step {
+                        track,
+                        duration,
+                        ease,
+                });
+
+                self
+        }
+
+        pub fn id(&self) -> TweenId
+        {
+                self.id
+        }
+
+        /// Moves a model to `to` after the previous step.
+        pub fn then_position(
+                &mut self,
+                model: impl Into<String>,
+                to: Point3<f32>,
+                duration: Duration,
+                ease: Ease,
+        ) -> &mut Self
+        {
+                let track = Track::Position {
+                        model: model.into(),
+                        from: None,
+                        to,
+                };
+
+                self.push(track, duration, ease)
+        }
+
+        pub fn then_scale(
+                &mut self,
+                model: impl Into<String>,
+                to: Vector3<f32>,
+                duration: Duration,
+                ease: Ease,
+        ) -> &mut Self
+        {
+                let track = Track::Scale {
+                        model: model.into(),
+                        from: None,
+                        to,
+                };
+
+                self.push(track, duration, ease)
+        }
+
+        pub fn then_rotation(
+                &mut self,
+                model: impl Into<String>,
+                to: Quaternion<f32>,
+                duration: Duration,
+                ease: Ease,
+        ) -> &mut Self
+        {
+                let track = Track::Rotation {
+                        model: model.into(),
+                        from: None,
+                        to,
+                };
+
+                self.push(track, duration, ease)
+        }
+
+        /// Blends morph target `target` of a model to `to` after the
+        /// previous step.
+        pub fn then_morph_weight(
+                &mut self,
+                model: impl Into<String>,
+                target: impl Into<String>,
+                to: f32,
+                duration: Duration,
+                ease: Ease,
+        ) -> &mut Self
+        {
+                let track = Track::Morph {
+                        model: model.into(),
+                        target: target.into(),
+                        from: None,
+                        to,
+                };
+
+                self.push(track, duration, ease)
+        }
+
+        pub fn then_value<F>(
+                &mut self,
+                from: f32,
+                to: f32,
+                duration: Duration,
+                ease: Ease,
+                set: F,
+        ) -> &mut Self
+        where
+                F: 'static + FnMut(&mut Engine, f32),
+        {
+                let track = Track::Value {
+                        from,
+                        to,
+                        set: Box::new(set),
+                };
+
+                self.push(track, duration, ease)
+        }
+
+        /// Pauses the chain for `duration`.
+        pub fn then_wait(
+                &mut self,
+                duration: Duration,
+        ) -> &mut Self
+        {
+                self.push(Track::Wait, duration, Ease::Linear)
+        }
+
+        /// Runs `f` after the last step. Not called if the tween is cancelled.
+        pub fn on_complete<F>(
+                &mut self,
+                f: F,
+        ) -> &mut Self
+        where
+                F: 'static + FnOnce(&mut Engine),
+        {
+                self.on_complete = Some(Box::new(f));
+
+                self
+        }
+}
+
+/// Active tweens of an [`Engine`].
+pub struct TweenSystem
+{
+        tweens: Vec<Tween>,
+
+        next_id: u64,
+
+        last_update: Option<Instant>,
+}
+
+impl std::fmt::Debug for TweenSystem
+{
+        fn fmt(
+                &self,
+                f: &mut std::fmt::Formatter<'_>,
+        ) -> std::fmt::Result
+        {
+                f.debug_struct("TweenSystem")
+                        .field("active", &self.tweens.len())
+                        .field("next_id", &self.next_id)
+                        .finish()
+        }
+}
+
+impl Default for TweenSystem
+{
+        fn default() -> Self
+        {
+                Self::new()
+        }
+}
+
+impl TweenSystem
+{
+        pub fn new() -> Self
+        {
+                Self {
+                        tweens: Vec::new(),
+                        next_id: 0,
+                        last_update: None,
+                }
+        }
+
+        fn add(
+                &mut self,
+                track: Track,
+                duration: Duration,
+                ease: Ease,
+        ) -> &mut Tween
+        {
+                let id = TweenId(self.next_id);
+                self.next_id += 1;
+
+                self.tweens.push(Tween::new(id, track, duration, ease));
+
+                self.tweens.last_mut().unwrap()
+        }
+
+        /// Stops a tween where it is, without running its callback.
+        ///
+        /// Returns `false` if it already finished.
+        pub fn cancel(
+                &mut self,
+                id: TweenId,
+        ) -> bool
+        {
+                let len = self.tweens.len();
+
+                self.tweens.retain(|t| t.id != id);
+
+                self.tweens.len() != len
+        }
+
+        /// Cancels every tween that animates `model`.
+        pub fn cancel_model(
+                &mut self,
+                model: &str,
+        )
+        {
+                self.tweens
+                        .retain(|t| !t.steps.iter().any(|s| s.track.model() == Some(model)));
+        }
+
+        pub fn is_active(
+                &self,
+                id: TweenId,
+        ) -> bool
+        {
+                self.tweens.iter().any(|t| t.id == id)
+        }
+
+        /// Whether a tween changes the transform of `model`.
+        pub fn moves(
+                &self,
+                model: &str,
+        ) -> bool
+        {
+                self.tweens.iter().any(|t| {
+                        t.steps.iter().any(|s| {
+                                !matches!(s.track, Track::Morph { .. })
+                                        && s.track.model() == Some(model)
+                        })
+                })
+        }
+
+        pub fn len(&self) -> usize
+        {
+                self.tweens.len()
+        }
+
+        pub fn is_empty(&self) -> bool
+        {
+                self.tweens.is_empty()
+        }
+
+        /// Advances every tween by the time since the previous call.
+        ///
+        /// Called by the engine once per frame. Tweens are taken out while
+        /// they run so setters and callbacks get the whole [`Engine`] and
+        /// may schedule new tweens.
+        pub(crate) fn update(engine: &mut Engine)
+        {
+                let now = Instant::now();
+
+                let dt = engine
+                        .tweens
+                        .last_update
+                        .replace(now)
+                        .map(|last| now - last)
+                        .unwrap_or_default();
+
+                // Models are loaded asynchronously on the web, keep the
+                // tweens waiting until they exist.
+                if engine.state.is_none()
+                {
+                        return;
+                }
+
+                let mut tweens = std::mem::take(&mut engine.tweens.tweens);
+
+                let mut callbacks = Vec::new();
+
+                tweens.retain_mut(|tween| {
+                        let mut budget = dt;
+
+                        while let Some(step) = tween.steps.front_mut()
+                        {
+                                let models = &engine.state.as_ref().unwrap().models;
+
+                                if !step.track.capture(models)
+                                {
+                                        log::warn!(
+                                                "Tween {:?}: model {:?} not found, skipping step",
+                                                tween.id,
+                                                step.track.model()
+                                        );
+
+                                        tween.steps.pop_front();
+                                        tween.elapsed = Duration::ZERO;
+
+                                        continue;
+                                }
+
+                                let remaining = step.duration.saturating_sub(tween.elapsed);
+                                let advance = remaining.min(budget);
+
+                                tween.elapsed += advance;
+                                budget -= advance;
+
+                                let t = if step.duration.is_zero()
+                                {
+                                        1.0
+                                }
+                                else
+                                {
+                                        tween.elapsed.as_secs_f32() / step.duration.as_secs_f32()
+                                };
+
+                                step.track.apply(engine, step.ease.apply(t));
+
+                                if tween.elapsed < step.duration
+                                {
+                                        return true;
+                                }
+
+                                tween.steps.pop_front();
+                                tween.elapsed = Duration::ZERO;
+                        }
+
+                        if let Some(f) = tween.on_complete.take()
+                        {
+                                callbacks.push(f);
+                        }
+
+                        false
+                });
+
+                // Keep tweens scheduled by setters during the update.
+                tweens.append(&mut engine.tweens.tweens);
+                engine.tweens.tweens = tweens;
+
+                for f in callbacks
+                {
+                        f(engine);
+                }
+        }
+}
+
+impl Engine
+{
+        /// Moves `model` to `to` over `duration`.
+        pub fn tween_position(
+                &mut self,
+                model: impl Into<String>,
+                to: Point3<f32>,
+                duration: Duration,
+                ease: Ease,
+        ) -> &mut Tween
+        {
+                let track = Track::Position {
+                        model: model.into(),
+                        from: None,
+                        to,
+                };
+
+                self.tweens.add(track, duration, ease)
+        }
+
+        pub fn tween_scale(
+                &mut self,
+                model: impl Into<String>,
+                to: Vector3<f32>,
+                duration: Duration,
+                ease: Ease,
+        ) -> &mut Tween
+        {
+                let track = Track::Scale {
+                        model: model.into(),
+                        from: None,
+                        to,
+                };
+
+                self.tweens.add(track, duration, ease)
+        }
+
+        /// Rotates `model` towards `to` over `duration`.
+        ///
+        /// Models with `is_spinning` set keep spinning on top of the tween.
+        pub fn tween_rotation(
+                &mut self,
+                model: impl Into<String>,
+                to: Quaternion<f32>,
+                duration: Duration,
+                ease: Ease,
+        ) -> &mut Tween
+        {
+                let track = Track::Rotation {
+                        model: model.into(),
+                        from: None,
+                        to,
+                };
+
+                self.tweens.add(track, duration, ease)
+        }
+
+        /// Blends morph target `target` of `model` to `to` over `duration`,
+        /// see [`crate::geometry::morph`].
+        pub fn tween_morph_weight(
+                &mut self,
+                model: impl Into<String>,
+                target: impl Into<String>,
+                to: f32,
+                duration: Duration,
+                ease: Ease,
+        ) -> &mut Tween
+        {
+                let track = Track::Morph {
+                        model: model.into(),
+                        target: target.into(),
+                        from: None,
+                        to,
+                };
+
+                self.tweens.add(track, duration, ease)
+        }
+
+        /// Interpolates from `from` to `to` and passes every value to `set`,
+        /// for animating anything that isn't a model transform.
+        pub fn tween_value<F>(
+                &mut self,
+                from: f32,
+                to: f32,
+                duration: Duration,
+                ease: Ease,
+                set: F,
+        ) -> &mut Tween
+        where
+                F: 'static + FnMut(&mut Engine, f32),
+        {
+                let track = Track::Value {
+                        from,
+                        to,
+                        set: Box::new(set),
+                };
+
+                self.tweens.add(track, duration, ease)
+        }
+
+        /// See [`TweenSystem::cancel`].
+        pub fn cancel_tween(
+                &mut self,
+                id: TweenId,
+        ) -> bool
+        {
+                self.tweens.cancel(id)
+        }
+}
@@ -0,0 +1,216 @@
+//! The "Assets" window of the debug UI: every loaded model with its
+//! meshes and textures, their estimated GPU memory and how many models
+//! share their file, and the shared materials with the slots using them.
+//!
+//! Models can be unloaded and loaded again from their file from the
+//! window, see [`Engine::unload_model`] and [`Engine::restore_model`]. The
+//! listing comes from [`Engine::asset_usage`].
+
+use crate::engine::Engine;
+use crate::memory::{AssetUsage, ModelAsset};
+
+/// What a button of the window asked for, done on the next frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum AssetAction
+{
+        Unload(String),
+        Reload(String),
+}
+
+/// The "Assets" window, owned by the [`Engine`].
+#[derive(Debug, Default)]
+pub struct AssetPanel
+{
+        action: Option<AssetAction>,
+}
+
+impl AssetPanel
+{
+        pub fn new() -> Self
+        {
+                Self::default()
+        }
+
+        /// Unloads or reloads the model a button was clicked for.
+        ///
+        /// Called by the engine once per frame.
+        pub(crate) fn update(engine: &mut Engine)
+        {
+                match engine.asset_panel.action.take()
+                {
+                        Some(AssetAction::Unload(handle)) =>
+                        {
+                                engine.unload_model(&handle);
+                        }
+                        Some(AssetAction::Reload(handle)) =>
+                        {
+                                engine.restore_model(&handle);
+                        }
+                        None =>
+                        {}
+                }
+        }
+
+        /// Draws the "Assets" window.
+        ///
+        /// Called by the engine while the debug UI is open.
+        pub(crate) fn ui(
+                &mut self,
+                ctx: &egui::Context,
+                usage: &AssetUsage,
+        )
+        {
+                egui::Window::new("Assets")
+                        .default_open(false)
+                        .vscroll(true)
+                        .show(ctx, |ui| {
+                                ui.label(format!(
+                                        "{} models, {}",
+                                        usage.models.len(),
+                                        format_bytes(usage.total)
+                                ));
+
+                                ui.separator();
+
+                                for model in &usage.models
+                                {
+                                        if let Some(action) = Self::model_ui(ui, model)
+                                        {
+                                                self.action = Some(action);
+                                        }
+                                }
+
+                                egui::CollapsingHeader::new(format!(
+                                        "Shared materials ({})",
+                                        usage.materials.len()
+                                ))
+                                .show(ui, |ui| {
+                                        egui::Grid::new("asset_materials").striped(true).show(
+                                                ui,
+                                                |ui| {
+                                                        ui.strong("Material");
+                                                        ui.strong("Users");
+                                                        ui.end_row();
+
+                                                        for material in &usage.materials
+                                                        {
+                                                                ui.label(format!(
+                                                                        "{} #{}",
+                                                                        material.name,
+                                                                        material.handle.id()
+                                                                ));
+                                                                ui.label(material
+                                                                        .users
+                                                                        .to_string());
+                                                                ui.end_row();
+                                                        }
+                                                },
+                                        );
+                                });
+                        });
+        }
+
+        fn model_ui(
+                ui: &mut egui::Ui,
+                model: &ModelAsset,
+        ) -> Option<AssetAction>
+        {
+                let mut action = None;
+
+                let title = format!(
+                        "{}{} - {}",
+                        model.handle,
+                        if model.visible { "" } else { " (hidden)" },
+                        format_bytes(model.bytes)
+                );
+
+                egui::CollapsingHeader::new(title)
+                        .id_salt(&model.handle)
+                        .show(ui, |ui| {
+                                ui.horizontal(|ui| {
+                                        match &model.file
+                                        {
+                                                Some(file) => ui.label(format!(
+                                                        "{}, used by {}",
+                                                        file, model.file_refs
+                                                )),
+                                                None => ui.weak("Made at runtime"),
+                                        };
+                                });
+
+                                ui.horizontal(|ui| {
+                                        if ui.button("Unload").clicked()
+                                        {
+                                                action = Some(AssetAction::Unload(
+                                                        model.handle.clone(),
+                                                ));
+                                        }
+
+                                        if ui.add_enabled(
+                                                model.file.is_some(),
+                                                egui::Button::new("Reload"),
+                                        )
+                                        .clicked()
+                                        {
+                                                action = Some(AssetAction::Reload(
+                                                        model.handle.clone(),
+                                                ));
+                                        }
+                                });
+
+                                egui::Grid::new(("asset_meshes", &model.handle))
+                                        .striped(true)
+                                        .show(ui, |ui| {
+                                                ui.strong("Mesh");
+                                                ui.strong("Vertices");
+                                                ui.strong("Indices");
+                                                ui.strong("Size");
+                                                ui.end_row();
+
+                                                for mesh in &model.meshes
+                                                {
+                                                        ui.label(&mesh.name);
+                                                        ui.label(mesh.vertices.to_string());
+                                                        ui.label(mesh.indices.to_string());
+                                                        ui.label(format_bytes(mesh.bytes));
+                                                        ui.end_row();
+                                                }
+                                        });
+
+                                egui::Grid::new(("asset_textures", &model.handle))
+                                        .striped(true)
+                                        .show(ui, |ui| {
+                                                ui.strong("Texture");
+                                                ui.strong("Size");
+                                                ui.strong("Memory");
+                                                ui.end_row();
+
+                                                for texture in &model.textures
+                                                {
+                                                        ui.label(&texture.name);
+                                                        ui.label(format!(
+                                                                "{}x{}",
+                                                                texture.width, texture.height
+                                                        ));
+                                                        ui.label(format_bytes(texture.bytes));
+                                                        ui.end_row();
+                                                }
+                                        });
+                        });
+
+                action
+        }
+}
+
+/// `bytes` in KiB or MiB.
+fn format_bytes(bytes: u64) -> String
+{
+        if bytes >= 1024 * 1024
+        {
+                format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0))
+        }
+        else
+        {
+                format!("{:.1} KiB", bytes as f64 / 1024.0)
+        }
+}
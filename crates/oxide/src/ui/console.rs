@@ -0,0 +1,161 @@
+//! The "Console" window of the debug UI: the last lines logged and the
+//! levels of [`crate::log`], changed while the game runs.
+
+use crate::log::{Level, LevelFilter};
+
+const LEVELS: [LevelFilter; 6] = [
+        LevelFilter::Off,
+        LevelFilter::Error,
+        LevelFilter::Warn,
+        LevelFilter::Info,
+        LevelFilter::Debug,
+        LevelFilter::Trace,
+];
+
+/// The "Console" window, owned by the [`Engine`](crate::engine::Engine).
+#[derive(Debug)]
+pub struct Console
+{
+        /// Only lines containing it are shown.
+        search: String,
+        /// Module typed in for a new level.
+        module: String,
+}
+
+impl Default for Console
+{
+        fn default() -> Self
+        {
+                Self {
+                        search: String::new(),
+                        module: "oxide".to_string(),
+                }
+        }
+}
+
+impl Console
+{
+        pub fn new() -> Self
+        {
+                Self::default()
+        }
+
+        /// Draws the "Console" window.
+        ///
+        /// Called by the engine while the debug UI is open.
+        pub(crate) fn ui(
+                &mut self,
+                ctx: &egui::Context,
+        )
+        {
+                egui::Window::new("Console")
+                        .default_open(false)
+                        .default_width(600.0)
+                        .show(ctx, |ui| {
+                                self.levels_ui(ui);
+
+                                ui.separator();
+
+                                ui.horizontal(|ui| {
+                                        ui.label("Search");
+                                        ui.text_edit_singleline(&mut self.search);
+                                });
+
+                                egui::ScrollArea::vertical()
+                                        .max_height(300.0)
+                                        .stick_to_bottom(true)
+                                        .show(ui, |ui| {
+                                                for line in crate::log::recent_lines()
+                                                        .iter()
+                                                        .filter(|line| {
+                                                                line.message.contains(&self.search)
+                                                                        || line.target.contains(
+                                                                                &self.search,
+                                                                        )
+                                                        })
+                                                {
+                                                        let color = match line.level
+                                                        {
+                                                                Level::Error =>
+                                                                {
+                                                                        ui.visuals().error_fg_color
+                                                                }
+                                                                Level::Warn =>
+                                                                {
+                                                                        ui.visuals().warn_fg_color
+                                                                }
+                                                                _ => ui.visuals().text_color(),
+                                                        };
+
+                                                        ui.label(egui::RichText::new(
+                                                                line.to_string(),
+                                                        )
+                                                        .monospace()
+                                                        .color(color));
+                                                }
+                                        });
+                        });
+        }
+
+        fn levels_ui(
+                &mut self,
+                ui: &mut egui::Ui,
+        )
+        {
+                let config = crate::log::config();
+
+                let mut level = config.level;
+
+                if level_combo(ui, "Level", &mut level)
+                {
+                        crate::log::set_level(level);
+                }
+
+                for (module, level) in &config.modules
+                {
+                        ui.horizontal(|ui| {
+                                let mut level = *level;
+
+                                if level_combo(ui, module, &mut level)
+                                {
+                                        crate::log::set_module_level(module, Some(level));
+                                }
+
+                                if ui.small_button("x").clicked()
+                                {
+                                        crate::log::set_module_level(module, None);
+                                }
+                        });
+                }
+
+                ui.horizontal(|ui| {
+                        ui.text_edit_singleline(&mut self.module);
+
+                        if ui.button("Add module").clicked() && !self.module.is_empty()
+                        {
+                                crate::log::set_module_level(&self.module, Some(config.level));
+                        }
+                });
+        }
+}
+
+/// A combo box of the levels under `label`, true when one was picked.
+fn level_combo(
+        ui: &mut egui::Ui,
+        label: &str,
+        level: &mut LevelFilter,
+) -> bool
+{
+        let before = *level;
+
+        egui::ComboBox::from_label(label)
+                .selected_text(level.as_str())
+                .show_ui(ui, |ui| {
+                        for option in LEVELS
+                        {
+                                ui.selectable_value(level, option, option.as_str());
+                        }
+                });
+
+        *level != before
+}
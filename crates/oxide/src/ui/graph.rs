@@ -0,0 +1,450 @@
+//! Node graph view of the [`RenderGraph`] for the debug panel.
+//!
+//! Passes are drawn left to right in execution order, with their attachments
+//! as ports. An edge connects the pass that stored a resource to the pass
+//! that loads it, and the last pass writing the surface connects to the
+//! `present` node. Dragging a node reorders the passes, clicking one shows
+//! its settings below the graph.
+
+use crate::renderer::graph::{Attachment, AttachmentLoad, DEPTH, GraphIssue, RenderGraph, SURFACE};
+use egui::epaint::CubicBezierShape;
+use egui::{Align2, Color32, FontId, Pos2, Rect, Sense, Stroke, StrokeKind, Vec2, pos2, vec2};
+
+const NODE_WIDTH: f32 = 170.0;
+const HEADER: f32 = 24.0;
+/// Height of one text line, each attachment takes two.
+const LINE: f32 = 15.0;
+const FOOTER: f32 = 20.0;
+const GAP: f32 = 50.0;
+const PADDING: f32 = 10.0;
+const PORT_RADIUS: f32 = 4.0;
+
+/// What the view needs of a pass, collected up front so the graph isn't
+/// borrowed while drawing.
+struct Node
+{
+        name: String,
+        enabled: bool,
+        attachments: Vec<Attachment>,
+}
+
+/// Selection and drag state of the graph view, kept across frames.
+#[derive(Debug, Default)]
+pub struct RenderGraphView
+{
+        selected: Option<usize>,
+
+        /// Pass being dragged and how far it moved.
+        dragging: Option<(usize, f32)>,
+}
+
+impl RenderGraphView
+{
+        pub fn ui(
+                &mut self,
+                ui: &mut egui::Ui,
+                graph: &mut RenderGraph,
+        )
+        {
+                let analysis = graph.analyze();
+
+                if let Some(total) = graph.gpu_total()
+                {
+                        ui.label(format!("GPU {:.3} ms per frame", total.as_secs_f64() * 1000.0));
+                }
+
+                let nodes: Vec<Node> = graph
+                        .passes
+                        .iter_mut()
+                        .map(|p| Node {
+                                name: p.name().to_string(),
+                                enabled: p.enabled(),
+                                attachments: p.attachments(),
+                        })
+                        .collect();
+
+                let rows = nodes.iter().map(|n| n.attachments.len()).max().unwrap_or(0);
+                let node_height = HEADER + rows as f32 * 2.0 * LINE + FOOTER;
+
+                let slot = |i: usize| {
+                        Rect::from_min_size(
+                                pos2(PADDING + i as f32 * (NODE_WIDTH + GAP), PADDING),
+                                vec2(NODE_WIDTH, node_height),
+                        )
+                };
+
+                let mut reorder: Option<(usize, usize)> = None;
+                let dragging = self.dragging;
+
+                egui::ScrollArea::horizontal()
+                        .id_salt("render_graph_view")
+                        .show(ui, |ui| {
+                                let size = vec2(
+                                        slot(nodes.len()).min.x + NODE_WIDTH * 0.5,
+                                        node_height + 2.0 * PADDING,
+                                );
+
+                                let (canvas, _) = ui.allocate_exact_size(size, Sense::hover());
+                                let painter = ui.painter_at(canvas);
+                                let origin = canvas.min.to_vec2();
+
+                                let rect_of = |i: usize| {
+                                        let rect = slot(i).translate(origin);
+
+                                        match dragging
+                                        {
+                                                Some((d, dx)) if d == i =>
+                                                {
+                                                        rect.translate(vec2(dx, 0.0))
+                                                }
+                                                _ => rect,
+                                        }
+                                };
+
+                                let port = |i: usize, resource: &str, output: bool| {
+                                        let row = nodes[i]
+                                                .attachments
+                                                .iter()
+                                                .position(|a| a.resource == resource)
+                                                .unwrap_or(0);
+
+                                        let rect = rect_of(i);
+                                        let x = if output { rect.max.x } else { rect.min.x };
+
+                                        pos2(
+                                                x,
+                                                rect.min.y
+                                                        + HEADER
+                                                        + (row as f32 * 2.0 + 0.5) * LINE,
+                                        )
+                                };
+
+                                for dependency in &analysis.dependencies
+                                {
+                                        edge(
+                                                &painter,
+                                                port(dependency.from, &dependency.resource, true),
+                                                port(dependency.to, &dependency.resource, false),
+                                                resource_color(&dependency.resource),
+                                        );
+                                }
+
+                                // What's left in the surface is what gets presented.
+                                let present = Rect::from_min_size(
+                                        slot(nodes.len()).min + origin,
+                                        vec2(NODE_WIDTH * 0.5, HEADER),
+                                );
+
+                                if let Some(&writer) = analysis.outputs.get(SURFACE)
+                                {
+                                        edge(
+                                                &painter,
+                                                port(writer, SURFACE, true),
+                                                present.left_center(),
+                                                resource_color(SURFACE),
+                                        );
+                                }
+
+                                painter.rect(
+                                        present,
+                                        4.0,
+                                        ui.visuals().extreme_bg_color,
+                                        ui.visuals().widgets.noninteractive.bg_stroke,
+                                        StrokeKind::Inside,
+                                );
+                                painter.text(
+                                        present.center(),
+                                        Align2::CENTER_CENTER,
+                                        "present",
+                                        FontId::proportional(13.0),
+                                        ui.visuals().text_color(),
+                                );
+
+                                for (i, node) in nodes.iter().enumerate()
+                                {
+                                        let rect = rect_of(i);
+
+                                        let response = ui.interact(
+                                                rect,
+                                                ui.id().with(("render_pass", i)),
+                                                Sense::click_and_drag(),
+                                        );
+
+                                        if response.clicked()
+                                        {
+                                                self.selected = Some(i);
+                                        }
+
+                                        if response.dragged()
+                                        {
+                                                let dx = match self.dragging
+                                                {
+                                                        Some((d, dx)) if d == i => dx,
+                                                        _ => 0.0,
+                                                };
+
+                                                self.dragging =
+                                                        Some((i, dx + response.drag_delta().x));
+                                        }
+
+                                        if response.drag_stopped()
+                                        {
+                                                let target = ((rect.min.x - origin.x - PADDING)
+                                                        / (NODE_WIDTH + GAP))
+                                                        .round()
+                                                        .clamp(0.0, (nodes.len() - 1) as f32)
+                                                        as usize;
+
+                                                reorder = Some((i, target));
+                                                self.dragging = None;
+                                        }
+
+                                        let issue = analysis.issues.iter().any(|issue| match issue
+                                        {
+                                                GraphIssue::ReadBeforeWrite {
+                                                        pass, ..
+                                                } => *pass == i,
+                                                GraphIssue::Overwrite {
+                                                        pass, ..
+                                                } => *pass == i,
+                                        });
+
+                                        draw_node(
+                                                ui,
+                                                &painter,
+                                                rect,
+                                                node,
+                                                graph,
+                                                self.selected == Some(i),
+                                                issue,
+                                        );
+                                }
+                        });
+
+                if let Some((from, to)) = reorder
+                        && from != to
+                {
+                        let pass = graph.passes.remove(from);
+                        graph.passes.insert(to, pass);
+
+                        if self.selected == Some(from)
+                        {
+                                self.selected = Some(to);
+                        }
+                }
+
+                for issue in &analysis.issues
+                {
+                        let text = match issue
+                        {
+                                GraphIssue::ReadBeforeWrite {
+                                        pass,
+                                        resource,
+                                } => format!(
+                                        "⚠ {} loads {} before any pass stored it",
+                                        nodes[*pass].name, resource
+                                ),
+                                GraphIssue::Overwrite {
+                                        pass,
+                                        overwritten,
+                                        resource,
+                                } => format!(
+                                        "⚠ {} clears {}, discarding the output of {}",
+                                        nodes[*pass].name, resource, nodes[*overwritten].name
+                                ),
+                        };
+
+                        ui.colored_label(ui.visuals().warn_fg_color, text);
+                }
+
+                self.selected_ui(ui, graph);
+        }
+
+        fn selected_ui(
+                &mut self,
+                ui: &mut egui::Ui,
+                graph: &mut RenderGraph,
+        )
+        {
+                let pass = match self.selected.and_then(|i| graph.passes.get_mut(i))
+                {
+                        Some(p) => p,
+                        None =>
+                        {
+                                ui.weak("Click a pass to edit it, drag it to reorder.");
+                                return;
+                        }
+                };
+
+                ui.separator();
+
+                let mut enabled = pass.enabled();
+
+                ui.horizontal(|ui| {
+                        ui.strong(pass.name().to_string());
+                        ui.checkbox(&mut enabled, "Enabled");
+                });
+
+                pass.set_enabled(enabled);
+
+                pass.ui(ui);
+        }
+}
+
+fn draw_node(
+        ui: &egui::Ui,
+        painter: &egui::Painter,
+        rect: Rect,
+        node: &Node,
+        graph: &RenderGraph,
+        selected: bool,
+        issue: bool,
+)
+{
+        let visuals = ui.visuals();
+
+        let text_color = if node.enabled
+        {
+                visuals.strong_text_color()
+        }
+        else
+        {
+                visuals.weak_text_color()
+        };
+
+        let stroke = if issue
+        {
+                Stroke::new(1.5, visuals.warn_fg_color)
+        }
+        else if selected
+        {
+                visuals.selection.stroke
+        }
+        else
+        {
+                visuals.widgets.noninteractive.bg_stroke
+        };
+
+        painter.rect(rect, 4.0, visuals.extreme_bg_color, stroke, StrokeKind::Inside);
+
+        painter.text(
+                rect.min + vec2(8.0, HEADER * 0.5),
+                Align2::LEFT_CENTER,
+                &node.name,
+                FontId::proportional(13.0),
+                text_color,
+        );
+
+        if !node.enabled
+        {
+                painter.text(
+                        pos2(rect.max.x - 8.0, rect.min.y + HEADER * 0.5),
+                        Align2::RIGHT_CENTER,
+                        "off",
+                        FontId::proportional(11.0),
+                        visuals.weak_text_color(),
+                );
+        }
+
+        for (row, attachment) in node.attachments.iter().enumerate()
+        {
+                let y = rect.min.y + HEADER + row as f32 * 2.0 * LINE;
+                let color = resource_color(&attachment.resource);
+
+                let load = match attachment.load
+                {
+                        AttachmentLoad::Clear => "clear",
+                        AttachmentLoad::Load => "load",
+                };
+
+                painter.text(
+                        pos2(rect.min.x + 12.0, y + LINE * 0.5),
+                        Align2::LEFT_CENTER,
+                        format!("{} ({})", attachment.resource, load),
+                        FontId::proportional(12.0),
+                        text_color,
+                );
+
+                let format = graph
+                        .formats
+                        .get(&attachment.resource)
+                        .map(|f| format!("{:?}", f))
+                        .unwrap_or_else(|| "unknown format".to_string());
+
+                painter.text(
+                        pos2(rect.min.x + 12.0, y + LINE * 1.5),
+                        Align2::LEFT_CENTER,
+                        format,
+                        FontId::monospace(10.0),
+                        visuals.weak_text_color(),
+                );
+
+                // Inputs on the left, outputs on the right.
+                if attachment.load == AttachmentLoad::Load
+                {
+                        painter.circle_filled(pos2(rect.min.x, y + LINE * 0.5), PORT_RADIUS, color);
+                }
+
+                if attachment.store
+                {
+                        painter.circle_filled(pos2(rect.max.x, y + LINE * 0.5), PORT_RADIUS, color);
+                }
+        }
+
+        let gpu = graph
+                .gpu_timing(&node.name)
+                .map(|t| format!("  GPU {:.3} ms", t.average.as_secs_f64() * 1000.0))
+                .unwrap_or_default();
+
+        let timing = graph
+                .timing(&node.name)
+                .filter(|_| node.enabled)
+                .map(|t| format!("CPU {:.3} ms{}", t.average.as_secs_f64() * 1000.0, gpu))
+                .unwrap_or_else(|| "not recorded".to_string());
+
+        painter.text(
+                pos2(rect.min.x + 8.0, rect.max.y - FOOTER * 0.5),
+                Align2::LEFT_CENTER,
+                timing,
+                FontId::monospace(10.0),
+                visuals.weak_text_color(),
+        );
+}
+
+fn edge(
+        painter: &egui::Painter,
+        from: Pos2,
+        to: Pos2,
+        color: Color32,
+)
+{
+        let bend = Vec2::new(((to.x - from.x) * 0.5).max(30.0), 0.0);
+
+        painter.add(CubicBezierShape::from_points_stroke(
+                [from, from + bend, to - bend, to],
+                false,
+                Color32::TRANSPARENT,
+                Stroke::new(2.0, color),
+        ));
+}
+
+fn resource_color(resource: &str) -> Color32
+{
+        match resource
+        {
+                SURFACE => Color32::from_rgb(90, 170, 250),
+                DEPTH => Color32::from_rgb(230, 170, 60),
+                _ =>
+                {
+                        let hash = resource
+                                .bytes()
+                                .fold(7u32, |h, b| h.wrapping_mul(31).wrapping_add(b as u32));
+
+                        Color32::from_rgb(
+                                100 + (hash % 156) as u8,
+                                100 + (hash / 156 % 156) as u8,
+                                100 + (hash / 24336 % 156) as u8,
+                        )
+                }
+        }
+}
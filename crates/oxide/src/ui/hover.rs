@@ -0,0 +1,303 @@
+//! Picking models with the cursor in the debug UI.
+//!
+//! While the debug UI is shown and
+//! [`Config::highlight_hovered`](crate::config::Config::highlight_hovered)
+//! is on, the model under the cursor is picked like
+//! [`Engine::raycast`], against the oriented bounds. It's outlined by the
+//! [`HighlightPass`] and named in a tooltip with its triangle count.
+//!
+//! Clicking a model selects it, clicking nothing clears the selection and
+//! dragging over the scene selects what's inside the box, Shift adding to
+//! the selection, see [`crate::selection`]. Selected models are outlined
+//! too.
+//!
+//! Nothing is hovered while the pointer is over the UI, the camera is
+//! locked in or distances are measured, see [`crate::measure`].
+
+use crate::config::Config;
+use crate::engine::{Engine, EngineState};
+use crate::math::collision::Ray;
+use crate::renderer::highlight::HighlightPass;
+
+/// Rim color of the hovered model.
+pub const HOVER_COLOR: [f32; 3] = [1.0, 0.6, 0.15];
+
+/// Rim color of the selected models.
+pub const SELECTED_COLOR: [f32; 3] = [0.2, 0.55, 1.0];
+
+/// A drag shorter than this, in points, is a click.
+const MIN_BOX_SIZE: f32 = 4.0;
+
+impl EngineState
+{
+        /// `pos` of the UI in normalized device coordinates of the
+        /// [`PipelineManager::viewport`](crate::renderer::pipeline::PipelineManager::viewport),
+        /// `None` outside of it.
+        fn screen_to_ndc(
+                &self,
+                pos: egui::Pos2,
+        ) -> Option<(f32, f32)>
+        {
+                let screen = self.gui.renderer.context().screen_rect();
+
+                let config = self.surface_manager.render_configuration();
+                let [x, y, width, height] = self
+                        .pipeline_manager
+                        .viewport_rect(config.width, config.height);
+
+                // From points to the target's pixels, then into the viewport.
+                let u = ((pos.x - screen.min.x) / screen.width() * config.width as f32 - x) / width;
+                let v = ((pos.y - screen.min.y) / screen.height() * config.height as f32 - y)
+                        / height;
+
+                if !(0.0..=1.0).contains(&u) || !(0.0..=1.0).contains(&v)
+                {
+                        return None;
+                }
+
+                Some((u * 2.0 - 1.0, 1.0 - v * 2.0))
+        }
+
+        /// Where normalized device coordinates of the viewport are in the
+        /// UI, the inverse of [`EngineState::screen_to_ndc`].
+        pub(crate) fn ndc_to_screen(
+                &self,
+                (x, y): (f32, f32),
+        ) -> egui::Pos2
+        {
+                let screen = self.gui.renderer.context().screen_rect();
+
+                let config = self.surface_manager.render_configuration();
+                let [vx, vy, width, height] = self
+                        .pipeline_manager
+                        .viewport_rect(config.width, config.height);
+
+                let px = vx + (x + 1.0) * 0.5 * width;
+                let py = vy + (1.0 - y) * 0.5 * height;
+
+                egui::pos2(
+                        screen.min.x + px / config.width as f32 * screen.width(),
+                        screen.min.y + py / config.height as f32 * screen.height(),
+                )
+        }
+
+        /// The ray through the cursor, `None` when it's outside the
+        /// viewport. Must be called between [`EngineState::begin_ui`] and
+        /// [`EngineState::end_ui`].
+        pub fn cursor_ray(&self) -> Option<Ray>
+        {
+                let pointer = self.gui.renderer.context().pointer_hover_pos()?;
+                let (x, y) = self.screen_to_ndc(pointer)?;
+
+                Some(self.camera.ray(x, y))
+        }
+
+        /// Picks the model under the cursor, highlights it and shows its
+        /// tooltip, and selects what's clicked or dragged over. Must be
+        /// called between [`EngineState::begin_ui`] and
+        /// [`EngineState::end_ui`].
+        pub fn show_hovered(
+                &mut self,
+                config: &Config,
+        )
+        {
+                self.hovered = None;
+
+                if let Some(pass) = self
+                        .render_graph
+                        .pass_mut::<HighlightPass>()
+                        .filter(|pass| pass.enabled)
+                {
+                        pass.models.extend(self
+                                .selection
+                                .handles()
+                                .iter()
+                                .map(|handle| (handle.clone(), SELECTED_COLOR)));
+                }
+
+                let ctx = self.gui.renderer.context().clone();
+
+                // Clicks pick points while measuring, see `crate::measure`.
+                if self.measurement.enabled
+                        || self.camera.locked_in
+                        || ctx.is_pointer_over_area()
+                        || ctx.is_using_pointer()
+                {
+                        return;
+                }
+
+                if self.box_select(&ctx)
+                {
+                        return;
+                }
+
+                let hit = self.cursor_ray().and_then(|ray| {
+                        self.models
+                                .iter()
+                                .filter(|(_, model)| model.visible)
+                                .filter_map(|(handle, model)| {
+                                        ray.intersect_obb(&model.world_obb()?)
+                                                .map(|d| ((handle, model), d))
+                                })
+                                .min_by(|a, b| a.1.total_cmp(&b.1))
+                                .map(|(hit, _)| hit)
+                });
+
+                let (clicked, shift) =
+                        ctx.input(|i| (i.pointer.primary_clicked(), i.modifiers.shift));
+
+                let Some((handle, model)) = hit
+                else
+                {
+                        if clicked && !shift
+                        {
+                                self.selection.clear();
+                        }
+
+                        return;
+                };
+
+                let handle = handle.clone();
+
+                if config.highlight_hovered
+                {
+                        egui::Tooltip::always_open(
+                                ctx.clone(),
+                                egui::LayerId::background(),
+                                egui::Id::new("hovered_model"),
+                                egui::PopupAnchor::Pointer,
+                        )
+                        .gap(12.0)
+                        .show(|ui| {
+                                ui.strong(&handle);
+                                ui.label(format!("Triangles: {}", model.triangle_count()));
+                        });
+
+                        if let Some(pass) = self
+                                .render_graph
+                                .pass_mut::<HighlightPass>()
+                                .filter(|pass| pass.enabled)
+                        {
+                                pass.models.push((handle.clone(), HOVER_COLOR));
+                        }
+                }
+
+                if clicked && shift
+                {
+                        self.selection.toggle(&handle);
+                }
+                else if clicked
+                {
+                        self.selection.set(Some(&handle));
+                }
+
+                self.hovered = Some(handle);
+        }
+
+        /// Draws the box dragged over the scene and selects the models whose
+        /// bounds have their center inside it once it's released. Returns
+        /// true while a box is dragged.
+        fn box_select(
+                &mut self,
+                ctx: &egui::Context,
+        ) -> bool
+        {
+                let id = egui::Id::new("box_select");
+
+                let (pressed, down, released, pointer, shift) = ctx.input(|i| {
+                        (
+                                i.pointer.primary_pressed(),
+                                i.pointer.primary_down(),
+                                i.pointer.primary_released(),
+                                i.pointer.interact_pos(),
+                                i.modifiers.shift,
+                        )
+                });
+
+                // Kept by `egui` between frames, only for drags starting in
+                // the scene.
+                if pressed && let Some(pointer) = pointer
+                {
+                        ctx.data_mut(|d| d.insert_temp(id, pointer));
+                }
+
+                let Some(start) = ctx.data(|d| d.get_temp::<egui::Pos2>(id))
+                else
+                {
+                        return false;
+                };
+
+                // Released over the UI, the box is dropped.
+                let Some(pointer) = pointer.filter(|_| down || released)
+                else
+                {
+                        ctx.data_mut(|d| d.remove::<egui::Pos2>(id));
+
+                        return false;
+                };
+
+                let rect = egui::Rect::from_two_pos(start, pointer);
+
+                if rect.width() < MIN_BOX_SIZE && rect.height() < MIN_BOX_SIZE
+                {
+                        if !down
+                        {
+                                ctx.data_mut(|d| d.remove::<egui::Pos2>(id));
+                        }
+
+                        return false;
+                }
+
+                if down
+                {
+                        let color = egui::Color32::from_rgb(50, 140, 255);
+
+                        ctx.layer_painter(egui::LayerId::new(egui::Order::Foreground, id))
+                                .rect(
+                                        rect,
+                                        0.0,
+                                        color.gamma_multiply(0.15),
+                                        egui::Stroke::new(1.0, color),
+                                        egui::StrokeKind::Inside,
+                                );
+
+                        return true;
+                }
+
+                ctx.data_mut(|d| d.remove::<egui::Pos2>(id));
+
+                if !shift
+                {
+                        self.selection.clear();
+                }
+
+                let inside: Vec<String> = self
+                        .models
+                        .iter()
+                        .filter(|(_, model)| model.visible)
+                        .filter_map(|(handle, model)| {
+                                let ndc = self.camera.project(model.world_bounds()?.center())?;
+
+                                rect.contains(self.ndc_to_screen(ndc))
+                                        .then(|| handle.clone())
+                        })
+                        .collect();
+
+                for handle in inside
+                {
+                        self.selection.add(&handle);
+                }
+
+                true
+        }
+}
+
+impl Engine
+{
+        /// The model under the cursor in the debug UI, see
+        /// [`crate::ui::hover`].
+        pub fn hovered_model(&self) -> Option<&str>
+        {
+                self.state.as_ref()?.hovered.as_deref()
+        }
+}
@@ -0,0 +1,330 @@
+//! Live editing of game values in the debug UI.
+//!
+//! Types implementing [`Inspect`] draw their own editing UI. The
+//! `#[derive(Inspect)]` macro of `oxide-macro` writes it for structs and
+//! field-less enums: numbers become drag values, `bool`s checkboxes, enums
+//! combo boxes and nested structs collapsible sections. Fields marked
+//! `#[inspect(color)]` get a color picker, `#[inspect(skip)]` hides one.
+//!
+//! Values shared with [`Engine::inspect`] show up in the "Inspector" window
+//! while the debug UI is open:
+//!
+//! ```ignore
+//! #[derive(Inspect)]
+//! struct Settings
+//! {
+//!         speed: f32,
+//!         #[inspect(color)]
+//!         tint: [f32; 3],
+//! }
+//!
+//! let settings = Rc::new(RefCell::new(Settings::default()));
+//! engine.inspect("Settings", settings.clone());
+//! ```
+
+use crate::engine::Engine;
+use cgmath::{Point2, Point3, Vector2, Vector3, Vector4};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Draws an editing UI for a value, see [`crate::ui::inspect`].
+pub trait Inspect
+{
+        /// Shows the value under `label`. Returns true when it was edited.
+        fn inspect(
+                &mut self,
+                ui: &mut egui::Ui,
+                label: &str,
+        ) -> bool;
+}
+
+/// Colors picked for `#[inspect(color)]` fields, RGB or RGBA.
+pub trait InspectColor
+{
+        fn inspect_color(
+                &mut self,
+                ui: &mut egui::Ui,
+                label: &str,
+        ) -> bool;
+}
+
+/// A label in front of the value's widgets.
+fn row(
+        ui: &mut egui::Ui,
+        label: &str,
+        add: impl FnOnce(&mut egui::Ui) -> bool,
+) -> bool
+{
+        ui.horizontal(|ui| {
+                ui.label(label);
+                add(ui)
+        })
+        .inner
+}
+
+macro_rules! inspect_number {
+        ($($ty:ty => $speed:expr),* $(,)?) => {
+                $(
+                        impl Inspect for $ty
+                        {
+                                fn inspect(
+                                        &mut self,
+                                        ui: &mut egui::Ui,
+                                        label: &str,
+                                ) -> bool
+                                {
+                                        row(ui, label, |ui| {
+                                                ui.add(egui::DragValue::new(self).speed($speed))
+                                                        .changed()
+                                        })
+                                }
+                        }
+                )*
+        };
+}
+
+inspect_number! {
+        f32 => 0.1,
+        f64 => 0.1,
+        i8 => 1.0,
+        i16 => 1.0,
+        i32 => 1.0,
+        i64 => 1.0,
+        isize => 1.0,
+        u8 => 1.0,
+        u16 => 1.0,
+        u32 => 1.0,
+        u64 => 1.0,
+        usize => 1.0,
+}
+
+macro_rules! inspect_vector {
+        ($($ty:ident { $($field:ident),* }),* $(,)?) => {
+                $(
+                        impl Inspect for $ty<f32>
+                        {
+                                fn inspect(
+                                        &mut self,
+                                        ui: &mut egui::Ui,
+                                        label: &str,
+                                ) -> bool
+                                {
+                                        row(ui, label, |ui| {
+                                                let mut changed = false;
+                                                $(
+                                                        changed |= ui
+                                                                .add(egui::DragValue::new(&mut self.$field)
+                                                                        .speed(0.1)
+                                                                        .prefix(concat!(stringify!($field), ": ")))
+                                                                .changed();
+                                                )*
+                                                changed
+                                        })
+                                }
+                        }
+                )*
+        };
+}
+
+inspect_vector! {
+        Vector2 { x, y },
+        Vector3 { x, y, z },
+        Vector4 { x, y, z, w },
+        Point2 { x, y },
+        Point3 { x, y, z },
+}
+
+impl Inspect for bool
+{
+        fn inspect(
+                &mut self,
+                ui: &mut egui::Ui,
+                label: &str,
+        ) -> bool
+        {
+                ui.checkbox(self, label).changed()
+        }
+}
+
+impl Inspect for String
+{
+        fn inspect(
+                &mut self,
+                ui: &mut egui::Ui,
+                label: &str,
+        ) -> bool
+        {
+                row(ui, label, |ui| ui.text_edit_singleline(self).changed())
+        }
+}
+
+/// Shown, but not editable.
+impl Inspect for &'static str
+{
+        fn inspect(
+                &mut self,
+                ui: &mut egui::Ui,
+                label: &str,
+        ) -> bool
+        {
+                row(ui, label, |ui| {
+                        ui.label(*self);
+                        false
+                })
+        }
+}
+
+impl<T: Inspect> Inspect for Option<T>
+{
+        fn inspect(
+                &mut self,
+                ui: &mut egui::Ui,
+                label: &str,
+        ) -> bool
+        {
+                match self
+                {
+                        Some(value) => value.inspect(ui, label),
+                        None => row(ui, label, |ui| {
+                                ui.weak("None");
+                                false
+                        }),
+                }
+        }
+}
+
+impl<T: Inspect> Inspect for Vec<T>
+{
+        fn inspect(
+                &mut self,
+                ui: &mut egui::Ui,
+                label: &str,
+        ) -> bool
+        {
+                let mut changed = false;
+
+                egui::CollapsingHeader::new(format!("{} [{}]", label, self.len()))
+                        .id_salt(label)
+                        .show(ui, |ui| {
+                                for (i, value) in self.iter_mut().enumerate()
+                                {
+                                        changed |= value.inspect(ui, &i.to_string());
+                                }
+                        });
+
+                changed
+        }
+}
+
+impl InspectColor for [f32; 3]
+{
+        fn inspect_color(
+                &mut self,
+                ui: &mut egui::Ui,
+                label: &str,
+        ) -> bool
+        {
+                row(ui, label, |ui| ui.color_edit_button_rgb(self).changed())
+        }
+}
+
+impl InspectColor for [f32; 4]
+{
+        fn inspect_color(
+                &mut self,
+                ui: &mut egui::Ui,
+                label: &str,
+        ) -> bool
+        {
+                row(ui, label, |ui| ui.color_edit_button_rgba_unmultiplied(self).changed())
+        }
+}
+
+/// Values shown in the "Inspector" window, owned by the [`Engine`].
+#[derive(Default)]
+pub struct Inspectors
+{
+        values: Vec<(String, Rc<RefCell<dyn Inspect>>)>,
+}
+
+impl std::fmt::Debug for Inspectors
+{
+        fn fmt(
+                &self,
+                f: &mut std::fmt::Formatter<'_>,
+        ) -> std::fmt::Result
+        {
+                f.debug_list()
+                        .entries(self.values.iter().map(|(name, _)| name))
+                        .finish()
+        }
+}
+
+impl Inspectors
+{
+        pub fn new() -> Self
+        {
+                Self::default()
+        }
+
+        /// Draws the "Inspector" window.
+        ///
+        /// Called by the engine while the debug UI is open. Values borrowed
+        /// elsewhere right now are skipped for the frame.
+        pub(crate) fn ui(
+                &mut self,
+                ctx: &egui::Context,
+        )
+        {
+                if self.values.is_empty()
+                {
+                        return;
+                }
+
+                egui::Window::new("Inspector")
+                        .default_open(false)
+                        .vscroll(true)
+                        .show(ctx, |ui| {
+                                for (name, value) in &self.values
+                                {
+                                        match value.try_borrow_mut()
+                                        {
+                                                Ok(mut value) =>
+                                                {
+                                                        value.inspect(ui, name);
+                                                }
+                                                Err(_) =>
+                                                {
+                                                        ui.weak(format!("{} is in use", name));
+                                                }
+                                        }
+                                }
+                        });
+        }
+}
+
+impl Engine
+{
+        /// Shows `value` in the debug UI's "Inspector" window under `name`,
+        /// replacing a value with the same name. See [`crate::ui::inspect`].
+        pub fn inspect<T: Inspect + 'static>(
+                &mut self,
+                name: impl Into<String>,
+                value: Rc<RefCell<T>>,
+        )
+        {
+                let name = name.into();
+
+                self.inspectors.values.retain(|(n, _)| *n != name);
+                self.inspectors.values.push((name, value));
+        }
+
+        /// Removes a value added with [`Engine::inspect`].
+        pub fn stop_inspecting(
+                &mut self,
+                name: &str,
+        )
+        {
+                self.inspectors.values.retain(|(n, _)| n != name);
+        }
+}
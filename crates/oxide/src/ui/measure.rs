@@ -0,0 +1,140 @@
+//! The "Measure" window of the debug UI, see [`crate::measure`].
+//!
+//! While measuring, clicks in the scene pick points and the line to the
+//! point under the cursor is drawn once the start is picked. The distance
+//! is also written next to the line.
+
+use crate::engine::EngineState;
+use crate::measure;
+use crate::renderer::debug_draw::DebugDrawPass;
+use cgmath::{EuclideanSpace, Point3};
+
+fn point_label(point: Option<Point3<f32>>) -> String
+{
+        match point
+        {
+                Some(p) => format!("({:.3}, {:.3}, {:.3})", p.x, p.y, p.z),
+                None => "Click in the scene".to_string(),
+        }
+}
+
+impl EngineState
+{
+        /// Picks the points clicked in the scene, draws the measured line
+        /// and the "Measure" window while
+        /// [`Measurement::enabled`](crate::measure::Measurement::enabled).
+        /// Must be called between
+        /// [`EngineState::begin_ui`] and [`EngineState::end_ui`].
+        pub fn show_measurement(&mut self)
+        {
+                if !self.measurement.enabled
+                {
+                        return;
+                }
+
+                let ctx = self.gui.renderer.context().clone();
+
+                let in_scene = !self.camera.locked_in && !ctx.is_pointer_over_area();
+
+                let cursor = self
+                        .cursor_ray()
+                        .filter(|_| in_scene)
+                        .and_then(|ray| measure::pick_point(&self.models, &ray));
+
+                if let Some(point) = cursor
+                        && ctx.input(|i| i.pointer.primary_clicked())
+                {
+                        self.measurement.push(point);
+                }
+
+                let measurement = self.measurement;
+
+                if let Some(pass) = self
+                        .render_graph
+                        .pass_mut::<DebugDrawPass>()
+                        .filter(|pass| pass.enabled)
+                {
+                        measurement.lines(&mut pass.lines);
+
+                        // Where the end would be.
+                        if let (Some(start), None, Some(point)) =
+                                (measurement.start(), measurement.end(), cursor)
+                        {
+                                measure::marker(&mut pass.lines, point);
+                                measure::legs(&mut pass.lines, start, point);
+                        }
+                }
+
+                if let (Some(start), Some(end), Some(distance)) =
+                        (measurement.start(), measurement.end(), measurement.distance())
+                        && let Some(ndc) = self.camera.project(start.midpoint(end))
+                {
+                        ctx.layer_painter(egui::LayerId::background()).text(
+                                self.ndc_to_screen(ndc),
+                                egui::Align2::LEFT_BOTTOM,
+                                format!("{:.3}", distance),
+                                egui::FontId::monospace(14.0),
+                                egui::Color32::WHITE,
+                        );
+                }
+
+                let mut open = true;
+
+                egui::Window::new("Measure")
+                        .open(&mut open)
+                        .show(&ctx, |ui| {
+                                egui::Grid::new("measure_grid")
+                                        .num_columns(2)
+                                        .show(ui, |ui| {
+                                                ui.label("Start");
+                                                ui.label(point_label(measurement.start()));
+                                                ui.end_row();
+
+                                                ui.label("End");
+                                                ui.label(point_label(measurement.end()));
+                                                ui.end_row();
+
+                                                if let (
+                                                        Some(delta),
+                                                        Some(distance),
+                                                        Some(elevation),
+                                                ) = (
+                                                        measurement.delta(),
+                                                        measurement.distance(),
+                                                        measurement.elevation(),
+                                                )
+                                                {
+                                                        ui.label("Distance");
+                                                        ui.strong(format!("{:.3}", distance));
+                                                        ui.end_row();
+
+                                                        for (axis, value) in [
+                                                                ("ΔX", delta.x),
+                                                                ("ΔY", delta.y),
+                                                                ("ΔZ", delta.z),
+                                                        ]
+                                                        {
+                                                                ui.label(axis);
+                                                                ui.label(format!("{:.3}", value));
+                                                                ui.end_row();
+                                                        }
+
+                                                        ui.label("Elevation");
+                                                        ui.label(format!("{:.1}°", elevation));
+                                                        ui.end_row();
+                                                }
+                                        });
+
+                                if ui.button("Clear").clicked()
+                                {
+                                        self.measurement.clear();
+                                }
+                        });
+
+                if !open
+                {
+                        self.measurement.enabled = false;
+                        self.measurement.clear();
+                }
+        }
+}
@@ -0,0 +1,579 @@
+//! A top-down map of the scene in a corner of the screen.
+//!
+//! The visible models are drawn every frame into an offscreen target by an
+//! [`Orthographic`] camera looking straight down on the
+//! [`MinimapFollow`] target, then shown as an image over the scene with the
+//! [`MapMarker`]s on top:
+//!
+//! ```ignore
+//! let minimap = engine.minimap();
+//!
+//! minimap.enabled = true;
+//! minimap.follow = MinimapFollow::Model("player".to_string());
+//! minimap.set_marker("exit", MapMarker::new(exit_position, Color32::GREEN));
+//!
+//! // Zooms in on the mouse wheel.
+//! minimap.zoom(1.1);
+//! ```
+//!
+//! The markers are painted by `egui` rather than drawn in the scene, so
+//! they stay the same size whatever the zoom.
+
+use crate::camera::{CameraUniform, Orthographic, create_camera_bind_group_layout};
+use crate::engine::{Engine, EngineState};
+use crate::model::Model;
+use crate::texture::Texture;
+use cgmath::{EuclideanSpace, InnerSpace, Matrix4, Point3, Rad, Vector3};
+use egui::{Align, Align2, Color32, Pos2, Vec2};
+use std::collections::BTreeMap;
+use wgpu::util::DeviceExt;
+
+/// What the minimap is centered on.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MinimapFollow
+{
+        Camera,
+        /// The center of the model's bounds, the camera while it's missing.
+        Model(String),
+        Point(Point3<f32>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MarkerIcon
+{
+        Dot,
+        Square,
+        /// Pointing along `heading`, a yaw like
+        /// [`CameraCore::yaw`](crate::camera::CameraCore::yaw).
+        Arrow
+        {
+                heading: Rad<f32>,
+        },
+        /// A texture registered with
+        /// [`GuiRenderer::register_texture`](crate::ui::renderer::GuiRenderer::register_texture),
+        /// tinted with the marker's color.
+        Image(egui::TextureId),
+}
+
+/// Something shown on the minimap.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MapMarker
+{
+        pub position: Point3<f32>,
+        pub icon: MarkerIcon,
+        pub color: Color32,
+        /// Width of the icon in points.
+        pub size: f32,
+        /// Kept at the edge of the map pointing its way when it's outside,
+        /// hidden otherwise.
+        pub clamp_to_edge: bool,
+}
+
+impl MapMarker
+{
+        /// A dot at `position`.
+        pub fn new(
+                position: Point3<f32>,
+                color: Color32,
+        ) -> Self
+        {
+                Self {
+                        position,
+                        icon: MarkerIcon::Dot,
+                        color,
+                        size: 8.0,
+                        clamp_to_edge: false,
+                }
+        }
+}
+
+/// The offscreen target the map is drawn into.
+#[derive(Debug)]
+struct MinimapTarget
+{
+        id: egui::TextureId,
+        texture: wgpu::Texture,
+        depth_texture: Texture,
+}
+
+#[derive(Debug)]
+pub struct Minimap
+{
+        /// Off by default.
+        pub enabled: bool,
+        pub follow: MinimapFollow,
+        /// World units from the center to the edge of the map, smaller is
+        /// zoomed in, see [`Minimap::zoom`].
+        pub range: f32,
+        /// Limits of `range` for [`Minimap::zoom`].
+        pub min_range: f32,
+        pub max_range: f32,
+        /// World units above and below the target drawn, a lower value keeps
+        /// ceilings and floors further down off the map.
+        pub depth: f32,
+        /// Turns the map with the camera so its view points up, north stays
+        /// up otherwise.
+        pub rotate: bool,
+        /// Width and height on screen in points.
+        pub size: f32,
+        /// Corner or edge of the screen the map is placed at.
+        pub anchor: Align2,
+        /// Distance to the edges of the screen in points.
+        pub margin: f32,
+        /// Round instead of square.
+        pub circular: bool,
+        pub background: Color32,
+        /// Arrow in the center pointing where the camera looks.
+        pub show_heading: bool,
+        pub markers: BTreeMap<String, MapMarker>,
+        target: Option<MinimapTarget>,
+}
+
+impl Default for Minimap
+{
+        fn default() -> Self
+        {
+                Self::new()
+        }
+}
+
+impl Minimap
+{
+        pub fn new() -> Self
+        {
+                Self {
+                        enabled: false,
+                        follow: MinimapFollow::Camera,
+                        range: 50.0,
+                        min_range: 5.0,
+                        max_range: 500.0,
+                        depth: 100.0,
+                        rotate: false,
+                        size: 180.0,
+                        anchor: Align2::RIGHT_TOP,
+                        margin: 12.0,
+                        circular: true,
+                        background: Color32::from_rgb(24, 28, 32),
+                        show_heading: true,
+                        markers: BTreeMap::new(),
+                        target: None,
+                }
+        }
+
+        /// Multiplies the zoom by `factor`, above 1 zooms in.
+        pub fn zoom(
+                &mut self,
+                factor: f32,
+        )
+        {
+                if factor > 0.0
+                {
+                        self.range = (self.range / factor).clamp(self.min_range, self.max_range);
+                }
+        }
+
+        /// Adds `marker`, replacing the marker `name`.
+        pub fn set_marker(
+                &mut self,
+                name: &str,
+                marker: MapMarker,
+        )
+        {
+                self.markers.insert(name.to_string(), marker);
+        }
+
+        pub fn remove_marker(
+                &mut self,
+                name: &str,
+        ) -> Option<MapMarker>
+        {
+                self.markers.remove(name)
+        }
+}
+
+impl Engine
+{
+        /// The [`Minimap`], see [`crate::ui::minimap`].
+        pub fn minimap(&mut self) -> &mut Minimap
+        {
+                &mut self.minimap
+        }
+}
+
+impl EngineState
+{
+        /// Draws the scene into the minimap and shows it, must be called
+        /// between [`EngineState::begin_ui`] and [`EngineState::end_ui`].
+        pub fn show_minimap(
+                &mut self,
+                minimap: &mut Minimap,
+                encoder: &mut wgpu::CommandEncoder,
+        )
+        {
+                let ctx = self.gui.renderer.context().clone();
+                let resolution = ((minimap.size * ctx.pixels_per_point()).round() as u32)
+                        .clamp(16, self.device.limits().max_texture_dimension_2d);
+
+                if minimap
+                        .target
+                        .as_ref()
+                        .is_none_or(|t| t.texture.width() != resolution)
+                {
+                        if let Some(target) = minimap.target.take()
+                        {
+                                self.gui.renderer.free_texture(target.id);
+                        }
+
+                        minimap.target = Some(self.create_minimap_target(resolution));
+                }
+
+                let view_proj = self.minimap_view_proj(minimap);
+                let camera = self.minimap_camera(view_proj);
+                let target = minimap.target.as_ref().unwrap();
+                let models: Vec<&Model> = self.models.values().filter(|m| m.visible).collect();
+
+                self.draw_offscreen(
+                        &models,
+                        &camera,
+                        &target.texture
+                                .create_view(&wgpu::TextureViewDescriptor::default()),
+                        &target.depth_texture,
+                        linear_color(minimap.background),
+                        encoder,
+                );
+
+                let size = Vec2::splat(minimap.size);
+                let offset = Vec2::new(
+                        edge_offset(minimap.anchor.x(), minimap.margin),
+                        edge_offset(minimap.anchor.y(), minimap.margin),
+                );
+
+                let forward = self.camera.core.forward();
+                let heading = Rad(forward.z.atan2(forward.x));
+
+                egui::Area::new(egui::Id::new("oxide_minimap"))
+                        .anchor(minimap.anchor, offset)
+                        .interactable(false)
+                        .show(&ctx, |ui| {
+                                let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+                                let radius = if minimap.circular { size.x * 0.5 } else { 4.0 };
+
+                                ui.put(
+                                        rect,
+                                        egui::Image::new((target.id, size)).corner_radius(radius),
+                                );
+
+                                let painter = ui.painter_at(rect.expand(2.0));
+                                let map = MapProjection {
+                                        view_proj,
+                                        center: rect.center(),
+                                        half_size: size.x * 0.5,
+                                        circular: minimap.circular,
+                                };
+
+                                for marker in minimap.markers.values()
+                                {
+                                        map.paint_marker(&painter, marker);
+                                }
+
+                                if minimap.show_heading
+                                {
+                                        map.paint_marker(
+                                                &painter,
+                                                &MapMarker {
+                                                        icon: MarkerIcon::Arrow {
+                                                                heading,
+                                                        },
+                                                        size: 12.0,
+                                                        clamp_to_edge: true,
+                                                        ..MapMarker::new(
+                                                                self.camera.core.position,
+                                                                Color32::WHITE,
+                                                        )
+                                                },
+                                        );
+                                }
+
+                                let stroke = egui::Stroke::new(2.0, Color32::from_gray(200));
+
+                                if minimap.circular
+                                {
+                                        painter.circle_stroke(rect.center(), size.x * 0.5, stroke);
+                                }
+                                else
+                                {
+                                        painter.rect_stroke(
+                                                rect,
+                                                radius,
+                                                stroke,
+                                                egui::StrokeKind::Inside,
+                                        );
+                                }
+                        });
+        }
+
+        fn create_minimap_target(
+                &mut self,
+                resolution: u32,
+        ) -> MinimapTarget
+        {
+                let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+                        label: Some("Minimap"),
+                        size: wgpu::Extent3d {
+                                width: resolution,
+                                height: resolution,
+                                depth_or_array_layers: 1,
+                        },
+                        mip_level_count: 1,
+                        sample_count: 1,
+                        dimension: wgpu::TextureDimension::D2,
+                        format: self.surface_manager.render_format(),
+                        usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                                | wgpu::TextureUsages::TEXTURE_BINDING,
+                        view_formats: &[],
+                });
+
+                let depth_texture = Texture::create_depth_texture(
+                        &self.device,
+                        &wgpu::SurfaceConfiguration {
+                                width: resolution,
+                                height: resolution,
+                                ..self.surface_manager.configuration.clone()
+                        },
+                        "Minimap Depth",
+                );
+
+                let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+                let id = self.gui.renderer.register_texture(&self.device, &view);
+
+                MinimapTarget {
+                        id,
+                        texture,
+                        depth_texture,
+                }
+        }
+
+        /// Looking straight down on the followed target.
+        fn minimap_view_proj(
+                &self,
+                minimap: &Minimap,
+        ) -> Matrix4<f32>
+        {
+                let center = match &minimap.follow
+                {
+                        MinimapFollow::Camera => None,
+                        MinimapFollow::Model(handle) => self
+                                .models
+                                .get(handle)
+                                .and_then(|m| m.world_bounds())
+                                .map(|b| b.center()),
+                        MinimapFollow::Point(point) => Some(*point),
+                }
+                .unwrap_or(self.camera.core.position);
+
+                let forward = self.camera.core.forward();
+                let flat = Vector3::new(forward.x, 0.0, forward.z);
+
+                // Screen up is -Z, or the camera's view when rotating.
+                let up = if minimap.rotate && flat.magnitude2() > 1e-6
+                {
+                        flat.normalize()
+                }
+                else
+                {
+                        -Vector3::unit_z()
+                };
+
+                let depth = minimap.depth.max(0.01);
+                let eye = center + Vector3::unit_y() * depth;
+
+                let mut projection = Orthographic::new(minimap.range.max(0.01), 0.0, depth * 2.0);
+                projection.reversed_z = self.camera.projection.reversed_z;
+
+                projection.calc_matrix() * Matrix4::look_at_rh(eye, center, up)
+        }
+
+        fn minimap_camera(
+                &self,
+                view_proj: Matrix4<f32>,
+        ) -> wgpu::BindGroup
+        {
+                let mut uniform = CameraUniform::new();
+
+                // Far above, the lighting only needs a direction.
+                uniform.view_position = (self.camera.core.position + Vector3::unit_y() * 1.0e4)
+                        .to_homogeneous()
+                        .into();
+                uniform.view_proj = view_proj.into();
+                uniform.environment = self.camera.uniform.environment;
+
+                let buffer = self
+                        .device
+                        .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                                label: Some("Minimap Camera Buffer"),
+                                contents: bytemuck::cast_slice(&[uniform]),
+                                usage: wgpu::BufferUsages::UNIFORM,
+                        });
+
+                self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                        layout: &create_camera_bind_group_layout(&self.device),
+                        entries: &[wgpu::BindGroupEntry {
+                                binding: 0,
+                                resource: buffer.as_entire_binding(),
+                        }],
+                        label: Some("minimap_camera_bind_group"),
+                })
+        }
+}
+
+/// Offset of an [`egui::Area`] anchored at `align`, `margin` away from
+/// the edge.
+fn edge_offset(
+        align: Align,
+        margin: f32,
+) -> f32
+{
+        match align
+        {
+                Align::Min => margin,
+                Align::Center => 0.0,
+                Align::Max => -margin,
+        }
+}
+
+/// `color` as the linear value a render pass clears to.
+fn linear_color(color: Color32) -> wgpu::Color
+{
+        let rgba = egui::Rgba::from(color);
+
+        wgpu::Color {
+                r: rgba.r() as f64,
+                g: rgba.g() as f64,
+                b: rgba.b() as f64,
+                a: rgba.a() as f64,
+        }
+}
+
+/// World positions to points on the map.
+struct MapProjection
+{
+        view_proj: Matrix4<f32>,
+        center: Pos2,
+        half_size: f32,
+        circular: bool,
+}
+
+impl MapProjection
+{
+        /// From the center of the map, `1.0` at the edges.
+        fn project(
+                &self,
+                position: Point3<f32>,
+        ) -> Vec2
+        {
+                let clip = self.view_proj * position.to_homogeneous();
+
+                Vec2::new(clip.x, -clip.y) / clip.w.max(f32::EPSILON)
+        }
+
+        fn paint_marker(
+                &self,
+                painter: &egui::Painter,
+                marker: &MapMarker,
+        )
+        {
+                let mut at = self.project(marker.position);
+
+                let outside = if self.circular
+                {
+                        at.length() > 1.0
+                }
+                else
+                {
+                        at.x.abs() > 1.0 || at.y.abs() > 1.0
+                };
+
+                if outside
+                {
+                        if !marker.clamp_to_edge
+                        {
+                                return;
+                        }
+
+                        at = if self.circular
+                        {
+                                at.normalized()
+                        }
+                        else
+                        {
+                                at / at.x.abs().max(at.y.abs())
+                        };
+                }
+
+                let position = self.center + at * self.half_size;
+                let radius = marker.size * 0.5;
+
+                match marker.icon
+                {
+                        MarkerIcon::Dot =>
+                        {
+                                painter.circle_filled(position, radius, marker.color);
+                        }
+                        MarkerIcon::Square =>
+                        {
+                                painter.rect_filled(
+                                        egui::Rect::from_center_size(
+                                                position,
+                                                Vec2::splat(marker.size),
+                                        ),
+                                        0.0,
+                                        marker.color,
+                                );
+                        }
+                        MarkerIcon::Arrow {
+                                heading,
+                        } =>
+                        {
+                                let (sin, cos) = heading.0.sin_cos();
+                                let ahead = marker.position.to_vec() + Vector3::new(cos, 0.0, sin);
+                                let ahead = self.view_proj * ahead.extend(1.0);
+                                let tip = Vec2::new(ahead.x, -ahead.y) / ahead.w.max(f32::EPSILON)
+                                        - self.project(marker.position);
+
+                                let forward = if tip.length_sq() > 1e-12
+                                {
+                                        tip.normalized()
+                                }
+                                else
+                                {
+                                        -Vec2::Y
+                                };
+                                let side = forward.rot90();
+
+                                painter.add(egui::Shape::convex_polygon(
+                                        vec![
+                                                position + forward * radius,
+                                                position - forward * radius * 0.6
+                                                        + side * radius * 0.7,
+                                                position - forward * radius * 0.6
+                                                        - side * radius * 0.7,
+                                        ],
+                                        marker.color,
+                                        egui::Stroke::new(1.0, Color32::BLACK),
+                                ));
+                        }
+                        MarkerIcon::Image(id) =>
+                        {
+                                painter.image(
+                                        id,
+                                        egui::Rect::from_center_size(
+                                                position,
+                                                Vec2::splat(marker.size),
+                                        ),
+                                        egui::Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0)),
+                                        marker.color,
+                                );
+                        }
+                }
+        }
+}
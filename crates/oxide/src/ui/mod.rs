@@ -1,11 +1,84 @@
+//! The debug UI and its settings.
+//!
+//! Everything drawing with `egui` needs the `ui` feature, enabled by
+//! default. Without it only the settings kept in the config file remain and
+//! [`Config::enable_debug`](crate::config::Config::enable_debug) does
+//! nothing.
+
+#[cfg(feature = "ui")]
 use crate::camera::CameraController;
+use crate::engine::Engine;
+#[cfg(feature = "ui")]
 use crate::ui::renderer::GuiRenderer;
+#[cfg(feature = "ui")]
 use egui::{Align2, Button, Vec2};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "ui")]
 use wgpu::{Device, TextureFormat};
+#[cfg(feature = "ui")]
 use winit::window::Window;
 
+#[cfg(feature = "ui")]
+pub mod assets;
+#[cfg(feature = "ui")]
+pub mod console;
+#[cfg(feature = "ui")]
+pub mod graph;
+#[cfg(feature = "ui")]
+pub mod hover;
+#[cfg(feature = "ui")]
+pub mod inspect;
+#[cfg(feature = "ui")]
+pub mod measure;
+#[cfg(feature = "ui")]
+pub mod minimap;
+#[cfg(feature = "ui")]
 pub mod renderer;
+#[cfg(feature = "ui")]
+pub mod selection;
+#[cfg(feature = "ui")]
+pub mod thumbnail;
+
+/// Debug UI settings kept in the config file, see
+/// [`EngineBuilder::with_config_file`](crate::engine::EngineBuilder::with_config_file).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UiSettings
+{
+        pub scale: f32,
+        pub show_right_panel: bool,
+        pub right_panel_width: f32,
+}
+
+impl Default for UiSettings
+{
+        fn default() -> Self
+        {
+                Self {
+                        scale: 1.2,
+                        show_right_panel: true,
+                        right_panel_width: 400.0,
+                }
+        }
+}
 
+/// Directional input for moving the focus through the debug UI without a
+/// mouse, e.g. from a gamepad, see
+/// [`Engine::ui_navigate`](crate::engine::Engine::ui_navigate).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UiNav
+{
+        Up,
+        Down,
+        Left,
+        Right,
+        /// Clicks the focused widget.
+        Activate,
+        /// Drops the focus, the game gets the keyboard back.
+        Back,
+}
+
+#[cfg(feature = "ui")]
 #[derive(Debug)]
 pub struct UiSystem
 {
@@ -13,6 +86,7 @@ pub struct UiSystem
         pub renderer: GuiRenderer,
 }
 
+#[cfg(feature = "ui")]
 impl UiSystem
 {
         pub fn new(
@@ -38,6 +112,28 @@ impl UiSystem
         }
 }
 
+impl Engine
+{
+        /// Moves the focus in the debug UI, for gamepads and other input
+        /// without a mouse. Does nothing while the debug UI is hidden.
+        pub fn ui_navigate(
+                &mut self,
+                nav: UiNav,
+        )
+        {
+                #[cfg(not(feature = "ui"))]
+                let _ = nav;
+
+                #[cfg(feature = "ui")]
+                if let Some(state) = &mut self.state
+                        && self.config.enable_debug
+                {
+                        state.gui.renderer.navigate(nav);
+                }
+        }
+}
+
+#[cfg(feature = "ui")]
 pub fn draw_dpad(
         ctx: &egui::Context,
         controller: &mut CameraController,
@@ -1,8 +1,16 @@
+use crate::audio::AudioSystem;
 use crate::camera::Camera;
+use crate::config::Config;
 use crate::engine::FillMode;
+use crate::measure::Measurement;
 use crate::model::Model;
 use crate::renderer::graph::RenderGraph;
-use crate::ui::draw_dpad;
+use crate::selection::Selection;
+use crate::ui::graph::RenderGraphView;
+use crate::ui::thumbnail::{THUMBNAIL_SIZE, Thumbnails};
+use crate::ui::{UiNav, draw_dpad};
+use crate::transform::Transform;
+use crate::undo::{ModelState, UndoStack};
 use derivative::Derivative;
 use egui::{Align2, Context, FontData, FontDefinitions, FontFamily, Vec2};
 use egui_wgpu::Renderer;
@@ -31,6 +39,15 @@ pub struct GuiRenderer
 
         pub show_right_panel: bool,
 
+        graph_view: RenderGraphView,
+
+        /// Pictures of the models in the models window, see
+        /// [`crate::ui::thumbnail`].
+        pub thumbnails: Thumbnails,
+
+        /// Transform copied in the models window.
+        copied_transform: Option<Transform>,
+
         frame_started: bool,
 }
 
@@ -95,10 +112,47 @@ impl GuiRenderer
                         show_right_panel: true,
                         state: egui_state,
                         renderer: egui_renderer,
+                        graph_view: RenderGraphView::default(),
+                        thumbnails: Thumbnails::default(),
+                        copied_transform: None,
                         frame_started: false,
                 }
         }
 
+        /// Queues `nav` as key presses for the next frame. Without a
+        /// focused widget any direction focuses the first one.
+        pub fn navigate(
+                &mut self,
+                nav: UiNav,
+        )
+        {
+                let focused = self.context().memory(|m| m.focused()).is_some();
+
+                let key = match nav
+                {
+                        UiNav::Back => egui::Key::Escape,
+                        UiNav::Activate => egui::Key::Enter,
+                        _ if !focused => egui::Key::Tab,
+                        UiNav::Up => egui::Key::ArrowUp,
+                        UiNav::Down => egui::Key::ArrowDown,
+                        UiNav::Left => egui::Key::ArrowLeft,
+                        UiNav::Right => egui::Key::ArrowRight,
+                };
+
+                let events = &mut self.state.egui_input_mut().events;
+
+                for pressed in [true, false]
+                {
+                        events.push(egui::Event::Key {
+                                key,
+                                physical_key: None,
+                                pressed,
+                                repeat: false,
+                                modifiers: egui::Modifiers::NONE,
+                        });
+                }
+        }
+
         pub fn handle_input(
                 &mut self,
                 window: &Window,
@@ -108,6 +162,26 @@ impl GuiRenderer
                 let _ = self.state.on_window_event(window, event);
         }
 
+        /// Makes `view` drawable with egui, until
+        /// [`GuiRenderer::free_texture`].
+        pub fn register_texture(
+                &mut self,
+                device: &Device,
+                view: &TextureView,
+        ) -> egui::TextureId
+        {
+                self.renderer
+                        .register_native_texture(device, view, wgpu::FilterMode::Linear)
+        }
+
+        pub fn free_texture(
+                &mut self,
+                id: egui::TextureId,
+        )
+        {
+                self.renderer.free_texture(&id);
+        }
+
         pub fn ppp(
                 &mut self,
                 v: f32,
@@ -119,10 +193,10 @@ impl GuiRenderer
         pub fn begin_frame(
                 &mut self,
                 window: &Window,
-                ui_scale: &mut f32,
+                pixels_per_point: f32,
         )
         {
-                self.ppp(self.current_pixels_per_point(window, ui_scale));
+                self.ppp(pixels_per_point);
 
                 let raw_input = self.state.take_egui_input(window);
 
@@ -204,28 +278,41 @@ impl GuiRenderer
                 &mut self,
                 graph: &mut RenderGraph,
                 ui_scale: &mut f32,
-                fill_mode: &mut FillMode,
+                config: &mut Config,
                 features: wgpu::Features,
                 camera: &mut Camera,
                 dt: &Duration,
                 models: &mut HashMap<String, Model>,
+                selection: &mut Selection,
+                measurement: &mut Measurement,
+                history: &mut UndoStack,
+                queue: &wgpu::Queue,
+                audio: &mut AudioSystem,
         )
         {
-                self.debug_window(graph, ui_scale, fill_mode, features, camera, &dt, models);
+                self.debug_window(
+                        graph, ui_scale, config, features, camera, &dt, models, selection,
+                        measurement, history, queue, audio,
+                );
         }
 
         pub fn debug_window(
                 &mut self,
                 graph: &mut RenderGraph,
                 ui_scale: &mut f32,
-                fill_mode: &mut FillMode,
+                config: &mut Config,
                 features: wgpu::Features,
                 camera: &mut Camera,
                 dt: &Duration,
                 models: &mut HashMap<String, Model>,
+                selection: &mut Selection,
+                measurement: &mut Measurement,
+                history: &mut UndoStack,
+                queue: &wgpu::Queue,
+                audio: &mut AudioSystem,
         )
         {
-                let mut temp_fill_mode = *fill_mode;
+                let mut temp_fill_mode = config.fill_mode;
                 let mut scale: f32 = *ui_scale;
 
                 egui::Area::new("nice".into())
@@ -245,7 +332,13 @@ impl GuiRenderer
 
                 if self.show_right_panel
                 {
-                        egui::Window::new("Right Panel").resizable(true).default_width(400.0).anchor(Align2::RIGHT_TOP, Vec2::ZERO).show(self.context(), |ui| {
+                        let graph_view = &mut self.graph_view;
+
+                        let right_panel_width = &mut config.ui.right_panel_width;
+
+                        egui::Window::new("Right Panel").resizable(true).default_width(*right_panel_width).anchor(Align2::RIGHT_TOP, Vec2::ZERO).show(self.state.egui_ctx(), |ui| {
+                                *right_panel_width = ui.max_rect().width().round();
+
                                 egui::ScrollArea::new(true).show(ui, |ui| {
                                         // UI scale controls
                                         ui.horizontal(|ui| {
@@ -290,48 +383,53 @@ impl GuiRenderer
                                                                     "Vertex",
                                                             );
                                                     }
+                                                    ui.selectable_value(
+                                                            &mut temp_fill_mode,
+                                                            FillMode::WireframeOverlay,
+                                                            "Wireframe Overlay",
+                                                    );
+                                                    ui.selectable_value(
+                                                            &mut temp_fill_mode,
+                                                            FillMode::UvChecker,
+                                                            "UV Checker",
+                                                    );
+                                                    ui.selectable_value(
+                                                            &mut temp_fill_mode,
+                                                            FillMode::Normals,
+                                                            "Normals",
+                                                    );
+                                                    ui.selectable_value(
+                                                            &mut temp_fill_mode,
+                                                            FillMode::Overdraw,
+                                                            "Overdraw",
+                                                    );
+                                                    ui.selectable_value(
+                                                            &mut temp_fill_mode,
+                                                            FillMode::MipLevel,
+                                                            "Mip Level",
+                                                    );
                                             });
 
+                                        config.environment.ui(ui);
+
+                                        ui.checkbox(
+                                                &mut config.highlight_hovered,
+                                                "Highlight hovered model",
+                                        );
+
+                                        ui.checkbox(&mut measurement.enabled, "Measure distances");
+
+                                        config.snapping.ui(ui, config.grid.spacing);
+
                                         camera.ui(ui);
 
+                                        audio.ui(ui);
+
                                         // Collapsible section for passes
                                         egui::CollapsingHeader::new("Render Pass Graph")
                                             .default_open(true)
                                             .show(ui, |ui| {
-                                                    let len = graph.passes.len();
-                                                    let mut move_req: Option<(usize, isize)> = None;
-
-                                                    for (i, pass) in graph.passes.iter_mut().enumerate()
-                                                    {
-                                                            let mut enabled = pass.enabled();
-
-                                                            ui.horizontal(|ui| {
-                                                                    pass.ui(ui);
-
-                                                                    ui.with_layout(
-                                                                            egui::Layout::right_to_left(egui::Align::Center),
-                                                                            |ui| {
-                                                                                    ui.checkbox(&mut enabled, "Enabled");
-
-                                                                                    if ui.button(egui::RichText::new("[ ↓ ]").strong().text_style(egui::TextStyle::Monospace)).clicked() && i + 1 < len {
-                                                                                            move_req = Some((i, 1));
-                                                                                    }
-                                                                                    if ui.button(egui::RichText::new("[ ↑ ]").strong().text_style(egui::TextStyle::Monospace)).clicked() && i > 0 {
-                                                                                            move_req = Some((i, -1));
-                                                                                    }
-                                                                            },
-                                                                    );
-                                                            });
-
-                                                            ui.separator();
-                                                            pass.set_enabled(enabled);
-                                                    }
-
-                                                    if let Some((i, d)) = move_req
-                                                    {
-                                                            let j = (i as isize + d) as usize;
-                                                            graph.passes.swap(i, j);
-                                                    }
+                                                    graph_view.ui(ui, graph);
                                             });
 
 
@@ -339,18 +437,125 @@ impl GuiRenderer
 
                         });
 
+                        let thumbnails = &self.thumbnails;
+
+                        let snapping = config.snapping;
+                        let mut moved = None;
+                        let mut touched = None;
+                        let mut copied = self.copied_transform;
+
                         egui::Window::new("Models").show(self.context(), |ui| {
                                 for (key, value) in models.iter_mut()
                                 {
                                         ui.group(|ui| {
-                                                ui.label(format!("Model: {}", key));
+                                                ui.horizontal(|ui| {
+                                                        if let Some(id) = thumbnails.get(key)
+                                                        {
+                                                                let size = THUMBNAIL_SIZE as f32;
+
+                                                                ui.image((id, egui::vec2(size, size)));
+                                                        }
+
+                                                        let is_selected = selection.contains(key);
+
+                                                        if ui.selectable_label(
+                                                                is_selected,
+                                                                format!("Model: {}", key),
+                                                        )
+                                                        .clicked()
+                                                        {
+                                                                // Shift adds to the selection,
+                                                                // see `crate::selection`.
+                                                                if ui.input(|i| i.modifiers.shift)
+                                                                {
+                                                                        selection.toggle(key);
+                                                                }
+                                                                else if is_selected
+                                                                        && selection.len() == 1
+                                                                {
+                                                                        selection.clear();
+                                                                }
+                                                                else
+                                                                {
+                                                                        selection.set(Some(key));
+                                                                }
+                                                        }
+
+                                                        // Same as the frame key, see
+                                                        // `Camera::frame`.
+                                                        if ui.button("Focus").clicked()
+                                                                && let Some(bounds) =
+                                                                        value.world_bounds()
+                                                        {
+                                                                selection.set(Some(key));
+                                                                camera.frame(&bounds);
+                                                        }
+                                                });
                                                 ui.push_id(key, |ui| {
+                                                        let state = ModelState::of(value);
+                                                        let before = value.transform;
+
                                                         value.ui(ui);
+
+                                                        ui.horizontal(|ui| {
+                                                                if ui.button("Copy transform").clicked()
+                                                                {
+                                                                        copied = Some(value.transform);
+                                                                }
+
+                                                                if let Some(transform) = copied
+                                                                        && ui.button("Paste transform").clicked()
+                                                                {
+                                                                        value.transform = transform;
+                                                                }
+                                                        });
+
+                                                        ui.collapsing("Materials", |ui| {
+                                                                for (i, material) in value.materials.iter_mut().enumerate()
+                                                                {
+                                                                        ui.push_id(i, |ui| material.ui(ui, queue));
+                                                                }
+                                                        });
+
+                                                        if snapping.enabled && value.transform != before
+                                                        {
+                                                                snapping.apply(&before, &mut value.transform);
+
+                                                                if value.transform.position != before.position
+                                                                {
+                                                                        moved = Some(key.clone());
+                                                                }
+                                                        }
+
+                                                        if ModelState::of(value) != state
+                                                        {
+                                                                touched = Some((key.clone(), state));
+                                                        }
                                                 });
                                         });
                                         ui.separator();
                                 }
                         });
+
+                        if snapping.surface
+                                && let Some(handle) = moved
+                        {
+                                crate::snapping::drop_to_surface(models, &handle);
+                        }
+
+                        self.copied_transform = copied;
+
+                        // A drag is one edit, recorded once the pointer is
+                        // released, see `crate::undo`.
+                        if let Some((handle, state)) = touched
+                        {
+                                history.touch(&handle, state, models);
+                        }
+
+                        if !self.context().input(|i| i.pointer.any_down())
+                        {
+                                history.finish(models);
+                        }
                 }
 
                 if camera.show_dpad
@@ -359,29 +564,11 @@ impl GuiRenderer
                 }
 
                 *ui_scale = scale;
-                if *fill_mode != temp_fill_mode
+                config.ui.scale = scale;
+                config.ui.show_right_panel = self.show_right_panel;
+                if config.fill_mode != temp_fill_mode
                 {
-                        *fill_mode = temp_fill_mode;
+                        config.fill_mode = temp_fill_mode;
                 }
         }
-
-        #[cfg(target_arch = "wasm32")]
-        pub fn current_pixels_per_point(
-                &self,
-                _window: &winit::window::Window,
-                ui_scale: &mut f32,
-        ) -> f32
-        {
-                web_sys::window().unwrap().device_pixel_ratio() as f32 * *ui_scale
-        }
-
-        #[cfg(not(target_arch = "wasm32"))]
-        pub fn current_pixels_per_point(
-                &self,
-                window: &Window,
-                ui_scale: &mut f32,
-        ) -> f32
-        {
-                window.scale_factor() as f32 * ui_scale.clone()
-        }
 }
@@ -0,0 +1,340 @@
+//! The "Selection" window of the debug UI, editing every selected model at
+//! once, see [`crate::selection`].
+//!
+//! Visibility, layer and tint are set on all of them in one edit, undone
+//! at once, see [`crate::undo`]. The tint replaces the base color factor
+//! of every material of the models. Attaching them to a parent keeps them
+//! where they are, see [`Engine::attach`], and isn't undone.
+
+use crate::attachment::AttachmentSystem;
+use crate::engine::Engine;
+use crate::model::Model;
+use crate::selection::Selection;
+use crate::transform::Transform;
+use crate::undo::{Edit, ModelState, UndoStack};
+use cgmath::SquareMatrix;
+use std::collections::HashMap;
+
+/// What a button of the window asked for, done on the next frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum SelectionAction
+{
+        Attach(String),
+        Detach,
+        Merge,
+}
+
+/// An edit of every selected model.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BulkEdit
+{
+        Visible(bool),
+        Layer(u32),
+        Tint([f32; 4]),
+}
+
+impl BulkEdit
+{
+        fn apply(
+                self,
+                model: &mut Model,
+                queue: &wgpu::Queue,
+        )
+        {
+                match self
+                {
+                        BulkEdit::Visible(visible) => model.visible = visible,
+                        BulkEdit::Layer(layer) => model.layer = layer,
+                        BulkEdit::Tint(tint) =>
+                        {
+                                for material in &mut model.materials
+                                {
+                                        let mut desc = material.desc();
+                                        desc.base_color_factor = tint;
+
+                                        material.apply_desc(queue, &desc);
+                                }
+                        }
+                }
+        }
+}
+
+/// The "Selection" window, owned by the [`Engine`].
+#[derive(Debug)]
+pub struct SelectionPanel
+{
+        action: Option<SelectionAction>,
+
+        /// Picked in the window, applied by its buttons.
+        parent: Option<String>,
+        layer: u32,
+        tint: [f32; 4],
+}
+
+impl Default for SelectionPanel
+{
+        fn default() -> Self
+        {
+                Self {
+                        action: None,
+                        parent: None,
+                        layer: 0,
+                        tint: [1.0, 1.0, 1.0, 1.0],
+                }
+        }
+}
+
+impl SelectionPanel
+{
+        pub fn new() -> Self
+        {
+                Self::default()
+        }
+
+        /// Drops models that are gone from the selection and does what a
+        /// button was clicked for.
+        ///
+        /// Called by the engine once per frame.
+        pub(crate) fn update(engine: &mut Engine)
+        {
+                if let Some(state) = engine.state.as_mut()
+                {
+                        let models = &state.models;
+
+                        state.selection.retain(|handle| models.contains_key(handle));
+                }
+
+                let Some(action) = engine.selection_panel.action.take()
+                else
+                {
+                        return;
+                };
+
+                let handles = engine.selection().to_vec();
+
+                match action
+                {
+                        SelectionAction::Attach(parent) =>
+                        {
+                                let Some(models) = engine.state.as_ref().map(|s| &s.models)
+                                else
+                                {
+                                        return;
+                                };
+
+                                // Where the models are relative to the
+                                // parent, so they stay in place.
+                                let Some(inverse) = models
+                                        .get(&parent)
+                                        .and_then(|p| p.calculate_transform().invert())
+                                else
+                                {
+                                        return;
+                                };
+
+                                let offsets: Vec<(String, Transform)> = handles
+                                        .iter()
+                                        .filter(|handle| **handle != parent)
+                                        .filter_map(|handle| {
+                                                let matrix = inverse
+                                                        * models.get(handle)?.transform.matrix();
+
+                                                Some((
+                                                        handle.clone(),
+                                                        Transform::from_matrix(&matrix),
+                                                ))
+                                        })
+                                        .collect();
+
+                                for (handle, offset) in offsets
+                                {
+                                        if let Some(attachment) =
+                                                engine.attach(handle, parent.as_str(), "")
+                                        {
+                                                attachment.offset = offset;
+                                        }
+                                }
+                        }
+                        SelectionAction::Detach =>
+                        {
+                                for handle in &handles
+                                {
+                                        engine.detach(handle);
+                                }
+                        }
+                        SelectionAction::Merge =>
+                        {
+                                let Some(state) = engine.state.as_ref()
+                                else
+                                {
+                                        return;
+                                };
+
+                                if let Some(batch) = (1..)
+                                        .map(|n| format!("batch_{}", n))
+                                        .find(|h| !state.models.contains_key(h))
+                                {
+                                        engine.merge_models(&handles, batch);
+                                }
+                        }
+                }
+        }
+
+        /// Draws the "Selection" window while models are selected.
+        ///
+        /// Called by the engine while the debug UI is open.
+        pub(crate) fn ui(
+                &mut self,
+                ctx: &egui::Context,
+                selection: &mut Selection,
+                models: &mut HashMap<String, Model>,
+                history: &mut UndoStack,
+                queue: &wgpu::Queue,
+                attachments: &AttachmentSystem,
+        )
+        {
+                if selection.is_empty()
+                {
+                        return;
+                }
+
+                let mut edit = None;
+
+                egui::Window::new("Selection").show(ctx, |ui| {
+                        let triangles: u32 = selection
+                                .handles()
+                                .iter()
+                                .filter_map(|h| models.get(h))
+                                .map(|m| m.triangle_count())
+                                .sum();
+
+                        ui.label(format!("{} selected, {} triangles", selection.len(), triangles));
+                        ui.weak(selection.handles().join(", "));
+
+                        ui.separator();
+
+                        ui.horizontal(|ui| {
+                                if ui.button("Show").clicked()
+                                {
+                                        edit = Some(BulkEdit::Visible(true));
+                                }
+
+                                if ui.button("Hide").clicked()
+                                {
+                                        edit = Some(BulkEdit::Visible(false));
+                                }
+                        });
+
+                        ui.horizontal(|ui| {
+                                ui.label("Layer");
+                                ui.add(egui::DragValue::new(&mut self.layer));
+
+                                if ui.button("Set").clicked()
+                                {
+                                        edit = Some(BulkEdit::Layer(self.layer));
+                                }
+
+                                if ui.button("Select layer").clicked()
+                                {
+                                        selection.clear();
+
+                                        for (handle, _) in
+                                                models.iter().filter(|(_, m)| m.layer == self.layer)
+                                        {
+                                                selection.add(handle);
+                                        }
+                                }
+                        });
+
+                        ui.horizontal(|ui| {
+                                ui.label("Tint");
+                                ui.color_edit_button_rgba_unmultiplied(&mut self.tint);
+
+                                if ui.button("Apply").clicked()
+                                {
+                                        edit = Some(BulkEdit::Tint(self.tint));
+                                }
+                        });
+
+                        ui.horizontal(|ui| {
+                                egui::ComboBox::from_label("Parent")
+                                        .selected_text(self.parent.as_deref().unwrap_or("None"))
+                                        .show_ui(ui, |ui| {
+                                                let mut handles: Vec<&String> = models
+                                                        .keys()
+                                                        .filter(|h| !selection.contains(h))
+                                                        .collect();
+                                                handles.sort();
+
+                                                for handle in handles
+                                                {
+                                                        ui.selectable_value(
+                                                                &mut self.parent,
+                                                                Some(handle.clone()),
+                                                                handle.as_str(),
+                                                        );
+                                                }
+                                        });
+
+                                if let Some(parent) = &self.parent
+                                        && ui.button("Attach").clicked()
+                                {
+                                        self.action = Some(SelectionAction::Attach(parent.clone()));
+                                }
+
+                                let attached = selection
+                                        .handles()
+                                        .iter()
+                                        .any(|h| attachments.get(h).is_some());
+
+                                if ui.add_enabled(attached, egui::Button::new("Detach"))
+                                        .clicked()
+                                {
+                                        self.action = Some(SelectionAction::Detach);
+                                }
+                        });
+
+                        ui.separator();
+
+                        ui.horizontal(|ui| {
+                                if ui.button("Merge as static batch").clicked()
+                                {
+                                        self.action = Some(SelectionAction::Merge);
+                                }
+
+                                if ui.button("Clear selection").clicked()
+                                {
+                                        selection.clear();
+                                }
+                        });
+                });
+
+                let Some(edit) = edit
+                else
+                {
+                        return;
+                };
+
+                // After the edit in progress in the models window.
+                history.finish(models);
+
+                let edits = selection
+                        .handles()
+                        .iter()
+                        .filter_map(|handle| {
+                                let model = models.get_mut(handle)?;
+                                let before = ModelState::of(model);
+
+                                edit.apply(model, queue);
+
+                                Some(Edit {
+                                        handle: handle.clone(),
+                                        before,
+                                        after: ModelState::of(model),
+                                })
+                        })
+                        .collect();
+
+                history.record_all(edits);
+        }
+}
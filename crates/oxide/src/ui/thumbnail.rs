@@ -0,0 +1,204 @@
+//! Small pictures of the models for the debug UI.
+//!
+//! Every model is drawn once into a [`THUMBNAIL_SIZE`] square offscreen
+//! target when it shows up, looking at its bounds from the front with the
+//! engine's model pipeline and lighting. The models window shows them next
+//! to the entries, so the models of large scenes are easier to tell apart.
+//!
+//! Models that appear while a fill mode other than
+//! [`FillMode::Fill`] is set get their thumbnail once it's back.
+
+use crate::camera::{CameraUniform, Projection, create_camera_bind_group_layout};
+use crate::engine::EngineState;
+use crate::model::Model;
+use crate::renderer::pipeline::FillMode;
+use crate::texture::Texture;
+use cgmath::{Deg, EuclideanSpace, InnerSpace, Matrix4, Vector3};
+use std::collections::HashMap;
+use wgpu::util::DeviceExt;
+
+/// Width and height of a thumbnail in pixels.
+pub const THUMBNAIL_SIZE: u32 = 64;
+
+#[derive(Debug)]
+struct Thumbnail
+{
+        id: egui::TextureId,
+        /// Kept alive while egui draws it.
+        _texture: wgpu::Texture,
+}
+
+/// The thumbnails by model handle, owned by the
+/// [`GuiRenderer`](crate::ui::renderer::GuiRenderer).
+#[derive(Debug, Default)]
+pub struct Thumbnails
+{
+        thumbnails: HashMap<String, Thumbnail>,
+}
+
+impl Thumbnails
+{
+        pub fn get(
+                &self,
+                handle: &str,
+        ) -> Option<egui::TextureId>
+        {
+                self.thumbnails.get(handle).map(|t| t.id)
+        }
+}
+
+impl EngineState
+{
+        /// Draws the thumbnails of the models without one and frees the ones
+        /// of removed models.
+        pub fn update_thumbnails(
+                &mut self,
+                encoder: &mut wgpu::CommandEncoder,
+        )
+        {
+                let removed: Vec<String> =
+                        self.gui.renderer
+                                .thumbnails
+                                .thumbnails
+                                .keys()
+                                .filter(|handle| !self.models.contains_key(*handle))
+                                .cloned()
+                                .collect();
+
+                for handle in removed
+                {
+                        if let Some(thumbnail) =
+                                self.gui.renderer.thumbnails.thumbnails.remove(&handle)
+                        {
+                                self.gui.renderer.free_texture(thumbnail.id);
+                        }
+                }
+
+                if self.pipeline_manager.fill_mode != FillMode::Fill
+                {
+                        return;
+                }
+
+                let missing: Vec<String> = self
+                        .models
+                        .keys()
+                        .filter(|handle| {
+                                !self.gui
+                                        .renderer
+                                        .thumbnails
+                                        .thumbnails
+                                        .contains_key(*handle)
+                        })
+                        .cloned()
+                        .collect();
+
+                for handle in missing
+                {
+                        let texture = self.draw_thumbnail(&self.models[&handle], encoder);
+                        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+                        let id = self.gui.renderer.register_texture(&self.device, &view);
+
+                        self.gui.renderer.thumbnails.thumbnails.insert(
+                                handle,
+                                Thumbnail {
+                                        id,
+                                        _texture: texture,
+                                },
+                        );
+                }
+        }
+
+        fn draw_thumbnail(
+                &self,
+                model: &Model,
+                encoder: &mut wgpu::CommandEncoder,
+        ) -> wgpu::Texture
+        {
+                let size = wgpu::Extent3d {
+                        width: THUMBNAIL_SIZE,
+                        height: THUMBNAIL_SIZE,
+                        depth_or_array_layers: 1,
+                };
+
+                let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+                        label: Some("Thumbnail"),
+                        size,
+                        mip_level_count: 1,
+                        sample_count: 1,
+                        dimension: wgpu::TextureDimension::D2,
+                        format: self.surface_manager.render_format(),
+                        usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                                | wgpu::TextureUsages::TEXTURE_BINDING,
+                        view_formats: &[],
+                });
+
+                let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+                let depth_texture = Texture::create_depth_texture(
+                        &self.device,
+                        &wgpu::SurfaceConfiguration {
+                                width: THUMBNAIL_SIZE,
+                                height: THUMBNAIL_SIZE,
+                                ..self.surface_manager.configuration.clone()
+                        },
+                        "Thumbnail Depth",
+                );
+
+                let camera = self.thumbnail_camera(model);
+
+                self.draw_offscreen(
+                        &[model],
+                        &camera,
+                        &view,
+                        &depth_texture,
+                        wgpu::Color::TRANSPARENT,
+                        encoder,
+                );
+
+                texture
+        }
+
+        /// A camera in front of the world bounds of `model`, framing them.
+        fn thumbnail_camera(
+                &self,
+                model: &Model,
+        ) -> wgpu::BindGroup
+        {
+                let (center, radius) = model
+                        .world_bounds()
+                        .map(|b| (b.center(), (b.size().magnitude() * 0.5).max(0.001)))
+                        .unwrap_or((cgmath::Point3::origin(), 1.0));
+
+                let fovy: Deg<f32> = Deg(40.0);
+                let distance = radius / (fovy.0.to_radians() * 0.5).sin();
+                let eye = center + Vector3::new(0.5, 0.4, 1.0).normalize() * distance;
+
+                let mut projection =
+                        Projection::new(fovy, distance * 0.01, distance + radius * 2.0);
+                projection.reversed_z = self.camera.projection.reversed_z;
+
+                let view = Matrix4::look_at_rh(eye, center, Vector3::unit_y());
+
+                let mut uniform = CameraUniform::new();
+                uniform.view_position = eye.to_homogeneous().into();
+                uniform.view_proj = (projection.calc_matrix() * view).into();
+                uniform.environment = self.camera.uniform.environment;
+
+                let buffer = self
+                        .device
+                        .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                                label: Some("Thumbnail Camera Buffer"),
+                                contents: bytemuck::cast_slice(&[uniform]),
+                                usage: wgpu::BufferUsages::UNIFORM,
+                        });
+
+                self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                        layout: &create_camera_bind_group_layout(&self.device),
+                        entries: &[wgpu::BindGroupEntry {
+                                binding: 0,
+                                resource: buffer.as_entire_binding(),
+                        }],
+                        label: Some("thumbnail_camera_bind_group"),
+                })
+        }
+}
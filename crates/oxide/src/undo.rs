@@ -0,0 +1,312 @@
+//! Undo and redo of models edited in the debug UI.
+//!
+//! Every edit in the models window, the transform, visibility, layer,
+//! morph weights and material factors of a model, is recorded once it's
+//! done, a drag being a single edit. An edit of the whole selection, see
+//! [`crate::selection`], is undone at once. Ctrl+Z undoes the last one and
+//! Ctrl+Y redoes it while the debug UI is open, or from code:
+//!
+//! ```ignore
+//! engine.register_behavior(|eng| {
+//!         if eng.pressed_keys.contains(&KeyCode::Backspace)
+//!         {
+//!                 eng.undo();
+//!         }
+//! });
+//! ```
+//!
+//! Tools of the game can record their own changes with
+//! [`UndoStack::record`]. Changes made from code otherwise aren't
+//! recorded, undoing an edit puts back what the model was before it.
+
+use crate::engine::Engine;
+use crate::material::MaterialDesc;
+use crate::model::Model;
+use crate::transform::Transform;
+use std::collections::HashMap;
+
+/// Edits kept before the oldest is dropped.
+pub const DEFAULT_LIMIT: usize = 100;
+
+/// What an edit can change of a model.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelState
+{
+        pub transform: Transform,
+
+        pub visible: bool,
+
+        pub layer: u32,
+
+        /// Weights by name, see [`Model::morph_weights`].
+        pub morph_weights: Vec<(String, f32)>,
+
+        /// Factors of every material slot.
+        pub materials: Vec<MaterialDesc>,
+}
+
+impl ModelState
+{
+        pub fn of(model: &Model) -> Self
+        {
+                Self {
+                        transform: model.transform,
+                        visible: model.visible,
+                        layer: model.layer,
+                        morph_weights: model.morph_weights().to_vec(),
+                        materials: model.materials.iter().map(|m| m.desc()).collect(),
+                }
+        }
+
+        /// Puts the state back on `model`, uploading the materials that
+        /// changed.
+        pub fn apply(
+                &self,
+                model: &mut Model,
+                queue: &wgpu::Queue,
+        )
+        {
+                model.transform = self.transform;
+                model.visible = self.visible;
+                model.layer = self.layer;
+
+                for (name, weight) in &self.morph_weights
+                {
+                        model.set_morph_weight(name, *weight);
+                }
+
+                for (material, desc) in model.materials.iter_mut().zip(&self.materials)
+                {
+                        if material.desc() != *desc
+                        {
+                                material.apply_desc(queue, desc);
+                        }
+                }
+        }
+}
+
+/// A model before and after an edit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Edit
+{
+        pub handle: String,
+        pub before: ModelState,
+        pub after: ModelState,
+}
+
+/// Edits that can be undone and redone, owned by the
+/// [`EngineState`](crate::engine::EngineState).
+#[derive(Debug)]
+pub struct UndoStack
+{
+        /// Edits done together, e.g. to every selected model.
+        undo: Vec<Vec<Edit>>,
+        redo: Vec<Vec<Edit>>,
+
+        /// Edit in progress, by handle, with the state before it.
+        pending: Option<(String, ModelState)>,
+
+        /// Edits kept, the oldest are dropped past it.
+        pub limit: usize,
+}
+
+impl Default for UndoStack
+{
+        fn default() -> Self
+        {
+                Self {
+                        undo: Vec::new(),
+                        redo: Vec::new(),
+                        pending: None,
+                        limit: DEFAULT_LIMIT,
+                }
+        }
+}
+
+impl UndoStack
+{
+        pub fn new() -> Self
+        {
+                Self::default()
+        }
+
+        /// Edits that can be undone.
+        pub fn len(&self) -> usize
+        {
+                self.undo.len()
+        }
+
+        pub fn is_empty(&self) -> bool
+        {
+                self.undo.is_empty()
+        }
+
+        pub fn can_redo(&self) -> bool
+        {
+                !self.redo.is_empty()
+        }
+
+        pub fn clear(&mut self)
+        {
+                self.undo.clear();
+                self.redo.clear();
+                self.pending = None;
+        }
+
+        /// Records an edit, dropping what could be redone. Edits that
+        /// didn't change anything are ignored.
+        pub fn record(
+                &mut self,
+                edit: Edit,
+        )
+        {
+                self.record_all(vec![edit]);
+        }
+
+        /// Records edits of several models undone together, like
+        /// [`UndoStack::record`].
+        pub fn record_all(
+                &mut self,
+                mut edits: Vec<Edit>,
+        )
+        {
+                edits.retain(|edit| edit.before != edit.after);
+
+                if edits.is_empty()
+                {
+                        return;
+                }
+
+                self.redo.clear();
+                self.undo.push(edits);
+
+                if self.undo.len() > self.limit
+                {
+                        let excess = self.undo.len() - self.limit;
+                        self.undo.drain(..excess);
+                }
+        }
+
+        /// Notes that the model `handle` is being edited, `before` being its
+        /// state before this frame's change. The edit is recorded by
+        /// [`UndoStack::finish`], or when another model is edited.
+        #[cfg(feature = "ui")]
+        pub(crate) fn touch(
+                &mut self,
+                handle: &str,
+                before: ModelState,
+                models: &HashMap<String, Model>,
+        )
+        {
+                if self.pending.as_ref().is_some_and(|(h, _)| h == handle)
+                {
+                        return;
+                }
+
+                self.finish(models);
+                self.pending = Some((handle.to_string(), before));
+        }
+
+        /// Records the edit in progress with the model's state now.
+        pub(crate) fn finish(
+                &mut self,
+                models: &HashMap<String, Model>,
+        )
+        {
+                let Some((handle, before)) = self.pending.take()
+                else
+                {
+                        return;
+                };
+
+                if let Some(model) = models.get(&handle)
+                {
+                        let after = ModelState::of(model);
+
+                        self.record(Edit {
+                                handle,
+                                before,
+                                after,
+                        });
+                }
+        }
+
+        /// Puts the models of the last edit back to before it. Returns false
+        /// when there's nothing to undo.
+        pub fn undo(
+                &mut self,
+                models: &mut HashMap<String, Model>,
+                queue: &wgpu::Queue,
+        ) -> bool
+        {
+                self.finish(models);
+
+                let Some(edits) = self.undo.pop()
+                else
+                {
+                        return false;
+                };
+
+                for edit in &edits
+                {
+                        match models.get_mut(&edit.handle)
+                        {
+                                Some(model) => edit.before.apply(model, queue),
+                                None => log::warn!("Undo: no model {}", edit.handle),
+                        }
+                }
+
+                self.redo.push(edits);
+
+                true
+        }
+
+        /// Applies the last undone edit again. Returns false when there's
+        /// nothing to redo.
+        pub fn redo(
+                &mut self,
+                models: &mut HashMap<String, Model>,
+                queue: &wgpu::Queue,
+        ) -> bool
+        {
+                self.finish(models);
+
+                let Some(edits) = self.redo.pop()
+                else
+                {
+                        return false;
+                };
+
+                for edit in &edits
+                {
+                        match models.get_mut(&edit.handle)
+                        {
+                                Some(model) => edit.after.apply(model, queue),
+                                None => log::warn!("Redo: no model {}", edit.handle),
+                        }
+                }
+
+                self.undo.push(edits);
+
+                true
+        }
+}
+
+impl Engine
+{
+        /// Undoes the last edit of the debug UI, see [`crate::undo`].
+        pub fn undo(&mut self) -> bool
+        {
+                self.state
+                        .as_mut()
+                        .is_some_and(|state| state.history.undo(&mut state.models, &state.queue))
+        }
+
+        /// Redoes the last undone edit, see [`crate::undo`].
+        pub fn redo(&mut self) -> bool
+        {
+                self.state
+                        .as_mut()
+                        .is_some_and(|state| state.history.redo(&mut state.models, &state.queue))
+        }
+}
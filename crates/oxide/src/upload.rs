@@ -0,0 +1,195 @@
+//! GPU uploads of models loaded at runtime, spread over frames.
+//!
+//! Models spawned while the engine runs, e.g. with
+//! [`Engine::spawn_model`] or restored by a save game, and the ones loaded
+//! behind the loading screen, see [`crate::loading`], are read and decoded
+//! right away but only uploaded while the frame's upload budget lasts:
+//!
+//! ```ignore
+//! let mut engine = EngineBuilder::new()
+//!         .with_upload_budget(16 * 1024 * 1024)
+//!         .build()?;
+//! ```
+//!
+//! Queued models are uploaded in the order they were spawned, each whole,
+//! and appear in the frame they're uploaded in, publishing
+//! [`UploadEvent::Uploaded`]. A model larger than the budget is uploaded
+//! alone in a frame. Streamed texture levels, see [`crate::streaming`], get
+//! what the models leave of the budget.
+//!
+//! Without a budget models are uploaded as soon as they're loaded, in the
+//! `spawn_model` call on native.
+
+use crate::engine::Engine;
+use crate::material::create_material_bind_group_layout;
+use crate::resources::{ModelData, create_transform_bind_group_layout};
+use crate::texture::TextureLimits;
+use std::collections::VecDeque;
+
+/// Published on the event bus when a queued model was uploaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UploadEvent
+{
+        Uploaded(String),
+}
+
+/// Models waiting for their upload and the budget, owned by the
+/// [`Engine`].
+#[derive(Debug, Default)]
+pub struct UploadQueue
+{
+        /// Bytes uploaded per frame at most, `None` for no limit.
+        pub bytes_per_frame: Option<u64>,
+
+        pending: VecDeque<(String, ModelData)>,
+
+        /// Bytes uploaded this frame.
+        spent: u64,
+}
+
+impl UploadQueue
+{
+        pub fn new() -> Self
+        {
+                Self::default()
+        }
+
+        /// Models waiting for their upload.
+        pub fn pending(&self) -> usize
+        {
+                self.pending.len()
+        }
+
+        /// Bytes the waiting models will upload.
+        pub fn pending_bytes(&self) -> u64
+        {
+                self.pending
+                        .iter()
+                        .map(|(_, data)| data.upload_bytes())
+                        .sum()
+        }
+
+        /// Bytes uploaded this frame so far.
+        pub fn spent_bytes(&self) -> u64
+        {
+                self.spent
+        }
+
+        /// Drops the model `handle` if it's still waiting for its upload,
+        /// returns whether it was.
+        pub(crate) fn cancel(
+                &mut self,
+                handle: &str,
+        ) -> bool
+        {
+                let len = self.pending.len();
+
+                self.pending.retain(|(pending, _)| pending != handle);
+
+                self.pending.len() != len
+        }
+
+        /// Takes `bytes` from the frame's budget, false when they don't fit.
+        /// The first upload of a frame always fits, so larger ones still
+        /// happen.
+        pub(crate) fn try_spend(
+                &mut self,
+                bytes: u64,
+        ) -> bool
+        {
+                let budget = self.bytes_per_frame.unwrap_or(u64::MAX);
+
+                if self.spent > 0 && self.spent.saturating_add(bytes) > budget
+                {
+                        return false;
+                }
+
+                self.spent = self.spent.saturating_add(bytes);
+
+                true
+        }
+
+        /// Uploads the queued models the frame's budget allows.
+        ///
+        /// Called by the engine once per frame, before the other systems
+        /// upload.
+        pub(crate) fn update(engine: &mut Engine)
+        {
+                #[cfg(target_arch = "wasm32")]
+                engine.uploads
+                        .pending
+                        .extend(engine.spawned.borrow_mut().drain(..));
+
+                engine.uploads.spent = 0;
+
+                while let Some((_, data)) = engine.uploads.pending.front()
+                {
+                        if engine.state.is_none() || !engine.uploads.try_spend(data.upload_bytes())
+                        {
+                                break;
+                        }
+
+                        if let Some((handle, data)) = engine.uploads.pending.pop_front()
+                        {
+                                engine.upload_model(handle, data);
+                        }
+                }
+        }
+}
+
+impl Engine
+{
+        /// Limits the textures of the models the engine loads are uploaded
+        /// within.
+        pub fn texture_limits(&self) -> TextureLimits
+        {
+                TextureLimits {
+                        max_size: self.config.max_texture_size,
+                        stream_initial_size: self.texture_streaming.initial_size,
+                }
+        }
+
+        /// Uploads the model `data` under `handle` now without a budget, or
+        /// queues it.
+        pub(crate) fn queue_upload(
+                &mut self,
+                handle: String,
+                data: ModelData,
+        )
+        {
+                if self.uploads.bytes_per_frame.is_none() && self.state.is_some()
+                {
+                        self.upload_model(handle, data);
+                }
+                else
+                {
+                        self.uploads.pending.push_back((handle, data));
+                }
+        }
+
+        fn upload_model(
+                &mut self,
+                handle: String,
+                data: ModelData,
+        )
+        {
+                let limits = self.texture_limits();
+
+                let Some(state) = self.state.as_mut()
+                else
+                {
+                        return;
+                };
+
+                let model = data.upload_with_limits(
+                        &state.device,
+                        &state.queue,
+                        &create_material_bind_group_layout(&state.device),
+                        &create_transform_bind_group_layout(&state.device),
+                        limits,
+                );
+
+                state.models.insert(handle.clone(), model);
+                self.events.publish(UploadEvent::Uploaded(handle));
+        }
+}
@@ -1,6 +1,3 @@
-#[cfg(target_arch = "wasm32")]
-use wasm_bindgen::UnwrapThrowExt;
-
 pub fn show_start_message()
 {
         let oxide_string = r#"
@@ -22,19 +19,14 @@ pub fn show_start_message()
         log::info!("{oxide_string}")
 }
 
+/// Installs [`crate::log`] at the info level.
 pub fn config_logging()
 {
-        #[cfg(not(target_arch = "wasm32"))]
-        {
-                env_logger::init();
+        crate::log::init(crate::log::LogConfig::default());
 
-                log::info!("Running on native.");
-        }
+        #[cfg(not(target_arch = "wasm32"))]
+        log::info!("Running on native.");
 
         #[cfg(target_arch = "wasm32")]
-        {
-                console_log::init_with_level(log::Level::Info).unwrap_throw();
-
-                log::info!("Running on wasm32.");
-        }
+        log::info!("Running on wasm32.");
 }
@@ -0,0 +1,129 @@
+//! Window lifecycle hooks.
+//!
+//! Callbacks registered with [`Engine::on_window_event`] run when the window
+//! is resized, gains or loses focus, moves to a display with a different
+//! scale factor, or is asked to close. The same events are published on the
+//! event bus for behaviors that only want to read them.
+//!
+//! A hook can keep the window open, e.g. to ask about unsaved changes, and
+//! exit later with [`Engine::request_exit`]:
+//!
+//! ```ignore
+//! engine.on_window_event(|eng, event| {
+//!         if *event == WindowEvent::CloseRequested && eng.has_unsaved_changes()
+//!         {
+//!                 eng.veto_close();
+//!                 eng.push_state(ConfirmQuit);
+//!         }
+//! });
+//! ```
+//!
+//! Browsers don't ask before closing a tab, so `CloseRequested` is never
+//! sent on the web.
+
+use crate::engine::Engine;
+
+/// Published on the event bus and passed to the hooks.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WindowEvent
+{
+        /// New inner size in physical pixels, zero while minimized.
+        Resized
+        {
+                width: u32,
+                height: u32,
+        },
+
+        Focused(bool),
+
+        /// The window moved to a display with another DPI, or the DPI
+        /// setting changed.
+        ScaleFactorChanged
+        {
+                scale_factor: f64,
+        },
+
+        /// The user tried to close the window, see [`Engine::veto_close`].
+        CloseRequested,
+}
+
+pub type WindowHook = Box<dyn FnMut(&mut Engine, &WindowEvent)>;
+
+/// Registered hooks, owned by the [`Engine`].
+#[derive(Default)]
+pub struct WindowHooks
+{
+        hooks: Vec<WindowHook>,
+
+        /// A hook vetoed the current close request.
+        close_vetoed: bool,
+}
+
+impl std::fmt::Debug for WindowHooks
+{
+        fn fmt(
+                &self,
+                f: &mut std::fmt::Formatter<'_>,
+        ) -> std::fmt::Result
+        {
+                f.debug_struct("WindowHooks")
+                        .field("hooks", &self.hooks.len())
+                        .field("close_vetoed", &self.close_vetoed)
+                        .finish()
+        }
+}
+
+impl WindowHooks
+{
+        pub fn new() -> Self
+        {
+                Self::default()
+        }
+
+        /// Runs the hooks and publishes `event`. Returns false when a hook
+        /// vetoed a close request.
+        ///
+        /// Called by the engine for the window events it forwards.
+        pub(crate) fn dispatch(
+                engine: &mut Engine,
+                event: WindowEvent,
+        ) -> bool
+        {
+                engine.window_hooks.close_vetoed = false;
+
+                // Hooks registered by a hook run from the next event on.
+                let mut hooks = std::mem::take(&mut engine.window_hooks.hooks);
+
+                for hook in &mut hooks
+                {
+                        hook(engine, &event);
+                }
+
+                hooks.append(&mut engine.window_hooks.hooks);
+                engine.window_hooks.hooks = hooks;
+
+                engine.events.publish(event);
+
+                !std::mem::take(&mut engine.window_hooks.close_vetoed)
+        }
+}
+
+impl Engine
+{
+        /// Calls `hook` for every [`WindowEvent`], see [`crate::window`].
+        pub fn on_window_event<F>(
+                &mut self,
+                hook: F,
+        ) where
+                F: 'static + FnMut(&mut Engine, &WindowEvent),
+        {
+                self.window_hooks.hooks.push(Box::new(hook));
+        }
+
+        /// Keeps the window open, only has an effect from a hook handling
+        /// [`WindowEvent::CloseRequested`].
+        pub fn veto_close(&mut self)
+        {
+                self.window_hooks.close_vetoed = true;
+        }
+}